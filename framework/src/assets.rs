@@ -0,0 +1,178 @@
+//! where a tutorial's images and models come from.
+//!
+//! [`assets!`](crate::assets!) lists a chapter's files once. they get compiled into the binary,
+//! so it runs from anywhere and on targets without a filesystem, and the same names can be read
+//! from disk instead when `LEARNWGPU_ASSETS=disk` is set. disk loads are fresh every time, poll
+//! [`AssetSource::modified`] to hot reload a file while editing it.
+//!
+//! images are color unless the listing says otherwise. `as Linear` marks data, normal and
+//! roughness maps, which [`AssetSource::load_texture`] then uploads as `Rgba8Unorm` so the
+//! shader gets the stored values back instead of ones the sampler decoded from srgb.
+//!
+//! t27-normal-map's bricks:
+//!
+//! ```ignore
+//! let assets = framework::assets!("src": "bricks.png", "bricks-normal.png" as Linear);
+//! let normal = assets
+//!     .load_texture(device, queue, "bricks-normal.png")
+//!     .expect("Fail to load bricks-normal.png");
+//! ```
+
+use std::borrow::Cow;
+use std::io;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::texture::Texture;
+
+/// environment variable that picks the source, `disk` or `embedded`, embedded by default
+pub const ENV_VAR: &str = "LEARNWGPU_ASSETS";
+
+/// what the bytes of an image stand for
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// colors as they are shown, gamma encoded. albedo, diffuse, anything painted
+    Srgb,
+    /// numbers that only happen to be stored in an image: normals, roughness, heights
+    Linear,
+}
+
+impl ColorSpace {
+    /// the rgba8 format that hands the shader what the image means, the sampler decodes srgb
+    pub fn format(self) -> wgpu::TextureFormat {
+        match self {
+            ColorSpace::Srgb => wgpu::TextureFormat::Rgba8UnormSrgb,
+            ColorSpace::Linear => wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+}
+
+/// one listed file
+pub struct Asset {
+    pub name: &'static str,
+    /// the contents at build time
+    pub bytes: &'static [u8],
+    pub color_space: ColorSpace,
+}
+
+pub enum AssetSource {
+    /// the listed files as they were compiled into the binary
+    Embedded(&'static [Asset]),
+    /// the files under this directory, the listing still has their color spaces
+    Disk(PathBuf, &'static [Asset]),
+}
+
+impl AssetSource {
+    /// [`ENV_VAR`] decides between `files` and the same names in `dir`, normally both are
+    /// filled in by [`assets!`](crate::assets!)
+    pub fn select(files: &'static [Asset], dir: &str) -> AssetSource {
+        match std::env::var(ENV_VAR).as_deref() {
+            Ok("disk") => AssetSource::Disk(PathBuf::from(dir), files),
+            _ => AssetSource::Embedded(files),
+        }
+    }
+
+    pub fn load(&self, name: &str) -> io::Result<Cow<'static, [u8]>> {
+        match self {
+            AssetSource::Embedded(files) => files
+                .iter()
+                .find(|file| file.name == name)
+                .map(|file| Cow::Borrowed(file.bytes))
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::NotFound, format!("{} isn't embedded", name))
+                }),
+            AssetSource::Disk(dir, _) => std::fs::read(dir.join(name)).map(Cow::Owned),
+        }
+    }
+
+    /// how `name` was listed, [`ColorSpace::Srgb`] for names that weren't
+    pub fn color_space(&self, name: &str) -> ColorSpace {
+        let (AssetSource::Embedded(files) | AssetSource::Disk(_, files)) = self;
+        files
+            .iter()
+            .find(|file| file.name == name)
+            .map_or(ColorSpace::Srgb, |file| file.color_space)
+    }
+
+    /// decode the image `name` into a texture of its [`AssetSource::color_space`]'s format.
+    /// `.ktx2` files go through [`Texture::from_ktx2`] instead, their format says the color
+    /// space
+    pub fn load_texture(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+    ) -> io::Result<Texture> {
+        let bytes = self.load(name)?;
+        if name.ends_with(".ktx2") {
+            return Texture::from_ktx2(device, queue, &bytes, name)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err));
+        }
+        let img = image::load_from_memory(&bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Ok(Texture::from_image_format(
+            device,
+            queue,
+            &img,
+            self.color_space(name).format(),
+            name,
+        ))
+    }
+
+    /// last change of a file on disk, `None` for embedded files since they never change
+    pub fn modified(&self, name: &str) -> Option<SystemTime> {
+        match self {
+            AssetSource::Embedded(_) => None,
+            AssetSource::Disk(dir, _) => std::fs::metadata(dir.join(name))
+                .and_then(|metadata| metadata.modified())
+                .ok(),
+        }
+    }
+}
+
+/// an [`AssetSource`] for files in `$dir`, relative to the calling crate's manifest.
+/// every listed file is embedded, so a missing one fails the build. a name followed by
+/// `as Linear` is a data map, see [`ColorSpace`]
+#[macro_export]
+macro_rules! assets {
+    (@color_space) => {
+        $crate::assets::ColorSpace::Srgb
+    };
+    (@color_space $color_space:ident) => {
+        $crate::assets::ColorSpace::$color_space
+    };
+    ($dir:literal: $($name:literal $(as $color_space:ident)?),+ $(,)?) => {
+        $crate::assets::AssetSource::select(
+            &[$($crate::assets::Asset {
+                name: $name,
+                bytes: include_bytes!(concat!(env!("CARGO_MANIFEST_DIR"), "/", $dir, "/", $name)),
+                color_space: $crate::assets!(@color_space $($color_space)?),
+            }),+],
+            concat!(env!("CARGO_MANIFEST_DIR"), "/", $dir),
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_assets_load_as_unorm() {
+        let assets = crate::assets!(
+            "../tutorials/t027-normal-map/src": "bricks.png",
+            "bricks-normal.png" as Linear,
+        );
+        assert_eq!(
+            assets.color_space("bricks-normal.png").format(),
+            wgpu::TextureFormat::Rgba8Unorm
+        );
+        assert_eq!(
+            assets.color_space("bricks.png").format(),
+            wgpu::TextureFormat::Rgba8UnormSrgb
+        );
+        // names that weren't listed are color
+        assert_eq!(assets.color_space("missing.png"), ColorSpace::Srgb);
+        assert!(assets.load("bricks-normal.png").is_ok());
+    }
+}
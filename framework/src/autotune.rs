@@ -0,0 +1,282 @@
+//! workgroup sizes picked by measuring instead of guessing. the best size for a kernel depends
+//! on the gpu: how wide its simd units are, how many registers a thread takes, how the
+//! texture cache likes its reads. [`pick`] runs the kernel with each candidate size at
+//! startup, keeps the fastest and remembers it in `autotune/<adapter>.txt`, so the next run on
+//! the same adapter starts with it right away.
+//!
+//! the kernel's shader is written with one of the sizes, and [`with_workgroup_size`] rewrites
+//! its `@workgroup_size` for the others. the dispatch has to divide by the same size.
+//!
+//! ```ignore
+//! let size = autotune::pick(adapter, device, queue, "blur", &[[8, 8, 1], [16, 16, 1]], |size| {
+//!     let kernel = Blur::with_workgroup_size(device, size);
+//!     move |encoder: &mut wgpu::CommandEncoder| kernel.dispatch(encoder)
+//! });
+//! ```
+//!
+//! [`ENV_VAR`] set to `calibrate` measures again even when a size is saved, `off` takes the
+//! first candidate without measuring anything.
+
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use tracing::{info, warn};
+
+/// where the files go, relative to the working directory
+pub const DIR: &str = "autotune";
+
+/// environment variable with the mode, see the module docs
+pub const ENV_VAR: &str = "LEARNWGPU_AUTOTUNE";
+
+// a round is this many dispatches in one submission, the fastest of the rounds counts. one
+// dispatch alone is too short to time from the cpu
+const DISPATCHES: usize = 20;
+const ROUNDS: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    /// the saved size when there is one, measure when there isn't
+    Cached,
+    Calibrate,
+    Off,
+}
+
+impl Mode {
+    fn from_env() -> Mode {
+        match std::env::var(ENV_VAR).as_deref() {
+            Ok("calibrate") => Mode::Calibrate,
+            Ok("off") => Mode::Off,
+            _ => Mode::Cached,
+        }
+    }
+}
+
+/// `source` with every `@workgroup_size(..)` set to `size`, trailing ones are left out.
+/// meant for files with one compute entry point
+pub fn with_workgroup_size(source: &str, size: [u32; 3]) -> String {
+    const ATTRIBUTE: &str = "@workgroup_size(";
+    let size = match size {
+        [x, 1, 1] => format!("{}", x),
+        [x, y, 1] => format!("{}, {}", x, y),
+        [x, y, z] => format!("{}, {}, {}", x, y, z),
+    };
+
+    let mut rewritten = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(start) = rest.find(ATTRIBUTE) {
+        let (before, attribute) = rest.split_at(start + ATTRIBUTE.len());
+        let end = attribute
+            .find(')')
+            .expect("Fail to find the end of @workgroup_size");
+        rewritten.push_str(before);
+        rewritten.push_str(&size);
+        rest = &attribute[end..];
+    }
+    rewritten.push_str(rest);
+    rewritten
+}
+
+/// whether the device runs a workgroup of `size` at all
+pub fn fits(limits: &wgpu::Limits, size: [u32; 3]) -> bool {
+    size.iter().all(|&n| n > 0)
+        && size[0] <= limits.max_compute_workgroup_size_x
+        && size[1] <= limits.max_compute_workgroup_size_y
+        && size[2] <= limits.max_compute_workgroup_size_z
+        && size.iter().product::<u32>() <= limits.max_compute_invocations_per_workgroup
+}
+
+/// the fastest of `candidates` for `kernel` on this adapter. `prepare` builds the kernel with
+/// a size and returns what records one dispatch of it, it's called once per candidate that
+/// gets measured. candidates the device can't run are skipped, the first of the rest is the
+/// answer when nothing is measured
+pub fn pick<K>(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    kernel: &str,
+    candidates: &[[u32; 3]],
+    mut prepare: impl FnMut([u32; 3]) -> K,
+) -> [u32; 3]
+where
+    K: FnMut(&mut wgpu::CommandEncoder),
+{
+    let limits = device.limits();
+    let candidates: Vec<[u32; 3]> = candidates
+        .iter()
+        .copied()
+        .filter(|&size| fits(&limits, size))
+        .collect();
+    let first = *candidates
+        .first()
+        .expect("Fail to find a workgroup size the device can run");
+
+    let adapter_name = adapter.get_info().name;
+    let path = PathBuf::from(DIR).join(format!("{}.txt", file_stem(&adapter_name)));
+    let mut saved = std::fs::read_to_string(&path)
+        .map(|text| parse(&text))
+        .unwrap_or_default();
+
+    match Mode::from_env() {
+        Mode::Off => return first,
+        Mode::Cached => {
+            let cached = saved
+                .iter()
+                .find(|(name, size)| name == kernel && candidates.contains(size));
+            if let Some(&(_, size)) = cached {
+                info!("{}: {} from {}", kernel, format_size(size), path.display());
+                return size;
+            }
+        }
+        Mode::Calibrate => {}
+    }
+
+    let mut best = (first, Duration::MAX);
+    for &size in &candidates {
+        let mut dispatch = prepare(size);
+        let time = measure(device, queue, &mut dispatch);
+        info!(
+            "{}: {} takes {:.3}ms a dispatch",
+            kernel,
+            format_size(size),
+            time.as_secs_f64() * 1000. / DISPATCHES as f64
+        );
+        if time < best.1 {
+            best = (size, time);
+        }
+    }
+    info!(
+        "{}: {} is the fastest on {}",
+        kernel,
+        format_size(best.0),
+        adapter_name
+    );
+
+    match saved.iter_mut().find(|(name, _)| name == kernel) {
+        Some(entry) => entry.1 = best.0,
+        None => saved.push((kernel.to_string(), best.0)),
+    }
+    let written = std::fs::create_dir_all(DIR).and_then(|_| std::fs::write(&path, format(&saved)));
+    if let Err(err) = written {
+        warn!("Fail to save {}: {}", path.display(), err);
+    }
+    best.0
+}
+
+// the fastest round, after one more that isn't counted while the driver warms up
+fn measure(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    dispatch: &mut impl FnMut(&mut wgpu::CommandEncoder),
+) -> Duration {
+    let mut round = || {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("autotune encoder"),
+        });
+        for _ in 0..DISPATCHES {
+            dispatch(&mut encoder);
+        }
+        let start = Instant::now();
+        queue.submit(Some(encoder.finish()));
+        device.poll(wgpu::Maintain::Wait);
+        start.elapsed()
+    };
+
+    round();
+    (0..ROUNDS).map(|_| round()).min().unwrap_or(Duration::MAX)
+}
+
+// adapter names have spaces, slashes and parentheses, none of them go in a file name
+fn file_stem(adapter_name: &str) -> String {
+    let stem = adapter_name
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if stem.is_empty() {
+        "unknown".to_string()
+    } else {
+        stem
+    }
+}
+
+fn format_size(size: [u32; 3]) -> String {
+    format!("{}x{}x{}", size[0], size[1], size[2])
+}
+
+fn format(saved: &[(String, [u32; 3])]) -> String {
+    saved
+        .iter()
+        .map(|(kernel, size)| format!("{} = {}\n", kernel, format_size(*size)))
+        .collect()
+}
+
+// `kernel = XxYxZ` lines, ones that don't parse are dropped and get measured again
+fn parse(text: &str) -> Vec<(String, [u32; 3])> {
+    text.lines()
+        .filter_map(|line| {
+            let (kernel, size) = line.split_once('=')?;
+            let numbers = size
+                .trim()
+                .split('x')
+                .map(|n| n.parse::<u32>().ok())
+                .collect::<Option<Vec<_>>>()?;
+            let size: [u32; 3] = numbers.try_into().ok()?;
+            Some((kernel.trim().to_string(), size))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_every_workgroup_size() {
+        let source =
+            "@compute @workgroup_size(8, 8)\nfn a() {}\n@compute @workgroup_size(64)\nfn b() {}\n";
+        assert_eq!(
+            with_workgroup_size(source, [16, 4, 1]),
+            "@compute @workgroup_size(16, 4)\nfn a() {}\n@compute @workgroup_size(16, 4)\nfn b() {}\n"
+        );
+        assert_eq!(
+            with_workgroup_size("@workgroup_size(8, 8)", [256, 1, 1]),
+            "@workgroup_size(256)"
+        );
+        assert_eq!(
+            with_workgroup_size("@workgroup_size(8)", [4, 4, 4]),
+            "@workgroup_size(4, 4, 4)"
+        );
+    }
+
+    #[test]
+    fn sizes_over_the_limits_dont_fit() {
+        let limits = wgpu::Limits::default();
+        assert!(fits(&limits, [16, 16, 1]));
+        assert!(!fits(&limits, [32, 16, 1]));
+        assert!(!fits(&limits, [1, 1, 128]));
+        assert!(!fits(&limits, [0, 8, 1]));
+    }
+
+    #[test]
+    fn saved_sizes_round_trip() {
+        let saved = vec![
+            ("compute_tonemap".to_string(), [16, 8, 1]),
+            ("cull".to_string(), [256, 1, 1]),
+        ];
+        assert_eq!(parse(&format(&saved)), saved);
+        assert_eq!(
+            parse("a = 8x8\nb = 8x8x1x1\nc 8x8x1\nd = 4x4x1\n"),
+            vec![("d".to_string(), [4, 4, 1])]
+        );
+    }
+
+    #[test]
+    fn adapter_names_make_file_names() {
+        assert_eq!(
+            file_stem("NVIDIA GeForce RTX 3070 (PCIe/SSE2)"),
+            "nvidia-geforce-rtx-3070-pcie-sse2"
+        );
+        assert_eq!(file_stem(""), "unknown");
+    }
+}
@@ -0,0 +1,108 @@
+//! copy a texture onto a render target with a fullscreen triangle, scaling it with
+//! whatever sampler the bind group was made with. [`Blit::with_sharpening`] also runs
+//! contrast adaptive sharpening on the way, to win back some detail lost to upscaling.
+
+use wgpu::include_wgsl;
+
+pub struct Blit {
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Blit {
+    /// `target_format` is the format of the texture the blit draws into
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Blit {
+        Self::create(device, target_format, "fs_main")
+    }
+
+    /// like [`Blit::new`], but sharpens the source while copying it.
+    /// bind groups are interchangeable with a plain blit's, the layout is the same
+    pub fn with_sharpening(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Blit {
+        Self::create(device, target_format, "fs_cas")
+    }
+
+    fn create(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        fs_entry_point: &str,
+    ) -> Blit {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("blit_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("blit.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("blit"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: fs_entry_point,
+                targets: &[Some(target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Blit { layout, pipeline }
+    }
+
+    pub fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        source: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("blit_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(source),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+    }
+
+    /// covers the current viewport of `rpass` with the source of `bind_group`
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, bind_group: &'a wgpu::BindGroup) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
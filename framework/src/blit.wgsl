@@ -0,0 +1,59 @@
+// draws a texture over the whole viewport with a single oversized triangle, no vertex buffer
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragInput.tex_coord = uv;
+    return fragInput;
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    return textureSample(t_source, s_source, input.tex_coord);
+}
+
+// 0 is the mildest sharpening, 1 the strongest
+let sharpness = 0.6;
+
+// contrast adaptive sharpening, after AMD's FidelityFX CAS: a cross shaped unsharp mask whose
+// strength drops where the neighbourhood already has a lot of contrast, so edges don't ring
+@fragment
+fn fs_cas(input: FragInput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_source));
+    let uv = input.tex_coord;
+
+    //   b
+    // d e f
+    //   h
+    let b = textureSample(t_source, s_source, uv + vec2<f32>(0.0, -texel.y)).rgb;
+    let d = textureSample(t_source, s_source, uv + vec2<f32>(-texel.x, 0.0)).rgb;
+    let e = textureSample(t_source, s_source, uv);
+    let f = textureSample(t_source, s_source, uv + vec2<f32>(texel.x, 0.0)).rgb;
+    let h = textureSample(t_source, s_source, uv + vec2<f32>(0.0, texel.y)).rgb;
+
+    let min_rgb = min(e.rgb, min(min(b, d), min(f, h)));
+    let max_rgb = max(e.rgb, max(max(b, d), max(f, h)));
+
+    // how much headroom there is before clipping, relative to the brightest neighbour
+    let headroom = min(min_rgb, 1.0 - max_rgb) / max(max_rgb, vec3<f32>(0.0001));
+    let amp = sqrt(clamp(headroom, vec3<f32>(0.0), vec3<f32>(1.0)));
+    // negative lobe weight, between -1/8 and -1/5
+    let w = amp * mix(-0.125, -0.2, sharpness);
+
+    let color = (e.rgb + (b + d + f + h) * w) / (1.0 + 4.0 * w);
+    return vec4<f32>(clamp(color, vec3<f32>(0.0), vec3<f32>(1.0)), e.a);
+}
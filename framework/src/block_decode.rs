@@ -0,0 +1,684 @@
+//! cpu decoders for the block compressed formats [`crate::ktx2`] files carry, so a texture
+//! still loads on an adapter that can't sample them. every format splits the image into 4x4
+//! blocks and stores each one on its own: a couple of endpoint colors and a small index per
+//! texel saying where between them it lies.
+//!
+//! - bc1: two 565 colors and 2 bit indices, 8 bytes
+//! - bc3: an alpha block with two values and 3 bit indices, then a bc1 block, 16 bytes
+//! - bc7: one of eight modes, up to three subsets of texels with their own endpoints, 16 bytes
+//! - etc2 rgb: etc1's two half blocks with a base color and an intensity table each, plus the
+//!   t, h and planar modes hidden in what would be overflowing etc1 colors, 8 bytes
+//! - etc2 rgba: an eac alpha block in front of an etc2 rgb one, 16 bytes
+
+/// `data` of `format`, `width` x `height` texels, as rgba8 with 4 bytes a texel. `None` for
+/// formats that aren't decoded here. srgb formats come out still srgb encoded
+pub fn to_rgba8(
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    data: &[u8],
+) -> Option<Vec<u8>> {
+    use wgpu::TextureFormat::*;
+    let decode_block: fn(&[u8]) -> [[u8; 4]; 16] = match format {
+        Rgba8Unorm | Rgba8UnormSrgb => return Some(data.to_vec()),
+        Bc1RgbaUnorm | Bc1RgbaUnormSrgb => |block| bc1(block, false),
+        Bc3RgbaUnorm | Bc3RgbaUnormSrgb => bc3,
+        Bc7RgbaUnorm | Bc7RgbaUnormSrgb => bc7,
+        Etc2Rgb8Unorm | Etc2Rgb8UnormSrgb => etc2_rgb,
+        Etc2Rgba8Unorm | Etc2Rgba8UnormSrgb => etc2_rgba,
+        _ => return None,
+    };
+    let block_size = format.describe().block_size as usize;
+
+    let (blocks_wide, blocks_high) = (width.div_ceil(4), height.div_ceil(4));
+    let mut rgba = vec![0; (width * height * 4) as usize];
+    for (i, block) in data
+        .chunks_exact(block_size)
+        .take((blocks_wide * blocks_high) as usize)
+        .enumerate()
+    {
+        let (block_x, block_y) = (i as u32 % blocks_wide * 4, i as u32 / blocks_wide * 4);
+        for (texel, color) in decode_block(block).iter().enumerate() {
+            let (x, y) = (block_x + texel as u32 % 4, block_y + texel as u32 / 4);
+            // the blocks on the right and bottom edges hang over the image
+            if x < width && y < height {
+                let offset = ((y * width + x) * 4) as usize;
+                rgba[offset..offset + 4].copy_from_slice(color);
+            }
+        }
+    }
+    Some(rgba)
+}
+
+// the blocks below come back in rows, texel `y * 4 + x`
+
+fn rgb565(color: u16) -> [i32; 3] {
+    let (r, g, b) = (
+        (color >> 11) as i32 & 31,
+        (color >> 5) as i32 & 63,
+        color as i32 & 31,
+    );
+    [
+        (r << 3) | (r >> 2),
+        (g << 2) | (g >> 4),
+        (b << 3) | (b >> 2),
+    ]
+}
+
+// `four_colors` is for bc3, whose color block never has bc1's transparent texels
+fn bc1(block: &[u8], four_colors: bool) -> [[u8; 4]; 16] {
+    let color0 = u16::from_le_bytes([block[0], block[1]]);
+    let color1 = u16::from_le_bytes([block[2], block[3]]);
+    let (c0, c1) = (rgb565(color0), rgb565(color1));
+    let mix = |w0: i32, w1: i32, total: i32| {
+        let channel = |i: usize| ((w0 * c0[i] + w1 * c1[i]) / total) as u8;
+        [channel(0), channel(1), channel(2), 255]
+    };
+
+    let palette = if color0 > color1 || four_colors {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(2, 1, 3), mix(1, 2, 3)]
+    } else {
+        [mix(1, 0, 1), mix(0, 1, 1), mix(1, 1, 2), [0; 4]]
+    };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    std::array::from_fn(|texel| palette[(indices >> (2 * texel)) as usize & 3])
+}
+
+fn bc3(block: &[u8]) -> [[u8; 4]; 16] {
+    let (a0, a1) = (block[0] as u32, block[1] as u32);
+    let alphas: [u8; 8] = std::array::from_fn(|code| {
+        let code = code as u32;
+        match code {
+            0 => a0 as u8,
+            1 => a1 as u8,
+            _ if a0 > a1 => (((8 - code) * a0 + (code - 1) * a1) / 7) as u8,
+            6 => 0,
+            7 => 255,
+            _ => (((6 - code) * a0 + (code - 1) * a1) / 5) as u8,
+        }
+    });
+
+    let mut indices = [0; 8];
+    indices[..6].copy_from_slice(&block[2..8]);
+    let indices = u64::from_le_bytes(indices);
+
+    let mut texels = bc1(&block[8..16], true);
+    for (texel, color) in texels.iter_mut().enumerate() {
+        color[3] = alphas[(indices >> (3 * texel)) as usize & 7];
+    }
+    texels
+}
+
+// bits read from the lowest up, the way bc7 packs its fields
+struct Bits {
+    value: u128,
+    position: u32,
+}
+
+impl Bits {
+    fn read(&mut self, count: u32) -> u32 {
+        let bits = (self.value >> self.position) as u32 & ((1 << count) - 1);
+        self.position += count;
+        bits
+    }
+}
+
+struct Bc7Mode {
+    subsets: usize,
+    partition_bits: u32,
+    rotation_bits: u32,
+    index_selection_bits: u32,
+    color_bits: u32,
+    alpha_bits: u32,
+    /// a p-bit per endpoint
+    endpoint_pbits: bool,
+    /// a p-bit per subset, shared by both its endpoints
+    shared_pbits: bool,
+    index_bits: u32,
+    /// modes 4 and 5 index color and alpha separately
+    secondary_index_bits: u32,
+}
+
+#[rustfmt::skip]
+const BC7_MODES: [Bc7Mode; 8] = [
+    Bc7Mode { subsets: 3, partition_bits: 4, rotation_bits: 0, index_selection_bits: 0, color_bits: 4, alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 6, alpha_bits: 0, endpoint_pbits: false, shared_pbits: true, index_bits: 3, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 3, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 0, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 0, endpoint_pbits: true, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 1, color_bits: 5, alpha_bits: 6, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 3 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 2, index_selection_bits: 0, color_bits: 7, alpha_bits: 8, endpoint_pbits: false, shared_pbits: false, index_bits: 2, secondary_index_bits: 2 },
+    Bc7Mode { subsets: 1, partition_bits: 0, rotation_bits: 0, index_selection_bits: 0, color_bits: 7, alpha_bits: 7, endpoint_pbits: true, shared_pbits: false, index_bits: 4, secondary_index_bits: 0 },
+    Bc7Mode { subsets: 2, partition_bits: 6, rotation_bits: 0, index_selection_bits: 0, color_bits: 5, alpha_bits: 5, endpoint_pbits: true, shared_pbits: false, index_bits: 2, secondary_index_bits: 0 },
+];
+
+// the two subset partitions, a bit per texel that's set for subset 1
+#[rustfmt::skip]
+const BC7_PARTITIONS_2: [u16; 64] = [
+    0xcccc, 0x8888, 0xeeee, 0xecc8, 0xc880, 0xfeec, 0xfec8, 0xec80,
+    0xc800, 0xffec, 0xfe80, 0xe800, 0xffe8, 0xff00, 0xfff0, 0xf000,
+    0xf710, 0x008e, 0x7100, 0x08ce, 0x008c, 0x7310, 0x3100, 0x8cce,
+    0x088c, 0x3110, 0x6666, 0x366c, 0x17e8, 0x0ff0, 0x718e, 0x399c,
+    0xaaaa, 0xf0f0, 0x5a5a, 0x33cc, 0x3c3c, 0x55aa, 0x9696, 0xa55a,
+    0x73ce, 0x13c8, 0x324c, 0x3bdc, 0x6996, 0xc33c, 0x9966, 0x0660,
+    0x0272, 0x04e4, 0x4e40, 0x2720, 0xc936, 0x936c, 0x39c6, 0x639c,
+    0x9336, 0x9cc6, 0x817e, 0xe718, 0xccf0, 0x0fcc, 0x7744, 0xee22,
+];
+
+#[rustfmt::skip]
+const BC7_PARTITIONS_3: [[u8; 16]; 64] = [
+    [0,0,1,1,0,0,1,1,0,2,2,1,2,2,2,2], [0,0,0,1,0,0,1,1,2,2,1,1,2,2,2,1],
+    [0,0,0,0,2,0,0,1,2,2,1,1,2,2,1,1], [0,2,2,2,0,0,2,2,0,0,1,1,0,1,1,1],
+    [0,0,0,0,0,0,0,0,1,1,2,2,1,1,2,2], [0,0,1,1,0,0,1,1,0,0,2,2,0,0,2,2],
+    [0,0,2,2,0,0,2,2,1,1,1,1,1,1,1,1], [0,0,1,1,0,0,1,1,2,2,1,1,2,2,1,1],
+    [0,0,0,0,0,0,0,0,1,1,1,1,2,2,2,2], [0,0,0,0,1,1,1,1,1,1,1,1,2,2,2,2],
+    [0,0,0,0,1,1,1,1,2,2,2,2,2,2,2,2], [0,0,1,2,0,0,1,2,0,0,1,2,0,0,1,2],
+    [0,1,1,2,0,1,1,2,0,1,1,2,0,1,1,2], [0,1,2,2,0,1,2,2,0,1,2,2,0,1,2,2],
+    [0,0,1,1,0,1,1,2,1,1,2,2,1,2,2,2], [0,0,1,1,2,0,0,1,2,2,0,0,2,2,2,0],
+    [0,0,0,1,0,0,1,1,0,1,1,2,1,1,2,2], [0,1,1,1,0,0,1,1,2,0,0,1,2,2,0,0],
+    [0,0,0,0,1,1,2,2,1,1,2,2,1,1,2,2], [0,0,2,2,0,0,2,2,0,0,2,2,1,1,1,1],
+    [0,1,1,1,0,1,1,1,0,2,2,2,0,2,2,2], [0,0,0,1,0,0,0,1,2,2,2,1,2,2,2,1],
+    [0,0,0,0,0,0,1,1,0,1,2,2,0,1,2,2], [0,0,0,0,1,1,0,0,2,2,1,0,2,2,1,0],
+    [0,1,2,2,0,1,2,2,0,0,1,1,0,0,0,0], [0,0,1,2,0,0,1,2,1,1,2,2,2,2,2,2],
+    [0,1,1,0,1,2,2,1,1,2,2,1,0,1,1,0], [0,0,0,0,0,1,1,0,1,2,2,1,1,2,2,1],
+    [0,0,2,2,1,1,0,2,1,1,0,2,0,0,2,2], [0,1,1,0,0,1,1,0,2,0,0,2,2,2,2,2],
+    [0,0,1,1,0,1,2,2,0,1,2,2,0,0,1,1], [0,0,0,0,2,0,0,0,2,2,1,1,2,2,2,1],
+    [0,0,0,0,0,0,0,2,1,1,2,2,1,2,2,2], [0,2,2,2,0,0,2,2,0,0,1,2,0,0,1,1],
+    [0,0,1,1,0,0,1,2,0,0,2,2,0,2,2,2], [0,1,2,0,0,1,2,0,0,1,2,0,0,1,2,0],
+    [0,0,0,0,1,1,1,1,2,2,2,2,0,0,0,0], [0,1,2,0,1,2,0,1,2,0,1,2,0,1,2,0],
+    [0,1,2,0,2,0,1,2,1,2,0,1,0,1,2,0], [0,0,1,1,2,2,0,0,1,1,2,2,0,0,1,1],
+    [0,0,1,1,1,1,2,2,2,2,0,0,0,0,1,1], [0,1,0,1,0,1,0,1,2,2,2,2,2,2,2,2],
+    [0,0,0,0,0,0,0,0,2,1,2,1,2,1,2,1], [0,0,2,2,1,1,2,2,0,0,2,2,1,1,2,2],
+    [0,0,2,2,0,0,1,1,0,0,2,2,0,0,1,1], [0,2,2,0,1,2,2,1,0,2,2,0,1,2,2,1],
+    [0,1,0,1,2,2,2,2,2,2,2,2,0,1,0,1], [0,0,0,0,2,1,2,1,2,1,2,1,2,1,2,1],
+    [0,1,0,1,0,1,0,1,0,1,0,1,2,2,2,2], [0,2,2,2,0,1,1,1,0,2,2,2,0,1,1,1],
+    [0,0,0,2,1,1,1,2,0,0,0,2,1,1,1,2], [0,0,0,0,2,1,1,2,2,1,1,2,2,1,1,2],
+    [0,2,2,2,0,1,1,1,0,1,1,1,0,2,2,2], [0,0,0,2,1,1,1,2,1,1,1,2,0,0,0,2],
+    [0,1,1,0,0,1,1,0,0,1,1,0,2,2,2,2], [0,0,0,0,0,0,0,0,2,1,1,2,2,1,1,2],
+    [0,1,1,0,0,1,1,0,2,2,2,2,2,2,2,2], [0,0,2,2,0,0,1,1,0,0,1,1,0,0,2,2],
+    [0,0,2,2,1,1,2,2,1,1,2,2,0,0,2,2], [0,0,0,0,0,0,0,0,0,0,0,0,2,1,1,2],
+    [0,0,0,2,0,0,0,1,0,0,0,2,0,0,0,1], [0,2,2,2,1,2,2,2,0,2,2,2,1,2,2,2],
+    [0,1,0,1,2,2,2,2,2,2,2,2,2,2,2,2], [0,1,1,1,2,0,1,1,2,2,0,1,2,2,2,0],
+];
+
+// the texel of each subset past the first whose index has its top bit left out, subset 0's
+// is always texel 0
+#[rustfmt::skip]
+const BC7_ANCHORS_2: [u8; 64] = [
+    15,15,15,15,15,15,15,15, 15,15,15,15,15,15,15,15,
+    15, 2, 8, 2, 2, 8, 8,15,  2, 8, 2, 2, 8, 8, 2, 2,
+    15,15, 6, 8, 2, 8,15,15,  2, 8, 2, 2, 2,15,15, 6,
+     6, 2, 6, 8,15,15, 2, 2, 15,15,15,15,15, 2, 2,15,
+];
+
+#[rustfmt::skip]
+const BC7_ANCHORS_3: [[u8; 64]; 2] = [
+    [
+         3, 3,15,15, 8, 3,15,15,  8, 8, 6, 6, 6, 5, 3, 3,
+         3, 3, 8,15, 3, 3, 6,10,  5, 8, 8, 6, 8, 5,15,15,
+         8,15, 3, 5, 6,10, 8,15, 15, 3,15, 5,15,15,15,15,
+         3,15, 5, 5, 5, 8, 5,10,  5,10, 8,13,15,12, 3, 3,
+    ],
+    [
+        15, 8, 8, 3,15,15, 3, 8, 15,15,15,15,15,15,15, 8,
+        15, 8,15, 3,15, 8,15, 8,  3,15, 6,10,15,15,10, 8,
+        15, 3,15,10,10, 8, 9,10,  6,15, 8,15, 3, 6, 6, 8,
+        15, 3,15,15,15,15,15,15, 15,15,15,15, 3,15,15, 8,
+    ],
+];
+
+const BC7_WEIGHTS_2: [u32; 4] = [0, 21, 43, 64];
+const BC7_WEIGHTS_3: [u32; 8] = [0, 9, 18, 27, 37, 46, 55, 64];
+#[rustfmt::skip]
+const BC7_WEIGHTS_4: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+fn bc7_weight(bits: u32, index: u32) -> u32 {
+    match bits {
+        2 => BC7_WEIGHTS_2[index as usize],
+        3 => BC7_WEIGHTS_3[index as usize],
+        _ => BC7_WEIGHTS_4[index as usize],
+    }
+}
+
+// reads `count` indices, the anchors' one bit shorter
+fn bc7_indices(bits: &mut Bits, count: u32, anchors: &[usize]) -> [u32; 16] {
+    std::array::from_fn(|texel| {
+        if count == 0 {
+            0
+        } else if anchors.contains(&texel) {
+            bits.read(count - 1)
+        } else {
+            bits.read(count)
+        }
+    })
+}
+
+fn bc7(block: &[u8]) -> [[u8; 4]; 16] {
+    let mut bits = Bits {
+        value: u128::from_le_bytes(block.try_into().expect("Fail to read a 16 byte block")),
+        position: 0,
+    };
+    // the mode is the position of the lowest set bit, a block without one is reserved
+    let mode_number = block[0].trailing_zeros();
+    if mode_number >= 8 {
+        return [[0; 4]; 16];
+    }
+    bits.read(mode_number + 1);
+    let mode = &BC7_MODES[mode_number as usize];
+
+    let partition = bits.read(mode.partition_bits) as usize;
+    let rotation = bits.read(mode.rotation_bits);
+    let index_selection = bits.read(mode.index_selection_bits);
+
+    // [subset * 2 + endpoint][channel], every red first, then green, blue and alpha
+    let endpoints_count = mode.subsets * 2;
+    let mut endpoints = [[0u32; 4]; 6];
+    for channel in 0..4 {
+        let count = if channel < 3 {
+            mode.color_bits
+        } else {
+            mode.alpha_bits
+        };
+        for endpoint in endpoints.iter_mut().take(endpoints_count) {
+            endpoint[channel] = bits.read(count);
+        }
+    }
+
+    let mut precision = [
+        mode.color_bits,
+        mode.color_bits,
+        mode.color_bits,
+        mode.alpha_bits,
+    ];
+    if mode.endpoint_pbits || mode.shared_pbits {
+        let pbits: Vec<u32> = if mode.endpoint_pbits {
+            (0..endpoints_count).map(|_| bits.read(1)).collect()
+        } else {
+            (0..mode.subsets)
+                .flat_map(|_| {
+                    let pbit = bits.read(1);
+                    [pbit, pbit]
+                })
+                .collect()
+        };
+        for (endpoint, pbit) in endpoints.iter_mut().zip(pbits) {
+            for (channel, value) in endpoint.iter_mut().enumerate() {
+                if precision[channel] > 0 {
+                    *value = (*value << 1) | pbit;
+                }
+            }
+        }
+        for channel_precision in precision.iter_mut().filter(|p| **p > 0) {
+            *channel_precision += 1;
+        }
+    }
+
+    // up to 8 bits, the top bits repeated into the bottom ones
+    for endpoint in endpoints.iter_mut().take(endpoints_count) {
+        for (channel, value) in endpoint.iter_mut().enumerate() {
+            *value = match precision[channel] {
+                0 => 255,
+                p => (*value << (8 - p)) | (*value >> (2 * p - 8)),
+            };
+        }
+    }
+
+    let subset_of = |texel: usize| match mode.subsets {
+        1 => 0,
+        2 => (BC7_PARTITIONS_2[partition] >> texel) as usize & 1,
+        _ => BC7_PARTITIONS_3[partition][texel] as usize,
+    };
+    let anchors: Vec<usize> = match mode.subsets {
+        1 => vec![0],
+        2 => vec![0, BC7_ANCHORS_2[partition] as usize],
+        _ => vec![
+            0,
+            BC7_ANCHORS_3[0][partition] as usize,
+            BC7_ANCHORS_3[1][partition] as usize,
+        ],
+    };
+    let primary = bc7_indices(&mut bits, mode.index_bits, &anchors);
+    let secondary = bc7_indices(&mut bits, mode.secondary_index_bits, &anchors);
+
+    std::array::from_fn(|texel| {
+        let subset = subset_of(texel);
+        let (e0, e1) = (endpoints[subset * 2], endpoints[subset * 2 + 1]);
+        // modes 4 and 5 weigh color and alpha with their own indices, mode 4 can swap them
+        let (mut color_bits, mut color_index) = (mode.index_bits, primary[texel]);
+        let (mut alpha_bits, mut alpha_index) = (mode.index_bits, primary[texel]);
+        if mode.secondary_index_bits > 0 {
+            alpha_bits = mode.secondary_index_bits;
+            alpha_index = secondary[texel];
+            if index_selection == 1 {
+                std::mem::swap(&mut color_bits, &mut alpha_bits);
+                std::mem::swap(&mut color_index, &mut alpha_index);
+            }
+        }
+
+        let mut color: [u8; 4] = std::array::from_fn(|channel| {
+            let weight = if channel < 3 {
+                bc7_weight(color_bits, color_index)
+            } else {
+                bc7_weight(alpha_bits, alpha_index)
+            };
+            (((64 - weight) * e0[channel] + weight * e1[channel] + 32) >> 6) as u8
+        });
+        if rotation > 0 {
+            color.swap(3, rotation as usize - 1);
+        }
+        color
+    })
+}
+
+const ETC_MODIFIERS: [[i32; 2]; 8] = [
+    [2, 8],
+    [5, 17],
+    [9, 29],
+    [13, 42],
+    [18, 60],
+    [24, 80],
+    [33, 106],
+    [47, 183],
+];
+
+const ETC_DISTANCES: [i32; 8] = [3, 6, 11, 16, 23, 32, 41, 64];
+
+// bits `high` down to `low` of a big endian block
+fn field(block: u64, high: u32, low: u32) -> i32 {
+    ((block >> low) & ((1 << (high - low + 1)) - 1)) as i32
+}
+
+fn extend4(value: i32) -> i32 {
+    value * 17
+}
+
+fn extend5(value: i32) -> i32 {
+    (value << 3) | (value >> 2)
+}
+
+fn clamp_rgb(rgb: [i32; 3]) -> [u8; 4] {
+    [
+        rgb[0].clamp(0, 255) as u8,
+        rgb[1].clamp(0, 255) as u8,
+        rgb[2].clamp(0, 255) as u8,
+        255,
+    ]
+}
+
+// etc indices run down the columns, texel `x * 4 + y`, with the low bits after the high ones
+fn etc_index(block: u64, x: usize, y: usize) -> usize {
+    let texel = x * 4 + y;
+    let high = (block >> (16 + texel)) & 1;
+    let low = (block >> texel) & 1;
+    (high << 1 | low) as usize
+}
+
+fn etc2_rgb(block: &[u8]) -> [[u8; 4]; 16] {
+    let block = u64::from_be_bytes(block[..8].try_into().expect("Fail to read an 8 byte block"));
+    let differential = field(block, 33, 33) == 1;
+
+    if differential {
+        let base = [
+            field(block, 63, 59),
+            field(block, 55, 51),
+            field(block, 47, 43),
+        ];
+        // 3 bit two's complement
+        let delta = [
+            field(block, 58, 56),
+            field(block, 50, 48),
+            field(block, 42, 40),
+        ]
+        .map(|d| if d >= 4 { d - 8 } else { d });
+        let overflows = |channel: usize| !(0..32).contains(&(base[channel] + delta[channel]));
+        if overflows(0) {
+            return etc2_t(block);
+        } else if overflows(1) {
+            return etc2_h(block);
+        } else if overflows(2) {
+            return etc2_planar(block);
+        }
+        let colors = [
+            base.map(extend5),
+            [0, 1, 2].map(|channel| extend5(base[channel] + delta[channel])),
+        ];
+        return etc1(block, colors);
+    }
+
+    let colors = [
+        [
+            field(block, 63, 60),
+            field(block, 55, 52),
+            field(block, 47, 44),
+        ]
+        .map(extend4),
+        [
+            field(block, 59, 56),
+            field(block, 51, 48),
+            field(block, 43, 40),
+        ]
+        .map(extend4),
+    ];
+    etc1(block, colors)
+}
+
+// two half blocks with a color each, brightened or darkened by a table of modifiers
+fn etc1(block: u64, colors: [[i32; 3]; 2]) -> [[u8; 4]; 16] {
+    let flipped = field(block, 32, 32) == 1;
+    let tables = [field(block, 39, 37), field(block, 36, 34)];
+
+    std::array::from_fn(|texel| {
+        let (x, y) = (texel % 4, texel / 4);
+        // side by side halves, or one above the other when flipped
+        let half = if flipped { y / 2 } else { x / 2 };
+        let [small, large] = ETC_MODIFIERS[tables[half] as usize];
+        let modifier = [small, large, -small, -large][etc_index(block, x, y)];
+        clamp_rgb(colors[half].map(|c| c + modifier))
+    })
+}
+
+fn etc2_paint(block: u64, paint: [[u8; 4]; 4]) -> [[u8; 4]; 16] {
+    std::array::from_fn(|texel| paint[etc_index(block, texel % 4, texel / 4)])
+}
+
+fn etc2_t(block: u64) -> [[u8; 4]; 16] {
+    let c0 = [
+        field(block, 60, 59) << 2 | field(block, 57, 56),
+        field(block, 55, 52),
+        field(block, 51, 48),
+    ]
+    .map(extend4);
+    let c1 = [
+        field(block, 47, 44),
+        field(block, 43, 40),
+        field(block, 39, 36),
+    ]
+    .map(extend4);
+    let distance = ETC_DISTANCES[(field(block, 35, 34) << 1 | field(block, 32, 32)) as usize];
+    etc2_paint(
+        block,
+        [
+            clamp_rgb(c0),
+            clamp_rgb(c1.map(|c| c + distance)),
+            clamp_rgb(c1),
+            clamp_rgb(c1.map(|c| c - distance)),
+        ],
+    )
+}
+
+fn etc2_h(block: u64) -> [[u8; 4]; 16] {
+    let c0 = [
+        field(block, 62, 59),
+        field(block, 58, 56) << 1 | field(block, 52, 52),
+        field(block, 51, 51) << 3 | field(block, 49, 47),
+    ];
+    let c1 = [
+        field(block, 46, 43),
+        field(block, 42, 39),
+        field(block, 38, 35),
+    ];
+    // the lowest bit of the distance is whether the first color is the larger one
+    let larger = |c: [i32; 3]| c[0] << 8 | c[1] << 4 | c[2];
+    let distance = ETC_DISTANCES[(field(block, 34, 34) << 2
+        | field(block, 32, 32) << 1
+        | (larger(c0) >= larger(c1)) as i32) as usize];
+    let (c0, c1) = (c0.map(extend4), c1.map(extend4));
+    etc2_paint(
+        block,
+        [
+            clamp_rgb(c0.map(|c| c + distance)),
+            clamp_rgb(c0.map(|c| c - distance)),
+            clamp_rgb(c1.map(|c| c + distance)),
+            clamp_rgb(c1.map(|c| c - distance)),
+        ],
+    )
+}
+
+// a gradient through three colors: at the origin, the right edge and the bottom edge
+fn etc2_planar(block: u64) -> [[u8; 4]; 16] {
+    let extend6 = |v: i32| (v << 2) | (v >> 4);
+    let extend7 = |v: i32| (v << 1) | (v >> 6);
+    let origin = [
+        extend6(field(block, 62, 57)),
+        extend7(field(block, 56, 56) << 6 | field(block, 54, 49)),
+        extend6(field(block, 48, 48) << 5 | field(block, 44, 43) << 3 | field(block, 41, 39)),
+    ];
+    let horizontal = [
+        extend6(field(block, 38, 34) << 1 | field(block, 32, 32)),
+        extend7(field(block, 31, 25)),
+        extend6(field(block, 24, 19)),
+    ];
+    let vertical = [
+        extend6(field(block, 18, 13)),
+        extend7(field(block, 12, 6)),
+        extend6(field(block, 5, 0)),
+    ];
+
+    std::array::from_fn(|texel| {
+        let (x, y) = ((texel % 4) as i32, (texel / 4) as i32);
+        clamp_rgb(std::array::from_fn(|c| {
+            (x * (horizontal[c] - origin[c]) + y * (vertical[c] - origin[c]) + 4 * origin[c] + 2)
+                >> 2
+        }))
+    })
+}
+
+#[rustfmt::skip]
+const EAC_MODIFIERS: [[i32; 8]; 16] = [
+    [-3, -6, -9, -15, 2, 5, 8, 14],
+    [-3, -7, -10, -13, 2, 6, 9, 12],
+    [-2, -5, -8, -13, 1, 4, 7, 12],
+    [-2, -4, -6, -13, 1, 3, 5, 12],
+    [-3, -6, -8, -12, 2, 5, 7, 11],
+    [-3, -7, -9, -11, 2, 6, 8, 10],
+    [-4, -7, -8, -11, 3, 6, 7, 10],
+    [-3, -5, -8, -11, 2, 4, 7, 10],
+    [-2, -6, -8, -10, 1, 5, 7, 9],
+    [-2, -5, -8, -10, 1, 4, 7, 9],
+    [-2, -4, -8, -10, 1, 3, 7, 9],
+    [-2, -5, -7, -10, 1, 4, 6, 9],
+    [-3, -4, -7, -10, 2, 3, 6, 9],
+    [-1, -2, -3, -10, 0, 1, 2, 9],
+    [-4, -6, -8, -9, 3, 5, 7, 8],
+    [-3, -5, -7, -9, 2, 4, 6, 8],
+];
+
+fn etc2_rgba(block: &[u8]) -> [[u8; 4]; 16] {
+    let alpha = u64::from_be_bytes(block[..8].try_into().expect("Fail to read a 16 byte block"));
+    let base = field(alpha, 63, 56);
+    let multiplier = field(alpha, 55, 52);
+    let modifiers = EAC_MODIFIERS[field(alpha, 51, 48) as usize];
+
+    let mut texels = etc2_rgb(&block[8..16]);
+    for (texel, color) in texels.iter_mut().enumerate() {
+        // 3 bits a texel from the top, down the columns like the color indices
+        let (x, y) = (texel % 4, texel / 4);
+        let shift = 45 - 3 * (x * 4 + y) as u32;
+        let modifier = modifiers[field(alpha, shift + 2, shift) as usize];
+        color[3] = (base + modifier * multiplier).clamp(0, 255) as u8;
+    }
+    texels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bc1_interpolates_between_its_colors() {
+        // pure red and pure blue, the texels going through indices 0, 1, 2, 3 in turn
+        let block = [0x00, 0xf8, 0x1f, 0x00, 0xe4, 0xe4, 0xe4, 0xe4];
+        let texels = to_rgba8(wgpu::TextureFormat::Bc1RgbaUnorm, 4, 4, &block).unwrap();
+        assert_eq!(
+            &texels[..16],
+            &[255, 0, 0, 255, 0, 0, 255, 255, 170, 0, 85, 255, 85, 0, 170, 255]
+        );
+    }
+
+    #[test]
+    fn bc1_has_transparent_black_when_the_colors_are_swapped() {
+        let block = [0x1f, 0x00, 0x00, 0xf8, 0xff, 0xff, 0xff, 0xff];
+        let texels = to_rgba8(wgpu::TextureFormat::Bc1RgbaUnorm, 4, 4, &block).unwrap();
+        assert!(texels.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn bc7_mode_6_solid_color() {
+        // mode 6, both endpoints the same 7 bit color with its p-bit, every index 0
+        let mut bits: u128 = 1 << 6;
+        let mut position = 7;
+        let mut push = |value: u128, count: u32| {
+            bits |= value << position;
+            position += count;
+        };
+        for channel in [100u128, 50, 25, 127] {
+            push(channel, 7);
+            push(channel, 7);
+        }
+        push(1, 1);
+        push(1, 1);
+        let texels =
+            to_rgba8(wgpu::TextureFormat::Bc7RgbaUnorm, 4, 4, &bits.to_le_bytes()).unwrap();
+        assert_eq!(&texels[..4], &[201, 101, 51, 255]);
+        assert!(texels.chunks(4).all(|texel| texel == [201, 101, 51, 255]));
+    }
+
+    #[test]
+    fn etc1_individual_colors_and_modifiers() {
+        // left half 0x8, right half 0x4 in every channel, tables 0 and 7, not flipped. every
+        // texel uses index 0, the small positive modifier
+        // byte 3: table 0, table 7, individual, not flipped
+        let block = [0x84, 0x84, 0x84, 0b0001_1100, 0, 0, 0, 0];
+        let texels = to_rgba8(wgpu::TextureFormat::Etc2Rgb8Unorm, 4, 4, &block).unwrap();
+        let texel = |x: usize| &texels[x * 4..x * 4 + 3];
+        assert_eq!(texel(0), &[138, 138, 138]);
+        assert_eq!(texel(3), &[115, 115, 115]);
+    }
+
+    #[test]
+    fn eac_alpha_is_base_plus_modifier_times_multiplier() {
+        // base 128, multiplier 2, table 0, every index 4 (+2)
+        let mut block = [
+            128,
+            0x20,
+            0b1001_0010,
+            0b0100_1001,
+            0b0010_0100,
+            0b1001_0010,
+            0b0100_1001,
+            0b0010_0100,
+        ]
+        .to_vec();
+        block.extend_from_slice(&[0; 8]);
+        let texels = to_rgba8(wgpu::TextureFormat::Etc2Rgba8Unorm, 4, 4, &block).unwrap();
+        assert!(texels.chunks(4).all(|texel| texel[3] == 132));
+    }
+
+    #[test]
+    fn edge_blocks_are_cropped() {
+        let block = [0x00, 0xf8, 0x00, 0xf8, 0, 0, 0, 0].repeat(4);
+        let texels = to_rgba8(wgpu::TextureFormat::Bc1RgbaUnorm, 5, 3, &block).unwrap();
+        assert_eq!(texels.len(), 5 * 3 * 4);
+        assert!(texels.chunks(4).all(|texel| texel == [255, 0, 0, 255]));
+    }
+}
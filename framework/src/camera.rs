@@ -0,0 +1,546 @@
+//! a camera plus two controllers that move it:
+//! - [`FpsCameraController`], WASD to move, hold the right mouse button and drag to look around
+//! - [`OrbitCameraController`], left drag rotates around a target, scroll zooms, middle drag pans
+
+use glam::{Mat4, Vec3};
+use winit::event::{
+    DeviceEvent, ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode,
+    WindowEvent,
+};
+
+use wgpu::util::DeviceExt;
+
+use crate::watchdog;
+
+// keep pitch away from straight up/down, look_at breaks when forward is parallel to up
+const PITCH_LIMIT: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+pub struct Camera {
+    pub position: Vec3,
+    /// radians around +y, 0 looks down -z
+    pub yaw: f32,
+    /// radians, positive looks up
+    pub pitch: f32,
+    pub aspect: f32,
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl Camera {
+    pub fn new(position: Vec3, yaw: f32, pitch: f32, aspect: f32) -> Camera {
+        Camera {
+            position,
+            yaw,
+            pitch,
+            aspect,
+            fovy: 45.0_f32.to_radians(),
+            znear: 0.1,
+            zfar: 100.,
+        }
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        Vec3::new(-sin_yaw * cos_pitch, sin_pitch, -cos_yaw * cos_pitch)
+    }
+
+    pub fn right(&self) -> Vec3 {
+        self.forward().cross(Vec3::Y).normalize()
+    }
+
+    pub fn view(&self) -> Mat4 {
+        Mat4::look_at_rh(self.position, self.position + self.forward(), Vec3::Y)
+    }
+
+    pub fn projection(&self) -> Mat4 {
+        Mat4::perspective_rh(self.fovy, self.aspect, self.znear, self.zfar)
+    }
+
+    pub fn view_proj(&self) -> Mat4 {
+        self.projection() * self.view()
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.aspect = width as f32 / height as f32;
+    }
+}
+
+/// anything that turns input into camera movement, both controllers feed the same [`Camera`],
+/// so the uniform update path doesn't care which one is active
+pub trait CameraController {
+    /// returns true if the event was used
+    fn process_window_event(&mut self, event: &WindowEvent) -> bool;
+
+    /// returns true if the event was used
+    fn process_device_event(&mut self, event: &DeviceEvent) -> bool;
+
+    /// apply the input gathered since the last call, `dt` in seconds
+    fn update_camera(&mut self, camera: &mut Camera, dt: f32);
+}
+
+pub struct FpsCameraController {
+    /// units per second
+    pub speed: f32,
+    /// radians per pixel of mouse movement
+    pub sensitivity: f32,
+    forward: bool,
+    backward: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+    looking: bool,
+    yaw_delta: f32,
+    pitch_delta: f32,
+}
+
+impl FpsCameraController {
+    pub fn new(speed: f32, sensitivity: f32) -> FpsCameraController {
+        FpsCameraController {
+            speed,
+            sensitivity,
+            forward: false,
+            backward: false,
+            left: false,
+            right: false,
+            up: false,
+            down: false,
+            looking: false,
+            yaw_delta: 0.,
+            pitch_delta: 0.,
+        }
+    }
+}
+
+// WASD moves, space/shift go up/down, right mouse button enables mouse look
+impl CameraController for FpsCameraController {
+    fn process_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => {
+                let pressed = *state == ElementState::Pressed;
+                match key {
+                    VirtualKeyCode::W => self.forward = pressed,
+                    VirtualKeyCode::S => self.backward = pressed,
+                    VirtualKeyCode::A => self.left = pressed,
+                    VirtualKeyCode::D => self.right = pressed,
+                    VirtualKeyCode::Space => self.up = pressed,
+                    VirtualKeyCode::LShift => self.down = pressed,
+                    _ => return false,
+                }
+                true
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Right,
+                ..
+            } => {
+                self.looking = *state == ElementState::Pressed;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // raw mouse motion, only turns the camera while the right mouse button is held
+    fn process_device_event(&mut self, event: &DeviceEvent) -> bool {
+        match event {
+            DeviceEvent::MouseMotion { delta } if self.looking => {
+                self.yaw_delta += delta.0 as f32 * self.sensitivity;
+                self.pitch_delta += delta.1 as f32 * self.sensitivity;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, dt: f32) {
+        // moving the mouse right turns right, moving it down looks down
+        camera.yaw -= self.yaw_delta;
+        camera.pitch = (camera.pitch - self.pitch_delta).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+        self.yaw_delta = 0.;
+        self.pitch_delta = 0.;
+
+        // walk on the horizontal plane, looking up doesn't make you fly
+        let forward = Vec3::new(-camera.yaw.sin(), 0., -camera.yaw.cos());
+        let right = camera.right();
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+
+        let direction = forward * axis(self.forward, self.backward)
+            + right * axis(self.right, self.left)
+            + Vec3::Y * axis(self.up, self.down);
+
+        camera.position += direction.normalize_or_zero() * self.speed * dt;
+    }
+}
+
+pub struct OrbitCameraController {
+    pub target: Vec3,
+    pub distance: f32,
+    /// radians per pixel of mouse movement
+    pub sensitivity: f32,
+    /// distance change per scroll line, relative to the current distance
+    pub zoom_speed: f32,
+    rotating: bool,
+    panning: bool,
+    yaw_delta: f32,
+    pitch_delta: f32,
+    pan_delta: (f32, f32),
+    zoom_delta: f32,
+}
+
+impl OrbitCameraController {
+    pub fn new(target: Vec3, distance: f32, sensitivity: f32) -> OrbitCameraController {
+        OrbitCameraController {
+            target,
+            distance,
+            sensitivity,
+            zoom_speed: 0.1,
+            rotating: false,
+            panning: false,
+            yaw_delta: 0.,
+            pitch_delta: 0.,
+            pan_delta: (0., 0.),
+            zoom_delta: 0.,
+        }
+    }
+}
+
+impl CameraController for OrbitCameraController {
+    fn process_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::MouseInput { state, button, .. } => {
+                let pressed = *state == ElementState::Pressed;
+                match button {
+                    MouseButton::Left => self.rotating = pressed,
+                    MouseButton::Middle => self.panning = pressed,
+                    _ => return false,
+                }
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.zoom_delta += match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => *lines,
+                    // touchpads scroll in pixels, call ~20 pixels a line
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.,
+                };
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn process_device_event(&mut self, event: &DeviceEvent) -> bool {
+        match event {
+            DeviceEvent::MouseMotion { delta } if self.rotating => {
+                self.yaw_delta += delta.0 as f32 * self.sensitivity;
+                self.pitch_delta += delta.1 as f32 * self.sensitivity;
+                true
+            }
+            DeviceEvent::MouseMotion { delta } if self.panning => {
+                self.pan_delta.0 += delta.0 as f32;
+                self.pan_delta.1 += delta.1 as f32;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update_camera(&mut self, camera: &mut Camera, _dt: f32) {
+        // drag the model around: dragging right swings the camera left, dragging down lifts it
+        camera.yaw -= self.yaw_delta;
+        camera.pitch = (camera.pitch - self.pitch_delta).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+
+        // scrolling up moves closer, scaled by distance so zoom feels the same near and far
+        self.distance =
+            (self.distance * (1. - self.zoom_delta * self.zoom_speed)).max(camera.znear);
+
+        // pan in the view plane, the target moves with the mouse at roughly the target's depth
+        let pan_scale = self.distance * self.sensitivity;
+        let up = camera.right().cross(camera.forward());
+        self.target += (-camera.right() * self.pan_delta.0 + up * self.pan_delta.1) * pan_scale;
+
+        self.yaw_delta = 0.;
+        self.pitch_delta = 0.;
+        self.pan_delta = (0., 0.);
+        self.zoom_delta = 0.;
+
+        camera.position = self.target - camera.forward() * self.distance;
+    }
+}
+
+/// a view_proj uniform buffer and its bind group, for shaders that take
+/// `@group(N) @binding(0) var<uniform> view_proj: mat4x4<f32>;`
+pub struct CameraBinding {
+    pub buffer: wgpu::Buffer,
+    pub layout: wgpu::BindGroupLayout,
+    pub bind_group: wgpu::BindGroup,
+}
+
+impl CameraBinding {
+    pub fn new(device: &wgpu::Device, camera: &Camera) -> CameraBinding {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(camera.view_proj().as_ref()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("camera bindgroup layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(64),
+                },
+                count: None,
+            }],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        CameraBinding {
+            buffer,
+            layout,
+            bind_group,
+        }
+    }
+
+    /// upload the camera's current view_proj, call once per frame before rendering
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera) {
+        watchdog::write_buffer(
+            queue,
+            &self.buffer,
+            0,
+            bytemuck::cast_slice(camera.view_proj().as_ref()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use glam::{Vec3, Vec4, Vec4Swizzles};
+    use winit::event::{
+        DeviceEvent, DeviceId, ElementState, KeyboardInput, MouseButton, VirtualKeyCode,
+        WindowEvent,
+    };
+
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!(
+            actual.abs_diff_eq(expected, EPSILON),
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    // where `point` ends up after the perspective divide
+    fn to_ndc(view_proj: Mat4, point: Vec3) -> Vec3 {
+        let clip = view_proj * point.extend(1.);
+        clip.xyz() / clip.w
+    }
+
+    fn camera() -> Camera {
+        Camera::new(Vec3::ZERO, 0., 0., 1.)
+    }
+
+    #[test]
+    fn yaw_and_pitch_follow_the_documented_axes() {
+        let mut camera = camera();
+        assert_close(camera.forward(), Vec3::NEG_Z);
+        assert_close(camera.right(), Vec3::X);
+
+        // a quarter turn around +y, counter clockwise seen from above
+        camera.yaw = FRAC_PI_2;
+        assert_close(camera.forward(), Vec3::NEG_X);
+        assert_close(camera.right(), Vec3::NEG_Z);
+
+        camera.yaw = 0.;
+        camera.pitch = 0.5;
+        assert!(camera.forward().y > 0.);
+        assert!((camera.forward().length() - 1.).abs() < EPSILON);
+        // right stays level, rolling isn't something the camera does
+        assert_close(camera.right(), Vec3::X);
+        assert!(camera.right().dot(camera.forward()).abs() < EPSILON);
+    }
+
+    #[test]
+    fn view_moves_the_camera_to_the_origin_looking_down_negative_z() {
+        let mut camera = camera();
+        camera.position = Vec3::new(1., 2., 3.);
+        camera.yaw = 0.7;
+        camera.pitch = -0.3;
+
+        let view = camera.view();
+        assert_close(view.transform_point3(camera.position), Vec3::ZERO);
+        assert_close(
+            view.transform_point3(camera.position + camera.forward() * 2.),
+            Vec3::new(0., 0., -2.),
+        );
+        assert_close(
+            view.transform_point3(camera.position + camera.right()),
+            Vec3::X,
+        );
+        // a rigid transform, nothing gets stretched
+        assert!((view.determinant() - 1.).abs() < EPSILON);
+    }
+
+    #[test]
+    fn projection_maps_near_and_far_to_wgpu_depth() {
+        let camera = camera();
+        let projection = camera.projection();
+
+        // wgpu's depth range is 0..1, not gl's -1..1
+        let near = to_ndc(projection, Vec3::new(0., 0., -camera.znear));
+        let far = to_ndc(projection, Vec3::new(0., 0., -camera.zfar));
+        assert!(near.z.abs() < EPSILON, "near plane at {}", near.z);
+        assert!((far.z - 1.).abs() < EPSILON, "far plane at {}", far.z);
+
+        // depth grows with distance, what the Less compare of the chapters expects
+        let middle = to_ndc(projection, Vec3::new(0., 0., -1.));
+        assert!(middle.z > near.z && middle.z < far.z);
+
+        // behind the camera w turns negative, the rasterizer clips those
+        let behind = projection * Vec4::new(0., 0., 1., 1.);
+        assert!(behind.w < 0.);
+    }
+
+    #[test]
+    fn projection_keeps_fovy_vertical_and_aspect_horizontal() {
+        let mut camera = camera();
+        camera.resize(1600, 800);
+        assert!((camera.aspect - 2.).abs() < EPSILON);
+
+        let projection = camera.projection();
+        let half = camera.fovy / 2.;
+        // the top edge of the view at distance 1, and the right edge twice as far out
+        let top = to_ndc(projection, Vec3::new(0., half.tan(), -1.));
+        let right = to_ndc(projection, Vec3::new(half.tan() * 2., 0., -1.));
+        assert!((top.y - 1.).abs() < EPSILON, "top edge at {}", top.y);
+        assert!((right.x - 1.).abs() < EPSILON, "right edge at {}", right.x);
+
+        // y up stays up in ndc, the flip to texture coordinates happens later
+        assert!(to_ndc(projection, Vec3::new(0., 0.1, -1.)).y > 0.);
+    }
+
+    #[test]
+    fn view_proj_is_projection_after_view() {
+        let mut camera = camera();
+        camera.position = Vec3::new(-2., 1., 4.);
+        camera.yaw = -0.4;
+
+        let point = Vec3::new(0.5, 0.25, -1.);
+        let expected = camera.projection() * (camera.view() * point.extend(1.));
+        let actual = camera.view_proj() * point.extend(1.);
+        assert!(actual.abs_diff_eq(expected, EPSILON));
+
+        // what the camera looks at lands in the middle of the screen
+        let ahead = camera.position + camera.forward() * 5.;
+        let ndc = to_ndc(camera.view_proj(), ahead);
+        assert!(ndc.x.abs() < EPSILON && ndc.y.abs() < EPSILON);
+    }
+
+    #[test]
+    fn orbit_keeps_the_target_in_front_at_distance() {
+        let mut camera = camera();
+        let target = Vec3::new(1., 0.5, -2.);
+        let mut controller = OrbitCameraController::new(target, 4., 0.01);
+
+        controller.process_window_event(&mouse_input(MouseButton::Left, ElementState::Pressed));
+        controller.process_device_event(&DeviceEvent::MouseMotion { delta: (30., -20.) });
+        controller.update_camera(&mut camera, 0.);
+
+        assert!((camera.position.distance(target) - 4.).abs() < EPSILON);
+        assert_close(camera.position + camera.forward() * 4., target);
+    }
+
+    #[test]
+    fn orbit_and_fps_pitch_stop_short_of_straight_up() {
+        let mut camera = camera();
+        let mut controller = OrbitCameraController::new(Vec3::ZERO, 2., 1.);
+        controller.process_window_event(&mouse_input(MouseButton::Left, ElementState::Pressed));
+        controller.process_device_event(&DeviceEvent::MouseMotion { delta: (0., -100.) });
+        controller.update_camera(&mut camera, 0.);
+        assert!((camera.pitch - PITCH_LIMIT).abs() < EPSILON);
+
+        let mut camera = self::camera();
+        let mut controller = FpsCameraController::new(1., 1.);
+        controller.process_window_event(&mouse_input(MouseButton::Right, ElementState::Pressed));
+        controller.process_device_event(&DeviceEvent::MouseMotion { delta: (0., 100.) });
+        controller.update_camera(&mut camera, 0.);
+        assert!((camera.pitch + PITCH_LIMIT).abs() < EPSILON);
+        // look_at still has a usable up vector
+        assert!(camera.view().is_finite());
+    }
+
+    #[test]
+    fn fps_walks_level_at_speed() {
+        let mut camera = camera();
+        camera.pitch = 1.;
+        let mut controller = FpsCameraController::new(3., 0.01);
+
+        controller.process_window_event(&key(VirtualKeyCode::W, ElementState::Pressed));
+        controller.update_camera(&mut camera, 0.5);
+        // looking up doesn't lift the camera
+        assert_close(camera.position, Vec3::new(0., 0., -1.5));
+
+        // diagonals aren't faster
+        controller.process_window_event(&key(VirtualKeyCode::D, ElementState::Pressed));
+        controller.update_camera(&mut camera, 1.);
+        assert!((camera.position.distance(Vec3::new(0., 0., -1.5)) - 3.).abs() < EPSILON);
+
+        controller.process_window_event(&key(VirtualKeyCode::W, ElementState::Released));
+        controller.process_window_event(&key(VirtualKeyCode::D, ElementState::Released));
+        let before = camera.position;
+        controller.update_camera(&mut camera, 1.);
+        assert_close(camera.position, before);
+    }
+
+    fn mouse_input(button: MouseButton, state: ElementState) -> WindowEvent<'static> {
+        #[allow(deprecated)]
+        WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state,
+            button,
+            modifiers: Default::default(),
+        }
+    }
+
+    fn key(key: VirtualKeyCode, state: ElementState) -> WindowEvent<'static> {
+        #[allow(deprecated)]
+        WindowEvent::KeyboardInput {
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(key),
+                modifiers: Default::default(),
+            },
+            is_synthetic: false,
+        }
+    }
+}
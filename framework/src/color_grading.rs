@@ -0,0 +1,364 @@
+//! color grading through a 3d lookup table. a [`Lut`] is a small cube of colors, the finished
+//! image's color is the coordinate and what is stored there replaces it. anything a grading
+//! tool can do to a single pixel, curves, tints, saturation, fits in one.
+//!
+//! tables come from `.cube` files, the text format most grading tools export, or from strip
+//! pngs: `size` squares of `size` x `size` side by side, red across each square, green down
+//! and blue from square to square. both are in srgb encoded colors and [`ColorGrading`] looks
+//! them up with the image encoded the same way.
+//!
+//! ```ignore
+//! let lut = Lut::from_cube(device, queue, "warm", include_str!("warm.cube"))?;
+//! let grading = ColorGrading::new(device, config.format);
+//! let bind_group = grading.create_bind_group(device, &scene, &lut);
+//! // every frame, after the scene pass into `scene.view`
+//! grading.draw(&mut rpass, &bind_group);
+//! ```
+
+use std::io;
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{include_wgsl, util::DeviceExt};
+
+use crate::{texture::Texture, watchdog};
+
+/// a 3d texture of `size` texels along each side
+pub struct Lut {
+    pub name: String,
+    pub size: u32,
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+}
+
+impl Lut {
+    /// every color maps to itself, grading with it changes nothing
+    pub fn identity(device: &wgpu::Device, queue: &wgpu::Queue, size: u32) -> Lut {
+        let step = 1. / (size - 1) as f32;
+        let mut colors = Vec::new();
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    colors.push([r as f32 * step, g as f32 * step, b as f32 * step]);
+                }
+            }
+        }
+        Lut::from_colors(device, queue, "identity", size, &colors)
+    }
+
+    /// parse the text of a `.cube` file. only 3d tables over the default 0..1 domain
+    pub fn from_cube(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+        text: &str,
+    ) -> io::Result<Lut> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let mut size = None;
+        let mut colors = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with("TITLE") {
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or_default();
+            match keyword {
+                "LUT_3D_SIZE" => {
+                    size = words.next().and_then(|word| word.parse::<u32>().ok());
+                }
+                "LUT_1D_SIZE" => {
+                    return Err(invalid(format!("{} is a 1d table", name)));
+                }
+                "DOMAIN_MIN" | "DOMAIN_MAX" => {
+                    let expected = if keyword == "DOMAIN_MIN" { 0. } else { 1. };
+                    if words.any(|word| word.parse::<f32>() != Ok(expected)) {
+                        return Err(invalid(format!("{} has a domain other than 0..1", name)));
+                    }
+                }
+                _ => {
+                    let values: Vec<f32> = line
+                        .split_whitespace()
+                        .filter_map(|word| word.parse().ok())
+                        .collect();
+                    if values.len() != 3 {
+                        return Err(invalid(format!(
+                            "{} line {}: expected r g b, found {:?}",
+                            name,
+                            number + 1,
+                            line
+                        )));
+                    }
+                    colors.push([values[0], values[1], values[2]]);
+                }
+            }
+        }
+
+        let size = size.ok_or_else(|| invalid(format!("{} has no LUT_3D_SIZE", name)))?;
+        if size < 2 || colors.len() != (size * size * size) as usize {
+            return Err(invalid(format!(
+                "{} has {} colors, LUT_3D_SIZE {} needs {}",
+                name,
+                colors.len(),
+                size,
+                size * size * size
+            )));
+        }
+        Ok(Lut::from_colors(device, queue, name, size, &colors))
+    }
+
+    /// decode a strip png, `size` squares side by side in an image `size * size` wide
+    pub fn from_strip(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+        bytes: &[u8],
+    ) -> io::Result<Lut> {
+        let img = image::load_from_memory(bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?
+            .to_rgb8();
+        let size = img.height();
+        if size < 2 || img.width() != size * size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{} is {}x{}, a strip is size * size wide and size high",
+                    name,
+                    img.width(),
+                    img.height()
+                ),
+            ));
+        }
+
+        let mut colors = Vec::new();
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let pixel = img.get_pixel(b * size + r, g);
+                    colors.push(pixel.0.map(|v| v as f32 / 255.));
+                }
+            }
+        }
+        Ok(Lut::from_colors(device, queue, name, size, &colors))
+    }
+
+    // `colors` red fastest, then green, then blue, the order of a .cube file and of the
+    // texels of a 3d texture
+    fn from_colors(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+        size: u32,
+        colors: &[[f32; 3]],
+    ) -> Lut {
+        let texels: Vec<u8> = colors
+            .iter()
+            .flat_map(|color| {
+                let [r, g, b] = color.map(|v| (v.clamp(0., 1.) * 255. + 0.5) as u8);
+                [r, g, b, 255]
+            })
+            .collect();
+
+        // 8 bits are enough once the sampler blends between neighbours. a plain unorm, the
+        // colors are already srgb encoded and must come back out as they went in
+        let texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some(name),
+                size: wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: size,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D3,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+            },
+            &texels,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Lut {
+            name: name.to_string(),
+            size,
+            texture,
+            view,
+            sampler,
+        }
+    }
+}
+
+// matches `Grading` in color_grading.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct GradingUniform {
+    amount: f32,
+    _padding: [f32; 3],
+}
+
+pub struct ColorGrading {
+    /// how much of the graded color replaces the original, 0 to 1
+    pub amount: f32,
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+}
+
+impl ColorGrading {
+    /// `target_format` is the format of the texture the pass draws into, fully graded
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> ColorGrading {
+        let amount = 1.;
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Color Grading Buffer"),
+            contents: bytemuck::bytes_of(&GradingUniform {
+                amount,
+                _padding: [0.; 3],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("color_grading_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("color_grading.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("color grading"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        ColorGrading {
+            amount,
+            layout,
+            pipeline,
+            buffer,
+        }
+    }
+
+    /// grades `source` with `lut`, make a new one when the target is recreated or the lut
+    /// swapped
+    pub fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        source: &Texture,
+        lut: &Lut,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("color_grading_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&source.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&lut.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&lut.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: self.buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// upload `amount`, call after changing it and before the pass
+    pub fn update(&self, queue: &wgpu::Queue) {
+        let uniform = GradingUniform {
+            amount: self.amount,
+            _padding: [0.; 3],
+        };
+        watchdog::write_buffer(queue, &self.buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    /// covers the current viewport of `rpass` with the graded source of `bind_group`
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, bind_group: &'a wgpu::BindGroup) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,58 @@
+// looks every pixel of the source up in a 3d table, one fullscreen triangle
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Grading {
+    amount: f32,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragInput.tex_coord = uv;
+    return fragInput;
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var t_lut: texture_3d<f32>;
+@group(0) @binding(3)
+var s_lut: sampler;
+@group(0) @binding(4)
+var<uniform> grading: Grading;
+
+fn linear_to_srgb(c: vec3<f32>) -> vec3<f32> {
+    let low = c * 12.92;
+    let high = 1.055 * pow(c, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, c <= vec3<f32>(0.0031308));
+}
+
+fn srgb_to_linear(c: vec3<f32>) -> vec3<f32> {
+    let low = c / 12.92;
+    let high = pow((c + 0.055) / 1.055, vec3<f32>(2.4));
+    return select(high, low, c <= vec3<f32>(0.04045));
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    // the sampler hands back linear values, the table is indexed by srgb encoded ones
+    let color = textureSample(t_source, s_source, input.tex_coord);
+    let encoded = linear_to_srgb(clamp(color.rgb, vec3<f32>(0.0), vec3<f32>(1.0)));
+
+    // 0 and 1 land on the centers of the first and last texels, not on their outer edges
+    let size = f32(textureDimensions(t_lut).x);
+    let coord = encoded * ((size - 1.0) / size) + 0.5 / size;
+    let graded = srgb_to_linear(textureSample(t_lut, s_lut, coord).rgb);
+
+    return vec4<f32>(mix(color.rgb, graded, grading.amount), color.a);
+}
@@ -0,0 +1,131 @@
+//! a compatibility profile for the gl backend and webgl2. with [`ENV_VAR`] set to `webgl2` the
+//! device is requested with `Limits::downlevel_webgl2_defaults`: no compute shaders, storage
+//! buffers or storage textures, what a browser without webgpu would give. chapters ask the
+//! device what it has with [`storage_buffers`] and [`compute`] and take another way there:
+//!
+//! - arrays a shader reads, like point lights, go in a uniform buffer of a fixed length
+//!   instead of a storage buffer, [`array_shader`] rewrites the shader to match
+//! - work done in compute shaders runs on the cpu and is uploaded, t38's marching cubes
+//!
+//! chapters that are about compute, t30-ibl's bakes and t44-forward-plus' light culling, have
+//! no other way and still need the default profile.
+
+use std::borrow::Cow;
+
+use tracing::info;
+
+/// environment variable that picks the profile, `webgl2` or `default`, default by default
+pub const ENV_VAR: &str = "LEARNWGPU_PROFILE";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// what the chapters are written for, `Limits::default`
+    Default,
+    /// `Limits::downlevel_webgl2_defaults`, with the adapter's texture sizes
+    Webgl2,
+}
+
+impl Profile {
+    /// from [`ENV_VAR`]
+    pub fn select() -> Profile {
+        match std::env::var(ENV_VAR).as_deref() {
+            Ok("webgl2") => Profile::Webgl2,
+            _ => Profile::Default,
+        }
+    }
+
+    /// what the device is requested with
+    pub fn limits(self, adapter: &wgpu::Adapter) -> wgpu::Limits {
+        match self {
+            Profile::Default => wgpu::Limits::default(),
+            // the window can still be as large as the adapter allows
+            Profile::Webgl2 => {
+                wgpu::Limits::downlevel_webgl2_defaults().using_resolution(adapter.limits())
+            }
+        }
+    }
+
+    pub fn log(self, adapter: &wgpu::Adapter) {
+        let compliant = adapter.get_downlevel_capabilities().is_webgpu_compliant();
+        info!(
+            "{:?} profile on {} ({:?}), the adapter is {}webgpu compliant",
+            self,
+            adapter.get_info().name,
+            adapter.get_info().backend,
+            if compliant { "" } else { "not " }
+        );
+    }
+}
+
+/// whether shaders can read and write storage buffers
+pub fn storage_buffers(device: &wgpu::Device) -> bool {
+    device.limits().max_storage_buffers_per_shader_stage > 0
+}
+
+/// whether compute pipelines can run at all
+pub fn compute(device: &wgpu::Device) -> bool {
+    device.limits().max_compute_workgroups_per_dimension > 0
+}
+
+/// how to bind an array `array_shader` made the shader read: a read only storage buffer, or a
+/// uniform buffer where there are none
+pub fn array_binding_type(device: &wgpu::Device) -> wgpu::BufferBindingType {
+    if storage_buffers(device) {
+        wgpu::BufferBindingType::Storage { read_only: true }
+    } else {
+        wgpu::BufferBindingType::Uniform
+    }
+}
+
+/// the usage of the buffer behind such an array, with COPY_DST to update it
+pub fn array_usage(device: &wgpu::Device) -> wgpu::BufferUsages {
+    if storage_buffers(device) {
+        wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST
+    } else {
+        wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST
+    }
+}
+
+/// `source` as it is where there are storage buffers. without, the runtime sized
+/// `var<storage, read> name: array<T>;` becomes `var<uniform> name: array<T, len>;` and every
+/// `arrayLength(&name)` becomes `len`. the buffer then has to hold exactly `len` elements, and
+/// `T` has to be a multiple of 16 bytes, the stride of uniform arrays
+pub fn array_shader(
+    device: &wgpu::Device,
+    label: &str,
+    source: &str,
+    name: &str,
+    len: usize,
+) -> wgpu::ShaderModule {
+    let source = if storage_buffers(device) {
+        Cow::Borrowed(source)
+    } else {
+        Cow::Owned(uniform_array(source, name, len))
+    };
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source),
+    })
+}
+
+fn uniform_array(source: &str, name: &str, len: usize) -> String {
+    let declaration = format!("var<storage, read> {}: array<", name);
+    let start = source
+        .find(&declaration)
+        .unwrap_or_else(|| panic!("no `{}...>;` in the shader", declaration));
+    let element_start = start + declaration.len();
+    let element_end = element_start
+        + source[element_start..]
+            .find(">;")
+            .unwrap_or_else(|| panic!("`{}` isn't closed", declaration));
+    let element = &source[element_start..element_end];
+
+    let mut rewritten = String::with_capacity(source.len());
+    rewritten.push_str(&source[..start]);
+    rewritten.push_str(&format!(
+        "var<uniform> {}: array<{}, {}>;",
+        name, element, len
+    ));
+    rewritten.push_str(&source[element_end + 2..]);
+    rewritten.replace(&format!("arrayLength(&{})", name), &format!("{}u", len))
+}
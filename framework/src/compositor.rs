@@ -0,0 +1,554 @@
+//! the window as a stack of named layers instead of one pass everything draws into in turn.
+//! each layer is a render target the size of the window. [`App::render`](crate::App::render)
+//! draws the [`SCENE`], the layers an app names in [`App::layers`](crate::App::layers) come
+//! next, the watchdog's flash goes in [`DEBUG`], the hud and the console in [`UI`]. a final
+//! pass then blends the ones drawn that frame into the window in that order, the scene opaque
+//! and the rest by their alpha, each faded by its opacity.
+//!
+//! screenshots, recordings and F10 dumps are taken between two halves of that pass: first
+//! the layers that are `captured`, then the rest on top of them. the scene and the app's
+//! layers are captured to start with, debug and ui aren't, so a screenshot shows what the
+//! chapter drew. a layer that isn't captured but goes under one that is keeps its place in
+//! the window, the second half draws the whole stack again then. the console's `layers` lists them, and `layer <name> <setting>` changes one:
+//! `on` and `off`, an opacity from 0 to 1, `capture` and `nocapture`.
+//!
+//! layers are cleared to transparent, a pass drawing into one loads it and blends with
+//! `BlendState::ALPHA_BLENDING`, which leaves what the final pass expects behind.
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::include_wgsl;
+
+use crate::{screenshot::DumpTarget, texture::Texture, watchdog};
+
+/// what [`App::render`](crate::App::render) draws, always the bottom layer
+pub const SCENE: &str = "scene";
+/// the watchdog's flash
+pub const DEBUG: &str = "debug";
+/// the [`hud`](crate::hud) and the console
+pub const UI: &str = "ui";
+
+// matches `Layer` in compositor.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LayerUniform {
+    opacity: f32,
+    _padding: [f32; 3],
+}
+
+pub struct Layer {
+    pub name: &'static str,
+    pub enabled: bool,
+    /// 0 to 1
+    pub opacity: f32,
+    /// in screenshots, recordings and dumps
+    pub captured: bool,
+    target: Texture,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    /// asked for this frame, the others are left out of the final pass
+    drawn: bool,
+}
+
+impl Layer {
+    /// `name on, opacity 1.00, captured`, a line of the `layers` command
+    pub fn status(&self) -> String {
+        format!(
+            "{} {}, opacity {:.2}, {}",
+            self.name,
+            if self.enabled { "on" } else { "off" },
+            self.opacity,
+            if self.captured {
+                "captured"
+            } else {
+                "not captured"
+            }
+        )
+    }
+}
+
+/// one setting of a layer, what `layer <name> <setting>` asks for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Change {
+    Enabled(bool),
+    Opacity(f32),
+    Captured(bool),
+}
+
+/// the name and setting of `layer <name> <setting>`'s arguments
+pub fn parse_change(args: &[&str]) -> Result<(String, Change), String> {
+    let [name, setting] = args else {
+        return Err("layer takes a name and on, off, capture, nocapture or an opacity".to_string());
+    };
+    let change = match *setting {
+        "on" => Change::Enabled(true),
+        "off" => Change::Enabled(false),
+        "capture" => Change::Captured(true),
+        "nocapture" => Change::Captured(false),
+        opacity => match opacity.parse::<f32>() {
+            Ok(opacity) if (0. ..=1.).contains(&opacity) => Change::Opacity(opacity),
+            _ => return Err(format!("can't make sense of `{}`", opacity)),
+        },
+    };
+    Ok((name.to_string(), change))
+}
+
+pub struct Compositor {
+    layers: Vec<Layer>,
+    layout: wgpu::BindGroupLayout,
+    base_pipeline: wgpu::RenderPipeline,
+    layer_pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+}
+
+impl Compositor {
+    /// a layer for each of `names`, bottom first, in the surface's format
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        names: &[&'static str],
+    ) -> Compositor {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("compositor_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<LayerUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("compositor.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point, blend| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some("compositor"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: config.format,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let base_pipeline = create_pipeline("fs_base", None);
+        let layer_pipeline = create_pipeline(
+            "fs_layer",
+            Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+        );
+
+        let layers = names
+            .iter()
+            .map(|name| {
+                let target = Texture::create_render_target(
+                    device,
+                    config.width,
+                    config.height,
+                    config.format,
+                    name,
+                );
+                let uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(name),
+                    size: std::mem::size_of::<LayerUniform>() as u64,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let bind_group = create_bind_group(device, &layout, &target, &uniform_buffer);
+                Layer {
+                    name,
+                    enabled: true,
+                    opacity: 1.,
+                    captured: *name != DEBUG && *name != UI,
+                    target,
+                    uniform_buffer,
+                    bind_group,
+                    drawn: false,
+                }
+            })
+            .collect();
+
+        Compositor {
+            layers,
+            layout,
+            base_pipeline,
+            layer_pipeline,
+            format: config.format,
+            width: config.width,
+            height: config.height,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        self.width = config.width;
+        self.height = config.height;
+        for layer in &mut self.layers {
+            layer.target = Texture::create_render_target(
+                device,
+                config.width,
+                config.height,
+                config.format,
+                layer.name,
+            );
+            layer.bind_group =
+                create_bind_group(device, &self.layout, &layer.target, &layer.uniform_buffer);
+        }
+    }
+
+    pub fn layers(&self) -> &[Layer] {
+        &self.layers
+    }
+
+    pub fn enabled(&self, name: &str) -> bool {
+        matches!(self.layers.iter().find(|layer| layer.name == name), Some(layer) if layer.enabled)
+    }
+
+    /// apply a [`Change`] to the layer `name`, its new [`Layer::status`]
+    pub fn change(&mut self, name: &str, change: Change) -> Result<String, String> {
+        let layer = self
+            .layers
+            .iter_mut()
+            .find(|layer| layer.name == name)
+            .ok_or_else(|| format!("no layer `{}`, layers lists them", name))?;
+        match change {
+            Change::Enabled(enabled) => layer.enabled = enabled,
+            Change::Opacity(opacity) => layer.opacity = opacity,
+            Change::Captured(captured) => layer.captured = captured,
+        }
+        Ok(layer.status())
+    }
+
+    /// the view to draw the layer `name` into this frame, cleared to transparent the first
+    /// time it's asked for. `None` when there's no layer of that name
+    pub fn target(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+    ) -> Option<&wgpu::TextureView> {
+        let layer = self.layers.iter_mut().find(|layer| layer.name == name)?;
+        if !layer.drawn {
+            layer.drawn = true;
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("clear layer"),
+            });
+            encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some(layer.name),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &layer.target.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            queue.submit(Some(encoder.finish()));
+        }
+        Some(&layer.target.view)
+    }
+
+    /// the captured layers drawn this frame into `view`, over black
+    pub fn compose(&self, device: &wgpu::Device, queue: &wgpu::Queue, view: &wgpu::TextureView) {
+        self.draw(device, queue, view, true);
+    }
+
+    /// the layers drawn this frame that aren't captured, over what [`Compositor::compose`] left
+    /// in `view`, or all of them again when one of those goes under a captured layer. the
+    /// frame is done after it, the next one starts with no layers drawn
+    pub fn compose_uncaptured(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+    ) {
+        self.draw(device, queue, view, false);
+        for layer in &mut self.layers {
+            layer.drawn = false;
+        }
+    }
+
+    // one pass over `view`. the captured half clears it first, the other loads what that left
+    // unless one of its layers goes under a captured one, e.g. a scene that's `nocapture`,
+    // then it clears and draws every layer again so the order holds
+    fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        captured: bool,
+    ) {
+        let drawn = self
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| layer.drawn && layer.enabled);
+        let (layers, clear): (Vec<(usize, &Layer)>, bool) = if captured {
+            (drawn.filter(|(_, layer)| layer.captured).collect(), true)
+        } else {
+            let uncaptured: Vec<(usize, &Layer)> =
+                drawn.clone().filter(|(_, layer)| !layer.captured).collect();
+            let Some(&(first, _)) = uncaptured.first() else {
+                return;
+            };
+            let last_captured = drawn
+                .clone()
+                .filter(|(_, layer)| layer.captured)
+                .map(|(index, _)| index)
+                .last();
+            if last_captured.is_some_and(|last| last < first) {
+                (uncaptured, false)
+            } else {
+                (drawn.collect(), true)
+            }
+        };
+        for (_, layer) in &layers {
+            let uniform = LayerUniform {
+                opacity: layer.opacity,
+                _padding: [0.; 3],
+            };
+            watchdog::write_buffer(
+                queue,
+                &layer.uniform_buffer,
+                0,
+                bytemuck::bytes_of(&uniform),
+            );
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("compositor"),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("compositor pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: if clear {
+                            wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+                        } else {
+                            wgpu::LoadOp::Load
+                        },
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            for (index, layer) in layers {
+                // the scene covers what's under it, whatever alpha it wrote. it's only ever
+                // drawn first into a cleared pass
+                rpass.set_pipeline(if index == 0 {
+                    &self.base_pipeline
+                } else {
+                    &self.layer_pipeline
+                });
+                rpass.set_bind_group(0, &layer.bind_group, &[]);
+                rpass.draw(0..3, 0..1);
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    /// every layer's target, for F10 frame dumps. the ones not drawn this frame hold the last
+    /// frame they were
+    pub fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        self.layers
+            .iter()
+            .map(|layer| {
+                DumpTarget::new(
+                    layer.name,
+                    &layer.target.texture,
+                    self.width,
+                    self.height,
+                    self.format,
+                )
+            })
+            .collect()
+    }
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    target: &Texture,
+    uniform_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("compositor_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&target.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&target.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: uniform_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{headless_device, read_texture};
+
+    const WIDTH: u32 = 64;
+
+    // one layer's target filled with `color`, premultiplied
+    fn fill(
+        compositor: &mut Compositor,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        name: &str,
+        color: wgpu::Color,
+    ) {
+        let view = compositor.target(device, queue, name).unwrap();
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(color),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn first_pixel(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> [u8; 4] {
+        let texels: Vec<[u8; 4]> = read_texture(device, queue, texture, (WIDTH, 1), 0, 0);
+        texels[0]
+    }
+
+    fn near(pixel: [u8; 4], expected: [u8; 4]) -> bool {
+        pixel
+            .iter()
+            .zip(expected)
+            .all(|(&a, b)| (a as i32 - b as i32).abs() <= 2)
+    }
+
+    #[test]
+    fn a_scene_left_out_of_captures_stays_underneath() {
+        let Some((device, queue)) = headless_device() else {
+            return;
+        };
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            width: WIDTH,
+            height: 1,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        };
+        let mut compositor = Compositor::new(&device, &config, &[SCENE, "overlay", DEBUG, UI]);
+        compositor.change(SCENE, Change::Captured(false)).unwrap();
+        let window = device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: WIDTH,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        });
+        let view = window.create_view(&wgpu::TextureViewDescriptor::default());
+
+        fill(&mut compositor, &device, &queue, SCENE, wgpu::Color::RED);
+        let half_green = wgpu::Color {
+            r: 0.,
+            g: 0.5,
+            b: 0.,
+            a: 0.5,
+        };
+        fill(&mut compositor, &device, &queue, "overlay", half_green);
+
+        // the capture has the overlay over black, without the scene
+        compositor.compose(&device, &queue, &view);
+        assert!(near(
+            first_pixel(&device, &queue, &window),
+            [0, 128, 0, 255]
+        ));
+        // the window has it over the scene, not the scene drawn opaque over it
+        compositor.compose_uncaptured(&device, &queue, &view);
+        assert!(near(
+            first_pixel(&device, &queue, &window),
+            [128, 128, 0, 255]
+        ));
+    }
+
+    #[test]
+    fn parses_layer_changes() {
+        assert_eq!(
+            parse_change(&["ui", "off"]),
+            Ok(("ui".to_string(), Change::Enabled(false)))
+        );
+        assert_eq!(
+            parse_change(&["scene", "0.5"]),
+            Ok(("scene".to_string(), Change::Opacity(0.5)))
+        );
+        assert_eq!(
+            parse_change(&["debug", "capture"]),
+            Ok(("debug".to_string(), Change::Captured(true)))
+        );
+        assert!(parse_change(&["ui", "1.5"]).is_err());
+        assert!(parse_change(&["ui", "loud"]).is_err());
+        assert!(parse_change(&["ui"]).is_err());
+    }
+}
@@ -0,0 +1,44 @@
+// the final pass: every layer drawn over the window with a fullscreen triangle, the base one
+// opaque and the rest blended by their alpha, all scaled by the layer's opacity
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragInput.tex_coord = uv;
+    return fragInput;
+}
+
+// matches `LayerUniform` in compositor.rs
+struct Layer {
+    opacity: f32,
+};
+
+@group(0) @binding(0)
+var t_layer: texture_2d<f32>;
+@group(0) @binding(1)
+var s_layer: sampler;
+@group(0) @binding(2)
+var<uniform> layer: Layer;
+
+// the bottom layer, whatever alpha the scene left behind it covers the window, faded to black
+@fragment
+fn fs_base(input: FragInput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_layer, s_layer, input.tex_coord);
+    return vec4<f32>(color.rgb * layer.opacity, 1.0);
+}
+
+// layers are cleared to transparent and drawn with alpha blending, which leaves the color
+// already multiplied by the alpha. scaling both by the opacity fades the layer as a whole
+@fragment
+fn fs_layer(input: FragInput) -> @location(0) vec4<f32> {
+    return textureSample(t_layer, s_layer, input.tex_coord) * layer.opacity;
+}
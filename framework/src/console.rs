@@ -0,0 +1,646 @@
+//! a console over every chapter, opened and closed with `~`. a line typed into it is a command
+//! name and its arguments split at whitespace, enter runs it and prints what it answered, up
+//! and down walk back through the lines run before.
+//!
+//! the driver has a few commands of its own, `help`, `clear`, `screenshot`, `record`, `dump`,
+//! `graph` and `script`, and asks the app for more with [`App::commands`](crate::App::commands):
+//!
+//! ```ignore
+//! fn commands(commands: &mut Commands<Self>) {
+//!     commands.add("speed", "<value>", "how fast the cube turns", |app, _window, args| {
+//!         app.speed = console::parse(args, 0)?;
+//!         Ok(format!("speed {}", app.speed))
+//!     });
+//! }
+//! ```
+//!
+//! while it's open the console takes the keyboard, the app still gets the mouse and the key
+//! releases, so a key held when it opens doesn't stay down in the app. a
+//! [`script`](crate::script) runs the same commands on a clock.
+
+use std::collections::VecDeque;
+use std::str::FromStr;
+
+use bytemuck::{Pod, Zeroable};
+use tracing::{info, warn};
+use wgpu::{include_wgsl, util::DeviceExt};
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use crate::{font, watchdog};
+
+/// the key that opens and closes the console
+pub const TOGGLE: VirtualKeyCode = VirtualKeyCode::Grave;
+
+/// window pixels per font pixel
+const SCALE: f32 = 2.;
+/// the band's height in lines, the last one is the input
+const ROWS: usize = 16;
+/// the most characters in a line, more than fit across most windows
+const MAX_COLUMNS: usize = 128;
+/// how many characters the overlay's text can hold, rows times columns
+pub(crate) const MAX_TEXT: usize = ROWS * MAX_COLUMNS;
+/// lines kept for the band, older ones are dropped
+const SCROLLBACK: usize = 256;
+/// commands kept for up and down
+const HISTORY: usize = 100;
+// marks a character of an error in the text the shader reads
+const ERROR_BIT: u8 = 0x80;
+
+/// what a command answers, printed into the console. errors are shown in red
+pub type Reply = Result<String, String>;
+
+type Run<T> = Box<dyn FnMut(&mut T, &Window, &[&str]) -> Reply>;
+
+struct Command<T> {
+    name: &'static str,
+    usage: &'static str,
+    help: &'static str,
+    run: Run<T>,
+}
+
+/// the commands that run against a `T`, the app's or the driver's
+pub struct Commands<T> {
+    commands: Vec<Command<T>>,
+}
+
+impl<T> Default for Commands<T> {
+    fn default() -> Self {
+        Commands::new()
+    }
+}
+
+impl<T> Commands<T> {
+    pub fn new() -> Commands<T> {
+        Commands {
+            commands: Vec::new(),
+        }
+    }
+
+    /// `usage` names the arguments for `help`, e.g. `"<name> <value>"`. `run` gets the words
+    /// after the name. a later command with the same name replaces the earlier one
+    pub fn add(
+        &mut self,
+        name: &'static str,
+        usage: &'static str,
+        help: &'static str,
+        run: impl FnMut(&mut T, &Window, &[&str]) -> Reply + 'static,
+    ) -> &mut Self {
+        self.commands.retain(|command| command.name != name);
+        self.commands.push(Command {
+            name,
+            usage,
+            help,
+            run: Box::new(run),
+        });
+        self
+    }
+
+    /// run `line` against `target`, `None` when it doesn't start with one of these commands
+    pub fn run(&mut self, target: &mut T, window: &Window, line: &str) -> Option<Reply> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (name, args) = words.split_first()?;
+        let command = self
+            .commands
+            .iter_mut()
+            .find(|command| command.name == *name)?;
+        Some((command.run)(target, window, args))
+    }
+
+    /// a line for each command, its name, usage and what it does
+    pub fn help(&self) -> Vec<String> {
+        self.commands
+            .iter()
+            .map(|command| {
+                let call = format!("{} {}", command.name, command.usage);
+                format!("{:<24} {}", call.trim_end(), command.help)
+            })
+            .collect()
+    }
+}
+
+/// argument `index` of a command, parsed
+pub fn parse<F: FromStr>(args: &[&str], index: usize) -> Result<F, String> {
+    let arg = args
+        .get(index)
+        .ok_or_else(|| format!("argument {} is missing", index + 1))?;
+    arg.parse()
+        .map_err(|_| format!("can't make sense of `{}`", arg))
+}
+
+/// what [`Console::process_window_event`] did with an event
+#[derive(Debug, PartialEq, Eq)]
+pub enum Input {
+    /// not for the console, pass it on
+    Ignored,
+    /// the console used it
+    Handled,
+    /// enter was pressed, this line is to be run
+    Submit(String),
+}
+
+struct Line {
+    text: String,
+    error: bool,
+}
+
+pub struct Console {
+    open: bool,
+    input: String,
+    /// oldest first
+    history: VecDeque<String>,
+    /// the history entry up and down got to, `None` while typing a new line
+    recalled: Option<usize>,
+    /// oldest first
+    lines: VecDeque<Line>,
+    /// made the first time the console is drawn
+    overlay: Option<Overlay>,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Console::new()
+    }
+}
+
+impl Console {
+    pub fn new() -> Console {
+        Console {
+            open: false,
+            input: String::new(),
+            history: VecDeque::new(),
+            recalled: None,
+            lines: VecDeque::new(),
+            overlay: None,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// the toggle, and while it's open every key press and typed character. releases go on to
+    /// the app
+    pub fn process_window_event(&mut self, event: &WindowEvent) -> Input {
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(TOGGLE),
+                        ..
+                    },
+                ..
+            } => {
+                self.open = !self.open;
+                Input::Handled
+            }
+            _ if !self.open => Input::Ignored,
+            // the toggle types one of these right after opening
+            WindowEvent::ReceivedCharacter('`' | '~') => Input::Handled,
+            WindowEvent::ReceivedCharacter(c) => {
+                if font::glyph(*c).is_some() {
+                    self.input.push(*c);
+                }
+                Input::Handled
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => match key {
+                VirtualKeyCode::Escape => {
+                    self.open = false;
+                    Input::Handled
+                }
+                VirtualKeyCode::Back => {
+                    self.input.pop();
+                    Input::Handled
+                }
+                VirtualKeyCode::Up => {
+                    self.recall(-1);
+                    Input::Handled
+                }
+                VirtualKeyCode::Down => {
+                    self.recall(1);
+                    Input::Handled
+                }
+                VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => self.submit(),
+                _ => Input::Handled,
+            },
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Released,
+                        ..
+                    },
+                ..
+            } => Input::Ignored,
+            WindowEvent::KeyboardInput { .. } => Input::Handled,
+            _ => Input::Ignored,
+        }
+    }
+
+    fn submit(&mut self) -> Input {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+        self.recalled = None;
+        if line.is_empty() {
+            return Input::Handled;
+        }
+
+        if self.history.back() != Some(&line) {
+            self.history.push_back(line.clone());
+            if self.history.len() > HISTORY {
+                self.history.pop_front();
+            }
+        }
+        self.print(&format!("> {}", line));
+        Input::Submit(line)
+    }
+
+    // `step` back (-1) or forward (1) through the history, past the newest is an empty line
+    fn recall(&mut self, step: isize) {
+        if self.history.is_empty() {
+            return;
+        }
+        let newest = self.history.len() - 1;
+        self.recalled = match (self.recalled, step < 0) {
+            (None, true) => Some(newest),
+            (None, false) => None,
+            (Some(0), true) => Some(0),
+            (Some(index), true) => Some(index - 1),
+            (Some(index), false) if index == newest => None,
+            (Some(index), false) => Some(index + 1),
+        };
+        self.input = match self.recalled {
+            Some(index) => self.history[index].clone(),
+            None => String::new(),
+        };
+    }
+
+    /// add `text` to the console, a line per line of it
+    pub fn print(&mut self, text: &str) {
+        self.push(text, false);
+    }
+
+    /// a command's answer, errors are also logged as warnings
+    pub fn reply(&mut self, reply: Reply) {
+        match reply {
+            Ok(text) => {
+                if !text.is_empty() {
+                    info!("{}", text);
+                }
+                self.push(&text, false)
+            }
+            Err(text) => {
+                warn!("{}", text);
+                self.push(&text, true)
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    fn push(&mut self, text: &str, error: bool) {
+        for line in text.lines() {
+            self.lines.push_back(Line {
+                text: line.to_string(),
+                error,
+            });
+        }
+        while self.lines.len() > SCROLLBACK {
+            self.lines.pop_front();
+        }
+    }
+
+    /// the console over the finished frame in `view`, when it's open
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        config: &wgpu::SurfaceConfiguration,
+    ) {
+        if !self.open {
+            return;
+        }
+        let columns = ((config.width as f32 / (8. * SCALE)) as usize).clamp(1, MAX_COLUMNS);
+        let text = self.layout(columns);
+        self.overlay
+            .get_or_insert_with(|| Overlay::new(device, config.format))
+            .draw(device, queue, view, Grid::band(ROWS, columns), &text);
+    }
+
+    // `ROWS` lines of `columns` characters: the newest lines, wrapped, above the input line.
+    // characters there's no glyph for become `?`
+    fn layout(&self, columns: usize) -> Vec<u8> {
+        let mut rows: Vec<Vec<u8>> = Vec::new();
+        for line in self.lines.iter().rev() {
+            let mark = if line.error { ERROR_BIT } else { 0 };
+            let bytes: Vec<u8> = line
+                .text
+                .chars()
+                .map(|c| font::glyph(c).map_or(b'?', |_| c as u8) | mark)
+                .collect();
+            let wrapped: Vec<Vec<u8>> = if bytes.is_empty() {
+                vec![Vec::new()]
+            } else {
+                bytes.chunks(columns).map(<[u8]>::to_vec).collect()
+            };
+            rows.extend(wrapped.into_iter().rev());
+            if rows.len() >= ROWS - 1 {
+                break;
+            }
+        }
+        rows.truncate(ROWS - 1);
+        rows.reverse();
+
+        // the end of the input and the cursor when it's longer than the line
+        let input: Vec<u8> = format!("> {}_", self.input).bytes().collect();
+        rows.push(input[input.len().saturating_sub(columns)..].to_vec());
+
+        let mut text = vec![0; ROWS * columns];
+        let first = ROWS - rows.len();
+        for (row, bytes) in rows.iter().enumerate() {
+            let start = (first + row) * columns;
+            text[start..start + bytes.len()].copy_from_slice(bytes);
+        }
+        text
+    }
+}
+
+// matches the start of `Console` in console.wgsl, the text follows
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ConsoleUniform {
+    scale: f32,
+    rows: u32,
+    columns: u32,
+    band: u32,
+}
+
+/// the size of the text an [`Overlay`] draws from the top left corner of the window
+pub(crate) struct Grid {
+    pub rows: usize,
+    pub columns: usize,
+    /// the background reaches the right edge of the window, otherwise it ends with the text
+    pub band: bool,
+}
+
+impl Grid {
+    fn band(rows: usize, columns: usize) -> Grid {
+        Grid {
+            rows,
+            columns,
+            band: true,
+        }
+    }
+}
+
+/// text in the console's font over a dark background, also what the
+/// [`params`](crate::params) panel is drawn with
+pub(crate) struct Overlay {
+    pipeline: wgpu::RenderPipeline,
+    console_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Overlay {
+    pub(crate) fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Overlay {
+        let console_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Console Buffer"),
+            size: (std::mem::size_of::<ConsoleUniform>() + MAX_TEXT) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // padded to whole vec4s, the shader's array has 48
+        let mut glyphs = bytemuck::cast_slice::<_, u8>(&font::GLYPHS).to_vec();
+        glyphs.resize(48 * 16, 0);
+        let font_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Font Buffer"),
+            contents: &glyphs,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let uniform = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("console_bind_group_layout"),
+            entries: &[uniform(0), uniform(1)],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("console_bind_group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: console_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: font_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("console.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("console"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Overlay {
+            pipeline,
+            console_buffer,
+            bind_group,
+        }
+    }
+
+    /// `text` is `grid.rows` rows of `grid.columns` characters, at most [`MAX_TEXT`]
+    pub(crate) fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        grid: Grid,
+        text: &[u8],
+    ) {
+        let uniform = ConsoleUniform {
+            scale: SCALE,
+            rows: grid.rows as u32,
+            columns: grid.columns as u32,
+            band: grid.band as u32,
+        };
+        watchdog::write_buffer(queue, &self.console_buffer, 0, bytemuck::bytes_of(&uniform));
+        // whole u32s, the rest of the buffer is never read
+        let mut text = text.to_vec();
+        text.resize(text.len().div_ceil(4) * 4, 0);
+        watchdog::write_buffer(
+            queue,
+            &self.console_buffer,
+            std::mem::size_of::<ConsoleUniform>() as u64,
+            &text,
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("console"),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("console pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: VirtualKeyCode) -> WindowEvent<'static> {
+        key_state(key, ElementState::Pressed)
+    }
+
+    #[allow(deprecated)]
+    fn key_state(key: VirtualKeyCode, state: ElementState) -> WindowEvent<'static> {
+        WindowEvent::KeyboardInput {
+            device_id: unsafe { winit::event::DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(key),
+                modifiers: Default::default(),
+            },
+            is_synthetic: false,
+        }
+    }
+
+    fn type_line(console: &mut Console, line: &str) -> Input {
+        for c in line.chars() {
+            console.process_window_event(&WindowEvent::ReceivedCharacter(c));
+        }
+        console.process_window_event(&key(VirtualKeyCode::Return))
+    }
+
+    #[test]
+    fn typing_submitting_and_recalling() {
+        let mut console = Console::new();
+        assert_eq!(
+            console.process_window_event(&WindowEvent::ReceivedCharacter('a')),
+            Input::Ignored
+        );
+        console.process_window_event(&key(TOGGLE));
+        console.process_window_event(&WindowEvent::ReceivedCharacter('~'));
+        assert_eq!(
+            type_line(&mut console, " set x 1 "),
+            Input::Submit("set x 1".to_string())
+        );
+        type_line(&mut console, "help");
+        type_line(&mut console, "help");
+        assert_eq!(console.history.len(), 2);
+
+        console.process_window_event(&key(VirtualKeyCode::Up));
+        assert_eq!(console.input, "help");
+        console.process_window_event(&key(VirtualKeyCode::Up));
+        console.process_window_event(&key(VirtualKeyCode::Up));
+        assert_eq!(console.input, "set x 1");
+        console.process_window_event(&key(VirtualKeyCode::Down));
+        console.process_window_event(&key(VirtualKeyCode::Down));
+        assert_eq!(console.input, "");
+
+        console.process_window_event(&key(VirtualKeyCode::Escape));
+        assert!(!console.is_open());
+    }
+
+    #[test]
+    fn releases_reach_the_app_while_open() {
+        let mut console = Console::new();
+        console.process_window_event(&key(TOGGLE));
+        // w was held when the console opened, the app has to see it come up
+        assert_eq!(
+            console.process_window_event(&key_state(VirtualKeyCode::W, ElementState::Released)),
+            Input::Ignored
+        );
+        assert_eq!(
+            console.process_window_event(&key(VirtualKeyCode::W)),
+            Input::Handled
+        );
+    }
+
+    #[test]
+    fn layout_wraps_and_keeps_the_newest_lines() {
+        let mut console = Console::new();
+        for i in 0..20 {
+            console.print(&format!("line {}", i));
+        }
+        console.reply(Err("abcdefghij".to_string()));
+        console.input = "0123456789".to_string();
+
+        let text = console.layout(8);
+        let row = |row: usize| &text[row * 8..row * 8 + 8];
+        assert_eq!(row(ROWS - 1), b"3456789_");
+        assert_eq!(row(ROWS - 2)[..2], [b'i' | ERROR_BIT, b'j' | ERROR_BIT]);
+        assert_eq!(row(ROWS - 3)[0], b'a' | ERROR_BIT);
+        assert_eq!(row(ROWS - 4), b"line 19\0");
+        assert_eq!(row(0), b"line 7\0\0");
+    }
+
+    #[test]
+    fn parse_arguments() {
+        assert_eq!(parse::<f32>(&["x", "0.5"], 1), Ok(0.5));
+        assert!(parse::<f32>(&["x"], 1).is_err());
+        assert!(parse::<u32>(&["-1"], 0).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+// the console, a band across the top of the window with a grid of 8x8 glyphs on a dark
+// background. drawn over the finished frame with alpha blending. the params panel is the same
+// grid in a box at the top left
+
+struct FragInput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// matches `ConsoleUniform` and the text written after it in console.rs
+struct Console {
+    // window pixels per font pixel
+    scale: f32,
+    // the bottom row is the input line
+    rows: u32,
+    columns: u32,
+    // 1 when the background spans the window's width, 0 when it ends after `columns`
+    band: u32,
+    // one character a byte, row after row of `columns`. bit 7 marks an error
+    text: array<vec4<u32>, 128>,
+};
+
+// `font::GLYPHS`, two u32 for the 8 rows of a glyph
+struct Font {
+    glyphs: array<vec4<u32>, 48>,
+};
+
+@group(0) @binding(0)
+var<uniform> console: Console;
+@group(0) @binding(1)
+var<uniform> font: Font;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // one triangle over the whole window, the fragment shader leaves out what's below the band
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return fragInput;
+}
+
+// the byte at `index` of the text
+fn character(index: u32) -> u32 {
+    let word = console.text[index / 16u][(index / 4u) % 4u];
+    return (word >> ((index % 4u) * 8u)) & 0xffu;
+}
+
+// whether `pixel` of the glyph for `code` is set, the font starts at the space
+fn lit(code: u32, pixel: vec2<u32>) -> bool {
+    if (code < 32u || code > 126u) {
+        return false;
+    }
+    let glyph = code - 32u;
+    let rows = font.glyphs[glyph / 2u][(glyph % 2u) * 2u + pixel.y / 4u];
+    let bits = (rows >> ((pixel.y % 4u) * 8u)) & 0xffu;
+    return ((bits >> pixel.x) & 1u) == 1u;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let cell_size = 8.0 * console.scale;
+    let cell = vec2<u32>(input.clip_position.xy / cell_size);
+    if (cell.y >= console.rows || (console.band == 0u && cell.x >= console.columns)) {
+        discard;
+    }
+
+    if (cell.x < console.columns) {
+        let code = character(cell.y * console.columns + cell.x);
+        let pixel = vec2<u32>((input.clip_position.xy % cell_size) / console.scale);
+        if (lit(code & 0x7fu, pixel)) {
+            if (code >= 128u) {
+                return vec4<f32>(1.0, 0.45, 0.4, 1.0);
+            }
+            if (cell.y == console.rows - 1u) {
+                return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+            }
+            return vec4<f32>(0.75, 0.78, 0.8, 1.0);
+        }
+    }
+    return vec4<f32>(0.02, 0.02, 0.03, 0.9);
+}
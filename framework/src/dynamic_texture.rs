@@ -0,0 +1,131 @@
+//! a texture with a copy of its pixels on the cpu, for textures that change while the app runs.
+//!
+//! edit [`DynamicTexture::image`], mark what changed with [`DynamicTexture::mark_dirty`] and
+//! call [`DynamicTexture::upload`] once per frame. only the rectangle covering every change since
+//! the last upload is written, a `write_texture` with that rectangle's origin, instead of the
+//! whole image.
+//!
+//! ```ignore
+//! canvas.image.put_pixel(x, y, image::Rgba([255, 0, 0, 255]));
+//! canvas.mark_dirty(x, y, 1, 1);
+//! // every frame
+//! canvas.upload(queue);
+//! ```
+
+use crate::{texture::Texture, watchdog};
+
+/// in texels, `x` and `y` are the top left corner
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    /// the smallest rect covering both
+    pub fn union(self, other: Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+}
+
+pub struct DynamicTexture {
+    pub texture: Texture,
+    /// what the texture will hold after the next [`DynamicTexture::upload`]
+    pub image: image::RgbaImage,
+    dirty: Option<Rect>,
+}
+
+impl DynamicTexture {
+    /// srgb rgba8 like [`Texture::from_image`], uploaded whole once
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        image: image::RgbaImage,
+        label: &str,
+    ) -> DynamicTexture {
+        let texture = Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(image.clone()),
+            label,
+        );
+        DynamicTexture {
+            texture,
+            image,
+            dirty: None,
+        }
+    }
+
+    /// note that `image` changed in this rect, clipped to the image
+    pub fn mark_dirty(&mut self, x: u32, y: u32, width: u32, height: u32) {
+        let x = x.min(self.image.width());
+        let y = y.min(self.image.height());
+        let rect = Rect {
+            x,
+            y,
+            width: width.min(self.image.width() - x),
+            height: height.min(self.image.height() - y),
+        };
+        if rect.width == 0 || rect.height == 0 {
+            return;
+        }
+
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => dirty.union(rect),
+            None => rect,
+        });
+    }
+
+    /// mark the whole image, e.g. after clearing it
+    pub fn mark_all_dirty(&mut self) {
+        self.mark_dirty(0, 0, self.image.width(), self.image.height());
+    }
+
+    /// what the next [`DynamicTexture::upload`] will write
+    pub fn dirty(&self) -> Option<Rect> {
+        self.dirty
+    }
+
+    /// write what changed since the last call, the rect that was written if anything was
+    pub fn upload(&mut self, queue: &wgpu::Queue) -> Option<Rect> {
+        let rect = self.dirty.take()?;
+
+        // the layout points into the full image, rows keep the full image's stride and the
+        // offset skips to the rect's first texel, so nothing is copied on the cpu side
+        let row_bytes = 4 * self.image.width();
+        watchdog::record_upload(4 * rect.width as u64 * rect.height as u64);
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: rect.x,
+                    y: rect.y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &self.image,
+            wgpu::ImageDataLayout {
+                offset: (rect.y * row_bytes + rect.x * 4) as wgpu::BufferAddress,
+                bytes_per_row: std::num::NonZeroU32::new(row_bytes),
+                rows_per_image: std::num::NonZeroU32::new(rect.height),
+            },
+            wgpu::Extent3d {
+                width: rect.width,
+                height: rect.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        Some(rect)
+    }
+}
@@ -0,0 +1,326 @@
+//! turn an equirectangular panorama, the usual layout of `.hdr` environment maps, into a cube
+//! map on the gpu. each face is one fullscreen triangle that looks its texels up in the
+//! panorama, so a skybox or an ibl bake can sample the result like any other cube.
+//!
+//! ```ignore
+//! let panorama = framework::equirect::load_hdr(&bytes)?;
+//! let sky = framework::equirect::to_cube(device, queue, &panorama, 512, "sky");
+//! ```
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{include_wgsl, util::DeviceExt};
+
+use crate::{texture::Texture, watchdog};
+
+/// of the cube [`to_cube`] returns, keeps values above 1 and is filterable everywhere
+pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// matches `Face` in equirect.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct FaceUniform {
+    face: u32,
+    _padding: [u32; 3],
+}
+
+/// decode a radiance `.hdr` into 32 bit floats. `image::load_from_memory` also reads them, but
+/// squashes them into rgb8 on the way, losing everything above 1
+pub fn load_hdr(bytes: &[u8]) -> image::ImageResult<image::DynamicImage> {
+    let decoder = image::codecs::hdr::HdrDecoder::new(bytes)?;
+    let metadata = decoder.metadata();
+    let pixels: Vec<f32> = decoder
+        .read_image_hdr()?
+        .into_iter()
+        .flat_map(|pixel| pixel.0)
+        .collect();
+    let img = image::Rgb32FImage::from_raw(metadata.width, metadata.height, pixels)
+        .expect("Fail to fit the decoded pixels into an image");
+    Ok(image::DynamicImage::ImageRgb32F(img))
+}
+
+/// a `size` x `size` cube of [`FORMAT`] with a `Cube` view and a linear sampler. the passes are
+/// submitted before this returns. `panorama` is read as linear floats, an 8 bit image's srgb
+/// values go in unconverted
+pub fn to_cube(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    panorama: &image::DynamicImage,
+    size: u32,
+    label: &str,
+) -> Texture {
+    let rgba = panorama.to_rgba32f();
+    let panorama_size = wgpu::Extent3d {
+        width: rgba.width(),
+        height: rgba.height(),
+        depth_or_array_layers: 1,
+    };
+    let panorama_texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("equirect panorama"),
+        size: panorama_size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba32Float,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    });
+    watchdog::write_texture(
+        queue,
+        panorama_texture.as_image_copy(),
+        bytemuck::cast_slice(rgba.as_raw()),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: std::num::NonZeroU32::new(16 * panorama_size.width),
+            rows_per_image: std::num::NonZeroU32::new(panorama_size.height),
+        },
+        panorama_size,
+    );
+    let panorama_view = panorama_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let cube = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        // COPY_SRC for the tests
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+    });
+
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("equirect_bind_group_layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        }],
+    });
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("equirect_bind_group"),
+        layout: &layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(&panorama_view),
+        }],
+    });
+
+    // which face each draw is
+    let face_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("equirect_face_bind_group_layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<FaceUniform>() as u64),
+            },
+            count: None,
+        }],
+    });
+    let face_bind_groups: Vec<wgpu::BindGroup> = (0..6)
+        .map(|face| {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Equirect Face Buffer"),
+                contents: bytemuck::bytes_of(&FaceUniform {
+                    face,
+                    _padding: [0; 3],
+                }),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("equirect_face_bind_group"),
+                layout: &face_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            })
+        })
+        .collect();
+
+    let shader = device.create_shader_module(include_wgsl!("equirect.wgsl"));
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&layout, &face_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("equirect"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_face",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_face",
+            targets: &[Some(FORMAT.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("equirect encoder"),
+    });
+    for (face, face_bind_group) in (0..6).zip(&face_bind_groups) {
+        let view = cube.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("equirect face view"),
+            dimension: Some(wgpu::TextureViewDimension::D2),
+            base_array_layer: face,
+            array_layer_count: std::num::NonZeroU32::new(1),
+            ..Default::default()
+        });
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("equirect face pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&pipeline);
+        rpass.set_bind_group(0, &bind_group, &[]);
+        rpass.set_bind_group(1, face_bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+    queue.submit(Some(encoder.finish()));
+
+    let view = cube.create_view(&wgpu::TextureViewDescriptor {
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..Default::default()
+    });
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some(label),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+    Texture {
+        texture: cube,
+        view,
+        sampler,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{f16_to_f32, headless, read_texture};
+    use glam::Vec3;
+    use tracing::warn;
+
+    // the panorama, a gradient across and down that's different on either side of the seam
+    const WIDTH: u32 = 16;
+    const HEIGHT: u32 = 8;
+    const SIZE: u32 = 8;
+
+    fn panorama_texel(x: i32, y: i32) -> [f32; 3] {
+        let x = x.rem_euclid(WIDTH as i32) as f32;
+        let y = y.clamp(0, HEIGHT as i32 - 1) as f32;
+        [
+            x / WIDTH as f32,
+            y / HEIGHT as f32,
+            (x + y) / (WIDTH + HEIGHT) as f32,
+        ]
+    }
+
+    // `direction` in equirect.wgsl
+    fn direction(face: u32, u: f32, v: f32) -> Vec3 {
+        match face {
+            0 => Vec3::new(1., -v, -u),
+            1 => Vec3::new(-1., -v, u),
+            2 => Vec3::new(u, 1., v),
+            3 => Vec3::new(u, -1., -v),
+            4 => Vec3::new(u, -v, 1.),
+            _ => Vec3::new(-u, -v, -1.),
+        }
+        .normalize()
+    }
+
+    // `fs_face` in equirect.wgsl, the bilinear lookup by hand
+    fn cpu_lookup(dir: Vec3) -> [f32; 3] {
+        let (lon, lat) = (dir.x.atan2(-dir.z), dir.y.clamp(-1., 1.).asin());
+        let u = 0.5 + lon / std::f32::consts::TAU;
+        let v = 0.5 - lat / std::f32::consts::PI;
+        let (x, y) = (u * WIDTH as f32 - 0.5, v * HEIGHT as f32 - 0.5);
+        let (base_x, base_y) = (x.floor() as i32, y.floor() as i32);
+        let (tx, ty) = (x - x.floor(), y - y.floor());
+        let mix = |a: [f32; 3], b: [f32; 3], t: f32| [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t);
+        let top = mix(
+            panorama_texel(base_x, base_y),
+            panorama_texel(base_x + 1, base_y),
+            tx,
+        );
+        let bottom = mix(
+            panorama_texel(base_x, base_y + 1),
+            panorama_texel(base_x + 1, base_y + 1),
+            tx,
+        );
+        mix(top, bottom, ty)
+    }
+
+    // every texel of every face at the panorama's direction through it, the seam behind -z and
+    // the poles on +y and -y included
+    #[test]
+    fn faces_match_the_cpu() {
+        let Some((adapter, device, queue)) = headless() else {
+            return;
+        };
+        // wgpu-hal 0.14 doesn't copy out of cube maps on gl, six square layers are one there
+        if adapter.get_info().backend == wgpu::Backend::Gl {
+            warn!("no cube map copies on gl, skipping the test");
+            return;
+        }
+        let pixels: Vec<f32> = (0..HEIGHT as i32)
+            .flat_map(|y| (0..WIDTH as i32).flat_map(move |x| panorama_texel(x, y)))
+            .collect();
+        let panorama = image::DynamicImage::ImageRgb32F(
+            image::Rgb32FImage::from_raw(WIDTH, HEIGHT, pixels).unwrap(),
+        );
+        let cube = to_cube(&device, &queue, &panorama, SIZE, "cube");
+
+        for face in 0..6 {
+            let texels: Vec<[u16; 4]> =
+                read_texture(&device, &queue, &cube.texture, (SIZE, SIZE), face, 0);
+            for (i, texel) in texels.iter().enumerate() {
+                let (x, y) = (i as u32 % SIZE, i as u32 / SIZE);
+                let u = (x as f32 + 0.5) / SIZE as f32 * 2. - 1.;
+                let v = (y as f32 + 0.5) / SIZE as f32 * 2. - 1.;
+                let expected = cpu_lookup(direction(face, u, v));
+                for channel in 0..3 {
+                    let got = f16_to_f32(texel[channel]);
+                    assert!(
+                        (got - expected[channel]).abs() < 3e-3,
+                        "face {} texel ({}, {}) channel {} is {}, expected {}",
+                        face,
+                        x,
+                        y,
+                        channel,
+                        got,
+                        expected[channel]
+                    );
+                }
+            }
+        }
+    }
+}
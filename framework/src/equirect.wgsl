@@ -0,0 +1,80 @@
+// one face of a cube map per draw, looked up from an equirectangular panorama.
+// the face comes in a uniform of its own, gl has no first instance to pass it in
+
+struct Face {
+    // wgpu cube face order is +x, -x, +y, -y, +z, -z
+    face: u32,
+};
+
+struct FaceOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    // -1..1 across the face, y down like the image rows
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_face(@builtin(vertex_index) index: u32) -> FaceOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var output: FaceOutput;
+    output.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    output.uv = uv * 2.0 - 1.0;
+    return output;
+}
+
+// where a texel of a face points, in wgpu's layer order +x, -x, +y, -y, +z, -z
+fn direction(face: u32, uv: vec2<f32>) -> vec3<f32> {
+    let u = uv.x;
+    let v = uv.y;
+    var dir: vec3<f32>;
+    if (face == 0u) {
+        dir = vec3<f32>(1.0, -v, -u);
+    } else if (face == 1u) {
+        dir = vec3<f32>(-1.0, -v, u);
+    } else if (face == 2u) {
+        dir = vec3<f32>(u, 1.0, v);
+    } else if (face == 3u) {
+        dir = vec3<f32>(u, -1.0, -v);
+    } else if (face == 4u) {
+        dir = vec3<f32>(u, -v, 1.0);
+    } else {
+        dir = vec3<f32>(-u, -v, -1.0);
+    }
+    return normalize(dir);
+}
+
+// rgba32float, which isn't filterable without a feature, so the bilinear filter is done by hand
+@group(0) @binding(0)
+var t_panorama: texture_2d<f32>;
+@group(1) @binding(0)
+var<uniform> face: Face;
+
+let PI: f32 = 3.14159265359;
+
+// u wraps around the seam, v stops at the poles
+fn texel(coord: vec2<i32>, size: vec2<i32>) -> vec4<f32> {
+    let x = (coord.x % size.x + size.x) % size.x;
+    let y = clamp(coord.y, 0, size.y - 1);
+    return textureLoad(t_panorama, vec2<i32>(x, y), 0);
+}
+
+@fragment
+fn fs_face(input: FaceOutput) -> @location(0) vec4<f32> {
+    let dir = direction(face.face, input.uv);
+    // longitude across, -z in the middle of the image, latitude down from the top
+    let lon_lat = vec2<f32>(atan2(dir.x, -dir.z), asin(clamp(dir.y, -1.0, 1.0)));
+    let uv = vec2<f32>(0.5 + lon_lat.x / (2.0 * PI), 0.5 - lon_lat.y / PI);
+
+    let size = textureDimensions(t_panorama);
+    let position = uv * vec2<f32>(size) - 0.5;
+    let base = vec2<i32>(floor(position));
+    let t = fract(position);
+
+    let top = mix(texel(base, size), texel(base + vec2<i32>(1, 0), size), t.x);
+    let bottom = mix(
+        texel(base + vec2<i32>(0, 1), size),
+        texel(base + vec2<i32>(1, 1), size),
+        t.x
+    );
+    return vec4<f32>(mix(top, bottom, t.y).rgb, 1.0);
+}
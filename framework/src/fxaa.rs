@@ -0,0 +1,101 @@
+//! anti-aliasing as a fullscreen pass over the finished image. cheaper than msaa and works
+//! whatever the scene drew, but it only sees pixels: it finds edges by their contrast and
+//! blurs along them, thin features it can't tell from edges get softened too.
+//!
+//! ```ignore
+//! let scene = Texture::create_render_target(device, width, height, config.format, "scene");
+//! let fxaa = Fxaa::new(device, config.format);
+//! let bind_group = fxaa.create_bind_group(device, &scene);
+//! // every frame, after the scene pass into `scene.view`
+//! fxaa.draw(&mut rpass, &bind_group);
+//! ```
+
+use wgpu::include_wgsl;
+
+use crate::texture::Texture;
+
+pub struct Fxaa {
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Fxaa {
+    /// `target_format` is the format of the texture the pass draws into
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Fxaa {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("fxaa_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("fxaa.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("fxaa"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Fxaa { layout, pipeline }
+    }
+
+    /// reads `source` through its own sampler, which has to filter linearly: the blending
+    /// across edges is the bilinear filter's. make a new one when the target is recreated
+    pub fn create_bind_group(&self, device: &wgpu::Device, source: &Texture) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("fxaa_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&source.sampler),
+                },
+            ],
+        })
+    }
+
+    /// covers the current viewport of `rpass` with the anti-aliased source of `bind_group`
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, bind_group: &'a wgpu::BindGroup) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
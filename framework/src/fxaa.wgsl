@@ -0,0 +1,208 @@
+// fast approximate anti-aliasing, after Timothy Lottes' FXAA 3.11 quality preset, one
+// fullscreen triangle. looks for strong contrast around each pixel, follows the edge it
+// belongs to in both directions to see where along it the pixel sits, then samples a little
+// across the edge so the bilinear filter blends the two sides
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragInput.tex_coord = uv;
+    return fragInput;
+}
+
+@group(0) @binding(0)
+var t_color: texture_2d<f32>;
+@group(0) @binding(1)
+var s_color: sampler;
+
+// contrast below either of these isn't an edge worth smoothing, the second scales with the
+// brightest neighbour so dark areas need less
+let EDGE_THRESHOLD_MIN: f32 = 0.0312;
+let EDGE_THRESHOLD_MAX: f32 = 0.125;
+// how much single pixel details get blurred, 0 keeps them sharp
+let SUBPIXEL_QUALITY: f32 = 0.75;
+let ITERATIONS: i32 = 12;
+
+// how far each step of the edge search goes, in pixels. later steps go further, long
+// shallow edges are found sooner at the price of a rougher end
+fn step_size(i: i32) -> f32 {
+    if (i < 5) {
+        return 1.0;
+    }
+    if (i == 5) {
+        return 1.5;
+    }
+    if (i < 10) {
+        return 2.0;
+    }
+    if (i == 10) {
+        return 4.0;
+    }
+    return 8.0;
+}
+
+// perceived brightness. the target may be srgb and come back linear from the sampler, the
+// square root brings it close to the gamma encoded values fxaa was tuned for
+fn luma(color: vec3<f32>) -> f32 {
+    return sqrt(dot(color, vec3<f32>(0.299, 0.587, 0.114)));
+}
+
+fn luma_at(uv: vec2<f32>) -> f32 {
+    return luma(textureSampleLevel(t_color, s_color, uv, 0.0).rgb);
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_color));
+    let uv = input.tex_coord;
+
+    let center = textureSampleLevel(t_color, s_color, uv, 0.0);
+    let luma_center = luma(center.rgb);
+    // uv grows downward, up is -y
+    let luma_up = luma_at(uv + vec2<f32>(0.0, -1.0) * texel);
+    let luma_down = luma_at(uv + vec2<f32>(0.0, 1.0) * texel);
+    let luma_left = luma_at(uv + vec2<f32>(-1.0, 0.0) * texel);
+    let luma_right = luma_at(uv + vec2<f32>(1.0, 0.0) * texel);
+
+    let luma_min = min(luma_center, min(min(luma_up, luma_down), min(luma_left, luma_right)));
+    let luma_max = max(luma_center, max(max(luma_up, luma_down), max(luma_left, luma_right)));
+    let contrast = luma_max - luma_min;
+    if (contrast < max(EDGE_THRESHOLD_MIN, luma_max * EDGE_THRESHOLD_MAX)) {
+        return center;
+    }
+
+    let luma_up_left = luma_at(uv + vec2<f32>(-1.0, -1.0) * texel);
+    let luma_up_right = luma_at(uv + vec2<f32>(1.0, -1.0) * texel);
+    let luma_down_left = luma_at(uv + vec2<f32>(-1.0, 1.0) * texel);
+    let luma_down_right = luma_at(uv + vec2<f32>(1.0, 1.0) * texel);
+
+    let luma_up_down = luma_up + luma_down;
+    let luma_left_right = luma_left + luma_right;
+    let luma_left_corners = luma_up_left + luma_down_left;
+    let luma_right_corners = luma_up_right + luma_down_right;
+    let luma_up_corners = luma_up_left + luma_up_right;
+    let luma_down_corners = luma_down_left + luma_down_right;
+
+    // which way the luma changes more, across rows or across columns. a horizontal edge
+    // changes from top to bottom
+    let edge_horizontal = abs(-2.0 * luma_left + luma_left_corners)
+        + abs(-2.0 * luma_center + luma_up_down) * 2.0
+        + abs(-2.0 * luma_right + luma_right_corners);
+    let edge_vertical = abs(-2.0 * luma_up + luma_up_corners)
+        + abs(-2.0 * luma_center + luma_left_right) * 2.0
+        + abs(-2.0 * luma_down + luma_down_corners);
+    let is_horizontal = edge_horizontal >= edge_vertical;
+
+    // the neighbours across the edge, before and after the pixel
+    var luma_before = luma_left;
+    var luma_after = luma_right;
+    var step_length = texel.x;
+    if (is_horizontal) {
+        luma_before = luma_up;
+        luma_after = luma_down;
+        step_length = texel.y;
+    }
+    let gradient_before = luma_before - luma_center;
+    let gradient_after = luma_after - luma_center;
+
+    // the edge lies toward the side that differs the most
+    let before_is_steepest = abs(gradient_before) >= abs(gradient_after);
+    let gradient_scaled = 0.25 * max(abs(gradient_before), abs(gradient_after));
+    var luma_local_average = 0.5 * (luma_after + luma_center);
+    if (before_is_steepest) {
+        step_length = -step_length;
+        luma_local_average = 0.5 * (luma_before + luma_center);
+    }
+
+    // half a pixel over, right on the edge, and one step along it each way
+    var edge_uv = uv;
+    var offset = vec2<f32>(0.0, texel.y);
+    if (is_horizontal) {
+        edge_uv.y = edge_uv.y + step_length * 0.5;
+        offset = vec2<f32>(texel.x, 0.0);
+    } else {
+        edge_uv.x = edge_uv.x + step_length * 0.5;
+    }
+    var uv_neg = edge_uv - offset;
+    var uv_pos = edge_uv + offset;
+
+    // walk both ways until the luma along the edge no longer matches the edge's average,
+    // that's where it ends
+    var luma_end_neg = luma_at(uv_neg) - luma_local_average;
+    var luma_end_pos = luma_at(uv_pos) - luma_local_average;
+    var reached_neg = abs(luma_end_neg) >= gradient_scaled;
+    var reached_pos = abs(luma_end_pos) >= gradient_scaled;
+    if (!reached_neg) {
+        uv_neg = uv_neg - offset;
+    }
+    if (!reached_pos) {
+        uv_pos = uv_pos + offset;
+    }
+    for (var i = 2; i < ITERATIONS; i = i + 1) {
+        if (reached_neg && reached_pos) {
+            break;
+        }
+        if (!reached_neg) {
+            luma_end_neg = luma_at(uv_neg) - luma_local_average;
+            reached_neg = abs(luma_end_neg) >= gradient_scaled;
+        }
+        if (!reached_pos) {
+            luma_end_pos = luma_at(uv_pos) - luma_local_average;
+            reached_pos = abs(luma_end_pos) >= gradient_scaled;
+        }
+        if (!reached_neg) {
+            uv_neg = uv_neg - offset * step_size(i);
+        }
+        if (!reached_pos) {
+            uv_pos = uv_pos + offset * step_size(i);
+        }
+    }
+
+    var distance_neg = uv.y - uv_neg.y;
+    var distance_pos = uv_pos.y - uv.y;
+    if (is_horizontal) {
+        distance_neg = uv.x - uv_neg.x;
+        distance_pos = uv_pos.x - uv.x;
+    }
+
+    // pixels near an end of the edge are moved further across it, the ones in the middle
+    // hardly at all, which turns a staircase into a ramp
+    let near_neg = distance_neg < distance_pos;
+    let distance_final = min(distance_neg, distance_pos);
+    let edge_length = distance_neg + distance_pos;
+    let pixel_offset = 0.5 - distance_final / edge_length;
+
+    // only if the nearer end goes the way the pixel does, otherwise it's on the far side
+    let center_is_darker = luma_center < luma_local_average;
+    var luma_end = luma_end_pos;
+    if (near_neg) {
+        luma_end = luma_end_neg;
+    }
+    var final_offset = 0.0;
+    if ((luma_end < 0.0) != center_is_darker) {
+        final_offset = pixel_offset;
+    }
+
+    // a pixel standing out from all of its neighbours is blended too, edge or not
+    let luma_average = (2.0 * (luma_up_down + luma_left_right) + luma_left_corners + luma_right_corners) / 12.0;
+    let subpixel = clamp(abs(luma_average - luma_center) / contrast, 0.0, 1.0);
+    let subpixel_smooth = (-2.0 * subpixel + 3.0) * subpixel * subpixel;
+    final_offset = max(final_offset, subpixel_smooth * subpixel_smooth * SUBPIXEL_QUALITY);
+
+    var final_uv = uv;
+    if (is_horizontal) {
+        final_uv.y = final_uv.y + final_offset * step_length;
+    } else {
+        final_uv.x = final_uv.x + final_offset * step_length;
+    }
+    return textureSampleLevel(t_color, s_color, final_uv, 0.0);
+}
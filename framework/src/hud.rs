@@ -0,0 +1,136 @@
+//! a few lines of numbers in the top left corner of the window, like a frame time or the counts
+//! of a query, for a chapter to watch while it runs. the driver draws them in the console's font
+//! on the [`UI`](crate::compositor::UI) layer, so screenshots and recordings leave them out.
+//!
+//! ```ignore
+//! // in the app
+//! fn hud(&mut self) -> Option<&mut framework::hud::Hud> {
+//!     Some(&mut self.hud)
+//! }
+//! // whenever the numbers change
+//! self.hud.set(&format!("frame time {:.2}ms", frame_time));
+//! ```
+
+use crate::console::{self, Grid, Overlay};
+use crate::font;
+
+pub struct Hud {
+    text: String,
+    /// made the first time there's text to draw
+    overlay: Option<Overlay>,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hud {
+    pub fn new() -> Hud {
+        Hud {
+            text: String::new(),
+            overlay: None,
+        }
+    }
+
+    /// replaces what's shown, a row per line of `text`. nothing is drawn while it's empty
+    pub fn set(&mut self, text: &str) {
+        self.text.clear();
+        self.text.push_str(text);
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// the text over the finished frame in `view`, what the driver does with
+    /// [`App::hud`](crate::App::hud)
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        format: wgpu::TextureFormat,
+    ) {
+        let Some((grid, text)) = self.layout() else {
+            return;
+        };
+        self.overlay
+            .get_or_insert_with(|| Overlay::new(device, format))
+            .draw(device, queue, view, grid, &text);
+    }
+
+    // as wide as the longest line and as many lines as fit in the overlay's text, characters
+    // the font has no glyph for become `?`
+    fn layout(&self) -> Option<(Grid, Vec<u8>)> {
+        let lines: Vec<Vec<u8>> = self
+            .text
+            .lines()
+            .map(|line| {
+                line.chars()
+                    .map(|c| font::glyph(c).map_or(b'?', |_| c as u8))
+                    .collect()
+            })
+            .collect();
+        let columns = lines
+            .iter()
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0)
+            .min(console::MAX_TEXT);
+        if columns == 0 {
+            return None;
+        }
+        let rows = lines.len().min(console::MAX_TEXT / columns);
+
+        let mut text = vec![0; rows * columns];
+        for (row, line) in lines.iter().take(rows).enumerate() {
+            let line = &line[..line.len().min(columns)];
+            text[row * columns..row * columns + line.len()].copy_from_slice(line);
+        }
+        let grid = Grid {
+            rows,
+            columns,
+            band: false,
+        };
+        Some((grid, text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_row_per_line() {
+        let mut hud = Hud::new();
+        hud.set("frame time 1.25ms\nscale 0.50\n");
+        let (grid, text) = hud.layout().unwrap();
+        assert_eq!((grid.rows, grid.columns), (2, "frame time 1.25ms".len()));
+        assert_eq!(&text[..grid.columns], b"frame time 1.25ms");
+        assert!(text[grid.columns..].starts_with(b"scale 0.50\0"));
+    }
+
+    #[test]
+    fn empty_draws_nothing() {
+        let mut hud = Hud::new();
+        assert!(hud.layout().is_none());
+        hud.set("\n");
+        assert!(hud.layout().is_none());
+    }
+
+    #[test]
+    fn stays_within_the_overlay() {
+        let mut hud = Hud::new();
+        hud.set(&format!("{}\n", "x".repeat(100)).repeat(100));
+        let (grid, text) = hud.layout().unwrap();
+        assert_eq!(grid.columns, 100);
+        assert!(grid.rows * grid.columns <= console::MAX_TEXT);
+        assert_eq!(text.len(), grid.rows * grid.columns);
+
+        hud.set("µs");
+        let (_, text) = hud.layout().unwrap();
+        assert_eq!(text, b"?s");
+    }
+}
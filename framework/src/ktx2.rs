@@ -0,0 +1,296 @@
+//! ktx2 files, textures stored the way the gpu samples them. a png is decoded to rgba8 and
+//! takes 4 bytes a texel in video memory, a bc7 or etc2 texture takes 1 and comes with its
+//! mip levels already made. [`Texture::from_ktx2`](crate::texture::Texture::from_ktx2) uploads
+//! every level as it is when the device has the format's feature, and decodes them to rgba8
+//! on the cpu with [`crate::block_decode`] when it doesn't.
+//!
+//! the payload has to be one of [`Ktx2::format`]'s formats already. basis universal files,
+//! basislz or uastc, are a step before that: they get transcoded to bc7 or etc2 for the gpu
+//! at hand, and the transcoder isn't part of the framework. `ktx transcode --target bc7` (or
+//! `etc2`) from ktx-software does it once, ahead of time. zstd supercompression isn't read
+//! either, `ktx transcode` without `--zstd` writes the levels plain.
+
+use std::fmt;
+
+/// what the compressed formats need, the driver asks for them when the adapter has them
+pub const FEATURES: wgpu::Features =
+    wgpu::Features::TEXTURE_COMPRESSION_BC.union(wgpu::Features::TEXTURE_COMPRESSION_ETC2);
+
+const IDENTIFIER: [u8; 12] = [
+    0xab, b'K', b'T', b'X', b' ', b'2', b'0', 0xbb, b'\r', b'\n', 0x1a, b'\n',
+];
+// the identifier, nine u32 fields, four u32 and two u64 of the index
+const HEADER_SIZE: usize = 80;
+// byte offset, byte length and uncompressed byte length, u64 each
+const LEVEL_SIZE: usize = 24;
+
+const SUPERCOMPRESSION_BASIS_LZ: u32 = 1;
+const SUPERCOMPRESSION_ZSTD: u32 = 2;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Error {
+    /// not a ktx2 file, or one that ends early
+    Invalid(String),
+    /// basis universal data, which has to be transcoded first, see the module docs
+    Basis,
+    /// a valid file the loader doesn't handle, like a cube map or a zstd compressed one
+    Unsupported(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Invalid(reason) => write!(f, "invalid ktx2 file, {}", reason),
+            Error::Basis => write!(
+                f,
+                "basis universal ktx2, transcode it with `ktx transcode --target bc7` first"
+            ),
+            Error::Unsupported(reason) => write!(f, "unsupported ktx2 file, {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// a parsed 2d ktx2 file, its levels still in `bytes`
+pub struct Ktx2<'a> {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    /// largest first, level 0 is `width` x `height`
+    pub levels: Vec<&'a [u8]>,
+}
+
+impl<'a> Ktx2<'a> {
+    pub fn parse(bytes: &'a [u8]) -> Result<Ktx2<'a>, Error> {
+        if bytes.len() < HEADER_SIZE || bytes[..12] != IDENTIFIER {
+            return Err(Error::Invalid("no ktx2 identifier".to_string()));
+        }
+        let u32_at = |offset: usize| {
+            u32::from_le_bytes(
+                bytes[offset..offset + 4]
+                    .try_into()
+                    .expect("Fail to read u32"),
+            )
+        };
+        let u64_at = |offset: usize| {
+            u64::from_le_bytes(
+                bytes[offset..offset + 8]
+                    .try_into()
+                    .expect("Fail to read u64"),
+            )
+        };
+
+        let vk_format = u32_at(12);
+        let width = u32_at(20);
+        let height = u32_at(24);
+        let depth = u32_at(28);
+        let layers = u32_at(32);
+        let faces = u32_at(36);
+        // 0 asks the loader to make the mips, there's still one level in the file
+        let level_count = u32_at(40).max(1) as usize;
+        let supercompression = u32_at(44);
+
+        if supercompression == SUPERCOMPRESSION_BASIS_LZ {
+            return Err(Error::Basis);
+        }
+        if supercompression == SUPERCOMPRESSION_ZSTD {
+            return Err(Error::Unsupported("zstd supercompression".to_string()));
+        }
+        if supercompression != 0 {
+            return Err(Error::Unsupported(format!(
+                "supercompression scheme {}",
+                supercompression
+            )));
+        }
+        if height == 0 || depth > 0 || layers > 0 || faces != 1 {
+            return Err(Error::Unsupported(
+                "only 2d textures, not 1d, 3d, arrays or cube maps".to_string(),
+            ));
+        }
+        let format = match vk_format {
+            // uastc has no format of its own, its data format descriptor says what it is
+            0 => return Err(Error::Basis),
+            37 => wgpu::TextureFormat::Rgba8Unorm,
+            43 => wgpu::TextureFormat::Rgba8UnormSrgb,
+            133 => wgpu::TextureFormat::Bc1RgbaUnorm,
+            134 => wgpu::TextureFormat::Bc1RgbaUnormSrgb,
+            137 => wgpu::TextureFormat::Bc3RgbaUnorm,
+            138 => wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+            145 => wgpu::TextureFormat::Bc7RgbaUnorm,
+            146 => wgpu::TextureFormat::Bc7RgbaUnormSrgb,
+            147 => wgpu::TextureFormat::Etc2Rgb8Unorm,
+            148 => wgpu::TextureFormat::Etc2Rgb8UnormSrgb,
+            151 => wgpu::TextureFormat::Etc2Rgba8Unorm,
+            152 => wgpu::TextureFormat::Etc2Rgba8UnormSrgb,
+            _ => return Err(Error::Unsupported(format!("vkFormat {}", vk_format))),
+        };
+        if width == 0 {
+            return Err(Error::Invalid("the width is 0".to_string()));
+        }
+        // the chain ends at 1x1, more levels than that would shift the size away
+        let max_levels = 32 - width.max(height).leading_zeros();
+        if level_count > max_levels as usize {
+            return Err(Error::Invalid(format!(
+                "{} levels, a {}x{} texture has at most {}",
+                level_count, width, height, max_levels
+            )));
+        }
+
+        let index_end = HEADER_SIZE + level_count * LEVEL_SIZE;
+        if bytes.len() < index_end {
+            return Err(Error::Invalid("the level index is cut off".to_string()));
+        }
+        let levels = (0..level_count)
+            .map(|level| {
+                let entry = HEADER_SIZE + level * LEVEL_SIZE;
+                let (offset, length) = (u64_at(entry) as usize, u64_at(entry + 8) as usize);
+                let expected = level_byte_size(format, width, height, level as u32)
+                    .ok_or_else(|| Error::Invalid(format!("level {} is too big", level)))?;
+                if length != expected {
+                    return Err(Error::Invalid(format!(
+                        "level {} is {} bytes, expected {}",
+                        level, length, expected
+                    )));
+                }
+                offset
+                    .checked_add(length)
+                    .and_then(|end| bytes.get(offset..end))
+                    .ok_or_else(|| Error::Invalid(format!("level {} is cut off", level)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Ktx2 {
+            width,
+            height,
+            format,
+            levels,
+        })
+    }
+
+    /// `width` and `height` of `level`, halved and rounded down, at least 1
+    pub fn level_size(&self, level: u32) -> (u32, u32) {
+        ((self.width >> level).max(1), (self.height >> level).max(1))
+    }
+}
+
+// whole blocks, the ones on the edges hang over. `None` when that doesn't fit in a usize
+fn level_byte_size(
+    format: wgpu::TextureFormat,
+    width: u32,
+    height: u32,
+    level: u32,
+) -> Option<usize> {
+    let info = format.describe();
+    let (block_width, block_height) = (
+        info.block_dimensions.0 as u32,
+        info.block_dimensions.1 as u32,
+    );
+    let (width, height) = ((width >> level).max(1), (height >> level).max(1));
+    let blocks = (width.div_ceil(block_width) as usize)
+        .checked_mul(height.div_ceil(block_height) as usize)?;
+    blocks.checked_mul(info.block_size as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a ktx2 file of `vk_format` with `levels` after the header and level index
+    fn file(
+        vk_format: u32,
+        width: u32,
+        height: u32,
+        levels: &[Vec<u8>],
+        supercompression: u32,
+    ) -> Vec<u8> {
+        let mut bytes = IDENTIFIER.to_vec();
+        for field in [
+            vk_format,
+            1,
+            width,
+            height,
+            0,
+            0,
+            1,
+            levels.len() as u32,
+            supercompression,
+        ] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.resize(HEADER_SIZE, 0);
+        let mut offset = HEADER_SIZE + levels.len() * LEVEL_SIZE;
+        for level in levels {
+            for field in [offset, level.len(), level.len()] {
+                bytes.extend_from_slice(&(field as u64).to_le_bytes());
+            }
+            offset += level.len();
+        }
+        for level in levels {
+            bytes.extend_from_slice(level);
+        }
+        bytes
+    }
+
+    #[test]
+    fn reads_every_level() {
+        // 8x4 bc7: two blocks, then one for 4x2 and one for 2x1
+        let levels = vec![vec![1; 32], vec![2; 16], vec![3; 16]];
+        let bytes = file(146, 8, 4, &levels, 0);
+        let ktx2 = Ktx2::parse(&bytes).unwrap();
+        assert_eq!((ktx2.width, ktx2.height), (8, 4));
+        assert_eq!(ktx2.format, wgpu::TextureFormat::Bc7RgbaUnormSrgb);
+        assert_eq!(
+            ktx2.levels,
+            vec![&levels[0][..], &levels[1][..], &levels[2][..]]
+        );
+        assert_eq!(ktx2.level_size(2), (2, 1));
+    }
+
+    #[test]
+    fn basis_and_broken_files_are_errors() {
+        assert_eq!(
+            Ktx2::parse(&file(0, 4, 4, &[vec![0; 16]], 1)).err(),
+            Some(Error::Basis)
+        );
+        assert_eq!(
+            Ktx2::parse(&file(0, 4, 4, &[vec![0; 16]], 0)).err(),
+            Some(Error::Basis)
+        );
+        assert!(matches!(
+            Ktx2::parse(b"\x89PNG\r\n\x1a\n"),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            Ktx2::parse(&file(145, 4, 4, &[vec![0; 8]], 0)),
+            Err(Error::Invalid(_))
+        ));
+        assert!(matches!(
+            Ktx2::parse(&file(145, 4, 4, &[vec![0; 16]], 2)),
+            Err(Error::Unsupported(_))
+        ));
+        let mut cut = file(145, 4, 4, &[vec![0; 16]], 0);
+        cut.truncate(cut.len() - 1);
+        assert!(matches!(Ktx2::parse(&cut), Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn more_levels_than_the_chain_are_errors() {
+        // 4x4 goes 4, 2, 1, a fourth level would be 0x0
+        let levels = vec![vec![0; 16], vec![0; 16], vec![0; 16], vec![0; 16]];
+        assert!(matches!(
+            Ktx2::parse(&file(145, 4, 4, &levels, 0)),
+            Err(Error::Invalid(_))
+        ));
+        // and one past any shift, every level written out
+        let bytes = file(145, 4, 4, &vec![vec![0; 16]; 40], 0);
+        assert!(matches!(Ktx2::parse(&bytes), Err(Error::Invalid(_))));
+    }
+
+    #[test]
+    fn an_overflowing_level_offset_is_an_error() {
+        let mut bytes = file(145, 4, 4, &[vec![0; 16]], 0);
+        bytes[HEADER_SIZE..HEADER_SIZE + 8].copy_from_slice(&(u64::MAX - 4).to_le_bytes());
+        assert!(matches!(Ktx2::parse(&bytes), Err(Error::Invalid(_))));
+    }
+}
@@ -0,0 +1,840 @@
+//! window, surface and event loop boilerplate shared by the tutorials.
+//!
+//! a tutorial implements [`App`] and hands it to [`run`], the driver owns
+//! instance/adapter/device/surface and calls back into the app.
+
+pub mod assets;
+pub mod autotune;
+pub mod blit;
+pub mod block_decode;
+pub mod camera;
+pub mod color_grading;
+pub mod compat;
+pub mod compositor;
+pub mod console;
+pub mod dynamic_texture;
+pub mod equirect;
+pub mod font;
+pub mod fxaa;
+pub mod hud;
+pub mod ktx2;
+pub mod light_editor;
+pub mod mipmap;
+pub mod overdraw;
+pub mod params;
+pub mod pass_graph;
+pub mod permutation;
+pub mod pipeline_stats;
+pub mod pixel_art;
+pub mod post_process;
+pub mod primitives;
+pub mod reel;
+pub mod render_scale;
+#[cfg(feature = "rhai")]
+pub mod rhai_script;
+pub mod scene;
+pub mod screenshot;
+pub mod script;
+pub mod targets;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod texture;
+pub mod timeline;
+pub mod tonemap;
+pub mod vertex_layout;
+pub mod warmup;
+pub mod watchdog;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use compositor::Compositor;
+use console::{Commands, Console, Input};
+use pass_graph::PassGraph;
+use screenshot::{Capture, DumpTarget};
+use script::Script;
+use texture::DepthTexture;
+use timeline::Timeline;
+use tracing::{info, warn, Level};
+use warmup::ProgressBar;
+use watchdog::Watchdog;
+use wgpu::{Backends, Instance};
+use winit::{
+    event::{DeviceEvent, ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    window::{Window, WindowBuilder},
+};
+
+pub trait App: 'static + Sized {
+    /// features the app can't run without, the device request fails if the adapter lacks them
+    fn required_features() -> wgpu::Features {
+        wgpu::Features::empty()
+    }
+
+    /// features the app uses when the adapter has them, check `device.features()` in `init`
+    fn optional_features() -> wgpu::Features {
+        wgpu::Features::empty()
+    }
+
+    /// keep a [`DepthTexture`] the size of the window, handed to `render` in [`Frame::depth`]
+    fn depth_buffer() -> bool {
+        false
+    }
+
+    /// called once after the surface is configured
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self;
+
+    /// called after the surface has been reconfigured to the new size, the depth buffer
+    /// already matches it
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    );
+
+    /// window events the driver doesn't handle itself (resize and exit)
+    fn update(&mut self, window: &Window, event: WindowEvent);
+
+    /// raw device input, e.g. mouse motion for mouse look
+    fn device_event(&mut self, _event: DeviceEvent) {}
+
+    /// build some of the pipeline variants the app switches between, see [`warmup`]. the
+    /// driver calls it once a frame and draws a progress bar instead of calling `render` until
+    /// it returns `None`
+    fn warmup(&mut self, _device: &wgpu::Device) -> Option<warmup::Progress> {
+        None
+    }
+
+    /// draw one frame into `frame.view`, the driver presents it afterwards
+    fn render(&mut self, frame: &Frame, device: &wgpu::Device, queue: &wgpu::Queue);
+
+    /// layers of the app's own over the scene, bottom first, drawn by
+    /// [`App::draw_layer`], see [`compositor`]
+    fn layers() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// draw `layer`, one of [`App::layers`], into `view`. called after `render` every frame
+    /// the layer is on, `view` is cleared to transparent
+    fn draw_layer(
+        &mut self,
+        _layer: &str,
+        _view: &wgpu::TextureView,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+    }
+
+    /// the offscreen textures `render` draws through, in pass order, for F10 frame dumps.
+    /// the driver adds its own depth buffer and the finished frame around them
+    fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        Vec::new()
+    }
+
+    /// the passes `render` runs and what flows between them, saved as graphviz dot by F9
+    fn pass_graph(&self) -> Option<PassGraph> {
+        None
+    }
+
+    /// the app's own console commands, added once before the first frame, see [`console`]
+    fn commands(_commands: &mut Commands<Self>) {}
+
+    /// the objects a rhai script can move, see [`scene`]. `None` for chapters without any
+    fn scene(&mut self) -> Option<&mut dyn scene::Scene> {
+        None
+    }
+
+    /// the chapter's [`params::Params`], the console's `param` reads and sets them
+    fn params(&mut self) -> Option<&mut params::Params> {
+        None
+    }
+
+    /// the chapter's [`light_editor::LightEditor`], the console's `scene load` replaces its
+    /// lights
+    fn light_editor(&mut self) -> Option<&mut light_editor::LightEditor> {
+        None
+    }
+
+    /// the chapter's [`hud::Hud`], drawn on the [`compositor::UI`] layer under the console
+    fn hud(&mut self) -> Option<&mut hud::Hud> {
+        None
+    }
+}
+
+/// what [`App::render`] draws into, and when. a pass drawing somewhere else keeps the times,
+/// `Frame { view, depth: None, ..*frame }`
+#[derive(Clone, Copy)]
+pub struct Frame<'a> {
+    pub view: &'a wgpu::TextureView,
+    /// `Some` when [`App::depth_buffer`] asks for one
+    pub depth: Option<&'a DepthTexture>,
+    /// seconds on the driver's [`timeline::Timeline`], everything animated follows it
+    pub time: f32,
+    /// how far `time` moved since the last frame, 0 while paused and negative going back
+    pub delta: f32,
+    /// wall clock seconds since the last frame, for what follows input like a fly camera
+    pub real_delta: f32,
+}
+
+impl<'a> Frame<'a> {
+    /// a pass that clears the view to `clear` and the depth buffer, if there is one, to 1
+    pub fn begin_render_pass<'e>(
+        &self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        clear: wgpu::Color,
+    ) -> wgpu::RenderPass<'e>
+    where
+        'a: 'e,
+    {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: self.depth.map(DepthTexture::attachment),
+        })
+    }
+}
+
+/// what the F keys and the driver's console commands ask of the next frame
+#[derive(Default)]
+struct Requests {
+    screenshot: bool,
+    /// start or stop recording
+    record: bool,
+    dump: bool,
+    graph: bool,
+    script: Option<ScriptRequest>,
+    /// list the compositor's layers
+    layers: bool,
+    layer: Option<(String, compositor::Change)>,
+    /// read a param, or set it to the value
+    param: Option<(String, Option<f32>)>,
+    /// the path the lights were loaded from and the lights
+    scene: Option<(String, Vec<light_editor::Light>)>,
+}
+
+enum ScriptRequest {
+    Run(Script),
+    #[cfg(feature = "rhai")]
+    RunRhai(Box<rhai_script::RhaiScript>),
+    Stop,
+}
+
+// a `.rhai` file is a rhai script, anything else a script of commands
+fn load_script(path: &str) -> Result<ScriptRequest, String> {
+    if path.ends_with(".rhai") {
+        load_rhai(path)
+    } else {
+        Script::load(path).map(ScriptRequest::Run)
+    }
+}
+
+#[cfg(feature = "rhai")]
+fn load_rhai(path: &str) -> Result<ScriptRequest, String> {
+    rhai_script::RhaiScript::load(path).map(|script| ScriptRequest::RunRhai(Box::new(script)))
+}
+
+#[cfg(not(feature = "rhai"))]
+fn load_rhai(path: &str) -> Result<ScriptRequest, String> {
+    Err(format!("{} needs the framework's rhai feature", path))
+}
+
+// the rhai script's frame, stopped when it fails
+#[cfg(feature = "rhai")]
+fn run_rhai<A: App>(
+    rhai: &mut Option<rhai_script::RhaiScript>,
+    app: &mut A,
+    console: &mut Console,
+    time: f32,
+) {
+    let Some(running) = rhai else {
+        return;
+    };
+    if let Some(reloaded) = running.reload() {
+        let path = running.path().display().to_string();
+        console.reply(reloaded.map(|()| format!("{} changed, starting over", path)));
+    }
+    let result = match app.scene() {
+        Some(scene) => running.frame(scene, time),
+        None => Err("this chapter has no scene for a script".to_string()),
+    };
+    if let Err(err) = result {
+        console.reply(Err(format!("{}\nthe script stopped", err)));
+        *rhai = None;
+    }
+}
+
+// the driver's own console commands, `help` and `clear` are run by `run_command`
+fn driver_commands() -> Commands<Requests> {
+    // each of them sets a flag the next frame acts on
+    fn request(
+        flag: fn(&mut Requests) -> &mut bool,
+    ) -> impl FnMut(&mut Requests, &Window, &[&str]) -> console::Reply {
+        move |requests, _, _| {
+            *flag(requests) = true;
+            Ok(String::new())
+        }
+    }
+
+    let mut commands = Commands::new();
+    commands
+        .add(
+            "screenshot",
+            "",
+            "save the next frame, like F12",
+            request(|r| &mut r.screenshot),
+        )
+        .add(
+            "record",
+            "",
+            "start or stop recording, like F11",
+            request(|r| &mut r.record),
+        )
+        .add(
+            "dump",
+            "",
+            "save every target of the next frame, like F10",
+            request(|r| &mut r.dump),
+        )
+        .add(
+            "graph",
+            "",
+            "save the pass graph, like F9",
+            request(|r| &mut r.graph),
+        )
+        .add(
+            "script",
+            "<path|stop>",
+            "run a script of commands or a .rhai script, see framework::script",
+            |requests, _, args| match args {
+                ["stop"] => {
+                    requests.script = Some(ScriptRequest::Stop);
+                    Ok(String::new())
+                }
+                [path] => {
+                    requests.script = Some(load_script(path)?);
+                    Ok(format!("running {}", path))
+                }
+                _ => Err("script takes a path or `stop`".to_string()),
+            },
+        )
+        .add(
+            "layers",
+            "",
+            "list the layers the window is composited from",
+            request(|r| &mut r.layers),
+        )
+        .add(
+            "layer",
+            "<name> <on|off|0..1|capture|nocapture>",
+            "turn a layer on or off, fade it, or leave it out of screenshots",
+            |requests, _, args| {
+                requests.layer = Some(compositor::parse_change(args)?);
+                Ok(String::new())
+            },
+        )
+        .add(
+            "param",
+            "<name> [value]",
+            "show or set one of the chapter's params, see framework::params",
+            |requests, _, args| {
+                let (name, value) = match args {
+                    [name] => (name, None),
+                    [name, value] => (
+                        name,
+                        Some(
+                            value
+                                .parse()
+                                .map_err(|_| format!("`{}` isn't a number", value))?,
+                        ),
+                    ),
+                    _ => return Err("param takes a name and maybe a value".to_string()),
+                };
+                requests.param = Some((name.to_string(), value));
+                Ok(String::new())
+            },
+        )
+        .add(
+            "scene",
+            "load <path>",
+            "replace the chapter's lights with a scene file's, see framework::light_editor",
+            |requests, _, args| {
+                let ["load", path] = args else {
+                    return Err("scene takes `load` and a path".to_string());
+                };
+                let text = std::fs::read_to_string(path)
+                    .map_err(|err| format!("Fail to read {}: {}", path, err))?;
+                let lights =
+                    light_editor::parse_scene(&text).map_err(|err| format!("{}: {}", path, err))?;
+                if lights.is_empty() {
+                    return Err(format!("{} has no lights", path));
+                }
+                requests.scene = Some((path.to_string(), lights));
+                Ok(String::new())
+            },
+        );
+    commands
+}
+
+// `param`'s reply, the value `name` has after it was set if there was a value
+fn param<A: App>(app: &mut A, name: &str, value: Option<f32>) -> console::Reply {
+    let params = app.params().ok_or("this chapter has no params")?;
+    if let Some(value) = value {
+        params.set(name, value)?;
+    }
+    params
+        .get(name)
+        .map(|value| format!("{} = {}", name, value))
+        .ok_or_else(|| format!("there's no parameter `{}`", name))
+}
+
+// a line typed into the console, tried against the driver's commands and then the app's
+fn run_command<A: App>(
+    line: &str,
+    window: &Window,
+    console: &mut Console,
+    driver: (&mut Commands<Requests>, &mut Requests),
+    app: (&mut Commands<A>, &mut A),
+) {
+    let (driver_commands, requests) = driver;
+    let (app_commands, app) = app;
+    let reply = match line.split_whitespace().next() {
+        Some("help") => {
+            let mut lines = vec![
+                format!("{:<24} {}", "help", "list the commands"),
+                format!("{:<24} {}", "clear", "empty the console"),
+            ];
+            lines.extend(driver_commands.help());
+            lines.extend(app_commands.help());
+            Ok(lines.join("\n"))
+        }
+        Some("clear") => {
+            console.clear();
+            return;
+        }
+        name => driver_commands
+            .run(requests, window, line)
+            .or_else(|| app_commands.run(app, window, line))
+            .unwrap_or_else(|| {
+                Err(format!(
+                    "unknown command `{}`, help lists them",
+                    name.unwrap_or_default()
+                ))
+            }),
+    };
+    console.reply(reply);
+}
+
+/// open a window titled `title` and drive `A` until it is closed or escape is pressed.
+/// F12 saves a screenshot, F11 starts and stops recording every frame as a png, F10 saves
+/// every target of the next frame into a directory of its own, F9 saves the pass graph.
+/// F3 to F8 pause, step, scrub and slow down the [`timeline`] the app animates on.
+/// `~` opens the [`console`], [`script::ENV_VAR`] names a [`script`] of its commands to run.
+/// the window is put together from layers by a [`compositor`], captures leave out the
+/// console
+pub fn run<A: App>(title: &str) {
+    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new()
+        .with_title(title)
+        .build(&event_loop)
+        .unwrap();
+
+    pollster::block_on(start::<A>(event_loop, window));
+}
+
+async fn start<A: App>(event_loop: EventLoop<()>, window: Window) {
+    let size = window.inner_size();
+
+    let instance = Instance::new(Backends::all());
+    let surface = unsafe { instance.create_surface(&window) };
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            compatible_surface: Some(&surface),
+        })
+        .await
+        .expect("Failed to find an appropriate adapter");
+
+    let profile = compat::Profile::select();
+    profile.log(&adapter);
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                // the watchdog times the gpu where it can, ktx2 textures stay compressed where
+                // the adapter samples their format
+                features: A::required_features()
+                    | ((A::optional_features() | watchdog::FEATURES | ktx2::FEATURES)
+                        & adapter.features()),
+                limits: profile.limits(&adapter),
+            },
+            None,
+        )
+        .await
+        .expect("Fail to create device");
+
+    let mut config = wgpu::SurfaceConfiguration {
+        // COPY_SRC for screenshots
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        format: surface.get_supported_formats(&adapter)[0],
+        width: size.width,
+        height: size.height,
+        present_mode: wgpu::PresentMode::Fifo,
+        alpha_mode: surface.get_supported_alpha_modes(&adapter)[0],
+    };
+
+    surface.configure(&device, &config);
+
+    let mut depth = A::depth_buffer().then(|| DepthTexture::new(&device, &config));
+    let mut app = A::init(&config, &adapter, &device, &queue);
+
+    let mut layers = vec![compositor::SCENE];
+    layers.extend(A::layers());
+    layers.extend([compositor::DEBUG, compositor::UI]);
+    let mut compositor = Compositor::new(&device, &config, &layers);
+
+    let mut watchdog = watchdog::Budgets::from_env()
+        .map(|budgets| Watchdog::new(&device, &queue, config.format, budgets));
+
+    // made on the first frame warm-up isn't done by then
+    let mut progress_bar: Option<ProgressBar> = None;
+    let mut warmed_up = false;
+
+    let mut console = Console::new();
+    let mut commands = driver_commands();
+    let mut app_commands = Commands::new();
+    A::commands(&mut app_commands);
+
+    let mut timeline = Timeline::new();
+    let mut capture = Capture::new();
+    let mut requests = Requests::default();
+    let mut script: Option<Script> = None;
+    #[cfg(feature = "rhai")]
+    let mut rhai: Option<rhai_script::RhaiScript> = None;
+    if let Ok(path) = std::env::var(script::ENV_VAR) {
+        match load_script(&path) {
+            Ok(request) => {
+                info!("running {}", path);
+                requests.script = Some(request);
+            }
+            Err(err) => warn!("{}: {}", script::ENV_VAR, err),
+        }
+    }
+    // (name prefix, frames saved, frames skipped because the staging buffers were busy)
+    let mut recording: Option<(String, u32, u32)> = None;
+
+    event_loop.run(move |event, _, control_flow| {
+        let _ = (&instance, &adapter);
+        *control_flow = ControlFlow::Wait;
+
+        match event {
+            Event::RedrawRequested(_) => {
+                let frame = surface
+                    .get_current_texture()
+                    .expect("Fail to request next swap chain texture");
+
+                let view = frame
+                    .texture
+                    .create_view(&wgpu::TextureViewDescriptor::default());
+
+                if requests.layers {
+                    requests.layers = false;
+                    let lines: Vec<String> = compositor
+                        .layers()
+                        .iter()
+                        .map(|layer| layer.status())
+                        .collect();
+                    console.reply(Ok(lines.join("\n")));
+                }
+                if let Some((name, change)) = requests.layer.take() {
+                    console.reply(compositor.change(&name, change));
+                }
+                if let Some((name, value)) = requests.param.take() {
+                    console.reply(param(&mut app, &name, value));
+                }
+                if let Some((path, lights)) = requests.scene.take() {
+                    console.reply(match app.light_editor() {
+                        Some(editor) => {
+                            let count = lights.len();
+                            editor.replace(lights);
+                            Ok(format!("{} lights from {}", count, path))
+                        }
+                        None => Err("this chapter has no light editor".to_string()),
+                    });
+                }
+
+                let scene = compositor
+                    .target(&device, &queue, compositor::SCENE)
+                    .expect("the scene is always a layer");
+                let progress = if warmed_up { None } else { app.warmup(&device) };
+                match progress {
+                    Some(progress) => progress_bar
+                        .get_or_insert_with(|| ProgressBar::new(&device, config.format))
+                        .draw(&device, &queue, scene, progress),
+                    None => {
+                        if !warmed_up {
+                            warmed_up = true;
+                            progress_bar = None;
+                        }
+                        // the time starts with the first frame after warm-up
+                        timeline.tick();
+                        match requests.script.take() {
+                            Some(ScriptRequest::Run(started)) => script = Some(started),
+                            #[cfg(feature = "rhai")]
+                            Some(ScriptRequest::RunRhai(started)) => rhai = Some(*started),
+                            Some(ScriptRequest::Stop) => {
+                                script = None;
+                                #[cfg(feature = "rhai")]
+                                {
+                                    rhai = None;
+                                }
+                            }
+                            None => {}
+                        }
+                        // the script's commands for this frame, as if typed into the console
+                        if let Some(running) = &mut script {
+                            if let Some(reloaded) = running.reload() {
+                                let path = running.path().display().to_string();
+                                console.reply(
+                                    reloaded.map(|()| format!("{} changed, starting over", path)),
+                                );
+                            }
+                            for line in running.due() {
+                                run_command(
+                                    &line,
+                                    &window,
+                                    &mut console,
+                                    (&mut commands, &mut requests),
+                                    (&mut app_commands, &mut app),
+                                );
+                            }
+                        }
+                        #[cfg(feature = "rhai")]
+                        run_rhai(&mut rhai, &mut app, &mut console, timeline.time());
+                        let target = Frame {
+                            view: scene,
+                            depth: depth.as_ref(),
+                            time: timeline.time(),
+                            delta: timeline.delta(),
+                            real_delta: timeline.real_delta(),
+                        };
+                        if let Some(watchdog) = &mut watchdog {
+                            watchdog.begin_frame(&device, &queue);
+                        }
+                        app.render(&target, &device, &queue);
+                        if let Some(watchdog) = &mut watchdog {
+                            watchdog.end_frame(&device, &queue);
+                        }
+                        for &layer in A::layers() {
+                            if compositor.enabled(layer) {
+                                let view = compositor.target(&device, &queue, layer);
+                                app.draw_layer(layer, view.unwrap(), &device, &queue);
+                            }
+                        }
+                    }
+                }
+
+                // the overlays go in layers of their own, the captures leave them out
+                if let Some(watchdog) = &mut watchdog {
+                    if watchdog.flashing() && compositor.enabled(compositor::DEBUG) {
+                        let view = compositor.target(&device, &queue, compositor::DEBUG);
+                        watchdog.draw(&device, &queue, view.unwrap());
+                    }
+                }
+                if compositor.enabled(compositor::UI) {
+                    if let Some(hud) = app.hud().filter(|hud| !hud.text().is_empty()) {
+                        let view = compositor.target(&device, &queue, compositor::UI);
+                        hud.draw(&device, &queue, view.unwrap(), config.format);
+                    }
+                }
+                if console.is_open() && compositor.enabled(compositor::UI) {
+                    let view = compositor.target(&device, &queue, compositor::UI);
+                    console.draw(&device, &queue, view.unwrap(), &config);
+                }
+                compositor.compose(&device, &queue, &view);
+
+                if requests.record {
+                    requests.record = false;
+                    match recording.take() {
+                        Some((prefix, saved, skipped)) => {
+                            info!("{}: {} frames, {} skipped", prefix, saved, skipped)
+                        }
+                        None => {
+                            let prefix = format!("recording-{}", timestamp());
+                            info!("recording to {}-*.png", prefix);
+                            recording = Some((prefix, 0, 0));
+                        }
+                    }
+                }
+                if requests.screenshot {
+                    requests.screenshot = false;
+                    let path = format!("screenshot-{}.png", timestamp());
+                    if !capture.capture(&device, &queue, &frame, &config, path) {
+                        warn!("screenshot skipped");
+                    }
+                }
+                if let Some((prefix, saved, skipped)) = &mut recording {
+                    let path = format!("{}-{:05}.png", prefix, saved);
+                    if capture.capture(&device, &queue, &frame, &config, path) {
+                        *saved += 1;
+                    } else {
+                        *skipped += 1;
+                    }
+                }
+                if requests.dump {
+                    requests.dump = false;
+                    let mut targets = app.dump_targets();
+                    targets.extend(compositor.dump_targets());
+                    if let Some(depth) = &depth {
+                        targets.push(DumpTarget::new(
+                            "depth",
+                            &depth.texture().texture,
+                            config.width,
+                            config.height,
+                            DepthTexture::FORMAT,
+                        ));
+                    }
+                    targets.push(DumpTarget::new(
+                        "final",
+                        &frame.texture,
+                        config.width,
+                        config.height,
+                        config.format,
+                    ));
+                    let dir = format!("frame-{}", timestamp());
+                    let queued = capture.dump(&device, &queue, &targets, &dir);
+                    info!("dumping {} targets to {}", queued, dir);
+                }
+                if requests.graph {
+                    requests.graph = false;
+                    match app.pass_graph() {
+                        Some(graph) => {
+                            let path = format!("passes-{}.dot", timestamp());
+                            match std::fs::write(&path, graph.to_dot()) {
+                                Ok(()) => info!("saved {}, `dot -Tsvg` draws it", path),
+                                Err(err) => warn!("Fail to save {}: {}", path, err),
+                            }
+                        }
+                        None => info!("this chapter describes no pass graph"),
+                    }
+                }
+                capture.poll(&device);
+
+                // after the captures, they show what the chapter drew
+                compositor.compose_uncaptured(&device, &queue, &view);
+                frame.present();
+            }
+            Event::RedrawEventsCleared => window.request_redraw(),
+            Event::WindowEvent { window_id, event } if window_id == window.id() => {
+                match console.process_window_event(&event) {
+                    Input::Ignored => {}
+                    Input::Handled => return,
+                    Input::Submit(line) => {
+                        run_command(
+                            &line,
+                            &window,
+                            &mut console,
+                            (&mut commands, &mut requests),
+                            (&mut app_commands, &mut app),
+                        );
+                        return;
+                    }
+                }
+                if timeline.process_window_event(&event) {
+                    return;
+                }
+                match event {
+                    // minimized, a zero sized surface can't be configured
+                    WindowEvent::Resized(size) if size.width == 0 || size.height == 0 => {}
+                    WindowEvent::Resized(size) => {
+                        config.width = size.width;
+                        config.height = size.height;
+                        surface.configure(&device, &config);
+                        if let Some(depth) = &mut depth {
+                            depth.resize(&device, &config);
+                        }
+                        compositor.resize(&device, &config);
+                        app.resize(&config, &device, &queue);
+
+                        window.request_redraw(); // for macos, need redraw when size change
+                    }
+
+                    WindowEvent::CloseRequested
+                    | WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => {
+                        info!("exit");
+                        *control_flow = ControlFlow::Exit
+                    }
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F12),
+                                ..
+                            },
+                        ..
+                    } => requests.screenshot = true,
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F10),
+                                ..
+                            },
+                        ..
+                    } => requests.dump = true,
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F9),
+                                ..
+                            },
+                        ..
+                    } => requests.graph = true,
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::F11),
+                                ..
+                            },
+                        ..
+                    } => requests.record = true,
+                    event => app.update(&window, event),
+                }
+            }
+            Event::DeviceEvent { event, .. } => app.device_event(event),
+            _ => {}
+        }
+    });
+}
+
+// milliseconds since the epoch, keeps capture file names apart
+fn timestamp() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or_default()
+}
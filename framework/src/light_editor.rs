@@ -0,0 +1,1026 @@
+//! point and spot lights that can be moved, aimed and tuned while a chapter runs, and saved.
+//!
+//! the lights live in `scenes/<chapter>.txt` under the working directory, a `[point]` or
+//! `[spot]` line per light followed by its `name = value` lines, angles in degrees. the file is
+//! rewritten after every edit and read back when it changes on disk, like [`crate::params`].
+//!
+//! e turns editing on and off. while it's on the gizmo is drawn over the frame: a cross at
+//! every light, the reach of the selected one, and its handles. drag with the right mouse
+//! button: the center moves the light in the view plane, the red / green / blue tips along x /
+//! y / z, the yellow tip aims a spot. tab selects the next light, `[` and `]` walk through the
+//! selected light's values, `-` and `=` step the selected one, every change is logged.
+//!
+//! ```ignore
+//! let mut editor = LightEditor::load(device, config.format, env!("CARGO_PKG_NAME"), defaults);
+//! // in update, before the camera controller
+//! if editor.process_window_event(window, &event) { return; }
+//! // every frame
+//! editor.poll();
+//! upload(&editor.lights);
+//! editor.draw(queue, &mut encoder, frame.view, &camera);
+//! ```
+
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use glam::{Mat4, Vec2, Vec3};
+use tracing::{info, warn};
+use wgpu::util::DeviceExt;
+use winit::{
+    event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use crate::{vertex_layout, watchdog};
+
+/// where the files go, relative to the working directory
+pub const DIR: &str = "scenes";
+
+// a light's handles are this fraction of its distance to the camera long, the same size on
+// screen wherever it is
+const GIZMO_SCALE: f32 = 0.15;
+// how close to a handle, in pixels, a press has to be to grab it
+const PICK_RADIUS: f32 = 12.;
+const CIRCLE_SEGMENTS: usize = 32;
+const MAX_VERTICES: usize = 4096;
+
+const HOVER_COLOR: [f32; 3] = [1., 1., 1.];
+const AIM_COLOR: [f32; 3] = [1., 0.8, 0.2];
+const AXIS_COLORS: [[f32; 3]; 3] = [[1., 0.2, 0.2], [0.2, 1., 0.2], [0.3, 0.4, 1.]];
+const AXES: [Vec3; 3] = [Vec3::X, Vec3::Y, Vec3::Z];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LightKind {
+    Point,
+    Spot,
+}
+
+impl LightKind {
+    fn name(self) -> &'static str {
+        match self {
+            LightKind::Point => "point",
+            LightKind::Spot => "spot",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    pub kind: LightKind,
+    pub position: Vec3,
+    /// unit length, where a spot points, points ignore it
+    pub direction: Vec3,
+    pub color: Vec3,
+    pub intensity: f32,
+    /// the light reaches 0 at this distance
+    pub range: f32,
+    /// radians from the direction to the edge of a spot's full brightness part
+    pub inner_angle: f32,
+    /// radians from the direction to the edge of a spot's cone
+    pub outer_angle: f32,
+}
+
+impl Light {
+    pub fn point(position: Vec3, color: Vec3, range: f32) -> Light {
+        Light {
+            kind: LightKind::Point,
+            position,
+            direction: Vec3::NEG_Y,
+            color,
+            intensity: 1.,
+            range,
+            inner_angle: 0.3,
+            outer_angle: 0.4,
+        }
+    }
+
+    /// `inner_angle` and `outer_angle` in radians
+    pub fn spot(
+        position: Vec3,
+        direction: Vec3,
+        color: Vec3,
+        inner_angle: f32,
+        outer_angle: f32,
+    ) -> Light {
+        Light {
+            kind: LightKind::Spot,
+            direction: direction.normalize(),
+            inner_angle,
+            outer_angle,
+            ..Light::point(position, color, 20.)
+        }
+    }
+}
+
+// what `-` and `=` change, the angles only for spots
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Field {
+    Intensity,
+    Range,
+    InnerAngle,
+    OuterAngle,
+    Red,
+    Green,
+    Blue,
+}
+
+const FIELDS: [Field; 7] = [
+    Field::Intensity,
+    Field::Range,
+    Field::InnerAngle,
+    Field::OuterAngle,
+    Field::Red,
+    Field::Green,
+    Field::Blue,
+];
+
+impl Field {
+    fn name(self) -> &'static str {
+        match self {
+            Field::Intensity => "intensity",
+            Field::Range => "range",
+            Field::InnerAngle => "inner_angle",
+            Field::OuterAngle => "outer_angle",
+            Field::Red => "red",
+            Field::Green => "green",
+            Field::Blue => "blue",
+        }
+    }
+
+    fn applies_to(self, light: &Light) -> bool {
+        light.kind == LightKind::Spot || !matches!(self, Field::InnerAngle | Field::OuterAngle)
+    }
+
+    // one press of `-` or `=`
+    fn step(self, light: &mut Light, direction: f32) {
+        let step_angle = |angle: &mut f32| {
+            *angle = (*angle + direction * 1f32.to_radians()).clamp(0., 1.5);
+        };
+        match self {
+            Field::Intensity => light.intensity = (light.intensity + direction * 0.1).max(0.),
+            Field::Range => light.range = (light.range + direction * 0.5).max(0.5),
+            Field::InnerAngle => {
+                step_angle(&mut light.inner_angle);
+                light.outer_angle = light.outer_angle.max(light.inner_angle);
+            }
+            Field::OuterAngle => {
+                step_angle(&mut light.outer_angle);
+                light.inner_angle = light.inner_angle.min(light.outer_angle);
+            }
+            Field::Red => light.color.x = (light.color.x + direction * 0.05).max(0.),
+            Field::Green => light.color.y = (light.color.y + direction * 0.05).max(0.),
+            Field::Blue => light.color.z = (light.color.z + direction * 0.05).max(0.),
+        }
+    }
+
+    fn value(self, light: &Light) -> f32 {
+        match self {
+            Field::Intensity => light.intensity,
+            Field::Range => light.range,
+            Field::InnerAngle => light.inner_angle.to_degrees(),
+            Field::OuterAngle => light.outer_angle.to_degrees(),
+            Field::Red => light.color.x,
+            Field::Green => light.color.y,
+            Field::Blue => light.color.z,
+        }
+    }
+}
+
+/// the scene file for `lights`, see the module docs
+pub fn to_scene(lights: &[Light]) -> String {
+    let vec3 = |v: Vec3| format!("{} {} {}", v.x, v.y, v.z);
+    let mut text = String::from("# written by the light editor, angles in degrees\n");
+    for light in lights {
+        text += &format!("\n[{}]\n", light.kind.name());
+        text += &format!("position = {}\n", vec3(light.position));
+        if light.kind == LightKind::Spot {
+            text += &format!("direction = {}\n", vec3(light.direction));
+        }
+        text += &format!("color = {}\n", vec3(light.color));
+        text += &format!("intensity = {}\n", light.intensity);
+        text += &format!("range = {}\n", light.range);
+        if light.kind == LightKind::Spot {
+            text += &format!("inner_angle = {}\n", light.inner_angle.to_degrees());
+            text += &format!("outer_angle = {}\n", light.outer_angle.to_degrees());
+        }
+    }
+    text
+}
+
+/// the lights of a scene file, values a light doesn't list keep [`Light::point`]'s or
+/// [`Light::spot`]'s. the error names the line that didn't parse
+pub fn parse_scene(text: &str) -> Result<Vec<Light>, String> {
+    let mut lights: Vec<Light> = Vec::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |what: &str| format!("line {}: {}, got {:?}", number + 1, what, line);
+
+        match line {
+            "[point]" => {
+                lights.push(Light::point(Vec3::ZERO, Vec3::ONE, 10.));
+                continue;
+            }
+            "[spot]" => {
+                lights.push(Light::spot(Vec3::ZERO, Vec3::NEG_Y, Vec3::ONE, 0.3, 0.4));
+                continue;
+            }
+            _ => {}
+        }
+
+        let light = lights
+            .last_mut()
+            .ok_or_else(|| error("expected `[point]` or `[spot]` first"))?;
+        let (name, value) = line
+            .split_once('=')
+            .map(|(name, value)| (name.trim(), value.trim()))
+            .ok_or_else(|| error("expected `name = value`"))?;
+        let floats = value
+            .split_whitespace()
+            .map(str::parse::<f32>)
+            .collect::<Result<Vec<f32>, _>>()
+            .map_err(|_| error("expected numbers"))?;
+        let float = || match floats[..] {
+            [x] => Ok(x),
+            _ => Err(error("expected one number")),
+        };
+        let vec3 = || match floats[..] {
+            [x, y, z] => Ok(Vec3::new(x, y, z)),
+            _ => Err(error("expected three numbers")),
+        };
+
+        match name {
+            "position" => light.position = vec3()?,
+            "direction" => light.direction = vec3()?.normalize_or_zero(),
+            "color" => light.color = vec3()?,
+            "intensity" => light.intensity = float()?,
+            "range" => light.range = float()?,
+            "inner_angle" => light.inner_angle = float()?.to_radians(),
+            "outer_angle" => light.outer_angle = float()?.to_radians(),
+            _ => return Err(error("unknown value")),
+        }
+    }
+
+    Ok(lights)
+}
+
+// the parts of the gizmo that can be dragged
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Handle {
+    Center,
+    Axis(usize),
+    Aim,
+}
+
+struct Drag {
+    handle: Handle,
+    // where on the handle it was grabbed: the offset from the light in the view plane, or how
+    // far along the axis, `None` until the axis stops pointing along the cursor ray
+    grab: Vec3,
+    along: Option<f32>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GizmoVertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+vertex_layout! {
+    GizmoVertex as "GizmoVertex", Vertex {
+        0 => position: Float32x3,
+        1 => color: Float32x3,
+    }
+}
+
+// the camera and window the last frame was drawn with and the cursor over it, picking works
+// against them
+struct Pointer {
+    view_proj: Mat4,
+    camera_position: Vec3,
+    camera_forward: Vec3,
+    window_size: Vec2,
+    cursor: Vec2,
+}
+
+impl Pointer {
+    // where `world` lands in the window, in pixels from the top left
+    fn to_screen(&self, world: Vec3) -> Option<Vec2> {
+        let clip = self.view_proj * world.extend(1.);
+        if clip.w <= 0. {
+            return None;
+        }
+        let ndc = Vec2::new(clip.x, clip.y) / clip.w;
+        Some(Vec2::new(ndc.x + 1., 1. - ndc.y) * 0.5 * self.window_size)
+    }
+
+    // the ray under the cursor, its origin and unit direction
+    fn cursor_ray(&self) -> (Vec3, Vec3) {
+        let ndc = self.cursor / self.window_size * 2. - Vec2::ONE;
+        let inverse = self.view_proj.inverse();
+        let near = inverse.project_point3(Vec3::new(ndc.x, -ndc.y, 0.));
+        let far = inverse.project_point3(Vec3::new(ndc.x, -ndc.y, 1.));
+        (near, (far - near).normalize())
+    }
+
+    // where the cursor ray meets the plane through `point` facing the camera
+    fn cursor_on_view_plane(&self, point: Vec3) -> Vec3 {
+        let (origin, direction) = self.cursor_ray();
+        let denominator = direction.dot(self.camera_forward);
+        if denominator.abs() < 1e-6 {
+            return point;
+        }
+        let t = (point - origin).dot(self.camera_forward) / denominator;
+        origin + direction * t
+    }
+
+    // how far along the line through `point` along `axis` the cursor ray passes closest, `None`
+    // while the axis points along the ray
+    fn cursor_along_axis(&self, point: Vec3, axis: Vec3) -> Option<f32> {
+        let (origin, direction) = self.cursor_ray();
+        let b = axis.dot(direction);
+        let denominator = 1. - b * b;
+        // the axis points at the camera, there's no telling how far along it the cursor is
+        if denominator < 1e-4 {
+            return None;
+        }
+        let w = point - origin;
+        Some((b * direction.dot(w) - axis.dot(w)) / denominator)
+    }
+}
+
+pub struct LightEditor {
+    path: PathBuf,
+    pub lights: Vec<Light>,
+    /// the gizmo is drawn and the keys and mouse are used only while this is on
+    pub enabled: bool,
+    selected: usize,
+    field: usize,
+    /// of the file when it was last read or written, anything newer gets reloaded
+    modified: Option<SystemTime>,
+
+    pointer: Pointer,
+    hovered: Option<Handle>,
+    drag: Option<Drag>,
+
+    pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+}
+
+impl LightEditor {
+    /// the lights saved for `chapter`, `defaults` if it hasn't saved any yet. the gizmo is
+    /// drawn into `format` targets
+    pub fn load(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        chapter: &str,
+        defaults: Vec<Light>,
+    ) -> LightEditor {
+        let shader = vertex_layout::shader(
+            device,
+            "light editor",
+            include_str!("light_editor.wgsl"),
+            &[GizmoVertex::WGSL],
+        );
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("light editor uniform"),
+            contents: bytemuck::bytes_of(&Mat4::IDENTITY.to_cols_array()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("light editor bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(64),
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("light editor bind group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        // no depth test, the handles stay visible inside and behind the geometry
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("light editor"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[GizmoVertex::buffer_layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("light editor vertices"),
+            size: (MAX_VERTICES * std::mem::size_of::<GizmoVertex>()) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut editor = LightEditor {
+            path: PathBuf::from(DIR).join(format!("{}.txt", chapter)),
+            lights: defaults,
+            enabled: false,
+            selected: 0,
+            field: 0,
+            modified: None,
+            pointer: Pointer {
+                view_proj: Mat4::IDENTITY,
+                camera_position: Vec3::ZERO,
+                camera_forward: Vec3::NEG_Z,
+                window_size: Vec2::ONE,
+                cursor: Vec2::ZERO,
+            },
+            hovered: None,
+            drag: None,
+            pipeline,
+            uniform_buffer,
+            bind_group,
+            vertex_buffer,
+        };
+        editor.reload();
+        editor
+    }
+
+    /// true when the lights came from the scene file rather than the defaults
+    pub fn saved(&self) -> bool {
+        self.modified.is_some()
+    }
+
+    /// reload the file if it changed since it was last read or written, true if it did
+    pub fn poll(&mut self) -> bool {
+        let modified = self.file_modified();
+        if modified.is_none() || modified <= self.modified {
+            return false;
+        }
+
+        self.reload();
+        info!("reloaded {}", self.path.display());
+        true
+    }
+
+    /// e, and while editing the keys and the right mouse button. true if the event was one
+    /// of them, the chapter and its camera shouldn't see it then
+    pub fn process_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        let size = window.inner_size();
+        self.pointer.window_size = Vec2::new(size.width as f32, size.height as f32);
+
+        match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => self.process_key(*keycode),
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer.cursor = Vec2::new(position.x as f32, position.y as f32);
+                if !self.enabled {
+                    return false;
+                }
+                match self.drag {
+                    Some(_) => {
+                        self.drag_to_cursor();
+                        true
+                    }
+                    None => {
+                        self.hovered = self.pick();
+                        false
+                    }
+                }
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Right,
+                ..
+            } if self.enabled => {
+                match state {
+                    ElementState::Pressed => self.start_drag(),
+                    ElementState::Released => {
+                        if self.drag.take().is_some() {
+                            self.log_selected();
+                            self.save();
+                        }
+                    }
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// what's being edited, for the window title
+    pub fn status(&self) -> String {
+        let light = match self.lights.get(self.selected) {
+            Some(light) if self.enabled => light,
+            _ => return String::new(),
+        };
+        let field = FIELDS[self.field];
+        format!(
+            "{} {}/{}, {} = {:.2}",
+            light.kind.name(),
+            self.selected + 1,
+            self.lights.len(),
+            field.name(),
+            field.value(light)
+        )
+    }
+
+    /// the gizmo over `view` for `camera`, in a pass of its own. nothing while editing is off
+    pub fn draw(
+        &mut self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        camera: &crate::camera::Camera,
+    ) {
+        self.pointer.view_proj = camera.view_proj();
+        self.pointer.camera_position = camera.position;
+        self.pointer.camera_forward = camera.forward();
+        if !self.enabled || self.lights.is_empty() {
+            return;
+        }
+
+        let mut vertices = self.gizmo_lines();
+        vertices.truncate(MAX_VERTICES);
+        watchdog::write_buffer(
+            queue,
+            &self.uniform_buffer,
+            0,
+            bytemuck::bytes_of(&self.pointer.view_proj.to_cols_array()),
+        );
+        watchdog::write_buffer(
+            queue,
+            &self.vertex_buffer,
+            0,
+            bytemuck::cast_slice(&vertices),
+        );
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("light editor"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.draw(0..vertices.len() as u32, 0..1);
+    }
+
+    fn process_key(&mut self, keycode: VirtualKeyCode) -> bool {
+        if keycode == VirtualKeyCode::E {
+            self.enabled = !self.enabled;
+            self.drag = None;
+            self.hovered = None;
+            info!("light editor {}", if self.enabled { "on" } else { "off" });
+            return true;
+        }
+        if !self.enabled || self.lights.is_empty() {
+            return false;
+        }
+
+        match keycode {
+            VirtualKeyCode::Tab => {
+                self.selected = (self.selected + 1) % self.lights.len();
+                self.skip_to_field(1);
+            }
+            VirtualKeyCode::LBracket => {
+                self.field = (self.field + FIELDS.len() - 1) % FIELDS.len();
+                self.skip_to_field(FIELDS.len() - 1);
+            }
+            VirtualKeyCode::RBracket => {
+                self.field = (self.field + 1) % FIELDS.len();
+                self.skip_to_field(1);
+            }
+            VirtualKeyCode::Minus | VirtualKeyCode::Equals => {
+                let direction = if keycode == VirtualKeyCode::Minus {
+                    -1.
+                } else {
+                    1.
+                };
+                FIELDS[self.field].step(&mut self.lights[self.selected], direction);
+                self.save();
+            }
+            _ => return false,
+        }
+        self.log_selected();
+        true
+    }
+
+    // a point light has no angles, keep walking by `by` until the field is one it has
+    fn skip_to_field(&mut self, by: usize) {
+        let light = &self.lights[self.selected];
+        while !FIELDS[self.field].applies_to(light) {
+            self.field = (self.field + by) % FIELDS.len();
+        }
+    }
+
+    fn log_selected(&self) {
+        let light = &self.lights[self.selected];
+        info!(
+            "{} at ({:.2}, {:.2}, {:.2})",
+            self.status(),
+            light.position.x,
+            light.position.y,
+            light.position.z
+        );
+    }
+
+    // how long the handles of a light at `position` are
+    fn gizmo_size(&self, position: Vec3) -> f32 {
+        (position - self.pointer.camera_position).length() * GIZMO_SCALE
+    }
+
+    fn handle_position(&self, light: &Light, handle: Handle) -> Vec3 {
+        let size = self.gizmo_size(light.position);
+        match handle {
+            Handle::Center => light.position,
+            Handle::Axis(axis) => light.position + AXES[axis] * size,
+            Handle::Aim => light.position + light.direction * size * 1.5,
+        }
+    }
+
+    fn handles(&self) -> Vec<Handle> {
+        let mut handles = vec![
+            Handle::Center,
+            Handle::Axis(0),
+            Handle::Axis(1),
+            Handle::Axis(2),
+        ];
+        if self.lights[self.selected].kind == LightKind::Spot {
+            handles.push(Handle::Aim);
+        }
+        handles
+    }
+
+    // the handle of the selected light under the cursor, the closest if there are several
+    fn pick(&self) -> Option<Handle> {
+        let light = self.lights.get(self.selected)?;
+        self.handles()
+            .into_iter()
+            .filter_map(|handle| {
+                let screen = self
+                    .pointer
+                    .to_screen(self.handle_position(light, handle))?;
+                let distance = screen.distance(self.pointer.cursor);
+                (distance < PICK_RADIUS).then_some((handle, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(handle, _)| handle)
+    }
+
+    // grab a handle of the selected light, or select the light under the cursor
+    fn start_drag(&mut self) {
+        if let Some(handle) = self.pick() {
+            let position = self.lights[self.selected].position;
+            self.drag = Some(Drag {
+                handle,
+                grab: self.pointer.cursor_on_view_plane(position) - position,
+                along: match handle {
+                    Handle::Axis(axis) => self.pointer.cursor_along_axis(position, AXES[axis]),
+                    _ => None,
+                },
+            });
+            return;
+        }
+
+        let closest = self
+            .lights
+            .iter()
+            .enumerate()
+            .filter_map(|(index, light)| {
+                let screen = self.pointer.to_screen(light.position)?;
+                let distance = screen.distance(self.pointer.cursor);
+                (distance < PICK_RADIUS).then_some((index, distance))
+            })
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((index, _)) = closest {
+            self.selected = index;
+            self.skip_to_field(1);
+            self.log_selected();
+        }
+    }
+
+    fn drag_to_cursor(&mut self) {
+        let drag = match &mut self.drag {
+            Some(drag) => drag,
+            None => return,
+        };
+        let position = self.lights[self.selected].position;
+        match drag.handle {
+            Handle::Center => {
+                self.lights[self.selected].position =
+                    self.pointer.cursor_on_view_plane(position) - drag.grab;
+            }
+            Handle::Axis(axis) => {
+                // the light holds still while the axis points along the ray, and the grab
+                // starts from where the cursor is once it doesn't
+                match (
+                    self.pointer.cursor_along_axis(position, AXES[axis]),
+                    drag.along,
+                ) {
+                    (Some(along), Some(grabbed)) => {
+                        self.lights[self.selected].position += AXES[axis] * (along - grabbed)
+                    }
+                    (along, None) => drag.along = along,
+                    (None, Some(_)) => {}
+                }
+            }
+            Handle::Aim => {
+                let direction = self.pointer.cursor_on_view_plane(position) - position;
+                if direction.length_squared() > 1e-6 {
+                    self.lights[self.selected].direction = direction.normalize();
+                }
+            }
+        }
+    }
+
+    fn gizmo_lines(&self) -> Vec<GizmoVertex> {
+        let mut vertices = Vec::new();
+        let mut line = |a: Vec3, b: Vec3, color: [f32; 3]| {
+            vertices.push(GizmoVertex {
+                position: a.to_array(),
+                color,
+            });
+            vertices.push(GizmoVertex {
+                position: b.to_array(),
+                color,
+            });
+        };
+        let circle = |line: &mut dyn FnMut(Vec3, Vec3, [f32; 3]),
+                      center: Vec3,
+                      u: Vec3,
+                      v: Vec3,
+                      color: [f32; 3]| {
+            let point = |i: usize| {
+                let angle = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+                center + u * angle.cos() + v * angle.sin()
+            };
+            for i in 0..CIRCLE_SEGMENTS {
+                line(point(i), point(i + 1), color);
+            }
+        };
+
+        // a cross at every light, in its own color
+        for light in &self.lights {
+            let size = self.gizmo_size(light.position) * 0.2;
+            let color = light.color.to_array();
+            for axis in AXES {
+                line(
+                    light.position - axis * size,
+                    light.position + axis * size,
+                    color,
+                );
+            }
+        }
+
+        // the selected light's reach, its cone or a circle around it
+        let light = &self.lights[self.selected];
+        let color = light.color.to_array();
+        match light.kind {
+            LightKind::Point => {
+                circle(
+                    &mut line,
+                    light.position,
+                    Vec3::X * light.range,
+                    Vec3::Z * light.range,
+                    color,
+                );
+            }
+            LightKind::Spot => {
+                let (u, v) = light.direction.any_orthonormal_pair();
+                let end = light.position + light.direction * light.range * light.outer_angle.cos();
+                let radius = light.range * light.outer_angle.sin();
+                for i in 0..4 {
+                    let angle = i as f32 * std::f32::consts::FRAC_PI_2;
+                    let rim = end + (u * angle.cos() + v * angle.sin()) * radius;
+                    line(light.position, rim, color);
+                }
+                circle(&mut line, end, u * radius, v * radius, color);
+            }
+        }
+
+        // and its handles
+        let highlighted = self.drag.as_ref().map(|drag| drag.handle).or(self.hovered);
+        for handle in self.handles() {
+            let color = if highlighted == Some(handle) {
+                HOVER_COLOR
+            } else {
+                match handle {
+                    Handle::Center => continue,
+                    Handle::Axis(axis) => AXIS_COLORS[axis],
+                    Handle::Aim => AIM_COLOR,
+                }
+            };
+            let tip = self.handle_position(light, handle);
+            line(light.position, tip, color);
+            // a small square at the tip, the part to grab
+            let size = self.gizmo_size(light.position) * 0.05;
+            let (u, v) = self.pointer.camera_forward.any_orthonormal_pair();
+            let corners = [u + v, u - v, -u - v, -u + v].map(|corner| tip + corner * size);
+            for i in 0..4 {
+                line(corners[i], corners[(i + 1) % 4], color);
+            }
+        }
+
+        vertices
+    }
+
+    fn file_modified(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    // the file replaces the lights in memory, unless it doesn't parse
+    fn reload(&mut self) {
+        let modified = self.file_modified();
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            // nothing saved yet
+            Err(_) => return,
+        };
+        self.modified = modified;
+
+        match parse_scene(&text) {
+            Ok(lights) if !lights.is_empty() => {
+                self.lights = lights;
+                self.selected = self.selected.min(self.lights.len() - 1);
+                self.skip_to_field(1);
+            }
+            Ok(_) => warn!(
+                "{}: no lights, keeping the current ones",
+                self.path.display()
+            ),
+            Err(err) => warn!("{}: {}", self.path.display(), err),
+        }
+    }
+
+    /// replace the lights with `lights` and save them as the chapter's, for `scene load`
+    pub fn replace(&mut self, lights: Vec<Light>) {
+        self.lights = lights;
+        self.selected = self.selected.min(self.lights.len().saturating_sub(1));
+        self.hovered = None;
+        self.drag = None;
+        self.skip_to_field(1);
+        self.save();
+    }
+
+    /// write the lights to the scene file, the editor does after each of its own edits
+    pub fn save(&mut self) {
+        let written = std::fs::create_dir_all(DIR)
+            .and_then(|_| std::fs::write(&self.path, to_scene(&self.lights)));
+        match written {
+            // our own write isn't a change to reload
+            Ok(()) => self.modified = self.file_modified(),
+            Err(err) => warn!("Fail to save {}: {}", self.path.display(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_round_trip() {
+        let lights = vec![
+            Light::point(Vec3::new(1., 2., 3.), Vec3::new(1., 0.5, 0.), 4.),
+            Light::spot(Vec3::Y, Vec3::NEG_Y, Vec3::ONE, 0.25, 0.5),
+        ];
+        let parsed = parse_scene(&to_scene(&lights)).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0], lights[0]);
+        assert_eq!(parsed[1].kind, LightKind::Spot);
+        assert!((parsed[1].outer_angle - 0.5).abs() < 1e-5);
+    }
+
+    // at (0, 0, 5) looking down -z with a 90 degree fov, in a 200x200 window
+    fn pointer(cursor: Vec2) -> Pointer {
+        let position = Vec3::new(0., 0., 5.);
+        let view = Mat4::look_at_rh(position, Vec3::ZERO, Vec3::Y);
+        let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1., 0.1, 100.);
+        Pointer {
+            view_proj: projection * view,
+            camera_position: position,
+            camera_forward: Vec3::NEG_Z,
+            window_size: Vec2::new(200., 200.),
+            cursor,
+        }
+    }
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!(
+            actual.abs_diff_eq(expected, 1e-3),
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn the_cursor_unprojects_to_a_ray() {
+        let (origin, direction) = pointer(Vec2::new(100., 100.)).cursor_ray();
+        assert_close(origin, Vec3::new(0., 0., 4.9));
+        assert_close(direction, Vec3::NEG_Z);
+
+        // the right edge is 45 degrees off, the top edge too
+        let (_, direction) = pointer(Vec2::new(200., 100.)).cursor_ray();
+        assert_close(direction, Vec3::new(1., 0., -1.).normalize());
+        let (_, direction) = pointer(Vec2::new(100., 0.)).cursor_ray();
+        assert_close(direction, Vec3::new(0., 1., -1.).normalize());
+    }
+
+    #[test]
+    fn dragging_stays_on_the_view_plane() {
+        let point = Vec3::new(1., 2., -3.);
+        for cursor in [
+            Vec2::new(100., 100.),
+            Vec2::new(10., 180.),
+            Vec2::new(200., 0.),
+        ] {
+            let pointer = pointer(cursor);
+            let on_plane = pointer.cursor_on_view_plane(point);
+            assert!((on_plane - point).dot(pointer.camera_forward).abs() < 1e-3);
+            // and under the cursor
+            let screen = pointer.to_screen(on_plane).unwrap();
+            assert!(
+                screen.distance(cursor) < 0.01,
+                "{} isn't {}",
+                screen,
+                cursor
+            );
+        }
+        // 8 away at 45 degrees is 8 to the right
+        assert_close(
+            pointer(Vec2::new(200., 100.)).cursor_on_view_plane(Vec3::new(0., 0., -3.)),
+            Vec3::new(8., 0., -3.),
+        );
+    }
+
+    #[test]
+    fn the_axis_under_the_cursor() {
+        let right = pointer(Vec2::new(200., 100.));
+        let along = right.cursor_along_axis(Vec3::ZERO, Vec3::X).unwrap();
+        assert!((along - 5.).abs() < 1e-3, "{}", along);
+        let center = pointer(Vec2::new(100., 100.));
+        assert!(center.cursor_along_axis(Vec3::ZERO, Vec3::X).unwrap().abs() < 1e-3);
+
+        // an axis along the ray has no closest point
+        assert_eq!(center.cursor_along_axis(Vec3::ZERO, Vec3::Z), None);
+        assert_eq!(center.cursor_along_axis(Vec3::ZERO, Vec3::NEG_Z), None);
+        let nearly = Vec3::new(1e-4, 0., 1.).normalize();
+        assert_eq!(center.cursor_along_axis(Vec3::ZERO, nearly), None);
+    }
+
+    #[test]
+    fn scene_errors_name_the_line() {
+        let err = parse_scene("[point]\nrange = far\n").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{}", err);
+        assert!(parse_scene("range = 1\n").is_err());
+        assert!(parse_scene("[spot]\nposition = 1 2\n").is_err());
+    }
+}
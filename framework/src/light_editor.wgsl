@@ -0,0 +1,27 @@
+// the light editor's gizmo, colored lines in world space drawn over the finished frame
+
+struct GizmoVertex {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> view_proj: mat4x4<f32>;
+
+@vertex
+fn vs_main(input: GizmoVertex) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = view_proj * vec4<f32>(input.position, 1.0);
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color, 1.0);
+}
@@ -0,0 +1,124 @@
+//! mipmaps on the gpu: each level is a [`Blit`] of the one above it, drawn into a target half
+//! its size, so the linear sampler averages 2x2 texels into one. srgb textures are decoded
+//! when sampled and encoded again when written, the average is taken in linear light.
+//!
+//! the levels are drawn into scratch textures and copied into place. the gl backend binds
+//! the whole texture when sampling a view of one level, it would read the level being drawn.
+//! so a texture that gets mips only needs `COPY_DST` and `TEXTURE_BINDING`, and a
+//! `mip_level_count` of [`mip_level_count`] for its size, see
+//! [`Texture::from_image_mipmapped`](crate::texture::Texture::from_image_mipmapped).
+
+use std::collections::HashMap;
+
+use crate::blit::Blit;
+
+/// levels down to 1x1, the full chain for a `width` x `height` texture
+pub fn mip_level_count(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+pub struct MipmapGenerator {
+    // the blit pipeline is made for the format it draws into, one per format seen
+    blits: HashMap<wgpu::TextureFormat, Blit>,
+    sampler: wgpu::Sampler,
+}
+
+impl MipmapGenerator {
+    pub fn new(device: &wgpu::Device) -> MipmapGenerator {
+        MipmapGenerator {
+            blits: HashMap::new(),
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("mipmap sampler"),
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                // level 0 of level 0, on gl the source is the whole texture
+                lod_max_clamp: 0.,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// fill levels 1.. of the 2d `texture` of `size` from level 0, in one submit
+    pub fn generate(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        format: wgpu::TextureFormat,
+        size: wgpu::Extent3d,
+        mip_level_count: u32,
+    ) {
+        let blit = self
+            .blits
+            .entry(format)
+            .or_insert_with(|| Blit::new(device, format));
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("mipmaps"),
+        });
+        let mut source = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        for level in 1..mip_level_count {
+            let level_size = wgpu::Extent3d {
+                width: (size.width >> level).max(1),
+                height: (size.height >> level).max(1),
+                depth_or_array_layers: 1,
+            };
+            let scratch = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("mip"),
+                size: level_size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+            });
+            let target = scratch.create_view(&wgpu::TextureViewDescriptor::default());
+
+            let bind_group = blit.create_bind_group(device, &source, &self.sampler);
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("mip"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &target,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+                blit.draw(&mut rpass, &bind_group);
+            }
+            encoder.copy_texture_to_texture(
+                scratch.as_image_copy(),
+                wgpu::ImageCopyTexture {
+                    texture,
+                    mip_level: level,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                level_size,
+            );
+            // the next level is drawn from this one
+            source = target;
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_chain() {
+        assert_eq!(mip_level_count(1, 1), 1);
+        assert_eq!(mip_level_count(2, 1), 2);
+        assert_eq!(mip_level_count(256, 256), 9);
+        // odd sizes round down, 300 -> 150 -> 75 -> 37 -> 18 -> 9 -> 4 -> 2 -> 1
+        assert_eq!(mip_level_count(300, 20), 9);
+    }
+}
@@ -0,0 +1,168 @@
+//! overdraw heat map: count how many fragments land on every pixel, then show the counts
+//! through a black, blue, green, yellow, red, white palette.
+//!
+//! build a counting copy of a scene pipeline with [`Overdraw::fragment_state`] as its fragment
+//! stage and no depth test, draw the scene with it into [`Overdraw::view`], then
+//! [`Overdraw::resolve`] paints the heat map into the window.
+
+use wgpu::include_wgsl;
+
+pub struct Overdraw {
+    shader: wgpu::ShaderModule,
+    count_targets: [Option<wgpu::ColorTargetState>; 1],
+    view: wgpu::TextureView,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl Overdraw {
+    /// float so additive blending works, 16 bits count exactly up to 2048
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R16Float;
+
+    /// `surface_format` is the format of the window the heat map gets drawn to
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        surface_format: wgpu::TextureFormat,
+    ) -> Overdraw {
+        let shader = device.create_shader_module(include_wgsl!("overdraw.wgsl"));
+
+        let count_targets = [Some(wgpu::ColorTargetState {
+            format: Self::FORMAT,
+            blend: Some(wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::REPLACE,
+            }),
+            write_mask: wgpu::ColorWrites::ALL,
+        })];
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("overdraw_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("overdraw heat"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_heat",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_heat",
+                targets: &[Some(surface_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let view = create_view(device, width, height);
+        let bind_group = create_bind_group(device, &layout, &view);
+
+        Overdraw {
+            shader,
+            count_targets,
+            view,
+            layout,
+            bind_group,
+            pipeline,
+        }
+    }
+
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        self.view = create_view(device, width, height);
+        self.bind_group = create_bind_group(device, &self.layout, &self.view);
+    }
+
+    /// fragment stage for the counting pipeline. it reads no inputs, so it fits behind any
+    /// vertex stage. leave `depth_stencil` out of that pipeline, hidden fragments count too
+    pub fn fragment_state(&self) -> wgpu::FragmentState<'_> {
+        wgpu::FragmentState {
+            module: &self.shader,
+            entry_point: "fs_count",
+            targets: &self.count_targets,
+        }
+    }
+
+    /// count target, clear it to 0 before drawing the scene with the counting pipeline
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// turn the counts into colors over all of `surface_view`
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder, surface_view: &wgpu::TextureView) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("overdraw heat"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: surface_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+fn create_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("overdraw_texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: Overdraw::FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("overdraw_bind_group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(view),
+        }],
+    })
+}
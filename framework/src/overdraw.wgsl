@@ -0,0 +1,48 @@
+// every fragment adds 1 to the count target, additive blending does the summing
+@fragment
+fn fs_count() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.0, 0.0, 0.0);
+}
+
+struct FragInput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_heat(@builtin(vertex_index) index: u32) -> FragInput {
+    // one triangle over the whole screen, same as blit.wgsl
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return fragInput;
+}
+
+@group(0) @binding(0)
+var t_count: texture_2d<f32>;
+
+// counts at or above this are drawn white
+let max_count = 8.0;
+
+// black, blue, cyan, green, yellow, red, white, evenly spaced over 0..max_count
+fn heat(t: f32) -> vec3<f32> {
+    let x = clamp(t, 0.0, 1.0) * 6.0;
+    if (x < 1.0) {
+        return mix(vec3<f32>(0.0, 0.0, 0.0), vec3<f32>(0.0, 0.0, 1.0), x);
+    } else if (x < 2.0) {
+        return mix(vec3<f32>(0.0, 0.0, 1.0), vec3<f32>(0.0, 1.0, 1.0), x - 1.0);
+    } else if (x < 3.0) {
+        return mix(vec3<f32>(0.0, 1.0, 1.0), vec3<f32>(0.0, 1.0, 0.0), x - 2.0);
+    } else if (x < 4.0) {
+        return mix(vec3<f32>(0.0, 1.0, 0.0), vec3<f32>(1.0, 1.0, 0.0), x - 3.0);
+    } else if (x < 5.0) {
+        return mix(vec3<f32>(1.0, 1.0, 0.0), vec3<f32>(1.0, 0.0, 0.0), x - 4.0);
+    }
+    return mix(vec3<f32>(1.0, 0.0, 0.0), vec3<f32>(1.0, 1.0, 1.0), x - 5.0);
+}
+
+@fragment
+fn fs_heat(input: FragInput) -> @location(0) vec4<f32> {
+    let count = textureLoad(t_count, vec2<i32>(input.clip_position.xy), 0).r;
+    return vec4<f32>(heat(count / max_count), 1.0);
+}
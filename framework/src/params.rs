@@ -0,0 +1,347 @@
+//! named tunables for a chapter, asked for by name where they're used instead of a const.
+//!
+//! values live in `params/<chapter>.txt` under the working directory, one `name = value` per
+//! line. the file is rewritten whenever a value changes and read back when it changes on disk,
+//! so a value can be tuned from an editor while the chapter runs. in the window `\` shows a
+//! panel with every parameter in the top left corner, `[` and `]` walk through them, `-` and
+//! `=` step the selected one, every change is logged.
+//!
+//! ```ignore
+//! let mut params = framework::params::Params::load(env!("CARGO_PKG_NAME"));
+//! // every frame
+//! params.poll();
+//! let angle = delta_time * params.float("rotate_speed", 10.);
+//! // over the finished frame
+//! params.draw(&device, &queue, &view, config.format);
+//! ```
+
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use tracing::{info, warn};
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+use crate::console::{self, Grid, Overlay};
+
+/// where the files go, relative to the working directory
+pub const DIR: &str = "params";
+
+/// the key that shows and hides the panel
+pub const PANEL: VirtualKeyCode = VirtualKeyCode::Backslash;
+
+// the panel's bottom line, drawn brighter like the console's input
+const PANEL_HELP: &str = "[ ] select  - = step";
+
+struct Param {
+    name: String,
+    value: f32,
+    /// what one press of `-` or `=` changes, a tenth of the default
+    step: f32,
+}
+
+pub struct Params {
+    path: PathBuf,
+    /// in the order they were first seen, the keys walk through them in this order
+    params: Vec<Param>,
+    selected: usize,
+    /// of the file when it was last read or written, anything newer gets reloaded
+    modified: Option<SystemTime>,
+    panel: bool,
+    /// made the first time the panel is drawn
+    overlay: Option<Overlay>,
+}
+
+impl Params {
+    /// the values saved for `chapter`, none if it hasn't saved any yet
+    pub fn load(chapter: &str) -> Params {
+        let mut params = Params {
+            path: PathBuf::from(DIR).join(format!("{}.txt", chapter)),
+            params: Vec::new(),
+            selected: 0,
+            modified: None,
+            panel: false,
+            overlay: None,
+        };
+        params.reload();
+        params
+    }
+
+    /// the current value of `name`, registered with `default` the first time it's asked for
+    /// unless the file already had one
+    pub fn float(&mut self, name: &str, default: f32) -> f32 {
+        let step = if default == 0. {
+            0.1
+        } else {
+            default.abs() / 10.
+        };
+
+        match self.params.iter_mut().find(|param| param.name == name) {
+            Some(param) => {
+                param.step = step;
+                param.value
+            }
+            None => {
+                self.params.push(Param {
+                    name: name.to_string(),
+                    value: default,
+                    step,
+                });
+                self.save();
+                default
+            }
+        }
+    }
+
+    /// the value of `name` if the chapter has asked for it or the file has one
+    pub fn get(&self, name: &str) -> Option<f32> {
+        self.params
+            .iter()
+            .find(|param| param.name == name)
+            .map(|param| param.value)
+    }
+
+    /// change `name` like the keys do, saved and logged. an error for a name that isn't there
+    pub fn set(&mut self, name: &str, value: f32) -> Result<(), String> {
+        let param = self
+            .params
+            .iter_mut()
+            .find(|param| param.name == name)
+            .ok_or_else(|| format!("there's no parameter `{}`", name))?;
+        param.value = value;
+        info!("{} = {}", name, value);
+        self.save();
+        Ok(())
+    }
+
+    /// reload the file if it changed since it was last read or written, true if it did
+    pub fn poll(&mut self) -> bool {
+        let modified = self.file_modified();
+        if modified.is_none() || modified <= self.modified {
+            return false;
+        }
+
+        self.reload();
+        info!("reloaded {}", self.path.display());
+        true
+    }
+
+    /// the keys that select and step parameters, true if the event was one of them
+    pub fn process_window_event(&mut self, event: &WindowEvent) -> bool {
+        let keycode = match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => *keycode,
+            _ => return false,
+        };
+        if keycode == PANEL {
+            self.panel = !self.panel;
+            return true;
+        }
+        if self.params.is_empty() {
+            return false;
+        }
+
+        let count = self.params.len();
+        match keycode {
+            VirtualKeyCode::LBracket => self.selected = (self.selected + count - 1) % count,
+            VirtualKeyCode::RBracket => self.selected = (self.selected + 1) % count,
+            VirtualKeyCode::Minus => self.step(-1.),
+            VirtualKeyCode::Equals => self.step(1.),
+            _ => return false,
+        }
+
+        let param = &self.params[self.selected];
+        info!("{} = {}", param.name, param.value);
+        true
+    }
+
+    /// the panel over the finished frame in `view`, when it's shown
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        format: wgpu::TextureFormat,
+    ) {
+        if !self.panel {
+            return;
+        }
+        let (grid, text) = self.layout();
+        self.overlay
+            .get_or_insert_with(|| Overlay::new(device, format))
+            .draw(device, queue, view, grid, &text);
+    }
+
+    // a line per parameter, the selected one marked with `>`, above the keys. as many as fit
+    // in the overlay's text, scrolled so the selected one is among them
+    fn layout(&self) -> (Grid, Vec<u8>) {
+        let mut lines: Vec<String> = self
+            .params
+            .iter()
+            .enumerate()
+            .map(|(index, param)| {
+                let mark = if index == self.selected { '>' } else { ' ' };
+                format!("{} {} = {:.3}", mark, param.name, param.value)
+            })
+            .collect();
+        if lines.is_empty() {
+            lines.push("no parameters".to_string());
+        }
+
+        let columns = lines
+            .iter()
+            .map(String::len)
+            .chain([PANEL_HELP.len()])
+            .max()
+            .unwrap_or(0)
+            .min(console::MAX_TEXT / 2);
+        let shown = (console::MAX_TEXT / columns - 1).min(lines.len());
+        let first = (self.selected + 1).saturating_sub(shown);
+        let mut lines: Vec<&str> = lines[first..first + shown]
+            .iter()
+            .map(String::as_str)
+            .collect();
+        lines.push(PANEL_HELP);
+
+        let mut text = vec![0; lines.len() * columns];
+        for (row, line) in lines.iter().enumerate() {
+            let bytes = &line.as_bytes()[..line.len().min(columns)];
+            text[row * columns..row * columns + bytes.len()].copy_from_slice(bytes);
+        }
+        let grid = Grid {
+            rows: lines.len(),
+            columns,
+            band: false,
+        };
+        (grid, text)
+    }
+
+    fn step(&mut self, direction: f32) {
+        let param = &mut self.params[self.selected];
+        param.value += direction * param.step;
+        self.save();
+    }
+
+    fn file_modified(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    // values from the file win over the ones in memory, names it doesn't mention are kept
+    fn reload(&mut self) {
+        self.modified = self.file_modified();
+        let text = match std::fs::read_to_string(&self.path) {
+            Ok(text) => text,
+            // nothing saved yet
+            Err(_) => return,
+        };
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parsed = line.split_once('=').and_then(|(name, value)| {
+                value
+                    .trim()
+                    .parse::<f32>()
+                    .ok()
+                    .map(|value| (name.trim(), value))
+            });
+            let (name, value) = match parsed {
+                Some(parsed) => parsed,
+                None => {
+                    warn!(
+                        "{}:{}: expected `name = value`, got {:?}",
+                        self.path.display(),
+                        number + 1,
+                        line
+                    );
+                    continue;
+                }
+            };
+
+            match self.params.iter_mut().find(|param| param.name == name) {
+                Some(param) => param.value = value,
+                None => self.params.push(Param {
+                    name: name.to_string(),
+                    value,
+                    step: 0.1,
+                }),
+            }
+        }
+    }
+
+    fn save(&mut self) {
+        let text: String = self
+            .params
+            .iter()
+            .map(|param| format!("{} = {}\n", param.name, param.value))
+            .collect();
+
+        let written = std::fs::create_dir_all(DIR).and_then(|_| std::fs::write(&self.path, text));
+        match written {
+            // our own write isn't a change to reload
+            Ok(()) => self.modified = self.file_modified(),
+            Err(err) => warn!("Fail to save {}: {}", self.path.display(), err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(names: &[&str]) -> Params {
+        Params {
+            path: PathBuf::from(DIR).join("test.txt"),
+            params: names
+                .iter()
+                .map(|name| Param {
+                    name: name.to_string(),
+                    value: 1.,
+                    step: 0.1,
+                })
+                .collect(),
+            selected: 1,
+            modified: None,
+            panel: true,
+            overlay: None,
+        }
+    }
+
+    #[test]
+    fn panel_marks_the_selected_parameter() {
+        let (grid, text) = params(&["speed", "rotate_speed"]).layout();
+        // as wide as the longest line
+        assert_eq!(
+            (grid.rows, grid.columns),
+            (3, "> rotate_speed = 1.000".len())
+        );
+        let row = |row: usize| &text[row * grid.columns..(row + 1) * grid.columns];
+        assert!(row(0).starts_with(b"  speed = 1.000\0"));
+        assert!(row(1).starts_with(b"> rotate_speed = 1.000"));
+        assert!(row(2).starts_with(PANEL_HELP.as_bytes()));
+    }
+
+    #[test]
+    fn panel_scrolls_to_the_selected_parameter() {
+        let names: Vec<String> = (0..1000).map(|i| format!("p{}", i)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let mut params = params(&names);
+        params.selected = 999;
+
+        let (grid, text) = params.layout();
+        assert!(grid.rows * grid.columns <= console::MAX_TEXT);
+        let last = &text[(grid.rows - 2) * grid.columns..(grid.rows - 1) * grid.columns];
+        assert!(last.starts_with(b"> p999 = 1.000"));
+    }
+}
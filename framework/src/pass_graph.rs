@@ -0,0 +1,109 @@
+//! the passes a chapter runs each frame and the textures and buffers flowing between them, as
+//! a graphviz dot file.
+//!
+//! nothing here records or orders passes, a chapter still encodes them itself and describes
+//! them a second time in [`crate::App::pass_graph`]. F9 saves the description, `dot -Tsvg`
+//! turns it into a picture with passes as boxes and resources as ellipses between them.
+//!
+//! ```ignore
+//! fn pass_graph(&self) -> Option<PassGraph> {
+//!     Some(
+//!         PassGraph::new()
+//!             .pass("gbuffer", &[], &["albedo", "normal", "depth"])
+//!             .pass("lighting", &["albedo", "normal", "depth"], &["window"]),
+//!     )
+//! }
+//! ```
+
+use std::fmt::Write;
+
+struct Pass {
+    name: &'static str,
+    reads: Vec<&'static str>,
+    writes: Vec<&'static str>,
+}
+
+#[derive(Default)]
+pub struct PassGraph {
+    passes: Vec<Pass>,
+}
+
+impl PassGraph {
+    pub fn new() -> PassGraph {
+        PassGraph::default()
+    }
+
+    /// the next pass in the order they run, with the resources it reads and writes by name.
+    /// the same name in different passes is the same resource
+    pub fn pass(
+        mut self,
+        name: &'static str,
+        reads: &[&'static str],
+        writes: &[&'static str],
+    ) -> PassGraph {
+        self.passes.push(Pass {
+            name,
+            reads: reads.to_vec(),
+            writes: writes.to_vec(),
+        });
+        self
+    }
+
+    /// the graph in graphviz's dot language, passes numbered in the order they run. every
+    /// write makes a new node for the resource, so a texture drawn into twice shows up twice
+    /// and the graph reads left to right without loops
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph passes {\n    rankdir=LR;\n");
+
+        // the latest node of each resource, 0 until some pass writes it
+        let mut versions: Vec<(&str, usize)> = Vec::new();
+        for (i, pass) in self.passes.iter().enumerate() {
+            let pass_id = format!("\"pass {}\"", i);
+            let _ = writeln!(
+                dot,
+                "    {} [label={:?}, shape=box, style=filled, fillcolor=\"#cfe2f3\"];",
+                pass_id,
+                format!("{}. {}", i + 1, pass.name)
+            );
+
+            for &resource in &pass.reads {
+                let version = match versions.iter().find(|(name, _)| *name == resource) {
+                    Some(&(_, version)) => version,
+                    // read before anything wrote it, something from outside the frame
+                    None => {
+                        versions.push((resource, 0));
+                        resource_node(&mut dot, resource, 0);
+                        0
+                    }
+                };
+                let _ = writeln!(dot, "    \"{}#{}\" -> {};", resource, version, pass_id);
+            }
+            for &resource in &pass.writes {
+                let version = match versions.iter_mut().find(|(name, _)| *name == resource) {
+                    Some((_, version)) => {
+                        *version += 1;
+                        *version
+                    }
+                    None => {
+                        versions.push((resource, 1));
+                        1
+                    }
+                };
+                resource_node(&mut dot, resource, version);
+                let _ = writeln!(dot, "    {} -> \"{}#{}\";", pass_id, resource, version);
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// ids are the name and version, the label only the name
+fn resource_node(dot: &mut String, resource: &str, version: usize) {
+    let _ = writeln!(
+        dot,
+        "    \"{}#{}\" [label={:?}, shape=ellipse];",
+        resource, version, resource
+    );
+}
@@ -0,0 +1,131 @@
+//! one shader file, many pipelines. the file marks optional features with `#ifdef NAME` blocks
+//! (see [`wgsl_check::preprocess`]) and a key, usually a set of flags, says which are on.
+//! [`Permutations`] preprocesses and compiles the source once per key it's asked for and
+//! keeps the pipeline built from it, so materials mix features without a wgsl file for every
+//! mix. the build checks every combination of the file's names already.
+//!
+//! ```ignore
+//! bitflags::bitflags! {
+//!     struct Features: u32 {
+//!         const NORMAL_MAP = 1 << 0;
+//!         const SHADOWS = 1 << 1;
+//!     }
+//! }
+//!
+//! impl Permutation for Features {
+//!     fn defines(&self) -> Vec<&'static str> {
+//!         [(Features::NORMAL_MAP, "NORMAL_MAP"), (Features::SHADOWS, "SHADOWS")]
+//!             .into_iter()
+//!             .filter(|(flag, _)| self.contains(*flag))
+//!             .map(|(_, name)| name)
+//!             .collect()
+//!     }
+//! }
+//!
+//! let mut shaders = Permutations::new("shader.wgsl", include_str!("shader.wgsl"), &[], keys);
+//! let pipeline = shaders.get(device, material.features, |module, features| {
+//!     create_pipeline(device, module)
+//! });
+//! ```
+//!
+//! the keys passed to [`Permutations::new`] are built ahead by [`Permutations::warmup`], from
+//! [`App::warmup`](crate::App::warmup), the rest the first time they're asked for.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+use crate::vertex_layout;
+use crate::warmup::{Pipelines, Progress};
+
+/// a key of [`Permutations`], `defines` are the names its `#ifdef`s see
+pub trait Permutation: Copy + Debug + Eq + Hash {
+    fn defines(&self) -> Vec<&'static str>;
+}
+
+pub struct Permutations<K> {
+    label: &'static str,
+    source: &'static str,
+    // `vertex_layout!` structs spliced in after preprocessing
+    structs: &'static [&'static str],
+    modules: HashMap<K, wgpu::ShaderModule>,
+    pipelines: Pipelines<K>,
+}
+
+impl<K: Permutation> Permutations<K> {
+    /// `structs` go through [`vertex_layout::shader`] like a single shader's, `keys` are the
+    /// permutations to build ahead
+    pub fn new(
+        label: &'static str,
+        source: &'static str,
+        structs: &'static [&'static str],
+        keys: impl IntoIterator<Item = K>,
+    ) -> Permutations<K> {
+        Permutations {
+            label,
+            source,
+            structs,
+            modules: HashMap::new(),
+            pipelines: Pipelines::new(keys),
+        }
+    }
+
+    /// the module for `key`, preprocessed and compiled the first time
+    pub fn module(&mut self, device: &wgpu::Device, key: K) -> &wgpu::ShaderModule {
+        let (label, source, structs) = (self.label, self.source, self.structs);
+        self.modules
+            .entry(key)
+            .or_insert_with(|| compile(device, label, source, structs, key))
+    }
+
+    /// build some of the keys given to [`Permutations::new`], see
+    /// [`Pipelines::step`](crate::warmup::Pipelines::step)
+    pub fn warmup(
+        &mut self,
+        device: &wgpu::Device,
+        mut build: impl FnMut(&wgpu::ShaderModule, K) -> wgpu::RenderPipeline,
+    ) -> Option<Progress> {
+        let (label, source, structs) = (self.label, self.source, self.structs);
+        let modules = &mut self.modules;
+        self.pipelines.step(|&key| {
+            let module = modules
+                .entry(key)
+                .or_insert_with(|| compile(device, label, source, structs, key));
+            build(module, key)
+        })
+    }
+
+    /// the pipeline for `key`, `build` makes it from the key's module when it isn't there yet
+    pub fn get(
+        &mut self,
+        device: &wgpu::Device,
+        key: K,
+        build: impl FnOnce(&wgpu::ShaderModule, K) -> wgpu::RenderPipeline,
+    ) -> &wgpu::RenderPipeline {
+        let (label, source, structs) = (self.label, self.source, self.structs);
+        let modules = &mut self.modules;
+        self.pipelines.get(key, |&key| {
+            let module = modules
+                .entry(key)
+                .or_insert_with(|| compile(device, label, source, structs, key));
+            build(module, key)
+        })
+    }
+
+    /// the pipeline for `key` if it's built, see [`Pipelines::built`]
+    pub fn built(&self, key: K) -> Option<&wgpu::RenderPipeline> {
+        self.pipelines.built(&key)
+    }
+}
+
+fn compile<K: Permutation>(
+    device: &wgpu::Device,
+    label: &str,
+    source: &str,
+    structs: &[&str],
+    key: K,
+) -> wgpu::ShaderModule {
+    let source = wgsl_check::preprocess(source, &key.defines())
+        .unwrap_or_else(|err| panic!("Fail to preprocess {}: {}", label, err));
+    vertex_layout::shader(device, &format!("{} {:?}", label, key), &source, structs)
+}
@@ -0,0 +1,104 @@
+//! pipeline statistics queries: how many vertex shader runs, primitives that survived
+//! clipping and culling, and fragment shader runs a render pass took.
+//!
+//! needs [`wgpu::Features::PIPELINE_STATISTICS_QUERY`], list it in
+//! [`crate::App::optional_features`] and [`PipelineStats::new`] returns `None` when it's missing.
+
+// the order the results come back in, lowest flag bit first
+const STATISTICS: wgpu::PipelineStatisticsTypes =
+    wgpu::PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS
+        .union(wgpu::PipelineStatisticsTypes::CLIPPER_PRIMITIVES_OUT)
+        .union(wgpu::PipelineStatisticsTypes::FRAGMENT_SHADER_INVOCATIONS);
+const RESULT_SIZE: wgpu::BufferAddress = 3 * std::mem::size_of::<u64>() as wgpu::BufferAddress;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PipelineStatistics {
+    pub vertex_invocations: u64,
+    /// primitives that made it past clipping and face culling
+    pub clipper_primitives: u64,
+    /// includes the helper invocations that fill out 2x2 quads, overdraw shows up here
+    pub fragment_invocations: u64,
+}
+
+pub struct PipelineStats {
+    query_set: wgpu::QuerySet,
+    resolve_buf: wgpu::Buffer,
+    read_buf: wgpu::Buffer,
+}
+
+impl PipelineStats {
+    pub fn new(device: &wgpu::Device) -> Option<PipelineStats> {
+        if !device
+            .features()
+            .contains(wgpu::Features::PIPELINE_STATISTICS_QUERY)
+        {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("pipeline statistics"),
+            ty: wgpu::QueryType::PipelineStatistics(STATISTICS),
+            count: 1,
+        });
+
+        // resolving writes into the buffer like a copy does
+        let resolve_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Query Resolve Buffer"),
+            size: RESULT_SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        // query results can't be mapped directly, they go through a copy
+        let read_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Query Read Buffer"),
+            size: RESULT_SIZE,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Some(PipelineStats {
+            query_set,
+            resolve_buf,
+            read_buf,
+        })
+    }
+
+    /// start counting, everything drawn in `rpass` until [`PipelineStats::end`] is counted
+    pub fn begin(&self, rpass: &mut wgpu::RenderPass) {
+        rpass.begin_pipeline_statistics_query(&self.query_set, 0);
+    }
+
+    pub fn end(&self, rpass: &mut wgpu::RenderPass) {
+        rpass.end_pipeline_statistics_query();
+    }
+
+    /// copy the counts somewhere readable, record after the pass that was measured
+    pub fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        encoder.resolve_query_set(&self.query_set, 0..1, &self.resolve_buf, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buf, 0, &self.read_buf, 0, RESULT_SIZE);
+    }
+
+    /// blocks until the gpu is done with the submitted work, call after `queue.submit`.
+    /// that stalls the frame, so read every now and then rather than every frame
+    pub fn read(&self, device: &wgpu::Device) -> PipelineStatistics {
+        let slice = self.read_buf.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Fail to map query read buffer")
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        let statistics = {
+            let data = slice.get_mapped_range();
+            let counts: &[u64] = bytemuck::cast_slice(&data);
+            PipelineStatistics {
+                vertex_invocations: counts[0],
+                clipper_primitives: counts[1],
+                fragment_invocations: counts[2],
+            }
+        };
+        self.read_buf.unmap();
+
+        statistics
+    }
+}
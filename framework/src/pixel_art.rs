@@ -0,0 +1,123 @@
+//! low resolution offscreen target for pixel art.
+//!
+//! draw the scene into [`PixelArtTarget::view`] at its own small resolution, then
+//! [`PixelArtTarget::blit`] scales it up by a whole number with nearest sampling and
+//! centers it in the window, the leftover space stays black.
+
+use crate::blit::Blit;
+
+pub struct PixelArtTarget {
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    view: wgpu::TextureView,
+    blit: Blit,
+    bind_group: wgpu::BindGroup,
+}
+
+impl PixelArtTarget {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+    /// `surface_format` is the format of the window the target gets blitted to
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        surface_format: wgpu::TextureFormat,
+    ) -> PixelArtTarget {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("pixel_art_texture"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // nearest keeps every texel a sharp square however far it is scaled up
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("pixel_art_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let blit = Blit::new(device, surface_format);
+        let bind_group = blit.create_bind_group(device, &view, &sampler);
+
+        PixelArtTarget {
+            width,
+            height,
+            format: Self::FORMAT,
+            view,
+            blit,
+            bind_group,
+        }
+    }
+
+    /// render target for the low resolution scene, its format is [`PixelArtTarget::FORMAT`]
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// whole number scale and centered rect (x, y, width, height) of the target in the window.
+    /// a window smaller than the target falls back to a plain fit, there is no whole number below 1
+    pub fn letterbox(&self, window_width: u32, window_height: u32) -> (u32, u32, u32, u32) {
+        let scale = (window_width / self.width).min(window_height / self.height);
+
+        let (width, height) = if scale >= 1 {
+            (self.width * scale, self.height * scale)
+        } else {
+            let fit = (window_width as f32 / self.width as f32)
+                .min(window_height as f32 / self.height as f32);
+            (
+                ((self.width as f32 * fit) as u32).max(1),
+                ((self.height as f32 * fit) as u32).max(1),
+            )
+        };
+
+        (
+            (window_width - width) / 2,
+            (window_height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    /// scale the target up into `surface_view`, clearing the bars around it to black
+    pub fn blit(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        surface_view: &wgpu::TextureView,
+        surface_width: u32,
+        surface_height: u32,
+    ) {
+        let (x, y, width, height) = self.letterbox(surface_width, surface_height);
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("pixel art blit"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: surface_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        rpass.set_viewport(x as f32, y as f32, width as f32, height as f32, 0., 1.);
+        self.blit.draw(&mut rpass, &self.bind_group);
+    }
+}
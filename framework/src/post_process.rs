@@ -0,0 +1,421 @@
+//! a stack of fullscreen effects over the finished scene. the scene draws into
+//! [`PostProcessChain::input`], then every enabled effect reads what the one before it wrote,
+//! back and forth between two window sized targets, and the last one draws into the output.
+//!
+//! the built in [`Effect`]s are in post_process.wgsl, [`PostProcessChain::push_shader`] adds
+//! one from a chapter's own shader. both targets follow the window in
+//! [`PostProcessChain::resize`], nothing holds on to their old bind groups.
+//!
+//! the effects that can be turned up or down read [`Strengths`] from a uniform, change
+//! [`PostProcessChain::strengths`] and call [`PostProcessChain::update`] before `render`.
+//!
+//! ```ignore
+//! let mut chain = PostProcessChain::new(device, config.format, config.width, config.height);
+//! chain.push(device, Effect::Grayscale);
+//! chain.push(device, Effect::Vignette);
+//! chain.strengths.vignette = 0.5;
+//! chain.update(queue);
+//! // every frame, the scene pass draws into `chain.input()`, then
+//! chain.render(&mut encoder, frame.view);
+//! ```
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{include_wgsl, util::DeviceExt};
+
+use crate::{
+    pass_graph::PassGraph,
+    screenshot::DumpTarget,
+    targets::{TargetDesc, Targets},
+    watchdog,
+};
+
+/// the effects post_process.wgsl comes with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Effect {
+    Grayscale,
+    Invert,
+    /// wide and cheap, push it twice for a smooth one
+    Blur,
+    /// darker toward the corners, by [`Strengths::vignette`]
+    Vignette,
+    /// red and blue drift apart toward the corners, by [`Strengths::chromatic_aberration`]
+    ChromaticAberration,
+}
+
+impl Effect {
+    pub fn name(self) -> &'static str {
+        match self {
+            Effect::Grayscale => "grayscale",
+            Effect::Invert => "invert",
+            Effect::Blur => "blur",
+            Effect::Vignette => "vignette",
+            Effect::ChromaticAberration => "chromatic aberration",
+        }
+    }
+
+    fn entry_point(self) -> &'static str {
+        match self {
+            Effect::Grayscale => "fs_grayscale",
+            Effect::Invert => "fs_invert",
+            Effect::Blur => "fs_blur",
+            Effect::Vignette => "fs_vignette",
+            Effect::ChromaticAberration => "fs_chromatic_aberration",
+        }
+    }
+}
+
+/// how strong the effects that have a strength are, 0 leaves the image as it is and 1 is
+/// how they were made to look
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Strengths {
+    pub vignette: f32,
+    pub chromatic_aberration: f32,
+}
+
+impl Default for Strengths {
+    fn default() -> Self {
+        Strengths {
+            vignette: 1.,
+            chromatic_aberration: 1.,
+        }
+    }
+}
+
+// matches `Strengths` in post_process.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct StrengthsUniform {
+    vignette: f32,
+    chromatic_aberration: f32,
+    _padding: [f32; 2],
+}
+
+impl From<Strengths> for StrengthsUniform {
+    fn from(strengths: Strengths) -> Self {
+        StrengthsUniform {
+            vignette: strengths.vignette,
+            chromatic_aberration: strengths.chromatic_aberration,
+            _padding: [0.; 2],
+        }
+    }
+}
+
+struct Pass {
+    name: &'static str,
+    pipeline: wgpu::RenderPipeline,
+    enabled: bool,
+}
+
+pub struct PostProcessChain {
+    /// what the effects read from their uniform after the next [`PostProcessChain::update`]
+    pub strengths: Strengths,
+    strengths_buffer: wgpu::Buffer,
+    format: wgpu::TextureFormat,
+    layout: wgpu::BindGroupLayout,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    // what runs when every pass is off, the scene still has to reach the output
+    copy: wgpu::RenderPipeline,
+    passes: Vec<Pass>,
+    // the ping pong pair, the scene goes into the first
+    targets: Targets,
+    bind_groups: [wgpu::BindGroup; 2],
+}
+
+impl PostProcessChain {
+    /// `format` is the format of the output `render` draws into, the targets have it too, so
+    /// the scene pipelines draw into the input as they would into the output
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> PostProcessChain {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("post_process_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<StrengthsUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let strengths = Strengths::default();
+        let strengths_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Post Process Strengths Buffer"),
+            contents: bytemuck::bytes_of(&StrengthsUniform::from(strengths)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("post_process.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let copy = create_pipeline(device, &pipeline_layout, &shader, "fs_copy", format);
+
+        let targets = Targets::new(
+            device,
+            width,
+            height,
+            vec![
+                TargetDesc::new(format, 1, "post process ping"),
+                TargetDesc::new(format, 1, "post process pong"),
+            ],
+        );
+        let bind_groups = create_bind_groups(device, &layout, &targets, &strengths_buffer);
+
+        PostProcessChain {
+            strengths,
+            strengths_buffer,
+            format,
+            layout,
+            pipeline_layout,
+            shader,
+            copy,
+            passes: Vec::new(),
+            targets,
+            bind_groups,
+        }
+    }
+
+    /// add a built in effect after the others, enabled. returns its index
+    pub fn push(&mut self, device: &wgpu::Device, effect: Effect) -> usize {
+        let pipeline = create_pipeline(
+            device,
+            &self.pipeline_layout,
+            &self.shader,
+            effect.entry_point(),
+            self.format,
+        );
+        self.push_pipeline(effect.name(), pipeline)
+    }
+
+    /// add an effect from a chapter's own `shader`, with a `vs_main` like post_process.wgsl's
+    /// and a fragment `entry_point` reading the source from @group(0) @binding(0) and (1).
+    /// @binding(2) holds the strengths, if it wants them. returns its index
+    pub fn push_shader(
+        &mut self,
+        device: &wgpu::Device,
+        name: &'static str,
+        shader: &wgpu::ShaderModule,
+        entry_point: &str,
+    ) -> usize {
+        let pipeline = create_pipeline(
+            device,
+            &self.pipeline_layout,
+            shader,
+            entry_point,
+            self.format,
+        );
+        self.push_pipeline(name, pipeline)
+    }
+
+    fn push_pipeline(&mut self, name: &'static str, pipeline: wgpu::RenderPipeline) -> usize {
+        self.passes.push(Pass {
+            name,
+            pipeline,
+            enabled: true,
+        });
+        self.passes.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    pub fn name(&self, index: usize) -> &'static str {
+        self.passes[index].name
+    }
+
+    pub fn is_enabled(&self, index: usize) -> bool {
+        self.passes[index].enabled
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        self.passes[index].enabled = enabled;
+    }
+
+    /// the names of the enabled effects in the order they run
+    pub fn enabled(&self) -> Vec<&'static str> {
+        self.passes
+            .iter()
+            .filter(|pass| pass.enabled)
+            .map(|pass| pass.name)
+            .collect()
+    }
+
+    /// where the scene draws, a texture of the output's format and the window's size
+    pub fn input(&self) -> &wgpu::TextureView {
+        &self.targets[0].view
+    }
+
+    /// follow the window, the targets and their bind groups are made again on a new size
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) {
+        if self.targets.resize(device, width, height) {
+            self.bind_groups =
+                create_bind_groups(device, &self.layout, &self.targets, &self.strengths_buffer);
+        }
+    }
+
+    /// upload `strengths`, call after changing them and before `render`
+    pub fn update(&self, queue: &wgpu::Queue) {
+        watchdog::write_buffer(
+            queue,
+            &self.strengths_buffer,
+            0,
+            bytemuck::bytes_of(&StrengthsUniform::from(self.strengths)),
+        );
+    }
+
+    /// every enabled effect in turn, the last into `output`. with none enabled the input is
+    /// copied over
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, output: &wgpu::TextureView) {
+        let mut pipelines: Vec<&wgpu::RenderPipeline> = self
+            .passes
+            .iter()
+            .filter(|pass| pass.enabled)
+            .map(|pass| &pass.pipeline)
+            .collect();
+        if pipelines.is_empty() {
+            pipelines.push(&self.copy);
+        }
+
+        // which target holds the image so far
+        let mut source = 0;
+        for (i, pipeline) in pipelines.iter().enumerate() {
+            let destination = if i + 1 == pipelines.len() {
+                output
+            } else {
+                &self.targets[1 - source].view
+            };
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("post process pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: destination,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // every pixel gets written, nothing to clear
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, &self.bind_groups[source], &[]);
+            rpass.draw(0..3, 0..1);
+            source = 1 - source;
+        }
+    }
+
+    /// the passes `render` adds to `graph` after the scene drew the input, ending in `output`
+    pub fn pass_graph(&self, mut graph: PassGraph, output: &'static str) -> PassGraph {
+        let mut names = self.enabled();
+        if names.is_empty() {
+            names.push("copy");
+        }
+        let targets = ["post process ping", "post process pong"];
+        for (i, name) in names.iter().enumerate() {
+            let destination = if i + 1 == names.len() {
+                output
+            } else {
+                targets[(i + 1) % 2]
+            };
+            graph = graph.pass(name, &[targets[i % 2]], &[destination]);
+        }
+        graph
+    }
+
+    /// both targets, for [`crate::App::dump_targets`]. the second only holds something once
+    /// two effects are on
+    pub fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        self.targets.dump_targets()
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    entry_point: &str,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point,
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+// [0] reads ping, [1] reads pong
+fn create_bind_groups(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    targets: &Targets,
+    strengths_buffer: &wgpu::Buffer,
+) -> [wgpu::BindGroup; 2] {
+    [0, 1].map(|i| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("post_process_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&targets[i].view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&targets[i].sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: strengths_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    })
+}
@@ -0,0 +1,93 @@
+// the built in effects of `PostProcessChain`, one fragment entry point each over a fullscreen
+// triangle. an effect of a chapter's own has to bind its source the same way, @group(0)
+// @binding(0) the texture and @binding(1) the sampler, vs_main can be copied from here.
+// @binding(2) has the strengths of those that can be turned up or down
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragInput.tex_coord = uv;
+    return fragInput;
+}
+
+// matches `StrengthsUniform` in post_process.rs, 0 is off and 1 the look they were made for
+struct Strengths {
+    vignette: f32,
+    chromatic_aberration: f32,
+};
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var<uniform> strengths: Strengths;
+
+fn source(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(t_source, s_source, uv, 0.0);
+}
+
+// the last pass when every effect is off, the chain still has to end in the output
+@fragment
+fn fs_copy(input: FragInput) -> @location(0) vec4<f32> {
+    return source(input.tex_coord);
+}
+
+@fragment
+fn fs_grayscale(input: FragInput) -> @location(0) vec4<f32> {
+    let color = source(input.tex_coord);
+    // weights for linear values, what the sampler hands back from srgb targets
+    let luminance = dot(color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return vec4<f32>(vec3<f32>(luminance), color.a);
+}
+
+@fragment
+fn fs_invert(input: FragInput) -> @location(0) vec4<f32> {
+    let color = source(input.tex_coord);
+    return vec4<f32>(1.0 - color.rgb, color.a);
+}
+
+// 3x3 taps two texels apart, wide for 9 samples but it skips every other texel. stacking it
+// twice fills those in and gets close to a gaussian
+@fragment
+fn fs_blur(input: FragInput) -> @location(0) vec4<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_source));
+    var sum = vec4<f32>(0.0);
+    for (var y = -1; y <= 1; y = y + 1) {
+        for (var x = -1; x <= 1; x = x + 1) {
+            sum = sum + source(input.tex_coord + vec2<f32>(f32(x), f32(y)) * texel * 2.0);
+        }
+    }
+    return sum / 9.0;
+}
+
+// darker toward the corners, nothing changes inside the middle half. above 1 the corners go
+// black sooner
+@fragment
+fn fs_vignette(input: FragInput) -> @location(0) vec4<f32> {
+    let color = source(input.tex_coord);
+    let distance = length(input.tex_coord - 0.5) * 1.414;
+    let falloff = max(1.0 - smoothstep(0.5, 1.1, distance) * strengths.vignette, 0.0);
+    return vec4<f32>(color.rgb * falloff, color.a);
+}
+
+// a lens that bends red less than blue: red is sampled a little closer to the center, blue a
+// little further out, green where it is. nothing in the middle, at 1 they're 1% of the screen
+// apart at the edges
+@fragment
+fn fs_chromatic_aberration(input: FragInput) -> @location(0) vec4<f32> {
+    let offset = (input.tex_coord - 0.5) * 0.01 * strengths.chromatic_aberration;
+    let color = source(input.tex_coord);
+    let red = source(input.tex_coord - offset).r;
+    let blue = source(input.tex_coord + offset).b;
+    return vec4<f32>(red, color.g, blue, color.a);
+}
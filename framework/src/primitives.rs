@@ -0,0 +1,268 @@
+//! standard shapes made from a few numbers instead of typed-in vertex arrays: cube, sphere,
+//! plane, torus and cylinder, each with positions, normals, uvs and `u16` indices. triangles
+//! wind counter-clockwise seen from outside, wgpu's default front face, and the shapes sit
+//! around the origin at unit size. [`Mesh::transformed`] moves and scales one,
+//! [`Mesh::append`] puts several into one buffer.
+//!
+//! [`Vertex`] can go to the gpu as it is, a chapter whose vertices carry more, a color or a
+//! tangent, maps them into its own type:
+//!
+//! ```ignore
+//! let mut mesh = Mesh::plane(8., 8);
+//! mesh.append(Mesh::sphere(32, 16).transformed(Mat4::from_translation(Vec3::Y)));
+//! let vertices: Vec<Vertex> = mesh
+//!     .vertices
+//!     .iter()
+//!     .map(|v| vertex(v.pos, v.normal, color))
+//!     .collect();
+//! ```
+
+use std::f32::consts::PI;
+
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat3, Mat4, Vec2, Vec3};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+pub struct Vertex {
+    pub pos: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+}
+
+crate::vertex_layout! {
+    Vertex as "VertexInput", Vertex {
+        0 => pos: Float32x3,
+        1 => normal: Float32x3,
+        2 => uv: Float32x2,
+    }
+}
+
+fn vertex(pos: Vec3, normal: Vec3, uv: Vec2) -> Vertex {
+    Vertex {
+        pos: pos.to_array(),
+        normal: normal.to_array(),
+        uv: uv.to_array(),
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u16>,
+}
+
+impl Mesh {
+    /// a cube from -0.5 to 0.5, each face split into `subdivisions` x `subdivisions` quads
+    /// with its own normal so the edges stay sharp. every face has the whole 0..1 uv square
+    pub fn cube(subdivisions: u32) -> Mesh {
+        // normal, then the directions u and v run in, seen from outside
+        let faces = [
+            (Vec3::X, Vec3::NEG_Z, Vec3::NEG_Y),
+            (Vec3::NEG_X, Vec3::Z, Vec3::NEG_Y),
+            (Vec3::Y, Vec3::X, Vec3::Z),
+            (Vec3::NEG_Y, Vec3::X, Vec3::NEG_Z),
+            (Vec3::Z, Vec3::X, Vec3::NEG_Y),
+            (Vec3::NEG_Z, Vec3::NEG_X, Vec3::NEG_Y),
+        ];
+        let mut mesh = Mesh::default();
+        for (normal, u, v) in faces {
+            mesh.append(grid(subdivisions, subdivisions, |uv| {
+                let pos = normal * 0.5 + u * (uv.x - 0.5) + v * (uv.y - 0.5);
+                vertex(pos, normal, uv)
+            }));
+        }
+        mesh
+    }
+
+    /// a sphere of radius 1, `sectors` around the y axis and `stacks` from pole to pole.
+    /// u runs once around, v from the north pole down, the texture isn't mirrored seen from
+    /// outside
+    pub fn sphere(sectors: u32, stacks: u32) -> Mesh {
+        // the rows at the poles collapse into points, their triangles have no area
+        grid(sectors, stacks, |uv| {
+            let (sin_theta, cos_theta) = (uv.y * PI).sin_cos();
+            let (sin_phi, cos_phi) = (uv.x * 2. * PI).sin_cos();
+            let normal = Vec3::new(-sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+            vertex(normal, normal, uv)
+        })
+    }
+
+    /// a flat square of `size` on the xz plane facing up, split into `subdivisions` x
+    /// `subdivisions` quads. uv covers 0..1 once, scale it for a tiling texture
+    pub fn plane(size: f32, subdivisions: u32) -> Mesh {
+        grid(subdivisions, subdivisions, |uv| {
+            let pos = Vec3::new(uv.x - 0.5, 0., uv.y - 0.5) * size;
+            vertex(pos, Vec3::Y, uv)
+        })
+    }
+
+    /// a ring around the y axis, `radius` to the middle of the tube and `tube_radius` across
+    /// it. `sectors` go around the ring, `sides` around the tube
+    pub fn torus(radius: f32, tube_radius: f32, sectors: u32, sides: u32) -> Mesh {
+        grid(sectors, sides, |uv| {
+            let (sin_phi, cos_phi) = (uv.x * 2. * PI).sin_cos();
+            let (sin_theta, cos_theta) = (uv.y * 2. * PI).sin_cos();
+            let around = Vec3::new(cos_phi, 0., sin_phi);
+            let normal = around * cos_theta + Vec3::Y * sin_theta;
+            vertex(around * radius + normal * tube_radius, normal, uv)
+        })
+    }
+
+    /// a capped cylinder of radius 0.5 from y = -0.5 to 0.5, `sectors` around. the side takes
+    /// the whole uv square, the caps a disk in it each
+    pub fn cylinder(sectors: u32) -> Mesh {
+        let mut mesh = grid(sectors, 1, |uv| {
+            let (sin_phi, cos_phi) = (uv.x * 2. * PI).sin_cos();
+            let normal = Vec3::new(cos_phi, 0., -sin_phi);
+            vertex(normal * 0.5 + Vec3::Y * (0.5 - uv.y), normal, uv)
+        });
+        for normal in [Vec3::Y, Vec3::NEG_Y] {
+            let center = mesh.vertices.len() as u16;
+            mesh.vertices
+                .push(vertex(normal * 0.5, normal, Vec2::splat(0.5)));
+            for sector in 0..=sectors {
+                let (sin_phi, cos_phi) = (sector as f32 / sectors as f32 * 2. * PI).sin_cos();
+                let rim = Vec3::new(cos_phi, 0., -sin_phi) * 0.5;
+                let uv = Vec2::new(rim.x, rim.z) + 0.5;
+                mesh.vertices.push(vertex(rim + normal * 0.5, normal, uv));
+            }
+            for sector in 0..sectors as u16 {
+                let (a, b) = (center + 1 + sector, center + 2 + sector);
+                // the rim runs counter-clockwise seen from above, the bottom turns it around
+                if normal.y > 0. {
+                    mesh.indices.extend_from_slice(&[center, a, b]);
+                } else {
+                    mesh.indices.extend_from_slice(&[center, b, a]);
+                }
+            }
+        }
+        mesh
+    }
+
+    /// positions moved by `transform`, normals turned with it and kept unit length, even when
+    /// the scale isn't uniform. a mirroring transform flips the winding back
+    pub fn transformed(mut self, transform: Mat4) -> Mesh {
+        let normal_matrix = Mat3::from_mat4(transform).inverse().transpose();
+        for vertex in &mut self.vertices {
+            vertex.pos = transform
+                .transform_point3(Vec3::from(vertex.pos))
+                .to_array();
+            vertex.normal = (normal_matrix * Vec3::from(vertex.normal))
+                .normalize()
+                .to_array();
+        }
+        if transform.determinant() < 0. {
+            for triangle in self.indices.chunks_exact_mut(3) {
+                triangle.swap(1, 2);
+            }
+        }
+        self
+    }
+
+    /// `other`'s triangles added after these, its indices moved past these vertices
+    pub fn append(&mut self, other: Mesh) {
+        let base = self.vertices.len();
+        assert!(
+            base + other.vertices.len() <= u16::MAX as usize + 1,
+            "Fail to fit the mesh in u16 indices"
+        );
+        self.vertices.extend(other.vertices);
+        self.indices
+            .extend(other.indices.iter().map(|&index| index + base as u16));
+    }
+}
+
+// (columns + 1) x (rows + 1) vertices made by `vertex` from their uv, two triangles a cell.
+// a cell's triangles face the way v x u points, `vertex` lays u and v out so that's outwards
+fn grid(columns: u32, rows: u32, mut vertex: impl FnMut(Vec2) -> Vertex) -> Mesh {
+    let (columns, rows) = (columns.max(1), rows.max(1));
+    assert!(
+        (columns + 1) * (rows + 1) <= u16::MAX as u32 + 1,
+        "Fail to fit the mesh in u16 indices"
+    );
+    let mut mesh = Mesh::default();
+    for row in 0..=rows {
+        for column in 0..=columns {
+            let uv = Vec2::new(column as f32 / columns as f32, row as f32 / rows as f32);
+            mesh.vertices.push(vertex(uv));
+        }
+    }
+    for row in 0..rows {
+        for column in 0..columns {
+            let top = (row * (columns + 1) + column) as u16;
+            let bottom = top + columns as u16 + 1;
+            mesh.indices
+                .extend_from_slice(&[top, bottom, top + 1, top + 1, bottom, bottom + 1]);
+        }
+    }
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // every triangle with an area faces the way its vertices' normals point
+    fn assert_faces_out(mesh: &Mesh) {
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]);
+            let face = (Vec3::from(b.pos) - Vec3::from(a.pos))
+                .cross(Vec3::from(c.pos) - Vec3::from(a.pos));
+            if face.length() < 1e-6 {
+                continue;
+            }
+            let normal = Vec3::from(a.normal) + Vec3::from(b.normal) + Vec3::from(c.normal);
+            assert!(face.dot(normal) > 0., "{:?} faces inwards", triangle);
+        }
+    }
+
+    #[test]
+    fn shapes_face_outwards() {
+        for mesh in [
+            Mesh::cube(2),
+            Mesh::sphere(16, 8),
+            Mesh::plane(4., 3),
+            Mesh::torus(1., 0.25, 16, 8),
+            Mesh::cylinder(12),
+        ] {
+            assert_faces_out(&mesh);
+            assert!(mesh
+                .indices
+                .iter()
+                .all(|&index| (index as usize) < mesh.vertices.len()));
+            for vertex in &mesh.vertices {
+                assert!((Vec3::from(vertex.normal).length() - 1.).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn tessellation_sets_the_counts() {
+        let cube = Mesh::cube(2);
+        assert_eq!(
+            (cube.vertices.len(), cube.indices.len()),
+            (6 * 9, 6 * 4 * 6)
+        );
+        let sphere = Mesh::sphere(16, 8);
+        assert_eq!(
+            (sphere.vertices.len(), sphere.indices.len()),
+            (17 * 9, 16 * 8 * 6)
+        );
+        let cylinder = Mesh::cylinder(12);
+        assert_eq!(cylinder.indices.len(), 12 * 6 + 2 * 12 * 3);
+    }
+
+    #[test]
+    fn transforms_and_appends() {
+        let mut mesh = Mesh::plane(1., 1);
+        mesh.append(Mesh::cube(1).transformed(
+            Mat4::from_scale(Vec3::new(1., -2., 1.)) * Mat4::from_translation(Vec3::Y),
+        ));
+        assert_faces_out(&mesh);
+        assert_eq!(mesh.indices[6], 4);
+        assert!(mesh.vertices[4..]
+            .iter()
+            .all(|vertex| vertex.pos[1] <= -1. + 1e-6));
+    }
+}
@@ -0,0 +1,544 @@
+//! a demo reel: several [`App`]s in one window, cross-fading from one to the next.
+//!
+//! every scene draws into one of two offscreen slots, the same way it would draw into the
+//! window, and a composite pass blends the slot fading out with the one fading in. all scenes
+//! are initialised up front, `App::init` wants the adapter and only the driver owns that, and
+//! only the showing scene and, during a fade, the incoming one get rendered.
+//!
+//! page down and page up fade to the next and previous scene right away, home turns the
+//! automatic advance every [`SCENE_SECONDS`] off and on. the fades and the advance run on the
+//! [`crate::timeline`] like the scenes do. the other events go to the showing scene, so each
+//! chapter's own keys still work. `load` in the console fades to a scene by its number or a
+//! part of its title.
+//!
+//! ```ignore
+//! struct Chapters;
+//!
+//! impl Playlist for Chapters {
+//!     fn scenes() -> Vec<Entry> {
+//!         vec![Entry::new::<t17_cube::Cube>(t17_cube::TITLE)]
+//!     }
+//! }
+//!
+//! framework::run::<Reel<Chapters>>("demo reel");
+//! ```
+
+use std::marker::PhantomData;
+
+use tracing::{info, warn};
+use wgpu::{include_wgsl, util::DeviceExt};
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use crate::console::Commands;
+use crate::texture::{DepthTexture, Texture};
+use crate::warmup::Progress;
+use crate::{watchdog, App, Frame};
+
+/// how long a scene shows before the reel moves on
+pub const SCENE_SECONDS: f32 = 8.;
+/// how long the cross-fade between two scenes takes
+pub const FADE_SECONDS: f32 = 1.5;
+
+/// the per frame half of [`App`], with `self` so different apps fit in one list
+pub trait Scene {
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    );
+    fn update(&mut self, window: &Window, event: WindowEvent);
+    fn device_event(&mut self, event: DeviceEvent);
+    fn warmup(&mut self, device: &wgpu::Device) -> Option<Progress>;
+    fn render(&mut self, frame: &Frame, device: &wgpu::Device, queue: &wgpu::Queue);
+}
+
+impl<A: App> Scene for A {
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        App::resize(self, config, device, queue)
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        App::update(self, window, event)
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        App::device_event(self, event)
+    }
+
+    fn warmup(&mut self, device: &wgpu::Device) -> Option<Progress> {
+        App::warmup(self, device)
+    }
+
+    fn render(&mut self, frame: &Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        App::render(self, frame, device, queue)
+    }
+}
+
+type InitScene =
+    fn(&wgpu::SurfaceConfiguration, &wgpu::Adapter, &wgpu::Device, &wgpu::Queue) -> Box<dyn Scene>;
+
+/// one [`App`] in the reel, with the static half of the trait captured
+pub struct Entry {
+    pub title: String,
+    depth_buffer: bool,
+    required_features: wgpu::Features,
+    optional_features: wgpu::Features,
+    init: InitScene,
+}
+
+impl Entry {
+    /// `title` is shown in the window while the scene is
+    pub fn new<A: App>(title: &str) -> Entry {
+        Entry {
+            title: title.to_string(),
+            depth_buffer: A::depth_buffer(),
+            required_features: A::required_features(),
+            optional_features: A::optional_features(),
+            init: init_scene::<A>,
+        }
+    }
+}
+
+fn init_scene<A: App>(
+    config: &wgpu::SurfaceConfiguration,
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+) -> Box<dyn Scene> {
+    Box::new(A::init(config, adapter, device, queue))
+}
+
+/// the scenes a [`Reel`] cycles through, in order
+pub trait Playlist: 'static {
+    fn scenes() -> Vec<Entry>;
+}
+
+// color and depth a scene draws into instead of the window
+struct Slot {
+    color: Texture,
+    depth: DepthTexture,
+}
+
+impl Slot {
+    fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> Slot {
+        Slot {
+            color: Texture::create_render_target(
+                device,
+                config.width,
+                config.height,
+                config.format,
+                "reel_slot",
+            ),
+            depth: DepthTexture::new(device, config),
+        }
+    }
+}
+
+pub struct Reel<P: Playlist> {
+    entries: Vec<Entry>,
+    /// one per entry
+    scenes: Vec<Box<dyn Scene>>,
+    current: usize,
+    /// the slot `current` draws into, the incoming scene gets the other one
+    current_slot: usize,
+    /// the entry fading in over `current` and when it started to
+    incoming: Option<(usize, f32)>,
+    /// when `current` finished fading in
+    shown_at: f32,
+    /// [`Frame::time`] as of the last frame, the fades and the advance follow the timeline
+    time: f32,
+    auto_advance: bool,
+    /// page up or down since the last frame, taken by `render`
+    requested_step: Option<isize>,
+    /// set on a switch, the window is only reachable from `update`
+    title_changed: bool,
+    /// the scene [`App::warmup`] is warming up, all of them are done before the first frame
+    warming: usize,
+    slots: [Slot; 2],
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    fade_buffer: wgpu::Buffer,
+    /// `bind_groups[i]` blends from slot `i` to the other one
+    bind_groups: [wgpu::BindGroup; 2],
+    _playlist: PhantomData<P>,
+}
+
+impl<P: Playlist> Reel<P> {
+    // start fading to the entry `step` places away, a fade still running is finished first
+    fn advance(&mut self, step: isize) {
+        self.finish_fade();
+        let count = self.entries.len() as isize;
+        let entry = (self.current as isize + step).rem_euclid(count) as usize;
+        info!("reel: {}", self.entries[entry].title);
+        self.incoming = Some((entry, self.time));
+        self.title_changed = true;
+    }
+
+    fn finish_fade(&mut self) {
+        if let Some((incoming, _)) = self.incoming.take() {
+            self.current = incoming;
+            self.current_slot = 1 - self.current_slot;
+            self.shown_at = self.time;
+        }
+    }
+
+    // the entry `name` is the number, from 1, or a part of the title of
+    fn find(&self, name: &str) -> Result<usize, String> {
+        match name.parse::<usize>() {
+            Ok(number) if (1..=self.entries.len()).contains(&number) => Ok(number - 1),
+            Ok(_) => Err(format!("there are {} scenes", self.entries.len())),
+            Err(_) => self
+                .entries
+                .iter()
+                .position(|entry| entry.title.contains(name))
+                .ok_or_else(|| format!("no scene's title has `{}` in it", name)),
+        }
+    }
+
+    // the entry events go to, the incoming one while fading
+    fn showing(&self) -> usize {
+        match self.incoming {
+            Some((incoming, _)) => incoming,
+            None => self.current,
+        }
+    }
+
+    // draw `entry` into `slot` the way it would draw into the window
+    fn render_scene(
+        &mut self,
+        entry: usize,
+        slot: usize,
+        frame: &Frame,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let slot = &self.slots[slot];
+        let frame = Frame {
+            view: &slot.color.view,
+            depth: self.entries[entry].depth_buffer.then_some(&slot.depth),
+            ..*frame
+        };
+        self.scenes[entry].render(&frame, device, queue);
+    }
+}
+
+fn create_bind_groups(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    slots: &[Slot; 2],
+    fade_buffer: &wgpu::Buffer,
+) -> [wgpu::BindGroup; 2] {
+    let create = |from: &Slot, to: &Slot| {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("reel_bind_group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&from.color.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&to.color.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&from.color.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: fade_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    };
+    [create(&slots[0], &slots[1]), create(&slots[1], &slots[0])]
+}
+
+impl<P: Playlist> App for Reel<P> {
+    // a scene's required features are only optional for the reel, scenes the adapter can't
+    // run are left out instead of failing the whole reel
+    fn optional_features() -> wgpu::Features {
+        P::scenes()
+            .iter()
+            .fold(wgpu::Features::empty(), |features, entry| {
+                features | entry.required_features | entry.optional_features
+            })
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        let entries: Vec<Entry> = P::scenes()
+            .into_iter()
+            .filter(|entry| {
+                let missing = entry.required_features - device.features();
+                if !missing.is_empty() {
+                    warn!("reel: skip {}, missing {:?}", entry.title, missing);
+                }
+                missing.is_empty()
+            })
+            .collect();
+        assert!(
+            !entries.is_empty(),
+            "Fail to find a scene the adapter can run"
+        );
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("reel_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("reel.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("reel"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let fade_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Fade Buffer"),
+            contents: bytemuck::cast_slice(&[0f32]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let slots = [Slot::new(device, config), Slot::new(device, config)];
+        let bind_groups = create_bind_groups(device, &layout, &slots, &fade_buffer);
+
+        let scenes = entries
+            .iter()
+            .map(|entry| (entry.init)(config, adapter, device, queue))
+            .collect();
+        info!("reel: {}", entries[0].title);
+
+        Reel {
+            entries,
+            scenes,
+            current: 0,
+            current_slot: 0,
+            incoming: None,
+            shown_at: 0.,
+            time: 0.,
+            auto_advance: true,
+            requested_step: None,
+            title_changed: true,
+            warming: 0,
+            slots,
+            layout,
+            pipeline,
+            fade_buffer,
+            bind_groups,
+            _playlist: PhantomData,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        self.slots = [Slot::new(device, config), Slot::new(device, config)];
+        self.bind_groups = create_bind_groups(device, &self.layout, &self.slots, &self.fade_buffer);
+
+        for scene in &mut self.scenes {
+            scene.resize(config, device, queue);
+        }
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.title_changed {
+            self.title_changed = false;
+            window.set_title(&self.entries[self.showing()].title);
+        }
+
+        let keycode = match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => Some(keycode),
+            _ => None,
+        };
+        match keycode {
+            Some(VirtualKeyCode::PageDown) => self.requested_step = Some(1),
+            Some(VirtualKeyCode::PageUp) => self.requested_step = Some(-1),
+            Some(VirtualKeyCode::Home) => {
+                self.auto_advance = !self.auto_advance;
+                info!("reel: auto advance {}", self.auto_advance);
+            }
+            _ => {
+                let showing = self.showing();
+                self.scenes[showing].update(window, event);
+            }
+        }
+    }
+
+    fn commands(commands: &mut Commands<Self>) {
+        commands.add(
+            "load",
+            "[number or title]",
+            "fade to a scene, without one list them",
+            |reel, _, args| {
+                if args.is_empty() {
+                    let scenes: Vec<String> = reel
+                        .entries
+                        .iter()
+                        .enumerate()
+                        .map(|(i, entry)| format!("{:>3} {}", i + 1, entry.title))
+                        .collect();
+                    return Ok(scenes.join("\n"));
+                }
+                let entry = reel.find(&args.join(" "))?;
+                let showing = reel.showing();
+                if entry == showing {
+                    return Ok(format!("{} is showing already", reel.entries[entry].title));
+                }
+                reel.requested_step = Some(entry as isize - showing as isize);
+                Ok(format!("loading {}", reel.entries[entry].title))
+            },
+        );
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        let showing = self.showing();
+        self.scenes[showing].device_event(event);
+    }
+
+    // the bar counts scenes, one with a lot of variants holds it for a while
+    fn warmup(&mut self, device: &wgpu::Device) -> Option<Progress> {
+        while self.warming < self.scenes.len() {
+            if self.scenes[self.warming].warmup(device).is_some() {
+                return Some(Progress {
+                    done: self.warming,
+                    total: self.scenes.len(),
+                });
+            }
+            self.warming += 1;
+        }
+        None
+    }
+
+    fn render(&mut self, frame: &Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.time = frame.time;
+        if let Some(step) = self.requested_step.take() {
+            self.advance(step);
+        } else if self.auto_advance
+            && self.incoming.is_none()
+            && self.time - self.shown_at >= SCENE_SECONDS
+        {
+            self.advance(1);
+        }
+
+        let fade = match &self.incoming {
+            // scrubbed back past its start it shows the scene fading out
+            Some((_, started)) => ((self.time - started) / FADE_SECONDS).max(0.),
+            None => 0.,
+        };
+        let fade = if fade >= 1. {
+            self.finish_fade();
+            0.
+        } else {
+            fade
+        };
+        watchdog::write_buffer(queue, &self.fade_buffer, 0, bytemuck::cast_slice(&[fade]));
+
+        self.render_scene(self.current, self.current_slot, frame, device, queue);
+        if let Some((incoming, _)) = self.incoming {
+            self.render_scene(incoming, 1 - self.current_slot, frame, device, queue);
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("reel composite"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_groups[self.current_slot], &[]);
+            rpass.draw(0..3, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,39 @@
+// blends the scene fading out with the one fading in, same fullscreen triangle as blit.wgsl
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragInput.tex_coord = uv;
+    return fragInput;
+}
+
+struct Fade {
+    // 0 shows only t_from, 1 only t_to
+    amount: f32,
+};
+
+@group(0) @binding(0)
+var t_from: texture_2d<f32>;
+@group(0) @binding(1)
+var t_to: texture_2d<f32>;
+@group(0) @binding(2)
+var s_slot: sampler;
+@group(0) @binding(3)
+var<uniform> fade: Fade;
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let outgoing = textureSample(t_from, s_slot, input.tex_coord);
+    let incoming = textureSample(t_to, s_slot, input.tex_coord);
+    // smoothstep eases in and out of the cut instead of a linear ramp
+    return mix(outgoing, incoming, smoothstep(0.0, 1.0, fade.amount));
+}
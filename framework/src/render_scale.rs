@@ -0,0 +1,155 @@
+//! offscreen scene target sized to the window times a render scale.
+//!
+//! draw the scene into [`ScaledTarget::view`], then [`ScaledTarget::blit`] stretches it over
+//! the whole window with linear filtering. below 1 the scene renders at fewer pixels and gets
+//! upsampled, above 1 it renders at more and gets downsampled, a cheap supersampling.
+//! with [`ScaledTarget::sharpen`] on, the upscale also sharpens to make up for the blur.
+
+use crate::blit::Blit;
+
+pub struct ScaledTarget {
+    pub scale: f32,
+    /// size of the scene target, the window size times `scale`
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+    /// sharpen while blitting instead of plain bilinear
+    pub sharpen: bool,
+    window_width: u32,
+    window_height: u32,
+    view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    blit: Blit,
+    sharpen_blit: Blit,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ScaledTarget {
+    pub const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+    pub const MIN_SCALE: f32 = 0.5;
+    pub const MAX_SCALE: f32 = 2.;
+
+    /// `surface_format` is the format of the window the target gets blitted to,
+    /// `scale` is clamped to [`ScaledTarget::MIN_SCALE`]..=[`ScaledTarget::MAX_SCALE`]
+    pub fn new(
+        device: &wgpu::Device,
+        window_width: u32,
+        window_height: u32,
+        scale: f32,
+        surface_format: wgpu::TextureFormat,
+    ) -> ScaledTarget {
+        let scale = scale.clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+        let (width, height) = scaled_size(window_width, window_height, scale);
+        let view = create_view(device, width, height);
+
+        // linear smooths the stretch, nearest would turn a 0.5 scale into visible blocks
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("render_scale_sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let blit = Blit::new(device, surface_format);
+        let sharpen_blit = Blit::with_sharpening(device, surface_format);
+        let bind_group = blit.create_bind_group(device, &view, &sampler);
+
+        ScaledTarget {
+            scale,
+            width,
+            height,
+            format: Self::FORMAT,
+            sharpen: false,
+            window_width,
+            window_height,
+            view,
+            sampler,
+            blit,
+            sharpen_blit,
+            bind_group,
+        }
+    }
+
+    /// call when the window resizes, the target follows at the same scale
+    pub fn resize(&mut self, device: &wgpu::Device, window_width: u32, window_height: u32) {
+        self.window_width = window_width;
+        self.window_height = window_height;
+        self.recreate(device);
+    }
+
+    /// change the scale live, anything sized to the target (depth, aspect) needs recreating too
+    pub fn set_scale(&mut self, device: &wgpu::Device, scale: f32) {
+        let scale = scale.clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+        if scale == self.scale {
+            return;
+        }
+        self.scale = scale;
+        self.recreate(device);
+    }
+
+    fn recreate(&mut self, device: &wgpu::Device) {
+        let (width, height) = scaled_size(self.window_width, self.window_height, self.scale);
+        self.width = width;
+        self.height = height;
+        self.view = create_view(device, width, height);
+        self.bind_group = self
+            .blit
+            .create_bind_group(device, &self.view, &self.sampler);
+    }
+
+    /// render target for the scene, its format is [`ScaledTarget::FORMAT`]
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    /// stretch the target over all of `surface_view`
+    pub fn blit(&self, encoder: &mut wgpu::CommandEncoder, surface_view: &wgpu::TextureView) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("render scale blit"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: surface_view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        // both blits share a bind group layout, one bind group serves either
+        if self.sharpen {
+            self.sharpen_blit.draw(&mut rpass, &self.bind_group);
+        } else {
+            self.blit.draw(&mut rpass, &self.bind_group);
+        }
+    }
+}
+
+fn scaled_size(width: u32, height: u32, scale: f32) -> (u32, u32) {
+    (
+        ((width as f32 * scale).round() as u32).max(1),
+        ((height as f32 * scale).round() as u32).max(1),
+    )
+}
+
+fn create_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("render_scale_texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: ScaledTarget::FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    });
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
@@ -0,0 +1,336 @@
+//! rhai scripts that drive a chapter's [`Scene`], behind the `rhai` feature. the script runs
+//! once on the first frame, and the functions it hands to `on_frame` are then called every frame
+//! with the seconds since it started and since the last frame, on the driver's
+//! [`crate::timeline`], so they stop and go back with the chapter:
+//!
+//! ```text
+//! // t57-water's demo.rhai, the torus rolls over and the rock glows
+//! fn roll(time, dt) {
+//!     set_rotation("torus", 0.0, time, 0.0);
+//!     set_material("rock", "color", [0.35 + 0.3 * sin(time), 0.4, 0.3]);
+//! }
+//! on_frame(Fn("roll"));
+//! spawn_copy("ball", 3.0, 0.5, 2.0);
+//! ```
+//!
+//! - `objects()`, the names of the scene's objects
+//! - `position(name)`, `[x, y, z]`
+//! - `set_position(name, x, y, z)`, `set_rotation(name, yaw, pitch, roll)` in radians,
+//!   `set_scale(name, scale)`
+//! - `set_material(name, param, value)`, a number or an array of them, the params are the scene's
+//! - `spawn_copy(name, x, y, z)`, a copy of the object there, it's in `objects()` from the next
+//!   frame. `spawn` is a word rhai keeps for itself
+//! - `on_frame(function)`
+//!
+//! that's all a script can reach, and a call that runs longer than [`MAX_OPERATIONS`] is stopped
+//! instead of hanging the frame. `script <path>` and [`crate::script::ENV_VAR`] start a `.rhai`
+//! file this way. it's read again when it changes and starts over, the scene keeps what the last
+//! run did to it.
+
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime};
+
+use glam::{EulerRot, Quat, Vec3};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, AST};
+
+use crate::scene::{Scene, Staged};
+
+/// operations a single call may take
+pub const MAX_OPERATIONS: u64 = 1_000_000;
+
+// how often the file is checked for changes
+const RELOAD_EVERY: Duration = Duration::from_secs(1);
+
+type Shared<T> = Rc<RefCell<T>>;
+
+pub struct RhaiScript {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    engine: Engine,
+    ast: AST,
+    /// `Some` while the script runs, the registered functions work on it
+    staged: Shared<Option<Staged>>,
+    callbacks: Shared<Vec<FnPtr>>,
+    /// the timeline's time on the first frame, when the script's top level runs
+    start: Option<f32>,
+    last_frame: f32,
+    last_check: Instant,
+}
+
+impl RhaiScript {
+    pub fn load(path: impl Into<PathBuf>) -> Result<RhaiScript, String> {
+        let path = path.into();
+        let staged = Shared::default();
+        let callbacks = Shared::default();
+        let engine = engine(&staged, &callbacks);
+        let (ast, modified) = read(&engine, &path)?;
+        Ok(RhaiScript {
+            path,
+            modified,
+            engine,
+            ast,
+            staged,
+            callbacks,
+            start: None,
+            last_frame: 0.,
+            last_check: Instant::now(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// read the file again when it changed since the last time, at most once a second. `None`
+    /// when it wasn't read. a script that doesn't compile any more keeps running as it was
+    pub fn reload(&mut self) -> Option<Result<(), String>> {
+        if self.last_check.elapsed() < RELOAD_EVERY {
+            return None;
+        }
+        self.last_check = Instant::now();
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(read(&self.engine, &self.path).map(|(ast, _)| {
+            self.ast = ast;
+            self.callbacks.borrow_mut().clear();
+            self.start = None;
+        }))
+    }
+
+    /// run the script's frame against `scene` at `now` on the timeline: the top level on the
+    /// first frame, the `on_frame` functions after that. what it did before an error still goes
+    /// into the scene
+    pub fn frame(&mut self, scene: &mut dyn Scene, now: f32) -> Result<(), String> {
+        *self.staged.borrow_mut() = Some(Staged::new(scene));
+        let result = match self.start {
+            None => {
+                self.start = Some(now);
+                self.engine.run_ast(&self.ast)
+            }
+            Some(start) => {
+                let time = (now - start) as f64;
+                let dt = (now - self.last_frame) as f64;
+                // a callback may register another one
+                let callbacks = self.callbacks.borrow().clone();
+                callbacks.iter().try_for_each(|callback| {
+                    callback
+                        .call::<Dynamic>(&self.engine, &self.ast, (time, dt))
+                        .map(drop)
+                })
+            }
+        };
+        self.last_frame = now;
+
+        let staged = self.staged.borrow_mut().take().expect("staged above");
+        let applied = staged.apply(scene);
+        result
+            .map_err(|err| format!("{}: {}", self.path.display(), err))
+            .and(applied)
+    }
+}
+
+fn read(engine: &Engine, path: &Path) -> Result<(AST, Option<SystemTime>), String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| format!("Fail to read {}: {}", path.display(), err))?;
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let ast = engine
+        .compile(source)
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok((ast, modified))
+}
+
+// `f` on the scene of the frame that's running
+fn with_staged<T>(
+    staged: &Shared<Option<Staged>>,
+    f: impl FnOnce(&mut Staged) -> Result<T, String>,
+) -> Result<T, Box<EvalAltResult>> {
+    let mut staged = staged.borrow_mut();
+    let staged = staged
+        .as_mut()
+        .ok_or("the scene is only there while a frame runs")?;
+    f(staged).map_err(Into::into)
+}
+
+// a number or an array of numbers, ints are taken as floats
+fn numbers(value: Dynamic) -> Result<Vec<f32>, String> {
+    let number = |value: &Dynamic| {
+        value
+            .as_float()
+            .map(|number| number as f32)
+            .or_else(|_| value.as_int().map(|number| number as f32))
+            .map_err(|_| format!("`{}` isn't a number", value))
+    };
+    match value.clone().try_cast::<Array>() {
+        Some(array) => array.iter().map(number).collect(),
+        None => number(&value).map(|number| vec![number]),
+    }
+}
+
+// the scene functions of the module docs, all of them go through `staged`
+fn engine(staged: &Shared<Option<Staged>>, callbacks: &Shared<Vec<FnPtr>>) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    // rhai's release limits, debug builds nest half as deep and turn down the demo's `roll`
+    engine.set_max_expr_depths(64, 32);
+
+    let scene = staged.clone();
+    engine.register_fn("objects", move || {
+        with_staged(&scene, |staged| {
+            Ok(staged
+                .names()
+                .into_iter()
+                .map(Dynamic::from)
+                .collect::<Array>())
+        })
+    });
+    let scene = staged.clone();
+    engine.register_fn("position", move |name: &str| {
+        with_staged(&scene, |staged| {
+            let translation = staged.transform(name)?.translation;
+            Ok(translation
+                .to_array()
+                .into_iter()
+                .map(|x| Dynamic::from_float(x as f64))
+                .collect::<Array>())
+        })
+    });
+    let scene = staged.clone();
+    engine.register_fn("set_position", move |name: &str, x: f64, y: f64, z: f64| {
+        with_staged(&scene, |staged| {
+            let mut transform = staged.transform(name)?;
+            transform.translation = Vec3::new(x as f32, y as f32, z as f32);
+            staged.set_transform(name, transform)
+        })
+    });
+    let scene = staged.clone();
+    engine.register_fn(
+        "set_rotation",
+        move |name: &str, yaw: f64, pitch: f64, roll: f64| {
+            with_staged(&scene, |staged| {
+                let mut transform = staged.transform(name)?;
+                transform.rotation =
+                    Quat::from_euler(EulerRot::YXZ, yaw as f32, pitch as f32, roll as f32);
+                staged.set_transform(name, transform)
+            })
+        },
+    );
+    let scene = staged.clone();
+    engine.register_fn("set_scale", move |name: &str, scale: f64| {
+        with_staged(&scene, |staged| {
+            let mut transform = staged.transform(name)?;
+            transform.scale = Vec3::splat(scale as f32);
+            staged.set_transform(name, transform)
+        })
+    });
+    let scene = staged.clone();
+    engine.register_fn(
+        "set_material",
+        move |name: &str, param: &str, value: Dynamic| {
+            with_staged(&scene, |staged| {
+                staged.set_material(name, param, numbers(value)?)
+            })
+        },
+    );
+    let scene = staged.clone();
+    engine.register_fn("spawn_copy", move |name: &str, x: f64, y: f64, z: f64| {
+        with_staged(&scene, |staged| {
+            let mut transform = staged.transform(name)?;
+            transform.translation = Vec3::new(x as f32, y as f32, z as f32);
+            staged.spawn(name, transform)
+        })
+    });
+    let callbacks = callbacks.clone();
+    engine.register_fn("on_frame", move |callback: FnPtr| {
+        callbacks.borrow_mut().push(callback);
+    });
+
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Transform;
+
+    // t57-water's objects, spawned copies are named like the chapter names them
+    struct Water {
+        objects: Vec<(String, Transform)>,
+        materials: Vec<(String, String, Vec<f32>)>,
+    }
+
+    impl Scene for Water {
+        fn objects(&self) -> Vec<(String, Transform)> {
+            self.objects.clone()
+        }
+
+        fn set_transform(&mut self, object: &str, transform: Transform) -> Result<(), String> {
+            let (_, old) = self
+                .objects
+                .iter_mut()
+                .find(|(name, _)| name == object)
+                .ok_or_else(|| format!("no {}", object))?;
+            *old = transform;
+            Ok(())
+        }
+
+        fn set_material(&mut self, object: &str, param: &str, value: &[f32]) -> Result<(), String> {
+            self.materials
+                .push((object.to_string(), param.to_string(), value.to_vec()));
+            Ok(())
+        }
+
+        fn spawn(&mut self, object: &str, transform: Transform) -> Result<(), String> {
+            let name = format!("{} {}", object, self.objects.len());
+            self.objects.push((name, transform));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn runs_the_water_demo() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../tutorials/t057-water/demo.rhai");
+        let mut script = RhaiScript::load(path).unwrap();
+        let mut scene = Water {
+            objects: ["torus", "rock", "ball"]
+                .map(|name| (name.to_string(), Transform::IDENTITY))
+                .into(),
+            materials: Vec::new(),
+        };
+
+        // the top level spawns the row of balls
+        script.frame(&mut scene, 2.).unwrap();
+        assert_eq!(scene.objects.len(), 7);
+        assert_eq!(scene.objects[3].1.translation, Vec3::new(-1., 0.4, 4.));
+        assert_eq!(scene.objects[6].1.translation, Vec3::new(5., 0.4, 4.));
+
+        // and `roll` runs from then on, with the time since the script started
+        script.frame(&mut scene, 2.5).unwrap();
+        assert_eq!(scene.materials.len(), 1);
+        assert_eq!(
+            (&*scene.materials[0].0, &*scene.materials[0].1),
+            ("rock", "color")
+        );
+        assert!((scene.materials[0].2[0] - (0.35 + 0.3 * 0.5_f32.sin())).abs() < 1e-5);
+        assert_eq!(scene.objects[6].1.translation.x, 5.);
+        assert_eq!(scene.objects[6].1.translation.z, 4.);
+    }
+
+    #[test]
+    fn a_runaway_loop_is_stopped() {
+        let path = std::env::temp_dir().join("learnwgpu-runaway.rhai");
+        std::fs::write(&path, "loop {}").unwrap();
+        let mut script = RhaiScript::load(&path).unwrap();
+        let mut scene = Water {
+            objects: Vec::new(),
+            materials: Vec::new(),
+        };
+        assert!(script.frame(&mut scene, 0.).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+//! a chapter's objects as a script sees them. a chapter implements [`Scene`] and hands it out
+//! from [`App::scene`](crate::App::scene), and a script can then move its objects, change their
+//! materials and spawn copies of them by name, see `rhai_script` behind the `rhai` feature.
+//!
+//! the script never holds the app. it runs against a [`Staged`] copy of the transforms, and
+//! what it changed goes into the scene afterwards, in the order it was done.
+
+use glam::{Mat4, Quat, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        translation: Vec3::ZERO,
+        rotation: Quat::IDENTITY,
+        scale: Vec3::ONE,
+    };
+
+    pub fn from_translation(translation: Vec3) -> Transform {
+        Transform {
+            translation,
+            ..Transform::IDENTITY
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::IDENTITY
+    }
+}
+
+pub trait Scene {
+    /// every object by name with where it is now, in the order a script lists them
+    fn objects(&self) -> Vec<(String, Transform)>;
+
+    /// move `object`, an error for a name the scene doesn't have
+    fn set_transform(&mut self, object: &str, transform: Transform) -> Result<(), String>;
+
+    /// set `param` of `object`'s material, which params there are and how many numbers each
+    /// takes is up to the scene
+    fn set_material(&mut self, object: &str, param: &str, value: &[f32]) -> Result<(), String>;
+
+    /// a new object at `transform` with `object`'s mesh and material, named by the scene
+    fn spawn(&mut self, object: &str, transform: Transform) -> Result<(), String>;
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Edit {
+    Transform(String, Transform),
+    Material(String, String, Vec<f32>),
+    Spawn(String, Transform),
+}
+
+/// the scene while a script runs. reads see the transforms from before the script with its own
+/// moves on top, the edits wait for [`Staged::apply`]. spawned objects only show up in the
+/// next frame's copy, the scene names them
+pub struct Staged {
+    objects: Vec<(String, Transform)>,
+    edits: Vec<Edit>,
+}
+
+impl Staged {
+    pub fn new(scene: &dyn Scene) -> Staged {
+        Staged {
+            objects: scene.objects(),
+            edits: Vec::new(),
+        }
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.objects.iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    pub fn transform(&self, object: &str) -> Result<Transform, String> {
+        self.find(object).map(|index| self.objects[index].1)
+    }
+
+    pub fn set_transform(&mut self, object: &str, transform: Transform) -> Result<(), String> {
+        let index = self.find(object)?;
+        self.objects[index].1 = transform;
+        self.edits
+            .push(Edit::Transform(object.to_string(), transform));
+        Ok(())
+    }
+
+    pub fn set_material(
+        &mut self,
+        object: &str,
+        param: &str,
+        value: Vec<f32>,
+    ) -> Result<(), String> {
+        self.find(object)?;
+        self.edits
+            .push(Edit::Material(object.to_string(), param.to_string(), value));
+        Ok(())
+    }
+
+    pub fn spawn(&mut self, object: &str, transform: Transform) -> Result<(), String> {
+        self.find(object)?;
+        self.edits.push(Edit::Spawn(object.to_string(), transform));
+        Ok(())
+    }
+
+    /// hand the edits to `scene` in the order they were made. all of them are tried, the error
+    /// names the ones the scene turned down
+    pub fn apply(self, scene: &mut dyn Scene) -> Result<(), String> {
+        let errors: Vec<String> = self
+            .edits
+            .into_iter()
+            .filter_map(|edit| {
+                match edit {
+                    Edit::Transform(object, transform) => scene.set_transform(&object, transform),
+                    Edit::Material(object, param, value) => {
+                        scene.set_material(&object, &param, &value)
+                    }
+                    Edit::Spawn(object, transform) => scene.spawn(&object, transform),
+                }
+                .err()
+            })
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+
+    fn find(&self, object: &str) -> Result<usize, String> {
+        self.objects
+            .iter()
+            .position(|(name, _)| name == object)
+            .ok_or_else(|| format!("there's no object `{}`", object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // objects by name and what was done to them
+    struct Log {
+        objects: Vec<(String, Transform)>,
+        done: Vec<String>,
+    }
+
+    impl Scene for Log {
+        fn objects(&self) -> Vec<(String, Transform)> {
+            self.objects.clone()
+        }
+
+        fn set_transform(&mut self, object: &str, transform: Transform) -> Result<(), String> {
+            self.done
+                .push(format!("move {} {}", object, transform.translation.x));
+            Ok(())
+        }
+
+        fn set_material(&mut self, object: &str, param: &str, value: &[f32]) -> Result<(), String> {
+            if param != "color" {
+                return Err(format!("{} has no {}", object, param));
+            }
+            self.done.push(format!("{} {} {:?}", object, param, value));
+            Ok(())
+        }
+
+        fn spawn(&mut self, object: &str, _transform: Transform) -> Result<(), String> {
+            self.done.push(format!("spawn {}", object));
+            Ok(())
+        }
+    }
+
+    fn scene() -> Log {
+        Log {
+            objects: vec![
+                ("ball".to_string(), Transform::IDENTITY),
+                ("rock".to_string(), Transform::from_translation(Vec3::X)),
+            ],
+            done: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn reads_see_the_scripts_own_moves() {
+        let scene = scene();
+        let mut staged = Staged::new(&scene);
+        assert_eq!(staged.names(), ["ball", "rock"]);
+        assert_eq!(staged.transform("rock").unwrap().translation, Vec3::X);
+
+        let moved = Transform::from_translation(Vec3::Y);
+        staged.set_transform("rock", moved).unwrap();
+        assert_eq!(staged.transform("rock"), Ok(moved));
+        assert_eq!(
+            staged.transform("tree").unwrap_err(),
+            "there's no object `tree`"
+        );
+        assert!(staged.spawn("tree", moved).is_err());
+    }
+
+    #[test]
+    fn applies_in_order_and_names_what_failed() {
+        let mut scene = scene();
+        let mut staged = Staged::new(&scene);
+        staged
+            .set_transform("ball", Transform::from_translation(Vec3::splat(2.)))
+            .unwrap();
+        staged.set_material("rock", "shine", vec![1.]).unwrap();
+        staged.spawn("ball", Transform::IDENTITY).unwrap();
+        staged
+            .set_material("ball", "color", vec![1., 0., 0.])
+            .unwrap();
+
+        assert_eq!(staged.apply(&mut scene).unwrap_err(), "rock has no shine");
+        assert_eq!(
+            scene.done,
+            ["move ball 2", "spawn ball", "ball color [1.0, 0.0, 0.0]"]
+        );
+    }
+}
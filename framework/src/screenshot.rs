@@ -0,0 +1,472 @@
+//! screenshots and recordings that never wait on the gpu.
+//!
+//! [`Capture::capture`] copies a frame into a staging buffer from a small pool and asks for it
+//! to be mapped, then returns right away. [`Capture::poll`] picks up the buffers that finished
+//! mapping on later frames and hands their pixels to a worker thread for the slow png encoding.
+//!
+//! [`Capture::dump`] goes through the same steps for a list of [`DumpTarget`]s, the
+//! intermediate textures of a frame, so a whole pipeline can be looked at one picture per pass.
+
+use std::sync::mpsc;
+use std::thread;
+
+use tracing::{info, warn};
+
+/// staging buffers alive at once, a capture past this is skipped instead of waiting
+const POOL_SIZE: usize = 3;
+
+struct Staging {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+}
+
+// how the texels of a format become 8 bit rgba in the png
+#[derive(Clone, Copy)]
+enum Encoding {
+    Rgba8,
+    Bgra8,
+    R8,
+    R16Float,
+    Rg16Float,
+    Rgba16Float,
+    R32Float,
+    Rgba32Float,
+    Depth,
+}
+
+impl Encoding {
+    fn of(format: wgpu::TextureFormat) -> Option<Encoding> {
+        use wgpu::TextureFormat::*;
+        Some(match format {
+            Rgba8Unorm | Rgba8UnormSrgb => Encoding::Rgba8,
+            Bgra8Unorm | Bgra8UnormSrgb => Encoding::Bgra8,
+            R8Unorm => Encoding::R8,
+            R16Float => Encoding::R16Float,
+            Rg16Float => Encoding::Rg16Float,
+            Rgba16Float => Encoding::Rgba16Float,
+            R32Float => Encoding::R32Float,
+            Rgba32Float => Encoding::Rgba32Float,
+            Depth32Float => Encoding::Depth,
+            _ => return None,
+        })
+    }
+
+    fn bytes_per_pixel(self) -> u32 {
+        match self {
+            Encoding::R8 => 1,
+            Encoding::R16Float => 2,
+            Encoding::Rgba8
+            | Encoding::Bgra8
+            | Encoding::Rg16Float
+            | Encoding::R32Float
+            | Encoding::Depth => 4,
+            Encoding::Rgba16Float => 8,
+            Encoding::Rgba32Float => 16,
+        }
+    }
+}
+
+/// a texture to save in a frame dump, mip 0 of its first layer. the texture needs `COPY_SRC`,
+/// [`crate::texture::Texture::create_render_target`] and `create_depth` have it
+pub struct DumpTarget<'a> {
+    /// ends up in the file name
+    pub label: &'a str,
+    pub texture: &'a wgpu::Texture,
+    pub width: u32,
+    pub height: u32,
+    pub format: wgpu::TextureFormat,
+}
+
+impl<'a> DumpTarget<'a> {
+    pub fn new(
+        label: &'a str,
+        texture: &'a wgpu::Texture,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> DumpTarget<'a> {
+        DumpTarget {
+            label,
+            texture,
+            width,
+            height,
+            format,
+        }
+    }
+}
+
+struct Pending {
+    id: u64,
+    staging: Staging,
+    encoding: Encoding,
+    // back to the pool once read, dumps make their buffers for a single use
+    pooled: bool,
+    path: String,
+}
+
+struct Job {
+    path: String,
+    width: u32,
+    height: u32,
+    encoding: Encoding,
+    pixels: Vec<u8>,
+}
+
+type MapResult = (u64, Result<(), wgpu::BufferAsyncError>);
+
+pub struct Capture {
+    free: Vec<Staging>,
+    pending: Vec<Pending>,
+    next_id: u64,
+    mapped_sender: mpsc::Sender<MapResult>,
+    mapped_receiver: mpsc::Receiver<MapResult>,
+    jobs: mpsc::Sender<Job>,
+}
+
+impl Capture {
+    /// starts the png worker thread, it lives as long as the `Capture`
+    pub fn new() -> Capture {
+        let (jobs, job_receiver) = mpsc::channel::<Job>();
+        thread::spawn(move || {
+            for job in job_receiver {
+                save(job);
+            }
+        });
+
+        let (mapped_sender, mapped_receiver) = mpsc::channel();
+
+        Capture {
+            free: Vec::new(),
+            pending: Vec::new(),
+            next_id: 0,
+            mapped_sender,
+            mapped_receiver,
+            jobs,
+        }
+    }
+
+    /// copy `frame` and save it as a png at `path` once the copy is back. `config` is the one
+    /// the surface was configured with, its usage needs `COPY_SRC` and its format has to be
+    /// 8 bit rgba or bgra. returns false when the capture was skipped, because of the format
+    /// or because every staging buffer is still in use
+    pub fn capture(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        frame: &wgpu::SurfaceTexture,
+        config: &wgpu::SurfaceConfiguration,
+        path: String,
+    ) -> bool {
+        let (width, height, format) = (config.width, config.height, config.format);
+        let encoding = match Encoding::of(format) {
+            Some(encoding @ (Encoding::Rgba8 | Encoding::Bgra8)) => encoding,
+            _ => {
+                warn!("can't capture {:?}, only 8 bit rgba and bgra", format);
+                return false;
+            }
+        };
+
+        let staging = match self
+            .free
+            .iter()
+            .position(|s| s.width == width && s.height == height)
+        {
+            Some(i) => self.free.swap_remove(i),
+            None if self.pending.iter().filter(|p| p.pooled).count() < POOL_SIZE => {
+                // whatever is left in `free` has the old size, make room for one that fits
+                self.free.pop();
+                create_staging(device, width, height, encoding.bytes_per_pixel())
+            }
+            None => return false,
+        };
+
+        let source = frame.texture.as_image_copy();
+        self.copy(device, queue, source, staging, encoding, true, path);
+        true
+    }
+
+    /// save each of `targets` as a png in the directory `dir`, named after its place in the
+    /// list and its label. a dump doesn't wait for the pool, every target gets a staging
+    /// buffer of its own. returns how many were queued, formats without a png conversion
+    /// are skipped with a warning
+    pub fn dump(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        targets: &[DumpTarget],
+        dir: &str,
+    ) -> usize {
+        if let Err(err) = std::fs::create_dir_all(dir) {
+            warn!("Fail to create {}: {}", dir, err);
+            return 0;
+        }
+
+        let mut queued = 0;
+        for (i, target) in targets.iter().enumerate() {
+            let encoding = match Encoding::of(target.format) {
+                Some(encoding) => encoding,
+                None => {
+                    warn!("can't dump {} of {:?}", target.label, target.format);
+                    continue;
+                }
+            };
+            let staging = create_staging(
+                device,
+                target.width,
+                target.height,
+                encoding.bytes_per_pixel(),
+            );
+            let source = wgpu::ImageCopyTexture {
+                texture: target.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: match encoding {
+                    Encoding::Depth => wgpu::TextureAspect::DepthOnly,
+                    _ => wgpu::TextureAspect::All,
+                },
+            };
+            let path = format!("{}/{:02}-{}.png", dir, i, target.label.replace(' ', "-"));
+            self.copy(device, queue, source, staging, encoding, false, path);
+            queued += 1;
+        }
+        queued
+    }
+
+    // copy `source` into `staging` and ask for it to be mapped, `poll` takes it from there
+    #[allow(clippy::too_many_arguments)]
+    fn copy(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        source: wgpu::ImageCopyTexture,
+        staging: Staging,
+        encoding: Encoding,
+        pooled: bool,
+        path: String,
+    ) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("capture"),
+        });
+        encoder.copy_texture_to_buffer(
+            source,
+            wgpu::ImageCopyBuffer {
+                buffer: &staging.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(staging.padded_bytes_per_row),
+                    rows_per_image: std::num::NonZeroU32::new(staging.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: staging.width,
+                height: staging.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(Some(encoder.finish()));
+
+        // the callback runs inside a later poll or submit, it only says which buffer is ready
+        let id = self.next_id;
+        self.next_id += 1;
+        let sender = self.mapped_sender.clone();
+        staging
+            .buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send((id, result));
+            });
+
+        self.pending.push(Pending {
+            id,
+            staging,
+            encoding,
+            pooled,
+            path,
+        });
+    }
+
+    /// call once a frame, never blocks. sends the captures that finished mapping to the worker
+    pub fn poll(&mut self, device: &wgpu::Device) {
+        if self.pending.is_empty() {
+            return;
+        }
+
+        device.poll(wgpu::Maintain::Poll);
+
+        while let Ok((id, result)) = self.mapped_receiver.try_recv() {
+            let i = match self.pending.iter().position(|p| p.id == id) {
+                Some(i) => i,
+                None => continue,
+            };
+            let pending = self.pending.swap_remove(i);
+            let staging = pending.staging;
+
+            if let Err(err) = result {
+                warn!("Fail to map capture for {}: {}", pending.path, err);
+                if pending.pooled {
+                    self.free.push(staging);
+                }
+                continue;
+            }
+
+            // drop the row padding the copy needed
+            let row_bytes = (staging.width * pending.encoding.bytes_per_pixel()) as usize;
+            let pixels = {
+                let mapped = staging.buffer.slice(..).get_mapped_range();
+                let mut pixels = Vec::with_capacity(row_bytes * staging.height as usize);
+                for row in mapped.chunks_exact(staging.padded_bytes_per_row as usize) {
+                    pixels.extend_from_slice(&row[..row_bytes]);
+                }
+                pixels
+            };
+            staging.buffer.unmap();
+
+            let _ = self.jobs.send(Job {
+                path: pending.path,
+                width: staging.width,
+                height: staging.height,
+                encoding: pending.encoding,
+                pixels,
+            });
+
+            if pending.pooled {
+                self.free.push(staging);
+            }
+        }
+    }
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn create_staging(device: &wgpu::Device, width: u32, height: u32, bytes_per_pixel: u32) -> Staging {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = (width * bytes_per_pixel).div_ceil(align) * align;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("capture staging"),
+        size: padded_bytes_per_row as wgpu::BufferAddress * height as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    Staging {
+        buffer,
+        width,
+        height,
+        padded_bytes_per_row,
+    }
+}
+
+// runs on the worker thread
+fn save(job: Job) {
+    let pixels = to_rgba8(job.encoding, &job.pixels);
+    match image::save_buffer(
+        &job.path,
+        &pixels,
+        job.width,
+        job.height,
+        image::ColorType::Rgba8,
+    ) {
+        Ok(()) => info!("saved {}", job.path),
+        Err(err) => warn!("Fail to save {}: {}", job.path, err),
+    }
+}
+
+// the png is always opaque, on screen alpha means nothing and in a target it would hide the
+// color in most viewers. floats are clamped to 0..1 as they are, no tonemapping, single
+// channels come out gray and two channels red and green
+fn to_rgba8(encoding: Encoding, bytes: &[u8]) -> Vec<u8> {
+    let unit = |v: f32| (v.clamp(0., 1.) * 255. + 0.5) as u8;
+    let f16 = |b: &[u8]| f16_to_f32(u16::from_le_bytes([b[0], b[1]]));
+    let f32 = |b: &[u8]| f32::from_le_bytes([b[0], b[1], b[2], b[3]]);
+    match encoding {
+        Encoding::Rgba8 => bytes
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2], 255])
+            .collect(),
+        Encoding::Bgra8 => bytes
+            .chunks_exact(4)
+            .flat_map(|p| [p[2], p[1], p[0], 255])
+            .collect(),
+        Encoding::R8 => bytes.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+        Encoding::R16Float => bytes
+            .chunks_exact(2)
+            .flat_map(|p| {
+                let v = unit(f16(p));
+                [v, v, v, 255]
+            })
+            .collect(),
+        Encoding::Rg16Float => bytes
+            .chunks_exact(4)
+            .flat_map(|p| [unit(f16(&p[0..])), unit(f16(&p[2..])), 0, 255])
+            .collect(),
+        Encoding::Rgba16Float => bytes
+            .chunks_exact(8)
+            .flat_map(|p| {
+                [
+                    unit(f16(&p[0..])),
+                    unit(f16(&p[2..])),
+                    unit(f16(&p[4..])),
+                    255,
+                ]
+            })
+            .collect(),
+        Encoding::R32Float => bytes
+            .chunks_exact(4)
+            .flat_map(|p| {
+                let v = unit(f32(p));
+                [v, v, v, 255]
+            })
+            .collect(),
+        Encoding::Rgba32Float => bytes
+            .chunks_exact(16)
+            .flat_map(|p| {
+                [
+                    unit(f32(&p[0..])),
+                    unit(f32(&p[4..])),
+                    unit(f32(&p[8..])),
+                    255,
+                ]
+            })
+            .collect(),
+        Encoding::Depth => {
+            // almost everything sits close to 1, stretch what was drawn over the whole range,
+            // near white and far black. the cleared far plane stays black
+            let depths: Vec<f32> = bytes.chunks_exact(4).map(f32).collect();
+            let drawn = depths.iter().copied().filter(|&d| d < 1.);
+            let near = drawn.clone().fold(1., f32::min);
+            let far = drawn.fold(0., f32::max);
+            let range = (far - near).max(f32::EPSILON);
+            depths
+                .iter()
+                .flat_map(|&d| {
+                    let v = if d < 1. {
+                        unit(1. - (d - near) / range)
+                    } else {
+                        0
+                    };
+                    [v, v, v, 255]
+                })
+                .collect()
+        }
+    }
+}
+
+// half floats by hand, for the 16 bit float targets
+pub(crate) fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 == 0 { 1. } else { -1. };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32 / 1024.;
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-14),
+        31 if mantissa == 0. => f32::INFINITY,
+        31 => f32::NAN,
+        _ => (1. + mantissa) * 2f32.powi(exponent - 15),
+    }
+}
@@ -0,0 +1,236 @@
+//! demo scripts, a text file of [`console`](crate::console) commands the driver runs on a clock,
+//! so a chapter can be put through its paces without a recompile. [`ENV_VAR`] names a script to
+//! run from the first frame, the `script <path>` command starts one and `script stop` ends it.
+//! the file is read again when it changes, and the script starts over.
+//!
+//! a line is one of
+//! - `at <seconds> <command>`, runs once, that long after the script started
+//! - `every <seconds> <command>`, runs from the start on and then every that long, `every 0`
+//!   runs it every frame
+//! - `<command>`, runs on the first frame, the same as `at 0`
+//!
+//! `#` starts a comment. e.g. t46-post-process' `demo.txt`:
+//!
+//! ```text
+//! # the vignette closes in, then the colors split
+//! set vignette 0
+//! at 2 set vignette 0.8
+//! at 4 set vignette 1.5
+//! at 6 toggle chromatic aberration
+//! every 2 toggle grayscale
+//! ```
+//!
+//! the commands are the console's, the driver's and what the app adds in
+//! [`App::commands`](crate::App::commands), so a script changes only what those let it change
+//! and nothing more. there is no language behind it, no variables, conditions or arithmetic.
+//! a `.rhai` file given the same way is a rhai script instead, see `rhai_script` behind the
+//! `rhai` feature.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// environment variable with the path of a script to run, see the module docs
+pub const ENV_VAR: &str = "LEARNWGPU_SCRIPT";
+
+// how often the file is checked for changes
+const RELOAD_EVERY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum When {
+    At(f32),
+    Every(f32),
+}
+
+#[derive(Debug, PartialEq)]
+struct Entry {
+    when: When,
+    command: String,
+    /// in seconds since the start, when the entry runs next. past it for an `at` that ran
+    next: f32,
+}
+
+// the entries of a script, a line that doesn't parse is an error naming it
+fn parse(source: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    for (number, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let (first, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let (when, command) = match first {
+            "at" | "every" => {
+                let (seconds, command) = rest
+                    .trim_start()
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| format!("line {}: `{}` without a command", number + 1, first))?;
+                let seconds: f32 = seconds
+                    .parse()
+                    .ok()
+                    .filter(|seconds: &f32| *seconds >= 0.)
+                    .ok_or_else(|| {
+                        format!(
+                            "line {}: can't make sense of `{}` seconds",
+                            number + 1,
+                            seconds
+                        )
+                    })?;
+                match first {
+                    "at" => (When::At(seconds), command.trim()),
+                    _ => (When::Every(seconds), command.trim()),
+                }
+            }
+            _ => (When::At(0.), line),
+        };
+        entries.push(Entry {
+            when,
+            command: command.to_string(),
+            next: match when {
+                When::At(seconds) => seconds,
+                When::Every(_) => 0.,
+            },
+        });
+    }
+    Ok(entries)
+}
+
+pub struct Script {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    entries: Vec<Entry>,
+    /// set on the first frame that asks what's due, the clock doesn't run during warm-up
+    start: Option<Instant>,
+    last_check: Instant,
+}
+
+impl Script {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Script, String> {
+        let path = path.into();
+        let (entries, modified) = read(&path)?;
+        Ok(Script {
+            path,
+            modified,
+            entries,
+            start: None,
+            last_check: Instant::now(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// read the file again when it changed since the last time, at most once a second. `None`
+    /// when it wasn't read. a script that doesn't parse any more keeps running as it was
+    pub fn reload(&mut self) -> Option<Result<(), String>> {
+        if self.last_check.elapsed() < RELOAD_EVERY {
+            return None;
+        }
+        self.last_check = Instant::now();
+        let modified = std::fs::metadata(&self.path)
+            .and_then(|m| m.modified())
+            .ok();
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(read(&self.path).map(|(entries, _)| {
+            self.entries = entries;
+            self.start = None;
+        }))
+    }
+
+    /// the commands to run this frame, in the order of their lines
+    pub fn due(&mut self) -> Vec<String> {
+        let seconds = self.start.get_or_insert_with(Instant::now).elapsed();
+        self.due_at(seconds.as_secs_f32())
+    }
+
+    fn due_at(&mut self, seconds: f32) -> Vec<String> {
+        let mut commands = Vec::new();
+        for entry in &mut self.entries {
+            if seconds < entry.next {
+                continue;
+            }
+            commands.push(entry.command.clone());
+            entry.next = match entry.when {
+                When::At(_) => f32::INFINITY,
+                // once per frame however late the frame is, not once per missed period
+                When::Every(period) if period > 0. => {
+                    entry.next + ((seconds - entry.next) / period).floor() * period + period
+                }
+                When::Every(_) => seconds,
+            };
+        }
+        commands
+    }
+}
+
+fn read(path: &Path) -> Result<(Vec<Entry>, Option<SystemTime>), String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| format!("Fail to read {}: {}", path.display(), err))?;
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let entries = parse(&source).map_err(|err| format!("{}, {}", path.display(), err))?;
+    Ok((entries, modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn script(source: &str) -> Script {
+        Script {
+            path: PathBuf::new(),
+            modified: None,
+            entries: parse(source).unwrap(),
+            start: None,
+            last_check: Instant::now(),
+        }
+    }
+
+    #[test]
+    fn parses_lines_and_skips_comments() {
+        let entries = parse(
+            "# a comment\n\ntoggle vignette\nat 2.5 set vignette 0.8 # fade\nevery 0 graph\n",
+        )
+        .unwrap();
+        let lines: Vec<(When, &str)> = entries
+            .iter()
+            .map(|entry| (entry.when, entry.command.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (When::At(0.), "toggle vignette"),
+                (When::At(2.5), "set vignette 0.8"),
+                (When::Every(0.), "graph"),
+            ]
+        );
+    }
+
+    #[test]
+    fn bad_lines_name_their_number() {
+        assert_eq!(
+            parse("toggle\nat soon toggle").unwrap_err(),
+            "line 2: can't make sense of `soon` seconds"
+        );
+        assert_eq!(
+            parse("every 2").unwrap_err(),
+            "line 1: `every` without a command"
+        );
+        assert!(parse("at -1 toggle").is_err());
+    }
+
+    #[test]
+    fn runs_at_once_and_every_on_its_period() {
+        let mut script = script("first\nat 1 later\nevery 0.5 tick\nevery 0 frame");
+        assert_eq!(script.due_at(0.), ["first", "tick", "frame"]);
+        assert_eq!(script.due_at(0.25), ["frame"]);
+        assert_eq!(script.due_at(0.5), ["tick", "frame"]);
+        // a long frame runs `tick` once, then it's back on its period
+        assert_eq!(script.due_at(1.7), ["later", "tick", "frame"]);
+        assert_eq!(script.due_at(1.9), ["frame"]);
+        assert_eq!(script.due_at(2.), ["tick", "frame"]);
+    }
+}
@@ -0,0 +1,131 @@
+//! offscreen textures that follow the window size, for passes that read what an earlier pass
+//! drew: a bloom chain at halving sizes, a single fxaa input, a g-buffer.
+//!
+//! each target is described once by a [`TargetDesc`], [`Targets::resize`] recreates all of
+//! them when the window changes and says so, bind groups pointing at the old textures have to
+//! be made again then.
+//!
+//! ```ignore
+//! let mut targets = Targets::new(device, config.width, config.height, vec![
+//!     TargetDesc::new(tonemap::HDR_FORMAT, 1, "scene"),
+//!     TargetDesc::new(tonemap::HDR_FORMAT, 2, "half"),
+//! ]);
+//! // in App::resize
+//! if targets.resize(device, config.width, config.height) {
+//!     bind_group = blit.create_bind_group(device, &targets[1].view, &targets[1].sampler);
+//! }
+//! ```
+
+use std::ops::Index;
+
+use crate::{screenshot::DumpTarget, texture::Texture};
+
+/// how to make one target
+#[derive(Clone, Copy, Debug)]
+pub struct TargetDesc {
+    pub format: wgpu::TextureFormat,
+    /// the window size is divided by it, rounded up and never below 1 texel
+    pub divisor: u32,
+    pub label: &'static str,
+}
+
+impl TargetDesc {
+    pub fn new(format: wgpu::TextureFormat, divisor: u32, label: &'static str) -> TargetDesc {
+        TargetDesc {
+            format,
+            divisor,
+            label,
+        }
+    }
+
+    /// the size of this target for a `width` x `height` window
+    pub fn size(&self, width: u32, height: u32) -> (u32, u32) {
+        let divide = |size: u32| size.div_ceil(self.divisor).max(1);
+        (divide(width), divide(height))
+    }
+}
+
+/// render targets made with [`Texture::create_render_target`], indexed in the order of the
+/// descs they were made from
+pub struct Targets {
+    descs: Vec<TargetDesc>,
+    textures: Vec<Texture>,
+    width: u32,
+    height: u32,
+}
+
+impl Targets {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32, descs: Vec<TargetDesc>) -> Targets {
+        let textures = create_textures(device, width, height, &descs);
+        Targets {
+            descs,
+            textures,
+            width,
+            height,
+        }
+    }
+
+    /// recreate every target for a `width` x `height` window, true if they were. the same size
+    /// keeps the old textures and their bind groups
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) -> bool {
+        if (width, height) == (self.width, self.height) {
+            return false;
+        }
+        self.textures = create_textures(device, width, height, &self.descs);
+        self.width = width;
+        self.height = height;
+        true
+    }
+
+    /// the size of target `index` right now
+    pub fn size(&self, index: usize) -> (u32, u32) {
+        self.descs[index].size(self.width, self.height)
+    }
+
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Texture> {
+        self.textures.iter()
+    }
+
+    /// every target under its label, for [`crate::App::dump_targets`]
+    pub fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        self.descs
+            .iter()
+            .zip(&self.textures)
+            .map(|(desc, texture)| {
+                let (width, height) = desc.size(self.width, self.height);
+                DumpTarget::new(desc.label, &texture.texture, width, height, desc.format)
+            })
+            .collect()
+    }
+}
+
+impl Index<usize> for Targets {
+    type Output = Texture;
+
+    fn index(&self, index: usize) -> &Texture {
+        &self.textures[index]
+    }
+}
+
+fn create_textures(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    descs: &[TargetDesc],
+) -> Vec<Texture> {
+    descs
+        .iter()
+        .map(|desc| {
+            let (width, height) = desc.size(width, height);
+            Texture::create_render_target(device, width, height, desc.format, desc.label)
+        })
+        .collect()
+}
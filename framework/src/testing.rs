@@ -0,0 +1,133 @@
+//! what a chapter's gpu tests need, behind the `testing` feature: a device without a window and
+//! a way to read a buffer or a texture back. a chapter turns the feature on in its dev-dependencies.
+//!
+//! ```ignore
+//! let Some((device, queue)) = framework::testing::headless_device() else {
+//!     return;
+//! };
+//! // run the compute pass
+//! let vertices: Vec<Vertex> = framework::testing::read_back(&device, &queue, &buffer);
+//! ```
+
+use bytemuck::Pod;
+use tracing::warn;
+
+/// a device on the default adapter with the default limits. without an adapter, e.g. on ci
+/// without a gpu, it warns and is `None`, the test should pass then
+pub fn headless_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    headless().map(|(_, device, queue)| (device, queue))
+}
+
+/// [`headless_device`] and the adapter it's on, for tests that ask what it supports
+pub fn headless() -> Option<(wgpu::Adapter, wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::new(wgpu::Backends::all());
+    let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::default(),
+        force_fallback_adapter: false,
+        compatible_surface: None,
+    }));
+    let Some(adapter) = adapter else {
+        warn!("no adapter, skipping the gpu test");
+        return None;
+    };
+    let (device, queue) = pollster::block_on(adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            label: None,
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::default(),
+        },
+        None,
+    ))
+    .expect("Fail to create device");
+    Some((adapter, device, queue))
+}
+
+/// the whole of `buffer`, which needs `COPY_SRC`, after the work submitted so far
+pub fn read_back<T: Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &wgpu::Buffer,
+) -> Vec<T> {
+    let size = buffer.size();
+    let staging = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Staging Buffer"),
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_buffer_to_buffer(buffer, 0, &staging, 0, size);
+    queue.submit(Some(encoder.finish()));
+
+    let slice = staging.slice(..);
+    slice.map_async(wgpu::MapMode::Read, |result| {
+        result.expect("Fail to map staging buffer")
+    });
+    device.poll(wgpu::Maintain::Wait);
+    let data = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+    staging.unmap();
+    data
+}
+
+/// the texels of mip `mip` of layer `layer` of `texture`, which needs `COPY_SRC`, row after row
+/// without the padding the copy needs. `T` is one texel, `width` x `height` the mip's size
+pub fn read_texture<T: Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    (width, height): (u32, u32),
+    layer: u32,
+    mip: u32,
+) -> Vec<T> {
+    let texel = std::mem::size_of::<T>() as u32;
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let padded_bytes_per_row = (width * texel).div_ceil(align) * align;
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Texture Read Buffer"),
+        size: (padded_bytes_per_row * height) as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: mip,
+            origin: wgpu::Origin3d {
+                x: 0,
+                y: 0,
+                z: layer,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: None,
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    // the alignment is a multiple of every texel size, a padded row is whole texels
+    let padded: Vec<T> = read_back(device, queue, &buffer);
+    padded
+        .chunks_exact((padded_bytes_per_row / texel) as usize)
+        .flat_map(|row| &row[..width as usize])
+        .copied()
+        .collect()
+}
+
+/// a channel of a 16 bit float texture as an `f32`
+pub fn f16_to_f32(bits: u16) -> f32 {
+    crate::screenshot::f16_to_f32(bits)
+}
@@ -0,0 +1,521 @@
+//! a texture bundled with its default view and a sampler, plus constructors for the kinds the
+//! tutorials keep creating: images, with or without mips, compressed ktx2 files, cube maps,
+//! depth buffers and offscreen render targets. [`TextureCache`] keeps images loaded by path,
+//! for models whose materials share their maps.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use tracing::info;
+
+use crate::assets::ColorSpace;
+use crate::ktx2::{self, Ktx2};
+use crate::mipmap::{self, MipmapGenerator};
+use crate::{block_decode, watchdog};
+
+pub struct Texture {
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+}
+
+impl Texture {
+    pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+    /// decode a png or jpeg, see [`Texture::from_image`]
+    pub fn from_bytes(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: &str,
+    ) -> image::ImageResult<Texture> {
+        let img = image::load_from_memory(bytes)?;
+        Ok(Self::from_image(device, queue, &img, label))
+    }
+
+    /// srgb rgba8 with a linear, clamp to edge sampler. images are color, so srgb is right
+    /// for them, data like normal maps goes through [`Texture::from_image_format`] instead
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        label: &str,
+    ) -> Texture {
+        Self::from_image_format(
+            device,
+            queue,
+            img,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            label,
+        )
+    }
+
+    /// [`Texture::from_image`] with another 4 byte per texel `format`, e.g. `Rgba8Unorm` so
+    /// the values reach the shader as they are stored
+    pub fn from_image_format(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        img: &image::DynamicImage,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Texture {
+        let texture = upload_image(device, queue, img, format, 1, label);
+        Self::with_sampler(texture, linear_sampler(device, label))
+    }
+
+    /// [`Texture::from_image_format`] with the full mip chain, filled on the gpu by
+    /// `mipmaps`. the sampler is trilinear, it blends between the two nearest levels too
+    pub fn from_image_mipmapped(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mipmaps: &mut MipmapGenerator,
+        img: &image::DynamicImage,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Texture {
+        let mip_level_count = mipmap::mip_level_count(img.width(), img.height());
+        let texture = upload_image(device, queue, img, format, mip_level_count, label);
+        let size = wgpu::Extent3d {
+            width: img.width(),
+            height: img.height(),
+            depth_or_array_layers: 1,
+        };
+        mipmaps.generate(device, queue, &texture, format, size, mip_level_count);
+
+        Self::with_sampler(texture, trilinear_sampler(device, label))
+    }
+
+    /// a ktx2 file with every mip level it has, in its own compressed format when the device
+    /// has that format's feature and decoded to rgba8 when it doesn't, see [`crate::ktx2`].
+    /// the color space comes from the file, the sampler is trilinear when there are mips
+    pub fn from_ktx2(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bytes: &[u8],
+        label: &str,
+    ) -> Result<Texture, ktx2::Error> {
+        let ktx2 = Ktx2::parse(bytes)?;
+        let info = ktx2.format.describe();
+        // wgpu only takes compressed textures that are whole blocks
+        let whole_blocks = ktx2.width % info.block_dimensions.0 as u32 == 0
+            && ktx2.height % info.block_dimensions.1 as u32 == 0;
+        let native = device.features().contains(info.required_features) && whole_blocks;
+        let format = match (native, info.srgb) {
+            (true, _) => ktx2.format,
+            (false, true) => wgpu::TextureFormat::Rgba8UnormSrgb,
+            (false, false) => wgpu::TextureFormat::Rgba8Unorm,
+        };
+        if !native {
+            info!(
+                "{}: no {:?} on this device, decoding it to {:?}",
+                label, ktx2.format, format
+            );
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: ktx2.width,
+                height: ktx2.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: ktx2.levels.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+
+        let info = format.describe();
+        let (block_width, block_height) = (
+            info.block_dimensions.0 as u32,
+            info.block_dimensions.1 as u32,
+        );
+        for (level, data) in ktx2.levels.iter().enumerate() {
+            let (width, height) = ktx2.level_size(level as u32);
+            let decoded;
+            let data = if native {
+                *data
+            } else {
+                decoded = block_decode::to_rgba8(ktx2.format, width, height, data)
+                    .expect("Fail to decode a ktx2 format");
+                &decoded
+            };
+
+            // small levels are still a whole block, the copy covers all of it
+            let (blocks_wide, blocks_high) =
+                (width.div_ceil(block_width), height.div_ceil(block_height));
+            watchdog::write_texture(
+                queue,
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(blocks_wide * info.block_size as u32),
+                    rows_per_image: std::num::NonZeroU32::new(blocks_high),
+                },
+                wgpu::Extent3d {
+                    width: blocks_wide * block_width,
+                    height: blocks_high * block_height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let sampler = if ktx2.levels.len() > 1 {
+            trilinear_sampler(device, label)
+        } else {
+            linear_sampler(device, label)
+        };
+        Ok(Self::with_sampler(texture, sampler))
+    }
+
+    /// see [`Texture::from_image`], the path doubles as the label
+    pub fn from_path(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+    ) -> image::ImageResult<Texture> {
+        let path = path.as_ref();
+        let img = image::open(path)?;
+        Ok(Self::from_image(
+            device,
+            queue,
+            &img,
+            &path.display().to_string(),
+        ))
+    }
+
+    /// srgb rgba8 cube map from six square images of the same size, in wgpu's layer order:
+    /// +x, -x, +y, -y, +z, -z. the view is a `Cube` view, the sampler linear
+    pub fn from_cube_images(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        faces: &[image::DynamicImage; 6],
+        label: &str,
+    ) -> Texture {
+        let size = wgpu::Extent3d {
+            width: faces[0].width(),
+            height: faces[0].height(),
+            depth_or_array_layers: 6,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+
+        for (layer, face) in faces.iter().enumerate() {
+            let rgba = face.to_rgba8();
+            assert_eq!(
+                rgba.dimensions(),
+                (size.width, size.height),
+                "Fail to create {}, face {} has another size than face 0",
+                label,
+                layer
+            );
+
+            watchdog::write_texture(
+                queue,
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &rgba,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(4 * size.width),
+                    rows_per_image: std::num::NonZeroU32::new(size.height),
+                },
+                wgpu::Extent3d {
+                    depth_or_array_layers: 1,
+                    ..size
+                },
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        Texture {
+            texture,
+            view,
+            sampler: linear_sampler(device, label),
+        }
+    }
+
+    /// [`Texture::DEPTH_FORMAT`] depth attachment, bindable and copyable too. the sampler
+    /// compares, for shadow map style lookups
+    pub fn create_depth(device: &wgpu::Device, width: u32, height: u32, label: &str) -> Texture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Self::DEPTH_FORMAT,
+            // COPY_SRC for frame dumps
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        Self::with_sampler(texture, sampler)
+    }
+
+    /// offscreen color target that a later pass samples, with a linear, clamp to edge sampler
+    pub fn create_render_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Texture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            // COPY_SRC for frame dumps
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        });
+
+        Self::with_sampler(texture, linear_sampler(device, label))
+    }
+
+    /// like [`Texture::create_render_target`], but written by compute shaders instead of
+    /// render passes
+    pub fn create_storage_target(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+        label: &str,
+    ) -> Texture {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            // COPY_SRC for frame dumps
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+        });
+
+        Self::with_sampler(texture, linear_sampler(device, label))
+    }
+
+    fn with_sampler(texture: wgpu::Texture, sampler: wgpu::Sampler) -> Texture {
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Texture {
+            texture,
+            view,
+            sampler,
+        }
+    }
+}
+
+/// images from disk, each file uploaded once per [`ColorSpace`] it's asked for in. paths are
+/// compared after canonicalizing, so `dir/../a.png` and `a.png` are the same texture
+#[derive(Default)]
+pub struct TextureCache {
+    textures: HashMap<(PathBuf, ColorSpace), Rc<Texture>>,
+}
+
+impl TextureCache {
+    pub fn new() -> TextureCache {
+        TextureCache::default()
+    }
+
+    /// the texture from an earlier call with the same file and color space, or a new one in
+    /// `color_space`'s format, a normal map is [`ColorSpace::Linear`]
+    pub fn load(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: impl AsRef<Path>,
+        color_space: ColorSpace,
+    ) -> image::ImageResult<Rc<Texture>> {
+        let path = path.as_ref();
+        // a file that doesn't exist fails in `image::open` below with a better error
+        let key = (
+            path.canonicalize().unwrap_or_else(|_| path.to_path_buf()),
+            color_space,
+        );
+        if let Some(texture) = self.textures.get(&key) {
+            return Ok(texture.clone());
+        }
+        let img = image::open(path)?;
+        let texture = Rc::new(Texture::from_image_format(
+            device,
+            queue,
+            &img,
+            color_space.format(),
+            &path.display().to_string(),
+        ));
+        self.textures.insert(key, texture.clone());
+        Ok(texture)
+    }
+
+    /// how many textures were uploaded, a file loaded in both color spaces counts twice
+    pub fn len(&self) -> usize {
+        self.textures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.textures.is_empty()
+    }
+}
+
+// a 2d texture with `img` in level 0, the other levels are left for the mipmap generator
+fn upload_image(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    img: &image::DynamicImage,
+    format: wgpu::TextureFormat,
+    mip_level_count: u32,
+    label: &str,
+) -> wgpu::Texture {
+    let rgba = img.to_rgba8();
+    let size = wgpu::Extent3d {
+        width: rgba.width(),
+        height: rgba.height(),
+        depth_or_array_layers: 1,
+    };
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size,
+        mip_level_count,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    });
+
+    watchdog::write_texture(
+        queue,
+        texture.as_image_copy(),
+        &rgba,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: std::num::NonZeroU32::new(4 * size.width),
+            rows_per_image: std::num::NonZeroU32::new(size.height),
+        },
+        size,
+    );
+    texture
+}
+
+// blends between the two nearest mip levels too
+fn trilinear_sampler(device: &wgpu::Device, label: &str) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some(label),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    })
+}
+
+fn linear_sampler(device: &wgpu::Device, label: &str) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some(label),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    })
+}
+
+/// window sized depth buffer. the driver keeps one for apps whose
+/// [`App::depth_buffer`](crate::App::depth_buffer) is true and recreates it with the surface
+pub struct DepthTexture {
+    texture: Texture,
+}
+
+impl DepthTexture {
+    pub const FORMAT: wgpu::TextureFormat = Texture::DEPTH_FORMAT;
+
+    pub fn new(device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) -> DepthTexture {
+        DepthTexture {
+            texture: Texture::create_depth(device, config.width, config.height, "depth_texture"),
+        }
+    }
+
+    /// match the surface again after it was reconfigured
+    pub fn resize(&mut self, device: &wgpu::Device, config: &wgpu::SurfaceConfiguration) {
+        *self = Self::new(device, config);
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    /// cleared to the far plane, stored so later passes can test against it
+    pub fn attachment(&self) -> wgpu::RenderPassDepthStencilAttachment<'_> {
+        wgpu::RenderPassDepthStencilAttachment {
+            view: &self.texture.view,
+            depth_ops: Some(wgpu::Operations {
+                load: wgpu::LoadOp::Clear(1.),
+                store: true,
+            }),
+            stencil_ops: None,
+        }
+    }
+}
@@ -0,0 +1,330 @@
+//! the clock every chapter animates on, one that can be paused, stepped a frame at a time,
+//! slowed down and scrubbed back and forth. the driver owns the one [`Timeline`], ticks it
+//! before each frame and hands its time to the chapter in [`Frame::time`] and
+//! [`Frame::delta`]. everything that moves reads those instead of its own `Instant`, so it all
+//! stops and rewinds together, a shader's time uniform included. what follows the input and
+//! not the scene, a fly camera or a stats line every second, reads [`Frame::real_delta`] and
+//! keeps going while the scene is paused. the keys are function keys, the chapters have the
+//! letters, space and the arrows
+//! - F5 pauses and resumes
+//! - F6 and F7 step one [`STEP`] back or forward, and pause
+//! - F3 and F4 scrub [`SCRUB`] seconds back or forward, held down they keep going
+//! - F8 slows down to half, a quarter, a tenth and back to full speed
+//!
+//! [`Frame::time`]: crate::Frame::time
+//! [`Frame::delta`]: crate::Frame::delta
+//! [`Frame::real_delta`]: crate::Frame::real_delta
+//!
+//! time never goes below 0. what can be worked out from the time alone, an angle, a scale, a
+//! color, just follows it back. what builds on the frame before, particles or anything else
+//! integrated a step at a time, goes in a [`Resimulated`], which replays it from a checkpoint
+//! when the time goes back.
+//!
+//! ```ignore
+//! let mut particles = Resimulated::new(spawn(), timeline::STEP);
+//! // in App::render
+//! let particles = particles.advance_to(frame.time, |particles, _, step| {
+//!     particles.iter_mut().for_each(|particle| particle.update(step))
+//! });
+//! ```
+
+use std::time::Instant;
+
+use tracing::info;
+use winit::event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent};
+
+/// a frame at 60 fps, what F6 and F7 step
+pub const STEP: f32 = 1. / 60.;
+/// in seconds, how far one press of F3 or F4 scrubs
+pub const SCRUB: f32 = 0.25;
+/// the speeds F8 goes through
+pub const SPEEDS: [f32; 4] = [1., 0.5, 0.25, 0.1];
+
+pub struct Timeline {
+    time: f32,
+    delta: f32,
+    /// wall clock seconds between the last two ticks
+    real_delta: f32,
+    /// index into SPEEDS
+    speed: usize,
+    paused: bool,
+    /// steps and scrubs asked for since the last tick, in seconds
+    pending: f32,
+    last_tick: Option<Instant>,
+}
+
+impl Default for Timeline {
+    fn default() -> Timeline {
+        Timeline::new()
+    }
+}
+
+impl Timeline {
+    pub fn new() -> Timeline {
+        Timeline {
+            time: 0.,
+            delta: 0.,
+            real_delta: 0.,
+            speed: 0,
+            paused: false,
+            pending: 0.,
+            last_tick: None,
+        }
+    }
+
+    /// seconds on the timeline, as of the last [`Timeline::tick`]
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// how far the last tick moved the time, 0 while paused and negative going back
+    pub fn delta(&self) -> f32 {
+        self.delta
+    }
+
+    /// wall clock seconds between the last two ticks, paused or not and at any speed
+    pub fn real_delta(&self) -> f32 {
+        self.real_delta
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn speed(&self) -> f32 {
+        SPEEDS[self.speed]
+    }
+
+    /// move the time on by how long it's been since the last tick, at the current speed, plus
+    /// whatever was stepped or scrubbed since. once a frame, before anything reads the time.
+    /// the first tick doesn't move it
+    pub fn tick(&mut self) -> f32 {
+        let now = Instant::now();
+        let real = self
+            .last_tick
+            .map_or(0., |last| now.duration_since(last).as_secs_f32());
+        self.last_tick = Some(now);
+        self.tick_by(real)
+    }
+
+    fn tick_by(&mut self, real: f32) -> f32 {
+        self.real_delta = real;
+        let running = if self.paused { 0. } else { real * self.speed() };
+        let before = self.time;
+        self.time = (self.time + running + self.pending).max(0.);
+        self.pending = 0.;
+        self.delta = self.time - before;
+        self.delta
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// `frames` [`STEP`]s forward, or back when negative, on the next tick. pauses so they can
+    /// be looked at
+    pub fn step(&mut self, frames: i32) {
+        self.paused = true;
+        self.pending += frames as f32 * STEP;
+    }
+
+    /// `seconds` forward or back on the next tick, running or paused
+    pub fn scrub(&mut self, seconds: f32) {
+        self.pending += seconds;
+    }
+
+    /// the next slower speed, from the slowest back to full
+    pub fn slower(&mut self) {
+        self.speed = (self.speed + 1) % SPEEDS.len();
+    }
+
+    /// the keys in the module docs, true if the event was one of them
+    pub fn process_window_event(&mut self, event: &WindowEvent) -> bool {
+        let keycode = match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(keycode),
+                        ..
+                    },
+                ..
+            } => *keycode,
+            _ => return false,
+        };
+
+        match keycode {
+            VirtualKeyCode::F5 => self.toggle_pause(),
+            VirtualKeyCode::F7 => self.step(1),
+            VirtualKeyCode::F6 => self.step(-1),
+            VirtualKeyCode::F4 => self.scrub(SCRUB),
+            VirtualKeyCode::F3 => self.scrub(-SCRUB),
+            VirtualKeyCode::F8 => self.slower(),
+            _ => return false,
+        }
+        info!(
+            "timeline {:.2}s, {}, speed {}",
+            self.time + self.pending,
+            if self.paused { "paused" } else { "running" },
+            self.speed()
+        );
+        true
+    }
+}
+
+// more than this and every other checkpoint goes, the older history gets coarser
+const MAX_CHECKPOINTS: usize = 1024;
+// steps between checkpoints
+const CHECKPOINT_STEPS: u64 = 60;
+
+/// state that's advanced in fixed steps and follows a [`Timeline`] back by replaying it. a
+/// copy of the state is kept every 60 steps, going back starts again from the last one before
+/// the time asked for, so a rewind replays at most the gap between two copies. that's 60 steps
+/// for the first 1024 copies, about 17 minutes at 60 steps a second. past that every other copy
+/// goes each time they fill up again, the gaps in the older history keep doubling and a rewind
+/// far back can replay thousands of steps, while one into the last few seconds stays short. the
+/// steps have to come out the same every time for the replay to match
+pub struct Resimulated<S> {
+    step: f32,
+    state: S,
+    /// taken since time 0
+    steps: u64,
+    /// (steps, state), the first is the initial state
+    checkpoints: Vec<(u64, S)>,
+}
+
+impl<S: Clone> Resimulated<S> {
+    /// `initial` at time 0, advanced `step` seconds at a time
+    pub fn new(initial: S, step: f32) -> Resimulated<S> {
+        Resimulated {
+            step,
+            state: initial.clone(),
+            steps: 0,
+            checkpoints: vec![(0, initial)],
+        }
+    }
+
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// the state at `time`, the last whole step before it. `update(state, time, step)`
+    /// advances the state by one step from `time`
+    pub fn advance_to(&mut self, time: f32, mut update: impl FnMut(&mut S, f32, f32)) -> &S {
+        let target = (time.max(0.) / self.step) as u64;
+        if target < self.steps {
+            let index = self
+                .checkpoints
+                .partition_point(|(steps, _)| *steps <= target)
+                - 1;
+            // past it the steps run again, with whatever they read now
+            self.checkpoints.truncate(index + 1);
+            let (steps, state) = &self.checkpoints[index];
+            self.steps = *steps;
+            self.state = state.clone();
+        }
+
+        while self.steps < target {
+            update(&mut self.state, self.steps as f32 * self.step, self.step);
+            self.steps += 1;
+            if self.steps.is_multiple_of(CHECKPOINT_STEPS) {
+                self.checkpoints.push((self.steps, self.state.clone()));
+                if self.checkpoints.len() > MAX_CHECKPOINTS {
+                    let mut index = 0;
+                    self.checkpoints.retain(|_| {
+                        index += 1;
+                        index % 2 == 1
+                    });
+                }
+            }
+        }
+        &self.state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn pauses_steps_scrubs_and_slows() {
+        let mut timeline = Timeline::new();
+        timeline.tick_by(1.);
+        assert_eq!(timeline.time(), 1.);
+        assert_eq!(timeline.real_delta(), 1.);
+
+        timeline.toggle_pause();
+        assert_eq!(timeline.tick_by(1.), 0.);
+        // the wall clock doesn't stop
+        assert_eq!(timeline.real_delta(), 1.);
+
+        timeline.step(3);
+        timeline.step(-1);
+        assert_close(timeline.tick_by(1.), 2. * STEP);
+        assert!(timeline.paused());
+
+        timeline.toggle_pause();
+        timeline.slower();
+        timeline.scrub(-0.5);
+        assert_close(timeline.tick_by(0.5), 0.25 - 0.5);
+
+        // never before the start
+        timeline.scrub(-10.);
+        timeline.tick_by(0.);
+        assert_eq!(timeline.time(), 0.);
+    }
+
+    // a ball under gravity, a step at a time
+    fn fall(state: &mut (f32, f32), _: f32, step: f32) {
+        state.1 -= 9.8 * step;
+        state.0 += state.1 * step;
+    }
+
+    #[test]
+    fn going_back_replays_the_same_states() {
+        let mut forward = Resimulated::new((10., 0.), STEP);
+        let at_1 = *forward.advance_to(1.5, fall);
+        let at_3 = *forward.advance_to(3.2, fall);
+
+        let mut steps = 0;
+        assert_eq!(
+            *forward.advance_to(1.5, |state, time, step| {
+                steps += 1;
+                fall(state, time, step)
+            }),
+            at_1
+        );
+        // from the checkpoint at 1 second, not from 0
+        assert!(steps < CHECKPOINT_STEPS as usize);
+        assert_eq!(*forward.advance_to(3.2, fall), at_3);
+    }
+
+    #[test]
+    fn thins_out_old_checkpoints() {
+        let mut resimulated = Resimulated::new(0u64, 1.);
+        let end = CHECKPOINT_STEPS * MAX_CHECKPOINTS as u64 * 3;
+        resimulated.advance_to(end as f32, |count, _, _| *count += 1);
+        assert!(resimulated.checkpoints.len() <= MAX_CHECKPOINTS);
+        // the old gaps doubled with every thinning, the newest is still at most one apart
+        let gaps: Vec<u64> = resimulated
+            .checkpoints
+            .windows(2)
+            .map(|pair| pair[1].0 - pair[0].0)
+            .collect();
+        assert!(gaps.iter().all(|gap| {
+            gap % CHECKPOINT_STEPS == 0 && (gap / CHECKPOINT_STEPS).is_power_of_two()
+        }));
+        assert!(gaps.iter().max() > Some(&(CHECKPOINT_STEPS * 2)));
+        assert!(gaps.last() <= Some(&(CHECKPOINT_STEPS * 2)));
+        assert_eq!(*resimulated.advance_to(5., |count, _, _| *count += 1), 5);
+    }
+}
@@ -0,0 +1,628 @@
+//! the second half of hdr rendering: the scene draws into an [`HDR_FORMAT`] target where light
+//! can go past 1, then [`Tonemap`] covers the window with it, scaled by an exposure and squeezed
+//! into 0..1 by a [`Curve`].
+//!
+//! ```ignore
+//! let hdr = Texture::create_render_target(device, width, height, tonemap::HDR_FORMAT, "hdr");
+//! let tonemap = Tonemap::new(device, config.format);
+//! let bind_group = tonemap.create_bind_group(device, &hdr);
+//! // every frame, after the scene pass into `hdr.view`
+//! tonemap.draw(&mut rpass, &bind_group);
+//! ```
+//!
+//! [`HDR_FORMAT`] works on every adapter webgpu allows, [`hdr_format`] still asks the adapter
+//! and falls back through [`HDR_CANDIDATES`], and [`log_hdr_formats`] shows what it supports.
+//!
+//! where the target can't be filtered [`path`] picks [`ComputeTonemap`] instead, a compute
+//! pass that loads texels and writes them into a storage texture, blitted into the window.
+//! [`ComputeTonemap::tuned`] times it with a few workgroup sizes first, see [`autotune`].
+
+use bytemuck::{Pod, Zeroable};
+use wgpu::{include_wgsl, util::DeviceExt};
+
+use tracing::info;
+
+use crate::{autotune, blit::Blit, compat, screenshot::DumpTarget, texture::Texture, watchdog};
+
+/// half floats, enough range for sunlight next to shadow and filterable everywhere
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// what an hdr target can be, best first. rg11b10 is half the memory without alpha, rgb10a2
+/// stops at 1 and is only there to keep 10 bits of precision when nothing else renders
+pub const HDR_CANDIDATES: [wgpu::TextureFormat; 3] = [
+    HDR_FORMAT,
+    wgpu::TextureFormat::Rg11b10Float,
+    wgpu::TextureFormat::Rgb10a2Unorm,
+];
+
+/// the first of [`HDR_CANDIDATES`] the adapter can draw into, blend into and sample filtered,
+/// [`HDR_FORMAT`] if none of them says so
+pub fn hdr_format(adapter: &wgpu::Adapter) -> wgpu::TextureFormat {
+    HDR_CANDIDATES
+        .into_iter()
+        .find(|format| {
+            let features = adapter.get_texture_format_features(*format);
+            features.allowed_usages.contains(
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            ) && features.flags.contains(
+                wgpu::TextureFormatFeatureFlags::FILTERABLE
+                    | wgpu::TextureFormatFeatureFlags::BLENDABLE,
+            )
+        })
+        .unwrap_or(HDR_FORMAT)
+}
+
+/// one line per candidate with what the adapter can do with it
+pub fn log_hdr_formats(adapter: &wgpu::Adapter) {
+    use wgpu::{TextureFormatFeatureFlags as Flags, TextureUsages as Usages};
+
+    info!("hdr formats on {}", adapter.get_info().name);
+    for format in HDR_CANDIDATES {
+        let features = adapter.get_texture_format_features(format);
+        let usage = |usage| yes_no(features.allowed_usages.contains(usage));
+        let flag = |flag| yes_no(features.flags.contains(flag));
+        info!(
+            "  {:?}: render {}, storage {}, filter {}, blend {}, msaa {}{}",
+            format,
+            usage(Usages::RENDER_ATTACHMENT),
+            usage(Usages::STORAGE_BINDING),
+            flag(Flags::FILTERABLE),
+            flag(Flags::BLENDABLE),
+            flag(Flags::MULTISAMPLE),
+            if format == hdr_format(adapter) {
+                ", picked"
+            } else {
+                ""
+            },
+        );
+    }
+}
+
+fn yes_no(supported: bool) -> &'static str {
+    if supported {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// how values above 1 are brought into range
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    /// no curve at all, what drawing straight into the window does
+    Clamp,
+    /// x / (1 + x), soft but never quite white
+    Reinhard,
+    /// a fit of the ACES filmic curve, more contrast and highlights that reach white
+    Aces,
+}
+
+impl Curve {
+    /// the one after this, wrapping around
+    pub fn next(self) -> Curve {
+        match self {
+            Curve::Clamp => Curve::Reinhard,
+            Curve::Reinhard => Curve::Aces,
+            Curve::Aces => Curve::Clamp,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Curve::Clamp => "clamp",
+            Curve::Reinhard => "reinhard",
+            Curve::Aces => "aces",
+        }
+    }
+}
+
+// matches `Tonemap` in tonemap.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct TonemapUniform {
+    curve: u32,
+    exposure: f32,
+    _padding: [f32; 2],
+}
+
+pub struct Tonemap {
+    pub curve: Curve,
+    /// the scene is multiplied by it before the curve, 1 leaves it as it is
+    pub exposure: f32,
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+}
+
+impl Tonemap {
+    /// `target_format` is the format of the texture the pass draws into, aces at exposure 1
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Tonemap {
+        let curve = Curve::Aces;
+        let exposure = 1.;
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tonemap Buffer"),
+            contents: bytemuck::bytes_of(&TonemapUniform {
+                curve: curve as u32,
+                exposure,
+                _padding: [0.; 2],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("tonemap_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("tonemap.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("tonemap"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(target_format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Tonemap {
+            curve,
+            exposure,
+            layout,
+            pipeline,
+            buffer,
+        }
+    }
+
+    /// reads `source` through its own sampler, make a new one when the target is recreated
+    pub fn create_bind_group(&self, device: &wgpu::Device, source: &Texture) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("tonemap_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&source.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    /// upload `curve` and `exposure`, call after changing them and before the pass
+    pub fn update(&self, queue: &wgpu::Queue) {
+        let uniform = TonemapUniform {
+            curve: self.curve as u32,
+            exposure: self.exposure,
+            _padding: [0.; 2],
+        };
+        watchdog::write_buffer(queue, &self.buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    /// covers the current viewport of `rpass` with the tonemapped source of `bind_group`
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, bind_group: &'a wgpu::BindGroup) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+/// where the tonemapping runs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Path {
+    /// [`Tonemap`], a fullscreen triangle sampling the hdr target
+    Fragment,
+    /// [`ComputeTonemap`], a compute pass into a storage texture and a blit into the window
+    Compute,
+}
+
+impl Path {
+    pub fn name(self) -> &'static str {
+        match self {
+            Path::Fragment => "fragment",
+            Path::Compute => "compute",
+        }
+    }
+}
+
+// what the compute path writes, core webgpu has rgba16float storage and it filters for the blit
+const COMPUTE_OUTPUT_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// whether the device can run [`ComputeTonemap`] at all: compute shaders, which downlevel gl
+/// and the webgl2 profile lack, and a storage texture to write into. never on gl, wgpu-hal
+/// 0.14 panics there on the storage image the pipeline binds
+pub fn compute_supported(adapter: &wgpu::Adapter, device: &wgpu::Device) -> bool {
+    adapter.get_info().backend != wgpu::Backend::Gl
+        && compat::compute(device)
+        && device.limits().max_storage_textures_per_shader_stage > 0
+        && adapter
+            .get_texture_format_features(COMPUTE_OUTPUT_FORMAT)
+            .allowed_usages
+            .contains(wgpu::TextureUsages::STORAGE_BINDING)
+}
+
+/// the fragment path whenever the adapter can filter `hdr_format`, the compute path, which
+/// only loads texels, when it can't. surfaces can't be storage textures, so the compute path
+/// always goes through a texture of its own
+pub fn path(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    hdr_format: wgpu::TextureFormat,
+) -> Path {
+    let filterable = adapter
+        .get_texture_format_features(hdr_format)
+        .flags
+        .contains(wgpu::TextureFormatFeatureFlags::FILTERABLE);
+    if !filterable && compute_supported(adapter, device) {
+        Path::Compute
+    } else {
+        Path::Fragment
+    }
+}
+
+/// what [`ComputeTonemap::tuned`] chooses from, all within webgpu's default limits
+pub const WORKGROUP_SIZES: [[u32; 3]; 5] =
+    [[8, 8, 1], [16, 16, 1], [16, 8, 1], [32, 8, 1], [64, 1, 1]];
+
+/// the tonemap of [`Tonemap`] as a compute pass. it reads `curve` and `exposure` from the
+/// `Tonemap` its targets are made with, so both paths stay in step
+pub struct ComputeTonemap {
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::ComputePipeline,
+    workgroup_size: [u32; 3],
+    blit: Blit,
+}
+
+/// the storage texture the compute pass writes, and the bind groups on either side of it.
+/// make a new one when the hdr target is recreated
+pub struct ComputeTarget {
+    output: Texture,
+    width: u32,
+    height: u32,
+    bind_group: wgpu::BindGroup,
+    blit_bind_group: wgpu::BindGroup,
+}
+
+impl ComputeTonemap {
+    /// `target_format` is the format of the texture the blit draws into, 8x8 workgroups
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> ComputeTonemap {
+        Self::with_workgroup_size(device, target_format, [8, 8, 1])
+    }
+
+    /// [`ComputeTonemap::new`] with the workgroup size [`autotune::pick`] measures fastest on
+    /// this adapter, out of [`WORKGROUP_SIZES`]
+    pub fn tuned(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target_format: wgpu::TextureFormat,
+    ) -> ComputeTonemap {
+        // a 1080p frame to time it on, the size of the work matters more than what's in it
+        let (width, height) = (1920, 1080);
+        let tonemap = Tonemap::new(device, target_format);
+        let source =
+            Texture::create_render_target(device, width, height, HDR_FORMAT, "autotune_hdr");
+        let size = autotune::pick(
+            adapter,
+            device,
+            queue,
+            "compute_tonemap",
+            &WORKGROUP_SIZES,
+            |size| {
+                let kernel = Self::with_workgroup_size(device, target_format, size);
+                let target = kernel.create_target(device, &tonemap, &source, width, height);
+                move |encoder: &mut wgpu::CommandEncoder| kernel.dispatch(encoder, &target)
+            },
+        );
+        Self::with_workgroup_size(device, target_format, size)
+    }
+
+    /// workgroups of `workgroup_size` texels, the shader is rewritten to match
+    pub fn with_workgroup_size(
+        device: &wgpu::Device,
+        target_format: wgpu::TextureFormat,
+        workgroup_size: [u32; 3],
+    ) -> ComputeTonemap {
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("compute_tonemap_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: COMPUTE_OUTPUT_FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("tonemap.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(
+                autotune::with_workgroup_size(include_str!("tonemap.wgsl"), workgroup_size).into(),
+            ),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("compute tonemap"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        ComputeTonemap {
+            layout,
+            pipeline,
+            workgroup_size,
+            blit: Blit::new(device, target_format),
+        }
+    }
+
+    /// `source` is the hdr target, `width` x `height` its size
+    pub fn create_target(
+        &self,
+        device: &wgpu::Device,
+        tonemap: &Tonemap,
+        source: &Texture,
+        width: u32,
+        height: u32,
+    ) -> ComputeTarget {
+        let output = Texture::create_storage_target(
+            device,
+            width,
+            height,
+            COMPUTE_OUTPUT_FORMAT,
+            "tonemap_output",
+        );
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("compute_tonemap_bind_group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&source.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: tonemap.buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&output.view),
+                },
+            ],
+        });
+        let blit_bind_group = self
+            .blit
+            .create_bind_group(device, &output.view, &output.sampler);
+
+        ComputeTarget {
+            output,
+            width,
+            height,
+            bind_group,
+            blit_bind_group,
+        }
+    }
+
+    /// tonemap the hdr target of `target` into its storage texture, before the pass `draw`
+    /// goes into. `Tonemap::update` still uploads the curve and exposure
+    pub fn dispatch(&self, encoder: &mut wgpu::CommandEncoder, target: &ComputeTarget) {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("compute tonemap pass"),
+        });
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &target.bind_group, &[]);
+        let [x, y, _] = self.workgroup_size;
+        cpass.dispatch_workgroups(target.width.div_ceil(x), target.height.div_ceil(y), 1);
+    }
+
+    /// covers the current viewport of `rpass` with what `dispatch` wrote
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>, target: &'a ComputeTarget) {
+        self.blit.draw(rpass, &target.blit_bind_group);
+    }
+}
+
+impl ComputeTarget {
+    /// the storage texture, for [`crate::App::dump_targets`]
+    pub fn dump_target(&self) -> DumpTarget<'_> {
+        DumpTarget::new(
+            "tonemap output",
+            &self.output.texture,
+            self.width,
+            self.height,
+            COMPUTE_OUTPUT_FORMAT,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::warn;
+
+    use crate::testing::{f16_to_f32, headless, read_texture};
+
+    // the curves of tonemap.wgsl
+    fn map(curve: Curve, exposure: f32, hdr: f32) -> f32 {
+        let x = hdr * exposure;
+        match curve {
+            Curve::Clamp => x.clamp(0., 1.),
+            Curve::Reinhard => x / (1. + x),
+            Curve::Aces => (x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0., 1.),
+        }
+    }
+
+    // every texel against the curves here, with each size `tuned` picks from, on a target none
+    // of them divides so the last workgroups hang over its edges
+    #[test]
+    fn the_compute_path_matches_the_cpu() {
+        let Some((adapter, device, queue)) = headless() else {
+            return;
+        };
+        if !compute_supported(&adapter, &device) {
+            warn!("no compute tonemap on {:?}", adapter.get_info().backend);
+            return;
+        }
+        let (width, height) = (37, 19);
+        let hdr: Vec<[f32; 4]> = (0..width * height)
+            .map(|i| {
+                let (x, y) = ((i % width) as f32, (i / width) as f32);
+                [x / 8., y / 4., (x + y) / 16., 1.]
+            })
+            .collect();
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        // 32 bit floats, the compute path doesn't need them filterable
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("hdr"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        });
+        queue.write_texture(
+            texture.as_image_copy(),
+            bytemuck::cast_slice(&hdr),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(width * 16),
+                rows_per_image: None,
+            },
+            size,
+        );
+        let source = Texture {
+            view: texture.create_view(&wgpu::TextureViewDescriptor::default()),
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor::default()),
+            texture,
+        };
+
+        let target_format = wgpu::TextureFormat::Rgba8Unorm;
+        let mut tonemap = Tonemap::new(&device, target_format);
+        for workgroup_size in WORKGROUP_SIZES {
+            let kernel =
+                ComputeTonemap::with_workgroup_size(&device, target_format, workgroup_size);
+            let target = kernel.create_target(&device, &tonemap, &source, width, height);
+            for (curve, exposure) in [
+                (Curve::Clamp, 1.),
+                (Curve::Reinhard, 0.5),
+                (Curve::Aces, 2.),
+            ] {
+                tonemap.curve = curve;
+                tonemap.exposure = exposure;
+                tonemap.update(&queue);
+                let mut encoder =
+                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                kernel.dispatch(&mut encoder, &target);
+                queue.submit(Some(encoder.finish()));
+
+                let output: Vec<[u16; 4]> = read_texture(
+                    &device,
+                    &queue,
+                    &target.output.texture,
+                    (width, height),
+                    0,
+                    0,
+                );
+                for (i, (texel, hdr)) in output.iter().zip(&hdr).enumerate() {
+                    for channel in 0..3 {
+                        let expected = map(curve, exposure, hdr[channel]);
+                        let got = f16_to_f32(texel[channel]);
+                        assert!(
+                            (got - expected).abs() < 2e-3,
+                            "{:?} {:?}: texel {} channel {} is {}, expected {}",
+                            workgroup_size,
+                            curve,
+                            i,
+                            channel,
+                            got,
+                            expected
+                        );
+                    }
+                    assert_eq!(f16_to_f32(texel[3]), 1.);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+// squeezes an hdr scene into the 0..1 the window can show, one fullscreen triangle
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Tonemap {
+    // 0 clamp, 1 reinhard, 2 aces, the order of `Curve`
+    curve: u32,
+    exposure: f32,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragInput.tex_coord = uv;
+    return fragInput;
+}
+
+@group(0) @binding(0)
+var t_hdr: texture_2d<f32>;
+@group(0) @binding(1)
+var s_hdr: sampler;
+@group(0) @binding(2)
+var<uniform> tonemap: Tonemap;
+
+// the simplest curve, x / (1 + x). never reaches 1, so highlights stay a little grey
+fn reinhard(color: vec3<f32>) -> vec3<f32> {
+    return color / (1.0 + color);
+}
+
+// Krzysztof Narkowicz's fit of the ACES filmic curve: a toe that deepens the shadows and a
+// shoulder that rolls highlights off to white
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp(color * (a * color + b) / (color * (c * color + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+fn map(hdr: vec3<f32>) -> vec3<f32> {
+    let color = hdr * tonemap.exposure;
+    switch tonemap.curve {
+        case 1u: {
+            return reinhard(color);
+        }
+        case 2u: {
+            return aces(color);
+        }
+        default: {
+            // what writing straight to the window does, everything above 1 is lost
+            return clamp(color, vec3<f32>(0.0), vec3<f32>(1.0));
+        }
+    }
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let hdr = textureSample(t_hdr, s_hdr, input.tex_coord);
+    return vec4<f32>(map(hdr.rgb), hdr.a);
+}
+
+// the compute path of `ComputeTonemap`, one texel each. it loads instead of sampling, so the
+// hdr target doesn't have to be filterable, and writes into a storage texture a blit copies
+// into the window afterwards. `ComputeTonemap` rewrites the workgroup size, 8x8 is its default
+@group(0) @binding(3)
+var t_output: texture_storage_2d<rgba16float, write>;
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(t_output);
+    if (i32(id.x) >= size.x || i32(id.y) >= size.y) {
+        return;
+    }
+    let coord = vec2<i32>(id.xy);
+    let hdr = textureLoad(t_hdr, coord, 0);
+    textureStore(t_output, coord, vec4<f32>(map(hdr.rgb), hdr.a));
+}
@@ -0,0 +1,245 @@
+//! vertex and instance layouts written once, in rust. [`vertex_layout!`](crate::vertex_layout)
+//! gives a type its `ATTRIBUTES`, its `buffer_layout()` and the wgsl struct the vertex shader
+//! reads them through, so the `shader_location`s can't drift from the `@location`s:
+//!
+//! ```ignore
+//! framework::vertex_layout! {
+//!     Transform as "Transform", Instance {
+//!         2 => mat0: Float32x4,
+//!         3 => mat1: Float32x4,
+//!         4 => mat2: Float32x4,
+//!         5 => mat3: Float32x4,
+//!     }
+//! }
+//! ```
+//!
+//! the shader files keep their own copy of the struct, wgsl-check validates them at build time
+//! and they read on their own. [`shader`] swaps that copy for the generated one when the module
+//! is created, and panics when the two are different so a drifted copy can't go unnoticed.
+
+/// gives `$ty` `ATTRIBUTES`, `WGSL`, the struct `$wgsl` with a `@location` per attribute, and
+/// `buffer_layout()`. the stride is the attributes' sizes added up, the data is packed
+#[macro_export]
+macro_rules! vertex_layout {
+    (@wgsl Float32) => { "f32" };
+    (@wgsl Float32x2) => { "vec2<f32>" };
+    (@wgsl Float32x3) => { "vec3<f32>" };
+    (@wgsl Float32x4) => { "vec4<f32>" };
+    (@wgsl Uint32) => { "u32" };
+    (@wgsl Uint32x2) => { "vec2<u32>" };
+    (@wgsl Uint32x3) => { "vec3<u32>" };
+    (@wgsl Uint32x4) => { "vec4<u32>" };
+    (@wgsl Sint32) => { "i32" };
+    (@wgsl Sint32x2) => { "vec2<i32>" };
+    (@wgsl Sint32x3) => { "vec3<i32>" };
+    (@wgsl Sint32x4) => { "vec4<i32>" };
+    (@wgsl Unorm8x4) => { "vec4<f32>" };
+    (@wgsl Snorm8x4) => { "vec4<f32>" };
+    (@wgsl Uint8x4) => { "vec4<u32>" };
+    (@wgsl Float16x2) => { "vec2<f32>" };
+    (@wgsl Float16x4) => { "vec4<f32>" };
+    ($ty:ty as $wgsl:literal, $step_mode:ident {
+        $($location:literal => $field:ident: $format:ident),+ $(,)?
+    }) => {
+        impl $ty {
+            pub const ATTRIBUTES: &'static [wgpu::VertexAttribute] =
+                &wgpu::vertex_attr_array![$($location => $format),+];
+
+            /// generated from `ATTRIBUTES`, `framework::vertex_layout::shader` puts it in
+            pub const WGSL: &'static str = concat!(
+                "struct ", $wgsl, " {\n",
+                $("    @location(", $location, ") ", stringify!($field), ": ",
+                    $crate::vertex_layout!(@wgsl $format), ",\n",)+
+                "};\n",
+            );
+
+            pub fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+                wgpu::VertexBufferLayout {
+                    array_stride: 0 $(+ wgpu::VertexFormat::$format.size())+,
+                    step_mode: wgpu::VertexStepMode::$step_mode,
+                    attributes: Self::ATTRIBUTES,
+                }
+            }
+        }
+    };
+}
+
+/// `source` with each of `structs`, the `WGSL` of a [`vertex_layout!`](crate::vertex_layout),
+/// in place of the shader's own struct of that name, or in front when it has none. panics when
+/// the shader's struct isn't the same as the generated one
+pub fn shader(
+    device: &wgpu::Device,
+    label: &str,
+    source: &str,
+    structs: &[&str],
+) -> wgpu::ShaderModule {
+    let source = splice(label, source, structs).unwrap_or_else(|err| panic!("{}", err));
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    })
+}
+
+fn splice(label: &str, source: &str, structs: &[&str]) -> Result<String, String> {
+    let mut source = source.to_owned();
+    for generated in structs {
+        let name = struct_name(generated).expect("vertex_layout! makes a struct");
+        match find_struct(&source, name) {
+            Some((start, end)) => {
+                if tokens(&source[start..end]) != tokens(generated) {
+                    return Err(format!(
+                        "{}'s `struct {}` isn't the rust layout's, it should be:\n{}",
+                        label, name, generated
+                    ));
+                }
+                source.replace_range(start..end, generated.trim_end());
+            }
+            None => source.insert_str(0, generated),
+        }
+    }
+    Ok(source)
+}
+
+fn struct_name(source: &str) -> Option<&str> {
+    let rest = source.trim_start().strip_prefix("struct ")?;
+    rest.split(|c: char| c == '{' || c.is_whitespace()).next()
+}
+
+// the byte range of `struct name { ... }`, with the `;` after it if there is one. comments are
+// searched as blanks, so a struct or a `}` mentioned in one isn't taken for code
+fn find_struct(source: &str, name: &str) -> Option<(usize, usize)> {
+    let code = blank_comments(source);
+    let mut from = 0;
+    while let Some(offset) = code[from..].find("struct ") {
+        let start = from + offset;
+        from = start + "struct ".len();
+        let word_start = !code[..start].ends_with(|c: char| c.is_alphanumeric() || c == '_');
+        if !word_start || struct_name(&code[start..]) != Some(name) {
+            continue;
+        }
+        let close = start + code[start..].find('}')? + 1;
+        let end = match code[close..].trim_start().strip_prefix(';') {
+            Some(rest) => code.len() - rest.len(),
+            None => close,
+        };
+        return Some((start, end));
+    }
+    None
+}
+
+// `source` with every byte of its `//` and `/* */` comments turned into a space, so offsets
+// into it are offsets into `source`. block comments nest in wgsl
+fn blank_comments(source: &str) -> String {
+    let bytes = source.as_bytes();
+    let mut code = bytes.to_vec();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let pair = (bytes[i], bytes.get(i + 1).copied());
+        let len = match pair {
+            (b'/', Some(b'*')) => {
+                depth += 1;
+                2
+            }
+            (b'*', Some(b'/')) if depth > 0 => {
+                depth -= 1;
+                2
+            }
+            (b'/', Some(b'/')) if depth == 0 => {
+                let line = bytes[i..].iter().position(|&b| b == b'\n');
+                line.unwrap_or(bytes.len() - i)
+            }
+            _ if depth > 0 => 1,
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        for byte in &mut code[i..i + len] {
+            if *byte != b'\n' {
+                *byte = b' ';
+            }
+        }
+        i += len;
+    }
+    String::from_utf8(code).expect("only whole comments are blanked")
+}
+
+// what's left of a struct without comments and whitespace, the trailing `,` and `;` don't count
+fn tokens(source: &str) -> String {
+    let code: String = blank_comments(source)
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    code.trim_end_matches(';').replace(",}", "}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Transform;
+
+    crate::vertex_layout! {
+        Transform as "Transform", Instance {
+            2 => mat0: Float32x4,
+            3 => mat1: Float32x4,
+            4 => mat2: Float32x4,
+            5 => mat3: Float32x4,
+        }
+    }
+
+    #[test]
+    fn generated_layout() {
+        let layout = Transform::buffer_layout();
+        assert_eq!(layout.array_stride, 64);
+        assert_eq!(layout.step_mode, wgpu::VertexStepMode::Instance);
+        let offsets: Vec<_> = layout.attributes.iter().map(|a| a.offset).collect();
+        assert_eq!(offsets, [0, 16, 32, 48]);
+        assert_eq!(
+            Transform::WGSL,
+            "struct Transform {\n    @location(2) mat0: vec4<f32>,\n    \
+             @location(3) mat1: vec4<f32>,\n    @location(4) mat2: vec4<f32>,\n    \
+             @location(5) mat3: vec4<f32>,\n};\n"
+        );
+    }
+
+    #[test]
+    fn splice_replaces_the_shaders_struct() {
+        let source = "struct VertexInput {\n    @location(0) pos: vec3<f32>,\n};\n\n\
+                      // the model matrix\n\
+                      struct Transform {\n    @location(2) mat0:vec4<f32>, // x\n    \
+                      @location(3) mat1:vec4<f32>,\n    @location(4) mat2:vec4<f32>,\n    \
+                      @location(5) mat3:vec4<f32>\n}\n\nfn f() {}\n";
+        let spliced = splice("test", source, &[Transform::WGSL]).unwrap();
+        assert!(spliced.starts_with("struct VertexInput {"));
+        assert!(spliced.contains("// the model matrix\nstruct Transform {"));
+        assert!(spliced.contains("@location(5) mat3: vec4<f32>,\n};\n\nfn f() {}"));
+        assert!(!spliced.contains("// x"));
+
+        // a shader without the struct gets it in front
+        let spliced = splice("test", "fn f() {}\n", &[Transform::WGSL]).unwrap();
+        assert!(spliced.starts_with(Transform::WGSL));
+    }
+
+    #[test]
+    fn splice_fails_on_a_drifted_struct() {
+        let source = "struct Transform {\n    @location(2) mat0: vec4<f32>,\n    \
+                      @location(3) mat1: vec4<f32>,\n    @location(4) mat2: vec4<f32>,\n    \
+                      @location(9) mat3: vec4<f32>,\n};\n";
+        let err = splice("test", source, &[Transform::WGSL]).unwrap_err();
+        assert!(err.contains("test's `struct Transform`"));
+    }
+
+    #[test]
+    fn splice_skips_comments() {
+        // neither the struct named in the comments nor the `}` in one are the shader's
+        let source = "// struct Transform { was here }\n/* struct Transform {} /* nested */ */\n\
+                      struct Transform {\n    @location(2) mat0: vec4<f32>, // {}\n    \
+                      @location(3) mat1: vec4<f32>,\n    @location(4) mat2: vec4<f32>,\n    \
+                      @location(5) mat3: vec4<f32>,\n};\n";
+        let spliced = splice("test", source, &[Transform::WGSL]).unwrap();
+        assert!(spliced.starts_with("// struct Transform { was here }\n/* struct Transform {}"));
+        assert!(spliced.ends_with(Transform::WGSL));
+    }
+}
@@ -0,0 +1,273 @@
+//! pipelines built before the first frame instead of the first time they're needed. a pipeline
+//! bakes in its blend state, sample count, shader entry points and so on, so every variant an
+//! app switches between is its own pipeline, and building one can take tens of milliseconds
+//! while the driver compiles shaders. built on a key press that's a visible hitch.
+//!
+//! [`Pipelines`] is the set of variants, keyed by whatever tells them apart. the app lists the
+//! keys up front and hands [`Pipelines::step`] to the driver from [`App::warmup`]: the driver
+//! calls it once a frame, with a [`ProgressBar`] on screen, and only starts calling
+//! [`App::render`] when every variant is built.
+//!
+//! ```ignore
+//! // in init
+//! let pipelines = Pipelines::new([1, 4]);
+//! // App::warmup
+//! fn warmup(&mut self, device: &wgpu::Device) -> Option<Progress> {
+//!     self.pipelines.step(|&samples| create_pipeline(device, samples))
+//! }
+//! // in render
+//! let pipeline = self.pipelines.get(self.samples, |&samples| create_pipeline(device, samples));
+//! ```
+//!
+//! [`App::warmup`]: crate::App::warmup
+//! [`App::render`]: crate::App::render
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+use bytemuck::{Pod, Zeroable};
+use tracing::{info, warn};
+use wgpu::include_wgsl;
+use wgpu::util::DeviceExt;
+
+use crate::watchdog;
+
+// how long one step keeps building, it always builds at least one. the bar is redrawn between
+// steps, so this is about how often it moves
+const STEP_BUDGET: Duration = Duration::from_millis(30);
+
+/// how far the warm-up is
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    pub fn fraction(self) -> f32 {
+        if self.total == 0 {
+            1.
+        } else {
+            self.done as f32 / self.total as f32
+        }
+    }
+}
+
+/// render pipelines by key, see the module docs
+pub struct Pipelines<K> {
+    built: HashMap<K, wgpu::RenderPipeline>,
+    // the keys still to build, the next one last so it pops off
+    pending: Vec<K>,
+    total: usize,
+    started: Option<Instant>,
+}
+
+impl<K: Debug + Eq + Hash> Pipelines<K> {
+    /// nothing is built yet, `keys` are the variants [`Pipelines::step`] builds
+    pub fn new(keys: impl IntoIterator<Item = K>) -> Pipelines<K> {
+        let mut pending: Vec<K> = Vec::new();
+        for key in keys {
+            if !pending.contains(&key) {
+                pending.push(key);
+            }
+        }
+        pending.reverse();
+        Pipelines {
+            built: HashMap::new(),
+            total: pending.len(),
+            pending,
+            started: None,
+        }
+    }
+
+    /// build the next few variants with `build`, for [`STEP_BUDGET`] or at least one. `None`
+    /// once there's nothing left to build
+    pub fn step(&mut self, mut build: impl FnMut(&K) -> wgpu::RenderPipeline) -> Option<Progress> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let started = *self.started.get_or_insert_with(Instant::now);
+        let step_started = Instant::now();
+        while let Some(key) = self.pending.pop() {
+            let pipeline = build(&key);
+            self.built.insert(key, pipeline);
+            if step_started.elapsed() >= STEP_BUDGET {
+                break;
+            }
+        }
+        if self.pending.is_empty() {
+            info!(
+                "built {} pipelines in {:.1}ms",
+                self.total,
+                started.elapsed().as_secs_f64() * 1000.
+            );
+        }
+        Some(self.progress())
+    }
+
+    pub fn progress(&self) -> Progress {
+        Progress {
+            done: self.total - self.pending.len(),
+            total: self.total,
+        }
+    }
+
+    /// the variant for `key`. one that wasn't listed, or isn't built yet, is built now with
+    /// `build`, and a warning since that's the hitch warming up is there to avoid
+    pub fn get(
+        &mut self,
+        key: K,
+        build: impl FnOnce(&K) -> wgpu::RenderPipeline,
+    ) -> &wgpu::RenderPipeline {
+        self.built.entry(key).or_insert_with_key(|key| {
+            warn!("{:?} wasn't warmed up, building it mid-frame", key);
+            build(key)
+        })
+    }
+
+    /// the variant for `key` if it's built. `get` borrows mutably, this is for drawing several
+    /// variants in one pass once they are
+    pub fn built(&self, key: &K) -> Option<&wgpu::RenderPipeline> {
+        self.built.get(key)
+    }
+}
+
+// matches `Progress` in warmup.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ProgressUniform {
+    fraction: f32,
+    _padding: [f32; 3],
+}
+
+/// what the driver shows while [`App::warmup`](crate::App::warmup) is running, a bar across
+/// an otherwise empty frame
+pub struct ProgressBar {
+    pipeline: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ProgressBar {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> ProgressBar {
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Warmup Progress Buffer"),
+            contents: bytemuck::bytes_of(&ProgressUniform {
+                fraction: 0.,
+                _padding: [0.; 3],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("warmup_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<ProgressUniform>() as u64
+                    ),
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("warmup_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("warmup.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("warmup progress"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        ProgressBar {
+            pipeline,
+            buffer,
+            bind_group,
+        }
+    }
+
+    /// fill `view` with the bar at `progress`
+    pub fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        view: &wgpu::TextureView,
+        progress: Progress,
+    ) {
+        watchdog::write_buffer(
+            queue,
+            &self.buffer,
+            0,
+            bytemuck::bytes_of(&ProgressUniform {
+                fraction: progress.fraction(),
+                _padding: [0.; 3],
+            }),
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("warmup progress"),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("warmup progress pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_keys_are_built_once() {
+        let pipelines = Pipelines::new([1, 4, 1, 8]);
+        assert_eq!(pipelines.progress(), Progress { done: 0, total: 3 });
+        assert_eq!(pipelines.pending, vec![8, 4, 1]);
+        assert_eq!(Progress { done: 1, total: 4 }.fraction(), 0.25);
+        assert_eq!(Pipelines::<u32>::new([]).progress().fraction(), 1.);
+    }
+}
@@ -0,0 +1,43 @@
+// the warm-up progress bar, a thin bar across the middle of the window filled left to right.
+// drawn over a cleared frame, one triangle covers the window
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// matches `ProgressUniform` in warmup.rs
+struct Progress {
+    // done / total, 0 to 1
+    fraction: f32,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragInput.tex_coord = uv;
+    return fragInput;
+}
+
+@group(0) @binding(0)
+var<uniform> progress: Progress;
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let background = vec4<f32>(0.05, 0.05, 0.07, 1.0);
+    // the bar spans 60% of the width, a fixed 8 pixels high
+    let pixel = fwidth(input.tex_coord);
+    let x = (input.tex_coord.x - 0.2) / 0.6;
+    let from_center = abs(input.tex_coord.y - 0.5) / pixel.y;
+    if (x < 0.0 || x > 1.0 || from_center > 4.0) {
+        return background;
+    }
+    if (x < progress.fraction) {
+        return vec4<f32>(0.85, 0.85, 0.9, 1.0);
+    }
+    return vec4<f32>(0.2, 0.2, 0.25, 1.0);
+}
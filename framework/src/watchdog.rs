@@ -0,0 +1,542 @@
+//! per frame budgets, so a change that makes a chapter a lot slower doesn't go unnoticed. the
+//! driver measures every frame against [`Budgets`] and, when one is over, logs a warning and
+//! flashes a colored frame around the window: red for the cpu, yellow for uploads, purple for
+//! the gpu.
+//!
+//! - cpu: how long [`crate::App::render`] takes to record and submit its work
+//! - uploads: the bytes written through [`write_buffer`] and [`write_texture`] or counted with
+//!   [`record_upload`] since the last frame. the framework and every chapter write through
+//!   them instead of calling the queue, so a frame's uploads are all seen, whether they happen
+//!   in `render`, `draw_layer` or an input event
+//! - gpu: from before to after the frame's submissions, with timestamp queries. it's only
+//!   measured where the adapter has [`wgpu::Features::TIMESTAMP_QUERY`], one frame at a time
+//!
+//! [`ENV_VAR`] changes the budgets, e.g. `cpu=4,upload=1,gpu=2` in milliseconds, mebibytes
+//! and milliseconds, or turns the watchdog `off`.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use bytemuck::{Pod, Zeroable};
+use tracing::warn;
+use wgpu::{include_wgsl, util::DeviceExt};
+
+/// environment variable with the budgets, see the module docs
+pub const ENV_VAR: &str = "LEARNWGPU_BUDGETS";
+
+/// the features the gpu timing uses, the driver asks for them when the adapter has them
+pub const FEATURES: wgpu::Features = wgpu::Features::TIMESTAMP_QUERY;
+
+// how long a flash takes to fade out, and the same budget isn't warned about more often
+const FLASH: Duration = Duration::from_millis(500);
+const WARN_EVERY: Duration = Duration::from_secs(1);
+// in pixels
+const BORDER_WIDTH: f32 = 12.;
+
+const MIB: u64 = 1024 * 1024;
+
+static UPLOADED: AtomicU64 = AtomicU64::new(0);
+
+/// count `bytes` as uploaded this frame, for writes that don't go through [`write_buffer`]
+pub fn record_upload(bytes: u64) {
+    UPLOADED.fetch_add(bytes, Ordering::Relaxed);
+}
+
+/// `queue.write_buffer`, counted against the upload budget
+pub fn write_buffer(queue: &wgpu::Queue, buffer: &wgpu::Buffer, offset: u64, data: &[u8]) {
+    record_upload(data.len() as u64);
+    queue.write_buffer(buffer, offset, data);
+}
+
+/// `queue.write_texture`, counted against the upload budget. counts the rows of `data` the
+/// copy spans, so a layout with a wider stride than the copy counts the whole stride
+pub fn write_texture(
+    queue: &wgpu::Queue,
+    texture: wgpu::ImageCopyTexture,
+    data: &[u8],
+    layout: wgpu::ImageDataLayout,
+    size: wgpu::Extent3d,
+) {
+    record_upload(spanned_bytes(data.len(), &layout, size));
+    queue.write_texture(texture, data, layout, size);
+}
+
+// the bytes of a `len` long source the copy reads, from the layout's offset on
+fn spanned_bytes(len: usize, layout: &wgpu::ImageDataLayout, size: wgpu::Extent3d) -> u64 {
+    let rows = layout.rows_per_image.map_or(size.height, |rows| rows.get());
+    let available = (len as u64).saturating_sub(layout.offset);
+    match layout.bytes_per_row {
+        Some(bytes_per_row) => {
+            (bytes_per_row.get() as u64 * rows as u64 * size.depth_or_array_layers as u64)
+                .min(available)
+        }
+        None => available,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Budgets {
+    pub cpu: Duration,
+    pub upload_bytes: u64,
+    pub gpu: Duration,
+}
+
+/// half a 60hz frame each for the cpu and the gpu, and 16 MiB of uploads
+impl Default for Budgets {
+    fn default() -> Self {
+        Budgets {
+            cpu: Duration::from_millis(8),
+            upload_bytes: 16 * MIB,
+            gpu: Duration::from_millis(8),
+        }
+    }
+}
+
+impl Budgets {
+    /// the defaults with what [`ENV_VAR`] sets, `None` when it says `off`. entries that don't
+    /// parse are warned about and left at their default
+    pub fn from_env() -> Option<Budgets> {
+        match std::env::var(ENV_VAR) {
+            Ok(value) => Self::parse(&value),
+            Err(_) => Some(Budgets::default()),
+        }
+    }
+
+    fn parse(value: &str) -> Option<Budgets> {
+        if value.trim() == "off" {
+            return None;
+        }
+        let mut budgets = Budgets::default();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let parsed = entry
+                .split_once('=')
+                .and_then(|(key, number)| Some((key.trim(), number.trim().parse::<f64>().ok()?)))
+                .filter(|&(_, number)| number >= 0.);
+            match parsed {
+                Some(("cpu", ms)) => budgets.cpu = Duration::from_secs_f64(ms / 1000.),
+                Some(("upload", mib)) => budgets.upload_bytes = (mib * MIB as f64) as u64,
+                Some(("gpu", ms)) => budgets.gpu = Duration::from_secs_f64(ms / 1000.),
+                _ => warn!(
+                    "{}: can't read `{}`, expected cpu=, upload= or gpu=",
+                    ENV_VAR, entry
+                ),
+            }
+        }
+        Some(budgets)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Budget {
+    Cpu,
+    Upload,
+    Gpu,
+}
+
+impl Budget {
+    fn color(self) -> [f32; 3] {
+        match self {
+            Budget::Cpu => [1., 0.15, 0.1],
+            Budget::Upload => [1., 0.85, 0.1],
+            Budget::Gpu => [0.7, 0.2, 1.],
+        }
+    }
+}
+
+// matches `Flash` in watchdog.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct FlashUniform {
+    color: [f32; 4],
+    width: f32,
+    _padding: [f32; 3],
+}
+
+// two timestamps around the frame's submissions, read back without waiting
+struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buf: wgpu::Buffer,
+    read_buf: wgpu::Buffer,
+    // nanoseconds per tick
+    period: f32,
+    // the read buffer is mapped or on its way, no new measurement until it's read
+    in_flight: bool,
+    // started this frame
+    started: bool,
+    mapped_sender: mpsc::Sender<Result<(), wgpu::BufferAsyncError>>,
+    mapped_receiver: mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+impl GpuTimer {
+    fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<GpuTimer> {
+        if !device.features().contains(FEATURES) {
+            return None;
+        }
+        let size = 2 * std::mem::size_of::<u64>() as u64;
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("watchdog timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        // resolving writes into the buffer like a copy does, and it can't be mapped directly
+        let resolve_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Watchdog Resolve Buffer"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let read_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Watchdog Read Buffer"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let (mapped_sender, mapped_receiver) = mpsc::channel();
+        Some(GpuTimer {
+            query_set,
+            resolve_buf,
+            read_buf,
+            period: queue.get_timestamp_period(),
+            in_flight: false,
+            started: false,
+            mapped_sender,
+            mapped_receiver,
+        })
+    }
+
+    // the measurement that finished mapping since the last call, if any
+    fn poll(&mut self, device: &wgpu::Device) -> Option<Duration> {
+        if !self.in_flight {
+            return None;
+        }
+        device.poll(wgpu::Maintain::Poll);
+        let result = self.mapped_receiver.try_recv().ok()?;
+        self.in_flight = false;
+        if let Err(err) = result {
+            warn!("Fail to map watchdog timestamps: {}", err);
+            return None;
+        }
+
+        let ticks = {
+            let data = self.read_buf.slice(..).get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data);
+            timestamps[1].saturating_sub(timestamps[0])
+        };
+        self.read_buf.unmap();
+        Some(Duration::from_nanos(
+            (ticks as f64 * self.period as f64) as u64,
+        ))
+    }
+
+    fn begin(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.started = !self.in_flight;
+        if !self.started {
+            return;
+        }
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("watchdog begin"),
+        });
+        encoder.write_timestamp(&self.query_set, 0);
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn end(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.started {
+            return;
+        }
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("watchdog end"),
+        });
+        encoder.write_timestamp(&self.query_set, 1);
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buf, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buf, 0, &self.read_buf, 0, 16);
+        queue.submit(Some(encoder.finish()));
+
+        let sender = self.mapped_sender.clone();
+        self.read_buf
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        self.in_flight = true;
+    }
+}
+
+pub struct Watchdog {
+    budgets: Budgets,
+    timer: Option<GpuTimer>,
+    render_started: Instant,
+    // the last breach, for the flash
+    flash: Option<(Budget, Instant)>,
+    warned: Vec<(Budget, Instant)>,
+    pipeline: wgpu::RenderPipeline,
+    flash_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl Watchdog {
+    /// `format` is the window's, the flash draws straight into it
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        budgets: Budgets,
+    ) -> Watchdog {
+        let flash_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Watchdog Flash Buffer"),
+            contents: bytemuck::bytes_of(&FlashUniform {
+                color: [0.; 4],
+                width: BORDER_WIDTH,
+                _padding: [0.; 3],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("watchdog_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<FlashUniform>() as u64
+                    ),
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("watchdog_bind_group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: flash_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("watchdog.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("watchdog flash"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // the app's textures and meshes went up in `init`, they aren't the first frame's
+        UPLOADED.store(0, Ordering::Relaxed);
+        Watchdog {
+            budgets,
+            timer: GpuTimer::new(device, queue),
+            render_started: Instant::now(),
+            flash: None,
+            warned: Vec::new(),
+            pipeline,
+            flash_buffer,
+            bind_group,
+        }
+    }
+
+    /// call right before [`crate::App::render`]
+    pub fn begin_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(gpu) = self.timer.as_mut().and_then(|timer| timer.poll(device)) {
+            if gpu > self.budgets.gpu {
+                self.breach(
+                    Budget::Gpu,
+                    format!(
+                        "the gpu took {:.2} ms, over its {:.2} ms budget",
+                        ms(gpu),
+                        ms(self.budgets.gpu)
+                    ),
+                );
+            }
+        }
+        if let Some(timer) = &mut self.timer {
+            timer.begin(device, queue);
+        }
+        self.render_started = Instant::now();
+    }
+
+    /// call right after [`crate::App::render`]
+    pub fn end_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let cpu = self.render_started.elapsed();
+        // everything since the last frame, events and layers included
+        let uploaded = UPLOADED.swap(0, Ordering::Relaxed);
+        if let Some(timer) = &mut self.timer {
+            timer.end(device, queue);
+        }
+
+        if cpu > self.budgets.cpu {
+            self.breach(
+                Budget::Cpu,
+                format!(
+                    "render took {:.2} ms on the cpu, over its {:.2} ms budget",
+                    ms(cpu),
+                    ms(self.budgets.cpu)
+                ),
+            );
+        }
+        if uploaded > self.budgets.upload_bytes {
+            self.breach(
+                Budget::Upload,
+                format!(
+                    "{:.2} MiB uploaded in a frame, over the {:.2} MiB budget",
+                    uploaded as f64 / MIB as f64,
+                    self.budgets.upload_bytes as f64 / MIB as f64
+                ),
+            );
+        }
+    }
+
+    fn breach(&mut self, budget: Budget, message: String) {
+        let now = Instant::now();
+        self.flash = Some((budget, now));
+        match self.warned.iter_mut().find(|(b, _)| *b == budget) {
+            Some((_, last)) if now - *last < WARN_EVERY => {}
+            Some((_, last)) => {
+                *last = now;
+                warn!("{}", message);
+            }
+            None => {
+                self.warned.push((budget, now));
+                warn!("{}", message);
+            }
+        }
+    }
+
+    /// whether [`Watchdog::draw`] has a flash to draw
+    pub fn flashing(&self) -> bool {
+        self.flash.is_some()
+    }
+
+    /// the flash over the finished frame in `view`, while one is fading out. not measured
+    pub fn draw(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, view: &wgpu::TextureView) {
+        let Some((budget, started)) = self.flash else {
+            return;
+        };
+        let fade = 1. - started.elapsed().as_secs_f32() / FLASH.as_secs_f32();
+        if fade <= 0. {
+            self.flash = None;
+            return;
+        }
+
+        let [r, g, b] = budget.color();
+        let flash = FlashUniform {
+            color: [r, g, b, fade],
+            width: BORDER_WIDTH,
+            _padding: [0.; 3],
+        };
+        queue.write_buffer(&self.flash_buffer, 0, bytemuck::bytes_of(&flash));
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("watchdog flash"),
+        });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("watchdog flash pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn texture_uploads_count_the_rows_they_span() {
+        let size = |width, height, depth_or_array_layers| wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers,
+        };
+        let layout = |offset, bytes_per_row, rows_per_image| wgpu::ImageDataLayout {
+            offset,
+            bytes_per_row: std::num::NonZeroU32::new(bytes_per_row),
+            rows_per_image: std::num::NonZeroU32::new(rows_per_image),
+        };
+        // a whole 64x32 rgba image
+        assert_eq!(
+            spanned_bytes(8192, &layout(0, 256, 0), size(64, 32, 1)),
+            8192
+        );
+        // six faces of a cube map
+        assert_eq!(
+            spanned_bytes(6 * 1024, &layout(0, 64, 16), size(16, 16, 6)),
+            6 * 1024
+        );
+        // 4 rows out of the middle of a wider image, at its stride
+        assert_eq!(
+            spanned_bytes(8192, &layout(1024, 256, 0), size(8, 4, 1)),
+            1024
+        );
+        // never more than is left after the offset
+        assert_eq!(
+            spanned_bytes(8192, &layout(8000, 256, 0), size(64, 32, 1)),
+            192
+        );
+        // a single row without a stride
+        assert_eq!(spanned_bytes(256, &layout(0, 0, 0), size(64, 1, 1)), 256);
+    }
+
+    #[test]
+    fn parse_budgets() {
+        assert_eq!(Budgets::parse("off"), None);
+        assert_eq!(Budgets::parse(""), Some(Budgets::default()));
+        assert_eq!(
+            Budgets::parse("cpu=4, upload=0.5,gpu=2"),
+            Some(Budgets {
+                cpu: Duration::from_millis(4),
+                upload_bytes: MIB / 2,
+                gpu: Duration::from_millis(2),
+            })
+        );
+        // what doesn't parse keeps the default
+        assert_eq!(
+            Budgets::parse("cpu=fast,gpu=-1,vram=3,upload=1"),
+            Some(Budgets {
+                upload_bytes: MIB,
+                ..Budgets::default()
+            })
+        );
+    }
+}
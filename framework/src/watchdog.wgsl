@@ -0,0 +1,39 @@
+// the watchdog's flash, a colored frame around the window's edge that fades out. drawn over the
+// finished frame with alpha blending
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// matches `FlashUniform` in watchdog.rs
+struct Flash {
+    // rgb, a is how much is left of the fade
+    color: vec4<f32>,
+    // the border's width in pixels
+    width: f32,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragInput.tex_coord = uv;
+    return fragInput;
+}
+
+@group(0) @binding(0)
+var<uniform> flash: Flash;
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    // pixels to the nearest edge, tex_coord spans the window so its rate of change is a pixel
+    let pixel = fwidth(input.tex_coord);
+    let to_edge = min(input.tex_coord, 1.0 - input.tex_coord) / pixel;
+    let distance = min(to_edge.x, to_edge.y);
+    let border = 1.0 - smoothstep(flash.width * 0.5, flash.width, distance);
+    return vec4<f32>(flash.color.rgb, flash.color.a * border);
+}
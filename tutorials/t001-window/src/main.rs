@@ -1,4 +1,6 @@
-use tracing::info;
+use std::time::Instant;
+
+use tracing::{info, warn};
 use wgpu::{Backends, Color, Instance};
 use winit::{
     event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
@@ -8,13 +10,124 @@ use winit::{
 
 fn main() {
     tracing_subscriber::fmt::init();
+    let background = parse_args();
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new().build(&event_loop).unwrap();
 
-    pollster::block_on(run(event_loop, window));
+    pollster::block_on(run(event_loop, window, background));
+}
+
+// number keys 1-6 pick a theme, space toggles the hue animation
+const THEMES: [(&str, Color); 6] = [
+    ("green", Color::GREEN),
+    ("black", Color::BLACK),
+    ("white", Color::WHITE),
+    ("red", Color::RED),
+    ("blue", Color::BLUE),
+    (
+        "night",
+        Color {
+            r: 0.05,
+            g: 0.05,
+            b: 0.15,
+            a: 1.0,
+        },
+    ),
+];
+
+// degrees per second
+const HUE_SPEED: f64 = 30.;
+
+#[derive(Clone, Copy, Debug)]
+enum Background {
+    Solid(Color),
+    Animated,
+}
+
+// usage: t01-window [--color RRGGBB] [--theme NAME] [--animate]
+fn parse_args() -> Background {
+    let mut background = Background::Solid(Color::GREEN);
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--color" => match args.next().as_deref().and_then(parse_hex_color) {
+                Some(color) => background = Background::Solid(color),
+                None => warn!("--color expects a hex color like ff8800 or #ff8800"),
+            },
+            "--theme" => {
+                let name = args.next().unwrap_or_default();
+                match THEMES.iter().find(|(theme, _)| *theme == name) {
+                    Some((_, color)) => background = Background::Solid(*color),
+                    None => warn!("unknown theme {:?}", name),
+                }
+            }
+            "--animate" => background = Background::Animated,
+            _ => warn!("unknown argument {:?}", arg),
+        }
+    }
+
+    background
+}
+
+// "ff8800" or "#ff8800", as typed in any color picker
+fn parse_hex_color(hex: &str) -> Option<Color> {
+    let hex = hex.trim_start_matches('#');
+    if hex.len() != 6 {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(Color {
+        r: channel(0)? as f64 / 255.,
+        g: channel(2)? as f64 / 255.,
+        b: channel(4)? as f64 / 255.,
+        a: 1.0,
+    })
+}
+
+// h in degrees, s and v in 0..1
+fn hsv_to_rgb(h: f64, s: f64, v: f64) -> Color {
+    let c = v * s;
+    let h = (h % 360.) / 60.;
+    let x = c * (1. - (h % 2. - 1.).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.),
+        1 => (x, c, 0.),
+        2 => (0., c, x),
+        3 => (0., x, c),
+        4 => (x, 0., c),
+        _ => (c, 0., x),
+    };
+    let m = v - c;
+
+    Color {
+        r: r + m,
+        g: g + m,
+        b: b + m,
+        a: 1.0,
+    }
 }
 
-async fn run(event_loop: EventLoop<()>, window: Window) {
+// picked colors are srgb, an srgb surface expects linear values and encodes them itself
+fn srgb_to_linear(color: Color) -> Color {
+    let f = |c: f64| {
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    Color {
+        r: f(color.r),
+        g: f(color.g),
+        b: f(color.b),
+        a: color.a,
+    }
+}
+
+async fn run(event_loop: EventLoop<()>, window: Window, mut background: Background) {
     let size = window.inner_size();
 
     let instance = Instance::new(Backends::all());
@@ -51,12 +164,29 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         alpha_mode: surface.get_supported_alpha_modes(&adapter)[0],
     };
     surface.configure(&device, &config);
+
+    let srgb_surface = config.format.describe().srgb;
+    let now = Instant::now();
+    info!("background: {:?}", background);
+
     event_loop.run(move |event, _, control_flow| {
         let _ = (&instance, &adapter);
         *control_flow = ControlFlow::Wait;
 
         match event {
             Event::RedrawRequested(_) => {
+                let clear_color = match background {
+                    Background::Solid(color) => color,
+                    Background::Animated => {
+                        hsv_to_rgb(now.elapsed().as_secs_f64() * HUE_SPEED, 0.6, 0.9)
+                    }
+                };
+                let clear_color = if srgb_surface {
+                    srgb_to_linear(clear_color)
+                } else {
+                    clear_color
+                };
+
                 let frame = surface
                     .get_current_texture()
                     .expect("Fail to request next swap chain texture");
@@ -72,7 +202,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                             view: &view,
                             resolve_target: None,
                             ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(Color::GREEN),
+                                load: wgpu::LoadOp::Clear(clear_color),
                                 store: true,
                             },
                         })],
@@ -98,6 +228,36 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                     info!("exit");
                     *control_flow = ControlFlow::Exit
                 }
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            state: ElementState::Pressed,
+                            virtual_keycode: Some(key),
+                            ..
+                        },
+                    ..
+                } => {
+                    let theme = match key {
+                        VirtualKeyCode::Key1 => 0,
+                        VirtualKeyCode::Key2 => 1,
+                        VirtualKeyCode::Key3 => 2,
+                        VirtualKeyCode::Key4 => 3,
+                        VirtualKeyCode::Key5 => 4,
+                        VirtualKeyCode::Key6 => 5,
+                        VirtualKeyCode::Space => {
+                            background = match background {
+                                Background::Animated => Background::Solid(Color::GREEN),
+                                Background::Solid(_) => Background::Animated,
+                            };
+                            info!("background: {:?}", background);
+                            return;
+                        }
+                        _ => return,
+                    };
+                    let (name, color) = THEMES[theme];
+                    background = Background::Solid(color);
+                    info!("theme: {}", name);
+                }
                 _ => {}
             },
             _ => {}
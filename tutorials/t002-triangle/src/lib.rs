@@ -0,0 +1,303 @@
+use bytemuck::{Pod, Zeroable};
+use tracing::{info, warn};
+use wgpu::include_wgsl;
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+// every version draws the same red, but feeds the vertex shader differently
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Version {
+    // positions computed from the vertex index in the shader
+    V1,
+    // one triangle from a vertex buffer
+    V2,
+    // two triangles from a vertex buffer, every triangle has its own 3 vertices
+    V3,
+    // four triangles sharing vertices through an index buffer
+    V4,
+}
+
+impl Version {
+    // the number keys switch versions while running
+    fn from_key(key: VirtualKeyCode) -> Option<Version> {
+        match key {
+            VirtualKeyCode::Key1 => Some(Version::V1),
+            VirtualKeyCode::Key2 => Some(Version::V2),
+            VirtualKeyCode::Key3 => Some(Version::V3),
+            VirtualKeyCode::Key4 => Some(Version::V4),
+            _ => None,
+        }
+    }
+
+    fn title(self) -> String {
+        format!("triangle {:?}", self)
+    }
+}
+
+// usage: t02-triangle [--version 1|2|3|4], the version to start with
+fn parse_version() -> Version {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg != "--version" {
+            warn!("unknown argument {:?}", arg);
+            continue;
+        }
+
+        return match args.next().as_deref() {
+            Some("1") => Version::V1,
+            Some("2") => Version::V2,
+            Some("3") => Version::V3,
+            Some("4") => Version::V4,
+            other => {
+                warn!("unknown version {:?}, falling back to 1", other);
+                Version::V1
+            }
+        };
+    }
+
+    Version::V1
+}
+
+/// the chapter's title in the reel, on its own [`title`] has the version in it
+pub const TITLE: &str = "triangle";
+
+/// the window title for the version on the command line. the arguments are read again in
+/// `init`, where the warnings about them can be logged
+pub fn title() -> String {
+    parse_version().title()
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+}
+
+const fn vertex(x: f32, y: f32) -> Vertex {
+    Vertex { pos: [x, y, 0.] }
+}
+const VERTICES_ONE_TRIANGLE: [Vertex; 3] = [
+    vertex(-0.5, -0.5), // left bottom
+    vertex(0.5, -0.5),  // right bottom
+    vertex(0., 0.5),    // top
+];
+
+// a quad as two triangles, the shared diagonal is stored twice
+const VERTICES_TWO_TRIANGLE: [Vertex; 6] = [
+    vertex(-0.5, -0.5), // left bottom
+    vertex(0.5, -0.5),  // right bottom
+    vertex(-0.5, 0.5),  // left top
+    vertex(0.5, -0.5),  // right bottom, again
+    vertex(0.5, 0.5),   // right top
+    vertex(-0.5, 0.5),  // left top, again
+];
+
+// a diamond cut into four triangles around its center, every vertex stored once
+const VERTICES_FOUR_TRIANGLE: [Vertex; 5] = [
+    vertex(0., 0.),   // 0 center
+    vertex(0.6, 0.),  // 1 right
+    vertex(0., 0.6),  // 2 top
+    vertex(-0.6, 0.), // 3 left
+    vertex(0., -0.6), // 4 bottom
+];
+
+const INDICES_FOUR_TRIANGLE: [u16; 12] = [
+    0, 1, 2, // right top
+    0, 2, 3, // left top
+    0, 3, 4, // left bottom
+    0, 4, 1, // right bottom
+];
+
+// how much the index buffer saves over repeating vertices like v3 does
+fn print_vertex_count_comparison() {
+    let vertex_size = std::mem::size_of::<Vertex>();
+    let triangles = INDICES_FOUR_TRIANGLE.len() / 3;
+
+    let duplicated_vertices = triangles * 3;
+    let duplicated_bytes = duplicated_vertices * vertex_size;
+
+    let indexed_vertices = VERTICES_FOUR_TRIANGLE.len();
+    let indexed_bytes =
+        indexed_vertices * vertex_size + INDICES_FOUR_TRIANGLE.len() * std::mem::size_of::<u16>();
+
+    info!(
+        "v3 style (VERTICES_TWO_TRIANGLE): {} vertices for {} triangles",
+        VERTICES_TWO_TRIANGLE.len(),
+        VERTICES_TWO_TRIANGLE.len() / 3
+    );
+    info!(
+        "{} triangles duplicated: {} vertices, {} bytes",
+        triangles, duplicated_vertices, duplicated_bytes
+    );
+    info!(
+        "{} triangles indexed: {} vertices + {} indices, {} bytes",
+        triangles,
+        indexed_vertices,
+        INDICES_FOUR_TRIANGLE.len(),
+        indexed_bytes
+    );
+}
+
+pub struct Triangle {
+    version: Version,
+    // v1
+    index_pipeline: wgpu::RenderPipeline,
+    // v2 - v4
+    buffer_pipeline: wgpu::RenderPipeline,
+    one_triangle_buf: wgpu::Buffer,
+    two_triangle_buf: wgpu::Buffer,
+    four_triangle_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+}
+
+impl framework::App for Triangle {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        let version = parse_version();
+        info!("drawing {:?}", version);
+
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3],
+        };
+
+        // every version is built up front, switching only picks what to draw with
+        let create_pipeline = |vertex: wgpu::VertexState| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex,
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(config.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        let index_pipeline = create_pipeline(wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        });
+        let buffer_pipeline = create_pipeline(wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_buffer",
+            buffers: &[vertex_buffer_layout],
+        });
+
+        let create_vertices_buf = |vertices: &[Vertex]| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Vertices Buffer"),
+                contents: bytemuck::cast_slice(vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+        };
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indices Buffer"),
+            contents: bytemuck::cast_slice(&INDICES_FOUR_TRIANGLE),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        if version == Version::V4 {
+            print_vertex_count_comparison();
+        }
+
+        Triangle {
+            version,
+            index_pipeline,
+            buffer_pipeline,
+            one_triangle_buf: create_vertices_buf(&VERTICES_ONE_TRIANGLE),
+            two_triangle_buf: create_vertices_buf(&VERTICES_TWO_TRIANGLE),
+            four_triangle_buf: create_vertices_buf(&VERTICES_FOUR_TRIANGLE),
+            indices_buf,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        _config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(keycode),
+                    ..
+                },
+            ..
+        } = event
+        {
+            if let Some(next) = Version::from_key(keycode) {
+                if next != self.version {
+                    self.version = next;
+                    info!("drawing {:?}", self.version);
+                    window.set_title(&self.version.title());
+                    if self.version == Version::V4 {
+                        print_vertex_count_comparison();
+                    }
+                }
+            }
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::GREEN);
+
+            match self.version {
+                Version::V1 => {
+                    rpass.set_pipeline(&self.index_pipeline);
+                    rpass.draw(0..3, 0..1);
+                }
+                Version::V2 => {
+                    rpass.set_pipeline(&self.buffer_pipeline);
+                    rpass.set_vertex_buffer(0, self.one_triangle_buf.slice(..));
+                    rpass.draw(0..VERTICES_ONE_TRIANGLE.len() as u32, 0..1);
+                }
+                Version::V3 => {
+                    rpass.set_pipeline(&self.buffer_pipeline);
+                    rpass.set_vertex_buffer(0, self.two_triangle_buf.slice(..));
+                    rpass.draw(0..VERTICES_TWO_TRIANGLE.len() as u32, 0..1);
+                }
+                Version::V4 => {
+                    rpass.set_pipeline(&self.buffer_pipeline);
+                    rpass.set_vertex_buffer(0, self.four_triangle_buf.slice(..));
+                    rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+                    rpass.draw_indexed(0..INDICES_FOUR_TRIANGLE.len() as u32, 0, 0..1);
+                }
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -1,3 +1,4 @@
+// v1, positions generated from the vertex index, no buffers at all
 @vertex
 fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) vec4<f32> {
     let x = f32(i32(in_vertex_index) - 1);
@@ -5,6 +6,12 @@ fn vs_main(@builtin(vertex_index) in_vertex_index: u32) -> @builtin(position) ve
     return vec4<f32>(x, y, 0.0, 1.0);
 }
 
+// v2 - v4, positions read from a vertex buffer
+@vertex
+fn vs_buffer(@location(0) pos: vec3<f32>) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(pos, 1.0);
+}
+
 @fragment
 fn fs_main() -> @location(0) vec4<f32> {
     return vec4<f32>(1.0, 0.0, 0.0, 1.0);
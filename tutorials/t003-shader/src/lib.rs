@@ -0,0 +1,255 @@
+use bytemuck::{Pod, Zeroable};
+use framework::watchdog;
+use glam::{Mat4, Quat, Vec3};
+use tracing::{info, warn};
+use wgpu::include_wgsl;
+use winit::{event::WindowEvent, window::Window};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "shader";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Version {
+    // everything comes from the vertex buffer
+    V1,
+    // adds a uniform bind group with a tint and a transform, animated over time
+    V2,
+}
+
+// usage: t03-shader [--version 1|2]
+fn parse_version() -> Version {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg != "--version" {
+            warn!("unknown argument {:?}", arg);
+            continue;
+        }
+
+        return match args.next().as_deref() {
+            Some("1") => Version::V1,
+            Some("2") => Version::V2,
+            other => {
+                warn!("unknown version {:?}, falling back to 1", other);
+                Version::V1
+            }
+        };
+    }
+
+    Version::V1
+}
+
+// matches `struct Uniforms` in shader.wgsl, mat4 first so nothing needs padding
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Uniforms {
+    transform: [[f32; 4]; 4],
+    tint: [f32; 4],
+}
+
+impl Uniforms {
+    fn at_time(time: f32) -> Uniforms {
+        let transform = Mat4::from_scale_rotation_translation(
+            Vec3::splat(0.5 + 0.2 * (time * 2.).sin()),
+            Quat::from_rotation_z(time),
+            Vec3::new(0.3 * time.cos(), 0.3 * time.sin(), 0.),
+        );
+        let tint = [
+            0.75 + 0.25 * time.sin(),
+            0.75 + 0.25 * (time + 2.094).sin(),
+            0.75 + 0.25 * (time + 4.188).sin(),
+            1.,
+        ];
+
+        Uniforms {
+            transform: transform.to_cols_array_2d(),
+            tint,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, color }
+}
+
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let vertices = vec![
+        vertex([-1., -1., 0.], [1., 0., 0.]), // left bottom, red
+        vertex([1., -1., 0.], [0., 1., 0.]),  // right bottom, green
+        vertex([0., 1., 0.], [0., 0., 1.]),   // top, blue
+    ];
+
+    let indices = vec![0, 1, 2];
+
+    (vertices, indices)
+}
+
+pub struct Shader {
+    version: Version,
+    render_pipeline: wgpu::RenderPipeline,
+    uniform_buffer: wgpu::Buffer,
+    uniform_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+}
+
+impl framework::App for Shader {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        let version = parse_version();
+        info!("drawing {:?}", version);
+
+        // uniforms, only bound by v2
+        let uniform_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("uniform bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<Uniforms>() as wgpu::BufferAddress
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Uniform Buffer"),
+            contents: bytemuck::bytes_of(&Uniforms::at_time(0.)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("uniform bind group"),
+            layout: &uniform_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let bind_group_layouts: &[&wgpu::BindGroupLayout] = match version {
+            Version::V1 => &[],
+            Version::V2 => &[&uniform_bindgroup_layout],
+        };
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts,
+            push_constant_ranges: &[],
+        });
+
+        let (vs_entry, fs_entry) = match version {
+            Version::V1 => ("vs_main", "fs_main"),
+            Version::V2 => ("vs_uniform", "fs_uniform"),
+        };
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+        };
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: vs_entry,
+                buffers: &[vertex_buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: fs_entry,
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (vertices, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        Shader {
+            version,
+            render_pipeline,
+            uniform_buffer,
+            uniform_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        _config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+    }
+
+    fn update(&mut self, _window: &Window, _event: WindowEvent) {}
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.version == Version::V2 {
+            let uniforms = Uniforms::at_time(frame.time);
+            watchdog::write_buffer(
+                queue,
+                &self.uniform_buffer,
+                0,
+                bytemuck::bytes_of(&uniforms),
+            );
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_pipeline(&self.render_pipeline);
+            if self.version == Version::V2 {
+                rpass.set_bind_group(0, &self.uniform_bindgroup, &[]);
+            }
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            // rpass.draw(0..3, 0..1);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1)
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
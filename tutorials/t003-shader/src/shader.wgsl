@@ -8,6 +8,16 @@ struct FragInput {
     @builtin(position) clip_position: vec4<f32>,
 };
 
+// v2, the first bind group: data shared by every vertex of the draw call
+struct Uniforms {
+    transform: mat4x4<f32>,
+    tint: vec4<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> uniforms: Uniforms;
+
 @vertex
 fn vs_main(input: VertexInput) -> FragInput {
     var fragInput : FragInput;
@@ -20,3 +30,16 @@ fn vs_main(input: VertexInput) -> FragInput {
 fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
     return vec4<f32>(input.color, 1.0);
 }
+
+@vertex
+fn vs_uniform(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = uniforms.transform * vec4<f32>(input.pos, 1.0);
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_uniform(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color * uniforms.tint.rgb, 1.0);
+}
@@ -0,0 +1,564 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{texture::Texture, watchdog};
+use tracing::{info, warn};
+use wgpu::include_wgsl;
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "texture";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Version {
+    // one texture and a sampler
+    V1,
+    // two textures, a sampler and a blend factor uniform in one bind group
+    V2,
+}
+
+// usage: t04-texture [--version 1|2]
+fn parse_version() -> Version {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg != "--version" {
+            warn!("unknown argument {:?}", arg);
+            continue;
+        }
+
+        return match args.next().as_deref() {
+            Some("1") => Version::V1,
+            Some("2") => Version::V2,
+            other => {
+                warn!("unknown version {:?}, falling back to 1", other);
+                Version::V1
+            }
+        };
+    }
+
+    Version::V1
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coord: [f32; 2],
+}
+
+fn vertex(pos: [f32; 3], tex_coord: [f32; 2]) -> Vertex {
+    Vertex { pos, tex_coord }
+}
+
+// v2: up/down change the blend factor, space toggles its animation
+const BLEND_STEP: f32 = 0.1;
+
+// v1: F cycles the filter on the sampled color, the index is what the shader switches on
+const FILTERS: [&str; 4] = ["none", "grayscale", "sepia", "channel swap"];
+
+// A toggles between the image and the atlas, W cycles the wrap mode,
+// U stretches the image uvs beyond [0, 1], I insets the atlas tile by half a texel
+const ADDRESS_MODES: [(&str, wgpu::AddressMode); 3] = [
+    ("clamp to edge", wgpu::AddressMode::ClampToEdge),
+    ("repeat", wgpu::AddressMode::Repeat),
+    ("mirror repeat", wgpu::AddressMode::MirrorRepeat),
+];
+
+// 2x2 tiles of solid color, small enough that one texel covers a lot of pixels
+const ATLAS_TILE_SIZE: u32 = 8;
+const ATLAS_SIZE: u32 = ATLAS_TILE_SIZE * 2;
+const ATLAS_COLORS: [[u8; 4]; 4] = [
+    [230, 60, 60, 255],  // top left, the tile we draw
+    [60, 200, 60, 255],  // top right
+    [60, 90, 230, 255],  // bottom left
+    [240, 220, 60, 255], // bottom right
+];
+
+fn create_atlas() -> Vec<u8> {
+    let mut rgba = Vec::with_capacity((ATLAS_SIZE * ATLAS_SIZE * 4) as usize);
+    for y in 0..ATLAS_SIZE {
+        for x in 0..ATLAS_SIZE {
+            let tile = (y / ATLAS_TILE_SIZE) * 2 + x / ATLAS_TILE_SIZE;
+            rgba.extend_from_slice(&ATLAS_COLORS[tile as usize]);
+        }
+    }
+    rgba
+}
+
+// uv rect of the quad, (min, max) with v pointing down
+fn uv_rect(atlas: bool, wide_uv: bool, inset: bool) -> ([f32; 2], [f32; 2]) {
+    if atlas {
+        // the top left tile covers [0, 0.5], linear filtering at its border blends in the
+        // neighbouring tiles, pulling the uvs half a texel inwards keeps every sample inside
+        let half_texel = if inset { 0.5 / ATLAS_SIZE as f32 } else { 0. };
+        return ([half_texel; 2], [0.5 - half_texel; 2]);
+    }
+
+    if wide_uv {
+        // the texture repeats 3 times, what happens outside [0, 1] is up to the address mode
+        ([-1.; 2], [2.; 2])
+    } else {
+        ([0.; 2], [1.; 2])
+    }
+}
+
+fn create_vertices(uv_min: [f32; 2], uv_max: [f32; 2]) -> (Vec<Vertex>, Vec<u16>) {
+    let vertices = vec![
+        vertex([-0.5, -0.5, 0.], [uv_min[0], uv_max[1]]), // left bottom
+        vertex([0.5, -0.5, 0.], [uv_max[0], uv_max[1]]),  // right bottom
+        vertex([0.5, 0.5, 0.], [uv_max[0], uv_min[1]]),   // top right
+        vertex([-0.5, 0.5, 0.], [uv_min[0], uv_min[1]]),  // top left
+    ];
+
+    let indices = vec![
+        0, 1, 3, // first triangle
+        1, 2, 3, // second triangle
+    ];
+
+    (vertices, indices)
+}
+
+pub struct TexturedQuad {
+    version: Version,
+    render_pipeline: wgpu::RenderPipeline,
+    // [diffuse, atlas] x address modes, v1
+    bind_groups: Vec<Vec<wgpu::BindGroup>>,
+    // one per address mode, v2
+    blend_bind_groups: Vec<wgpu::BindGroup>,
+    filter_buffer: wgpu::Buffer,
+    blend_buffer: wgpu::Buffer,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+
+    atlas: bool,
+    wide_uv: bool,
+    inset: bool,
+    address_mode: usize,
+    filter: usize,
+    blend_factor: f32,
+    animate_blend: bool,
+    // the keys change these, `render` writes them before the next frame
+    filter_dirty: bool,
+    vertices_dirty: bool,
+}
+
+impl framework::App for TexturedQuad {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        let version = parse_version();
+        info!("drawing {:?}", version);
+
+        // texture
+        // embedded by default, LEARNWGPU_ASSETS=disk reads them from src/ instead
+        let assets = framework::assets!("src": "happy-tree.png", "spengebob.jpeg");
+        let diffuse_bytes = assets
+            .load("spengebob.jpeg")
+            .expect("Fail to load spengebob.jpeg");
+
+        let diffuse = Texture::from_bytes(device, queue, &diffuse_bytes, "diffuse_texture")
+            .expect("Fail to decode spengebob.jpeg");
+
+        // atlas
+        let atlas_img = image::RgbaImage::from_raw(ATLAS_SIZE, ATLAS_SIZE, create_atlas())
+            .expect("Fail to create atlas image");
+        let atlas = Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(atlas_img),
+            "atlas_texture",
+        );
+
+        // one sampler per address mode, the mode is fixed once the sampler is created
+        let samplers: Vec<wgpu::Sampler> = ADDRESS_MODES
+            .iter()
+            .map(|(name, address_mode)| {
+                device.create_sampler(&wgpu::SamplerDescriptor {
+                    label: Some(*name),
+                    address_mode_u: *address_mode,
+                    address_mode_v: *address_mode,
+                    address_mode_w: *address_mode,
+                    mag_filter: wgpu::FilterMode::Linear,
+                    min_filter: wgpu::FilterMode::Linear,
+                    mipmap_filter: wgpu::FilterMode::Nearest,
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("texture_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(4),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        // the filter index, padded to 16 bytes like the blend factor below
+        let filter_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Filter Buffer"),
+            contents: bytemuck::cast_slice(&[0u32; 4]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // [diffuse, atlas] x address modes
+        let bind_groups: Vec<Vec<wgpu::BindGroup>> = [&diffuse.view, &atlas.view]
+            .iter()
+            .map(|texture_view| {
+                samplers
+                    .iter()
+                    .map(|sampler| {
+                        device.create_bind_group(&wgpu::BindGroupDescriptor {
+                            label: Some("diffuse_bind_group"),
+                            layout: &texture_bind_group_layout,
+                            entries: &[
+                                wgpu::BindGroupEntry {
+                                    binding: 0,
+                                    resource: wgpu::BindingResource::TextureView(texture_view),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 1,
+                                    resource: wgpu::BindingResource::Sampler(sampler),
+                                },
+                                wgpu::BindGroupEntry {
+                                    binding: 4,
+                                    resource: filter_buffer.as_entire_binding(),
+                                },
+                            ],
+                        })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // v2, same group 0 with two more bindings: the second texture and the blend factor
+        let second = Texture::from_bytes(
+            device,
+            queue,
+            &assets
+                .load("happy-tree.png")
+                .expect("Fail to load happy-tree.png"),
+            "second_texture",
+        )
+        .expect("Fail to decode happy-tree.png");
+
+        // a lone f32, padded to 16 bytes to stay clear of uniform layout rules
+        let blend_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Blend Buffer"),
+            contents: bytemuck::cast_slice(&[0.5f32, 0., 0., 0.]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let blend_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("blend_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(4),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        // one per address mode, like the v1 bind groups
+        let blend_bind_groups: Vec<wgpu::BindGroup> = samplers
+            .iter()
+            .map(|sampler| {
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("blend_bind_group"),
+                    layout: &blend_bind_group_layout,
+                    entries: &[
+                        wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: wgpu::BindingResource::TextureView(&diffuse.view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 1,
+                            resource: wgpu::BindingResource::Sampler(sampler),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 2,
+                            resource: wgpu::BindingResource::TextureView(&second.view),
+                        },
+                        wgpu::BindGroupEntry {
+                            binding: 3,
+                            resource: blend_buffer.as_entire_binding(),
+                        },
+                    ],
+                })
+            })
+            .collect();
+
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let (bind_group_layout, fs_entry) = match version {
+            Version::V1 => (&texture_bind_group_layout, "fs_main"),
+            Version::V2 => (&blend_bind_group_layout, "fs_blend"),
+        };
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x2],
+        };
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[vertex_buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: fs_entry,
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (uv_min, uv_max) = uv_rect(false, false, false);
+        let (vertices, indices) = create_vertices(uv_min, uv_max);
+
+        // uvs change at runtime, so the vertex buffer needs COPY_DST
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        TexturedQuad {
+            version,
+            render_pipeline,
+            bind_groups,
+            blend_bind_groups,
+            filter_buffer,
+            blend_buffer,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            atlas: false,
+            wide_uv: false,
+            inset: false,
+            address_mode: 0,
+            filter: 0,
+            blend_factor: 0.5,
+            animate_blend: true,
+            filter_dirty: false,
+            vertices_dirty: false,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        _config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+    }
+
+    fn update(&mut self, _window: &Window, event: WindowEvent) {
+        let key = match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => key,
+            _ => return,
+        };
+
+        if self.version == Version::V2 {
+            match key {
+                VirtualKeyCode::Up => {
+                    self.blend_factor = (self.blend_factor + BLEND_STEP).min(1.);
+                    self.animate_blend = false;
+                }
+                VirtualKeyCode::Down => {
+                    self.blend_factor = (self.blend_factor - BLEND_STEP).max(0.);
+                    self.animate_blend = false;
+                }
+                VirtualKeyCode::Space => self.animate_blend = !self.animate_blend,
+                // the atlas and the filters only exist in v1
+                VirtualKeyCode::A | VirtualKeyCode::I | VirtualKeyCode::F => return,
+                _ => {}
+            }
+        }
+
+        match key {
+            VirtualKeyCode::Up | VirtualKeyCode::Down | VirtualKeyCode::Space
+                if self.version == Version::V2 =>
+            {
+                info!(
+                    "blend factor {:.1}, animate {}",
+                    self.blend_factor, self.animate_blend
+                );
+                return;
+            }
+            VirtualKeyCode::F => {
+                self.filter = (self.filter + 1) % FILTERS.len();
+                self.filter_dirty = true;
+                info!("filter {}", FILTERS[self.filter]);
+                return;
+            }
+            VirtualKeyCode::A => self.atlas = !self.atlas,
+            VirtualKeyCode::W => self.address_mode = (self.address_mode + 1) % ADDRESS_MODES.len(),
+            VirtualKeyCode::U => self.wide_uv = !self.wide_uv,
+            VirtualKeyCode::I => self.inset = !self.inset,
+            _ => return,
+        }
+
+        self.vertices_dirty = true;
+        let (uv_min, uv_max) = uv_rect(self.atlas, self.wide_uv, self.inset);
+        info!(
+            "{}, {}, uv {:?} - {:?}",
+            if self.atlas { "atlas" } else { "image" },
+            ADDRESS_MODES[self.address_mode].0,
+            uv_min,
+            uv_max
+        );
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.version == Version::V2 {
+            if self.animate_blend {
+                self.blend_factor = 0.5 + 0.5 * frame.time.sin();
+            }
+            watchdog::write_buffer(
+                queue,
+                &self.blend_buffer,
+                0,
+                bytemuck::bytes_of(&self.blend_factor),
+            );
+        }
+        if self.filter_dirty {
+            self.filter_dirty = false;
+            watchdog::write_buffer(
+                queue,
+                &self.filter_buffer,
+                0,
+                bytemuck::bytes_of(&(self.filter as u32)),
+            );
+        }
+        if self.vertices_dirty {
+            self.vertices_dirty = false;
+            let (uv_min, uv_max) = uv_rect(self.atlas, self.wide_uv, self.inset);
+            let (vertices, _) = create_vertices(uv_min, uv_max);
+            watchdog::write_buffer(
+                queue,
+                &self.vertices_buf,
+                0,
+                bytemuck::cast_slice(&vertices),
+            );
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_pipeline(&self.render_pipeline);
+            match self.version {
+                Version::V1 => rpass.set_bind_group(
+                    0,
+                    &self.bind_groups[self.atlas as usize][self.address_mode],
+                    &[],
+                ),
+                Version::V2 => {
+                    rpass.set_bind_group(0, &self.blend_bind_groups[self.address_mode], &[])
+                }
+            }
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            // rpass.draw(0..3, 0..1);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1)
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
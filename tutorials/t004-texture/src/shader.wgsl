@@ -21,7 +21,57 @@ var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
 
+// v1, the filter applied to the sample, picked by a uniform at runtime. binding 4 since v2
+// uses 2 and 3 in the same module
+struct PostFilter {
+    kind: u32,
+};
+
+@group(0) @binding(4)
+var<uniform> post_filter: PostFilter;
+
 @fragment
 fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
-    return textureSample(t_diffuse, s_diffuse, input.tex_coord);
+    let color = textureSample(t_diffuse, s_diffuse, input.tex_coord);
+    // every fragment takes the same branch, a uniform doesn't change within a draw
+    switch post_filter.kind {
+        // grayscale, weighted by how bright each channel looks
+        case 1u: {
+            let luma = dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114));
+            return vec4<f32>(vec3<f32>(luma), color.a);
+        }
+        // sepia
+        case 2u: {
+            let sepia = vec3<f32>(
+                dot(color.rgb, vec3<f32>(0.393, 0.769, 0.189)),
+                dot(color.rgb, vec3<f32>(0.349, 0.686, 0.168)),
+                dot(color.rgb, vec3<f32>(0.272, 0.534, 0.131)),
+            );
+            return vec4<f32>(min(sepia, vec3<f32>(1.0)), color.a);
+        }
+        // channel swap, rgb -> brg
+        case 3u: {
+            return vec4<f32>(color.brg, color.a);
+        }
+        default: {
+            return color;
+        }
+    }
+}
+
+// v2, a second texture and a blend factor share the same bind group
+struct Blend {
+    factor: f32,
+};
+
+@group(0) @binding(2)
+var t_second: texture_2d<f32>;
+@group(0) @binding(3)
+var<uniform> blend: Blend;
+
+@fragment
+fn fs_blend(input: FragInput) -> @location(0) vec4<f32> {
+    let first = textureSample(t_diffuse, s_diffuse, input.tex_coord);
+    let second = textureSample(t_second, s_diffuse, input.tex_coord);
+    return mix(first, second, blend.factor);
 }
@@ -0,0 +1,4 @@
+// fail the build on shader errors instead of at startup
+fn main() {
+    wgsl_check::validate("src");
+}
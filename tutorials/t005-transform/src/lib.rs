@@ -0,0 +1,284 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{texture::Texture, vertex_layout, watchdog};
+use glam::{Mat4, Quat, Vec3};
+use winit::{event::WindowEvent, window::Window};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "transform";
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coord: [f32; 2],
+}
+
+// the shader's `VertexInput` and `Transform` are generated from these, see `vertex_layout`
+framework::vertex_layout! {
+    Vertex as "VertexInput", Vertex {
+        0 => pos: Float32x3,
+        1 => tex_coord: Float32x2,
+    }
+}
+
+fn vertex(pos: [f32; 3], tex_coord: [f32; 2]) -> Vertex {
+    Vertex { pos, tex_coord }
+}
+
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let vertices = vec![
+        vertex([-0.5, -0.5, 0.], [0., 1.]), // left bottom
+        vertex([0.5, -0.5, 0.], [1., 1.]),  // right bottom
+        vertex([0.5, 0.5, 0.], [1., 0.]),   // top right
+        vertex([-0.5, 0.5, 0.], [0., 0.]),  // top left
+    ];
+
+    let indices = vec![
+        0, 1, 3, // first triangle
+        1, 2, 3, // second triangle
+    ];
+
+    (vertices, indices)
+}
+
+struct Transform {
+    translation: glam::Vec3,
+    rotation: glam::Quat,
+    scale: glam::Vec3,
+}
+
+// one model matrix per instance, a column per attribute
+framework::vertex_layout! {
+    Transform as "Transform", Instance {
+        2 => mat0: Float32x4,
+        3 => mat1: Float32x4,
+        4 => mat2: Float32x4,
+        5 => mat3: Float32x4,
+    }
+}
+
+impl Transform {
+    fn new() -> Transform {
+        Transform {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+            // scale: Vec3::NEG_ONE,
+            // scale: Vec3::splat(0.5),
+        }
+    }
+
+    fn rotate(&self, axis: Vec3, radius: f32) -> Transform {
+        Transform {
+            rotation: self.rotation * Quat::from_axis_angle(axis, radius),
+            ..*self
+        }
+        // self.rotation = self.rotation + Quat::from_axis_angle(axis, radius);
+        // self
+    }
+
+    fn rotate_z(&self, radius: f32) -> Transform {
+        self.rotate(Vec3::Z, radius)
+    }
+
+    pub(crate) fn set_scale(&self, scale: f32) -> Transform {
+        Transform {
+            scale: Vec3::splat(scale),
+            ..*self
+        }
+    }
+
+    pub(crate) fn add_translate(&self, tran_val: f32) -> Transform {
+        Transform {
+            translation: Vec3::new(
+                self.translation.x + tran_val,
+                self.translation.y + tran_val,
+                self.translation.z,
+            ),
+            ..*self
+        }
+    }
+
+    fn to_mat4(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+pub struct TransformedQuad {
+    render_pipeline: wgpu::RenderPipeline,
+    diffuse_bind_group: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    // the model matrix, one instance
+    transform_buf: wgpu::Buffer,
+    transform: Transform,
+}
+
+impl framework::App for TransformedQuad {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // texture
+        // embedded by default, LEARNWGPU_ASSETS=disk reads them from src/ instead
+        let assets = framework::assets!("src": "spengebob.jpeg");
+        let diffuse_bytes = assets
+            .load("spengebob.jpeg")
+            .expect("Fail to load spengebob.jpeg");
+
+        let diffuse = Texture::from_bytes(device, queue, &diffuse_bytes, "diffuse_texture")
+            .expect("Fail to decode spengebob.jpeg");
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("texture_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let diffuse_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("diffuse_bind_group"),
+            layout: &texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&diffuse.sampler),
+                },
+            ],
+        });
+
+        let shader = vertex_layout::shader(
+            device,
+            "shader.wgsl",
+            include_str!("shader.wgsl"),
+            &[Vertex::WGSL, Transform::WGSL],
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&texture_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::buffer_layout(), Transform::buffer_layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (vertices, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // transform
+        let transform = Transform::new();
+        let transform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Transform Buffer"),
+            contents: bytemuck::cast_slice(transform.to_mat4().as_ref()),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        TransformedQuad {
+            render_pipeline,
+            diffuse_bind_group,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            transform_buf,
+            transform,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        _config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+    }
+
+    fn update(&mut self, _window: &Window, _event: WindowEvent) {}
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.transform = self.transform.rotate_z(frame.delta);
+        self.transform = self.transform.set_scale(frame.time.sin().max(0.1));
+        // about a hundredth a frame at 60 fps, and none while paused
+        self.transform = self
+            .transform
+            .add_translate(frame.time.cos() * 0.6 * frame.delta);
+
+        let mat4 = self.transform.to_mat4();
+        watchdog::write_buffer(
+            queue,
+            &self.transform_buf,
+            0,
+            bytemuck::cast_slice(mat4.as_ref()),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_pipeline(&self.render_pipeline);
+            rpass.set_bind_group(0, &self.diffuse_bind_group, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..)); // vertex_buffer
+            rpass.set_vertex_buffer(1, self.transform_buf.slice(..)); // transform mat4 buffer
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            // rpass.draw(0..3, 0..1);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1)
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
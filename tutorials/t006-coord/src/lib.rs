@@ -0,0 +1,480 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::Camera,
+    params::Params,
+    texture::Texture,
+    timeline::{self, Resimulated},
+    vertex_layout, watchdog,
+};
+use glam::{Mat4, Quat, Vec3};
+use tracing::debug;
+use winit::{event::WindowEvent, window::Window};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "coord";
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coord: [f32; 2],
+}
+
+// the shader's `VertexInput` and `Transform` are generated from these, see `vertex_layout`
+framework::vertex_layout! {
+    Vertex as "VertexInput", Vertex {
+        0 => pos: Float32x3,
+        1 => tex_coord: Float32x2,
+    }
+}
+
+fn vertex(pos: [f32; 3], tex_coord: [f32; 2]) -> Vertex {
+    Vertex { pos, tex_coord }
+}
+
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let vertices = vec![
+        vertex([-0.5, -0.5, 0.], [0., 1.]), // left bottom front
+        vertex([0.5, -0.5, 0.], [1., 1.]),  // right bottom front
+        vertex([0.5, 0.5, 0.], [1., 0.]),   // top right front
+        vertex([-0.5, 0.5, 0.], [0., 0.]),  // top left front
+    ];
+
+    let indices = vec![
+        0, 1, 3, // first triangle
+        1, 2, 3, // second triangle
+    ];
+
+    (vertices, indices)
+}
+
+#[derive(Clone)]
+struct Transform {
+    translation: glam::Vec3,
+    rotation: glam::Quat,
+    scale: glam::Vec3,
+}
+
+// one model matrix per instance, a column per attribute
+framework::vertex_layout! {
+    Transform as "Transform", Instance {
+        2 => mat0: Float32x4,
+        3 => mat1: Float32x4,
+        4 => mat2: Float32x4,
+        5 => mat3: Float32x4,
+    }
+}
+
+impl Transform {
+    fn new() -> Transform {
+        Transform {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+            // scale: Vec3::NEG_ONE,
+            // scale: Vec3::splat(0.5),
+        }
+    }
+
+    fn rotate(&self, axis: Vec3, radius: f32) -> Transform {
+        Transform {
+            rotation: self.rotation * Quat::from_axis_angle(axis, radius),
+            ..*self
+        }
+        // self.rotation = self.rotation + Quat::from_axis_angle(axis, radius);
+        // self
+    }
+
+    fn rotate_x(&self, radius: f32) -> Transform {
+        self.rotate(Vec3::X, radius)
+    }
+
+    // the tests turn around the other axes too
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn rotate_y(&self, radius: f32) -> Transform {
+        self.rotate(Vec3::Y, radius)
+    }
+
+    // the tests turn around the other axes too
+    #[cfg_attr(not(test), allow(dead_code))]
+    fn rotate_z(&self, radius: f32) -> Transform {
+        self.rotate(Vec3::Z, radius)
+    }
+
+    pub(crate) fn set_scale(&self, scale: f32) -> Transform {
+        Transform {
+            scale: Vec3::splat(scale),
+            ..*self
+        }
+    }
+
+    pub(crate) fn add_translate(&self, tran_val: f32) -> Transform {
+        Transform {
+            translation: Vec3::new(
+                self.translation.x + tran_val,
+                self.translation.y + tran_val,
+                self.translation.z,
+            ),
+            ..*self
+        }
+    }
+
+    fn to_mat4(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+pub struct Coord {
+    render_pipeline: wgpu::RenderPipeline,
+    diffuse_bindgroup: wgpu::BindGroup,
+    view_bindgroup: wgpu::BindGroup,
+    projection_bindgroup: wgpu::BindGroup,
+    projection_buffer: wgpu::Buffer,
+    camera: Camera,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    // the model matrix, one instance
+    transform_buf: wgpu::Buffer,
+    // it turns and drifts a little every step, so going back on the timeline replays those
+    // steps
+    transform: Resimulated<Transform>,
+    // the speeds, tunable while running, see framework::params
+    params: Params,
+    format: wgpu::TextureFormat,
+}
+
+impl framework::App for Coord {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // texture
+        // embedded by default, LEARNWGPU_ASSETS=disk reads them from src/ instead
+        let assets = framework::assets!("src": "spengebob.jpeg");
+        let diffuse_bytes = assets
+            .load("spengebob.jpeg")
+            .expect("Fail to load spengebob.jpeg");
+
+        let diffuse = Texture::from_bytes(device, queue, &diffuse_bytes, "diffuse_texture")
+            .expect("Fail to decode spengebob.jpeg");
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("texture_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let diffuse_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("diffuse_bind_group"),
+            layout: &texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&diffuse.sampler),
+                },
+            ],
+        });
+
+        // coord, at (0, 0, 3) looking up at (0, 1, 0). the camera keeps the aspect ratio so the
+        // projection can be rebuilt when the window is resized
+        let mut camera = Camera::new(
+            Vec3::new(0., 0., 3.),
+            0.,
+            (1. / 3.0f32).atan(),
+            config.width as f32 / config.height as f32,
+        );
+        camera.zfar = 40.;
+        let view = camera.view();
+        let projection = camera.projection();
+
+        // mat4X4 bindgroup layout
+        let mat4_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("mat4x4 bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64),
+                    },
+                    count: None,
+                }],
+            });
+
+        let view_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("View Buffer"),
+            contents: bytemuck::cast_slice(view.as_ref()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let projection_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Projection Buffer"),
+            contents: bytemuck::cast_slice(projection.as_ref()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let view_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("view bind group"),
+            layout: &mat4_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: view_buffer.as_entire_binding(),
+            }],
+        });
+
+        let projection_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Projection Bindgroup Buffer"),
+            layout: &mat4_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: projection_buffer.as_entire_binding(),
+            }],
+        });
+
+        // shader
+        let shader = vertex_layout::shader(
+            device,
+            "shader.wgsl",
+            include_str!("shader.wgsl"),
+            &[Vertex::WGSL, Transform::WGSL],
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &texture_bind_group_layout, // group 0, texture
+                &mat4_bindgroup_layout,     // group 1, view
+                &mat4_bindgroup_layout,     // group 2, projection
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::buffer_layout(), Transform::buffer_layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (vertices, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let transform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Transform Buffer"),
+            contents: bytemuck::cast_slice(Transform::new().to_mat4().as_ref()),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        Coord {
+            render_pipeline,
+            diffuse_bindgroup,
+            view_bindgroup,
+            projection_bindgroup,
+            projection_buffer,
+            camera,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            transform_buf,
+            transform: Resimulated::new(Transform::new(), timeline::STEP),
+            params: Params::load(env!("CARGO_PKG_NAME")),
+            format: config.format,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        // otherwise the quad stretches with the window
+        self.camera.resize(config.width, config.height);
+        watchdog::write_buffer(
+            queue,
+            &self.projection_buffer,
+            0,
+            bytemuck::cast_slice(self.camera.projection().as_ref()),
+        );
+    }
+
+    fn update(&mut self, _window: &Window, event: WindowEvent) {
+        self.params.process_window_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let game_time = frame.time;
+
+        debug!("game time: {:?}", game_time);
+
+        self.params.poll();
+        let rotate_speed = self.params.float("rotate_speed", 1.);
+        let translate_speed = self.params.float("translate_speed", 1.);
+        let scale_speed = self.params.float("scale_speed", 1.);
+
+        let transform = self
+            .transform
+            .advance_to(game_time, |transform, time, step| {
+                *transform =
+                    // transform.rotate_z(step);
+                    transform.rotate_x(step * rotate_speed);
+
+                // a hundredth per step, as it was per frame at 60 fps
+                *transform = transform.add_translate(time.cos() * translate_speed / 100.);
+            });
+        let transform = transform.set_scale((game_time * scale_speed).sin().max(0.1));
+        let mat4 = transform.to_mat4();
+        watchdog::write_buffer(
+            queue,
+            &self.transform_buf,
+            0,
+            bytemuck::cast_slice(mat4.as_ref()),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_pipeline(&self.render_pipeline);
+            rpass.set_bind_group(0, &self.diffuse_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.view_bindgroup, &[]);
+            rpass.set_bind_group(2, &self.projection_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..)); // vertex_buffer
+            rpass.set_vertex_buffer(1, self.transform_buf.slice(..)); // transform mat4 buffer
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            // rpass.draw(0..3, 0..1);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1)
+        }
+
+        queue.submit(Some(encoder.finish()));
+        // `\` shows the speeds over the quad
+        self.params.draw(device, queue, frame.view, self.format);
+    }
+
+    fn params(&mut self) -> Option<&mut Params> {
+        Some(&mut self.params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use glam::{Mat4, Quat, Vec3};
+
+    use super::Transform;
+
+    const EPSILON: f32 = 1e-5;
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!(
+            actual.abs_diff_eq(expected, EPSILON),
+            "{} isn't {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn new_is_identity() {
+        assert!(Transform::new()
+            .to_mat4()
+            .abs_diff_eq(Mat4::IDENTITY, EPSILON));
+    }
+
+    #[test]
+    fn to_mat4_scales_then_rotates_then_translates() {
+        let transform = Transform::new()
+            .rotate_z(FRAC_PI_2)
+            .set_scale(2.)
+            .add_translate(1.);
+        let mat4 = transform.to_mat4();
+
+        // (1, 0, 0) scaled to (2, 0, 0), turned to (0, 2, 0), moved to (1, 3, 0)
+        assert_close(mat4.transform_point3(Vec3::X), Vec3::new(1., 3., 0.));
+        // directions ignore the translation
+        assert_close(mat4.transform_vector3(Vec3::X), Vec3::new(0., 2., 0.));
+    }
+
+    #[test]
+    fn rotations_stack_in_the_local_frame() {
+        // rotate_y after rotate_x turns around the already tilted y axis
+        let transform = Transform::new().rotate_x(FRAC_PI_2).rotate_y(FRAC_PI_2);
+        let expected = Quat::from_rotation_x(FRAC_PI_2) * Quat::from_rotation_y(FRAC_PI_2);
+        assert_close(
+            transform.to_mat4().transform_vector3(Vec3::Z),
+            expected * Vec3::Z,
+        );
+        // +z goes to +x under y, then x stays put under x
+        assert_close(transform.to_mat4().transform_vector3(Vec3::Z), Vec3::X);
+    }
+
+    #[test]
+    fn add_translate_moves_along_x_and_y_only() {
+        let transform = Transform::new().add_translate(0.5).add_translate(0.25);
+        assert_close(transform.translation, Vec3::new(0.75, 0.75, 0.));
+        // the other parts are left alone
+        assert_eq!(transform.rotation, Quat::IDENTITY);
+        assert_eq!(transform.scale, Vec3::ONE);
+    }
+
+    #[test]
+    fn set_scale_replaces_instead_of_multiplying() {
+        let transform = Transform::new().set_scale(3.).set_scale(0.5);
+        assert_eq!(transform.scale, Vec3::splat(0.5));
+    }
+}
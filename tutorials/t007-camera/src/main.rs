@@ -1,9 +1,11 @@
 use std::time::Instant;
 
 use bytemuck::{Pod, Zeroable};
+use framework::camera::{Camera, CameraController, FpsCameraController, OrbitCameraController};
+use framework::{texture::Texture, vertex_layout};
 use glam::{Mat4, Quat, Vec3};
 use tracing::{info, Level};
-use wgpu::{include_wgsl, Backends, Instance};
+use wgpu::{Backends, Instance};
 use winit::{
     event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
@@ -21,9 +23,10 @@ fn main() {
     pollster::block_on(run(event_loop, window));
 }
 
-const TRANSLATE_SPEED: f32 = 1.;
-const ROTATE_SPEED: f32 = 10.;
-const SCALE_SPEED: f32 = 1.;
+const CAMERA_SPEED: f32 = 2.;
+const CAMERA_SENSITIVITY: f32 = 0.003;
+// seconds between latency estimates in the title
+const LATENCY_INTERVAL: f32 = 1.;
 
 #[repr(C)]
 #[derive(Clone, Copy, Pod, Zeroable)]
@@ -32,6 +35,14 @@ struct Vertex {
     tex_coord: [f32; 2],
 }
 
+// the shader's `VertexInput` and `Transform` are generated from these, see `vertex_layout`
+framework::vertex_layout! {
+    Vertex as "VertexInput", Vertex {
+        0 => pos: Float32x3,
+        1 => tex_coord: Float32x2,
+    }
+}
+
 fn vertex(pos: [f32; 3], tex_coord: [f32; 2]) -> Vertex {
     Vertex { pos, tex_coord }
 }
@@ -58,6 +69,16 @@ struct Transform {
     scale: glam::Vec3,
 }
 
+// one model matrix per instance, a column per attribute
+framework::vertex_layout! {
+    Transform as "Transform", Instance {
+        2 => mat0: Float32x4,
+        3 => mat1: Float32x4,
+        4 => mat2: Float32x4,
+        5 => mat3: Float32x4,
+    }
+}
+
 impl Transform {
     fn new() -> Transform {
         Transform {
@@ -82,14 +103,6 @@ impl Transform {
         self.rotate(Vec3::X, radius)
     }
 
-    fn rotate_y(&self, radius: f32) -> Transform {
-        self.rotate(Vec3::Y, radius)
-    }
-
-    fn rotate_z(&self, radius: f32) -> Transform {
-        self.rotate(Vec3::Z, radius)
-    }
-
     pub(crate) fn set_scale(&self, scale: f32) -> Transform {
         Transform {
             scale: Vec3::splat(scale),
@@ -111,36 +124,6 @@ impl Transform {
     fn to_mat4(&self) -> Mat4 {
         Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
     }
-
-    fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
-        use std::mem;
-        wgpu::VertexBufferLayout {
-            array_stride: mem::size_of::<Mat4>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Instance,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: mem::size_of::<[f32; 4 * 0]>() as wgpu::BufferAddress,
-                    shader_location: 2,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: mem::size_of::<[f32; 4 * 1]>() as wgpu::BufferAddress,
-                    shader_location: 3,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: mem::size_of::<[f32; 4 * 2]>() as wgpu::BufferAddress,
-                    shader_location: 4,
-                },
-                wgpu::VertexAttribute {
-                    format: wgpu::VertexFormat::Float32x4,
-                    offset: mem::size_of::<[f32; 4 * 3]>() as wgpu::BufferAddress,
-                    shader_location: 5,
-                },
-            ],
-        }
-    }
 }
 
 async fn run(event_loop: EventLoop<()>, window: Window) {
@@ -170,59 +153,14 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         .expect("Fail to create device");
 
     // texture
-    // let diffuse_bytes = include_bytes!("happy-tree.png");
-    let diffuse_bytes = include_bytes!("spengebob.jpeg");
+    // embedded by default, LEARNWGPU_ASSETS=disk reads them from src/ instead
+    let assets = framework::assets!("src": "spengebob.jpeg");
+    let diffuse_bytes = assets
+        .load("spengebob.jpeg")
+        .expect("Fail to load spengebob.jpeg");
 
-    let diffuse_img = image::load_from_memory(diffuse_bytes).unwrap();
-    let diffuse_rgba = diffuse_img.to_rgba8();
-    // let diffuse_rgba = diffuse_img.as_rgba8().unwrap();
-
-    use image::GenericImageView;
-    let dimensions = diffuse_img.dimensions();
-
-    let texture_size = wgpu::Extent3d {
-        width: dimensions.0,
-        height: dimensions.1,
-        depth_or_array_layers: 1,
-    };
-
-    let diffuse_texture = device.create_texture(&wgpu::TextureDescriptor {
-        label: Some("diffuse_texture"),
-        size: texture_size,
-        mip_level_count: 1,
-        sample_count: 1,
-        dimension: wgpu::TextureDimension::D2,
-        format: wgpu::TextureFormat::Rgba8UnormSrgb,
-        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
-    });
-
-    queue.write_texture(
-        wgpu::ImageCopyTexture {
-            texture: &diffuse_texture,
-            mip_level: 0,
-            origin: wgpu::Origin3d::ZERO,
-            aspect: wgpu::TextureAspect::All,
-        },
-        &diffuse_rgba,
-        wgpu::ImageDataLayout {
-            offset: 0,
-            bytes_per_row: std::num::NonZeroU32::new(4 * dimensions.0),
-            rows_per_image: std::num::NonZeroU32::new(dimensions.1),
-        },
-        texture_size,
-    );
-
-    let diffuse_texture_view = diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());
-    let diffuse_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
-        label: Some("texture sampler"),
-        address_mode_u: wgpu::AddressMode::ClampToEdge,
-        address_mode_v: wgpu::AddressMode::ClampToEdge,
-        address_mode_w: wgpu::AddressMode::ClampToEdge,
-        mag_filter: wgpu::FilterMode::Linear,
-        min_filter: wgpu::FilterMode::Linear,
-        mipmap_filter: wgpu::FilterMode::Nearest,
-        ..Default::default()
-    });
+    let diffuse = Texture::from_bytes(&device, &queue, &diffuse_bytes, "diffuse_texture")
+        .expect("Fail to decode spengebob.jpeg");
 
     let texture_bind_group_layout =
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -253,25 +191,41 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         entries: &[
             wgpu::BindGroupEntry {
                 binding: 0,
-                resource: wgpu::BindingResource::TextureView(&diffuse_texture_view),
+                resource: wgpu::BindingResource::TextureView(&diffuse.view),
             },
             wgpu::BindGroupEntry {
                 binding: 1,
-                resource: wgpu::BindingResource::Sampler(&diffuse_sampler),
+                resource: wgpu::BindingResource::Sampler(&diffuse.sampler),
             },
         ],
     });
 
-    // coord
-    // let view = Mat4::look_at_rh(Vec3::new(0., 0., 3.), Vec3::ZERO, Vec3::Y);
-    let view = Mat4::look_at_rh(Vec3::new(0., 0., 3.), Vec3::new(0., 1., 0.), Vec3::Y);
-    let projection = Mat4::perspective_rh(
-        // std::f32::consts::PI / 4.,
-        (45.0 as f32).to_radians(),
+    // camera, tab switches between the controllers
+    // - fps: WASD to move, space/shift up/down, hold the right mouse button to look around
+    // - orbit: left drag rotates around the origin, scroll zooms, middle drag pans
+    let mut camera = Camera::new(
+        Vec3::new(0., 0., 3.),
+        0.,
+        0.,
         size.width as f32 / size.height as f32,
-        0.1,
-        40.,
     );
+    let mut camera_controllers: [(&str, Box<dyn CameraController>); 2] = [
+        (
+            "fps",
+            Box::new(FpsCameraController::new(CAMERA_SPEED, CAMERA_SENSITIVITY)),
+        ),
+        (
+            "orbit",
+            Box::new(OrbitCameraController::new(
+                Vec3::ZERO,
+                3.,
+                CAMERA_SENSITIVITY,
+            )),
+        ),
+    ];
+    let mut active_controller = 0;
+    let view = camera.view();
+    let projection = camera.projection();
 
     // mat4X4 bindgroup layout
     let mat4_bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
@@ -319,7 +273,12 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
     });
 
     // shader
-    let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+    let shader = vertex_layout::shader(
+        &device,
+        "shader.wgsl",
+        include_str!("shader.wgsl"),
+        &[Vertex::WGSL, Transform::WGSL],
+    );
 
     let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: None,
@@ -333,19 +292,13 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     let preferred_format = surface.get_supported_formats(&adapter)[0];
 
-    let vertex_buffer_layout = wgpu::VertexBufferLayout {
-        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-        step_mode: wgpu::VertexStepMode::Vertex,
-        attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
-    };
-
     let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: None,
         layout: Some(&pipeline_layout),
         vertex: wgpu::VertexState {
             module: &shader,
             entry_point: "vs_main",
-            buffers: &[vertex_buffer_layout, Transform::buffer_layout()],
+            buffers: &[Vertex::buffer_layout(), Transform::buffer_layout()],
         },
         fragment: Some(wgpu::FragmentState {
             module: &shader,
@@ -389,10 +342,69 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
     let mut last_frame_game_time: f32 = 0.;
 
+    // late latching, L toggles it
+    // - off: the camera is sampled first, then the frame waits for a swapchain image and records
+    // - on: the frame acquires and records at the end of the previous loop iteration, the input
+    //   that arrives while it waits for vsync is read right before the camera upload and submit
+    let mut late_latch = false;
+    let mut pending_frame: Option<(wgpu::SurfaceTexture, wgpu::CommandBuffer, Instant)> = None;
+    let mut latency_sum = (0., 0.);
+    let mut latency_frames = 0;
+    let mut latency_start = Instant::now();
+
     event_loop.run(move |event, _, control_flow| {
         let _ = (&instance, &adapter, &shader, &pipeline_layout);
         *control_flow = ControlFlow::Wait;
 
+        // wait for the next swapchain image and record the frame, the camera buffer is only
+        // bound here, its contents are whatever was last written when the frame is submitted
+        let encode_frame = |mat4: Mat4| {
+            let transform_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Transform Buffer"),
+                contents: bytemuck::cast_slice(mat4.as_ref()),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+            let frame = surface
+                .get_current_texture()
+                .expect("Fail to request next swap chain texture");
+
+            let view = frame
+                .texture
+                .create_view(&wgpu::TextureViewDescriptor::default());
+
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+            {
+                let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                });
+
+                rpass.set_pipeline(&render_pipeline);
+                rpass.set_bind_group(0, &diffuse_bindgroup, &[]);
+                rpass.set_bind_group(1, &view_bindgroup, &[]);
+                rpass.set_bind_group(2, &projection_bindgroup, &[]);
+                rpass.set_vertex_buffer(0, vertices_buf.slice(..)); // vertex_buffer
+                rpass.set_vertex_buffer(1, transform_buf.slice(..)); // transform mat4 buffer
+                rpass.set_index_buffer(indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+                // rpass.draw(0..3, 0..1);
+                rpass.draw_indexed(0..indices.len() as u32, 0, 0..1)
+            }
+
+            (frame, encoder.finish())
+        };
+
         match event {
             Event::RedrawRequested(_) => {
                 let game_time = now.elapsed().as_secs_f32();
@@ -401,73 +413,104 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
 
                 info!("------------game time : {:?}", game_time);
 
-                transform =
-                    // transform.rotate_z((std::f32::consts::PI * delta_time).sin() * ROTATE_SPEED);
-                    // transform.rotate_z(delta_time);
-                transform.rotate_x(delta_time);
+                transform = transform.rotate_x(delta_time);
 
                 transform = transform.add_translate(game_time.cos() / 100.);
                 transform = transform.set_scale(game_time.sin().max(0.1));
-                let mat4 = transform.to_mat4();
-                let mut transform_buf =
-                    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                        label: Some("Transform Buffer"),
-                        contents: bytemuck::cast_slice(mat4.as_ref()),
-                        usage: wgpu::BufferUsages::VERTEX,
-                    });
 
-                let frame = surface
-                    .get_current_texture()
-                    .expect("Fail to request next swap chain texture");
-
-                let view = frame
-                    .texture
-                    .create_view(&wgpu::TextureViewDescriptor::default());
-
-                let mut encoder =
-                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
-
-                {
-                    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                        label: Some("Render Pass"),
-                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                            view: &view,
-                            resolve_target: None,
-                            ops: wgpu::Operations {
-                                load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                                store: true,
-                            },
-                        })],
-                        depth_stencil_attachment: None,
-                    });
-
-                    rpass.set_pipeline(&render_pipeline);
-                    rpass.set_bind_group(0, &diffuse_bindgroup, &[]);
-                    rpass.set_bind_group(1, &view_bindgroup, &[]);
-                    rpass.set_bind_group(2, &projection_bindgroup, &[]);
-                    rpass.set_vertex_buffer(0, vertices_buf.slice(..)); // vertex_buffer
-                    rpass.set_vertex_buffer(1, transform_buf.slice(..)); // transform mat4 buffer
-                    rpass.set_index_buffer(indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+                // `early_sample` is when early sampling reads the camera, before acquire and
+                // recording, `latched` is when the camera actually got read
+                let (frame, commands, early_sample, latched) = match pending_frame.take() {
+                    Some((frame, commands, early_sample)) => {
+                        camera_controllers[active_controller]
+                            .1
+                            .update_camera(&mut camera, delta_time);
+                        (frame, commands, early_sample, Instant::now())
+                    }
+                    None => {
+                        let early_sample = Instant::now();
+                        camera_controllers[active_controller]
+                            .1
+                            .update_camera(&mut camera, delta_time);
+                        let (frame, commands) = encode_frame(transform.to_mat4());
+                        (frame, commands, early_sample, early_sample)
+                    }
+                };
+
+                // write_buffer lands before the submitted commands run, so this is the last
+                // moment the camera can change for this frame
+                queue.write_buffer(
+                    &view_buffer,
+                    0,
+                    bytemuck::cast_slice(camera.view().as_ref()),
+                );
+                queue.write_buffer(
+                    &projection_buffer,
+                    0,
+                    bytemuck::cast_slice(camera.projection().as_ref()),
+                );
+
+                // how old the camera input is at submit, and how old it would be sampled early
+                latency_sum.0 += latched.elapsed().as_secs_f32();
+                latency_sum.1 += early_sample.elapsed().as_secs_f32();
+                latency_frames += 1;
+
+                queue.submit(Some(commands));
+                frame.present();
 
-                    // rpass.draw(0..3, 0..1);
-                    rpass.draw_indexed(0..indices.len() as u32, 0, 0..1)
+                if latency_start.elapsed().as_secs_f32() >= LATENCY_INTERVAL {
+                    let title = format!(
+                        "{}, {}, camera {:.2}ms old at submit, early sampling {:.2}ms",
+                        camera_controllers[active_controller].0,
+                        if late_latch {
+                            "late latching"
+                        } else {
+                            "early sampling"
+                        },
+                        latency_sum.0 * 1000. / latency_frames as f32,
+                        latency_sum.1 * 1000. / latency_frames as f32,
+                    );
+                    window.set_title(&title);
+
+                    latency_sum = (0., 0.);
+                    latency_frames = 0;
+                    latency_start = Instant::now();
                 }
-
-                queue.submit(Some(encoder.finish()));
-                frame.present();
             }
             Event::RedrawEventsCleared => {
                 info!("----------------------------------- redraw ");
+                // record the next frame now, the wait for its swapchain image happens before
+                // the next batch of input events instead of after it
+                if late_latch && pending_frame.is_none() {
+                    pending_frame = Some({
+                        let early_sample = Instant::now();
+                        let (frame, commands) = encode_frame(transform.to_mat4());
+                        (frame, commands, early_sample)
+                    });
+                }
+
                 // RedrawRequested will only trigger once, unless we manually
                 // request it.
                 window.request_redraw();
             }
+            Event::DeviceEvent { event, .. } => {
+                camera_controllers[active_controller]
+                    .1
+                    .process_device_event(&event);
+            }
             Event::WindowEvent { window_id, event } if window_id == window.id() => {
+                camera_controllers[active_controller]
+                    .1
+                    .process_window_event(&event);
+
                 match event {
                     WindowEvent::Resized(size) => {
+                        // the recorded frame targets the old swapchain
+                        pending_frame = None;
                         config.width = size.width;
                         config.height = size.height;
                         surface.configure(&device, &config);
+                        camera.resize(size.width, size.height);
 
                         window.request_redraw(); // for macos, need redraw when size change
                     }
@@ -485,6 +528,32 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
                         info!("exit");
                         *control_flow = ControlFlow::Exit
                     }
+
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::Tab),
+                                ..
+                            },
+                        ..
+                    } => {
+                        active_controller = (active_controller + 1) % camera_controllers.len();
+                        window.set_title(camera_controllers[active_controller].0);
+                    }
+
+                    WindowEvent::KeyboardInput {
+                        input:
+                            KeyboardInput {
+                                state: ElementState::Pressed,
+                                virtual_keycode: Some(VirtualKeyCode::L),
+                                ..
+                            },
+                        ..
+                    } => {
+                        // the title picks up the new mode with the next latency estimate
+                        late_latch = !late_latch;
+                    }
                     _ => {}
                 }
             }
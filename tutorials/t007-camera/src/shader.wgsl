@@ -1,3 +1,4 @@
+// `Vertex` in main.rs is what's used, vertex_layout! generates this and the struct below
 struct VertexInput{
     @location(0) pos: vec3<f32>,
     @location(1) tex_coord: vec2<f32>,
@@ -0,0 +1,38 @@
+// convert the captured cubemap into a 2:1 equirectangular image,
+// one invocation per output pixel
+
+@group(0)
+@binding(0)
+var env_cube: texture_cube<f32>;
+@group(0)
+@binding(1)
+var env_sampler: sampler;
+@group(0)
+@binding(2)
+var output: texture_storage_2d<rgba32float, write>;
+
+let PI: f32 = 3.14159265359;
+
+@compute
+@workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if (i32(id.x) >= size.x || i32(id.y) >= size.y) {
+        return;
+    }
+
+    // pixel center -> [0, 1]
+    let uv = (vec2<f32>(id.xy) + vec2<f32>(0.5)) / vec2<f32>(size);
+
+    // u = 0.5 looks down -z, the default camera forward
+    let longitude = (uv.x - 0.5) * 2.0 * PI;
+    let latitude = (0.5 - uv.y) * PI;
+    let dir = vec3<f32>(
+        cos(latitude) * sin(longitude),
+        sin(latitude),
+        -cos(latitude) * cos(longitude),
+    );
+
+    let color = textureSampleLevel(env_cube, env_sampler, dir, 0.0);
+    textureStore(output, vec2<i32>(id.xy), vec4<f32>(color.rgb, 1.0));
+}
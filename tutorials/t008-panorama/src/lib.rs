@@ -0,0 +1,721 @@
+use std::time::Instant;
+
+use bytemuck::{Pod, Zeroable};
+use framework::{texture::DepthTexture, watchdog};
+use glam::{Mat4, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "panorama";
+
+const CUBE_FACE_SIZE: u32 = 512;
+const PANORAMA_WIDTH: u32 = CUBE_FACE_SIZE * 4;
+const PANORAMA_HEIGHT: u32 = CUBE_FACE_SIZE * 2;
+
+const CUBE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const PANORAMA_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba32Float;
+// the capture renders with its own depth buffer, the window with the driver's
+const DEPTH_FORMAT: wgpu::TextureFormat = DepthTexture::FORMAT;
+
+const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+
+const ROTATE_SPEED: f32 = 0.3;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3]) -> Vertex {
+    Vertex { pos, normal }
+}
+
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let vertices = vec![
+        // front
+        vertex([-0.5, -0.5, 0.5], [0., 0., 1.]),
+        vertex([0.5, -0.5, 0.5], [0., 0., 1.]),
+        vertex([0.5, 0.5, 0.5], [0., 0., 1.]),
+        vertex([-0.5, 0.5, 0.5], [0., 0., 1.]),
+        // back
+        vertex([0.5, -0.5, -0.5], [0., 0., -1.]),
+        vertex([-0.5, -0.5, -0.5], [0., 0., -1.]),
+        vertex([-0.5, 0.5, -0.5], [0., 0., -1.]),
+        vertex([0.5, 0.5, -0.5], [0., 0., -1.]),
+        // right
+        vertex([0.5, -0.5, 0.5], [1., 0., 0.]),
+        vertex([0.5, -0.5, -0.5], [1., 0., 0.]),
+        vertex([0.5, 0.5, -0.5], [1., 0., 0.]),
+        vertex([0.5, 0.5, 0.5], [1., 0., 0.]),
+        // left
+        vertex([-0.5, -0.5, -0.5], [-1., 0., 0.]),
+        vertex([-0.5, -0.5, 0.5], [-1., 0., 0.]),
+        vertex([-0.5, 0.5, 0.5], [-1., 0., 0.]),
+        vertex([-0.5, 0.5, -0.5], [-1., 0., 0.]),
+        // top
+        vertex([-0.5, 0.5, 0.5], [0., 1., 0.]),
+        vertex([0.5, 0.5, 0.5], [0., 1., 0.]),
+        vertex([0.5, 0.5, -0.5], [0., 1., 0.]),
+        vertex([-0.5, 0.5, -0.5], [0., 1., 0.]),
+        // bottom
+        vertex([-0.5, -0.5, -0.5], [0., -1., 0.]),
+        vertex([0.5, -0.5, -0.5], [0., -1., 0.]),
+        vertex([0.5, -0.5, 0.5], [0., -1., 0.]),
+        vertex([-0.5, -0.5, 0.5], [0., -1., 0.]),
+    ];
+
+    let indices = (0..6)
+        .flat_map(|face| {
+            let i = face * 4;
+            [i, i + 1, i + 2, i, i + 2, i + 3]
+        })
+        .collect();
+
+    (vertices, indices)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    color: [f32; 3],
+}
+
+impl InstanceRaw {
+    fn new(translation: Vec3, scale: Vec3, color: [f32; 3]) -> InstanceRaw {
+        InstanceRaw {
+            model: (Mat4::from_translation(translation) * Mat4::from_scale(scale))
+                .to_cols_array_2d(),
+            color,
+        }
+    }
+
+    fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+            2=>Float32x4, 3=>Float32x4, 4=>Float32x4, 5=>Float32x4, // model mat4
+            6=>Float32x3, // color
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+// a ring of pillars, a floor and a few boxes overhead, so every cube face sees something
+fn create_instances() -> Vec<InstanceRaw> {
+    let mut instances = vec![
+        InstanceRaw::new(
+            Vec3::new(0., -2., 0.),
+            Vec3::new(30., 0.2, 30.),
+            [0.4, 0.4, 0.4],
+        ),
+        InstanceRaw::new(Vec3::new(0., 6., 0.), Vec3::splat(1.5), [1.0, 0.9, 0.3]),
+    ];
+
+    let count = 12;
+    for i in 0..count {
+        let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+        let pos = Vec3::new(angle.sin() * 6., 0., -angle.cos() * 6.);
+        let color = [
+            0.5 + 0.5 * angle.cos(),
+            0.5 + 0.5 * (angle + 2.094).cos(),
+            0.5 + 0.5 * (angle + 4.188).cos(),
+        ];
+        instances.push(InstanceRaw::new(pos, Vec3::new(1., 4., 1.), color));
+        instances.push(InstanceRaw::new(
+            pos * 0.5 + Vec3::Y * 3.,
+            Vec3::splat(0.6),
+            color,
+        ));
+    }
+
+    instances
+}
+
+// wgpu cube face order is +x, -x, +y, -y, +z, -z
+fn cube_face_view_proj(face: u32, eye: Vec3) -> Mat4 {
+    let (dir, up) = match face {
+        0 => (Vec3::X, Vec3::NEG_Y),
+        1 => (Vec3::NEG_X, Vec3::NEG_Y),
+        2 => (Vec3::Y, Vec3::Z),
+        3 => (Vec3::NEG_Y, Vec3::NEG_Z),
+        4 => (Vec3::Z, Vec3::NEG_Y),
+        _ => (Vec3::NEG_Z, Vec3::NEG_Y),
+    };
+
+    // cubemap texels have their first row at the top, while the face up vectors
+    // above follow the gl convention, so flip y to land in the right orientation
+    let flip_y = Mat4::from_scale(Vec3::new(1., -1., 1.));
+    let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1., 0.1, 100.);
+
+    flip_y * projection * Mat4::look_at_rh(eye, eye + dir, up)
+}
+
+fn create_depth_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("depth_texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_scene_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let vertex_buffer_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("scene pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[vertex_buffer_layout, InstanceRaw::buffer_layout()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        // the y flip of the cube faces reverses the winding, so don't cull at all
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+struct Scene {
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    instances_buf: wgpu::Buffer,
+    instance_count: u32,
+}
+
+impl Scene {
+    fn new(device: &wgpu::Device) -> Scene {
+        let (vertices, indices) = create_vertices();
+        let instances = create_instances();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let instances_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instances Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Scene {
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            instances_buf,
+            instance_count: instances.len() as u32,
+        }
+    }
+
+    fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        pipeline: &'a wgpu::RenderPipeline,
+        camera_bindgroup: &'a wgpu::BindGroup,
+    ) {
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, camera_bindgroup, &[]);
+        rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+        rpass.set_vertex_buffer(1, self.instances_buf.slice(..));
+        rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+        rpass.draw_indexed(0..self.index_count, 0, 0..self.instance_count);
+    }
+}
+
+struct PanoramaCapture {
+    pipeline: wgpu::RenderPipeline,
+    face_views: Vec<wgpu::TextureView>,
+    face_bindgroups: Vec<wgpu::BindGroup>,
+    depth_view: wgpu::TextureView,
+    equirect_pipeline: wgpu::ComputePipeline,
+    equirect_bindgroup: wgpu::BindGroup,
+    panorama_texture: wgpu::Texture,
+    readback_buf: wgpu::Buffer,
+}
+
+impl PanoramaCapture {
+    fn new(
+        device: &wgpu::Device,
+        scene_layout: &wgpu::PipelineLayout,
+        scene_shader: &wgpu::ShaderModule,
+        camera_bindgroup_layout: &wgpu::BindGroupLayout,
+        eye: Vec3,
+    ) -> PanoramaCapture {
+        let pipeline = create_scene_pipeline(device, scene_layout, scene_shader, CUBE_FORMAT);
+
+        let cube_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("capture cube texture"),
+            size: wgpu::Extent3d {
+                width: CUBE_FACE_SIZE,
+                height: CUBE_FACE_SIZE,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: CUBE_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+
+        // one 2d view per face to render into, one cube view to sample from
+        let face_views = (0..6)
+            .map(|face| {
+                cube_texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("capture face view"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: face,
+                    array_layer_count: std::num::NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+
+        let cube_view = cube_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("capture cube view"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        let face_bindgroups = (0..6)
+            .map(|face| {
+                let view_proj = cube_face_view_proj(face, eye);
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Capture Face Buffer"),
+                    contents: bytemuck::cast_slice(view_proj.as_ref()),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                });
+
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("capture face bind group"),
+                    layout: camera_bindgroup_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                })
+            })
+            .collect();
+
+        let depth_view = create_depth_view(device, CUBE_FACE_SIZE, CUBE_FACE_SIZE);
+
+        let panorama_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("panorama texture"),
+            size: wgpu::Extent3d {
+                width: PANORAMA_WIDTH,
+                height: PANORAMA_HEIGHT,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: PANORAMA_FORMAT,
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::COPY_SRC,
+        });
+        let panorama_view = panorama_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let cube_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("capture cube sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let equirect_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("equirect bindgroup layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: PANORAMA_FORMAT,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let equirect_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("equirect bind group"),
+            layout: &equirect_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&cube_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&cube_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&panorama_view),
+                },
+            ],
+        });
+
+        let equirect_shader = device.create_shader_module(include_wgsl!("equirect.wgsl"));
+        let equirect_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("equirect pipeline layout"),
+                bind_group_layouts: &[&equirect_bindgroup_layout],
+                push_constant_ranges: &[],
+            });
+
+        let equirect_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("equirect pipeline"),
+            layout: Some(&equirect_pipeline_layout),
+            module: &equirect_shader,
+            entry_point: "cs_main",
+        });
+
+        // 16 bytes per rgba32float texel, PANORAMA_WIDTH keeps rows 256 aligned
+        let readback_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Panorama Readback Buffer"),
+            size: (PANORAMA_WIDTH * PANORAMA_HEIGHT * 16) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        PanoramaCapture {
+            pipeline,
+            face_views,
+            face_bindgroups,
+            depth_view,
+            equirect_pipeline,
+            equirect_bindgroup,
+            panorama_texture,
+            readback_buf,
+        }
+    }
+
+    // render the six faces, unwrap them in a compute pass and read the result back
+    fn capture(&self, device: &wgpu::Device, queue: &wgpu::Queue, scene: &Scene) -> Vec<f32> {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("panorama capture encoder"),
+        });
+
+        for (face_view, face_bindgroup) in self.face_views.iter().zip(&self.face_bindgroups) {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Capture Face Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: face_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(CLEAR_COLOR),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            scene.draw(&mut rpass, &self.pipeline, face_bindgroup);
+        }
+
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Equirect Pass"),
+            });
+            cpass.set_pipeline(&self.equirect_pipeline);
+            cpass.set_bind_group(0, &self.equirect_bindgroup, &[]);
+            cpass.dispatch_workgroups(PANORAMA_WIDTH / 8, PANORAMA_HEIGHT / 8, 1);
+        }
+
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.panorama_texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.readback_buf,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(PANORAMA_WIDTH * 16),
+                    rows_per_image: std::num::NonZeroU32::new(PANORAMA_HEIGHT),
+                },
+            },
+            wgpu::Extent3d {
+                width: PANORAMA_WIDTH,
+                height: PANORAMA_HEIGHT,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        queue.submit(Some(encoder.finish()));
+
+        let slice = self.readback_buf.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Fail to map panorama readback buffer")
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        let pixels = bytemuck::cast_slice(&slice.get_mapped_range()).to_vec();
+        self.readback_buf.unmap();
+
+        pixels
+    }
+}
+
+fn save_panorama(pixels: &[f32]) {
+    let rgb: Vec<image::Rgb<f32>> = pixels
+        .chunks_exact(4)
+        .map(|p| image::Rgb([p[0], p[1], p[2]]))
+        .collect();
+
+    let hdr_file = std::fs::File::create("panorama.hdr").expect("Fail to create panorama.hdr");
+    image::codecs::hdr::HdrEncoder::new(std::io::BufWriter::new(hdr_file))
+        .encode(&rgb, PANORAMA_WIDTH as usize, PANORAMA_HEIGHT as usize)
+        .expect("Fail to write panorama.hdr");
+
+    // clamp and gamma encode for the ldr version
+    let ldr: Vec<u8> = rgb
+        .iter()
+        .flat_map(|image::Rgb(c)| c.map(|v| (v.clamp(0., 1.).powf(1. / 2.2) * 255.).round() as u8))
+        .collect();
+    image::save_buffer(
+        "panorama.png",
+        &ldr,
+        PANORAMA_WIDTH,
+        PANORAMA_HEIGHT,
+        image::ColorType::Rgb8,
+    )
+    .expect("Fail to write panorama.png");
+}
+
+// capture from the same spot the window camera stands
+const EYE: Vec3 = Vec3::new(0., 1., 0.);
+
+pub struct Panorama {
+    render_pipeline: wgpu::RenderPipeline,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    scene: Scene,
+    capture: PanoramaCapture,
+    // P asks for one, `render` has the device to take it
+    capture_requested: bool,
+    aspect: f32,
+}
+
+impl framework::App for Panorama {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera, one mat4 view_proj
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64),
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(Mat4::IDENTITY.as_ref()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline =
+            create_scene_pipeline(device, &pipeline_layout, &shader, config.format);
+
+        let scene = Scene::new(device);
+
+        let capture = PanoramaCapture::new(
+            device,
+            &pipeline_layout,
+            &shader,
+            &camera_bindgroup_layout,
+            EYE,
+        );
+
+        info!("press P to save panorama.png and panorama.hdr");
+
+        Panorama {
+            render_pipeline,
+            camera_buffer,
+            camera_bindgroup,
+            scene,
+            capture,
+            capture_requested: false,
+            aspect: config.width as f32 / config.height as f32,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.aspect = config.width as f32 / config.height as f32;
+    }
+
+    fn update(&mut self, _window: &Window, event: WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::P),
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.capture_requested = true;
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.capture_requested {
+            self.capture_requested = false;
+            let start = Instant::now();
+            let pixels = self.capture.capture(device, queue, &self.scene);
+            save_panorama(&pixels);
+            info!(
+                "saved {}x{} panorama in {:?}",
+                PANORAMA_WIDTH,
+                PANORAMA_HEIGHT,
+                start.elapsed()
+            );
+        }
+
+        let yaw = frame.time * ROTATE_SPEED;
+        let forward = Vec3::new(yaw.sin(), 0., -yaw.cos());
+        let view = Mat4::look_at_rh(EYE, EYE + forward, Vec3::Y);
+        let projection = Mat4::perspective_rh(60.0_f32.to_radians(), self.aspect, 0.1, 100.);
+        let view_proj = projection * view;
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(view_proj.as_ref()),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, CLEAR_COLOR);
+            self.scene
+                .draw(&mut rpass, &self.render_pipeline, &self.camera_bindgroup);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
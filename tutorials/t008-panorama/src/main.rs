@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t08_panorama::Panorama>(t08_panorama::TITLE);
+}
@@ -0,0 +1,554 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{texture::DepthTexture, watchdog};
+use glam::{Mat4, Vec3, Vec4};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "stereo";
+
+// the driver's depth buffer
+const DEPTH_FORMAT: wgpu::TextureFormat = DepthTexture::FORMAT;
+
+const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+
+const ROTATE_SPEED: f32 = 0.3;
+
+// interpupillary distance in world units (meters), the human average is ~0.064
+const DEFAULT_IPD: f32 = 0.064;
+const IPD_STEP: f32 = 0.004;
+const DEFAULT_CONVERGENCE: f32 = 6.;
+const CONVERGENCE_STEP: f32 = 0.5;
+
+const FOV_Y: f32 = 60.;
+const Z_NEAR: f32 = 0.1;
+const Z_FAR: f32 = 100.;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3]) -> Vertex {
+    Vertex { pos, normal }
+}
+
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let vertices = vec![
+        // front
+        vertex([-0.5, -0.5, 0.5], [0., 0., 1.]),
+        vertex([0.5, -0.5, 0.5], [0., 0., 1.]),
+        vertex([0.5, 0.5, 0.5], [0., 0., 1.]),
+        vertex([-0.5, 0.5, 0.5], [0., 0., 1.]),
+        // back
+        vertex([0.5, -0.5, -0.5], [0., 0., -1.]),
+        vertex([-0.5, -0.5, -0.5], [0., 0., -1.]),
+        vertex([-0.5, 0.5, -0.5], [0., 0., -1.]),
+        vertex([0.5, 0.5, -0.5], [0., 0., -1.]),
+        // right
+        vertex([0.5, -0.5, 0.5], [1., 0., 0.]),
+        vertex([0.5, -0.5, -0.5], [1., 0., 0.]),
+        vertex([0.5, 0.5, -0.5], [1., 0., 0.]),
+        vertex([0.5, 0.5, 0.5], [1., 0., 0.]),
+        // left
+        vertex([-0.5, -0.5, -0.5], [-1., 0., 0.]),
+        vertex([-0.5, -0.5, 0.5], [-1., 0., 0.]),
+        vertex([-0.5, 0.5, 0.5], [-1., 0., 0.]),
+        vertex([-0.5, 0.5, -0.5], [-1., 0., 0.]),
+        // top
+        vertex([-0.5, 0.5, 0.5], [0., 1., 0.]),
+        vertex([0.5, 0.5, 0.5], [0., 1., 0.]),
+        vertex([0.5, 0.5, -0.5], [0., 1., 0.]),
+        vertex([-0.5, 0.5, -0.5], [0., 1., 0.]),
+        // bottom
+        vertex([-0.5, -0.5, -0.5], [0., -1., 0.]),
+        vertex([0.5, -0.5, -0.5], [0., -1., 0.]),
+        vertex([0.5, -0.5, 0.5], [0., -1., 0.]),
+        vertex([-0.5, -0.5, 0.5], [0., -1., 0.]),
+    ];
+
+    let indices = (0..6)
+        .flat_map(|face| {
+            let i = face * 4;
+            [i, i + 1, i + 2, i, i + 2, i + 3]
+        })
+        .collect();
+
+    (vertices, indices)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    color: [f32; 3],
+}
+
+impl InstanceRaw {
+    fn new(translation: Vec3, scale: Vec3, color: [f32; 3]) -> InstanceRaw {
+        InstanceRaw {
+            model: (Mat4::from_translation(translation) * Mat4::from_scale(scale))
+                .to_cols_array_2d(),
+            color,
+        }
+    }
+
+    fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+            2=>Float32x4, 3=>Float32x4, 4=>Float32x4, 5=>Float32x4, // model mat4
+            6=>Float32x3, // color
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+// a ring of pillars around the viewer, a floor and a few floating boxes at different depths
+fn create_instances() -> Vec<InstanceRaw> {
+    let mut instances = vec![
+        InstanceRaw::new(
+            Vec3::new(0., -2., 0.),
+            Vec3::new(30., 0.2, 30.),
+            [0.4, 0.4, 0.4],
+        ),
+        InstanceRaw::new(Vec3::new(0., 6., 0.), Vec3::splat(1.5), [1.0, 0.9, 0.3]),
+    ];
+
+    let count = 12;
+    for i in 0..count {
+        let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+        let pos = Vec3::new(angle.sin() * 6., 0., -angle.cos() * 6.);
+        let color = [
+            0.5 + 0.5 * angle.cos(),
+            0.5 + 0.5 * (angle + 2.094).cos(),
+            0.5 + 0.5 * (angle + 4.188).cos(),
+        ];
+        instances.push(InstanceRaw::new(pos, Vec3::new(1., 4., 1.), color));
+        instances.push(InstanceRaw::new(
+            pos * 0.5 + Vec3::Y * 3.,
+            Vec3::splat(0.6),
+            color,
+        ));
+    }
+
+    instances
+}
+
+fn create_scene_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let vertex_buffer_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("scene pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[vertex_buffer_layout, InstanceRaw::buffer_layout()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+struct Scene {
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    instances_buf: wgpu::Buffer,
+    instance_count: u32,
+}
+
+impl Scene {
+    fn new(device: &wgpu::Device) -> Scene {
+        let (vertices, indices) = create_vertices();
+        let instances = create_instances();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let instances_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instances Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Scene {
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            instances_buf,
+            instance_count: instances.len() as u32,
+        }
+    }
+
+    fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        pipeline: &'a wgpu::RenderPipeline,
+        camera_bindgroup: &'a wgpu::BindGroup,
+    ) {
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, camera_bindgroup, &[]);
+        rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+        rpass.set_vertex_buffer(1, self.instances_buf.slice(..));
+        rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+        rpass.draw_indexed(0..self.index_count, 0, 0..self.instance_count);
+    }
+}
+
+// right handed, 0..1 depth, like Mat4::perspective_rh but with an arbitrary
+// (possibly asymmetric) near plane rectangle
+fn off_axis_projection(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+    Mat4::from_cols(
+        Vec4::new(2. * near / (right - left), 0., 0., 0.),
+        Vec4::new(0., 2. * near / (top - bottom), 0., 0.),
+        Vec4::new(
+            (right + left) / (right - left),
+            (top + bottom) / (top - bottom),
+            far / (near - far),
+            -1.,
+        ),
+        Vec4::new(0., 0., near * far / (near - far), 0.),
+    )
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StereoMode {
+    Mono,
+    // left eye on the left half, for parallel viewing and most hmd/3d tv inputs
+    SideBySide,
+    // left eye on the right half, for cross-eyed free viewing
+    CrossEyed,
+}
+
+impl StereoMode {
+    fn next(self) -> StereoMode {
+        match self {
+            StereoMode::Mono => StereoMode::SideBySide,
+            StereoMode::SideBySide => StereoMode::CrossEyed,
+            StereoMode::CrossEyed => StereoMode::Mono,
+        }
+    }
+}
+
+struct StereoCamera {
+    position: Vec3,
+    yaw: f32,
+    ipd: f32,
+    convergence: f32,
+}
+
+impl StereoCamera {
+    fn forward(&self) -> Vec3 {
+        Vec3::new(self.yaw.sin(), 0., -self.yaw.cos())
+    }
+
+    fn right(&self) -> Vec3 {
+        self.forward().cross(Vec3::Y).normalize()
+    }
+
+    // eye is -1 for left, 1 for right and 0 for the mono center camera
+    fn eye_view(&self, eye: f32) -> Mat4 {
+        let pos = self.position + self.right() * eye * self.ipd * 0.5;
+        Mat4::look_at_rh(pos, pos + self.forward(), Vec3::Y)
+    }
+
+    // both eyes look straight ahead, their frustums are shifted so they overlap
+    // exactly at the convergence distance. toeing the cameras in instead would
+    // introduce vertical parallax at the screen edges
+    fn eye_projection(&self, eye: f32, aspect: f32) -> Mat4 {
+        let top = Z_NEAR * (FOV_Y.to_radians() * 0.5).tan();
+        let half_width = top * aspect;
+        let shift = -eye * self.ipd * 0.5 * Z_NEAR / self.convergence;
+
+        off_axis_projection(
+            -half_width + shift,
+            half_width + shift,
+            -top,
+            top,
+            Z_NEAR,
+            Z_FAR,
+        )
+    }
+
+    fn eye_view_proj(&self, eye: f32, aspect: f32) -> Mat4 {
+        self.eye_projection(eye, aspect) * self.eye_view(eye)
+    }
+}
+
+struct Viewport {
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+}
+
+fn eye_viewports(mode: StereoMode, width: u32, height: u32) -> (Viewport, Viewport) {
+    let (width, height) = (width as f32, height as f32);
+    let left_half = Viewport {
+        x: 0.,
+        y: 0.,
+        width: width * 0.5,
+        height,
+    };
+    let right_half = Viewport {
+        x: width * 0.5,
+        y: 0.,
+        width: width * 0.5,
+        height,
+    };
+
+    match mode {
+        StereoMode::Mono => (
+            Viewport {
+                x: 0.,
+                y: 0.,
+                width,
+                height,
+            },
+            right_half,
+        ),
+        StereoMode::SideBySide => (left_half, right_half),
+        StereoMode::CrossEyed => (right_half, left_half),
+    }
+}
+
+fn create_camera_bindgroup(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    label: &str,
+) -> (wgpu::Buffer, wgpu::BindGroup) {
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: bytemuck::cast_slice(Mat4::IDENTITY.as_ref()),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+
+    let bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+
+    (buffer, bindgroup)
+}
+
+pub struct Stereo {
+    render_pipeline: wgpu::RenderPipeline,
+    left_buffer: wgpu::Buffer,
+    left_bindgroup: wgpu::BindGroup,
+    right_buffer: wgpu::Buffer,
+    right_bindgroup: wgpu::BindGroup,
+    scene: Scene,
+    mode: StereoMode,
+    camera: StereoCamera,
+    width: u32,
+    height: u32,
+}
+
+impl framework::App for Stereo {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera, one mat4 view_proj per eye
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64),
+                    },
+                    count: None,
+                }],
+            });
+
+        let (left_buffer, left_bindgroup) =
+            create_camera_bindgroup(device, &camera_bindgroup_layout, "Left Eye Camera");
+        let (right_buffer, right_bindgroup) =
+            create_camera_bindgroup(device, &camera_bindgroup_layout, "Right Eye Camera");
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline =
+            create_scene_pipeline(device, &pipeline_layout, &shader, config.format);
+
+        info!("M: cycle mono / side by side / cross eyed, Up/Down: ipd, Left/Right: convergence");
+
+        Stereo {
+            render_pipeline,
+            left_buffer,
+            left_bindgroup,
+            right_buffer,
+            right_bindgroup,
+            scene: Scene::new(device),
+            mode: StereoMode::SideBySide,
+            camera: StereoCamera {
+                position: Vec3::new(0., 1., 0.),
+                yaw: 0.,
+                ipd: DEFAULT_IPD,
+                convergence: DEFAULT_CONVERGENCE,
+            },
+            width: config.width,
+            height: config.height,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.width = config.width;
+        self.height = config.height;
+    }
+
+    fn update(&mut self, _window: &Window, event: WindowEvent) {
+        let key = match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => key,
+            _ => return,
+        };
+
+        let camera = &mut self.camera;
+        match key {
+            VirtualKeyCode::M => self.mode = self.mode.next(),
+            VirtualKeyCode::Up => camera.ipd += IPD_STEP,
+            VirtualKeyCode::Down => camera.ipd = (camera.ipd - IPD_STEP).max(0.),
+            VirtualKeyCode::Right => camera.convergence += CONVERGENCE_STEP,
+            VirtualKeyCode::Left => {
+                camera.convergence = (camera.convergence - CONVERGENCE_STEP).max(CONVERGENCE_STEP)
+            }
+            _ => return,
+        }
+        info!(
+            "mode: {:?}, ipd: {:.3}, convergence: {:.1}",
+            self.mode, camera.ipd, camera.convergence
+        );
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera.yaw = frame.time * ROTATE_SPEED;
+
+        let (left_viewport, right_viewport) = eye_viewports(self.mode, self.width, self.height);
+
+        let stereo = self.mode != StereoMode::Mono;
+        let (left_eye, right_eye) = if stereo { (-1., 1.) } else { (0., 0.) };
+
+        let left_view_proj = self
+            .camera
+            .eye_view_proj(left_eye, left_viewport.width / left_viewport.height);
+        let right_view_proj = self
+            .camera
+            .eye_view_proj(right_eye, right_viewport.width / right_viewport.height);
+        watchdog::write_buffer(
+            queue,
+            &self.left_buffer,
+            0,
+            bytemuck::cast_slice(left_view_proj.as_ref()),
+        );
+        watchdog::write_buffer(
+            queue,
+            &self.right_buffer,
+            0,
+            bytemuck::cast_slice(right_view_proj.as_ref()),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, CLEAR_COLOR);
+
+            // the eyes never overlap, so they can share the color and depth attachments
+            let vp = &left_viewport;
+            rpass.set_viewport(vp.x, vp.y, vp.width, vp.height, 0., 1.);
+            self.scene
+                .draw(&mut rpass, &self.render_pipeline, &self.left_bindgroup);
+
+            if stereo {
+                let vp = &right_viewport;
+                rpass.set_viewport(vp.x, vp.y, vp.width, vp.height, 0., 1.);
+                self.scene
+                    .draw(&mut rpass, &self.render_pipeline, &self.right_bindgroup);
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t09_stereo::Stereo>(t09_stereo::TITLE);
+}
@@ -0,0 +1,44 @@
+struct VertexInput {
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+struct InstanceInput {
+    @location(2) mat0: vec4<f32>,
+    @location(3) mat1: vec4<f32>,
+    @location(4) mat2: vec4<f32>,
+    @location(5) mat3: vec4<f32>,
+    @location(6) color: vec3<f32>,
+};
+
+struct FragInput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> view_proj: mat4x4<f32>;
+
+@vertex
+fn vs_main(input: VertexInput, instance: InstanceInput) -> FragInput {
+    var fragInput: FragInput;
+    let model = mat4x4<f32>(
+        instance.mat0,
+        instance.mat1,
+        instance.mat2,
+        instance.mat3,
+    );
+    fragInput.clip_position = view_proj * model * vec4<f32>(input.pos, 1.0);
+    fragInput.normal = (model * vec4<f32>(input.normal, 0.0)).xyz;
+    fragInput.color = instance.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let light_dir = normalize(vec3<f32>(0.4, 1.0, 0.3));
+    let diffuse = max(dot(normalize(input.normal), light_dir), 0.0);
+    return vec4<f32>(input.color * (0.25 + 0.75 * diffuse), 1.0);
+}
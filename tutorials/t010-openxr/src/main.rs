@@ -0,0 +1,14 @@
+use tracing::Level;
+
+#[cfg(feature = "xr")]
+mod xr;
+
+fn main() {
+    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+
+    #[cfg(feature = "xr")]
+    xr::run();
+
+    #[cfg(not(feature = "xr"))]
+    tracing::warn!("built without openxr, run with `cargo run -p t10-openxr --features xr`");
+}
@@ -0,0 +1,833 @@
+// renders the stereo chapter's scene into an openxr head mounted display.
+//
+// wgpu can't talk to openxr directly, so the vulkan instance and device are
+// created by the openxr runtime (it knows which extensions the compositor needs)
+// and then wrapped into wgpu through wgpu-hal. the swapchain images handed out by
+// the runtime are wrapped the same way, after that it's plain wgpu again.
+
+use std::ffi::CString;
+
+use ash::vk::{self, Handle};
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Quat, Vec3, Vec4};
+use tracing::{info, warn};
+use wgpu::include_wgsl;
+use wgpu_hal::{api::Vulkan as V, Api};
+
+use openxr as xr;
+use wgpu::util::DeviceExt;
+
+const VIEW_TYPE: xr::ViewConfigurationType = xr::ViewConfigurationType::PRIMARY_STEREO;
+const VIEW_COUNT: u32 = 2;
+
+const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+const VK_COLOR_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+
+const Z_NEAR: f32 = 0.05;
+const Z_FAR: f32 = 100.;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3]) -> Vertex {
+    Vertex { pos, normal }
+}
+
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let vertices = vec![
+        // front
+        vertex([-0.5, -0.5, 0.5], [0., 0., 1.]),
+        vertex([0.5, -0.5, 0.5], [0., 0., 1.]),
+        vertex([0.5, 0.5, 0.5], [0., 0., 1.]),
+        vertex([-0.5, 0.5, 0.5], [0., 0., 1.]),
+        // back
+        vertex([0.5, -0.5, -0.5], [0., 0., -1.]),
+        vertex([-0.5, -0.5, -0.5], [0., 0., -1.]),
+        vertex([-0.5, 0.5, -0.5], [0., 0., -1.]),
+        vertex([0.5, 0.5, -0.5], [0., 0., -1.]),
+        // right
+        vertex([0.5, -0.5, 0.5], [1., 0., 0.]),
+        vertex([0.5, -0.5, -0.5], [1., 0., 0.]),
+        vertex([0.5, 0.5, -0.5], [1., 0., 0.]),
+        vertex([0.5, 0.5, 0.5], [1., 0., 0.]),
+        // left
+        vertex([-0.5, -0.5, -0.5], [-1., 0., 0.]),
+        vertex([-0.5, -0.5, 0.5], [-1., 0., 0.]),
+        vertex([-0.5, 0.5, 0.5], [-1., 0., 0.]),
+        vertex([-0.5, 0.5, -0.5], [-1., 0., 0.]),
+        // top
+        vertex([-0.5, 0.5, 0.5], [0., 1., 0.]),
+        vertex([0.5, 0.5, 0.5], [0., 1., 0.]),
+        vertex([0.5, 0.5, -0.5], [0., 1., 0.]),
+        vertex([-0.5, 0.5, -0.5], [0., 1., 0.]),
+        // bottom
+        vertex([-0.5, -0.5, -0.5], [0., -1., 0.]),
+        vertex([0.5, -0.5, -0.5], [0., -1., 0.]),
+        vertex([0.5, -0.5, 0.5], [0., -1., 0.]),
+        vertex([-0.5, -0.5, 0.5], [0., -1., 0.]),
+    ];
+
+    let indices = (0..6)
+        .flat_map(|face| {
+            let i = face * 4;
+            [i, i + 1, i + 2, i, i + 2, i + 3]
+        })
+        .collect();
+
+    (vertices, indices)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    color: [f32; 3],
+}
+
+impl InstanceRaw {
+    fn new(translation: Vec3, scale: Vec3, color: [f32; 3]) -> InstanceRaw {
+        InstanceRaw {
+            model: (Mat4::from_translation(translation) * Mat4::from_scale(scale))
+                .to_cols_array_2d(),
+            color,
+        }
+    }
+
+    fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+            2=>Float32x4, 3=>Float32x4, 4=>Float32x4, 5=>Float32x4, // model mat4
+            6=>Float32x3, // color
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+// same scene as the stereo chapter, lifted so the floor sits at the stage
+// origin, which openxr puts on the physical floor
+fn create_instances() -> Vec<InstanceRaw> {
+    let mut instances = vec![
+        InstanceRaw::new(
+            Vec3::new(0., -0.1, 0.),
+            Vec3::new(30., 0.2, 30.),
+            [0.4, 0.4, 0.4],
+        ),
+        InstanceRaw::new(Vec3::new(0., 8., 0.), Vec3::splat(1.5), [1.0, 0.9, 0.3]),
+    ];
+
+    let count = 12;
+    for i in 0..count {
+        let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+        let pos = Vec3::new(angle.sin() * 6., 2., -angle.cos() * 6.);
+        let color = [
+            0.5 + 0.5 * angle.cos(),
+            0.5 + 0.5 * (angle + 2.094).cos(),
+            0.5 + 0.5 * (angle + 4.188).cos(),
+        ];
+        instances.push(InstanceRaw::new(pos, Vec3::new(1., 4., 1.), color));
+        instances.push(InstanceRaw::new(
+            Vec3::new(pos.x * 0.5, 1.5, pos.z * 0.5),
+            Vec3::splat(0.3),
+            color,
+        ));
+    }
+
+    instances
+}
+
+fn create_depth_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("depth_texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_scene_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let vertex_buffer_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("scene pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[vertex_buffer_layout, InstanceRaw::buffer_layout()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+struct Scene {
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    instances_buf: wgpu::Buffer,
+    instance_count: u32,
+}
+
+impl Scene {
+    fn new(device: &wgpu::Device) -> Scene {
+        let (vertices, indices) = create_vertices();
+        let instances = create_instances();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let instances_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instances Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        Scene {
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            instances_buf,
+            instance_count: instances.len() as u32,
+        }
+    }
+
+    fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        pipeline: &'a wgpu::RenderPipeline,
+        camera_bindgroup: &'a wgpu::BindGroup,
+    ) {
+        rpass.set_pipeline(pipeline);
+        rpass.set_bind_group(0, camera_bindgroup, &[]);
+        rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+        rpass.set_vertex_buffer(1, self.instances_buf.slice(..));
+        rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+        rpass.draw_indexed(0..self.index_count, 0, 0..self.instance_count);
+    }
+}
+
+// right handed, 0..1 depth, like Mat4::perspective_rh but with an arbitrary
+// (possibly asymmetric) near plane rectangle
+fn off_axis_projection(left: f32, right: f32, bottom: f32, top: f32, near: f32, far: f32) -> Mat4 {
+    Mat4::from_cols(
+        Vec4::new(2. * near / (right - left), 0., 0., 0.),
+        Vec4::new(0., 2. * near / (top - bottom), 0., 0.),
+        Vec4::new(
+            (right + left) / (right - left),
+            (top + bottom) / (top - bottom),
+            far / (near - far),
+            -1.,
+        ),
+        Vec4::new(0., 0., near * far / (near - far), 0.),
+    )
+}
+
+// openxr reports the eye frustum as four half angles, angle_left and
+// angle_down are negative
+fn eye_projection(fov: xr::Fovf) -> Mat4 {
+    off_axis_projection(
+        Z_NEAR * fov.angle_left.tan(),
+        Z_NEAR * fov.angle_right.tan(),
+        Z_NEAR * fov.angle_down.tan(),
+        Z_NEAR * fov.angle_up.tan(),
+        Z_NEAR,
+        Z_FAR,
+    )
+}
+
+// the pose is the eye's transform in stage space, the view matrix is its inverse
+fn eye_view(pose: xr::Posef) -> Mat4 {
+    let rotation = Quat::from_xyzw(
+        pose.orientation.x,
+        pose.orientation.y,
+        pose.orientation.z,
+        pose.orientation.w,
+    );
+    let translation = Vec3::new(pose.position.x, pose.position.y, pose.position.z);
+
+    Mat4::from_rotation_translation(rotation, translation).inverse()
+}
+
+struct XrContext {
+    xr_instance: xr::Instance,
+    system: xr::SystemId,
+    blend_mode: xr::EnvironmentBlendMode,
+    session: xr::Session<xr::Vulkan>,
+    frame_waiter: xr::FrameWaiter,
+    frame_stream: xr::FrameStream<xr::Vulkan>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+// create the vulkan instance/device through openxr, then hand them to wgpu-hal
+fn create_context() -> XrContext {
+    let xr_entry = unsafe { xr::Entry::load() }.expect("Fail to load the openxr loader");
+
+    let available_extensions = xr_entry
+        .enumerate_extensions()
+        .expect("Fail to enumerate openxr extensions");
+    assert!(
+        available_extensions.khr_vulkan_enable2,
+        "openxr runtime doesn't support XR_KHR_vulkan_enable2"
+    );
+
+    let mut enabled_extensions = xr::ExtensionSet::default();
+    enabled_extensions.khr_vulkan_enable2 = true;
+
+    let xr_instance = xr_entry
+        .create_instance(
+            &xr::ApplicationInfo {
+                application_name: "learnwgpu",
+                application_version: 0,
+                engine_name: "learnwgpu",
+                engine_version: 0,
+            },
+            &enabled_extensions,
+            &[],
+        )
+        .expect("Fail to create openxr instance");
+
+    let instance_props = xr_instance.properties().unwrap();
+    info!(
+        "openxr runtime: {} {:?}",
+        instance_props.runtime_name, instance_props.runtime_version
+    );
+
+    let system = xr_instance
+        .system(xr::FormFactor::HEAD_MOUNTED_DISPLAY)
+        .expect("No head mounted display found");
+
+    let blend_mode = xr_instance
+        .enumerate_environment_blend_modes(system, VIEW_TYPE)
+        .unwrap()[0];
+
+    // wgpu-hal wants vulkan 1.1, make sure the runtime is fine with that
+    let vk_target_version = vk::make_api_version(0, 1, 1, 0);
+    let xr_target_version = xr::Version::new(1, 1, 0);
+    let requirements = xr_instance
+        .graphics_requirements::<xr::Vulkan>(system)
+        .unwrap();
+    assert!(
+        requirements.min_api_version_supported <= xr_target_version
+            && xr_target_version <= requirements.max_api_version_supported,
+        "openxr runtime doesn't support vulkan 1.1"
+    );
+
+    let vk_entry = unsafe { ash::Entry::load() }.expect("Fail to load vulkan");
+    let flags = wgpu_hal::InstanceFlags::empty();
+    let instance_extensions = <V as Api>::Instance::required_extensions(&vk_entry, flags)
+        .expect("Fail to query vulkan instance extensions");
+    let instance_extension_ptrs: Vec<_> = instance_extensions.iter().map(|e| e.as_ptr()).collect();
+
+    let app_name = CString::new("learnwgpu").unwrap();
+    let vk_app_info = vk::ApplicationInfo::builder()
+        .application_name(&app_name)
+        .engine_name(&app_name)
+        .api_version(vk_target_version);
+    let vk_instance_info = vk::InstanceCreateInfo::builder()
+        .application_info(&vk_app_info)
+        .enabled_extension_names(&instance_extension_ptrs);
+
+    let vk_instance = unsafe {
+        let raw = xr_instance
+            .create_vulkan_instance(
+                system,
+                std::mem::transmute::<
+                    vk::PFN_vkGetInstanceProcAddr,
+                    xr::sys::platform::VkGetInstanceProcAddr,
+                >(vk_entry.static_fn().get_instance_proc_addr),
+                &vk_instance_info as *const _ as *const _,
+            )
+            .expect("Fail to create vulkan instance")
+            .map_err(vk::Result::from_raw)
+            .expect("Vulkan refused to create the instance");
+        ash::Instance::load(vk_entry.static_fn(), vk::Instance::from_raw(raw as _))
+    };
+
+    // the runtime decides which gpu drives the headset
+    let vk_physical_device = vk::PhysicalDevice::from_raw(unsafe {
+        xr_instance
+            .vulkan_graphics_device(system, vk_instance.handle().as_raw() as _)
+            .unwrap() as _
+    });
+    let vk_device_properties =
+        unsafe { vk_instance.get_physical_device_properties(vk_physical_device) };
+    assert!(
+        vk_device_properties.api_version >= vk_target_version,
+        "headset gpu doesn't support vulkan 1.1"
+    );
+
+    let queue_family_index =
+        unsafe { vk_instance.get_physical_device_queue_family_properties(vk_physical_device) }
+            .iter()
+            .position(|family| family.queue_flags.contains(vk::QueueFlags::GRAPHICS))
+            .expect("No graphics queue on the headset gpu") as u32;
+
+    let hal_instance = unsafe {
+        <V as Api>::Instance::from_raw(
+            vk_entry.clone(),
+            vk_instance.clone(),
+            vk_target_version,
+            0,
+            instance_extensions,
+            flags,
+            false,
+            // openxr owns the vulkan instance, keep it alive until wgpu is done
+            Some(Box::new(xr_instance.clone())),
+        )
+    }
+    .expect("Fail to wrap vulkan instance");
+
+    let hal_adapter = hal_instance
+        .expose_adapter(vk_physical_device)
+        .expect("Fail to wrap vulkan physical device");
+
+    let features = wgpu::Features::empty();
+    let limits = hal_adapter.capabilities.limits.clone();
+    let device_extensions = hal_adapter.adapter.required_device_extensions(features);
+    let device_extension_ptrs: Vec<_> = device_extensions.iter().map(|e| e.as_ptr()).collect();
+    let uab_types =
+        wgpu_hal::UpdateAfterBindTypes::from_limits(&limits, &vk_device_properties.limits);
+    let mut physical_device_features =
+        hal_adapter
+            .adapter
+            .physical_device_features(&device_extensions, features, uab_types);
+
+    let queue_priorities = [1.0];
+    let queue_infos = [vk::DeviceQueueCreateInfo::builder()
+        .queue_family_index(queue_family_index)
+        .queue_priorities(&queue_priorities)
+        .build()];
+    let vk_device_info = physical_device_features
+        .add_to_device_create_builder(
+            vk::DeviceCreateInfo::builder()
+                .queue_create_infos(&queue_infos)
+                .enabled_extension_names(&device_extension_ptrs),
+        )
+        .build();
+
+    let vk_device = unsafe {
+        let raw = xr_instance
+            .create_vulkan_device(
+                system,
+                std::mem::transmute::<
+                    vk::PFN_vkGetInstanceProcAddr,
+                    xr::sys::platform::VkGetInstanceProcAddr,
+                >(vk_entry.static_fn().get_instance_proc_addr),
+                vk_physical_device.as_raw() as _,
+                &vk_device_info as *const _ as *const _,
+            )
+            .expect("Fail to create vulkan device")
+            .map_err(vk::Result::from_raw)
+            .expect("Vulkan refused to create the device");
+        ash::Device::load(vk_instance.fp_v1_0(), vk::Device::from_raw(raw as _))
+    };
+
+    let hal_device = unsafe {
+        hal_adapter.adapter.device_from_raw(
+            vk_device.clone(),
+            true,
+            &device_extensions,
+            features,
+            uab_types,
+            queue_family_index,
+            0,
+        )
+    }
+    .expect("Fail to wrap vulkan device");
+
+    let instance = unsafe { wgpu::Instance::from_hal::<V>(hal_instance) };
+    let adapter = unsafe { instance.create_adapter_from_hal(hal_adapter) };
+    let (device, queue) = unsafe {
+        adapter.create_device_from_hal(
+            hal_device,
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features,
+                limits,
+            },
+            None,
+        )
+    }
+    .expect("Fail to create device");
+
+    let (session, frame_waiter, frame_stream) = unsafe {
+        xr_instance.create_session::<xr::Vulkan>(
+            system,
+            &xr::vulkan::SessionCreateInfo {
+                instance: vk_instance.handle().as_raw() as _,
+                physical_device: vk_physical_device.as_raw() as _,
+                device: vk_device.handle().as_raw() as _,
+                queue_family_index,
+                queue_index: 0,
+            },
+        )
+    }
+    .expect("Fail to create openxr session");
+
+    XrContext {
+        xr_instance,
+        system,
+        blend_mode,
+        session,
+        frame_waiter,
+        frame_stream,
+        device,
+        queue,
+    }
+}
+
+struct XrSwapchain {
+    handle: xr::Swapchain<xr::Vulkan>,
+    width: u32,
+    height: u32,
+    // one entry per swapchain image, each holding a 2d view per eye layer
+    eye_views: Vec<[wgpu::TextureView; VIEW_COUNT as usize]>,
+    depth_view: wgpu::TextureView,
+}
+
+// a single swapchain with one array layer per eye
+fn create_swapchain(ctx: &XrContext) -> XrSwapchain {
+    let views = ctx
+        .xr_instance
+        .enumerate_view_configuration_views(ctx.system, VIEW_TYPE)
+        .unwrap();
+    assert_eq!(views.len(), VIEW_COUNT as usize);
+
+    let width = views[0].recommended_image_rect_width;
+    let height = views[0].recommended_image_rect_height;
+
+    let handle = ctx
+        .session
+        .create_swapchain(&xr::SwapchainCreateInfo {
+            create_flags: xr::SwapchainCreateFlags::EMPTY,
+            usage_flags: xr::SwapchainUsageFlags::COLOR_ATTACHMENT
+                | xr::SwapchainUsageFlags::SAMPLED,
+            format: VK_COLOR_FORMAT.as_raw() as _,
+            sample_count: 1,
+            width,
+            height,
+            face_count: 1,
+            array_size: VIEW_COUNT,
+            mip_count: 1,
+        })
+        .expect("Fail to create openxr swapchain");
+
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: VIEW_COUNT,
+    };
+
+    let eye_views = handle
+        .enumerate_images()
+        .unwrap()
+        .into_iter()
+        .map(|image| {
+            let texture = unsafe {
+                let hal_texture = <V as Api>::Device::texture_from_raw(
+                    vk::Image::from_raw(image),
+                    &wgpu_hal::TextureDescriptor {
+                        label: Some("xr swapchain image"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: COLOR_FORMAT,
+                        usage: wgpu_hal::TextureUses::COLOR_TARGET
+                            | wgpu_hal::TextureUses::COPY_DST,
+                        memory_flags: wgpu_hal::MemoryFlags::empty(),
+                    },
+                    // the runtime owns the image
+                    None,
+                );
+                ctx.device.create_texture_from_hal::<V>(
+                    hal_texture,
+                    &wgpu::TextureDescriptor {
+                        label: Some("xr swapchain image"),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: COLOR_FORMAT,
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                            | wgpu::TextureUsages::COPY_DST,
+                    },
+                )
+            };
+
+            [0, 1].map(|eye| {
+                texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("xr eye view"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: eye,
+                    array_layer_count: std::num::NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+        })
+        .collect();
+
+    let depth_view = create_depth_view(&ctx.device, width, height);
+
+    XrSwapchain {
+        handle,
+        width,
+        height,
+        eye_views,
+        depth_view,
+    }
+}
+
+fn create_camera_bindgroup(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    label: &str,
+) -> (wgpu::Buffer, wgpu::BindGroup) {
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: bytemuck::cast_slice(Mat4::IDENTITY.as_ref()),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+
+    let bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+
+    (buffer, bindgroup)
+}
+
+pub fn run() {
+    let mut ctx = create_context();
+    let device = &ctx.device;
+
+    let camera_bindgroup_layout =
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("camera bindgroup layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(64),
+                },
+                count: None,
+            }],
+        });
+
+    let eye_cameras = [
+        create_camera_bindgroup(device, &camera_bindgroup_layout, "Left Eye Camera"),
+        create_camera_bindgroup(device, &camera_bindgroup_layout, "Right Eye Camera"),
+    ];
+
+    let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&camera_bindgroup_layout],
+        push_constant_ranges: &[],
+    });
+
+    let render_pipeline = create_scene_pipeline(device, &pipeline_layout, &shader, COLOR_FORMAT);
+
+    let scene = Scene::new(device);
+
+    let stage = ctx
+        .session
+        .create_reference_space(xr::ReferenceSpaceType::STAGE, xr::Posef::IDENTITY)
+        .expect("Fail to create stage space");
+
+    let mut swapchain: Option<XrSwapchain> = None;
+    let mut session_running = false;
+    let mut event_storage = xr::EventDataBuffer::new();
+
+    'main_loop: loop {
+        while let Some(event) = ctx.xr_instance.poll_event(&mut event_storage).unwrap() {
+            match event {
+                xr::Event::SessionStateChanged(e) => {
+                    info!("session state: {:?}", e.state());
+                    match e.state() {
+                        xr::SessionState::READY => {
+                            ctx.session.begin(VIEW_TYPE).unwrap();
+                            session_running = true;
+                        }
+                        xr::SessionState::STOPPING => {
+                            ctx.session.end().unwrap();
+                            session_running = false;
+                        }
+                        xr::SessionState::EXITING | xr::SessionState::LOSS_PENDING => {
+                            break 'main_loop;
+                        }
+                        _ => {}
+                    }
+                }
+                xr::Event::InstanceLossPending(_) => break 'main_loop,
+                xr::Event::EventsLost(e) => warn!("lost {} openxr events", e.lost_event_count()),
+                _ => {}
+            }
+        }
+
+        if !session_running {
+            // nothing to render until the runtime says we're visible
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            continue;
+        }
+
+        // blocks until the runtime wants the next frame, this is our vsync
+        let frame_state = ctx.frame_waiter.wait().unwrap();
+        ctx.frame_stream.begin().unwrap();
+
+        if !frame_state.should_render {
+            ctx.frame_stream
+                .end(frame_state.predicted_display_time, ctx.blend_mode, &[])
+                .unwrap();
+            continue;
+        }
+
+        let swapchain = swapchain.get_or_insert_with(|| create_swapchain(&ctx));
+
+        let image_index = swapchain.handle.acquire_image().unwrap() as usize;
+        swapchain.handle.wait_image(xr::Duration::INFINITE).unwrap();
+
+        // head tracking, sampled for the time the frame will be displayed
+        let (_, views) = ctx
+            .session
+            .locate_views(VIEW_TYPE, frame_state.predicted_display_time, &stage)
+            .unwrap();
+
+        for (view, (buffer, _)) in views.iter().zip(&eye_cameras) {
+            let view_proj = eye_projection(view.fov) * eye_view(view.pose);
+            ctx.queue
+                .write_buffer(buffer, 0, bytemuck::cast_slice(view_proj.as_ref()));
+        }
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        for (eye_view, (_, camera_bindgroup)) in
+            swapchain.eye_views[image_index].iter().zip(&eye_cameras)
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Eye Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: eye_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(CLEAR_COLOR),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &swapchain.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            scene.draw(&mut rpass, &render_pipeline, camera_bindgroup);
+        }
+
+        ctx.queue.submit(Some(encoder.finish()));
+        swapchain.handle.release_image().unwrap();
+
+        let rect = xr::Rect2Di {
+            offset: xr::Offset2Di { x: 0, y: 0 },
+            extent: xr::Extent2Di {
+                width: swapchain.width as _,
+                height: swapchain.height as _,
+            },
+        };
+        let projection_views: Vec<_> = views
+            .iter()
+            .enumerate()
+            .map(|(eye, view)| {
+                xr::CompositionLayerProjectionView::new()
+                    .pose(view.pose)
+                    .fov(view.fov)
+                    .sub_image(
+                        xr::SwapchainSubImage::new()
+                            .swapchain(&swapchain.handle)
+                            .image_array_index(eye as u32)
+                            .image_rect(rect),
+                    )
+            })
+            .collect();
+
+        ctx.frame_stream
+            .end(
+                frame_state.predicted_display_time,
+                ctx.blend_mode,
+                &[&xr::CompositionLayerProjection::new()
+                    .space(&stage)
+                    .views(&projection_views)],
+            )
+            .unwrap();
+    }
+
+    info!("exit");
+}
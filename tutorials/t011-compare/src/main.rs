@@ -0,0 +1,605 @@
+use bytemuck::{Pod, Zeroable};
+use glam::{Mat4, Vec3};
+use tracing::{info, Level};
+use wgpu::{include_wgsl, Backends, Instance};
+
+use wgpu::util::DeviceExt;
+
+// renders one reference scene with every renderer level of the series and
+// writes them side by side into comparison.png, no window needed
+
+fn main() {
+    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+
+    pollster::block_on(run());
+}
+
+const TILE_WIDTH: u32 = 384; // 384 * 4 bytes keeps readback rows 256 aligned
+const TILE_HEIGHT: u32 = 256;
+const SHADOW_SIZE: u32 = 1024;
+
+const COLOR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+const OUTPUT_PATH: &str = "comparison.png";
+
+// in render order, the index is what the shader sees as `globals.level`
+const LEVELS: [&str; 5] = ["unlit", "textured", "lit", "shadowed", "pbr"];
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    tex_coord: [f32; 2],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3], tex_coord: [f32; 2]) -> Vertex {
+    Vertex {
+        pos,
+        normal,
+        tex_coord,
+    }
+}
+
+// unit cube, 4 vertices per face so every face gets its own normal and uvs
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+        // normal, u axis, v axis
+        ([0., 0., 1.], [1., 0., 0.], [0., 1., 0.]),
+        ([0., 0., -1.], [-1., 0., 0.], [0., 1., 0.]),
+        ([1., 0., 0.], [0., 0., -1.], [0., 1., 0.]),
+        ([-1., 0., 0.], [0., 0., 1.], [0., 1., 0.]),
+        ([0., 1., 0.], [1., 0., 0.], [0., 0., -1.]),
+        ([0., -1., 0.], [1., 0., 0.], [0., 0., 1.]),
+    ];
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for (normal, u, v) in faces {
+        let (n, u, v) = (Vec3::from(normal), Vec3::from(u), Vec3::from(v));
+        let base = vertices.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = (n + u * su + v * sv) * 0.5;
+            let tex_coord = [(su + 1.) * 0.5, (1. - sv) * 0.5];
+            vertices.push(vertex(pos.to_array(), normal, tex_coord));
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    (vertices, indices)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct InstanceRaw {
+    model: [[f32; 4]; 4],
+    color: [f32; 3],
+    material: [f32; 2], // metallic, roughness
+}
+
+impl InstanceRaw {
+    fn new(model: Mat4, color: [f32; 3], metallic: f32, roughness: f32) -> InstanceRaw {
+        InstanceRaw {
+            model: model.to_cols_array_2d(),
+            color,
+            material: [metallic, roughness],
+        }
+    }
+
+    fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        const ATTRIBUTES: [wgpu::VertexAttribute; 6] = wgpu::vertex_attr_array![
+            3=>Float32x4, 4=>Float32x4, 5=>Float32x4, 6=>Float32x4, // model mat4
+            7=>Float32x3, // color
+            8=>Float32x2, // metallic, roughness
+        ];
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &ATTRIBUTES,
+        }
+    }
+}
+
+// the reference scene: a ground plane, a dielectric and a metal box, and a
+// tall pillar throwing a shadow across both
+fn create_instances() -> Vec<InstanceRaw> {
+    vec![
+        InstanceRaw::new(
+            Mat4::from_scale_rotation_translation(
+                Vec3::new(10., 0.1, 10.),
+                glam::Quat::IDENTITY,
+                Vec3::new(0., -0.05, 0.),
+            ),
+            [0.8, 0.8, 0.8],
+            0.,
+            0.9,
+        ),
+        InstanceRaw::new(
+            Mat4::from_rotation_translation(
+                glam::Quat::from_rotation_y(0.6),
+                Vec3::new(-1., 0.5, 0.),
+            ),
+            [0.9, 0.2, 0.2],
+            0.,
+            0.4,
+        ),
+        InstanceRaw::new(
+            Mat4::from_rotation_translation(
+                glam::Quat::from_rotation_y(-0.3),
+                Vec3::new(1.2, 0.5, 0.3),
+            ),
+            [0.95, 0.75, 0.3],
+            1.,
+            0.3,
+        ),
+        InstanceRaw::new(
+            Mat4::from_scale_rotation_translation(
+                Vec3::new(0.4, 3., 0.4),
+                glam::Quat::IDENTITY,
+                Vec3::new(0.2, 1.5, -2.),
+            ),
+            [0.2, 0.4, 0.9],
+            0.,
+            0.6,
+        ),
+    ]
+}
+
+// cpu generated checkerboard, keeps the harness free of asset files
+fn create_checker_rgba(size: u32) -> Vec<u8> {
+    (0..size * size)
+        .flat_map(|i| {
+            let (x, y) = (i % size, i / size);
+            if (x / 8 + y / 8) % 2 == 0 {
+                [255, 255, 255, 255]
+            } else {
+                [160, 160, 160, 255]
+            }
+        })
+        .collect()
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Globals {
+    view_proj: [[f32; 4]; 4],
+    light_view_proj: [[f32; 4]; 4],
+    camera_pos: [f32; 4],
+    light_dir: [f32; 4],
+    level: u32,
+    _padding: [u32; 3],
+}
+
+fn create_globals(level: u32) -> Globals {
+    let camera_pos = Vec3::new(0., 3., 6.);
+    let view = Mat4::look_at_rh(camera_pos, Vec3::new(0., 0.5, 0.), Vec3::Y);
+    let projection = Mat4::perspective_rh(
+        45.0_f32.to_radians(),
+        TILE_WIDTH as f32 / TILE_HEIGHT as f32,
+        0.1,
+        40.,
+    );
+
+    let light_dir = Vec3::new(-0.5, -1., -0.6).normalize();
+    let light_view = Mat4::look_at_rh(-light_dir * 10., Vec3::ZERO, Vec3::Y);
+    let light_projection = Mat4::orthographic_rh(-6., 6., -6., 6., 0.1, 30.);
+
+    Globals {
+        view_proj: (projection * view).to_cols_array_2d(),
+        light_view_proj: (light_projection * light_view).to_cols_array_2d(),
+        camera_pos: camera_pos.extend(1.).to_array(),
+        light_dir: light_dir.extend(0.).to_array(),
+        level,
+        _padding: [0; 3],
+    }
+}
+
+async fn run() {
+    let instance = Instance::new(Backends::all());
+    let adapter = instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        })
+        .await
+        .expect("Failed to find an appropriate adapter");
+
+    let (device, queue) = adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::default(),
+            },
+            None,
+        )
+        .await
+        .expect("Fail to create device");
+
+    info!("rendering with {:?}", adapter.get_info().name);
+
+    // globals, group 0
+    let globals_bindgroup_layout =
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("globals bindgroup layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<Globals>() as wgpu::BufferAddress
+                    ),
+                },
+                count: None,
+            }],
+        });
+
+    let globals_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Globals Buffer"),
+        contents: bytemuck::bytes_of(&create_globals(0)),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+
+    let globals_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("globals bind group"),
+        layout: &globals_bindgroup_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: globals_buffer.as_entire_binding(),
+        }],
+    });
+
+    // diffuse texture
+    let checker_size = 64;
+    let texture_size = wgpu::Extent3d {
+        width: checker_size,
+        height: checker_size,
+        depth_or_array_layers: 1,
+    };
+    let diffuse_texture = device.create_texture_with_data(
+        &queue,
+        &wgpu::TextureDescriptor {
+            label: Some("diffuse_texture"),
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        },
+        &create_checker_rgba(checker_size),
+    );
+    let diffuse_texture_view = diffuse_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let diffuse_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("texture sampler"),
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Nearest,
+        min_filter: wgpu::FilterMode::Linear,
+        ..Default::default()
+    });
+
+    // shadow map
+    let shadow_texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("shadow_texture"),
+        size: wgpu::Extent3d {
+            width: SHADOW_SIZE,
+            height: SHADOW_SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    });
+    let shadow_view = shadow_texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let shadow_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("shadow sampler"),
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        compare: Some(wgpu::CompareFunction::LessEqual),
+        ..Default::default()
+    });
+
+    // material + shadow, group 1
+    let material_bindgroup_layout =
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("material bindgroup layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+            ],
+        });
+
+    let material_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("material bind group"),
+        layout: &material_bindgroup_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&diffuse_texture_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&diffuse_sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&shadow_view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::Sampler(&shadow_sampler),
+            },
+        ],
+    });
+
+    // shader
+    let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+    let vertex_buffer_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3, 2=>Float32x2],
+    };
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[&globals_bindgroup_layout, &material_bindgroup_layout],
+        push_constant_ranges: &[],
+    });
+
+    let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("scene pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[vertex_buffer_layout.clone(), InstanceRaw::buffer_layout()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(COLOR_FORMAT.into())],
+        }),
+        primitive: wgpu::PrimitiveState {
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    });
+
+    // the shadow pass only needs the globals, the shadow map can't be bound
+    // while it's the depth attachment
+    let shadow_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("shadow pipeline layout"),
+        bind_group_layouts: &[&globals_bindgroup_layout],
+        push_constant_ranges: &[],
+    });
+
+    let shadow_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("shadow pipeline"),
+        layout: Some(&shadow_pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_shadow",
+            buffers: &[vertex_buffer_layout, InstanceRaw::buffer_layout()],
+        },
+        fragment: None,
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    });
+
+    // geometry
+    let (vertices, indices) = create_vertices();
+    let instances = create_instances();
+
+    let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Vertices Buffer"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+
+    let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Indices Buffer"),
+        contents: bytemuck::cast_slice(&indices),
+        usage: wgpu::BufferUsages::INDEX,
+    });
+
+    let instances_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Instances Buffer"),
+        contents: bytemuck::cast_slice(&instances),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+
+    // offscreen targets, one tile at a time
+    let tile_size = wgpu::Extent3d {
+        width: TILE_WIDTH,
+        height: TILE_HEIGHT,
+        depth_or_array_layers: 1,
+    };
+    let color_texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("tile color texture"),
+        size: tile_size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: COLOR_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    });
+    let color_view = color_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let depth_texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("tile depth texture"),
+        size: tile_size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+    let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let readback_buf = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Readback Buffer"),
+        size: (TILE_WIDTH * TILE_HEIGHT * 4) as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut grid = image::RgbaImage::new(TILE_WIDTH * LEVELS.len() as u32, TILE_HEIGHT);
+
+    for (level, name) in LEVELS.iter().enumerate() {
+        queue.write_buffer(
+            &globals_buffer,
+            0,
+            bytemuck::bytes_of(&create_globals(level as u32)),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut spass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &shadow_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            spass.set_pipeline(&shadow_pipeline);
+            spass.set_bind_group(0, &globals_bindgroup, &[]);
+            spass.set_vertex_buffer(0, vertices_buf.slice(..));
+            spass.set_vertex_buffer(1, instances_buf.slice(..));
+            spass.set_index_buffer(indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            spass.draw_indexed(0..indices.len() as u32, 0, 0..instances.len() as u32);
+        }
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &color_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.3,
+                            a: 1.0,
+                        }),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            rpass.set_pipeline(&render_pipeline);
+            rpass.set_bind_group(0, &globals_bindgroup, &[]);
+            rpass.set_bind_group(1, &material_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, vertices_buf.slice(..));
+            rpass.set_vertex_buffer(1, instances_buf.slice(..));
+            rpass.set_index_buffer(indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..indices.len() as u32, 0, 0..instances.len() as u32);
+        }
+
+        encoder.copy_texture_to_buffer(
+            color_texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback_buf,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(TILE_WIDTH * 4),
+                    rows_per_image: std::num::NonZeroU32::new(TILE_HEIGHT),
+                },
+            },
+            tile_size,
+        );
+
+        queue.submit(Some(encoder.finish()));
+
+        let slice = readback_buf.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Fail to map readback buffer")
+        });
+        device.poll(wgpu::Maintain::Wait);
+
+        {
+            let data = slice.get_mapped_range();
+            let tile = image::RgbaImage::from_raw(TILE_WIDTH, TILE_HEIGHT, data.to_vec())
+                .expect("Readback size doesn't match the tile");
+            image::imageops::replace(&mut grid, &tile, (level as u32 * TILE_WIDTH) as i64, 0);
+        }
+        readback_buf.unmap();
+
+        info!("rendered level {}: {}", level, name);
+    }
+
+    grid.save(OUTPUT_PATH)
+        .expect("Fail to write comparison image");
+    info!("saved {} ({})", OUTPUT_PATH, LEVELS.join(" | "));
+}
@@ -0,0 +1,178 @@
+// one shader for every renderer level, `globals.level` picks how far down
+// the tutorial series the shading goes:
+// 0 unlit, 1 textured, 2 lit, 3 shadowed, 4 pbr
+
+struct Globals {
+    view_proj: mat4x4<f32>,
+    light_view_proj: mat4x4<f32>,
+    camera_pos: vec4<f32>,
+    light_dir: vec4<f32>,
+    level: u32,
+};
+
+struct VertexInput {
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coord: vec2<f32>,
+};
+
+struct InstanceInput {
+    @location(3) mat0: vec4<f32>,
+    @location(4) mat1: vec4<f32>,
+    @location(5) mat2: vec4<f32>,
+    @location(6) mat3: vec4<f32>,
+    @location(7) color: vec3<f32>,
+    // metallic, roughness
+    @location(8) material: vec2<f32>,
+};
+
+struct FragInput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tex_coord: vec2<f32>,
+    @location(3) color: vec3<f32>,
+    @location(4) material: vec2<f32>,
+};
+
+@group(0)
+@binding(0)
+var<uniform> globals: Globals;
+
+@group(1)
+@binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1)
+@binding(1)
+var s_diffuse: sampler;
+@group(1)
+@binding(2)
+var t_shadow: texture_depth_2d;
+@group(1)
+@binding(3)
+var s_shadow: sampler_comparison;
+
+let PI: f32 = 3.14159265359;
+
+fn model_matrix(instance: InstanceInput) -> mat4x4<f32> {
+    return mat4x4<f32>(instance.mat0, instance.mat1, instance.mat2, instance.mat3);
+}
+
+@vertex
+fn vs_main(input: VertexInput, instance: InstanceInput) -> FragInput {
+    let model = model_matrix(instance);
+    let world_pos = model * vec4<f32>(input.pos, 1.0);
+
+    var out: FragInput;
+    out.clip_position = globals.view_proj * world_pos;
+    out.world_pos = world_pos.xyz;
+    out.normal = (model * vec4<f32>(input.normal, 0.0)).xyz;
+    out.tex_coord = input.tex_coord;
+    out.color = instance.color;
+    out.material = instance.material;
+    return out;
+}
+
+// depth only pass from the light
+@vertex
+fn vs_shadow(input: VertexInput, instance: InstanceInput) -> @builtin(position) vec4<f32> {
+    return globals.light_view_proj * model_matrix(instance) * vec4<f32>(input.pos, 1.0);
+}
+
+fn shadow_factor(world_pos: vec3<f32>, normal: vec3<f32>) -> f32 {
+    // push the lookup along the normal a little to avoid shadow acne
+    let pos = globals.light_view_proj * vec4<f32>(world_pos + normal * 0.02, 1.0);
+    let ndc = pos.xyz / pos.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5);
+
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0 || ndc.z > 1.0) {
+        return 1.0;
+    }
+
+    // 3x3 pcf
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_shadow));
+    var lit = 0.0;
+    for (var x = -1; x <= 1; x += 1) {
+        for (var y = -1; y <= 1; y += 1) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel;
+            lit += textureSampleCompareLevel(t_shadow, s_shadow, uv + offset, ndc.z - 0.002);
+        }
+    }
+    return lit / 9.0;
+}
+
+fn blinn_phong(albedo: vec3<f32>, n: vec3<f32>, l: vec3<f32>, v: vec3<f32>, shadow: f32) -> vec3<f32> {
+    let h = normalize(l + v);
+    let diffuse = max(dot(n, l), 0.0);
+    let specular = pow(max(dot(n, h), 0.0), 32.0) * 0.5;
+    return albedo * 0.15 + (albedo * diffuse + vec3<f32>(specular)) * shadow;
+}
+
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let gv = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let gl = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return gv * gl;
+}
+
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+fn cook_torrance(albedo: vec3<f32>, metallic: f32, roughness: f32, n: vec3<f32>, l: vec3<f32>, v: vec3<f32>, shadow: f32) -> vec3<f32> {
+    let h = normalize(l + v);
+    let n_dot_l = max(dot(n, l), 0.0);
+    let n_dot_v = max(dot(n, v), 0.0001);
+    let n_dot_h = max(dot(n, h), 0.0);
+
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+    let f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+    let d = distribution_ggx(n_dot_h, roughness);
+    let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+
+    let specular = d * g * f / (4.0 * n_dot_v * n_dot_l + 0.0001);
+    let kd = (vec3<f32>(1.0) - f) * (1.0 - metallic);
+    let radiance = vec3<f32>(3.0);
+
+    let ambient = albedo * 0.03;
+    return ambient + (kd * albedo / PI + specular) * radiance * n_dot_l * shadow;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let level = globals.level;
+
+    var albedo = input.color;
+    if (level == 0u) {
+        return vec4<f32>(albedo, 1.0);
+    }
+
+    albedo = albedo * textureSample(t_diffuse, s_diffuse, input.tex_coord).rgb;
+    if (level == 1u) {
+        return vec4<f32>(albedo, 1.0);
+    }
+
+    let n = normalize(input.normal);
+    let l = normalize(-globals.light_dir.xyz);
+    let v = normalize(globals.camera_pos.xyz - input.world_pos);
+
+    var shadow = 1.0;
+    if (level >= 3u) {
+        shadow = shadow_factor(input.world_pos, n);
+    }
+
+    if (level <= 3u) {
+        return vec4<f32>(blinn_phong(albedo, n, l, v, shadow), 1.0);
+    }
+
+    let color = cook_torrance(albedo, input.material.x, input.material.y, n, l, v, shadow);
+    // reinhard, pbr radiance easily goes above 1
+    return vec4<f32>(color / (color + vec3<f32>(1.0)), 1.0);
+}
@@ -0,0 +1,202 @@
+use bytemuck::{Pod, Zeroable};
+use tracing::info;
+use wgpu::{include_wgsl, PrimitiveTopology};
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = TOPOLOGIES[TOPOLOGIES.len() - 1].0;
+
+// number keys 1-5 pick a topology, space cycles through them
+const TOPOLOGIES: [(&str, PrimitiveTopology); 5] = [
+    ("point list", PrimitiveTopology::PointList),
+    ("line list", PrimitiveTopology::LineList),
+    ("line strip", PrimitiveTopology::LineStrip),
+    ("triangle strip", PrimitiveTopology::TriangleStrip),
+    ("triangle list", PrimitiveTopology::TriangleList),
+];
+
+// a zigzag band, upper and lower vertices alternate from left to right
+const VERTEX_COUNT: usize = 12;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, color }
+}
+
+// the same vertices feed every pipeline, only the topology tells the gpu how to connect them
+// - point list: every vertex is a point (always 1 pixel in wgpu, there is no point size)
+// - line list: vertices 0-1, 2-3, 4-5 ... are separate lines
+// - line strip: every vertex is connected to the previous one
+// - triangle list: vertices 0-1-2, 3-4-5 ... are separate triangles
+// - triangle strip: every vertex makes a triangle with the previous two
+fn create_vertices() -> Vec<Vertex> {
+    (0..VERTEX_COUNT)
+        .map(|i| {
+            let t = i as f32 / (VERTEX_COUNT - 1) as f32;
+            let x = -0.8 + 1.6 * t;
+            let y = if i % 2 == 0 { 0.4 } else { -0.4 };
+            vertex([x, y, 0.], [1. - t, 0.5, t])
+        })
+        .collect()
+}
+
+fn topology_index(key: VirtualKeyCode) -> Option<usize> {
+    match key {
+        VirtualKeyCode::Key1 => Some(0),
+        VirtualKeyCode::Key2 => Some(1),
+        VirtualKeyCode::Key3 => Some(2),
+        VirtualKeyCode::Key4 => Some(3),
+        VirtualKeyCode::Key5 => Some(4),
+        _ => None,
+    }
+}
+
+pub struct Topology {
+    pipelines: Vec<wgpu::RenderPipeline>,
+    vertices_buf: wgpu::Buffer,
+    vertex_count: u32,
+    current: usize,
+}
+
+impl framework::App for Topology {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+        };
+
+        // topology is baked into the pipeline, so switching it at runtime means one pipeline each
+        let pipelines = TOPOLOGIES
+            .iter()
+            .map(|(name, topology)| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(*name),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: "vs_main",
+                        buffers: std::slice::from_ref(&vertex_buffer_layout),
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: "fs_main",
+                        targets: &[Some(config.format.into())],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        topology: *topology,
+                        // only used by indexed strip draws, we draw without an index buffer
+                        strip_index_format: None,
+                        front_face: wgpu::FrontFace::Ccw,
+                        // a strip flips winding every triangle, keep both sides visible
+                        cull_mode: None,
+                        polygon_mode: wgpu::PolygonMode::Fill,
+                        unclipped_depth: false,
+                        conservative: false,
+                    },
+                    depth_stencil: None,
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                })
+            })
+            .collect();
+
+        let vertices = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        info!("press 1-5 or space to switch topology");
+
+        Topology {
+            pipelines,
+            vertices_buf,
+            vertex_count: vertices.len() as u32,
+            current: TOPOLOGIES.len() - 1,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        _config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            let next = match key {
+                VirtualKeyCode::Space => Some((self.current + 1) % TOPOLOGIES.len()),
+                key => topology_index(key),
+            };
+
+            if let Some(next) = next {
+                self.current = next;
+                info!("topology: {}", TOPOLOGIES[self.current].0);
+                window.set_title(TOPOLOGIES[self.current].0);
+            }
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(&self.pipelines[self.current]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.draw(0..self.vertex_count, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
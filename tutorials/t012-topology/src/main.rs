@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t12_topology::Topology>(t12_topology::TITLE);
+}
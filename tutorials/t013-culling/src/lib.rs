@@ -0,0 +1,322 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{hud::Hud, pipeline_stats::PipelineStats, texture::DepthTexture, watchdog};
+use glam::{Mat4, Quat, Vec3};
+use tracing::info;
+use wgpu::{include_wgsl, Face, FrontFace};
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "culling";
+
+// reading the statistics back stalls the gpu, so only every so often
+const STATS_INTERVAL: f32 = 1.;
+
+// C cycles the cull mode, F flips which winding counts as front
+const CULL_MODES: [(&str, Option<Face>); 3] = [
+    ("cull none", None),
+    ("cull front", Some(Face::Front)),
+    ("cull back", Some(Face::Back)),
+];
+
+const FRONT_FACES: [(&str, FrontFace); 2] = [("ccw", FrontFace::Ccw), ("cw", FrontFace::Cw)];
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, color }
+}
+
+// every face is wound counter clockwise when looked at from outside the cube,
+// (u, v) are picked so that u x v points along the face normal
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y, [1., 0., 0.]), // right, red
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y, [0., 1., 1.]), // left, cyan
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z, [0., 1., 0.]), // top, green
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z, [1., 0., 1.]), // bottom, magenta
+        (Vec3::Z, Vec3::X, Vec3::Y, [0., 0., 1.]),     // front, blue
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y, [1., 1., 0.]), // back, yellow
+    ];
+
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+
+    for (normal, u, v, color) in faces {
+        let base = vertices.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = (normal + u * su + v * sv) * 0.5;
+            vertices.push(vertex(pos.to_array(), color));
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    (vertices, indices)
+}
+
+fn pipeline_index(cull: usize, front: usize) -> usize {
+    cull * FRONT_FACES.len() + front
+}
+
+pub struct Culling {
+    pipelines: Vec<wgpu::RenderPipeline>,
+    mvp_buffer: wgpu::Buffer,
+    mvp_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    aspect: f32,
+    cull: usize,
+    front: usize,
+    // None when the adapter can't do pipeline statistics queries
+    stats: Option<PipelineStats>,
+    // real seconds since the stats were last read
+    since_stats: f32,
+    // the counts from the last read, or why there are none
+    counts: String,
+    // the cull mode, the front face and the counts
+    hud: Hud,
+}
+
+impl framework::App for Culling {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn optional_features() -> wgpu::Features {
+        wgpu::Features::PIPELINE_STATISTICS_QUERY
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // mvp
+        let mvp_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("mvp bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64),
+                    },
+                    count: None,
+                }],
+            });
+
+        let mvp_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("MVP Buffer"),
+            contents: bytemuck::cast_slice(Mat4::IDENTITY.as_ref()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let mvp_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("mvp bind group"),
+            layout: &mvp_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: mvp_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&mvp_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+        };
+
+        // cull mode and front face are pipeline state, so build every combination up front
+        let mut pipelines = Vec::with_capacity(CULL_MODES.len() * FRONT_FACES.len());
+        for (_, cull_mode) in CULL_MODES {
+            for (_, front_face) in FRONT_FACES {
+                pipelines.push(
+                    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: None,
+                        layout: Some(&pipeline_layout),
+                        vertex: wgpu::VertexState {
+                            module: &shader,
+                            entry_point: "vs_main",
+                            buffers: std::slice::from_ref(&vertex_buffer_layout),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader,
+                            entry_point: "fs_main",
+                            targets: &[Some(config.format.into())],
+                        }),
+                        primitive: wgpu::PrimitiveState {
+                            front_face,
+                            cull_mode,
+                            ..Default::default()
+                        },
+                        // with culling off the far faces are drawn too, depth test keeps them behind
+                        depth_stencil: Some(wgpu::DepthStencilState {
+                            format: DepthTexture::FORMAT,
+                            depth_write_enabled: true,
+                            depth_compare: wgpu::CompareFunction::Less,
+                            stencil: wgpu::StencilState::default(),
+                            bias: wgpu::DepthBiasState::default(),
+                        }),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                    }),
+                );
+            }
+        }
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!("press C to cycle cull mode, F to flip front face");
+
+        let stats = PipelineStats::new(device);
+        let counts = if stats.is_some() {
+            "counting...".to_string()
+        } else {
+            "no pipeline statistics queries here".to_string()
+        };
+
+        Culling {
+            pipelines,
+            mvp_buffer,
+            mvp_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            aspect: config.width as f32 / config.height as f32,
+            cull: CULL_MODES.len() - 1,
+            front: 0,
+            stats,
+            since_stats: 0.,
+            counts,
+            hud: Hud::new(),
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.aspect = config.width as f32 / config.height as f32;
+    }
+
+    fn update(&mut self, _window: &Window, event: WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::C => self.cull = (self.cull + 1) % CULL_MODES.len(),
+                VirtualKeyCode::F => self.front = (self.front + 1) % FRONT_FACES.len(),
+                _ => {}
+            }
+        }
+    }
+
+    fn hud(&mut self) -> Option<&mut Hud> {
+        Some(&mut self.hud)
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let time = frame.time;
+        let model = Mat4::from_quat(Quat::from_euler(
+            glam::EulerRot::YXZ,
+            time * 0.7,
+            time * 0.4,
+            0.,
+        ));
+        let camera = Mat4::look_at_rh(Vec3::new(0., 0., 3.), Vec3::ZERO, Vec3::Y);
+        let projection = Mat4::perspective_rh(45.0_f32.to_radians(), self.aspect, 0.1, 40.);
+        let mvp = projection * camera * model;
+        watchdog::write_buffer(
+            queue,
+            &self.mvp_buffer,
+            0,
+            bytemuck::cast_slice(mvp.as_ref()),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            if let Some(stats) = &self.stats {
+                stats.begin(&mut rpass);
+            }
+
+            rpass.set_pipeline(&self.pipelines[pipeline_index(self.cull, self.front)]);
+            rpass.set_bind_group(0, &self.mvp_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1);
+
+            if let Some(stats) = &self.stats {
+                stats.end(&mut rpass);
+            }
+        }
+
+        self.since_stats += frame.real_delta;
+        let read_stats = self.since_stats >= STATS_INTERVAL;
+        if let (Some(stats), true) = (&self.stats, read_stats) {
+            stats.resolve(&mut encoder);
+        }
+
+        queue.submit(Some(encoder.finish()));
+
+        // culling shows up as fewer primitives out of the clipper and fewer fragment invocations
+        if let (Some(stats), true) = (&self.stats, read_stats) {
+            let statistics = stats.read(device);
+            self.counts = format!(
+                "{} vertex invocations\n{} primitives\n{} fragment invocations",
+                statistics.vertex_invocations,
+                statistics.clipper_primitives,
+                statistics.fragment_invocations
+            );
+            self.since_stats = 0.;
+        }
+        self.hud.set(&format!(
+            "{}, front face {}\n{}",
+            CULL_MODES[self.cull].0, FRONT_FACES[self.front].0, self.counts
+        ));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t13_culling::Culling>(t13_culling::TITLE);
+}
@@ -0,0 +1,260 @@
+use bytemuck::{Pod, Zeroable};
+use tracing::info;
+use wgpu::{include_wgsl, CompareFunction};
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "compare less, depth write on, clear depth 1";
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// number keys 1-4 pick the compare function, W toggles depth write, Z cycles the clear depth
+const COMPARE_FUNCTIONS: [(&str, CompareFunction); 4] = [
+    ("less", CompareFunction::Less),
+    ("greater", CompareFunction::Greater),
+    ("always", CompareFunction::Always),
+    ("equal", CompareFunction::Equal),
+];
+
+// greater needs a 0 clear to pass anything, equal only lets the 0.5 quad through a 0.5 clear
+const CLEAR_DEPTHS: [f32; 3] = [1., 0.5, 0.];
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, color }
+}
+
+// three overlapping quads, positions are already in clip space so z is the depth value.
+// they are drawn near to far on purpose, so without a working depth test the far one ends on top
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let quads = [
+        ([-0.6, 0.3], 0.25, [1., 0.2, 0.2]), // near, red
+        ([-0.2, 0.], 0.5, [0.2, 1., 0.2]),   // middle, green
+        ([0.2, -0.3], 0.75, [0.2, 0.2, 1.]), // far, blue
+    ];
+
+    let mut vertices = Vec::with_capacity(12);
+    let mut indices = Vec::with_capacity(18);
+
+    for ([x, y], z, color) in quads {
+        let base = vertices.len() as u16;
+        vertices.push(vertex([x - 0.4, y - 0.4, z], color));
+        vertices.push(vertex([x + 0.4, y - 0.4, z], color));
+        vertices.push(vertex([x + 0.4, y + 0.4, z], color));
+        vertices.push(vertex([x - 0.4, y + 0.4, z], color));
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    (vertices, indices)
+}
+
+fn create_depth_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("depth_texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn compare_index(key: VirtualKeyCode) -> Option<usize> {
+    match key {
+        VirtualKeyCode::Key1 => Some(0),
+        VirtualKeyCode::Key2 => Some(1),
+        VirtualKeyCode::Key3 => Some(2),
+        VirtualKeyCode::Key4 => Some(3),
+        _ => None,
+    }
+}
+
+fn pipeline_index(compare: usize, depth_write: bool) -> usize {
+    compare * 2 + depth_write as usize
+}
+
+pub struct DepthCompare {
+    pipelines: Vec<wgpu::RenderPipeline>,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    depth_view: wgpu::TextureView,
+    compare: usize,
+    depth_write: bool,
+    clear: usize,
+}
+
+impl framework::App for DepthCompare {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+        };
+
+        // depth stencil state is part of the pipeline, one variant per compare function and write flag
+        let mut pipelines = Vec::with_capacity(COMPARE_FUNCTIONS.len() * 2);
+        for (_, depth_compare) in COMPARE_FUNCTIONS {
+            for depth_write_enabled in [false, true] {
+                pipelines.push(
+                    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                        label: None,
+                        layout: Some(&pipeline_layout),
+                        vertex: wgpu::VertexState {
+                            module: &shader,
+                            entry_point: "vs_main",
+                            buffers: std::slice::from_ref(&vertex_buffer_layout),
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader,
+                            entry_point: "fs_main",
+                            targets: &[Some(config.format.into())],
+                        }),
+                        primitive: wgpu::PrimitiveState::default(),
+                        depth_stencil: Some(wgpu::DepthStencilState {
+                            format: DEPTH_FORMAT,
+                            depth_write_enabled,
+                            depth_compare,
+                            stencil: wgpu::StencilState::default(),
+                            bias: wgpu::DepthBiasState::default(),
+                        }),
+                        multisample: wgpu::MultisampleState::default(),
+                        multiview: None,
+                    }),
+                );
+            }
+        }
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!("press 1-4 to pick the compare function, W to toggle depth write, Z to cycle clear depth");
+
+        DepthCompare {
+            pipelines,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            depth_view: create_depth_view(device, config.width, config.height),
+            compare: 0,
+            depth_write: true,
+            clear: 0,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        // the depth texture has to match the color target size
+        self.depth_view = create_depth_view(device, config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::W => self.depth_write = !self.depth_write,
+                VirtualKeyCode::Z => self.clear = (self.clear + 1) % CLEAR_DEPTHS.len(),
+                key => match compare_index(key) {
+                    Some(index) => self.compare = index,
+                    None => return,
+                },
+            }
+
+            let title = format!(
+                "compare {}, depth write {}, clear depth {}",
+                COMPARE_FUNCTIONS[self.compare].0,
+                if self.depth_write { "on" } else { "off" },
+                CLEAR_DEPTHS[self.clear]
+            );
+            info!("{}", title);
+            window.set_title(&title);
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(CLEAR_DEPTHS[self.clear]),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            rpass.set_pipeline(&self.pipelines[pipeline_index(self.compare, self.depth_write)]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1)
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
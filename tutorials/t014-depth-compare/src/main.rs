@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t14_depth_compare::DepthCompare>(t14_depth_compare::TITLE);
+}
@@ -0,0 +1,283 @@
+use bytemuck::{Pod, Zeroable};
+use framework::watchdog;
+use glam::{Mat4, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "depth test on";
+
+// 32 bit float depth, no stencil. every render target size change needs a new depth texture
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, color }
+}
+
+// two quads crossing each other like an X seen from above, each one is partly in front of
+// the other, so no draw order can get it right, only a per pixel depth test can
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let red = [0.9, 0.2, 0.2];
+    let blue = [0.2, 0.4, 0.9];
+
+    let vertices = vec![
+        // red, going from back left to front right
+        vertex([-0.8, -0.5, -0.6], red),
+        vertex([0.8, -0.5, 0.6], red),
+        vertex([0.8, 0.5, 0.6], red),
+        vertex([-0.8, 0.5, -0.6], red),
+        // blue, going from front left to back right
+        vertex([-0.8, -0.5, 0.6], blue),
+        vertex([0.8, -0.5, -0.6], blue),
+        vertex([0.8, 0.5, -0.6], blue),
+        vertex([-0.8, 0.5, 0.6], blue),
+    ];
+
+    let indices = vec![
+        0, 1, 2, 0, 2, 3, // red
+        4, 5, 6, 4, 6, 7, // blue
+    ];
+
+    (vertices, indices)
+}
+
+fn create_depth_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("depth_texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+pub struct Depth {
+    // [depth test off, depth test on]
+    pipelines: [wgpu::RenderPipeline; 2],
+    mvp_buffer: wgpu::Buffer,
+    mvp_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    depth_view: wgpu::TextureView,
+    aspect: f32,
+    depth_test: bool,
+}
+
+impl framework::App for Depth {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // mvp
+        let mvp_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("mvp bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64),
+                    },
+                    count: None,
+                }],
+            });
+
+        let mvp_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("MVP Buffer"),
+            contents: bytemuck::cast_slice(Mat4::IDENTITY.as_ref()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let mvp_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("mvp bind group"),
+            layout: &mvp_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: mvp_buffer.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&mvp_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+        };
+
+        let create_pipeline = |depth_test: bool| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: std::slice::from_ref(&vertex_buffer_layout),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(config.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                // the render pass must have a depth attachment of the same format.
+                // keep a fragment only if it is closer than what is stored, then store its depth
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: depth_test,
+                    depth_compare: if depth_test {
+                        wgpu::CompareFunction::Less
+                    } else {
+                        wgpu::CompareFunction::Always
+                    },
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let pipelines = [create_pipeline(false), create_pipeline(true)];
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!("press D to toggle the depth test");
+
+        Depth {
+            pipelines,
+            mvp_buffer,
+            mvp_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            depth_view: create_depth_view(device, config.width, config.height),
+            aspect: config.width as f32 / config.height as f32,
+            depth_test: true,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        // depth attachment and color attachment must be the same size
+        self.depth_view = create_depth_view(device, config.width, config.height);
+        self.aspect = config.width as f32 / config.height as f32;
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::D),
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.depth_test = !self.depth_test;
+            let title = if self.depth_test {
+                "depth test on"
+            } else {
+                "depth test off"
+            };
+            info!("{}", title);
+            window.set_title(title);
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        // swing the camera around so both quads get their turn in front
+        let time = frame.time;
+        let model = Mat4::from_rotation_y(time.sin() * 0.6);
+        let camera = Mat4::look_at_rh(Vec3::new(0., 0.8, 3.), Vec3::ZERO, Vec3::Y);
+        let projection = Mat4::perspective_rh(45.0_f32.to_radians(), self.aspect, 0.1, 40.);
+        let mvp = projection * camera * model;
+        watchdog::write_buffer(
+            queue,
+            &self.mvp_buffer,
+            0,
+            bytemuck::cast_slice(mvp.as_ref()),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                // clear to the far plane every frame, 1.0 is as far as it gets
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            rpass.set_pipeline(&self.pipelines[self.depth_test as usize]);
+            rpass.set_bind_group(0, &self.mvp_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1)
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
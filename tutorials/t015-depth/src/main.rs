@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t15_depth::Depth>(t15_depth::TITLE);
+}
@@ -0,0 +1,223 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{pixel_art::PixelArtTarget, watchdog};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "pixel art on";
+
+// 16:9, small enough that every texel is obvious
+const PIXEL_WIDTH: u32 = 160;
+const PIXEL_HEIGHT: u32 = 90;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, color }
+}
+
+// a striped ground and a spinning triangle, thin diagonal edges show the stair steps best
+fn create_vertices(time: f32) -> Vec<Vertex> {
+    let mut vertices = Vec::new();
+
+    for i in 0..4 {
+        let top = -0.4 - i as f32 * 0.15;
+        let bottom = top - 0.15;
+        let shade = if i % 2 == 0 { 0.35 } else { 0.25 };
+        let color = [shade * 0.6, shade, shade * 0.4];
+        vertices.extend_from_slice(&[
+            vertex([-1., bottom, 0.], color),
+            vertex([1., bottom, 0.], color),
+            vertex([1., top, 0.], color),
+            vertex([-1., bottom, 0.], color),
+            vertex([1., top, 0.], color),
+            vertex([-1., top, 0.], color),
+        ]);
+    }
+
+    let colors = [[1., 0.3, 0.3], [0.3, 1., 0.3], [0.3, 0.3, 1.]];
+    for (i, color) in colors.into_iter().enumerate() {
+        let angle = time + i as f32 * std::f32::consts::TAU / 3.;
+        // squash x so the triangle looks round on a 16:9 target
+        vertices.push(vertex(
+            [angle.cos() * 0.5 * 9. / 16., 0.1 + angle.sin() * 0.5, 0.],
+            color,
+        ));
+    }
+
+    vertices
+}
+
+pub struct PixelArt {
+    target: PixelArtTarget,
+    // [into the pixel art target, straight into the window]
+    pipelines: [wgpu::RenderPipeline; 2],
+    vertices_buf: wgpu::Buffer,
+    vertex_count: u32,
+    width: u32,
+    height: u32,
+    pixel_art: bool,
+}
+
+impl framework::App for PixelArt {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        let target = PixelArtTarget::new(device, PIXEL_WIDTH, PIXEL_HEIGHT, config.format);
+
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+        };
+
+        // the target and the window may have different formats, so one pipeline each
+        let create_pipeline = |format: wgpu::TextureFormat| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: None,
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: std::slice::from_ref(&vertex_buffer_layout),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(format.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let pipelines = [
+            create_pipeline(target.format),
+            create_pipeline(config.format),
+        ];
+
+        let vertices = create_vertices(0.);
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        info!("press P to toggle pixel art mode");
+
+        PixelArt {
+            target,
+            pipelines,
+            vertices_buf,
+            vertex_count: vertices.len() as u32,
+            width: config.width,
+            height: config.height,
+            pixel_art: true,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        // the pixel art target keeps its size, only the scale and the bars change
+        self.width = config.width;
+        self.height = config.height;
+        let (_, _, width, height) = self.target.letterbox(self.width, self.height);
+        info!("pixel art scaled to {}x{}", width, height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::P),
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.pixel_art = !self.pixel_art;
+            window.set_title(if self.pixel_art {
+                "pixel art on"
+            } else {
+                "pixel art off"
+            });
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let vertices = create_vertices(frame.time);
+        watchdog::write_buffer(
+            queue,
+            &self.vertices_buf,
+            0,
+            bytemuck::cast_slice(&vertices),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let scene_view = if self.pixel_art {
+                self.target.view()
+            } else {
+                frame.view
+            };
+
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: scene_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.15,
+                            b: 0.3,
+                            a: 1.,
+                        }),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(&self.pipelines[!self.pixel_art as usize]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.draw(0..self.vertex_count, 0..1);
+        }
+
+        if self.pixel_art {
+            self.target
+                .blit(&mut encoder, frame.view, self.width, self.height);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t16_pixel_art::PixelArt>(t16_pixel_art::TITLE);
+}
@@ -0,0 +1,455 @@
+use std::time::SystemTime;
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    assets::AssetSource, hud::Hud, render_scale::ScaledTarget, texture::Texture, watchdog,
+};
+use glam::{Mat4, Quat, Vec3};
+use tracing::{info, warn};
+use wgpu::include_wgsl;
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "cube";
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+const RENDER_SCALE_STEP: f32 = 0.25;
+const TEXTURE: &str = "happy-tree.png";
+// frame time is averaged over this many seconds before the hud shows it
+const FRAME_TIME_INTERVAL: f32 = 1.;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coord: [f32; 2],
+}
+
+fn vertex(pos: [f32; 3], tex_coord: [f32; 2]) -> Vertex {
+    Vertex { pos, tex_coord }
+}
+
+// 8 corners aren't enough once every face wants its own uvs: a corner shared by 3 faces
+// needs 3 different tex coords, so each face gets its own 4 vertices, 24 in total.
+// faces are wound counter clockwise seen from outside, (u, v) are picked so u x v is the normal
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+
+    // the whole texture on every face, tex coord v points down
+    let corners = [
+        (-1., -1., [0., 1.]), // left bottom
+        (1., -1., [1., 1.]),  // right bottom
+        (1., 1., [1., 0.]),   // top right
+        (-1., 1., [0., 0.]),  // top left
+    ];
+
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+
+    for (normal, u, v) in faces {
+        let base = vertices.len() as u16;
+        for (su, sv, tex_coord) in corners {
+            let pos = (normal + u * su + v * sv) * 0.5;
+            vertices.push(vertex(pos.to_array(), tex_coord));
+        }
+        // two triangles per face
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    (vertices, indices)
+}
+
+fn create_depth_view(device: &wgpu::Device, width: u32, height: u32) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("depth_texture"),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+    });
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+fn create_diffuse_bindgroup(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture: &Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("diffuse_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            },
+        ],
+    })
+}
+
+pub struct Cube {
+    pipeline: wgpu::RenderPipeline,
+    diffuse_bindgroup: wgpu::BindGroup,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    assets: AssetSource,
+    // last change of the texture file, only known when the assets come from disk
+    texture_modified: Option<SystemTime>,
+    mvp_buffer: wgpu::Buffer,
+    mvp_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    target: ScaledTarget,
+    depth_view: wgpu::TextureView,
+    aspect: f32,
+    frame_count: u32,
+    // real seconds the last `frame_count` frames took
+    frame_time: f32,
+    // set by a key press, applied in render where the device is at hand
+    pending_scale: Option<f32>,
+    // milliseconds a frame took over the last interval, `None` until the first one ends
+    average_frame_time: Option<f32>,
+    // the average frame time, the render scale and the filter
+    hud: Hud,
+}
+
+impl framework::App for Cube {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // texture, LEARNWGPU_ASSETS=disk reads it from src/ and picks up edits while running
+        let assets = framework::assets!("src": "happy-tree.png");
+        let diffuse_bytes = assets.load(TEXTURE).expect("Fail to load happy-tree.png");
+        let diffuse_img =
+            image::load_from_memory(&diffuse_bytes).expect("Fail to decode happy-tree.png");
+        let diffuse = Texture::from_image(device, queue, &diffuse_img, "diffuse_texture");
+
+        let texture_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("texture_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let diffuse_bindgroup =
+            create_diffuse_bindgroup(device, &texture_bind_group_layout, &diffuse);
+
+        // mvp
+        let mvp_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("mvp bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64),
+                    },
+                    count: None,
+                }],
+            });
+
+        let mvp_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("MVP Buffer"),
+            contents: bytemuck::cast_slice(Mat4::IDENTITY.as_ref()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let mvp_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("mvp bind group"),
+            layout: &mvp_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: mvp_buffer.as_entire_binding(),
+            }],
+        });
+
+        // the scene renders offscreen at window size * render scale and gets stretched to the window
+        let target = ScaledTarget::new(device, config.width, config.height, 1., config.format);
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &texture_bind_group_layout, // group 0, texture
+                &mvp_bindgroup_layout,      // group 1, mvp
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x2],
+        };
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[vertex_buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(target.format.into())],
+            }),
+            // a closed cube never shows its inside, skip the back faces
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!("press - and = to change the render scale, S to toggle sharpening");
+
+        Cube {
+            pipeline,
+            diffuse_bindgroup,
+            texture_bind_group_layout,
+            texture_modified: assets.modified(TEXTURE),
+            assets,
+            mvp_buffer,
+            mvp_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            depth_view: create_depth_view(device, target.width, target.height),
+            target,
+            aspect: config.width as f32 / config.height as f32,
+            frame_count: 0,
+            frame_time: 0.,
+            pending_scale: None,
+            average_frame_time: None,
+            hud: Hud::new(),
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.target.resize(device, config.width, config.height);
+        // depth has to match the scene target, not the window
+        self.depth_view = create_depth_view(device, self.target.width, self.target.height);
+        self.aspect = config.width as f32 / config.height as f32;
+    }
+
+    fn update(&mut self, _window: &Window, event: WindowEvent) {
+        let key = match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => key,
+            _ => return,
+        };
+
+        let scale = self.pending_scale.unwrap_or(self.target.scale);
+        match key {
+            VirtualKeyCode::Minus => {
+                self.pending_scale = Some(
+                    (scale - RENDER_SCALE_STEP)
+                        .clamp(ScaledTarget::MIN_SCALE, ScaledTarget::MAX_SCALE),
+                )
+            }
+            VirtualKeyCode::Equals => {
+                self.pending_scale = Some(
+                    (scale + RENDER_SCALE_STEP)
+                        .clamp(ScaledTarget::MIN_SCALE, ScaledTarget::MAX_SCALE),
+                )
+            }
+            // sharpening only changes the blit, no need to wait for render
+            VirtualKeyCode::S => self.target.sharpen = !self.target.sharpen,
+            _ => {}
+        }
+    }
+
+    fn hud(&mut self) -> Option<&mut Hud> {
+        Some(&mut self.hud)
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if let Some(scale) = self.pending_scale.take() {
+            self.target.set_scale(device, scale);
+            self.depth_view = create_depth_view(device, self.target.width, self.target.height);
+        }
+
+        // average over an interval, a single frame's time jumps around too much to read
+        self.frame_count += 1;
+        self.frame_time += frame.real_delta;
+        let elapsed = self.frame_time;
+        if elapsed >= FRAME_TIME_INTERVAL {
+            self.average_frame_time = Some(elapsed * 1000. / self.frame_count as f32);
+            self.frame_count = 0;
+            self.frame_time = 0.;
+
+            // hot reload, checked along with the frame time so it isn't a file stat every frame
+            let modified = self.assets.modified(TEXTURE);
+            if modified != self.texture_modified {
+                self.texture_modified = modified;
+                // an editor may still be writing the file, keep the old texture until it decodes
+                let reloaded = self.assets.load(TEXTURE).map_err(|err| err.to_string());
+                match reloaded.and_then(|bytes| {
+                    image::load_from_memory(&bytes).map_err(|err| err.to_string())
+                }) {
+                    Ok(img) => {
+                        let diffuse = Texture::from_image(device, queue, &img, "diffuse_texture");
+                        self.diffuse_bindgroup = create_diffuse_bindgroup(
+                            device,
+                            &self.texture_bind_group_layout,
+                            &diffuse,
+                        );
+                        info!("reloaded {}", TEXTURE);
+                    }
+                    Err(err) => warn!("Fail to reload {}: {}", TEXTURE, err),
+                }
+            }
+        }
+        // every frame, a key press shows up before the next average does
+        let frame_time = match self.average_frame_time {
+            Some(ms) => format!("frame time {:.2}ms", ms),
+            None => "frame time ...".to_string(),
+        };
+        self.hud.set(&format!(
+            "{}\nrender scale {:.2}, {}x{}\n{}",
+            frame_time,
+            self.target.scale,
+            self.target.width,
+            self.target.height,
+            if self.target.sharpen {
+                "sharpened"
+            } else {
+                "bilinear"
+            }
+        ));
+
+        let time = frame.time;
+        let model = Mat4::from_quat(Quat::from_axis_angle(
+            Vec3::new(0.5, 1., 0.).normalize(),
+            time,
+        ));
+        let camera = Mat4::look_at_rh(Vec3::new(0., 0., 3.), Vec3::ZERO, Vec3::Y);
+        let projection = Mat4::perspective_rh(45.0_f32.to_radians(), self.aspect, 0.1, 40.);
+        // projection * view * model * local
+        let mvp = projection * camera * model;
+        watchdog::write_buffer(
+            queue,
+            &self.mvp_buffer,
+            0,
+            bytemuck::cast_slice(mvp.as_ref()),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: self.target.view(),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.diffuse_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.mvp_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1)
+        }
+
+        self.target.blit(&mut encoder, frame.view);
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
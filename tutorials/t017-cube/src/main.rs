@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t17_cube::Cube>(t17_cube::TITLE);
+}
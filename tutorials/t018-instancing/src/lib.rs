@@ -0,0 +1,374 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraBinding, CameraController, OrbitCameraController},
+    overdraw::Overdraw,
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::{Mat4, Quat, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "instancing";
+
+// GRID_SIZE * GRID_SIZE cubes, 10,000 of them
+const GRID_SIZE: u32 = 100;
+const GRID_SPACING: f32 = 1.5;
+const CAMERA_SENSITIVITY: f32 = 0.005;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3]) -> Vertex {
+    Vertex { pos, normal }
+}
+
+// per instance data, read once per cube instead of once per vertex
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CubeInstance {
+    model: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+impl CubeInstance {
+    // a mat4 doesn't fit one attribute, it goes in as 4 columns.
+    // locations continue after the vertex buffer's 0 and 1
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        2=>Float32x4, 3=>Float32x4, 4=>Float32x4, 5=>Float32x4, 6=>Float32x4
+    ];
+
+    fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<CubeInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// each face gets its own 4 vertices so it can carry its own normal, 24 in total.
+// faces are wound counter clockwise seen from outside, (u, v) are picked so u x v is the normal
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+    let corners = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)];
+
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+
+    for (normal, u, v) in faces {
+        let base = vertices.len() as u16;
+        for (su, sv) in corners {
+            let pos = (normal + u * su + v * sv) * 0.5;
+            vertices.push(vertex(pos.to_array(), normal.to_array()));
+        }
+        // two triangles per face
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    (vertices, indices)
+}
+
+// a wave runs across the grid, every cube bobs and spins a little out of step with its neighbours
+fn update_instances(instances: &mut [CubeInstance], time: f32) {
+    let half = (GRID_SIZE - 1) as f32 * GRID_SPACING * 0.5;
+
+    for (i, instance) in instances.iter_mut().enumerate() {
+        let x = (i as u32 % GRID_SIZE) as f32;
+        let z = (i as u32 / GRID_SIZE) as f32;
+
+        let phase = (x + z) * 0.2;
+        let translation = Vec3::new(
+            x * GRID_SPACING - half,
+            (time * 2. + phase).sin() * 0.5,
+            z * GRID_SPACING - half,
+        );
+        let rotation = Quat::from_rotation_y(time + phase);
+
+        instance.model = Mat4::from_rotation_translation(rotation, translation).to_cols_array_2d();
+        instance.color = [x / GRID_SIZE as f32, 0.5, z / GRID_SIZE as f32, 1.];
+    }
+}
+
+pub struct Instancing {
+    pipeline: wgpu::RenderPipeline,
+    // same geometry, counts fragments instead of shading them
+    overdraw_pipeline: wgpu::RenderPipeline,
+    overdraw: Overdraw,
+    show_overdraw: bool,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_binding: CameraBinding,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    // cpu side copy, rewritten in place every frame so nothing gets reallocated
+    instances: Vec<CubeInstance>,
+    instances_buf: wgpu::Buffer,
+    animate: bool,
+}
+
+impl framework::App for Instancing {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.6,
+            config.width as f32 / config.height as f32,
+        );
+        // the grid is ~150 units across, the default far plane would cut it off
+        camera.zfar = 500.;
+        let mut camera_controller =
+            OrbitCameraController::new(Vec3::ZERO, 120., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+        let camera_binding = CameraBinding::new(device, &camera);
+
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_binding.layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+        };
+
+        // slot 0 per vertex, slot 1 per instance
+        let vertex_buffer_layouts = [vertex_buffer_layout, CubeInstance::buffer_layout()];
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &vertex_buffer_layouts,
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let overdraw = Overdraw::new(device, config.width, config.height, config.format);
+        // no depth test, a fragment hidden behind another one still costs
+        let overdraw_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("overdraw count"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &vertex_buffer_layouts,
+            },
+            fragment: Some(overdraw.fragment_state()),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // all instances go into one buffer once, later frames only overwrite its contents
+        let mut instances = vec![CubeInstance::zeroed(); (GRID_SIZE * GRID_SIZE) as usize];
+        update_instances(&mut instances, 0.);
+        let instances_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instances Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        info!(
+            "{} cubes in one draw call, press space to pause the animation, O to show overdraw",
+            instances.len()
+        );
+
+        Instancing {
+            pipeline,
+            overdraw_pipeline,
+            overdraw,
+            show_overdraw: false,
+            camera,
+            camera_controller,
+            camera_binding,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            instances,
+            instances_buf,
+            animate: true,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.overdraw.resize(device, config.width, config.height);
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::Space => self.animate = !self.animate,
+                VirtualKeyCode::O => self.show_overdraw = !self.show_overdraw,
+                _ => return,
+            }
+
+            let mut title = String::from("instancing");
+            if !self.animate {
+                title.push_str(", paused");
+            }
+            if self.show_overdraw {
+                title.push_str(", overdraw");
+            }
+            window.set_title(&title);
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        self.camera_binding.update(queue, &self.camera);
+
+        // same buffer, new contents, no buffer is created after init
+        if self.animate {
+            update_instances(&mut self.instances, frame.time);
+            watchdog::write_buffer(
+                queue,
+                &self.instances_buf,
+                0,
+                bytemuck::cast_slice(&self.instances),
+            );
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = if self.show_overdraw {
+                // every fragment adds 1, so start from 0 and skip depth
+                encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("overdraw count"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: self.overdraw.view(),
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: true,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                })
+            } else {
+                frame.begin_render_pass(
+                    &mut encoder,
+                    wgpu::Color {
+                        r: 0.1,
+                        g: 0.15,
+                        b: 0.3,
+                        a: 1.,
+                    },
+                )
+            };
+
+            rpass.set_pipeline(if self.show_overdraw {
+                &self.overdraw_pipeline
+            } else {
+                &self.pipeline
+            });
+            rpass.set_bind_group(0, &self.camera_binding.bind_group, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_vertex_buffer(1, self.instances_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            // one draw, the instance range picks how many cubes come out of it
+            rpass.draw_indexed(0..self.index_count, 0, 0..self.instances.len() as u32)
+        }
+
+        if self.show_overdraw {
+            self.overdraw.resolve(&mut encoder, frame.view);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t18_instancing::Instancing>(t18_instancing::TITLE);
+}
@@ -0,0 +1,48 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+// one per cube, stepped per instance instead of per vertex
+struct InstanceInput {
+    @location(2) model0: vec4<f32>,
+    @location(3) model1: vec4<f32>,
+    @location(4) model2: vec4<f32>,
+    @location(5) model3: vec4<f32>,
+    @location(6) color: vec4<f32>,
+};
+
+struct FragInput {
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> view_proj: mat4x4<f32>;
+
+// fixed light from the upper right, enough shading to tell the faces apart
+let light_dir = vec3<f32>(0.4, 0.8, 0.45);
+
+@vertex
+fn vs_main(input: VertexInput, instance: InstanceInput) -> FragInput {
+    let model = mat4x4<f32>(
+        instance.model0,
+        instance.model1,
+        instance.model2,
+        instance.model3,
+    );
+
+    var fragInput : FragInput;
+    fragInput.clip_position = view_proj * model * vec4<f32>(input.pos, 1.0);
+    // the models only rotate and scale uniformly, so the model matrix works for normals too
+    fragInput.normal = (model * vec4<f32>(input.normal, 0.0)).xyz;
+    fragInput.color = instance.color.rgb;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let diffuse = max(dot(normalize(input.normal), normalize(light_dir)), 0.0);
+    return vec4<f32>(input.color * (0.3 + 0.7 * diffuse), 1.0);
+}
@@ -0,0 +1,539 @@
+use bytemuck::{Pod, Zeroable};
+use framework::watchdog;
+use glam::{Mat4, Quat, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "checkerboard on, history clamp on";
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+const SCENE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, color }
+}
+
+// matches `Frame` in both shaders, padded to 16 bytes for the uniform
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct FrameUniform {
+    parity: u32,
+    checkerboard: u32,
+    clamp_history: u32,
+    _padding: u32,
+}
+
+// every face is wound counter clockwise when looked at from outside the cube,
+// (u, v) are picked so that u x v points along the face normal
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y, [1., 0., 0.]), // right, red
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y, [0., 1., 1.]), // left, cyan
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z, [0., 1., 0.]), // top, green
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z, [1., 0., 1.]), // bottom, magenta
+        (Vec3::Z, Vec3::X, Vec3::Y, [0., 0., 1.]),     // front, blue
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y, [1., 1., 0.]), // back, yellow
+    ];
+
+    let mut vertices = Vec::with_capacity(24);
+    let mut indices = Vec::with_capacity(36);
+
+    for (normal, u, v, color) in faces {
+        let base = vertices.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = (normal + u * su + v * sv) * 0.5;
+            vertices.push(vertex(pos.to_array(), color));
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    (vertices, indices)
+}
+
+fn create_texture_view(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    usage: wgpu::TextureUsages,
+    label: &str,
+) -> wgpu::TextureView {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage,
+    });
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+// everything sized to the window, rebuilt on resize
+struct Targets {
+    scene_view: wgpu::TextureView,
+    depth_view: wgpu::TextureView,
+    // ping pong, a frame reads one and writes the other
+    history_views: [wgpu::TextureView; 2],
+    // resolve_bindgroups[i] reads history_views[1 - i]
+    resolve_bindgroups: [wgpu::BindGroup; 2],
+}
+
+impl Targets {
+    fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        resolve_layout: &wgpu::BindGroupLayout,
+        frame_buffer: &wgpu::Buffer,
+    ) -> Targets {
+        let sampled = wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING;
+        let scene_view = create_texture_view(
+            device,
+            width,
+            height,
+            SCENE_FORMAT,
+            sampled,
+            "scene_texture",
+        );
+        let depth_view = create_texture_view(
+            device,
+            width,
+            height,
+            DEPTH_FORMAT,
+            wgpu::TextureUsages::RENDER_ATTACHMENT,
+            "depth_texture",
+        );
+        let history_views = [0, 1].map(|_| {
+            create_texture_view(
+                device,
+                width,
+                height,
+                SCENE_FORMAT,
+                sampled,
+                "history_texture",
+            )
+        });
+
+        let resolve_bindgroups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("resolve bind group"),
+                layout: resolve_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&scene_view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&history_views[1 - i]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: frame_buffer.as_entire_binding(),
+                    },
+                ],
+            })
+        });
+
+        Targets {
+            scene_view,
+            depth_view,
+            history_views,
+            resolve_bindgroups,
+        }
+    }
+}
+
+pub struct Checkerboard {
+    scene_pipeline: wgpu::RenderPipeline,
+    resolve_pipeline: wgpu::RenderPipeline,
+    resolve_layout: wgpu::BindGroupLayout,
+    mvp_buffer: wgpu::Buffer,
+    mvp_bindgroup: wgpu::BindGroup,
+    frame_buffer: wgpu::Buffer,
+    frame_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    targets: Targets,
+    aspect: f32,
+    frame_index: u32,
+    checkerboard: bool,
+    clamp_history: bool,
+}
+
+impl framework::App for Checkerboard {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // mvp
+        let mvp_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("mvp bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(64),
+                    },
+                    count: None,
+                }],
+            });
+
+        let mvp_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("MVP Buffer"),
+            contents: bytemuck::cast_slice(Mat4::IDENTITY.as_ref()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let mvp_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("mvp bind group"),
+            layout: &mvp_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: mvp_buffer.as_entire_binding(),
+            }],
+        });
+
+        // frame, read by the scene pass to pick its half and by the resolve pass to fill the other
+        let frame_uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<FrameUniform>() as u64),
+            },
+            count: None,
+        };
+
+        let frame_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Frame Buffer"),
+            size: std::mem::size_of::<FrameUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let frame_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("frame bindgroup layout"),
+                entries: &[frame_uniform_entry(0)],
+            });
+
+        let frame_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("frame bind group"),
+            layout: &frame_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: frame_buffer.as_entire_binding(),
+            }],
+        });
+
+        // resolve, textureLoad only, no sampler
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        let resolve_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("resolve bindgroup layout"),
+            entries: &[texture_entry(0), texture_entry(1), frame_uniform_entry(2)],
+        });
+
+        // scene pipeline
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let scene_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &mvp_bindgroup_layout,   // group 0, mvp
+                    &frame_bindgroup_layout, // group 1, frame
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+        };
+
+        let scene_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("scene"),
+            layout: Some(&scene_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[vertex_buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(SCENE_FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // resolve pipeline, writes the window and the next history in one go
+        let resolve_shader = device.create_shader_module(include_wgsl!("resolve.wgsl"));
+
+        let resolve_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&resolve_layout],
+                push_constant_ranges: &[],
+            });
+
+        let resolve_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("resolve"),
+            layout: Some(&resolve_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &resolve_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &resolve_shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into()), Some(SCENE_FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let targets = Targets::new(
+            device,
+            config.width,
+            config.height,
+            &resolve_layout,
+            &frame_buffer,
+        );
+
+        info!("press C to toggle checkerboard rendering, H to toggle the history clamp");
+
+        Checkerboard {
+            scene_pipeline,
+            resolve_pipeline,
+            resolve_layout,
+            mvp_buffer,
+            mvp_bindgroup,
+            frame_buffer,
+            frame_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            targets,
+            aspect: config.width as f32 / config.height as f32,
+            frame_index: 0,
+            checkerboard: true,
+            clamp_history: true,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        // the history is thrown away with the old size, the first frame after fills it back in
+        self.targets = Targets::new(
+            device,
+            config.width,
+            config.height,
+            &self.resolve_layout,
+            &self.frame_buffer,
+        );
+        self.aspect = config.width as f32 / config.height as f32;
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        let key = match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => key,
+            _ => return,
+        };
+
+        match key {
+            VirtualKeyCode::C => self.checkerboard = !self.checkerboard,
+            VirtualKeyCode::H => self.clamp_history = !self.clamp_history,
+            _ => return,
+        }
+
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        window.set_title(&format!(
+            "checkerboard {}, history clamp {}",
+            on_off(self.checkerboard),
+            on_off(self.clamp_history)
+        ));
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let time = frame.time;
+        // fast sideways motion, that's where reusing last frame's pixels shows
+        let model = Mat4::from_rotation_translation(
+            Quat::from_axis_angle(Vec3::new(0.5, 1., 0.).normalize(), time),
+            Vec3::new((time * 1.5).sin() * 1.5, 0., 0.),
+        );
+        let camera = Mat4::look_at_rh(Vec3::new(0., 0., 4.), Vec3::ZERO, Vec3::Y);
+        let projection = Mat4::perspective_rh(45.0_f32.to_radians(), self.aspect, 0.1, 40.);
+        let mvp = projection * camera * model;
+        watchdog::write_buffer(
+            queue,
+            &self.mvp_buffer,
+            0,
+            bytemuck::cast_slice(mvp.as_ref()),
+        );
+
+        let frame_uniform = FrameUniform {
+            parity: self.frame_index & 1,
+            checkerboard: self.checkerboard as u32,
+            clamp_history: self.clamp_history as u32,
+            _padding: 0,
+        };
+        watchdog::write_buffer(
+            queue,
+            &self.frame_buffer,
+            0,
+            bytemuck::bytes_of(&frame_uniform),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // scene, only this frame's half of the quads
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("scene"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.targets.scene_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // alpha 0 marks background for the resolve pass
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.targets.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            rpass.set_pipeline(&self.scene_pipeline);
+            rpass.set_bind_group(0, &self.mvp_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.frame_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1)
+        }
+
+        // resolve, the other half comes from history
+        {
+            let current = (self.frame_index & 1) as usize;
+
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("resolve"),
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: frame.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: true,
+                        },
+                    }),
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: &self.targets.history_views[current],
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: true,
+                        },
+                    }),
+                ],
+                depth_stencil_attachment: None,
+            });
+
+            rpass.set_pipeline(&self.resolve_pipeline);
+            rpass.set_bind_group(0, &self.targets.resolve_bindgroups[current], &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+
+        self.frame_index = self.frame_index.wrapping_add(1);
+    }
+}
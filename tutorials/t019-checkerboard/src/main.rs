@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t19_checkerboard::Checkerboard>(t19_checkerboard::TITLE);
+}
@@ -0,0 +1,80 @@
+// fills in the half of the pixels that wasn't rendered this frame from the previous frame,
+// writes the full image to the window and keeps a copy as history for the next frame
+
+struct FragInput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct FragOutput {
+    @location(0) surface: vec4<f32>,
+    @location(1) history: vec4<f32>,
+};
+
+struct Frame {
+    parity: u32,
+    checkerboard: u32,
+    clamp_history: u32,
+};
+
+@group(0) @binding(0)
+var t_scene: texture_2d<f32>;
+@group(0) @binding(1)
+var t_history: texture_2d<f32>;
+@group(0) @binding(2)
+var<uniform> frame: Frame;
+
+// the clear color, in linear like wgpu::Color
+let background = vec3<f32>(0.1, 0.15, 0.3);
+
+fn rendered_this_frame(pixel: vec2<u32>) -> bool {
+    let quad = pixel / 2u;
+    return frame.checkerboard == 0u || ((quad.x + quad.y + frame.parity) & 1u) == 0u;
+}
+
+fn scene_color(pixel: vec2<i32>) -> vec3<f32> {
+    let size = vec2<i32>(textureDimensions(t_scene));
+    let color = textureLoad(t_scene, clamp(pixel, vec2<i32>(0), size - 1), 0);
+    // the scene target is cleared to alpha 0, nothing drawn there means background
+    return mix(background, color.rgb, color.a);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // one triangle over the whole screen, same as the framework's blit
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> FragOutput {
+    let pixel = vec2<i32>(input.clip_position.xy);
+
+    var color: vec3<f32>;
+    if (rendered_this_frame(vec2<u32>(pixel))) {
+        color = scene_color(pixel);
+    } else {
+        color = textureLoad(t_history, pixel, 0).rgb;
+
+        if (frame.clamp_history != 0u) {
+            // the 4 neighbouring quads were all rendered this frame, history that falls outside
+            // their range is stale, clamping it keeps moving edges from leaving a trail
+            let left = scene_color(pixel + vec2<i32>(-2, 0));
+            let right = scene_color(pixel + vec2<i32>(2, 0));
+            let up = scene_color(pixel + vec2<i32>(0, -2));
+            let down = scene_color(pixel + vec2<i32>(0, 2));
+            color = clamp(
+                color,
+                min(min(left, right), min(up, down)),
+                max(max(left, right), max(up, down)),
+            );
+        }
+    }
+
+    var output: FragOutput;
+    output.surface = vec4<f32>(color, 1.0);
+    output.history = vec4<f32>(color, 1.0);
+    return output;
+}
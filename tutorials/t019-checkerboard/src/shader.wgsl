@@ -0,0 +1,45 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) color:vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Frame {
+    parity: u32,
+    checkerboard: u32,
+    clamp_history: u32,
+};
+
+// projection * view * model
+@group(0) @binding(0)
+var<uniform> mvp: mat4x4<f32>;
+
+@group(1) @binding(0)
+var<uniform> frame: Frame;
+
+// 2x2 pixel quads, every frame takes the other color of the checkerboard
+fn rendered_this_frame(pixel: vec2<u32>) -> bool {
+    let quad = pixel / 2u;
+    return frame.checkerboard == 0u || ((quad.x + quad.y + frame.parity) & 1u) == 0u;
+}
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = mvp * vec4<f32>(input.pos, 1.0);
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    // a real implementation would skip the work in hardware, discard only shows which half is drawn
+    if (!rendered_this_frame(vec2<u32>(input.clip_position.xy))) {
+        discard;
+    }
+    return vec4<f32>(input.color, 1.0);
+}
@@ -0,0 +1,411 @@
+use std::{
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    assets::ColorSpace,
+    camera::{Camera, CameraBinding, CameraController, OrbitCameraController},
+    texture::{DepthTexture, Texture, TextureCache},
+};
+use glam::Vec3;
+use tracing::{info, warn};
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "model";
+
+const DEFAULT_MODEL: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/res/scene.obj");
+const CAMERA_SENSITIVITY: f32 = 0.005;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coord: [f32; 2],
+    normal: [f32; 3],
+}
+
+// one per mesh in the obj, a mesh uses a single material
+struct Mesh {
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    material: usize,
+}
+
+struct Material {
+    bind_group: wgpu::BindGroup,
+}
+
+fn model_path() -> PathBuf {
+    match std::env::args().nth(1) {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(DEFAULT_MODEL),
+    }
+}
+
+// map_Kd is resolved relative to the obj, materials without one get a 1x1 white texture
+// so the shader can always multiply texture by Kd. materials sharing a map share its texture
+fn load_materials(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    dir: &Path,
+    materials: &[tobj::Material],
+) -> Vec<Material> {
+    let white = Rc::new(Texture::from_image(
+        device,
+        queue,
+        &image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(
+            1,
+            1,
+            image::Rgba([255, 255, 255, 255]),
+        )),
+        "white",
+    ));
+    let mut textures = TextureCache::new();
+
+    let materials = materials
+        .iter()
+        .map(|material| {
+            let texture = if material.diffuse_texture.is_empty() {
+                white.clone()
+            } else {
+                let path = dir.join(&material.diffuse_texture);
+                textures
+                    // diffuse maps are color
+                    .load(device, queue, &path, ColorSpace::Srgb)
+                    .unwrap_or_else(|e| panic!("Fail to load {}: {}", path.display(), e))
+            };
+
+            let [r, g, b] = material.diffuse;
+            let diffuse_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Material Buffer"),
+                contents: bytemuck::cast_slice(&[r, g, b, 1.]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(material.name.as_str()),
+                layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: diffuse_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+
+            Material { bind_group }
+        })
+        .collect::<Vec<_>>();
+    info!(
+        "{} materials, {} textures uploaded",
+        materials.len(),
+        textures.len()
+    );
+    materials
+}
+
+fn load_meshes(device: &wgpu::Device, models: &[tobj::Model]) -> Vec<Mesh> {
+    models
+        .iter()
+        .map(|model| {
+            let mesh = &model.mesh;
+            if mesh.normals.is_empty() {
+                warn!("{} has no normals, lighting will be flat", model.name);
+            }
+
+            let vertices: Vec<Vertex> = (0..mesh.positions.len() / 3)
+                .map(|i| Vertex {
+                    pos: [
+                        mesh.positions[i * 3],
+                        mesh.positions[i * 3 + 1],
+                        mesh.positions[i * 3 + 2],
+                    ],
+                    // obj puts v = 0 at the bottom of the image, wgpu at the top
+                    tex_coord: if mesh.texcoords.is_empty() {
+                        [0., 0.]
+                    } else {
+                        [mesh.texcoords[i * 2], 1. - mesh.texcoords[i * 2 + 1]]
+                    },
+                    normal: if mesh.normals.is_empty() {
+                        [0., 1., 0.]
+                    } else {
+                        [
+                            mesh.normals[i * 3],
+                            mesh.normals[i * 3 + 1],
+                            mesh.normals[i * 3 + 2],
+                        ]
+                    },
+                })
+                .collect();
+
+            let vertices_label = format!("{} Vertices Buffer", model.name);
+            let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&vertices_label),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+            let indices_label = format!("{} Indices Buffer", model.name);
+            let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(&indices_label),
+                contents: bytemuck::cast_slice(&mesh.indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+
+            Mesh {
+                vertices_buf,
+                indices_buf,
+                index_count: mesh.indices.len() as u32,
+                material: mesh.material_id.unwrap_or(0),
+            }
+        })
+        .collect()
+}
+
+pub struct ModelViewer {
+    pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_binding: CameraBinding,
+    meshes: Vec<Mesh>,
+    materials: Vec<Material>,
+}
+
+impl framework::App for ModelViewer {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // obj
+        let path = model_path();
+        // single_index gives every vertex one index shared by position, uv and normal,
+        // which is what a vertex buffer wants. faces with more than 3 corners get split
+        let (models, materials) = tobj::load_obj(
+            &path,
+            &tobj::LoadOptions {
+                single_index: true,
+                triangulate: true,
+                ..Default::default()
+            },
+        )
+        .unwrap_or_else(|e| panic!("Fail to load {}: {}", path.display(), e));
+
+        let mut materials = materials.unwrap_or_else(|e| {
+            warn!("Fail to load materials of {}: {}", path.display(), e);
+            Vec::new()
+        });
+        // meshes without a material, or a missing mtl, fall back to plain white
+        if materials.is_empty() {
+            materials.push(tobj::Material {
+                name: "default".to_string(),
+                diffuse: [1., 1., 1.],
+                ..Default::default()
+            });
+        }
+
+        info!(
+            "{}: {} meshes, {} materials",
+            path.display(),
+            models.len(),
+            materials.len()
+        );
+
+        // material
+        let material_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("material_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(16),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        // models usually expect their textures to tile
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("texture sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        let materials = load_materials(
+            device,
+            queue,
+            &material_bindgroup_layout,
+            &sampler,
+            dir,
+            &materials,
+        );
+        let meshes = load_meshes(device, &models);
+
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.4,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 5., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+        let camera_binding = CameraBinding::new(device, &camera);
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_binding.layout,     // group 0, camera
+                &material_bindgroup_layout, // group 1, material
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x2, 2=>Float32x3],
+        };
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[vertex_buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            // not every obj is closed or consistently wound, so no culling
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        ModelViewer {
+            pipeline,
+            camera,
+            camera_controller,
+            camera_binding,
+            meshes,
+            materials,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, _window: &Window, event: WindowEvent) {
+        self.camera_controller.process_window_event(&event);
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        self.camera_binding.update(queue, &self.camera);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.1,
+                    g: 0.15,
+                    b: 0.3,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.camera_binding.bind_group, &[]);
+
+            // one draw per mesh, each with its own buffers and material
+            for mesh in &self.meshes {
+                rpass.set_bind_group(1, &self.materials[mesh.material].bind_group, &[]);
+                rpass.set_vertex_buffer(0, mesh.vertices_buf.slice(..));
+                rpass.set_index_buffer(mesh.indices_buf.slice(..), wgpu::IndexFormat::Uint32);
+                rpass.draw_indexed(0..mesh.index_count, 0, 0..1);
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,4 @@
+// cargo run -p t20-model -- path/to/model.obj, without a path the bundled scene is loaded
+fn main() {
+    framework::run::<t20_model::ModelViewer>(t20_model::TITLE);
+}
@@ -0,0 +1,45 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) tex_coord: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @location(1) normal: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Material {
+    diffuse: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> view_proj: mat4x4<f32>;
+
+// per material, Kd and map_Kd from the mtl file
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+@group(1) @binding(2)
+var<uniform> material: Material;
+
+let light_dir = vec3<f32>(0.4, 0.8, 0.45);
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    // the model is drawn where the file put it, no model matrix
+    fragInput.clip_position = view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.tex_coord = input.tex_coord;
+    fragInput.normal = input.normal;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let albedo = textureSample(t_diffuse, s_diffuse, input.tex_coord) * material.diffuse;
+    let diffuse = max(dot(normalize(input.normal), normalize(light_dir)), 0.0);
+    return vec4<f32>(albedo.rgb * (0.3 + 0.7 * diffuse), 1.0);
+}
@@ -0,0 +1,296 @@
+// an egui window over the scene: statistics, the node tree with each node's transform,
+// visibility and bounds, and the materials' parameters and texture slots. egui-winit turns window events into
+// egui input and egui-wgpu draws what egui tessellated, on top of the finished frame
+
+use egui::{CollapsingHeader, ComboBox, DragValue, Slider, Ui};
+use framework::assets::ColorSpace;
+use glam::Vec3;
+use winit::{event::WindowEvent, window::Window};
+
+use crate::{LoadedTexture, Material, Node};
+
+pub struct Inspector {
+    ctx: egui::Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    // what the window sent since the last frame, `App::render` doesn't see the window so
+    // egui gets it all in one go there
+    input: egui::RawInput,
+    // cursor and clipboard changes of the last frame, applied when the next event comes in
+    output: Option<egui::PlatformOutput>,
+    pixels_per_point: f32,
+    pub visible: bool,
+}
+
+// what the panel counts, recounted every frame
+pub struct Statistics {
+    pub nodes: usize,
+    pub visible_nodes: usize,
+    pub meshes: usize,
+    pub materials: usize,
+    pub images: usize,
+    pub draw_calls: usize,
+    pub triangles: u32,
+}
+
+impl Inspector {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Inspector {
+        Inspector {
+            ctx: egui::Context::default(),
+            state: egui_winit::State::new_with_wayland_display(None),
+            renderer: egui_wgpu::Renderer::new(device, format, None, 1),
+            input: egui::RawInput::default(),
+            output: None,
+            pixels_per_point: 1.,
+            visible: true,
+        }
+    }
+
+    /// true when egui used the event, e.g. a drag on one of its values, and the camera
+    /// shouldn't move
+    pub fn on_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        if let Some(output) = self.output.take() {
+            self.state.handle_platform_output(window, &self.ctx, output);
+        }
+        if !self.visible {
+            return false;
+        }
+
+        let response = self.state.on_event(&self.ctx, event);
+        let mut input = self.state.take_egui_input(window);
+        self.input.events.append(&mut input.events);
+        self.input.modifiers = input.modifiers;
+        self.pixels_per_point = window.scale_factor() as f32;
+        response.consumed
+    }
+
+    /// run `ui` and draw the result over `view`, the command buffers egui-wgpu uploads with
+    /// have to be submitted before `encoder`
+    pub fn draw(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        size: [u32; 2],
+        ui: impl FnOnce(&egui::Context),
+    ) -> Vec<wgpu::CommandBuffer> {
+        let mut input = std::mem::take(&mut self.input);
+        input.pixels_per_point = Some(self.pixels_per_point);
+        input.screen_rect = Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(size[0] as f32, size[1] as f32) / self.pixels_per_point,
+        ));
+        let output = self.ctx.run(input, ui);
+        self.output = Some(output.platform_output);
+
+        let paint_jobs = self.ctx.tessellate(output.shapes);
+        let screen = egui_wgpu::renderer::ScreenDescriptor {
+            size_in_pixels: size,
+            pixels_per_point: self.pixels_per_point,
+        };
+        for (id, delta) in &output.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        let uploads = self
+            .renderer
+            .update_buffers(device, queue, encoder, &paint_jobs, &screen);
+
+        {
+            // over the scene, not cleared
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("inspector"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.renderer.render(&mut rpass, &paint_jobs, &screen);
+        }
+
+        for id in &output.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+        uploads
+    }
+}
+
+// the whole panel. returns whether a node's transform or visibility changed, the world
+// transforms need updating then; material changes are marked on the material
+pub fn panel(
+    ctx: &egui::Context,
+    statistics: &Statistics,
+    roots: &[usize],
+    nodes: &mut [Node],
+    materials: &mut [Material],
+    textures: &[LoadedTexture],
+) -> bool {
+    let mut nodes_changed = false;
+    egui::Window::new("inspector")
+        .default_width(320.)
+        .show(ctx, |ui| {
+            CollapsingHeader::new("statistics")
+                .default_open(true)
+                .show(ui, |ui| {
+                    ui.label(format!(
+                        "{} nodes, {} visible",
+                        statistics.nodes, statistics.visible_nodes
+                    ));
+                    ui.label(format!(
+                        "{} meshes, {} materials, {} images",
+                        statistics.meshes, statistics.materials, statistics.images
+                    ));
+                    ui.label(format!(
+                        "{} draw calls, {} triangles",
+                        statistics.draw_calls, statistics.triangles
+                    ));
+                });
+
+            CollapsingHeader::new("nodes")
+                .default_open(true)
+                .show(ui, |ui| {
+                    egui::ScrollArea::vertical()
+                        .id_source("nodes")
+                        .max_height(400.)
+                        .show(ui, |ui| {
+                            for &root in roots {
+                                nodes_changed |= node_ui(ui, nodes, root);
+                            }
+                        });
+                });
+
+            CollapsingHeader::new("materials").show(ui, |ui| {
+                for (index, material) in materials.iter_mut().enumerate() {
+                    CollapsingHeader::new(material.name.clone())
+                        .id_source(("material", index))
+                        .show(ui, |ui| material_ui(ui, index, material, textures));
+                }
+            });
+        });
+    nodes_changed
+}
+
+fn node_ui(ui: &mut Ui, nodes: &mut [Node], index: usize) -> bool {
+    let mut changed = false;
+    let name = nodes[index].name.clone();
+    let children = nodes[index].children.clone();
+    CollapsingHeader::new(name).id_source(index).show(ui, |ui| {
+        let node = &mut nodes[index];
+        // a hidden node hides its children too
+        changed |= ui.checkbox(&mut node.visible, "visible").changed();
+        changed |= vec3_ui(ui, "translation", &mut node.translation, 0.01);
+        changed |= vec3_ui(ui, "rotation °", &mut node.rotation, 1.);
+        changed |= vec3_ui(ui, "scale", &mut node.scale, 0.01);
+        if let Some(mesh) = &node.mesh {
+            ui.label(format!(
+                "mesh {}, {} primitives, materials {:?}",
+                mesh.index,
+                mesh.materials.len(),
+                mesh.materials
+            ));
+            let (min, max) = node.world_bounds(mesh);
+            ui.label(format!(
+                "bounds ({:.2}, {:.2}, {:.2}) to ({:.2}, {:.2}, {:.2})",
+                min.x, min.y, min.z, max.x, max.y, max.z
+            ));
+        }
+        for child in children {
+            changed |= node_ui(ui, nodes, child);
+        }
+    });
+    changed
+}
+
+// the parameters go into the uniform before the next frame, a slot change remakes the bind
+// group
+fn material_ui(ui: &mut Ui, index: usize, material: &mut Material, textures: &[LoadedTexture]) {
+    let uniform = &mut material.uniform;
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        changed |= ui
+            .color_edit_button_rgba_unmultiplied(&mut uniform.base_color)
+            .changed();
+        ui.label("base color");
+    });
+    ui.horizontal(|ui| {
+        changed |= ui.color_edit_button_rgb(&mut uniform.emissive).changed();
+        ui.label("emissive");
+    });
+    changed |= ui
+        .add(Slider::new(&mut uniform.metallic, 0.0..=1.0).text("metallic"))
+        .changed();
+    changed |= ui
+        .add(Slider::new(&mut uniform.roughness, 0.0..=1.0).text("roughness"))
+        .changed();
+    changed |= ui
+        .add(Slider::new(&mut uniform.normal_strength, 0.0..=2.0).text("normal strength"))
+        .changed();
+    material.dirty |= changed;
+
+    material.rebind |= texture_slot(
+        ui,
+        ("base color", index),
+        "base color texture",
+        &mut material.base_color_texture,
+        textures,
+    );
+    material.rebind |= texture_slot(
+        ui,
+        ("normal", index),
+        "normal texture",
+        &mut material.normal_texture,
+        textures,
+    );
+}
+
+// a dropdown of every loaded texture, true when another one was picked
+fn texture_slot(
+    ui: &mut Ui,
+    id: impl std::hash::Hash,
+    label: &str,
+    slot: &mut Option<usize>,
+    textures: &[LoadedTexture],
+) -> bool {
+    let name = |texture: &LoadedTexture| {
+        let color_space = match texture.color_space {
+            ColorSpace::Srgb => "srgb",
+            ColorSpace::Linear => "linear",
+        };
+        format!("{} ({})", texture.name, color_space)
+    };
+    let before = *slot;
+    let selected = match before {
+        Some(index) => name(&textures[index]),
+        None => "none".to_string(),
+    };
+
+    ui.horizontal(|ui| {
+        ComboBox::from_id_source(id)
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                ui.selectable_value(slot, None, "none");
+                for (index, texture) in textures.iter().enumerate() {
+                    ui.selectable_value(slot, Some(index), name(texture));
+                }
+            });
+        ui.label(label);
+    });
+    *slot != before
+}
+
+fn vec3_ui(ui: &mut Ui, label: &str, value: &mut Vec3, speed: f64) -> bool {
+    ui.horizontal(|ui| {
+        let mut changed = false;
+        for component in [&mut value.x, &mut value.y, &mut value.z] {
+            changed |= ui.add(DragValue::new(component).speed(speed)).changed();
+        }
+        ui.label(label);
+        changed
+    })
+    .inner
+}
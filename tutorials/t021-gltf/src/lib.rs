@@ -0,0 +1,997 @@
+// a gltf viewer: every primitive of every node's mesh is drawn with its material's base color,
+// metallic, roughness, emissive and normal map. the inspector, an egui window that i shows and
+// hides, lists the scene's nodes and materials and edits them while it runs
+
+mod inspector;
+
+use std::{collections::HashSet, path::PathBuf};
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    assets::ColorSpace,
+    camera::{Camera, CameraBinding, CameraController, OrbitCameraController},
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::{EulerRot, Mat4, Quat, Vec3};
+use inspector::{Inspector, Statistics};
+use tracing::{info, warn};
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "gltf";
+
+const DEFAULT_SCENE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/res/scene.glb");
+const CAMERA_SENSITIVITY: f32 = 0.005;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coord: [f32; 2],
+    normal: [f32; 3],
+}
+
+// matches `Draw` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct DrawUniform {
+    model: [[f32; 4]; 4],
+    normal: [[f32; 4]; 4],
+}
+
+impl DrawUniform {
+    fn new(world: Mat4) -> DrawUniform {
+        DrawUniform {
+            model: world.to_cols_array_2d(),
+            normal: world.inverse().transpose().to_cols_array_2d(),
+        }
+    }
+}
+
+// gpu buffers of one primitive, a gltf mesh is a list of these
+struct Primitive {
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    material: usize,
+    // the corners of the box around the positions, gltf requires them
+    bounds: (Vec3, Vec3),
+}
+
+// matches `Material` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct MaterialUniform {
+    base_color: [f32; 4],
+    emissive: [f32; 3],
+    metallic: f32,
+    roughness: f32,
+    // how far the normal map tilts the normal, gltf's normal texture scale
+    normal_strength: f32,
+    _padding: [f32; 2],
+}
+
+// a gltf material's metallic-roughness parameters, and the loaded textures its slots point at.
+// the inspector edits `uniform` and sets `dirty`, the buffer is written before the next frame.
+// a slot it changed sets `rebind`, the bind group is made again then
+struct Material {
+    name: String,
+    uniform: MaterialUniform,
+    // into `GltfViewer::textures`, none is white for the base color and flat for the normal
+    base_color_texture: Option<usize>,
+    normal_texture: Option<usize>,
+    dirty: bool,
+    rebind: bool,
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+// one gltf image on the gpu, what the inspector's texture slots choose from
+struct LoadedTexture {
+    name: String,
+    color_space: ColorSpace,
+    view: wgpu::TextureView,
+}
+
+// what every material bind group shares, and the stand-ins for empty slots
+struct MaterialBindings {
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    white: wgpu::TextureView,
+    flat_normal: wgpu::TextureView,
+}
+
+impl MaterialBindings {
+    fn bind_group(
+        &self,
+        device: &wgpu::Device,
+        textures: &[LoadedTexture],
+        buffer: &wgpu::Buffer,
+        base_color_texture: Option<usize>,
+        normal_texture: Option<usize>,
+    ) -> wgpu::BindGroup {
+        let base_color = base_color_texture.map_or(&self.white, |index| &textures[index].view);
+        let normal = normal_texture.map_or(&self.flat_normal, |index| &textures[index].view);
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("material bind group"),
+            layout: &self.layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(base_color),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(normal),
+                },
+            ],
+        })
+    }
+}
+
+// a node's mesh, with the node's world transform in its bind group. several nodes can point
+// at the same mesh, the buffers are only made once
+struct NodeMesh {
+    index: usize,
+    // of the mesh's primitives, for the inspector
+    materials: Vec<usize>,
+    bounds: (Vec3, Vec3),
+    draw_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+// a gltf node, the local transform taken apart so the inspector can drag each part
+struct Node {
+    name: String,
+    mesh: Option<NodeMesh>,
+    translation: Vec3,
+    // euler angles in degrees, easier to edit than a quaternion
+    rotation: Vec3,
+    scale: Vec3,
+    children: Vec<usize>,
+    // a hidden node hides its children too
+    visible: bool,
+    world: Mat4,
+}
+
+impl Node {
+    fn local(&self) -> Mat4 {
+        let rotation = Quat::from_euler(
+            EulerRot::XYZ,
+            self.rotation.x.to_radians(),
+            self.rotation.y.to_radians(),
+            self.rotation.z.to_radians(),
+        );
+        Mat4::from_scale_rotation_translation(self.scale, rotation, self.translation)
+    }
+
+    // the box around the mesh's box moved into the world, bigger than the mesh when rotated
+    fn world_bounds(&self, mesh: &NodeMesh) -> (Vec3, Vec3) {
+        transform_bounds(self.world, mesh.bounds)
+    }
+}
+
+// the box around the box `(min, max)` moved by `matrix`, the one around its 8 corners
+fn transform_bounds(matrix: Mat4, (min, max): (Vec3, Vec3)) -> (Vec3, Vec3) {
+    (0..8)
+        .map(|corner| {
+            let pick = |bit, min: f32, max: f32| if corner & bit == 0 { min } else { max };
+            matrix.transform_point3(Vec3::new(
+                pick(1, min.x, max.x),
+                pick(2, min.y, max.y),
+                pick(4, min.z, max.z),
+            ))
+        })
+        .fold(
+            (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+            |(lo, hi), p| (lo.min(p), hi.max(p)),
+        )
+}
+
+fn scene_path() -> PathBuf {
+    match std::env::args().nth(1) {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(DEFAULT_SCENE),
+    }
+}
+
+// parent before children, so every node's world transform is its parent's times its own
+fn update_world(nodes: &mut [Node], index: usize, parent: Mat4) {
+    let world = parent * nodes[index].local();
+    nodes[index].world = world;
+    for child in nodes[index].children.clone() {
+        update_world(nodes, child, world);
+    }
+}
+
+// every node's world transform from the roots down, written into the draw buffers
+fn update_worlds(nodes: &mut [Node], roots: &[usize], queue: &wgpu::Queue) {
+    for &root in roots {
+        update_world(nodes, root, Mat4::IDENTITY);
+    }
+    for node in nodes.iter() {
+        if let Some(mesh) = &node.mesh {
+            watchdog::write_buffer(
+                queue,
+                &mesh.draw_buffer,
+                0,
+                bytemuck::bytes_of(&DrawUniform::new(node.world)),
+            );
+        }
+    }
+}
+
+// the visible nodes under `index` that have a mesh, in draw order
+fn collect_visible(nodes: &[Node], index: usize, out: &mut Vec<usize>) {
+    let node = &nodes[index];
+    if !node.visible {
+        return;
+    }
+    if node.mesh.is_some() {
+        out.push(index);
+    }
+    for &child in &node.children {
+        collect_visible(nodes, child, out);
+    }
+}
+
+fn create_texture_view(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    rgba: &[u8],
+    width: u32,
+    height: u32,
+    color_space: ColorSpace,
+) -> wgpu::TextureView {
+    let texture_size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("gltf_texture"),
+        size: texture_size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        // base color is authored in srgb, normal maps are vectors
+        format: color_space.format(),
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    });
+
+    watchdog::write_texture(
+        queue,
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        rgba,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: std::num::NonZeroU32::new(4 * width),
+            rows_per_image: std::num::NonZeroU32::new(height),
+        },
+        texture_size,
+    );
+
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+// the gpu wants 4 channels, gltf images come in whatever the file had
+fn to_rgba8(image: &gltf::image::Data) -> Option<Vec<u8>> {
+    match image.format {
+        gltf::image::Format::R8G8B8A8 => Some(image.pixels.clone()),
+        gltf::image::Format::R8G8B8 => Some(
+            image
+                .pixels
+                .chunks_exact(3)
+                .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn load_primitives(
+    device: &wgpu::Device,
+    mesh: gltf::Mesh,
+    buffers: &[gltf::buffer::Data],
+    default_material: usize,
+) -> Vec<Primitive> {
+    mesh.primitives()
+        .map(|primitive| {
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+
+            let positions: Vec<[f32; 3]> = reader
+                .read_positions()
+                .expect("Fail to read positions, a primitive must have them")
+                .collect();
+            let normals: Vec<[f32; 3]> = match reader.read_normals() {
+                Some(normals) => normals.collect(),
+                None => vec![[0., 1., 0.]; positions.len()],
+            };
+            // gltf already has v = 0 at the top of the image, same as wgpu
+            let tex_coords: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+                Some(tex_coords) => tex_coords.into_f32().collect(),
+                None => vec![[0., 0.]; positions.len()],
+            };
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+
+            let vertices: Vec<Vertex> = positions
+                .iter()
+                .zip(&tex_coords)
+                .zip(&normals)
+                .map(|((&pos, &tex_coord), &normal)| Vertex {
+                    pos,
+                    tex_coord,
+                    normal,
+                })
+                .collect();
+
+            let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Vertices Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+
+            let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Indices Buffer"),
+                contents: bytemuck::cast_slice(&indices),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+
+            let bounds = primitive.bounding_box();
+            Primitive {
+                vertices_buf,
+                indices_buf,
+                index_count: indices.len() as u32,
+                material: primitive.material().index().unwrap_or(default_material),
+                bounds: (Vec3::from(bounds.min), Vec3::from(bounds.max)),
+            }
+        })
+        .collect()
+}
+
+pub struct GltfViewer {
+    pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_binding: CameraBinding,
+    // meshes[mesh index] holds that mesh's primitives
+    meshes: Vec<Vec<Primitive>>,
+    materials: Vec<Material>,
+    material_bindings: MaterialBindings,
+    textures: Vec<LoadedTexture>,
+    // the camera position, for the specular highlights
+    eye_buffer: wgpu::Buffer,
+    eye_bindgroup: wgpu::BindGroup,
+    // nodes[gltf node index], the scene's roots index into it
+    nodes: Vec<Node>,
+    roots: Vec<usize>,
+    // the world transforms are rewritten when the inspector moved a node
+    nodes_dirty: bool,
+    images: usize,
+    inspector: Inspector,
+    size: [u32; 2],
+}
+
+impl GltfViewer {
+    fn statistics(&self, visible: &[usize]) -> Statistics {
+        let visible_primitives = || {
+            visible.iter().flat_map(move |&index| {
+                let mesh = self.nodes[index]
+                    .mesh
+                    .as_ref()
+                    .expect("only nodes with a mesh");
+                &self.meshes[mesh.index]
+            })
+        };
+        Statistics {
+            nodes: self.nodes.len(),
+            visible_nodes: visible.len(),
+            meshes: self.meshes.len(),
+            materials: self.materials.len(),
+            images: self.images,
+            draw_calls: visible_primitives().count(),
+            triangles: visible_primitives().map(|p| p.index_count / 3).sum(),
+        }
+    }
+}
+
+impl framework::App for GltfViewer {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // gltf, import reads the buffers and decodes the images too
+        let path = scene_path();
+        let (document, buffers, images) = gltf::import(&path)
+            .unwrap_or_else(|e| panic!("Fail to load {}: {}", path.display(), e));
+
+        // textures, one per gltf image. normal maps hold vectors, not colors, so the images
+        // a material uses as one are uploaded without the srgb decode
+        let normal_images: HashSet<usize> = document
+            .materials()
+            .filter_map(|material| material.normal_texture())
+            .map(|info| info.texture().source().index())
+            .collect();
+        let white = [255u8; 4];
+        let textures: Vec<LoadedTexture> = document
+            .images()
+            .zip(&images)
+            .map(|(image, data)| {
+                let color_space = if normal_images.contains(&image.index()) {
+                    ColorSpace::Linear
+                } else {
+                    ColorSpace::Srgb
+                };
+                let view = match to_rgba8(data) {
+                    Some(rgba) => create_texture_view(
+                        device,
+                        queue,
+                        &rgba,
+                        data.width,
+                        data.height,
+                        color_space,
+                    ),
+                    None => {
+                        warn!(
+                            "image {} is {:?}, not supported, using white",
+                            image.index(),
+                            data.format
+                        );
+                        create_texture_view(device, queue, &white, 1, 1, color_space)
+                    }
+                };
+                LoadedTexture {
+                    name: image
+                        .name()
+                        .map_or_else(|| format!("image {}", image.index()), str::to_string),
+                    color_space,
+                    view,
+                }
+            })
+            .collect();
+
+        // material
+        let material_size = std::mem::size_of::<MaterialUniform>() as u64;
+        let material_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("material_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(material_size),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let material_bindings = MaterialBindings {
+            layout: material_bindgroup_layout,
+            // one sampler for everything, the gltf samplers are ignored
+            sampler: device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some("texture sampler"),
+                address_mode_u: wgpu::AddressMode::Repeat,
+                address_mode_v: wgpu::AddressMode::Repeat,
+                address_mode_w: wgpu::AddressMode::Repeat,
+                mag_filter: wgpu::FilterMode::Linear,
+                min_filter: wgpu::FilterMode::Linear,
+                mipmap_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            }),
+            white: create_texture_view(device, queue, &white, 1, 1, ColorSpace::Srgb),
+            // straight up in tangent space
+            flat_normal: create_texture_view(
+                device,
+                queue,
+                &[128, 128, 255, 255],
+                1,
+                1,
+                ColorSpace::Linear,
+            ),
+        };
+
+        let create_material = |name: String,
+                               uniform: MaterialUniform,
+                               base_color_texture: Option<usize>,
+                               normal_texture: Option<usize>| {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Material Buffer"),
+                contents: bytemuck::bytes_of(&uniform),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+            let bind_group = material_bindings.bind_group(
+                device,
+                &textures,
+                &buffer,
+                base_color_texture,
+                normal_texture,
+            );
+
+            Material {
+                name,
+                uniform,
+                base_color_texture,
+                normal_texture,
+                dirty: false,
+                rebind: false,
+                buffer,
+                bind_group,
+            }
+        };
+
+        // the metallic-roughness model without its metallic-roughness and occlusion textures
+        let mut materials: Vec<Material> = document
+            .materials()
+            .enumerate()
+            .map(|(i, material)| {
+                let pbr = material.pbr_metallic_roughness();
+                let normal = material.normal_texture();
+                let uniform = MaterialUniform {
+                    base_color: pbr.base_color_factor(),
+                    emissive: material.emissive_factor(),
+                    metallic: pbr.metallic_factor(),
+                    roughness: pbr.roughness_factor(),
+                    normal_strength: normal.as_ref().map_or(1., |info| info.scale()),
+                    _padding: [0.; 2],
+                };
+                let name = material
+                    .name()
+                    .map_or_else(|| format!("material {}", i), str::to_string);
+                create_material(
+                    name,
+                    uniform,
+                    pbr.base_color_texture()
+                        .map(|info| info.texture().source().index()),
+                    normal.map(|info| info.texture().source().index()),
+                )
+            })
+            .collect();
+        // the gltf default material, for primitives that don't name one
+        let default_material = materials.len();
+        materials.push(create_material(
+            "default".to_string(),
+            MaterialUniform {
+                base_color: [1., 1., 1., 1.],
+                emissive: [0.; 3],
+                metallic: 1.,
+                roughness: 1.,
+                normal_strength: 1.,
+                _padding: [0.; 2],
+            },
+            None,
+            None,
+        ));
+
+        // meshes
+        let meshes: Vec<Vec<Primitive>> = document
+            .meshes()
+            .map(|mesh| load_primitives(device, mesh, &buffers, default_material))
+            .collect();
+
+        let draw_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("draw bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<DrawUniform>() as u64
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        // every node of the document, the world transforms are filled in from the scene's
+        // roots below and written again when the inspector moves a node
+        let mut nodes: Vec<Node> = document
+            .nodes()
+            .map(|node| {
+                let mesh = node.mesh().map(|mesh| {
+                    let primitives = &meshes[mesh.index()];
+                    let bounds = primitives.iter().fold(
+                        (Vec3::splat(f32::MAX), Vec3::splat(f32::MIN)),
+                        |(lo, hi), primitive| {
+                            (lo.min(primitive.bounds.0), hi.max(primitive.bounds.1))
+                        },
+                    );
+                    let draw_buffer =
+                        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                            label: Some("Draw Buffer"),
+                            contents: bytemuck::bytes_of(&DrawUniform::new(Mat4::IDENTITY)),
+                            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                        });
+                    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                        label: Some("draw bind group"),
+                        layout: &draw_bindgroup_layout,
+                        entries: &[wgpu::BindGroupEntry {
+                            binding: 0,
+                            resource: draw_buffer.as_entire_binding(),
+                        }],
+                    });
+                    NodeMesh {
+                        index: mesh.index(),
+                        materials: primitives.iter().map(|p| p.material).collect(),
+                        bounds,
+                        draw_buffer,
+                        bind_group,
+                    }
+                });
+
+                let (translation, rotation, scale) = node.transform().decomposed();
+                let (x, y, z) = Quat::from_array(rotation).to_euler(EulerRot::XYZ);
+                Node {
+                    name: node
+                        .name()
+                        .map_or_else(|| format!("node {}", node.index()), str::to_string),
+                    mesh,
+                    translation: Vec3::from(translation),
+                    rotation: Vec3::new(x.to_degrees(), y.to_degrees(), z.to_degrees()),
+                    scale: Vec3::from(scale),
+                    children: node.children().map(|child| child.index()).collect(),
+                    visible: true,
+                    world: Mat4::IDENTITY,
+                }
+            })
+            .collect();
+
+        let scene = document
+            .default_scene()
+            .or_else(|| document.scenes().next())
+            .expect("Fail to find a scene in the gltf");
+        let roots: Vec<usize> = scene.nodes().map(|node| node.index()).collect();
+        update_worlds(&mut nodes, &roots, queue);
+
+        info!(
+            "{}: {} nodes, {} meshes, {} materials, {} images",
+            path.display(),
+            nodes.len(),
+            meshes.len(),
+            default_material,
+            images.len()
+        );
+
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.4,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 6., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+        let camera_binding = CameraBinding::new(device, &camera);
+
+        let eye_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Eye Buffer"),
+            contents: bytemuck::cast_slice(&camera.position.extend(1.).to_array()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let eye_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("eye bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(16),
+                    },
+                    count: None,
+                }],
+            });
+        let eye_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("eye bind group"),
+            layout: &eye_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: eye_buffer.as_entire_binding(),
+            }],
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_binding.layout,    // group 0, camera
+                &material_bindings.layout, // group 1, material
+                &draw_bindgroup_layout,    // group 2, node transform
+                &eye_bindgroup_layout,     // group 3, camera position
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x2, 2=>Float32x3],
+        };
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[vertex_buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            // gltf is counter clockwise front facing, but materials can be double sided,
+            // so nothing is culled
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        info!("press i to show or hide the inspector");
+
+        GltfViewer {
+            pipeline,
+            camera,
+            camera_controller,
+            camera_binding,
+            meshes,
+            materials,
+            material_bindings,
+            textures,
+            eye_buffer,
+            eye_bindgroup,
+            nodes,
+            roots,
+            nodes_dirty: false,
+            images: images.len(),
+            inspector: Inspector::new(device, config.format),
+            size: [config.width, config.height],
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+        self.size = [config.width, config.height];
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        // egui first, a drag on one of its values shouldn't turn the camera too
+        if self.inspector.on_event(window, &event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::I),
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.inspector.visible = !self.inspector.visible;
+            return;
+        }
+        self.camera_controller.process_window_event(&event);
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        self.camera_binding.update(queue, &self.camera);
+
+        // what the inspector changed last frame
+        if self.nodes_dirty {
+            self.nodes_dirty = false;
+            update_worlds(&mut self.nodes, &self.roots, queue);
+        }
+        for material in self.materials.iter_mut() {
+            if material.dirty {
+                material.dirty = false;
+                watchdog::write_buffer(
+                    queue,
+                    &material.buffer,
+                    0,
+                    bytemuck::bytes_of(&material.uniform),
+                );
+            }
+            if material.rebind {
+                material.rebind = false;
+                material.bind_group = self.material_bindings.bind_group(
+                    device,
+                    &self.textures,
+                    &material.buffer,
+                    material.base_color_texture,
+                    material.normal_texture,
+                );
+            }
+        }
+        watchdog::write_buffer(
+            queue,
+            &self.eye_buffer,
+            0,
+            bytemuck::cast_slice(&self.camera.position.extend(1.).to_array()),
+        );
+
+        let mut visible = Vec::new();
+        for &root in &self.roots {
+            collect_visible(&self.nodes, root, &mut visible);
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.1,
+                    g: 0.15,
+                    b: 0.3,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.camera_binding.bind_group, &[]);
+            rpass.set_bind_group(3, &self.eye_bindgroup, &[]);
+
+            for &index in &visible {
+                let mesh = self.nodes[index]
+                    .mesh
+                    .as_ref()
+                    .expect("only nodes with a mesh");
+                rpass.set_bind_group(2, &mesh.bind_group, &[]);
+                for primitive in &self.meshes[mesh.index] {
+                    rpass.set_bind_group(1, &self.materials[primitive.material].bind_group, &[]);
+                    rpass.set_vertex_buffer(0, primitive.vertices_buf.slice(..));
+                    rpass.set_index_buffer(
+                        primitive.indices_buf.slice(..),
+                        wgpu::IndexFormat::Uint32,
+                    );
+                    rpass.draw_indexed(0..primitive.index_count, 0, 0..1);
+                }
+            }
+        }
+
+        let mut uploads = Vec::new();
+        if self.inspector.visible {
+            let statistics = self.statistics(&visible);
+            let (roots, nodes) = (&self.roots, &mut self.nodes);
+            let (materials, textures) = (&mut self.materials, &self.textures);
+            let mut nodes_changed = false;
+            uploads =
+                self.inspector
+                    .draw(device, queue, &mut encoder, frame.view, self.size, |ctx| {
+                        nodes_changed =
+                            inspector::panel(ctx, &statistics, roots, nodes, materials, textures)
+                    });
+            self.nodes_dirty |= nodes_changed;
+        }
+
+        queue.submit(uploads.into_iter().chain(Some(encoder.finish())));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_1_SQRT_2;
+
+    use super::*;
+
+    #[test]
+    fn bounds_follow_a_rotated_and_stretched_node() {
+        let bounds = (Vec3::splat(-1.), Vec3::ONE);
+        let matrix = Mat4::from_scale_rotation_translation(
+            Vec3::new(2., 1., 0.5),
+            Quat::from_rotation_y(45_f32.to_radians()),
+            Vec3::new(1., 2., 3.),
+        );
+        let (min, max) = transform_bounds(matrix, bounds);
+
+        // every corner, and points in between, land inside
+        for corner in 0..8 {
+            let pick = |bit, value: f32| if corner & bit == 0 { -value } else { value };
+            for t in [0.5, 1.] {
+                let point = matrix.transform_point3(Vec3::new(pick(1, t), pick(2, t), pick(4, t)));
+                assert!(
+                    point.cmpge(min - 1e-5).all() && point.cmple(max + 1e-5).all(),
+                    "{} is outside {}..{}",
+                    point,
+                    min,
+                    max
+                );
+            }
+        }
+
+        // and it's no bigger than it has to be: 2 and 0.5 wide, turned 45 degrees, reach
+        // (2 + 0.5) / sqrt 2 in x and z
+        let reach = Vec3::new(2.5 * FRAC_1_SQRT_2, 1., 2.5 * FRAC_1_SQRT_2);
+        let center = Vec3::new(1., 2., 3.);
+        assert!(min.abs_diff_eq(center - reach, 1e-4), "{}", min);
+        assert!(max.abs_diff_eq(center + reach, 1e-4), "{}", max);
+    }
+}
@@ -0,0 +1,4 @@
+// cargo run -p t21-gltf -- path/to/scene.glb, without a path the bundled scene is loaded
+fn main() {
+    framework::run::<t21_gltf::GltfViewer>(t21_gltf::TITLE);
+}
@@ -0,0 +1,103 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) tex_coord: vec2<f32>,
+    @location(2) normal: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) world_pos: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Material {
+    base_color: vec4<f32>,
+    emissive: vec3<f32>,
+    metallic: f32,
+    roughness: f32,
+    // scales the normal map's x and y, 0 is the flat surface
+    normal_strength: f32,
+};
+
+// world transform of the node the primitive hangs off
+struct Draw {
+    model: mat4x4<f32>,
+    // inverse transpose of model, keeps normals right under non uniform scale
+    normal: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> view_proj: mat4x4<f32>;
+
+@group(1) @binding(0)
+var t_base_color: texture_2d<f32>;
+@group(1) @binding(1)
+var s_base_color: sampler;
+@group(1) @binding(2)
+var<uniform> material: Material;
+@group(1) @binding(3)
+var t_normal: texture_2d<f32>;
+
+@group(2) @binding(0)
+var<uniform> draw: Draw;
+
+// w unused
+@group(3) @binding(0)
+var<uniform> eye: vec4<f32>;
+
+let light_dir = vec3<f32>(0.4, 0.8, 0.45);
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    let world_pos = draw.model * vec4<f32>(input.pos, 1.0);
+    fragInput.clip_position = view_proj * world_pos;
+    fragInput.world_pos = world_pos.xyz;
+    fragInput.tex_coord = input.tex_coord;
+    fragInput.normal = (draw.normal * vec4<f32>(input.normal, 0.0)).xyz;
+    return fragInput;
+}
+
+// the meshes have no tangents, the tangent frame comes from how the position and the texture
+// coordinates change between neighbouring pixels instead
+fn perturb_normal(normal: vec3<f32>, pos: vec3<f32>, uv: vec2<f32>, mapped: vec3<f32>) -> vec3<f32> {
+    let dp1 = dpdx(pos);
+    let dp2 = dpdy(pos);
+    let duv1 = dpdx(uv);
+    let duv2 = dpdy(uv);
+
+    let dp2perp = cross(dp2, normal);
+    let dp1perp = cross(normal, dp1);
+    let tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    let bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+    // without texture coordinates both are 0, the normal is left alone then
+    let scale = inverseSqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+    return normalize(mat3x3<f32>(tangent * scale, bitangent * scale, normal) * mapped);
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let albedo = textureSample(t_base_color, s_base_color, input.tex_coord) * material.base_color;
+    var mapped = textureSample(t_normal, s_base_color, input.tex_coord).xyz * 2.0 - 1.0;
+    mapped = vec3<f32>(mapped.xy * material.normal_strength, mapped.z);
+    let normal = perturb_normal(normalize(input.normal), input.world_pos, input.tex_coord, mapped);
+
+    let light = normalize(light_dir);
+    let view = normalize(eye.xyz - input.world_pos);
+    let half_dir = normalize(light + view);
+    let n_dot_l = max(dot(normal, light), 0.0);
+
+    // metals have no diffuse part and tint their reflection, everything else reflects 4%
+    let diffuse = albedo.rgb * (1.0 - material.metallic);
+    let f0 = mix(vec3<f32>(0.04), albedo.rgb, material.metallic);
+    // normalized blinn-phong, the exponent from the roughness the way ggx's alpha is
+    let alpha = max(material.roughness * material.roughness, 0.01);
+    let shininess = 2.0 / (alpha * alpha) - 2.0;
+    let specular = f0 * pow(max(dot(normal, half_dir), 0.0), shininess) * (shininess + 8.0) / 8.0;
+
+    // no environment to reflect, the ambient term stands in for it so metals aren't black
+    let ambient = 0.3 * (diffuse + f0);
+    let color = ambient + (0.7 * diffuse + specular) * n_dot_l + material.emissive;
+    return vec4<f32>(color, 1.0);
+}
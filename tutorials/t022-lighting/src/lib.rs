@@ -0,0 +1,421 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    primitives::Mesh,
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::{Mat4, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+// blinn-phong lighting from one point light: ambient, diffuse and a specular highlight
+// worked out per fragment, with a small unlit cube showing where the light is
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "blinn-phong";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const LIGHT_ORBIT_RADIUS: f32 = 2.;
+const LIGHT_HEIGHT: f32 = 1.5;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, normal, color }
+}
+
+// matches `Camera` in shader.wgsl, the eye position is needed for the specular term
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `Light` in shader.wgsl, vec3s are 16 byte aligned so the scalars fill the gaps
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LightUniform {
+    position: [f32; 3],
+    ambient: f32,
+    color: [f32; 3],
+    diffuse: f32,
+    specular: f32,
+    shininess: f32,
+    blinn: u32,
+    _padding: u32,
+}
+
+// a cube of `size` around `center`, one normal per face so the edges stay sharp
+fn push_cube(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    size: Vec3,
+    color: [f32; 3],
+) {
+    let cube = Mesh::cube(1).transformed(Mat4::from_translation(center) * Mat4::from_scale(size));
+    let base = vertices.len() as u16;
+    vertices.extend(cube.vertices.iter().map(|v| vertex(v.pos, v.normal, color)));
+    indices.extend(cube.indices.iter().map(|&index| base + index));
+}
+
+// the unit cube comes first, the light cube reuses its 36 indices
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::ZERO,
+        Vec3::ONE,
+        [1., 0.5, 0.31],
+    );
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(-1.5, -0.25, 1.),
+        Vec3::splat(0.5),
+        [0.3, 0.6, 1.],
+    );
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(1.3, -0.1, -1.2),
+        Vec3::new(0.4, 0.8, 0.4),
+        [0.4, 1., 0.4],
+    );
+    // ground
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(0., -0.6, 0.),
+        Vec3::new(6., 0.2, 6.),
+        [0.6, 0.6, 0.6],
+    );
+
+    (vertices, indices)
+}
+
+pub struct Lighting {
+    pipeline: wgpu::RenderPipeline,
+    light_pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    light: LightUniform,
+    light_buffer: wgpu::Buffer,
+    light_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    orbit_light: bool,
+    light_angle: f32,
+}
+
+impl framework::App for Lighting {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.5,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 6., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_layout = |label, visibility, size: usize| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(size as u64),
+                    },
+                    count: None,
+                }],
+            })
+        };
+
+        let camera_bindgroup_layout = uniform_layout(
+            "camera bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<CameraUniform>(),
+        );
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // light
+        let light = LightUniform {
+            position: [LIGHT_ORBIT_RADIUS, LIGHT_HEIGHT, 0.],
+            ambient: 0.1,
+            color: [1., 1., 1.],
+            diffuse: 1.,
+            specular: 0.5,
+            shininess: 32.,
+            blinn: 1,
+            _padding: 0,
+        };
+
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::bytes_of(&light),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // the light cube's vertex shader reads the light position too
+        let light_bindgroup_layout = uniform_layout(
+            "light bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<LightUniform>(),
+        );
+
+        let light_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("light bind group"),
+            layout: &light_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_buffer.as_entire_binding(),
+            }],
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_bindgroup_layout, // group 0, camera
+                &light_bindgroup_layout,  // group 1, light
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3, 2=>Float32x3],
+        };
+
+        // lit geometry and the light cube only differ in their entry points
+        let create_pipeline = |vs_entry_point, fs_entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(fs_entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vs_entry_point,
+                    buffers: std::slice::from_ref(&vertex_buffer_layout),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs_entry_point,
+                    targets: &[Some(config.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DepthTexture::FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline("vs_main", "fs_main");
+        let light_pipeline = create_pipeline("vs_light", "fs_light");
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!("press B to switch phong / blinn-phong, L to stop the light, up/down for shininess");
+
+        Lighting {
+            pipeline,
+            light_pipeline,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            light,
+            light_buffer,
+            light_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            orbit_light: true,
+            light_angle: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::B => self.light.blinn = 1 - self.light.blinn,
+                VirtualKeyCode::L => self.orbit_light = !self.orbit_light,
+                VirtualKeyCode::Up => self.light.shininess = (self.light.shininess * 2.).min(256.),
+                VirtualKeyCode::Down => self.light.shininess = (self.light.shininess / 2.).max(2.),
+                _ => return,
+            }
+
+            let title = format!(
+                "{}, shininess {}",
+                if self.light.blinn == 1 {
+                    "blinn-phong"
+                } else {
+                    "phong"
+                },
+                self.light.shininess
+            );
+            window.set_title(&title);
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        if self.orbit_light {
+            self.light_angle += frame.delta;
+        }
+        let (sin, cos) = self.light_angle.sin_cos();
+        self.light.position = [
+            cos * LIGHT_ORBIT_RADIUS,
+            LIGHT_HEIGHT,
+            sin * LIGHT_ORBIT_RADIUS,
+        ];
+        watchdog::write_buffer(
+            queue,
+            &self.light_buffer,
+            0,
+            bytemuck::bytes_of(&self.light),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.02,
+                    g: 0.02,
+                    b: 0.05,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.light_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1);
+
+            // the first 36 indices are the unit cube
+            rpass.set_pipeline(&self.light_pipeline);
+            rpass.draw_indexed(0..36, 0, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
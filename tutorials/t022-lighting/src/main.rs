@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t22_lighting::Lighting>(t22_lighting::TITLE);
+}
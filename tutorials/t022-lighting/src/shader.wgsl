@@ -0,0 +1,90 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+struct Light {
+    position: vec3<f32>,
+    ambient: f32,
+    color: vec3<f32>,
+    diffuse: f32,
+    specular: f32,
+    shininess: f32,
+    // 0 phong, 1 blinn-phong
+    blinn: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> light: Light;
+
+// the geometry is already in world space, no model matrix
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    fragInput.normal = input.normal;
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    // interpolation shortens the normal between vertices
+    let normal = normalize(input.normal);
+    let light_dir = normalize(light.position - input.world_pos);
+    let view_dir = normalize(camera.position.xyz - input.world_pos);
+
+    let diffuse = light.diffuse * max(dot(normal, light_dir), 0.0);
+
+    var spec_angle: f32;
+    if (light.blinn != 0u) {
+        // halfway between light and eye, stays well behaved when the reflection points away
+        let half_dir = normalize(light_dir + view_dir);
+        spec_angle = max(dot(normal, half_dir), 0.0);
+    } else {
+        let reflect_dir = reflect(-light_dir, normal);
+        spec_angle = max(dot(view_dir, reflect_dir), 0.0);
+    }
+    // no highlight on the side facing away from the light
+    let specular = light.specular * pow(spec_angle, light.shininess) * step(0.0, dot(normal, light_dir));
+
+    // the highlight takes the light's color, not the surface's
+    let color = (light.ambient + diffuse) * light.color * input.color + specular * light.color;
+    return vec4<f32>(color, 1.0);
+}
+
+// the light cube, the unit cube shrunk and moved to the light, unlit in the light's color
+@vertex
+fn vs_light(input: VertexInput) -> FragInput {
+    let world_pos = input.pos * 0.15 + light.position;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = input.normal;
+    fragInput.color = light.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_light(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color, 1.0);
+}
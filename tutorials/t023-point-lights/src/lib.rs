@@ -0,0 +1,525 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    compat,
+    light_editor::{Light, LightEditor},
+    primitives::Mesh,
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::{Mat4, Vec3};
+use tracing::info;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+// many point lights at once, kept in a storage buffer the fragment shader loops over, each
+// one with its own color and a radius it fades out over
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "point lights";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const NUM_LIGHTS: usize = 32;
+const LIGHT_RADIUS: f32 = 2.5;
+const PILLARS: usize = 12;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, normal, color }
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `PointLight` in shader.wgsl, 32 bytes so the array stride needs no padding
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct PointLight {
+    position: [f32; 3],
+    radius: f32,
+    color: [f32; 3],
+    intensity: f32,
+}
+
+// how a light moves, it circles the origin at its own height, radius and speed
+struct LightPath {
+    orbit: f32,
+    height: f32,
+    speed: f32,
+    phase: f32,
+}
+
+impl LightPath {
+    fn position(&self, time: f32) -> [f32; 3] {
+        let (sin, cos) = (self.phase + time * self.speed).sin_cos();
+        // bob up and down a little so the lights pass over and between the pillars
+        let height = self.height + (time * self.speed * 3. + self.phase).sin() * 0.3;
+        [cos * self.orbit, height, sin * self.orbit]
+    }
+}
+
+// fully saturated color for `hue` in 0..1
+fn hue_to_rgb(hue: f32) -> [f32; 3] {
+    let channel = |offset: f32| {
+        let h = (hue + offset).fract() * 6.;
+        ((h - 3.).abs() - 1.).clamp(0., 1.)
+    };
+    [channel(0.), channel(2. / 3.), channel(1. / 3.)]
+}
+
+fn create_lights() -> (Vec<LightPath>, Vec<Light>) {
+    let paths: Vec<LightPath> = (0..NUM_LIGHTS)
+        .map(|i| {
+            let t = i as f32 / NUM_LIGHTS as f32;
+            LightPath {
+                orbit: 1. + (i % 4) as f32 * 1.2,
+                height: 0.3 + (i % 3) as f32 * 0.4,
+                // alternate directions so the lights cross each other
+                speed: (1. + t) * if i % 2 == 0 { 0.4 } else { -0.3 },
+                phase: t * std::f32::consts::TAU,
+            }
+        })
+        .collect();
+
+    let lights = paths
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            Light::point(
+                Vec3::from(path.position(0.)),
+                Vec3::from(hue_to_rgb(i as f32 / NUM_LIGHTS as f32)),
+                LIGHT_RADIUS,
+            )
+        })
+        .collect();
+
+    (paths, lights)
+}
+
+// always NUM_LIGHTS, the uniform fallback's array has a fixed length. a scene file with fewer
+// lights is padded with dark ones
+fn point_lights(lights: &[Light]) -> Vec<PointLight> {
+    let mut point_lights: Vec<PointLight> = lights
+        .iter()
+        .take(NUM_LIGHTS)
+        .map(|light| PointLight {
+            position: light.position.to_array(),
+            radius: light.range,
+            color: light.color.to_array(),
+            intensity: light.intensity,
+        })
+        .collect();
+    point_lights.resize(
+        NUM_LIGHTS,
+        PointLight {
+            position: [0.; 3],
+            radius: 1.,
+            color: [0.; 3],
+            intensity: 0.,
+        },
+    );
+    point_lights
+}
+
+// a cube of `size` around `center`, as in t22
+fn push_cube(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    size: Vec3,
+    color: [f32; 3],
+) {
+    let cube = Mesh::cube(1).transformed(Mat4::from_translation(center) * Mat4::from_scale(size));
+    let base = vertices.len() as u16;
+    vertices.extend(cube.vertices.iter().map(|v| vertex(v.pos, v.normal, color)));
+    indices.extend(cube.indices.iter().map(|&index| base + index));
+}
+
+// the unit cube comes first, the light cubes reuse its 36 indices
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    let white = [0.8, 0.8, 0.8];
+    push_cube(&mut vertices, &mut indices, Vec3::ZERO, Vec3::ONE, white);
+
+    // a ring of pillars for the lights to weave through
+    for i in 0..PILLARS {
+        let angle = i as f32 / PILLARS as f32 * std::f32::consts::TAU;
+        let height = 0.8 + (i % 3) as f32 * 0.5;
+        push_cube(
+            &mut vertices,
+            &mut indices,
+            Vec3::new(angle.cos() * 3., height / 2. - 0.5, angle.sin() * 3.),
+            Vec3::new(0.4, height, 0.4),
+            white,
+        );
+    }
+
+    // ground
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(0., -0.6, 0.),
+        Vec3::new(12., 0.2, 12.),
+        [0.6, 0.6, 0.6],
+    );
+
+    (vertices, indices)
+}
+
+pub struct PointLights {
+    pipeline: wgpu::RenderPipeline,
+    light_pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    light_paths: Vec<LightPath>,
+    // the lights themselves, moved along their paths while the animation runs
+    editor: LightEditor,
+    lights_buffer: wgpu::Buffer,
+    lights_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    paused: bool,
+    time: f32,
+}
+
+impl PointLights {
+    fn title(&self) -> String {
+        if self.editor.enabled {
+            format!("point lights, editing {}", self.editor.status())
+        } else if self.paused {
+            "point lights, paused".to_string()
+        } else {
+            "point lights".to_string()
+        }
+    }
+}
+
+impl framework::App for PointLights {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.6,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 9., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // lights, a storage buffer holds any number of them where a uniform would need a
+        // fixed size array. the webgl2 profile has no storage buffers, `compat` falls back to
+        // a uniform array of NUM_LIGHTS there. rewritten every frame, so COPY_DST
+        let (light_paths, defaults) = create_lights();
+        let editor = LightEditor::load(device, config.format, env!("CARGO_PKG_NAME"), defaults);
+        let lights = point_lights(&editor.lights);
+
+        let lights_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Lights Buffer"),
+            contents: bytemuck::cast_slice(&lights),
+            usage: compat::array_usage(device),
+        });
+
+        // the light cubes' vertex shader reads the light positions too
+        let lights_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("lights bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: compat::array_binding_type(device),
+                        has_dynamic_offset: false,
+                        // all of them, a uniform array has a fixed length
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of_val(
+                            lights.as_slice(),
+                        ) as u64),
+                    },
+                    count: None,
+                }],
+            });
+
+        let lights_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("lights bind group"),
+            layout: &lights_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: lights_buffer.as_entire_binding(),
+            }],
+        });
+
+        // shader
+        let shader = compat::array_shader(
+            device,
+            "shader.wgsl",
+            include_str!("shader.wgsl"),
+            "lights",
+            NUM_LIGHTS,
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_bindgroup_layout, // group 0, camera
+                &lights_bindgroup_layout, // group 1, lights
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3, 2=>Float32x3],
+        };
+
+        // lit geometry and the light cubes only differ in their entry points
+        let create_pipeline = |vs_entry_point, fs_entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(fs_entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vs_entry_point,
+                    buffers: std::slice::from_ref(&vertex_buffer_layout),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs_entry_point,
+                    targets: &[Some(config.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DepthTexture::FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline("vs_main", "fs_main");
+        let light_pipeline = create_pipeline("vs_light", "fs_light");
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!("{} point lights, press space to pause them", NUM_LIGHTS);
+        info!("press E to edit the lights, right drag their handles");
+
+        PointLights {
+            pipeline,
+            light_pipeline,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            light_paths,
+            lights_buffer,
+            lights_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            // lights from a scene file stay where they were saved
+            paused: editor.saved(),
+            editor,
+            time: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.editor.process_window_event(window, &event) {
+            window.set_title(&self.title());
+            return;
+        }
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Space),
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.paused = !self.paused;
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        // animate on the cpu and upload the whole array, cheap for a few dozen lights. paused
+        // or while editing the lights stay where they are, running again puts them back on
+        // their paths
+        self.editor.poll();
+        if !self.paused && !self.editor.enabled {
+            self.time += frame.delta;
+            for (light, path) in self.editor.lights.iter_mut().zip(&self.light_paths) {
+                light.position = Vec3::from(path.position(self.time));
+            }
+        }
+        watchdog::write_buffer(
+            queue,
+            &self.lights_buffer,
+            0,
+            bytemuck::cast_slice(&point_lights(&self.editor.lights)),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.lights_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1);
+
+            // the first 36 indices are the unit cube, one instance per light
+            rpass.set_pipeline(&self.light_pipeline);
+            let count = self.editor.lights.len().min(NUM_LIGHTS) as u32;
+            rpass.draw_indexed(0..36, 0, 0..count);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+
+    // the editor's gizmo in a layer over the scene, screenshots keep it unless told otherwise
+    fn layers() -> &'static [&'static str] {
+        &["gizmo"]
+    }
+
+    fn draw_layer(
+        &mut self,
+        _layer: &str,
+        view: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.editor.draw(queue, &mut encoder, view, &self.camera);
+        queue.submit(Some(encoder.finish()));
+    }
+
+    // a loaded scene stays where the file put it, like a saved one at startup
+    fn light_editor(&mut self) -> Option<&mut LightEditor> {
+        self.paused = true;
+        Some(&mut self.editor)
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t23_point_lights::PointLights>(t23_point_lights::TITLE);
+}
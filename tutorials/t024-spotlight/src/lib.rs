@@ -0,0 +1,486 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    light_editor::{Light, LightEditor},
+    primitives::Mesh,
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::{Mat4, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+// a spotlight, a point light that only reaches inside a cone, softened between an inner
+// and an outer angle so the edge of the pool of light isn't hard
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "spotlight";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const LIGHT_POSITION: Vec3 = Vec3::new(0., 2.5, 2.5);
+// radians per key press, arrow keys aim the cone
+const AIM_STEP: f32 = 0.05;
+// radians per key press, -/= shrink and grow the cone
+const CONE_STEP: f32 = 0.05;
+const MIN_CONE: f32 = 0.05;
+const MAX_CONE: f32 = 1.2;
+// the full brightness part of the cone, as a fraction of the outer angle
+const INNER_CONE: f32 = 0.75;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, normal, color }
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `Spotlight` in shader.wgsl, vec3s are 16 byte aligned so the scalars fill the gaps
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct SpotlightUniform {
+    position: [f32; 3],
+    inner_cos: f32,
+    direction: [f32; 3],
+    outer_cos: f32,
+    color: [f32; 3],
+    intensity: f32,
+    range: f32,
+    ambient: f32,
+    _padding: [f32; 2],
+}
+
+impl SpotlightUniform {
+    fn new(light: &Light) -> SpotlightUniform {
+        SpotlightUniform {
+            position: light.position.to_array(),
+            inner_cos: light.inner_angle.cos(),
+            direction: light.direction.to_array(),
+            outer_cos: light.outer_angle.cos(),
+            color: light.color.to_array(),
+            intensity: light.intensity,
+            range: light.range,
+            ambient: 0.05,
+            _padding: [0.; 2],
+        }
+    }
+}
+
+// the spotlight's direction from its angles around y and above the horizon, in radians
+fn direction(yaw: f32, pitch: f32) -> Vec3 {
+    Vec3::new(
+        pitch.cos() * yaw.sin(),
+        pitch.sin(),
+        pitch.cos() * yaw.cos(),
+    )
+}
+
+// a cube of `size` around `center`, as in t22
+fn push_cube(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    size: Vec3,
+    color: [f32; 3],
+) {
+    let cube = Mesh::cube(1).transformed(Mat4::from_translation(center) * Mat4::from_scale(size));
+    let base = vertices.len() as u16;
+    vertices.extend(cube.vertices.iter().map(|v| vertex(v.pos, v.normal, color)));
+    indices.extend(cube.indices.iter().map(|&index| base + index));
+}
+
+// the unit cube comes first, the light cube reuses its 36 indices
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::ZERO,
+        Vec3::ONE,
+        [1., 0.5, 0.31],
+    );
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(-1.5, -0.25, 1.),
+        Vec3::splat(0.5),
+        [0.3, 0.6, 1.],
+    );
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(1.3, -0.1, -1.2),
+        Vec3::new(0.4, 0.8, 0.4),
+        [0.4, 1., 0.4],
+    );
+    // ground
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(0., -0.6, 0.),
+        Vec3::new(10., 0.2, 10.),
+        [0.6, 0.6, 0.6],
+    );
+
+    (vertices, indices)
+}
+
+pub struct Spotlight {
+    pipeline: wgpu::RenderPipeline,
+    light_pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    // the spotlight, the editor's first light
+    editor: LightEditor,
+    light_buffer: wgpu::Buffer,
+    light_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+}
+
+impl Spotlight {
+    fn title(&self) -> String {
+        if self.editor.enabled {
+            return format!("spotlight, editing {}", self.editor.status());
+        }
+        format!(
+            "spotlight, cone {:.0} degrees",
+            self.editor.lights[0].outer_angle.to_degrees() * 2.
+        )
+    }
+}
+
+impl framework::App for Spotlight {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.5,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 6., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_layout = |label, visibility, size: usize| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(size as u64),
+                    },
+                    count: None,
+                }],
+            })
+        };
+
+        let camera_bindgroup_layout = uniform_layout(
+            "camera bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<CameraUniform>(),
+        );
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // light, starts out pointing down at the origin unless the scene file moved it
+        let editor = LightEditor::load(
+            device,
+            config.format,
+            env!("CARGO_PKG_NAME"),
+            vec![Light::spot(
+                LIGHT_POSITION,
+                direction(std::f32::consts::PI, -0.8),
+                Vec3::new(1., 1., 0.9),
+                0.4 * INNER_CONE,
+                0.4,
+            )],
+        );
+
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::bytes_of(&SpotlightUniform::new(&editor.lights[0])),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // the light cube's vertex shader reads the light position too
+        let light_bindgroup_layout = uniform_layout(
+            "light bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<SpotlightUniform>(),
+        );
+
+        let light_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("light bind group"),
+            layout: &light_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_buffer.as_entire_binding(),
+            }],
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_bindgroup_layout, // group 0, camera
+                &light_bindgroup_layout,  // group 1, light
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3, 2=>Float32x3],
+        };
+
+        // lit geometry and the light cube only differ in their entry points
+        let create_pipeline = |vs_entry_point, fs_entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(fs_entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vs_entry_point,
+                    buffers: std::slice::from_ref(&vertex_buffer_layout),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs_entry_point,
+                    targets: &[Some(config.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DepthTexture::FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline("vs_main", "fs_main");
+        let light_pipeline = create_pipeline("vs_light", "fs_light");
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!("arrow keys aim the spotlight, -/= shrink and grow its cone");
+        info!("press E to edit the light, right drag its handles");
+
+        Spotlight {
+            pipeline,
+            light_pipeline,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            editor,
+            light_buffer,
+            light_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.editor.process_window_event(window, &event) {
+            window.set_title(&self.title());
+            return;
+        }
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            let light = &mut self.editor.lights[0];
+            let mut yaw = light.direction.x.atan2(light.direction.z);
+            let mut pitch = light.direction.y.asin();
+            match key {
+                VirtualKeyCode::Left => yaw += AIM_STEP,
+                VirtualKeyCode::Right => yaw -= AIM_STEP,
+                // the pitch stays between straight down and the horizon
+                VirtualKeyCode::Up => {
+                    pitch = (pitch + AIM_STEP).min(0.);
+                }
+                VirtualKeyCode::Down => {
+                    pitch = (pitch - AIM_STEP).max(-std::f32::consts::FRAC_PI_2);
+                }
+                VirtualKeyCode::Minus => {
+                    light.outer_angle = (light.outer_angle - CONE_STEP).max(MIN_CONE);
+                    light.inner_angle = light.outer_angle * INNER_CONE;
+                }
+                VirtualKeyCode::Equals => {
+                    light.outer_angle = (light.outer_angle + CONE_STEP).min(MAX_CONE);
+                    light.inner_angle = light.outer_angle * INNER_CONE;
+                }
+                _ => return,
+            }
+            light.direction = direction(yaw, pitch);
+            self.editor.save();
+
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        self.editor.poll();
+        watchdog::write_buffer(
+            queue,
+            &self.light_buffer,
+            0,
+            bytemuck::bytes_of(&SpotlightUniform::new(&self.editor.lights[0])),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.light_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1);
+
+            // the first 36 indices are the unit cube
+            rpass.set_pipeline(&self.light_pipeline);
+            rpass.draw_indexed(0..36, 0, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+
+    // the editor's gizmo in a layer over the scene, screenshots keep it unless told otherwise
+    fn layers() -> &'static [&'static str] {
+        &["gizmo"]
+    }
+
+    fn draw_layer(
+        &mut self,
+        _layer: &str,
+        view: &wgpu::TextureView,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        self.editor.draw(queue, &mut encoder, view, &self.camera);
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn light_editor(&mut self) -> Option<&mut LightEditor> {
+        Some(&mut self.editor)
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t24_spotlight::Spotlight>(t24_spotlight::TITLE);
+}
@@ -0,0 +1,93 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+struct Spotlight {
+    position: vec3<f32>,
+    // cosines, not angles, so the shader compares them straight against a dot product
+    inner_cos: f32,
+    // unit length, where the cone points
+    direction: vec3<f32>,
+    outer_cos: f32,
+    color: vec3<f32>,
+    intensity: f32,
+    // the light reaches 0 at this distance
+    range: f32,
+    ambient: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> light: Spotlight;
+
+let shininess = 32.0;
+
+// the geometry is already in world space, no model matrix
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    fragInput.normal = input.normal;
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let normal = normalize(input.normal);
+    let to_light = light.position - input.world_pos;
+    let light_dir = normalize(to_light);
+    let view_dir = normalize(camera.position.xyz - input.world_pos);
+
+    // 1 inside the inner cone, 0 outside the outer one, a smooth ramp in between
+    let theta = dot(-light_dir, light.direction);
+    let cone = smoothstep(light.outer_cos, light.inner_cos, theta);
+
+    let diffuse = max(dot(normal, light_dir), 0.0);
+    let half_dir = normalize(light_dir + view_dir);
+    let specular = pow(max(dot(normal, half_dir), 0.0), shininess) * step(0.0, dot(normal, light_dir));
+
+    // fades out towards the range, squared so it doesn't end in a hard edge
+    let falloff = clamp(1.0 - length(to_light) / light.range, 0.0, 1.0);
+    let reach = cone * falloff * falloff * light.intensity;
+
+    // the ambient term ignores the cone, so the scene outside it isn't pitch black
+    let color = light.ambient * input.color + reach * (diffuse * input.color + specular) * light.color;
+    return vec4<f32>(color, 1.0);
+}
+
+// the light cube, the unit cube shrunk and moved to the light, unlit in the light's color
+@vertex
+fn vs_light(input: VertexInput) -> FragInput {
+    let world_pos = input.pos * 0.15 + light.position;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = input.normal;
+    fragInput.color = light.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_light(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color, 1.0);
+}
@@ -0,0 +1,600 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    primitives::Mesh,
+    screenshot::DumpTarget,
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::{Mat4, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+// shadows from a directional light: the scene is drawn into a depth map from the light
+// first, then every fragment checks whether something was closer to the light than it
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "shadow mapping";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+const SHADOW_SIZE: u32 = 2048;
+// half the size of the light's orthographic box, it has to cover everything casting shadows
+const SHADOW_EXTENT: f32 = 6.;
+// how high the light stands, in radians above the horizon
+const LIGHT_ELEVATION: f32 = 0.9;
+const SHADOW_BIAS: f32 = 0.005;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, normal, color }
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `Light` in shader.wgsl, vec3s are 16 byte aligned so the scalars fill the gaps
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LightUniform {
+    view_proj: [[f32; 4]; 4],
+    direction: [f32; 3],
+    ambient: f32,
+    color: [f32; 3],
+    bias: f32,
+    pcf: u32,
+    _padding: [u32; 3],
+}
+
+// a directional light has no position, its shadow map comes from an orthographic box looking
+// along the light at the scene from far enough away
+fn light_view_proj(direction: Vec3) -> Mat4 {
+    let view = Mat4::look_at_rh(direction * SHADOW_EXTENT * 2., Vec3::ZERO, Vec3::Y);
+    let projection = Mat4::orthographic_rh(
+        -SHADOW_EXTENT,
+        SHADOW_EXTENT,
+        -SHADOW_EXTENT,
+        SHADOW_EXTENT,
+        0.1,
+        SHADOW_EXTENT * 4.,
+    );
+    projection * view
+}
+
+// a cube of `size` around `center`, as in t22
+fn push_cube(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    size: Vec3,
+    color: [f32; 3],
+) {
+    let cube = Mesh::cube(1).transformed(Mat4::from_translation(center) * Mat4::from_scale(size));
+    let base = vertices.len() as u16;
+    vertices.extend(cube.vertices.iter().map(|v| vertex(v.pos, v.normal, color)));
+    indices.extend(cube.indices.iter().map(|&index| base + index));
+}
+
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::ZERO,
+        Vec3::ONE,
+        [1., 0.5, 0.31],
+    );
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(-1.5, -0.25, 1.),
+        Vec3::splat(0.5),
+        [0.3, 0.6, 1.],
+    );
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(1.3, 0.3, -1.2),
+        Vec3::new(0.4, 1.6, 0.4),
+        [0.4, 1., 0.4],
+    );
+    // ground
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(0., -0.6, 0.),
+        Vec3::new(8., 0.2, 8.),
+        [0.6, 0.6, 0.6],
+    );
+
+    (vertices, indices)
+}
+
+// the shadow map, rendered from the light and sampled with depth comparison in the main pass
+fn create_shadow_texture(device: &wgpu::Device) -> (wgpu::Texture, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("shadow_texture"),
+        size: wgpu::Extent3d {
+            width: SHADOW_SIZE,
+            height: SHADOW_SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: SHADOW_FORMAT,
+        // COPY_SRC for frame dumps
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+    });
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (texture, view)
+}
+
+pub struct Shadow {
+    shadow_pipeline: wgpu::RenderPipeline,
+    pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    light: LightUniform,
+    light_buffer: wgpu::Buffer,
+    light_bindgroup: wgpu::BindGroup,
+    shadow_texture: wgpu::Texture,
+    shadow_view: wgpu::TextureView,
+    shadow_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    orbit_light: bool,
+    light_angle: f32,
+}
+
+impl Shadow {
+    fn update_light(&mut self) {
+        let (sin, cos) = self.light_angle.sin_cos();
+        let direction = Vec3::new(
+            cos * LIGHT_ELEVATION.cos(),
+            LIGHT_ELEVATION.sin(),
+            sin * LIGHT_ELEVATION.cos(),
+        );
+        self.light.direction = direction.to_array();
+        self.light.view_proj = light_view_proj(direction).to_cols_array_2d();
+    }
+
+    fn title(&self) -> String {
+        format!(
+            "shadow mapping, bias {}, {}",
+            if self.light.bias > 0. { "on" } else { "off" },
+            if self.light.pcf == 1 {
+                "pcf 3x3"
+            } else {
+                "single sample"
+            }
+        )
+    }
+}
+
+impl framework::App for Shadow {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.5,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 8., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_layout = |label, visibility, size: usize| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(size as u64),
+                    },
+                    count: None,
+                }],
+            })
+        };
+
+        let camera_bindgroup_layout = uniform_layout(
+            "camera bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<CameraUniform>(),
+        );
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // light, the direction and view_proj get filled in every frame
+        let light = LightUniform {
+            view_proj: Mat4::IDENTITY.to_cols_array_2d(),
+            direction: [0., 1., 0.],
+            ambient: 0.15,
+            color: [1., 1., 1.],
+            bias: SHADOW_BIAS,
+            pcf: 1,
+            _padding: [0; 3],
+        };
+
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::bytes_of(&light),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // the shadow pass's vertex shader reads the light's view_proj
+        let light_bindgroup_layout = uniform_layout(
+            "light bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<LightUniform>(),
+        );
+
+        let light_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("light bind group"),
+            layout: &light_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_buffer.as_entire_binding(),
+            }],
+        });
+
+        // shadow map, a depth texture plus a sampler that compares against it instead of
+        // returning the stored depth
+        let (shadow_texture, shadow_view) = create_shadow_texture(device);
+        let shadow_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let shadow_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("shadow_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                        count: None,
+                    },
+                ],
+            });
+
+        let shadow_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shadow_bind_group"),
+            layout: &shadow_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&shadow_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&shadow_sampler),
+                },
+            ],
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3, 2=>Float32x3],
+        };
+
+        // the shadow pass only writes depth, so no fragment stage and no shadow map bind group
+        let shadow_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &camera_bindgroup_layout, // group 0, camera, unused
+                    &light_bindgroup_layout,  // group 1, light
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let shadow_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("shadow"),
+            layout: Some(&shadow_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_shadow",
+                buffers: std::slice::from_ref(&vertex_buffer_layout),
+            },
+            fragment: None,
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: SHADOW_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_bindgroup_layout, // group 0, camera
+                &light_bindgroup_layout,  // group 1, light
+                &shadow_bindgroup_layout, // group 2, shadow map
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[vertex_buffer_layout],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!("press B to toggle the depth bias, P to toggle pcf, L to stop the light");
+
+        let mut shadow = Shadow {
+            shadow_pipeline,
+            pipeline,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            light,
+            light_buffer,
+            light_bindgroup,
+            shadow_texture,
+            shadow_view,
+            shadow_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            orbit_light: true,
+            light_angle: 0.5,
+        };
+        shadow.update_light();
+        shadow
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                // without bias the surfaces shadow themselves, shadow acne
+                VirtualKeyCode::B => {
+                    self.light.bias = if self.light.bias > 0. {
+                        0.
+                    } else {
+                        SHADOW_BIAS
+                    };
+                }
+                VirtualKeyCode::P => self.light.pcf = 1 - self.light.pcf,
+                VirtualKeyCode::L => self.orbit_light = !self.orbit_light,
+                _ => return,
+            }
+
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        if self.orbit_light {
+            self.light_angle += frame.delta * 0.3;
+        }
+        self.update_light();
+        watchdog::write_buffer(
+            queue,
+            &self.light_buffer,
+            0,
+            bytemuck::bytes_of(&self.light),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // pass 1, scene depth as the light sees it
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("shadow pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.shadow_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            rpass.set_pipeline(&self.shadow_pipeline);
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.light_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1);
+        }
+
+        // pass 2, the scene from the camera, every fragment checks the shadow map
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.1,
+                    g: 0.2,
+                    b: 0.3,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.light_bindgroup, &[]);
+            rpass.set_bind_group(2, &self.shadow_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+    fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        vec![DumpTarget::new(
+            "shadow map",
+            &self.shadow_texture,
+            SHADOW_SIZE,
+            SHADOW_SIZE,
+            SHADOW_FORMAT,
+        )]
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t25_shadow::Shadow>(t25_shadow::TITLE);
+}
@@ -0,0 +1,103 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+struct Light {
+    // world to the light's clip space, the shadow pass renders with it
+    view_proj: mat4x4<f32>,
+    // unit length, from the surface towards the light
+    direction: vec3<f32>,
+    ambient: f32,
+    color: vec3<f32>,
+    // the largest depth bias, used where the light grazes the surface
+    bias: f32,
+    // 0 one comparison, 1 average 3x3 comparisons
+    pcf: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> light: Light;
+
+@group(2) @binding(0)
+var shadow_map: texture_depth_2d;
+@group(2) @binding(1)
+var shadow_sampler: sampler_comparison;
+
+// shadow pass, depth only, no fragment stage
+@vertex
+fn vs_shadow(input: VertexInput) -> @builtin(position) vec4<f32> {
+    return light.view_proj * vec4<f32>(input.pos, 1.0);
+}
+
+// the geometry is already in world space, no model matrix
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    fragInput.normal = input.normal;
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+// 1 lit, 0 in shadow
+fn shadow(world_pos: vec3<f32>, bias: f32) -> f32 {
+    let clip = light.view_proj * vec4<f32>(world_pos, 1.0);
+    let ndc = clip.xyz / clip.w;
+    // ndc y points up, texture v points down
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + 0.5;
+    let depth = ndc.z - bias;
+
+    // outside the light's box nothing casts a shadow
+    if (any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || depth > 1.0) {
+        return 1.0;
+    }
+
+    // the comparison sampler returns 1 where depth <= the stored depth, filtered over 2x2
+    // texels. pcf averages a 3x3 block of those on top for softer edges
+    if (light.pcf == 0u) {
+        return textureSampleCompareLevel(shadow_map, shadow_sampler, uv, depth);
+    }
+
+    let texel = 1.0 / vec2<f32>(textureDimensions(shadow_map));
+    var lit = 0.0;
+    for (var y = -1; y <= 1; y = y + 1) {
+        for (var x = -1; x <= 1; x = x + 1) {
+            let offset = vec2<f32>(f32(x), f32(y)) * texel;
+            lit = lit + textureSampleCompareLevel(shadow_map, shadow_sampler, uv + offset, depth);
+        }
+    }
+    return lit / 9.0;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let normal = normalize(input.normal);
+    let n_dot_l = dot(normal, light.direction);
+
+    // surfaces at a grazing angle to the light need more bias to stay free of shadow acne
+    let bias = max(light.bias * (1.0 - n_dot_l), light.bias * 0.1);
+    let lit = shadow(input.world_pos, bias);
+
+    let diffuse = max(n_dot_l, 0.0) * lit;
+    let color = (light.ambient + diffuse) * light.color * input.color;
+    return vec4<f32>(color, 1.0);
+}
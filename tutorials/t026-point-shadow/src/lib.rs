@@ -0,0 +1,647 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    primitives::Mesh,
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::{Mat4, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+// shadows from a point light, which sees in every direction, so its depth goes into the six
+// faces of a cube map, looked up with the direction from the light
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "point light shadows";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const LIGHT_ORBIT_RADIUS: f32 = 1.8;
+const LIGHT_HEIGHT: f32 = 1.2;
+const LIGHT_FAR: f32 = 12.;
+const SHADOW_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+const SHADOW_SIZE: u32 = 1024;
+const SHADOW_BIAS: f32 = 0.01;
+// one face's view_proj per slot, dynamic offsets have to be aligned to 256 bytes
+const FACE_STRIDE: wgpu::BufferAddress = 256;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, normal, color }
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `Light` in shader.wgsl and shadow.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LightUniform {
+    position: [f32; 3],
+    far: f32,
+    color: [f32; 3],
+    ambient: f32,
+    bias: f32,
+    _padding: [f32; 3],
+}
+
+// view_proj for each cube face from `position`, in layer order +x, -x, +y, -y, +z, -z.
+// the face's up vectors follow the cube map convention, where t grows downwards. wgpu writes
+// clip space +y to the first row, so y is flipped as well to put the rows the way the lookup
+// expects them. that flip also mirrors the winding, the shadow pass doesn't cull
+fn face_view_projs(position: Vec3) -> [Mat4; 6] {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Y),
+        (Vec3::NEG_X, Vec3::NEG_Y),
+        (Vec3::Y, Vec3::Z),
+        (Vec3::NEG_Y, Vec3::NEG_Z),
+        (Vec3::Z, Vec3::NEG_Y),
+        (Vec3::NEG_Z, Vec3::NEG_Y),
+    ];
+
+    let flip_y = Mat4::from_scale(Vec3::new(1., -1., 1.));
+    let projection = Mat4::perspective_rh(std::f32::consts::FRAC_PI_2, 1., 0.05, LIGHT_FAR);
+
+    faces.map(|(forward, up)| {
+        flip_y * projection * Mat4::look_at_rh(position, position + forward, up)
+    })
+}
+
+// a cube of `size` around `center`, as in t22
+fn push_cube(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    size: Vec3,
+    color: [f32; 3],
+) {
+    let cube = Mesh::cube(1).transformed(Mat4::from_translation(center) * Mat4::from_scale(size));
+    let base = vertices.len() as u16;
+    vertices.extend(cube.vertices.iter().map(|v| vertex(v.pos, v.normal, color)));
+    indices.extend(cube.indices.iter().map(|&index| base + index));
+}
+
+// the unit cube comes first, the light cube reuses its 36 indices
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::ZERO,
+        Vec3::ONE,
+        [1., 0.5, 0.31],
+    );
+
+    // pillars around the light, their shadows fall outwards in every direction
+    for i in 0..8 {
+        let angle = i as f32 / 8. * std::f32::consts::TAU;
+        push_cube(
+            &mut vertices,
+            &mut indices,
+            Vec3::new(angle.cos() * 3.5, 0.5, angle.sin() * 3.5),
+            Vec3::new(0.4, 2., 0.4),
+            [0.4, 0.6, 1.],
+        );
+    }
+
+    // floating above the light, so the +y face has something to catch
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(0., 3., 0.),
+        Vec3::new(1.5, 0.2, 1.5),
+        [0.4, 1., 0.4],
+    );
+
+    // ground
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(0., -0.6, 0.),
+        Vec3::new(12., 0.2, 12.),
+        [0.6, 0.6, 0.6],
+    );
+
+    (vertices, indices)
+}
+
+// one depth texture, six layers, one per cube face
+fn create_shadow_texture(device: &wgpu::Device) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("shadow_texture"),
+        size: wgpu::Extent3d {
+            width: SHADOW_SIZE,
+            height: SHADOW_SIZE,
+            depth_or_array_layers: 6,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: SHADOW_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    })
+}
+
+pub struct PointShadow {
+    shadow_pipeline: wgpu::RenderPipeline,
+    pipeline: wgpu::RenderPipeline,
+    light_pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    light: LightUniform,
+    light_buffer: wgpu::Buffer,
+    light_bindgroup: wgpu::BindGroup,
+    faces_buffer: wgpu::Buffer,
+    faces_bindgroup: wgpu::BindGroup,
+    // render targets, one 2d view per layer
+    face_views: Vec<wgpu::TextureView>,
+    shadow_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    orbit_light: bool,
+    light_angle: f32,
+}
+
+impl framework::App for PointShadow {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.6,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 9., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_layout = |label, visibility, has_dynamic_offset, size: u64| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset,
+                        min_binding_size: wgpu::BufferSize::new(size),
+                    },
+                    count: None,
+                }],
+            })
+        };
+
+        let camera_bindgroup_layout = uniform_layout(
+            "camera bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            false,
+            std::mem::size_of::<CameraUniform>() as u64,
+        );
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // light, shared by the shadow pass and the main pass
+        let light = LightUniform {
+            position: [LIGHT_ORBIT_RADIUS, LIGHT_HEIGHT, 0.],
+            far: LIGHT_FAR,
+            color: [1., 1., 1.],
+            ambient: 0.05,
+            bias: SHADOW_BIAS,
+            _padding: [0.; 3],
+        };
+
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::bytes_of(&light),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let light_bindgroup_layout = uniform_layout(
+            "light bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            false,
+            std::mem::size_of::<LightUniform>() as u64,
+        );
+
+        let light_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("light bind group"),
+            layout: &light_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_buffer.as_entire_binding(),
+            }],
+        });
+
+        // the six face matrices in one buffer, each pass binds its own slot with a dynamic offset
+        let faces_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Faces Buffer"),
+            size: FACE_STRIDE * 6,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let faces_bindgroup_layout = uniform_layout(
+            "faces bindgroup layout",
+            wgpu::ShaderStages::VERTEX,
+            true,
+            std::mem::size_of::<Mat4>() as u64,
+        );
+
+        let faces_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("faces bind group"),
+            layout: &faces_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+                    buffer: &faces_buffer,
+                    offset: 0,
+                    size: wgpu::BufferSize::new(std::mem::size_of::<Mat4>() as u64),
+                }),
+            }],
+        });
+
+        // shadow map, rendered through one 2d view per layer and sampled through a cube view
+        let shadow_texture = create_shadow_texture(device);
+        let face_views = (0..6)
+            .map(|layer| {
+                shadow_texture.create_view(&wgpu::TextureViewDescriptor {
+                    label: Some("shadow face"),
+                    dimension: Some(wgpu::TextureViewDimension::D2),
+                    base_array_layer: layer,
+                    array_layer_count: std::num::NonZeroU32::new(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        let shadow_cube_view = shadow_texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("shadow cube"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+
+        let shadow_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("shadow sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+
+        let shadow_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("shadow_bind_group_layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Depth,
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                        count: None,
+                    },
+                ],
+            });
+
+        let shadow_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("shadow_bind_group"),
+            layout: &shadow_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&shadow_cube_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&shadow_sampler),
+                },
+            ],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3, 2=>Float32x3],
+        };
+
+        // shadow pass
+        let shadow_shader = device.create_shader_module(include_wgsl!("shadow.wgsl"));
+
+        let shadow_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[
+                    &light_bindgroup_layout, // group 0, light
+                    &faces_bindgroup_layout, // group 1, face view_proj
+                ],
+                push_constant_ranges: &[],
+            });
+
+        let shadow_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("shadow"),
+            layout: Some(&shadow_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shadow_shader,
+                entry_point: "vs_main",
+                buffers: std::slice::from_ref(&vertex_buffer_layout),
+            },
+            // no color targets, the fragment stage only writes depth
+            fragment: Some(wgpu::FragmentState {
+                module: &shadow_shader,
+                entry_point: "fs_main",
+                targets: &[],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: SHADOW_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // main pass
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_bindgroup_layout, // group 0, camera
+                &light_bindgroup_layout,  // group 1, light
+                &shadow_bindgroup_layout, // group 2, shadow cube
+            ],
+            push_constant_ranges: &[],
+        });
+
+        // lit geometry and the light cube only differ in their entry points
+        let create_pipeline = |vs_entry_point, fs_entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(fs_entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vs_entry_point,
+                    buffers: std::slice::from_ref(&vertex_buffer_layout),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs_entry_point,
+                    targets: &[Some(config.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DepthTexture::FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline("vs_main", "fs_main");
+        let light_pipeline = create_pipeline("vs_light", "fs_light");
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!("press B to toggle the depth bias, L to stop the light");
+
+        PointShadow {
+            shadow_pipeline,
+            pipeline,
+            light_pipeline,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            light,
+            light_buffer,
+            light_bindgroup,
+            faces_buffer,
+            faces_bindgroup,
+            face_views,
+            shadow_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            orbit_light: true,
+            light_angle: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                // without bias the surfaces shadow themselves, shadow acne
+                VirtualKeyCode::B => {
+                    self.light.bias = if self.light.bias > 0. {
+                        0.
+                    } else {
+                        SHADOW_BIAS
+                    };
+                }
+                VirtualKeyCode::L => self.orbit_light = !self.orbit_light,
+                _ => return,
+            }
+
+            let title = format!(
+                "point light shadows, bias {}",
+                if self.light.bias > 0. { "on" } else { "off" }
+            );
+            window.set_title(&title);
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        if self.orbit_light {
+            self.light_angle += frame.delta * 0.5;
+        }
+        let (sin, cos) = self.light_angle.sin_cos();
+        let position = Vec3::new(
+            cos * LIGHT_ORBIT_RADIUS,
+            LIGHT_HEIGHT + (self.light_angle * 2.).sin() * 0.5,
+            sin * LIGHT_ORBIT_RADIUS,
+        );
+        self.light.position = position.to_array();
+        watchdog::write_buffer(
+            queue,
+            &self.light_buffer,
+            0,
+            bytemuck::bytes_of(&self.light),
+        );
+
+        for (face, view_proj) in face_view_projs(position).iter().enumerate() {
+            watchdog::write_buffer(
+                queue,
+                &self.faces_buffer,
+                face as wgpu::BufferAddress * FACE_STRIDE,
+                bytemuck::cast_slice(view_proj.as_ref()),
+            );
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // pass 1, six times, the scene's distance to the light into each cube face
+        for (face, face_view) in self.face_views.iter().enumerate() {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("shadow pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: face_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            let offset = face as wgpu::DynamicOffset * FACE_STRIDE as wgpu::DynamicOffset;
+            rpass.set_pipeline(&self.shadow_pipeline);
+            rpass.set_bind_group(0, &self.light_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.faces_bindgroup, &[offset]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1);
+        }
+
+        // pass 2, the scene from the camera, every fragment looks up the cube in its direction
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.light_bindgroup, &[]);
+            rpass.set_bind_group(2, &self.shadow_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1);
+
+            // the first 36 indices are the unit cube
+            rpass.set_pipeline(&self.light_pipeline);
+            rpass.draw_indexed(0..36, 0, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t26_point_shadow::PointShadow>(t26_point_shadow::TITLE);
+}
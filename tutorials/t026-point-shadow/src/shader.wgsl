@@ -0,0 +1,84 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+// same as in shadow.wgsl
+struct Light {
+    position: vec3<f32>,
+    far: f32,
+    color: vec3<f32>,
+    ambient: f32,
+    bias: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> light: Light;
+
+// one depth texture with six layers, viewed as a cube so a direction picks the texel
+@group(2) @binding(0)
+var shadow_map: texture_depth_cube;
+@group(2) @binding(1)
+var shadow_sampler: sampler_comparison;
+
+// the geometry is already in world space, no model matrix
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    fragInput.normal = input.normal;
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let normal = normalize(input.normal);
+    let from_light = input.world_pos - light.position;
+    let distance = length(from_light);
+    let light_dir = -from_light / distance;
+
+    // the stored distance along the same direction, 1 when this fragment is no further away
+    let lit = textureSampleCompareLevel(shadow_map, shadow_sampler, from_light, distance / light.far - light.bias);
+
+    let attenuation = clamp(1.0 - distance / light.far, 0.0, 1.0);
+    let diffuse = max(dot(normal, light_dir), 0.0) * attenuation * lit;
+    let color = (light.ambient + diffuse) * light.color * input.color;
+    return vec4<f32>(color, 1.0);
+}
+
+// the light cube, the unit cube shrunk and moved to the light, unlit in the light's color
+@vertex
+fn vs_light(input: VertexInput) -> FragInput {
+    let world_pos = input.pos * 0.15 + light.position;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = input.normal;
+    fragInput.color = light.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_light(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color, 1.0);
+}
@@ -0,0 +1,42 @@
+// shadow pass, run once per cube face. writes the distance to the light instead of the
+// projected depth, so all six faces share one scale and the lookup needs no face matrices
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Light {
+    position: vec3<f32>,
+    // distances are stored divided by this, anything past it is never in shadow
+    far: f32,
+    color: vec3<f32>,
+    ambient: f32,
+    bias: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> light: Light;
+
+// the current face's view_proj, picked with a dynamic offset
+@group(1) @binding(0)
+var<uniform> face_view_proj: mat4x4<f32>;
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = face_view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @builtin(frag_depth) f32 {
+    return length(input.world_pos - light.position) / light.far;
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t27_normal_map::NormalMap>(t27_normal_map::TITLE);
+}
@@ -0,0 +1,102 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tangent: vec3<f32>,
+    @location(3) bitangent: vec3<f32>,
+    @location(4) uv: vec2<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tangent: vec3<f32>,
+    @location(3) bitangent: vec3<f32>,
+    @location(4) uv: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+struct Light {
+    position: vec3<f32>,
+    ambient: f32,
+    color: vec3<f32>,
+    // 0 uses the vertex normal, 1 the normal map
+    normal_mapping: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> light: Light;
+
+@group(2) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(2) @binding(1)
+var t_normal: texture_2d<f32>;
+@group(2) @binding(2)
+var s_material: sampler;
+
+let SHININESS: f32 = 32.0;
+let SPECULAR: f32 = 0.3;
+
+// the geometry is already in world space, no model matrix
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    fragInput.normal = input.normal;
+    fragInput.tangent = input.tangent;
+    fragInput.bitangent = input.bitangent;
+    fragInput.uv = input.uv;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    var normal = normalize(input.normal);
+    if (light.normal_mapping != 0u) {
+        // columns are where the map's x (+u), y (+v) and z (out of the surface) point in the world
+        let tbn = mat3x3<f32>(normalize(input.tangent), normalize(input.bitangent), normal);
+        // stored as 0..1, unpack to -1..1
+        let mapped = textureSample(t_normal, s_material, input.uv).xyz * 2.0 - 1.0;
+        normal = normalize(tbn * mapped);
+    }
+
+    let light_dir = normalize(light.position - input.world_pos);
+    let view_dir = normalize(camera.position.xyz - input.world_pos);
+    let half_dir = normalize(light_dir + view_dir);
+
+    let diffuse = max(dot(normal, light_dir), 0.0);
+    let specular = SPECULAR * pow(max(dot(normal, half_dir), 0.0), SHININESS) * step(0.0, dot(normal, light_dir));
+
+    let albedo = textureSample(t_diffuse, s_material, input.uv).rgb;
+    let color = (light.ambient + diffuse) * light.color * albedo + specular * light.color;
+    return vec4<f32>(color, 1.0);
+}
+
+// the light cube, the unit cube shrunk and moved to the light, unlit in the light's color
+@vertex
+fn vs_light(input: VertexInput) -> FragInput {
+    let world_pos = input.pos * 0.15 + light.position;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = input.normal;
+    fragInput.tangent = input.tangent;
+    fragInput.bitangent = input.bitangent;
+    fragInput.uv = input.uv;
+    return fragInput;
+}
+
+@fragment
+fn fs_light(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(light.color, 1.0);
+}
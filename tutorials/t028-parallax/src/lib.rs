@@ -0,0 +1,653 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    texture::{DepthTexture, Texture},
+    watchdog,
+};
+use glam::{Vec2, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+// parallax occlusion mapping: the uvs are pushed along the view ray through a height map
+// until it hits, so the bricks stand out of the wall when looked at from the side
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "parallax occlusion mapping";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const LIGHT_ORBIT_RADIUS: f32 = 2.;
+const LIGHT_HEIGHT: f32 = 1.;
+// brick texture, a whole number of bricks across so it tiles
+const TEXTURE_SIZE: u32 = 256;
+const BRICK_WIDTH: u32 = 64;
+const BRICK_HEIGHT: u32 = 32;
+// in texels, flat mortar then a slope up to the brick face
+const MORTAR: f32 = 2.;
+const BEVEL: f32 = 5.;
+// steepness of the normals derived from the height
+const BUMP_STRENGTH: f32 = 3.;
+// world units covered by one copy of the texture
+const TEXTURE_WORLD_SIZE: f32 = 1.;
+// depth of the mortar in uv units, the same as world units here
+const HEIGHT_SCALE: f32 = 0.04;
+const MAX_STEPS: u32 = 64;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    // where +u and +v of the texture point on the surface, filled in by `compute_tangents`
+    tangent: [f32; 3],
+    bitangent: [f32; 3],
+    uv: [f32; 2],
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `Light` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LightUniform {
+    position: [f32; 3],
+    ambient: f32,
+    color: [f32; 3],
+    _padding: u32,
+}
+
+// matches `Parallax` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ParallaxUniform {
+    height_scale: f32,
+    steps: u32,
+    enabled: u32,
+    _padding: u32,
+}
+
+// a cube of `size` around `center`, the uvs follow world units so bricks keep their size
+fn push_cube(vertices: &mut Vec<Vertex>, indices: &mut Vec<u16>, center: Vec3, size: Vec3) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+
+    for (normal, u, v) in faces {
+        let base = vertices.len() as u16;
+        let extent = Vec2::new((u * size).length(), (v * size).length()) / TEXTURE_WORLD_SIZE;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = center + (normal + u * su + v * sv) * 0.5 * size;
+            // v runs down the image, against the face's up axis
+            let uv = Vec2::new((su + 1.) * 0.5, (1. - sv) * 0.5) * extent;
+            vertices.push(Vertex {
+                pos: pos.to_array(),
+                normal: normal.to_array(),
+                tangent: [0.; 3],
+                bitangent: [0.; 3],
+                uv: uv.to_array(),
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+}
+
+// per triangle, solve edge = du * tangent + dv * bitangent for the directions u and v run
+// along, then average them over the triangles sharing a vertex
+fn compute_tangents(vertices: &mut [Vertex], indices: &[u16]) {
+    let mut tangents = vec![Vec3::ZERO; vertices.len()];
+    let mut bitangents = vec![Vec3::ZERO; vertices.len()];
+
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| vertices[i as usize]);
+        let edge1 = Vec3::from(b.pos) - Vec3::from(a.pos);
+        let edge2 = Vec3::from(c.pos) - Vec3::from(a.pos);
+        let duv1 = Vec2::from(b.uv) - Vec2::from(a.uv);
+        let duv2 = Vec2::from(c.uv) - Vec2::from(a.uv);
+
+        let det = duv1.x * duv2.y - duv2.x * duv1.y;
+        // uvs collapsed to a line, the triangle says nothing about the directions
+        if det.abs() < f32::EPSILON {
+            continue;
+        }
+        let tangent = (edge1 * duv2.y - edge2 * duv1.y) / det;
+        let bitangent = (edge2 * duv1.x - edge1 * duv2.x) / det;
+
+        for &i in triangle {
+            tangents[i as usize] += tangent;
+            bitangents[i as usize] += bitangent;
+        }
+    }
+
+    for ((vertex, tangent), bitangent) in vertices.iter_mut().zip(tangents).zip(bitangents) {
+        let normal = Vec3::from(vertex.normal);
+        // averaging skews the tangent, make it perpendicular to the normal again
+        let tangent = (tangent - normal * normal.dot(tangent)).normalize_or_zero();
+        // rebuilt from the other two, only the handedness comes from the triangles
+        let cross = normal.cross(tangent);
+        let bitangent = if cross.dot(bitangent) < 0. {
+            -cross
+        } else {
+            cross
+        };
+        vertex.tangent = tangent.to_array();
+        vertex.bitangent = bitangent.to_array();
+    }
+}
+
+// the unit cube comes first, the light cube reuses its 36 indices
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    push_cube(&mut vertices, &mut indices, Vec3::ZERO, Vec3::ONE);
+    // wall
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(0., 0.9, -2.6),
+        Vec3::new(6., 3., 0.2),
+    );
+    // ground
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(0., -0.6, 0.),
+        Vec3::new(6., 0.2, 6.),
+    );
+
+    compute_tangents(&mut vertices, &indices);
+
+    (vertices, indices)
+}
+
+fn hash(a: u32, b: u32) -> f32 {
+    let mut h = a.wrapping_mul(0x27d4_eb2d) ^ b.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    (h & 0xffff) as f32 / 65535.
+}
+
+// (row, column) of the brick under a texel and the height there, 0 in the mortar and 1 on
+// the brick face. every other row is shifted by half a brick
+fn brick(x: u32, y: u32) -> ((u32, u32), f32) {
+    let row = y / BRICK_HEIGHT;
+    let x = (x + row % 2 * BRICK_WIDTH / 2) % TEXTURE_SIZE;
+    let (bx, by) = (x % BRICK_WIDTH, y % BRICK_HEIGHT);
+    // distance to the nearest edge of the brick
+    let edge = bx
+        .min(BRICK_WIDTH - 1 - bx)
+        .min(by.min(BRICK_HEIGHT - 1 - by)) as f32;
+    let height = ((edge - MORTAR) / BEVEL).clamp(0., 1.);
+    ((row, x / BRICK_WIDTH), height)
+}
+
+// diffuse color and a tangent space normal map, x along +u, y along +v and z out of the
+// surface, derived from the slope of the brick height. the height itself goes in alpha
+fn create_brick_images() -> (image::RgbaImage, image::RgbaImage) {
+    let height = |x: i32, y: i32| {
+        let size = TEXTURE_SIZE as i32;
+        brick(x.rem_euclid(size) as u32, y.rem_euclid(size) as u32).1
+    };
+
+    let diffuse = image::RgbaImage::from_fn(TEXTURE_SIZE, TEXTURE_SIZE, |x, y| {
+        let ((row, column), h) = brick(x, y);
+        let color = if h > 0. {
+            let shade = 0.8 + 0.4 * hash(row, column);
+            [150. * shade, 60. * shade, 40. * shade]
+        } else {
+            [160., 155., 145.]
+        };
+        image::Rgba([color[0] as u8, color[1] as u8, color[2] as u8, 255])
+    });
+
+    let normal = image::RgbaImage::from_fn(TEXTURE_SIZE, TEXTURE_SIZE, |x, y| {
+        let (x, y) = (x as i32, y as i32);
+        let dx = height(x + 1, y) - height(x - 1, y);
+        let dy = height(x, y + 1) - height(x, y - 1);
+        // tilts away from where the surface rises
+        let n = Vec3::new(-dx * BUMP_STRENGTH, -dy * BUMP_STRENGTH, 1.).normalize();
+        let encoded = (n * 0.5 + 0.5) * 255.;
+        let h = height(x, y) * 255.;
+        image::Rgba([encoded.x as u8, encoded.y as u8, encoded.z as u8, h as u8])
+    });
+
+    (diffuse, normal)
+}
+
+pub struct Parallax {
+    pipeline: wgpu::RenderPipeline,
+    light_pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    light: LightUniform,
+    light_buffer: wgpu::Buffer,
+    light_bindgroup: wgpu::BindGroup,
+    material_bindgroup: wgpu::BindGroup,
+    parallax: ParallaxUniform,
+    parallax_buffer: wgpu::Buffer,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    orbit_light: bool,
+    light_angle: f32,
+}
+
+impl framework::App for Parallax {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.3,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 5., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_layout = |label, visibility, size: usize| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(size as u64),
+                    },
+                    count: None,
+                }],
+            })
+        };
+
+        let camera_bindgroup_layout = uniform_layout(
+            "camera bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<CameraUniform>(),
+        );
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // light
+        let light = LightUniform {
+            position: [LIGHT_ORBIT_RADIUS, LIGHT_HEIGHT, 0.],
+            ambient: 0.1,
+            color: [1., 1., 1.],
+            _padding: 0,
+        };
+
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::bytes_of(&light),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // the light cube's vertex shader reads the light position too
+        let light_bindgroup_layout = uniform_layout(
+            "light bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<LightUniform>(),
+        );
+
+        let light_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("light bind group"),
+            layout: &light_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: light_buffer.as_entire_binding(),
+            }],
+        });
+
+        // material, the normal map holds directions rather than colors so it must not be srgb
+        let (diffuse_img, normal_img) = create_brick_images();
+        let diffuse = Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(diffuse_img),
+            "diffuse_texture",
+        );
+        let normal = Texture::from_image_format(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(normal_img),
+            wgpu::TextureFormat::Rgba8Unorm,
+            "normal_texture",
+        );
+        // the uvs go past 1, the bricks repeat
+        let material_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("material sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let parallax = ParallaxUniform {
+            height_scale: HEIGHT_SCALE,
+            steps: 16,
+            enabled: 1,
+            _padding: 0,
+        };
+        let parallax_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Parallax Buffer"),
+            contents: bytemuck::bytes_of(&parallax),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let material_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("material bindgroup layout"),
+                entries: &[
+                    texture_entry(0),
+                    texture_entry(1),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(std::mem::size_of::<
+                                ParallaxUniform,
+                            >()
+                                as u64),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let material_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("material bind group"),
+            layout: &material_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&diffuse.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&normal.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&material_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: parallax_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_bindgroup_layout,   // group 0, camera
+                &light_bindgroup_layout,    // group 1, light
+                &material_bindgroup_layout, // group 2, diffuse, normal / height map, parallax
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![
+                0=>Float32x3, 1=>Float32x3, 2=>Float32x3, 3=>Float32x3, 4=>Float32x2
+            ],
+        };
+
+        // lit geometry and the light cube only differ in their entry points
+        let create_pipeline = |vs_entry_point, fs_entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(fs_entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vs_entry_point,
+                    buffers: std::slice::from_ref(&vertex_buffer_layout),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs_entry_point,
+                    targets: &[Some(config.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DepthTexture::FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline("vs_main", "fs_main");
+        let light_pipeline = create_pipeline("vs_light", "fs_light");
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!(
+            "press P to switch parallax on / off, up/down for the step count, L to stop the light"
+        );
+
+        Parallax {
+            pipeline,
+            light_pipeline,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            light,
+            light_buffer,
+            light_bindgroup,
+            material_bindgroup,
+            parallax,
+            parallax_buffer,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            orbit_light: true,
+            light_angle: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::P => self.parallax.enabled = 1 - self.parallax.enabled,
+                VirtualKeyCode::L => self.orbit_light = !self.orbit_light,
+                VirtualKeyCode::Up => {
+                    self.parallax.steps = (self.parallax.steps * 2).min(MAX_STEPS)
+                }
+                VirtualKeyCode::Down => self.parallax.steps = (self.parallax.steps / 2).max(1),
+                _ => return,
+            }
+
+            let title = if self.parallax.enabled == 1 {
+                format!("parallax occlusion mapping, {} steps", self.parallax.steps)
+            } else {
+                "normal mapping".to_string()
+            };
+            window.set_title(&title);
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        if self.orbit_light {
+            self.light_angle += frame.delta;
+        }
+        let (sin, cos) = self.light_angle.sin_cos();
+        self.light.position = [
+            cos * LIGHT_ORBIT_RADIUS,
+            LIGHT_HEIGHT,
+            sin * LIGHT_ORBIT_RADIUS,
+        ];
+        watchdog::write_buffer(
+            queue,
+            &self.light_buffer,
+            0,
+            bytemuck::bytes_of(&self.light),
+        );
+        watchdog::write_buffer(
+            queue,
+            &self.parallax_buffer,
+            0,
+            bytemuck::bytes_of(&self.parallax),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.02,
+                    g: 0.02,
+                    b: 0.05,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.light_bindgroup, &[]);
+            rpass.set_bind_group(2, &self.material_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1);
+
+            // the first 36 indices are the unit cube
+            rpass.set_pipeline(&self.light_pipeline);
+            rpass.draw_indexed(0..36, 0, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
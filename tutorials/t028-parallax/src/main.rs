@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t28_parallax::Parallax>(t28_parallax::TITLE);
+}
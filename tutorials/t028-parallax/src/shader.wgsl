@@ -0,0 +1,149 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tangent: vec3<f32>,
+    @location(3) bitangent: vec3<f32>,
+    @location(4) uv: vec2<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tangent: vec3<f32>,
+    @location(3) bitangent: vec3<f32>,
+    @location(4) uv: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+struct Light {
+    position: vec3<f32>,
+    ambient: f32,
+    color: vec3<f32>,
+};
+
+struct Parallax {
+    // how deep the mortar goes, in uv units
+    height_scale: f32,
+    // layers the view ray is split into
+    steps: u32,
+    // 0 plain normal mapping, 1 parallax occlusion mapping
+    enabled: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> light: Light;
+
+@group(2) @binding(0)
+var t_diffuse: texture_2d<f32>;
+// tangent space normal in rgb, height in a
+@group(2) @binding(1)
+var t_normal: texture_2d<f32>;
+@group(2) @binding(2)
+var s_material: sampler;
+@group(2) @binding(3)
+var<uniform> parallax: Parallax;
+
+let SHININESS: f32 = 32.0;
+let SPECULAR: f32 = 0.3;
+
+// the geometry is already in world space, no model matrix
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    fragInput.normal = input.normal;
+    fragInput.tangent = input.tangent;
+    fragInput.bitangent = input.bitangent;
+    fragInput.uv = input.uv;
+    return fragInput;
+}
+
+// the loop exits at a different step per pixel, so samples take explicit gradients instead of
+// the implicit ones textureSample needs
+fn depth_at(uv: vec2<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> f32 {
+    return 1.0 - textureSampleGrad(t_normal, s_material, uv, ddx, ddy).a;
+}
+
+// step along the view ray one depth layer at a time until it is below the height field, then
+// place the hit between the last two layers
+fn parallax_uv(uv: vec2<f32>, view_dir: vec3<f32>, ddx: vec2<f32>, ddy: vec2<f32>) -> vec2<f32> {
+    let layer_depth = 1.0 / f32(parallax.steps);
+    // uv shift per layer, grows at grazing angles, clamped so it doesn't blow up at the horizon
+    let delta = view_dir.xy / max(view_dir.z, 0.1) * parallax.height_scale * layer_depth;
+
+    var current_uv = uv;
+    var current_depth = 0.0;
+    var surface_depth = depth_at(current_uv, ddx, ddy);
+    for (var i = 0u; i < parallax.steps; i = i + 1u) {
+        if (current_depth >= surface_depth) {
+            break;
+        }
+        current_uv = current_uv - delta;
+        current_depth = current_depth + layer_depth;
+        surface_depth = depth_at(current_uv, ddx, ddy);
+    }
+
+    // below the surface by `after`, above it by `before` one layer earlier
+    let previous_uv = current_uv + delta;
+    let after = surface_depth - current_depth;
+    let before = depth_at(previous_uv, ddx, ddy) - current_depth + layer_depth;
+    return mix(current_uv, previous_uv, after / (after - before));
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let vertex_normal = normalize(input.normal);
+    let tbn = mat3x3<f32>(normalize(input.tangent), normalize(input.bitangent), vertex_normal);
+    let view_dir = normalize(camera.position.xyz - input.world_pos);
+    let ddx = dpdx(input.uv);
+    let ddy = dpdy(input.uv);
+
+    var uv = input.uv;
+    if (parallax.enabled != 0u) {
+        // tbn is orthonormal, its transpose takes world directions into tangent space
+        uv = parallax_uv(input.uv, transpose(tbn) * view_dir, ddx, ddy);
+    }
+
+    let mapped = textureSampleGrad(t_normal, s_material, uv, ddx, ddy).xyz * 2.0 - 1.0;
+    let normal = normalize(tbn * mapped);
+
+    let light_dir = normalize(light.position - input.world_pos);
+    let half_dir = normalize(light_dir + view_dir);
+
+    let diffuse = max(dot(normal, light_dir), 0.0);
+    let specular = SPECULAR * pow(max(dot(normal, half_dir), 0.0), SHININESS) * step(0.0, dot(normal, light_dir));
+
+    let albedo = textureSampleGrad(t_diffuse, s_material, uv, ddx, ddy).rgb;
+    let color = (light.ambient + diffuse) * light.color * albedo + specular * light.color;
+    return vec4<f32>(color, 1.0);
+}
+
+// the light cube, the unit cube shrunk and moved to the light, unlit in the light's color
+@vertex
+fn vs_light(input: VertexInput) -> FragInput {
+    let world_pos = input.pos * 0.15 + light.position;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = input.normal;
+    fragInput.tangent = input.tangent;
+    fragInput.bitangent = input.bitangent;
+    fragInput.uv = input.uv;
+    return fragInput;
+}
+
+@fragment
+fn fs_light(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(light.color, 1.0);
+}
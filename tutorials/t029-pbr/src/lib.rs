@@ -0,0 +1,609 @@
+use std::f32::consts::PI;
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    texture::{DepthTexture, Texture},
+    watchdog,
+};
+use glam::{Vec2, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "pbr";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+// GRID_SIZE * GRID_SIZE spheres, metallic rises by row and roughness by column
+const GRID_SIZE: u32 = 7;
+const GRID_SPACING: f32 = 2.5;
+const SPHERE_SECTORS: u32 = 64;
+const SPHERE_STACKS: u32 = 32;
+// copies of the texture around and down the sphere
+const UV_REPEAT: Vec2 = Vec2::new(4., 2.);
+// panel texture, a whole number of panels across so it tiles
+const TEXTURE_SIZE: u32 = 128;
+const PANEL_SIZE: u32 = 32;
+// in texels, width of the slope down into the groove between panels
+const GROOVE: f32 = 2.;
+const BUMP_STRENGTH: f32 = 2.;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    // where +u and +v of the texture point on the surface
+    tangent: [f32; 3],
+    bitangent: [f32; 3],
+    uv: [f32; 2],
+}
+
+// per sphere factors, the textures scale them
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Instance {
+    offset: [f32; 3],
+    albedo: [f32; 3],
+    metallic: f32,
+    roughness: f32,
+}
+
+// matches `Camera` in shader.wgsl, the eye position is needed for the specular term
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `PointLight` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct PointLight {
+    position: [f32; 4],
+    color: [f32; 4],
+}
+
+// matches `Scene` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct SceneUniform {
+    lights: [PointLight; 4],
+    textured: u32,
+    _padding: [u32; 3],
+}
+
+// unit sphere, theta runs down from the north pole and phi around the y axis
+fn create_sphere() -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for stack in 0..=SPHERE_STACKS {
+        let v = stack as f32 / SPHERE_STACKS as f32;
+        let (sin_theta, cos_theta) = (v * PI).sin_cos();
+        for sector in 0..=SPHERE_SECTORS {
+            let u = sector as f32 / SPHERE_SECTORS as f32;
+            let (sin_phi, cos_phi) = (u * 2. * PI).sin_cos();
+
+            let normal = Vec3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+            // u is flipped below so the texture isn't mirrored seen from outside, the tangent
+            // follows it
+            let tangent = Vec3::new(sin_phi, 0., -cos_phi);
+            let bitangent = Vec3::new(cos_theta * cos_phi, -sin_theta, cos_theta * sin_phi);
+            vertices.push(Vertex {
+                pos: normal.to_array(),
+                normal: normal.to_array(),
+                tangent: tangent.to_array(),
+                bitangent: bitangent.to_array(),
+                uv: (Vec2::new(1. - u, v) * UV_REPEAT).to_array(),
+            });
+        }
+    }
+
+    // the rows at the poles collapse into points, their triangles have no area and get culled
+    for stack in 0..SPHERE_STACKS {
+        for sector in 0..SPHERE_SECTORS {
+            let top = (stack * (SPHERE_SECTORS + 1) + sector) as u16;
+            let bottom = top + SPHERE_SECTORS as u16 + 1;
+            indices.extend_from_slice(&[top, top + 1, bottom, top + 1, bottom + 1, bottom]);
+        }
+    }
+
+    (vertices, indices)
+}
+
+fn create_instances() -> Vec<Instance> {
+    let half = (GRID_SIZE - 1) as f32 / 2.;
+    (0..GRID_SIZE * GRID_SIZE)
+        .map(|i| {
+            let (row, column) = (i / GRID_SIZE, i % GRID_SIZE);
+            Instance {
+                offset: [
+                    (column as f32 - half) * GRID_SPACING,
+                    (row as f32 - half) * GRID_SPACING,
+                    0.,
+                ],
+                albedo: [0.8, 0.25, 0.2],
+                metallic: row as f32 / (GRID_SIZE - 1) as f32,
+                roughness: column as f32 / (GRID_SIZE - 1) as f32,
+            }
+        })
+        .collect()
+}
+
+fn hash(a: u32, b: u32) -> f32 {
+    let mut h = a.wrapping_mul(0x27d4_eb2d) ^ b.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    (h & 0xffff) as f32 / 65535.
+}
+
+// 0 at the bottom of the groove between panels, 1 on the panel
+fn panel_height(x: u32, y: u32) -> f32 {
+    let (px, py) = (x % PANEL_SIZE, y % PANEL_SIZE);
+    let edge = px.min(PANEL_SIZE - 1 - px).min(py.min(PANEL_SIZE - 1 - py)) as f32;
+    (edge / GROOVE).min(1.)
+}
+
+fn gray(value: f32) -> image::Rgba<u8> {
+    let value = (value.clamp(0., 1.) * 255.) as u8;
+    image::Rgba([value, value, value, 255])
+}
+
+struct MaterialImages {
+    albedo: image::RgbaImage,
+    metallic: image::RgbaImage,
+    roughness: image::RgbaImage,
+    normal: image::RgbaImage,
+    ao: image::RgbaImage,
+}
+
+// metal panels, each a little rougher or smoother than the next, with dark dielectric grooves
+// between them that the ambient light doesn't reach
+fn create_material_images() -> MaterialImages {
+    let height = |x: i32, y: i32| {
+        let size = TEXTURE_SIZE as i32;
+        panel_height(x.rem_euclid(size) as u32, y.rem_euclid(size) as u32)
+    };
+    let create = |f: &dyn Fn(u32, u32) -> image::Rgba<u8>| {
+        image::RgbaImage::from_fn(TEXTURE_SIZE, TEXTURE_SIZE, f)
+    };
+
+    MaterialImages {
+        albedo: create(&|x, y| gray(0.3 + 0.7 * panel_height(x, y))),
+        metallic: create(&|x, y| gray(panel_height(x, y))),
+        roughness: create(&|x, y| {
+            if panel_height(x, y) < 1. {
+                gray(1.)
+            } else {
+                gray(0.7 + 0.3 * hash(x / PANEL_SIZE, y / PANEL_SIZE))
+            }
+        }),
+        // x along +u, y along +v and z out of the surface, from the slope of the height
+        normal: create(&|x, y| {
+            let (x, y) = (x as i32, y as i32);
+            let dx = height(x + 1, y) - height(x - 1, y);
+            let dy = height(x, y + 1) - height(x, y - 1);
+            let n = Vec3::new(-dx * BUMP_STRENGTH, -dy * BUMP_STRENGTH, 1.).normalize();
+            let encoded = (n * 0.5 + 0.5) * 255.;
+            image::Rgba([encoded.x as u8, encoded.y as u8, encoded.z as u8, 255])
+        }),
+        ao: create(&|x, y| gray(0.3 + 0.7 * panel_height(x, y))),
+    }
+}
+
+pub struct Pbr {
+    pipeline: wgpu::RenderPipeline,
+    light_pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    scene: SceneUniform,
+    scene_buffer: wgpu::Buffer,
+    scene_bindgroup: wgpu::BindGroup,
+    material_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    instances_buf: wgpu::Buffer,
+    index_count: u32,
+    instance_count: u32,
+}
+
+impl framework::App for Pbr {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            0.,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 22., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_layout = |label, visibility, size: usize| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(size as u64),
+                    },
+                    count: None,
+                }],
+            })
+        };
+
+        let camera_bindgroup_layout = uniform_layout(
+            "camera bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<CameraUniform>(),
+        );
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // lights, in front of the grid at its corners. the intensity is high because it falls
+        // off with the distance squared
+        let light = |x: f32, y: f32| PointLight {
+            position: [x, y, 10., 1.],
+            color: [300., 300., 300., 1.],
+        };
+        let scene = SceneUniform {
+            lights: [
+                light(-10., 10.),
+                light(10., 10.),
+                light(-10., -10.),
+                light(10., -10.),
+            ],
+            textured: 1,
+            _padding: [0; 3],
+        };
+
+        let scene_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Scene Buffer"),
+            contents: bytemuck::bytes_of(&scene),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // the light spheres' vertex shader reads the light positions too
+        let scene_bindgroup_layout = uniform_layout(
+            "scene bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<SceneUniform>(),
+        );
+
+        let scene_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("scene bind group"),
+            layout: &scene_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: scene_buffer.as_entire_binding(),
+            }],
+        });
+
+        // material, only albedo is a color, the other maps hold data and must not be srgb
+        let images = create_material_images();
+        let data_texture = |img: image::RgbaImage, label| {
+            Texture::from_image_format(
+                device,
+                queue,
+                &image::DynamicImage::ImageRgba8(img),
+                wgpu::TextureFormat::Rgba8Unorm,
+                label,
+            )
+        };
+        let albedo = Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::ImageRgba8(images.albedo),
+            "albedo_texture",
+        );
+        let metallic = data_texture(images.metallic, "metallic_texture");
+        let roughness = data_texture(images.roughness, "roughness_texture");
+        let normal = data_texture(images.normal, "normal_texture");
+        let ao = data_texture(images.ao, "ao_texture");
+
+        // the uvs go past 1, the panels repeat
+        let material_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("material sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            address_mode_w: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let material_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("material bindgroup layout"),
+                entries: &[
+                    texture_entry(0),
+                    texture_entry(1),
+                    texture_entry(2),
+                    texture_entry(3),
+                    texture_entry(4),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let material_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("material bind group"),
+            layout: &material_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&albedo.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&metallic.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&roughness.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(&normal.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&ao.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 5,
+                    resource: wgpu::BindingResource::Sampler(&material_sampler),
+                },
+            ],
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_bindgroup_layout,   // group 0, camera
+                &scene_bindgroup_layout,    // group 1, lights
+                &material_bindgroup_layout, // group 2, material maps
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![
+                0=>Float32x3, 1=>Float32x3, 2=>Float32x3, 3=>Float32x3, 4=>Float32x2
+            ],
+        };
+
+        let instance_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &wgpu::vertex_attr_array![5=>Float32x3, 6=>Float32x3, 7=>Float32, 8=>Float32],
+        };
+
+        // the spheres and the light spheres share the mesh, only the grid has instance data
+        let create_pipeline =
+            |vs_entry_point, fs_entry_point, buffers: &[wgpu::VertexBufferLayout]| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(fs_entry_point),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: vs_entry_point,
+                        buffers,
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: fs_entry_point,
+                        targets: &[Some(config.format.into())],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        cull_mode: Some(wgpu::Face::Back),
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: DepthTexture::FORMAT,
+                        depth_write_enabled: true,
+                        depth_compare: wgpu::CompareFunction::Less,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                })
+            };
+        let pipeline = create_pipeline(
+            "vs_main",
+            "fs_main",
+            &[vertex_buffer_layout.clone(), instance_buffer_layout],
+        );
+        let light_pipeline = create_pipeline("vs_light", "fs_light", &[vertex_buffer_layout]);
+
+        let (verticrs, indices) = create_sphere();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let instances = create_instances();
+        let instances_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instances Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        info!("metallic rises bottom to top, roughness left to right, press T for the textures");
+
+        Pbr {
+            pipeline,
+            light_pipeline,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            scene,
+            scene_buffer,
+            scene_bindgroup,
+            material_bindgroup,
+            vertices_buf,
+            indices_buf,
+            instances_buf,
+            index_count: indices.len() as u32,
+            instance_count: instances.len() as u32,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::T),
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.scene.textured = 1 - self.scene.textured;
+            window.set_title(if self.scene.textured == 1 {
+                "pbr, textured"
+            } else {
+                "pbr, factors only"
+            });
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller.update_camera(&mut self.camera, 0.);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+        watchdog::write_buffer(
+            queue,
+            &self.scene_buffer,
+            0,
+            bytemuck::bytes_of(&self.scene),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.02,
+                    g: 0.02,
+                    b: 0.02,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.scene_bindgroup, &[]);
+            rpass.set_bind_group(2, &self.material_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_vertex_buffer(1, self.instances_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.draw_indexed(0..self.index_count, 0, 0..self.instance_count);
+
+            rpass.set_pipeline(&self.light_pipeline);
+            rpass.draw_indexed(0..self.index_count, 0, 0..self.scene.lights.len() as u32);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
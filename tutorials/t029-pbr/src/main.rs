@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t29_pbr::Pbr>(t29_pbr::TITLE);
+}
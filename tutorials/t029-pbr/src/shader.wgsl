@@ -0,0 +1,184 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tangent: vec3<f32>,
+    @location(3) bitangent: vec3<f32>,
+    @location(4) uv: vec2<f32>,
+};
+
+// per sphere factors, multiplied with the texture values
+struct InstanceInput {
+    @location(5) offset: vec3<f32>,
+    @location(6) albedo: vec3<f32>,
+    @location(7) metallic: f32,
+    @location(8) roughness: f32,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) tangent: vec3<f32>,
+    @location(3) bitangent: vec3<f32>,
+    @location(4) uv: vec2<f32>,
+    @location(5) albedo: vec3<f32>,
+    @location(6) metallic: f32,
+    @location(7) roughness: f32,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+struct PointLight {
+    // w unused
+    position: vec4<f32>,
+    // radiant intensity, falls off with the distance squared, w unused
+    color: vec4<f32>,
+};
+
+struct Scene {
+    lights: array<PointLight, 4>,
+    // 0 factors only, 1 factors times the textures
+    textured: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> scene: Scene;
+
+@group(2) @binding(0)
+var t_albedo: texture_2d<f32>;
+@group(2) @binding(1)
+var t_metallic: texture_2d<f32>;
+@group(2) @binding(2)
+var t_roughness: texture_2d<f32>;
+@group(2) @binding(3)
+var t_normal: texture_2d<f32>;
+@group(2) @binding(4)
+var t_ao: texture_2d<f32>;
+@group(2) @binding(5)
+var s_material: sampler;
+
+let PI: f32 = 3.14159265359;
+let NUM_LIGHTS: i32 = 4;
+
+@vertex
+fn vs_main(input: VertexInput, instance: InstanceInput) -> FragInput {
+    let world_pos = input.pos + instance.offset;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = input.normal;
+    fragInput.tangent = input.tangent;
+    fragInput.bitangent = input.bitangent;
+    fragInput.uv = input.uv;
+    fragInput.albedo = instance.albedo;
+    fragInput.metallic = instance.metallic;
+    fragInput.roughness = instance.roughness;
+    return fragInput;
+}
+
+// D, how many microfacets face along the halfway vector, rough surfaces spread them out
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// G for one direction, microfacets hiding each other
+fn geometry_schlick_ggx(n_dot_x: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+// G, shadowed on the way in and masked on the way out
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    return geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+}
+
+// F, the share of light reflected rather than refracted, rising towards grazing angles
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    var albedo = input.albedo;
+    var metallic = input.metallic;
+    var roughness = input.roughness;
+    var ao = 1.0;
+    var n = normalize(input.normal);
+    if (scene.textured != 0u) {
+        albedo = albedo * textureSample(t_albedo, s_material, input.uv).rgb;
+        metallic = metallic * textureSample(t_metallic, s_material, input.uv).r;
+        roughness = roughness * textureSample(t_roughness, s_material, input.uv).r;
+        ao = textureSample(t_ao, s_material, input.uv).r;
+        let tbn = mat3x3<f32>(normalize(input.tangent), normalize(input.bitangent), n);
+        n = normalize(tbn * (textureSample(t_normal, s_material, input.uv).xyz * 2.0 - 1.0));
+    }
+    // a perfect mirror makes D a spike that no light position ever hits
+    roughness = max(roughness, 0.05);
+
+    let v = normalize(camera.position.xyz - input.world_pos);
+    let n_dot_v = max(dot(n, v), 0.0001);
+    // dielectrics reflect about 4% head on, metals reflect in their own color
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+
+    var radiance_out = vec3<f32>(0.0);
+    for (var i = 0; i < NUM_LIGHTS; i = i + 1) {
+        let light = scene.lights[i];
+        let to_light = light.position.xyz - input.world_pos;
+        let l = normalize(to_light);
+        let h = normalize(v + l);
+        let n_dot_l = max(dot(n, l), 0.0);
+        let radiance = light.color.rgb / dot(to_light, to_light);
+
+        let f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+        let specular = distribution_ggx(max(dot(n, h), 0.0), roughness)
+            * geometry_smith(n_dot_v, n_dot_l, roughness) * f
+            / (4.0 * n_dot_v * n_dot_l + 0.0001);
+        // what isn't reflected is refracted and scattered back out as diffuse, metals absorb it
+        let kd = (1.0 - f) * (1.0 - metallic);
+
+        radiance_out = radiance_out + (kd * albedo / PI + specular) * radiance * n_dot_l;
+    }
+
+    // stand in for the light bouncing around the scene until image based lighting
+    let ambient = vec3<f32>(0.03) * albedo * ao;
+    let color = ambient + radiance_out;
+    // the lights are far brighter than 1, reinhard squeezes them into range before the write
+    return vec4<f32>(color / (color + 1.0), 1.0);
+}
+
+// one small sphere per light, unlit in the light's color
+@vertex
+fn vs_light(input: VertexInput, @builtin(instance_index) index: u32) -> FragInput {
+    let light = scene.lights[index];
+    let world_pos = input.pos * 0.3 + light.position.xyz;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = input.normal;
+    fragInput.tangent = input.tangent;
+    fragInput.bitangent = input.bitangent;
+    fragInput.uv = input.uv;
+    // the hue of the light at full brightness
+    fragInput.albedo = light.color.rgb / max(light.color.r, max(light.color.g, light.color.b));
+    fragInput.metallic = 0.0;
+    fragInput.roughness = 0.0;
+    return fragInput;
+}
+
+@fragment
+fn fs_light(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.albedo, 1.0);
+}
@@ -0,0 +1,66 @@
+// the second half of the split sum, the specular brdf integrated over the hemisphere under a
+// white environment. it only depends on n_dot_v (x) and the roughness (y), and comes out as a
+// scale (r) and a bias (g) to f0, one invocation per texel
+
+@group(0) @binding(0)
+var output: texture_storage_2d<rg16float, write>;
+
+let PI: f32 = 3.14159265359;
+let SAMPLE_COUNT: u32 = 1024u;
+
+// evenly spread points in the unit square, a low discrepancy sequence
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    return vec2<f32>(f32(i) / f32(count), f32(reverseBits(i)) * 2.3283064365386963e-10);
+}
+
+// halfway vectors around +z, as many as the ggx distribution puts at each angle
+fn importance_sample_ggx(xi: vec2<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    return vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+}
+
+// the same G as the direct lights, but with k remapped for image based lighting
+fn geometry_schlick_ggx(n_dot_x: f32, roughness: f32) -> f32 {
+    let k = roughness * roughness / 2.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+@compute
+@workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output);
+    if (i32(id.x) >= size.x || i32(id.y) >= size.y) {
+        return;
+    }
+
+    // texel centers, so neither end is exactly 0
+    let n_dot_v = (f32(id.x) + 0.5) / f32(size.x);
+    let roughness = (f32(id.y) + 0.5) / f32(size.y);
+    // the normal is +z, the view direction anywhere at that angle to it
+    let v = vec3<f32>(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+
+    var scale = 0.0;
+    var bias = 0.0;
+    for (var i = 0u; i < SAMPLE_COUNT; i = i + 1u) {
+        let h = importance_sample_ggx(hammersley(i, SAMPLE_COUNT), roughness);
+        let l = normalize(2.0 * dot(v, h) * h - v);
+        let n_dot_l = max(l.z, 0.0);
+        if (n_dot_l > 0.0) {
+            let n_dot_h = max(h.z, 0.0);
+            let v_dot_h = max(dot(v, h), 0.0);
+            // D cancels against the pdf the samples were drawn with, what's left of the brdf
+            let g = geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+            let g_vis = g * v_dot_h / (n_dot_h * n_dot_v);
+            // fresnel split into the part f0 scales and the part added on top
+            let fc = pow(1.0 - v_dot_h, 5.0);
+            scale = scale + (1.0 - fc) * g_vis;
+            bias = bias + fc * g_vis;
+        }
+    }
+
+    let count = f32(SAMPLE_COUNT);
+    textureStore(output, vec2<i32>(id.xy), vec4<f32>(scale / count, bias / count, 0.0, 1.0));
+}
@@ -0,0 +1,512 @@
+// bakes the lighting of an hdr environment into cubemaps the pbr shader can sample directly.
+//
+// the diffuse and specular integrals over the environment are far too expensive per pixel,
+// but they only depend on a direction (and the roughness), so they are solved once at startup:
+// - irradiance, the cosine weighted hemisphere around each normal, small because it's blurry
+// - prefiltered, the environment blurred by the ggx lobe, one mip level per roughness
+// - brdf lut, the brdf integral the prefiltered light gets scaled by, from a compute pass
+//
+// every face of every mip is its own render pass drawing one fullscreen triangle.
+
+use bytemuck::{Pod, Zeroable};
+use framework::autotune;
+use tracing::info;
+use wgpu::include_wgsl;
+
+use wgpu::util::DeviceExt;
+
+// rgba16float keeps the sun's values above 1, and unlike rgba32float it's filterable everywhere
+const CUBE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+/// of the procedural environment, and what a panorama gets turned into
+pub const ENVIRONMENT_SIZE: u32 = 512;
+const IRRADIANCE_SIZE: u32 = 32;
+const PREFILTER_SIZE: u32 = 128;
+/// roughness 0 at mip 0 to roughness 1 at the last one
+pub const PREFILTER_MIPS: u32 = 5;
+const BRDF_LUT_SIZE: u32 = 256;
+// what brdf.wgsl is written with
+const BRDF_WORKGROUP_SIZE: [u32; 3] = [8, 8, 1];
+
+// matches `Face` in ibl.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct FaceUniform {
+    face: u32,
+    roughness: f32,
+    _padding: [u32; 2],
+}
+
+pub struct Ibl {
+    /// the environment itself, to draw as the background
+    pub environment: wgpu::TextureView,
+    pub irradiance: wgpu::TextureView,
+    /// [`PREFILTER_MIPS`] levels
+    pub prefiltered: wgpu::TextureView,
+    /// n_dot_v along x, roughness along y
+    pub brdf_lut: wgpu::TextureView,
+}
+
+fn create_cube(device: &wgpu::Device, label: &str, size: u32, mips: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some(label),
+        size: wgpu::Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 6,
+        },
+        mip_level_count: mips,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: CUBE_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+    })
+}
+
+// a 2d view of one face of one mip to render into
+fn face_view(texture: &wgpu::Texture, face: u32, mip: u32) -> wgpu::TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        label: Some("ibl face view"),
+        dimension: Some(wgpu::TextureViewDimension::D2),
+        base_mip_level: mip,
+        mip_level_count: std::num::NonZeroU32::new(1),
+        base_array_layer: face,
+        array_layer_count: std::num::NonZeroU32::new(1),
+        ..Default::default()
+    })
+}
+
+fn cube_view(texture: &wgpu::Texture) -> wgpu::TextureView {
+    texture.create_view(&wgpu::TextureViewDescriptor {
+        label: Some("ibl cube view"),
+        dimension: Some(wgpu::TextureViewDimension::Cube),
+        ..Default::default()
+    })
+}
+
+// the lut only has two channels, but shaders can write rg16float only where the adapter says
+// so, and only with TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES on. rgba16float always works
+fn brdf_lut_format(adapter: &wgpu::Adapter, device: &wgpu::Device) -> wgpu::TextureFormat {
+    let rg16float_storage = device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES)
+        && adapter
+            .get_texture_format_features(wgpu::TextureFormat::Rg16Float)
+            .allowed_usages
+            .contains(wgpu::TextureUsages::STORAGE_BINDING);
+
+    if rg16float_storage {
+        wgpu::TextureFormat::Rg16Float
+    } else {
+        wgpu::TextureFormat::Rgba16Float
+    }
+}
+
+// a compute pass writing the lut into a storage texture, in workgroups of `workgroup_size`
+fn bake_brdf_lut(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    encoder: &mut wgpu::CommandEncoder,
+    workgroup_size: [u32; 3],
+) -> wgpu::Texture {
+    let format = brdf_lut_format(adapter, device);
+    info!("baking the brdf lut as {:?}", format);
+
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("brdf lut texture"),
+        size: wgpu::Extent3d {
+            width: BRDF_LUT_SIZE,
+            height: BRDF_LUT_SIZE,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        // COPY_SRC for the tests
+        usage: wgpu::TextureUsages::STORAGE_BINDING
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("brdf lut bindgroup layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::StorageTexture {
+                access: wgpu::StorageTextureAccess::WriteOnly,
+                format,
+                view_dimension: wgpu::TextureViewDimension::D2,
+            },
+            count: None,
+        }],
+    });
+
+    let bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("brdf lut bind group"),
+        layout: &bindgroup_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(&view),
+        }],
+    });
+
+    // the storage format is part of the shader, brdf.wgsl is written for rg16float
+    let source = autotune::with_workgroup_size(include_str!("brdf.wgsl"), workgroup_size);
+    let source = match format {
+        wgpu::TextureFormat::Rg16Float => source.into(),
+        _ => source.replace("rg16float", "rgba16float").into(),
+    };
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("brdf.wgsl"),
+        source: wgpu::ShaderSource::Wgsl(source),
+    });
+
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("brdf lut pipeline layout"),
+        bind_group_layouts: &[&bindgroup_layout],
+        push_constant_ranges: &[],
+    });
+
+    let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some("brdf lut pipeline"),
+        layout: Some(&pipeline_layout),
+        module: &shader,
+        entry_point: "cs_main",
+    });
+
+    {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("brdf lut pass"),
+        });
+        cpass.set_pipeline(&pipeline);
+        cpass.set_bind_group(0, &bindgroup, &[]);
+        let [x, y, _] = workgroup_size;
+        cpass.dispatch_workgroups(BRDF_LUT_SIZE.div_ceil(x), BRDF_LUT_SIZE.div_ceil(y), 1);
+    }
+
+    texture
+}
+
+impl Ibl {
+    /// renders everything derived from `environment`, a cube view, e.g. of a panorama from
+    /// `framework::equirect`. without one the procedural sky in ibl.wgsl is rendered first.
+    /// the passes are submitted before this returns
+    pub fn bake(
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        environment: Option<wgpu::TextureView>,
+    ) -> Ibl {
+        let (procedural, environment_view) = match environment {
+            Some(view) => (None, view),
+            None => {
+                let cube = create_cube(device, "environment texture", ENVIRONMENT_SIZE, 1);
+                let view = cube_view(&cube);
+                (Some(cube), view)
+            }
+        };
+        let irradiance = create_cube(device, "irradiance texture", IRRADIANCE_SIZE, 1);
+        let prefiltered = create_cube(
+            device,
+            "prefiltered texture",
+            PREFILTER_SIZE,
+            PREFILTER_MIPS,
+        );
+
+        // face and roughness per pass
+        let face_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("ibl face bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<FaceUniform>() as u64
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        // one per face of every prefiltered mip, the environment and irradiance passes use
+        // the first six
+        let face_bindgroups: Vec<wgpu::BindGroup> = (0..PREFILTER_MIPS * 6)
+            .map(|i| {
+                let uniform = FaceUniform {
+                    face: i % 6,
+                    roughness: (i / 6) as f32 / (PREFILTER_MIPS - 1) as f32,
+                    _padding: [0; 2],
+                };
+                let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("IBL Face Buffer"),
+                    contents: bytemuck::bytes_of(&uniform),
+                    usage: wgpu::BufferUsages::UNIFORM,
+                });
+
+                device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("ibl face bind group"),
+                    layout: &face_bindgroup_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                })
+            })
+            .collect();
+
+        // the environment as the source of the irradiance and prefilter passes
+        let environment_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("environment sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let environment_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("environment bindgroup layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let environment_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("environment bind group"),
+            layout: &environment_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&environment_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&environment_sampler),
+                },
+            ],
+        });
+
+        // pipelines, the environment pass is procedural and reads nothing
+        let shader = device.create_shader_module(include_wgsl!("ibl.wgsl"));
+
+        let generate_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ibl generate pipeline layout"),
+            bind_group_layouts: &[&face_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+        let convolve_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("ibl convolve pipeline layout"),
+            bind_group_layouts: &[&face_bindgroup_layout, &environment_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |layout, fs_entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(fs_entry_point),
+                layout: Some(layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_face",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs_entry_point,
+                    targets: &[Some(CUBE_FORMAT.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let environment_pipeline = create_pipeline(&generate_layout, "fs_environment");
+        let irradiance_pipeline = create_pipeline(&convolve_layout, "fs_irradiance");
+        let prefilter_pipeline = create_pipeline(&convolve_layout, "fs_prefilter");
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("ibl bake encoder"),
+        });
+
+        // the convolve passes read the finished environment, wgpu orders them within the encoder
+        let mut draw_face = |pipeline: &wgpu::RenderPipeline,
+                             source: Option<&wgpu::BindGroup>,
+                             target: &wgpu::Texture,
+                             face: u32,
+                             mip: u32| {
+            let view = face_view(target, face, mip);
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("ibl bake pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, &face_bindgroups[(mip * 6 + face) as usize], &[]);
+            if let Some(source) = source {
+                rpass.set_bind_group(1, source, &[]);
+            }
+            rpass.draw(0..3, 0..1);
+        };
+
+        if let Some(procedural) = &procedural {
+            for face in 0..6 {
+                draw_face(&environment_pipeline, None, procedural, face, 0);
+            }
+        }
+        for face in 0..6 {
+            draw_face(
+                &irradiance_pipeline,
+                Some(&environment_bindgroup),
+                &irradiance,
+                face,
+                0,
+            );
+        }
+        for mip in 0..PREFILTER_MIPS {
+            for face in 0..6 {
+                draw_face(
+                    &prefilter_pipeline,
+                    Some(&environment_bindgroup),
+                    &prefiltered,
+                    face,
+                    mip,
+                );
+            }
+        }
+
+        let brdf_lut = bake_brdf_lut(adapter, device, &mut encoder, BRDF_WORKGROUP_SIZE);
+
+        queue.submit(Some(encoder.finish()));
+
+        Ibl {
+            environment: environment_view,
+            irradiance: cube_view(&irradiance),
+            prefiltered: cube_view(&prefiltered),
+            brdf_lut: brdf_lut.create_view(&wgpu::TextureViewDescriptor::default()),
+        }
+    }
+}
+
+// the brdf lut against the same integral on the cpu, on a device without a window. without an
+// adapter, e.g. on ci without a gpu, or on gl, it passes
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use framework::testing::{f16_to_f32, headless, read_texture};
+    use glam::Vec3;
+    use tracing::warn;
+
+    const SAMPLE_COUNT: u32 = 1024;
+
+    // `cs_main` in brdf.wgsl for one texel, its scale and bias
+    fn cpu_texel(x: u32, y: u32) -> [f32; 2] {
+        let n_dot_v = (x as f32 + 0.5) / BRDF_LUT_SIZE as f32;
+        let roughness = (y as f32 + 0.5) / BRDF_LUT_SIZE as f32;
+        let v = Vec3::new((1. - n_dot_v * n_dot_v).sqrt(), 0., n_dot_v);
+        let geometry = |n_dot_x: f32| {
+            let k = roughness * roughness / 2.;
+            n_dot_x / (n_dot_x * (1. - k) + k)
+        };
+
+        let (mut scale, mut bias) = (0., 0.);
+        for i in 0..SAMPLE_COUNT {
+            // hammersley and importance_sample_ggx
+            let xi = (
+                i as f32 / SAMPLE_COUNT as f32,
+                i.reverse_bits() as f32 * 2.328_306_4e-10,
+            );
+            let a = roughness * roughness;
+            let phi = std::f32::consts::TAU * xi.0;
+            let cos_theta = ((1. - xi.1) / (1. + (a * a - 1.) * xi.1)).sqrt();
+            let sin_theta = (1. - cos_theta * cos_theta).sqrt();
+            let h = Vec3::new(phi.cos() * sin_theta, phi.sin() * sin_theta, cos_theta);
+
+            let l = (2. * v.dot(h) * h - v).normalize();
+            let n_dot_l = l.z.max(0.);
+            if n_dot_l > 0. {
+                let n_dot_h = h.z.max(0.);
+                let v_dot_h = v.dot(h).max(0.);
+                let g = geometry(n_dot_v) * geometry(n_dot_l);
+                let g_vis = g * v_dot_h / (n_dot_h * n_dot_v);
+                let fc = (1. - v_dot_h).powi(5);
+                scale += (1. - fc) * g_vis;
+                bias += fc * g_vis;
+            }
+        }
+        [scale / SAMPLE_COUNT as f32, bias / SAMPLE_COUNT as f32]
+    }
+
+    // a grid of texels corner to corner, with the default workgroups, others that divide the
+    // lut and one that doesn't, its last workgroups hanging over the edges
+    #[test]
+    fn brdf_lut_matches_the_cpu() {
+        let Some((adapter, device, queue)) = headless() else {
+            return;
+        };
+        // storage textures panic in wgpu-hal 0.14's gl backend
+        if adapter.get_info().backend == wgpu::Backend::Gl {
+            warn!("no storage textures on gl, skipping the test");
+            return;
+        }
+        // no TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES on the test device, the lut is rgba
+        assert_eq!(
+            brdf_lut_format(&adapter, &device),
+            wgpu::TextureFormat::Rgba16Float
+        );
+
+        for workgroup_size in [BRDF_WORKGROUP_SIZE, [16, 16, 1], [32, 4, 1], [24, 8, 1]] {
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            let lut = bake_brdf_lut(&adapter, &device, &mut encoder, workgroup_size);
+            queue.submit(Some(encoder.finish()));
+            let texels: Vec<[u16; 4]> =
+                read_texture(&device, &queue, &lut, (BRDF_LUT_SIZE, BRDF_LUT_SIZE), 0, 0);
+
+            for y in (0..BRDF_LUT_SIZE).step_by(51) {
+                for x in (0..BRDF_LUT_SIZE).step_by(51) {
+                    let texel = texels[(y * BRDF_LUT_SIZE + x) as usize];
+                    let expected = cpu_texel(x, y);
+                    for channel in 0..2 {
+                        let got = f16_to_f32(texel[channel]);
+                        assert!(
+                            (got - expected[channel]).abs() < 2e-3 + expected[channel] * 0.01,
+                            "{:?}: texel ({}, {}) channel {} is {}, expected {}",
+                            workgroup_size,
+                            x,
+                            y,
+                            channel,
+                            got,
+                            expected[channel]
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
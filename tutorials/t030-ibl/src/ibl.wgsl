@@ -0,0 +1,147 @@
+// bakes the image based lighting, every pass draws one fullscreen triangle into one cube face
+
+struct Face {
+    // wgpu cube face order is +x, -x, +y, -y, +z, -z
+    face: u32,
+    // of the prefiltered mip level being drawn, unused by the other passes
+    roughness: f32,
+};
+
+struct FaceOutput {
+    // -1..1 across the face, y down like the texel rows
+    @location(0) uv: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> face: Face;
+
+@group(1) @binding(0)
+var t_environment: texture_cube<f32>;
+@group(1) @binding(1)
+var s_environment: sampler;
+
+let PI: f32 = 3.14159265359;
+// normalize(vec3(0.4, 0.5, -0.75))
+let SUN_DIR: vec3<f32> = vec3<f32>(0.4417, 0.5522, -0.7283);
+// radians between the irradiance samples, in both directions
+let SAMPLE_DELTA: f32 = 0.025;
+let PREFILTER_SAMPLES: u32 = 1024u;
+
+@vertex
+fn vs_face(@builtin(vertex_index) index: u32) -> FaceOutput {
+    // 0..2 covers the whole viewport with one triangle
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var output: FaceOutput;
+    output.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    output.uv = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    return output;
+}
+
+// where a texel of the face looks from the center of the cube
+fn direction(uv: vec2<f32>) -> vec3<f32> {
+    let u = uv.x;
+    let v = uv.y;
+    var dir: vec3<f32>;
+    if (face.face == 0u) {
+        dir = vec3<f32>(1.0, -v, -u);
+    } else if (face.face == 1u) {
+        dir = vec3<f32>(-1.0, -v, u);
+    } else if (face.face == 2u) {
+        dir = vec3<f32>(u, 1.0, v);
+    } else if (face.face == 3u) {
+        dir = vec3<f32>(u, -1.0, -v);
+    } else if (face.face == 4u) {
+        dir = vec3<f32>(u, -v, 1.0);
+    } else {
+        dir = vec3<f32>(-u, -v, -1.0);
+    }
+    return normalize(dir);
+}
+
+// two axes perpendicular to n, to turn directions around +z into directions around n
+fn tangent_frame(n: vec3<f32>) -> mat3x3<f32> {
+    var up = vec3<f32>(0.0, 1.0, 0.0);
+    if (abs(n.y) > 0.999) {
+        up = vec3<f32>(0.0, 0.0, 1.0);
+    }
+    let tangent = normalize(cross(up, n));
+    let bitangent = cross(n, tangent);
+    return mat3x3<f32>(tangent, bitangent, n);
+}
+
+// the hdr environment, a sky with a sun well above 1 over brown ground
+@fragment
+fn fs_environment(input: FaceOutput) -> @location(0) vec4<f32> {
+    let dir = direction(input.uv);
+
+    let horizon = vec3<f32>(1.0, 0.85, 0.7);
+    let zenith = vec3<f32>(0.15, 0.35, 0.9);
+    let ground = vec3<f32>(0.25, 0.2, 0.15);
+    var color: vec3<f32>;
+    if (dir.y > 0.0) {
+        color = mix(horizon, zenith, sqrt(dir.y));
+    } else {
+        color = mix(horizon, ground, pow(-dir.y, 0.3)) * 0.5;
+    }
+
+    // a glow around a small disk, the disk is what the shiny spheres reflect
+    let sun = max(dot(dir, SUN_DIR), 0.0);
+    color = color + vec3<f32>(1.0, 0.9, 0.7) * (pow(sun, 32.0) * 2.0 + pow(sun, 512.0) * 30.0);
+    return vec4<f32>(color, 1.0);
+}
+
+// diffuse light arriving from the whole hemisphere around each direction, cosine weighted
+@fragment
+fn fs_irradiance(input: FaceOutput) -> @location(0) vec4<f32> {
+    let frame = tangent_frame(direction(input.uv));
+
+    var irradiance = vec3<f32>(0.0);
+    var count = 0.0;
+    for (var phi = 0.0; phi < 2.0 * PI; phi = phi + SAMPLE_DELTA) {
+        for (var theta = 0.0; theta < 0.5 * PI; theta = theta + SAMPLE_DELTA) {
+            let local = vec3<f32>(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+            let radiance = textureSampleLevel(t_environment, s_environment, frame * local, 0.0).rgb;
+            // cos for the angle of incidence, sin because the rings near the pole are smaller
+            irradiance = irradiance + radiance * cos(theta) * sin(theta);
+            count = count + 1.0;
+        }
+    }
+    return vec4<f32>(PI * irradiance / count, 1.0);
+}
+
+// evenly spread points in the unit square, a low discrepancy sequence
+fn hammersley(i: u32, count: u32) -> vec2<f32> {
+    return vec2<f32>(f32(i) / f32(count), f32(reverseBits(i)) * 2.3283064365386963e-10);
+}
+
+// halfway vectors around +z, as many as the ggx distribution puts at each angle
+fn importance_sample_ggx(xi: vec2<f32>, roughness: f32) -> vec3<f32> {
+    let a = roughness * roughness;
+    let phi = 2.0 * PI * xi.x;
+    let cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+    let sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+    return vec3<f32>(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+}
+
+// the environment blurred by the specular lobe of one roughness, assuming the view direction
+// equals the normal and the reflection. rougher levels are smaller mips
+@fragment
+fn fs_prefilter(input: FaceOutput) -> @location(0) vec4<f32> {
+    let n = direction(input.uv);
+    let frame = tangent_frame(n);
+
+    var color = vec3<f32>(0.0);
+    var weight = 0.0;
+    for (var i = 0u; i < PREFILTER_SAMPLES; i = i + 1u) {
+        let h = frame * importance_sample_ggx(hammersley(i, PREFILTER_SAMPLES), face.roughness);
+        let l = normalize(2.0 * dot(n, h) * h - n);
+        let n_dot_l = dot(n, l);
+        if (n_dot_l > 0.0) {
+            color = color + textureSampleLevel(t_environment, s_environment, l, 0.0).rgb * n_dot_l;
+            weight = weight + n_dot_l;
+        }
+    }
+    return vec4<f32>(color / weight, 1.0);
+}
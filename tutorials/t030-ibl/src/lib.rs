@@ -0,0 +1,554 @@
+mod ibl;
+
+use std::f32::consts::PI;
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    equirect,
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::Vec3;
+use ibl::Ibl;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "ibl";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+// GRID_SIZE * GRID_SIZE spheres, metallic rises by row and roughness by column
+const GRID_SIZE: u32 = 7;
+const GRID_SPACING: f32 = 2.5;
+const SPHERE_SECTORS: u32 = 64;
+const SPHERE_STACKS: u32 = 32;
+const BACKGROUNDS: [&str; 3] = ["environment", "irradiance", "prefiltered"];
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Instance {
+    offset: [f32; 3],
+    albedo: [f32; 3],
+    metallic: f32,
+    roughness: f32,
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        let view_proj = camera.view_proj();
+        CameraUniform {
+            view_proj: view_proj.to_cols_array_2d(),
+            inv_view_proj: view_proj.inverse().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `PointLight` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct PointLight {
+    position: [f32; 4],
+    color: [f32; 4],
+}
+
+// matches `Scene` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct SceneUniform {
+    lights: [PointLight; 4],
+    ibl: u32,
+    background: u32,
+    _padding: [u32; 2],
+}
+
+// unit sphere, theta runs down from the north pole and phi around the y axis
+fn create_sphere() -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    for stack in 0..=SPHERE_STACKS {
+        let (sin_theta, cos_theta) = (stack as f32 / SPHERE_STACKS as f32 * PI).sin_cos();
+        for sector in 0..=SPHERE_SECTORS {
+            let (sin_phi, cos_phi) = (sector as f32 / SPHERE_SECTORS as f32 * 2. * PI).sin_cos();
+            let normal = [sin_theta * cos_phi, cos_theta, sin_theta * sin_phi];
+            vertices.push(Vertex {
+                pos: normal,
+                normal,
+            });
+        }
+    }
+
+    // the rows at the poles collapse into points, their triangles have no area and get culled
+    for stack in 0..SPHERE_STACKS {
+        for sector in 0..SPHERE_SECTORS {
+            let top = (stack * (SPHERE_SECTORS + 1) + sector) as u16;
+            let bottom = top + SPHERE_SECTORS as u16 + 1;
+            indices.extend_from_slice(&[top, top + 1, bottom, top + 1, bottom + 1, bottom]);
+        }
+    }
+
+    (vertices, indices)
+}
+
+fn create_instances() -> Vec<Instance> {
+    let half = (GRID_SIZE - 1) as f32 / 2.;
+    (0..GRID_SIZE * GRID_SIZE)
+        .map(|i| {
+            let (row, column) = (i / GRID_SIZE, i % GRID_SIZE);
+            Instance {
+                offset: [
+                    (column as f32 - half) * GRID_SPACING,
+                    (row as f32 - half) * GRID_SPACING,
+                    0.,
+                ],
+                albedo: [0.8, 0.25, 0.2],
+                metallic: row as f32 / (GRID_SIZE - 1) as f32,
+                roughness: column as f32 / (GRID_SIZE - 1) as f32,
+            }
+        })
+        .collect()
+}
+
+pub struct ImageBasedLighting {
+    pipeline: wgpu::RenderPipeline,
+    light_pipeline: wgpu::RenderPipeline,
+    sky_pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    scene: SceneUniform,
+    scene_buffer: wgpu::Buffer,
+    scene_bindgroup: wgpu::BindGroup,
+    ibl_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    instances_buf: wgpu::Buffer,
+    index_count: u32,
+    instance_count: u32,
+}
+
+impl ImageBasedLighting {
+    fn update_title(&self, window: &Window) {
+        window.set_title(&format!(
+            "ibl, ambient: {}, background: {}",
+            if self.scene.ibl == 1 { "ibl" } else { "flat" },
+            BACKGROUNDS[self.scene.background as usize]
+        ));
+    }
+}
+
+impl framework::App for ImageBasedLighting {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    // lets the brdf lut be written as rg16float, see ibl.rs
+    fn optional_features() -> wgpu::Features {
+        wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            0.,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 22., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_layout = |label, visibility, size: usize| {
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some(label),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(size as u64),
+                    },
+                    count: None,
+                }],
+            })
+        };
+
+        let camera_bindgroup_layout = uniform_layout(
+            "camera bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<CameraUniform>(),
+        );
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // lights, in front of the grid at its corners, dimmer than in the pbr chapter since
+        // the environment lights the spheres too
+        let light = |x: f32, y: f32| PointLight {
+            position: [x, y, 10., 1.],
+            color: [100., 100., 100., 1.],
+        };
+        let scene = SceneUniform {
+            lights: [
+                light(-10., 10.),
+                light(10., 10.),
+                light(-10., -10.),
+                light(10., -10.),
+            ],
+            ibl: 1,
+            background: 0,
+            _padding: [0; 2],
+        };
+
+        let scene_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Scene Buffer"),
+            contents: bytemuck::bytes_of(&scene),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // the light spheres' vertex shader reads the light positions too
+        let scene_bindgroup_layout = uniform_layout(
+            "scene bindgroup layout",
+            wgpu::ShaderStages::VERTEX_FRAGMENT,
+            std::mem::size_of::<SceneUniform>(),
+        );
+
+        let scene_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("scene bind group"),
+            layout: &scene_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: scene_buffer.as_entire_binding(),
+            }],
+        });
+
+        // image based lighting, baked once
+        let environment = std::env::args().nth(1).map(|path| {
+            let bytes =
+                std::fs::read(&path).unwrap_or_else(|err| panic!("Fail to read {}: {}", path, err));
+            let panorama = equirect::load_hdr(&bytes)
+                .unwrap_or_else(|err| panic!("Fail to decode {}: {}", path, err));
+            equirect::to_cube(device, queue, &panorama, ibl::ENVIRONMENT_SIZE, &path).view
+        });
+        let ibl = Ibl::bake(adapter, device, queue, environment);
+
+        // linear between the prefiltered mips, that's where the roughnesses in between live
+        let ibl_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("ibl sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let cube_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                view_dimension: wgpu::TextureViewDimension::Cube,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let ibl_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("ibl bindgroup layout"),
+                entries: &[
+                    cube_entry(0),
+                    cube_entry(1),
+                    cube_entry(2),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let ibl_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ibl bind group"),
+            layout: &ibl_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&ibl.environment),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&ibl.irradiance),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(&ibl.prefiltered),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::Sampler(&ibl_sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: wgpu::BindingResource::TextureView(&ibl.brdf_lut),
+                },
+            ],
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_bindgroup_layout, // group 0, camera
+                &scene_bindgroup_layout,  // group 1, lights
+                &ibl_bindgroup_layout,    // group 2, environment maps
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+        };
+
+        let instance_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &wgpu::vertex_attr_array![2=>Float32x3, 3=>Float32x3, 4=>Float32, 5=>Float32],
+        };
+
+        // the background goes last and only fills what's still at the cleared depth of 1, so
+        // it tests LessEqual and leaves the depth alone
+        let create_pipeline = |vs_entry_point,
+                               fs_entry_point,
+                               buffers: &[wgpu::VertexBufferLayout],
+                               depth_compare,
+                               depth_write_enabled| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(fs_entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vs_entry_point,
+                    buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs_entry_point,
+                    targets: &[Some(config.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DepthTexture::FORMAT,
+                    depth_write_enabled,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline(
+            "vs_main",
+            "fs_main",
+            &[vertex_buffer_layout.clone(), instance_buffer_layout],
+            wgpu::CompareFunction::Less,
+            true,
+        );
+        let light_pipeline = create_pipeline(
+            "vs_light",
+            "fs_light",
+            &[vertex_buffer_layout],
+            wgpu::CompareFunction::Less,
+            true,
+        );
+        let sky_pipeline = create_pipeline(
+            "vs_sky",
+            "fs_sky",
+            &[],
+            wgpu::CompareFunction::LessEqual,
+            false,
+        );
+
+        let (verticrs, indices) = create_sphere();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let instances = create_instances();
+        let instances_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instances Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        info!("press I to switch the ambient light, B to switch the background");
+
+        ImageBasedLighting {
+            pipeline,
+            light_pipeline,
+            sky_pipeline,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            scene,
+            scene_buffer,
+            scene_bindgroup,
+            ibl_bindgroup,
+            vertices_buf,
+            indices_buf,
+            instances_buf,
+            index_count: indices.len() as u32,
+            instance_count: instances.len() as u32,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(keycode),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match keycode {
+                VirtualKeyCode::I => self.scene.ibl = 1 - self.scene.ibl,
+                VirtualKeyCode::B => {
+                    self.scene.background = (self.scene.background + 1) % BACKGROUNDS.len() as u32
+                }
+                _ => return,
+            }
+            self.update_title(window);
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller.update_camera(&mut self.camera, 0.);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+        watchdog::write_buffer(
+            queue,
+            &self.scene_buffer,
+            0,
+            bytemuck::bytes_of(&self.scene),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.scene_bindgroup, &[]);
+            rpass.set_bind_group(2, &self.ibl_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_vertex_buffer(1, self.instances_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.draw_indexed(0..self.index_count, 0, 0..self.instance_count);
+
+            rpass.set_pipeline(&self.light_pipeline);
+            rpass.draw_indexed(0..self.index_count, 0, 0..self.scene.lights.len() as u32);
+
+            rpass.set_pipeline(&self.sky_pipeline);
+            rpass.draw(0..3, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
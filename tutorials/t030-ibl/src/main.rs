@@ -0,0 +1,4 @@
+// cargo run -p t30-ibl -- path/to/panorama.hdr, without a path the procedural sky is baked
+fn main() {
+    framework::run::<t30_ibl::ImageBasedLighting>(t30_ibl::TITLE);
+}
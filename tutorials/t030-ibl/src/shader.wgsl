@@ -0,0 +1,225 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+// per sphere material
+struct InstanceInput {
+    @location(2) offset: vec3<f32>,
+    @location(3) albedo: vec3<f32>,
+    @location(4) metallic: f32,
+    @location(5) roughness: f32,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) albedo: vec3<f32>,
+    @location(3) metallic: f32,
+    @location(4) roughness: f32,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // takes clip space back to world space, for the background directions
+    inv_view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+struct PointLight {
+    // w unused
+    position: vec4<f32>,
+    // radiant intensity, falls off with the distance squared, w unused
+    color: vec4<f32>,
+};
+
+struct Scene {
+    lights: array<PointLight, 4>,
+    // 0 a flat ambient term, 1 the irradiance and prefiltered maps
+    ibl: u32,
+    // what the background shows, 0 the environment, 1 the irradiance, 2 a prefiltered mip
+    background: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var<uniform> scene: Scene;
+
+@group(2) @binding(0)
+var t_environment: texture_cube<f32>;
+@group(2) @binding(1)
+var t_irradiance: texture_cube<f32>;
+@group(2) @binding(2)
+var t_prefiltered: texture_cube<f32>;
+@group(2) @binding(3)
+var s_ibl: sampler;
+// scale and bias to f0 by n_dot_v and roughness, baked by brdf.wgsl
+@group(2) @binding(4)
+var t_brdf_lut: texture_2d<f32>;
+
+let PI: f32 = 3.14159265359;
+let NUM_LIGHTS: i32 = 4;
+// the last mip of the prefiltered map, roughness 1
+let MAX_REFLECTION_LOD: f32 = 4.0;
+// the mip the background shows in prefiltered mode
+let BACKGROUND_LOD: f32 = 2.0;
+
+@vertex
+fn vs_main(input: VertexInput, instance: InstanceInput) -> FragInput {
+    let world_pos = input.pos + instance.offset;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = input.normal;
+    fragInput.albedo = instance.albedo;
+    fragInput.metallic = instance.metallic;
+    fragInput.roughness = instance.roughness;
+    return fragInput;
+}
+
+// D, how many microfacets face along the halfway vector, rough surfaces spread them out
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// G for one direction, microfacets hiding each other
+fn geometry_schlick_ggx(n_dot_x: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    return n_dot_x / (n_dot_x * (1.0 - k) + k);
+}
+
+// G, shadowed on the way in and masked on the way out
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    return geometry_schlick_ggx(n_dot_v, roughness) * geometry_schlick_ggx(n_dot_l, roughness);
+}
+
+// F, the share of light reflected rather than refracted, rising towards grazing angles
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// F for the environment, the reflections of rough surfaces average over many halfway
+// vectors, so they don't brighten as much at grazing angles
+fn fresnel_schlick_roughness(cos_theta: f32, f0: vec3<f32>, roughness: f32) -> vec3<f32> {
+    return f0 + (max(vec3<f32>(1.0 - roughness), f0) - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+// the lights and the sun are far brighter than 1, reinhard squeezes them into range
+fn tonemap(color: vec3<f32>) -> vec3<f32> {
+    return color / (color + 1.0);
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let albedo = input.albedo;
+    let metallic = input.metallic;
+    // a perfect mirror makes D a spike that no light position ever hits
+    let roughness = max(input.roughness, 0.05);
+    let n = normalize(input.normal);
+
+    let v = normalize(camera.position.xyz - input.world_pos);
+    let n_dot_v = max(dot(n, v), 0.0001);
+    // dielectrics reflect about 4% head on, metals reflect in their own color
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+
+    var radiance_out = vec3<f32>(0.0);
+    for (var i = 0; i < NUM_LIGHTS; i = i + 1) {
+        let light = scene.lights[i];
+        let to_light = light.position.xyz - input.world_pos;
+        let l = normalize(to_light);
+        let h = normalize(v + l);
+        let n_dot_l = max(dot(n, l), 0.0);
+        let radiance = light.color.rgb / dot(to_light, to_light);
+
+        let f = fresnel_schlick(max(dot(h, v), 0.0), f0);
+        let specular = distribution_ggx(max(dot(n, h), 0.0), roughness)
+            * geometry_smith(n_dot_v, n_dot_l, roughness) * f
+            / (4.0 * n_dot_v * n_dot_l + 0.0001);
+        // what isn't reflected is refracted and scattered back out as diffuse, metals absorb it
+        let kd = (1.0 - f) * (1.0 - metallic);
+
+        radiance_out = radiance_out + (kd * albedo / PI + specular) * radiance * n_dot_l;
+    }
+
+    // the light from everywhere else, the environment
+    var ambient = vec3<f32>(0.03) * albedo;
+    if (scene.ibl != 0u) {
+        let f = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+        let kd = (1.0 - f) * (1.0 - metallic);
+        let diffuse = textureSample(t_irradiance, s_ibl, n).rgb * albedo;
+
+        // the split sum, the prefiltered light along the reflection times the brdf integral
+        let r = reflect(-v, n);
+        let prefiltered = textureSampleLevel(t_prefiltered, s_ibl, r, roughness * MAX_REFLECTION_LOD).rgb;
+        let brdf = textureSample(t_brdf_lut, s_ibl, vec2<f32>(n_dot_v, roughness)).rg;
+        let specular = prefiltered * (f * brdf.x + brdf.y);
+
+        ambient = kd * diffuse + specular;
+    }
+    let color = ambient + radiance_out;
+    return vec4<f32>(tonemap(color), 1.0);
+}
+
+// one small sphere per light, unlit in the light's color
+@vertex
+fn vs_light(input: VertexInput, @builtin(instance_index) index: u32) -> FragInput {
+    let light = scene.lights[index];
+    let world_pos = input.pos * 0.3 + light.position.xyz;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = input.normal;
+    // the hue of the light at full brightness
+    fragInput.albedo = light.color.rgb / max(light.color.r, max(light.color.g, light.color.b));
+    fragInput.metallic = 0.0;
+    fragInput.roughness = 0.0;
+    return fragInput;
+}
+
+@fragment
+fn fs_light(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.albedo, 1.0);
+}
+
+struct SkyOutput {
+    @location(0) clip_xy: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// one triangle over the screen at the far plane, z = w so it only fills what the spheres
+// left at the cleared depth
+@vertex
+fn vs_sky(@builtin(vertex_index) index: u32) -> SkyOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var output: SkyOutput;
+    output.clip_xy = uv * 2.0 - 1.0;
+    output.clip_position = vec4<f32>(output.clip_xy, 1.0, 1.0);
+    return output;
+}
+
+@fragment
+fn fs_sky(input: SkyOutput) -> @location(0) vec4<f32> {
+    let world = camera.inv_view_proj * vec4<f32>(input.clip_xy, 1.0, 1.0);
+    let dir = world.xyz / world.w - camera.position.xyz;
+
+    var color: vec3<f32>;
+    if (scene.background == 1u) {
+        color = textureSampleLevel(t_irradiance, s_ibl, dir, 0.0).rgb;
+    } else if (scene.background == 2u) {
+        color = textureSampleLevel(t_prefiltered, s_ibl, dir, BACKGROUND_LOD).rgb;
+    } else {
+        color = textureSampleLevel(t_environment, s_ibl, dir, 0.0).rgb;
+    }
+    return vec4<f32>(tonemap(color), 1.0);
+}
@@ -0,0 +1,71 @@
+use framework::reel::{Entry, Playlist, Reel};
+
+// every chapter built on `framework`, cross-fading from one to the next, t20-model and
+// t21-gltf only with `--features models`. page down / page up skip ahead and back, home
+// pauses on the current chapter
+fn main() {
+    framework::run::<Reel<Chapters>>("demo reel");
+}
+
+struct Chapters;
+
+impl Playlist for Chapters {
+    fn scenes() -> Vec<Entry> {
+        vec![
+            Entry::new::<t02_triangle::Triangle>(t02_triangle::TITLE),
+            Entry::new::<t03_shader::Shader>(t03_shader::TITLE),
+            Entry::new::<t04_texture::TexturedQuad>(t04_texture::TITLE),
+            Entry::new::<t05_transform::TransformedQuad>(t05_transform::TITLE),
+            Entry::new::<t06_coord::Coord>(t06_coord::TITLE),
+            Entry::new::<t08_panorama::Panorama>(t08_panorama::TITLE),
+            Entry::new::<t09_stereo::Stereo>(t09_stereo::TITLE),
+            Entry::new::<t12_topology::Topology>(t12_topology::TITLE),
+            Entry::new::<t13_culling::Culling>(t13_culling::TITLE),
+            Entry::new::<t14_depth_compare::DepthCompare>(t14_depth_compare::TITLE),
+            Entry::new::<t15_depth::Depth>(t15_depth::TITLE),
+            Entry::new::<t16_pixel_art::PixelArt>(t16_pixel_art::TITLE),
+            Entry::new::<t17_cube::Cube>(t17_cube::TITLE),
+            Entry::new::<t18_instancing::Instancing>(t18_instancing::TITLE),
+            Entry::new::<t19_checkerboard::Checkerboard>(t19_checkerboard::TITLE),
+            #[cfg(feature = "models")]
+            Entry::new::<t20_model::ModelViewer>(t20_model::TITLE),
+            #[cfg(feature = "models")]
+            Entry::new::<t21_gltf::GltfViewer>(t21_gltf::TITLE),
+            Entry::new::<t22_lighting::Lighting>(t22_lighting::TITLE),
+            Entry::new::<t23_point_lights::PointLights>(t23_point_lights::TITLE),
+            Entry::new::<t24_spotlight::Spotlight>(t24_spotlight::TITLE),
+            Entry::new::<t25_shadow::Shadow>(t25_shadow::TITLE),
+            Entry::new::<t26_point_shadow::PointShadow>(t26_point_shadow::TITLE),
+            Entry::new::<t27_normal_map::NormalMap>(t27_normal_map::TITLE),
+            Entry::new::<t28_parallax::Parallax>(t28_parallax::TITLE),
+            Entry::new::<t29_pbr::Pbr>(t29_pbr::TITLE),
+            Entry::new::<t30_ibl::ImageBasedLighting>(t30_ibl::TITLE),
+            Entry::new::<t32_skybox::Skybox>(t32_skybox::TITLE),
+            Entry::new::<t33_paint::Paint>(t33_paint::TITLE),
+            Entry::new::<t34_tile_stream::TileStream>(t34_tile_stream::TITLE),
+            Entry::new::<t35_hdr::Hdr>(t35_hdr::TITLE),
+            Entry::new::<t36_fractal::Fractal>(t36_fractal::TITLE),
+            Entry::new::<t37_bloom::Bloom>(t37_bloom::TITLE),
+            Entry::new::<t38_marching_cubes::MarchingCubes>(t38_marching_cubes::TITLE),
+            Entry::new::<t39_msaa::Msaa>(t39_msaa::TITLE),
+            Entry::new::<t40_grass::Grass>(t40_grass::TITLE),
+            Entry::new::<t41_impostors::Impostors>(t41_impostors::TITLE),
+            Entry::new::<t42_ssao::AmbientOcclusion>(t42_ssao::TITLE),
+            Entry::new::<t43_deferred::Deferred>(t43_deferred::TITLE),
+            Entry::new::<t44_forward_plus::ForwardPlus>(t44_forward_plus::TITLE),
+            Entry::new::<t45_render_to_texture::RenderToTexture>(t45_render_to_texture::TITLE),
+            Entry::new::<t46_post_process::PostProcess>(t46_post_process::TITLE),
+            Entry::new::<t47_motion_blur::MotionBlur>(t47_motion_blur::TITLE),
+            Entry::new::<t48_color_grading::ColorGrading>(t48_color_grading::TITLE),
+            Entry::new::<t49_toon::Toon>(t49_toon::TITLE),
+            Entry::new::<t50_outline::Outline>(t50_outline::TITLE),
+            Entry::new::<t51_mipmaps::Mipmaps>(t51_mipmaps::TITLE),
+            Entry::new::<t52_texture_array::TextureArray>(t52_texture_array::TITLE),
+            Entry::new::<t53_sampler::Sampler>(t53_sampler::TITLE),
+            Entry::new::<t54_permutations::ShaderPermutations>(t54_permutations::TITLE),
+            Entry::new::<t55_terrain::Terrain>(t55_terrain::TITLE),
+            Entry::new::<t56_netsync::NetSync>(t56_netsync::TITLE),
+            Entry::new::<t57_water::Water>(t57_water::TITLE),
+        ]
+    }
+}
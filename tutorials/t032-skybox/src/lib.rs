@@ -0,0 +1,443 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    assets::AssetSource,
+    camera::{Camera, CameraController, OrbitCameraController},
+    equirect,
+    texture::{DepthTexture, Texture},
+    watchdog,
+};
+use glam::Vec3;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "skybox, reflection, six faces";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+// of each face of the cube the panorama is turned into
+const PANORAMA_FACE_SIZE: u32 = 512;
+
+// in the cube's layer order, +x, -x, +y, -y, +z, -z
+const FACES: [&str; 6] = [
+    "skybox/px.png",
+    "skybox/nx.png",
+    "skybox/py.png",
+    "skybox/ny.png",
+    "skybox/pz.png",
+    "skybox/nz.png",
+];
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    inv_view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        let view_proj = camera.view_proj();
+        CameraUniform {
+            view_proj: view_proj.to_cols_array_2d(),
+            inv_view_proj: view_proj.inverse().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// cargo run -p t32-skybox -- path/to/panorama.hdr, without a path the bundled one is used
+fn load_panorama(assets: &AssetSource) -> image::DynamicImage {
+    let bytes = match std::env::args().nth(1) {
+        Some(path) => std::fs::read(&path)
+            .unwrap_or_else(|err| panic!("Fail to read {}: {}", path, err))
+            .into(),
+        None => assets.load("sky.hdr").expect("Fail to load sky.hdr"),
+    };
+    equirect::load_hdr(&bytes).expect("Fail to decode the panorama")
+}
+
+// a unit cube, one normal per face so the edges stay sharp
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    for (normal, u, v) in faces {
+        let base = vertices.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            vertices.push(Vertex {
+                pos: ((normal + u * su + v * sv) * 0.5).to_array(),
+                normal: normal.to_array(),
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    (vertices, indices)
+}
+
+pub struct Skybox {
+    reflect_pipeline: wgpu::RenderPipeline,
+    refract_pipeline: wgpu::RenderPipeline,
+    sky_pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    faces_bindgroup: wgpu::BindGroup,
+    panorama_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    refract: bool,
+    panorama: bool,
+}
+
+impl framework::App for Skybox {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.2,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 3., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // two skies, six faces in one cube texture, or a panorama turned into one
+        let assets = framework::assets!(
+            "src": "sky.hdr",
+            "skybox/px.png",
+            "skybox/nx.png",
+            "skybox/py.png",
+            "skybox/ny.png",
+            "skybox/pz.png",
+            "skybox/nz.png",
+        );
+        let faces = FACES.map(|name| {
+            let bytes = assets
+                .load(name)
+                .unwrap_or_else(|err| panic!("Fail to load {}: {}", name, err));
+            image::load_from_memory(&bytes)
+                .unwrap_or_else(|err| panic!("Fail to decode {}: {}", name, err))
+        });
+        let faces_sky = Texture::from_cube_images(device, queue, &faces, "sky_texture");
+        let panorama_sky = equirect::to_cube(
+            device,
+            queue,
+            &load_panorama(&assets),
+            PANORAMA_FACE_SIZE,
+            "panorama_sky_texture",
+        );
+
+        let sky_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("sky bindgroup layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::Cube,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let create_sky_bindgroup = |sky: &Texture| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("sky bind group"),
+                layout: &sky_bindgroup_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&sky.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&sky.sampler),
+                    },
+                ],
+            })
+        };
+        let faces_bindgroup = create_sky_bindgroup(&faces_sky);
+        let panorama_bindgroup = create_sky_bindgroup(&panorama_sky);
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_bindgroup_layout, // group 0, camera
+                &sky_bindgroup_layout,    // group 1, sky
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3],
+        };
+
+        // the sky is drawn last at depth 1, LessEqual lets it pass where the clear value is
+        // still there and nothing is written since nothing draws after it
+        let create_pipeline = |vs_entry_point,
+                               fs_entry_point,
+                               buffers: &[wgpu::VertexBufferLayout],
+                               depth_compare,
+                               depth_write_enabled| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(fs_entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vs_entry_point,
+                    buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs_entry_point,
+                    targets: &[Some(config.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DepthTexture::FORMAT,
+                    depth_write_enabled,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let reflect_pipeline = create_pipeline(
+            "vs_main",
+            "fs_reflect",
+            std::slice::from_ref(&vertex_buffer_layout),
+            wgpu::CompareFunction::Less,
+            true,
+        );
+        let refract_pipeline = create_pipeline(
+            "vs_main",
+            "fs_refract",
+            &[vertex_buffer_layout],
+            wgpu::CompareFunction::Less,
+            true,
+        );
+        let sky_pipeline = create_pipeline(
+            "vs_sky",
+            "fs_sky",
+            &[],
+            wgpu::CompareFunction::LessEqual,
+            false,
+        );
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!("press R to switch the cube between reflection and refraction, P to switch the sky");
+
+        Skybox {
+            reflect_pipeline,
+            refract_pipeline,
+            sky_pipeline,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            faces_bindgroup,
+            panorama_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            refract: false,
+            panorama: false,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::R => self.refract = !self.refract,
+                VirtualKeyCode::P => self.panorama = !self.panorama,
+                _ => return,
+            }
+
+            window.set_title(&format!(
+                "skybox, {}, {}",
+                if self.refract {
+                    "refraction"
+                } else {
+                    "reflection"
+                },
+                if self.panorama {
+                    "hdr panorama"
+                } else {
+                    "six faces"
+                }
+            ));
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            // every pixel ends up covered by the cube or the sky, the clear color never shows
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            // the panorama's sun is far above 1, it clips to white on the way to the surface
+            rpass.set_bind_group(
+                1,
+                if self.panorama {
+                    &self.panorama_bindgroup
+                } else {
+                    &self.faces_bindgroup
+                },
+                &[],
+            );
+
+            rpass.set_pipeline(if self.refract {
+                &self.refract_pipeline
+            } else {
+                &self.reflect_pipeline
+            });
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1);
+
+            // after the cube, so the depth test skips the sky wherever the cube is
+            rpass.set_pipeline(&self.sky_pipeline);
+            rpass.draw(0..3, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t32_skybox::Skybox>(t32_skybox::TITLE);
+}
@@ -0,0 +1,87 @@
+// a cube that mirrors or refracts the sky around it, and the sky itself drawn behind everything
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // back from clip space to world space, for the sky's view directions
+    inv_view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@group(1) @binding(0)
+var t_sky: texture_cube<f32>;
+@group(1) @binding(1)
+var s_sky: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> VertexOutput {
+    var output: VertexOutput;
+    output.clip_position = camera.view_proj * vec4<f32>(input.position, 1.0);
+    output.world_position = input.position;
+    output.normal = input.normal;
+    return output;
+}
+
+// the view ray bounced off the surface picks the texel of the sky it would have hit
+@fragment
+fn fs_reflect(input: VertexOutput) -> @location(0) vec4<f32> {
+    let incident = normalize(input.world_position - camera.position.xyz);
+    let dir = reflect(incident, normalize(input.normal));
+    return vec4<f32>(textureSample(t_sky, s_sky, dir).rgb, 1.0);
+}
+
+// glsl's refract, naga doesn't have it. past the critical angle the ray reflects instead
+fn refract_dir(incident: vec3<f32>, normal: vec3<f32>, eta: f32) -> vec3<f32> {
+    let cos_i = dot(normal, incident);
+    let k = 1.0 - eta * eta * (1.0 - cos_i * cos_i);
+    if (k < 0.0) {
+        return reflect(incident, normal);
+    }
+    return eta * incident - (eta * cos_i + sqrt(k)) * normal;
+}
+
+// air to glass, the ray bends towards the normal on the way in. only the entry is refracted,
+// the exit on the far side is left out
+@fragment
+fn fs_refract(input: VertexOutput) -> @location(0) vec4<f32> {
+    let incident = normalize(input.world_position - camera.position.xyz);
+    let dir = refract_dir(incident, normalize(input.normal), 1.0 / 1.52);
+    return vec4<f32>(textureSample(t_sky, s_sky, dir).rgb, 1.0);
+}
+
+struct SkyOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) clip_xy: vec2<f32>,
+};
+
+// a fullscreen triangle with z = w, so after the divide it sits exactly on the far plane and
+// only shows where nothing else was drawn
+@vertex
+fn vs_sky(@builtin(vertex_index) index: u32) -> SkyOutput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var output: SkyOutput;
+    output.clip_xy = uv * 2.0 - 1.0;
+    output.clip_position = vec4<f32>(output.clip_xy, 1.0, 1.0);
+    return output;
+}
+
+@fragment
+fn fs_sky(input: SkyOutput) -> @location(0) vec4<f32> {
+    let world = camera.inv_view_proj * vec4<f32>(input.clip_xy, 1.0, 1.0);
+    let dir = world.xyz / world.w - camera.position.xyz;
+    return vec4<f32>(textureSample(t_sky, s_sky, dir).rgb, 1.0);
+}
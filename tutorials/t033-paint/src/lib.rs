@@ -0,0 +1,334 @@
+use bytemuck::{Pod, Zeroable};
+use framework::{dynamic_texture::DynamicTexture, watchdog};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "paint, drag with the left mouse button";
+
+const CANVAS_SIZE: u32 = 512;
+// in canvas texels
+const BRUSH_RADIUS: f32 = 6.;
+// of the window's shorter side
+const CANVAS_COVERAGE: f32 = 0.9;
+const PALETTE: [(VirtualKeyCode, [u8; 4]); 4] = [
+    (VirtualKeyCode::Key1, [20, 20, 20, 255]),
+    (VirtualKeyCode::Key2, [220, 40, 40, 255]),
+    (VirtualKeyCode::Key3, [40, 160, 60, 255]),
+    (VirtualKeyCode::Key4, [40, 80, 220, 255]),
+];
+
+// matches `Quad` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct QuadUniform {
+    scale: [f32; 2],
+    _padding: [f32; 2],
+}
+
+impl QuadUniform {
+    fn new(width: u32, height: u32) -> QuadUniform {
+        let shorter = width.min(height) as f32;
+        QuadUniform {
+            scale: [
+                CANVAS_COVERAGE * shorter / width as f32,
+                CANVAS_COVERAGE * shorter / height as f32,
+            ],
+            _padding: [0.; 2],
+        }
+    }
+}
+
+// paper white with a faint grid, so strokes have something to line up against
+fn blank_canvas() -> image::RgbaImage {
+    image::RgbaImage::from_fn(CANVAS_SIZE, CANVAS_SIZE, |x, y| {
+        if x % 32 == 0 || y % 32 == 0 {
+            image::Rgba([225, 225, 230, 255])
+        } else {
+            image::Rgba([245, 245, 240, 255])
+        }
+    })
+}
+
+pub struct Paint {
+    pipeline: wgpu::RenderPipeline,
+    quad: QuadUniform,
+    quad_buffer: wgpu::Buffer,
+    quad_bindgroup: wgpu::BindGroup,
+    canvas: DynamicTexture,
+    canvas_bindgroup: wgpu::BindGroup,
+    window_size: (u32, u32),
+    /// the cursor in canvas texels, last time it moved
+    cursor: Option<(f32, f32)>,
+    painting: bool,
+    color: [u8; 4],
+}
+
+impl Paint {
+    // window pixels to canvas texels, the inverse of the quad in vs_main
+    fn to_canvas(&self, x: f64, y: f64) -> (f32, f32) {
+        let (width, height) = self.window_size;
+        let ndc_x = 2. * x as f32 / width as f32 - 1.;
+        let ndc_y = 1. - 2. * y as f32 / height as f32;
+        let u = (ndc_x / self.quad.scale[0] + 1.) / 2.;
+        let v = (1. - ndc_y / self.quad.scale[1]) / 2.;
+        (u * CANVAS_SIZE as f32, v * CANVAS_SIZE as f32)
+    }
+
+    // a disc of the brush color, only the texels it touches become dirty
+    fn stamp(&mut self, (cx, cy): (f32, f32)) {
+        let size = CANVAS_SIZE as f32;
+        let x0 = (cx - BRUSH_RADIUS).floor().clamp(0., size) as u32;
+        let y0 = (cy - BRUSH_RADIUS).floor().clamp(0., size) as u32;
+        let x1 = (cx + BRUSH_RADIUS).ceil().clamp(0., size) as u32;
+        let y1 = (cy + BRUSH_RADIUS).ceil().clamp(0., size) as u32;
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let dx = x as f32 + 0.5 - cx;
+                let dy = y as f32 + 0.5 - cy;
+                if dx * dx + dy * dy <= BRUSH_RADIUS * BRUSH_RADIUS {
+                    self.canvas.image.put_pixel(x, y, image::Rgba(self.color));
+                }
+            }
+        }
+        self.canvas.mark_dirty(x0, y0, x1 - x0, y1 - y0);
+    }
+
+    // stamps every half radius from `from` to `to`, fast drags stay a line instead of dots
+    fn stroke(&mut self, from: (f32, f32), to: (f32, f32)) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let steps = ((dx * dx + dy * dy).sqrt() / (BRUSH_RADIUS / 2.))
+            .ceil()
+            .max(1.) as u32;
+        for i in 1..=steps {
+            let t = i as f32 / steps as f32;
+            self.stamp((from.0 + dx * t, from.1 + dy * t));
+        }
+    }
+
+    fn update_title(&self, window: &Window) {
+        if let Some(dirty) = self.canvas.dirty() {
+            window.set_title(&format!(
+                "paint, uploading {}x{} of {}x{}",
+                dirty.width, dirty.height, CANVAS_SIZE, CANVAS_SIZE
+            ));
+        }
+    }
+}
+
+impl framework::App for Paint {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        let quad = QuadUniform::new(config.width, config.height);
+        let quad_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Quad Buffer"),
+            contents: bytemuck::bytes_of(&quad),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let quad_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("quad bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let quad_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("quad bind group"),
+            layout: &quad_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: quad_buffer.as_entire_binding(),
+            }],
+        });
+
+        // canvas
+        let canvas = DynamicTexture::new(device, queue, blank_canvas(), "canvas_texture");
+
+        let canvas_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("canvas bindgroup layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let canvas_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("canvas bind group"),
+            layout: &canvas_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&canvas.texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&canvas.texture.sampler),
+                },
+            ],
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &quad_bindgroup_layout,   // group 0, quad
+                &canvas_bindgroup_layout, // group 1, canvas
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("paint"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        info!("drag with the left mouse button to paint, 1-4 pick a color, C clears");
+
+        Paint {
+            pipeline,
+            quad,
+            quad_buffer,
+            quad_bindgroup,
+            canvas,
+            canvas_bindgroup,
+            window_size: (config.width, config.height),
+            cursor: None,
+            painting: false,
+            color: PALETTE[0].1,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        self.window_size = (config.width, config.height);
+        self.quad = QuadUniform::new(config.width, config.height);
+        watchdog::write_buffer(queue, &self.quad_buffer, 0, bytemuck::bytes_of(&self.quad));
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let cursor = self.to_canvas(position.x, position.y);
+                if self.painting {
+                    self.stroke(self.cursor.unwrap_or(cursor), cursor);
+                    self.update_title(window);
+                }
+                self.cursor = Some(cursor);
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.painting = state == ElementState::Pressed;
+                if let (true, Some(cursor)) = (self.painting, self.cursor) {
+                    self.stamp(cursor);
+                    self.update_title(window);
+                }
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => {
+                if key == VirtualKeyCode::C {
+                    self.canvas.image = blank_canvas();
+                    self.canvas.mark_all_dirty();
+                    self.update_title(window);
+                } else if let Some((_, color)) = PALETTE.iter().find(|(k, _)| *k == key) {
+                    self.color = *color;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        // whatever was painted since the last frame, usually a few brush sized squares
+        self.canvas.upload(queue);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.2,
+                    g: 0.2,
+                    b: 0.22,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.quad_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.canvas_bindgroup, &[]);
+            rpass.draw(0..4, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
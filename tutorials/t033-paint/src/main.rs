@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t33_paint::Paint>(t33_paint::TITLE);
+}
@@ -0,0 +1,35 @@
+// the canvas, a square quad in the middle of the window with the painted texture on it
+
+struct Quad {
+    // half the quad's size in clip space, smaller along the longer side of the window
+    scale: vec2<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> quad: Quad;
+
+@group(1) @binding(0)
+var t_canvas: texture_2d<f32>;
+@group(1) @binding(1)
+var s_canvas: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coord: vec2<f32>,
+};
+
+// a triangle strip of four corners, no vertex buffer
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    let uv = vec2<f32>(f32(index & 1u), f32(index >> 1u));
+
+    var output: VertexOutput;
+    output.clip_position = vec4<f32>((uv * 2.0 - 1.0) * vec2<f32>(1.0, -1.0) * quad.scale, 0.0, 1.0);
+    output.tex_coord = uv;
+    return output;
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_canvas, s_canvas, input.tex_coord);
+}
@@ -0,0 +1,308 @@
+mod worker;
+
+use bytemuck::{Pod, Zeroable};
+use framework::{texture::Texture, watchdog};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+use worker::{Stats, Tile, IMAGE_SIZE, TILE_SIZE};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "tile stream, 4 tiles per frame";
+
+// of the window's shorter side
+const CANVAS_COVERAGE: f32 = 0.9;
+const STATS_INTERVAL: f32 = 1.;
+// tiles the render thread takes off the channel per frame, up and down change it. a whole
+// image is 64 tiles, below what the worker makes per frame it stalls on the full channel
+const START_BUDGET: u32 = 4;
+const MAX_BUDGET: u32 = 64;
+
+// matches `Quad` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct QuadUniform {
+    scale: [f32; 2],
+    _padding: [f32; 2],
+}
+
+impl QuadUniform {
+    fn new(width: u32, height: u32) -> QuadUniform {
+        let shorter = width.min(height) as f32;
+        QuadUniform {
+            scale: [
+                CANVAS_COVERAGE * shorter / width as f32,
+                CANVAS_COVERAGE * shorter / height as f32,
+            ],
+            _padding: [0.; 2],
+        }
+    }
+}
+
+pub struct TileStream {
+    pipeline: wgpu::RenderPipeline,
+    quad_buffer: wgpu::Buffer,
+    quad_bindgroup: wgpu::BindGroup,
+    image: Texture,
+    image_bindgroup: wgpu::BindGroup,
+    tiles: Receiver<Tile>,
+    stats: Arc<Stats>,
+    budget: u32,
+    uploaded: u64,
+    /// worker counters and `uploaded` when the stats were last logged
+    last_counts: (u64, u64, u64),
+    /// real seconds since then
+    since_stats: f32,
+}
+
+impl TileStream {
+    fn upload(&self, queue: &wgpu::Queue, tile: &Tile) {
+        watchdog::write_texture(
+            queue,
+            wgpu::ImageCopyTexture {
+                texture: &self.image.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: tile.x,
+                    y: tile.y,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &tile.pixels,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * TILE_SIZE),
+                rows_per_image: std::num::NonZeroU32::new(TILE_SIZE),
+            },
+            wgpu::Extent3d {
+                width: TILE_SIZE,
+                height: TILE_SIZE,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+impl framework::App for TileStream {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        let quad = QuadUniform::new(config.width, config.height);
+        let quad_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Quad Buffer"),
+            contents: bytemuck::bytes_of(&quad),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let quad_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("quad bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let quad_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("quad bind group"),
+            layout: &quad_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: quad_buffer.as_entire_binding(),
+            }],
+        });
+
+        // image, black until the first tiles arrive
+        let image = Texture::from_image(
+            device,
+            queue,
+            &image::DynamicImage::new_rgba8(IMAGE_SIZE, IMAGE_SIZE),
+            "stream_texture",
+        );
+
+        let image_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("image bindgroup layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let image_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("image bind group"),
+            layout: &image_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&image.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&image.sampler),
+                },
+            ],
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &quad_bindgroup_layout,  // group 0, quad
+                &image_bindgroup_layout, // group 1, image
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("tile stream"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let (tiles, stats) = worker::spawn();
+
+        info!("up / down change how many tiles are taken per frame");
+
+        TileStream {
+            pipeline,
+            quad_buffer,
+            quad_bindgroup,
+            image,
+            image_bindgroup,
+            tiles,
+            stats,
+            budget: START_BUDGET,
+            uploaded: 0,
+            last_counts: (0, 0, 0),
+            since_stats: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let quad = QuadUniform::new(config.width, config.height);
+        watchdog::write_buffer(queue, &self.quad_buffer, 0, bytemuck::bytes_of(&quad));
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::Up => self.budget = (self.budget * 2).min(MAX_BUDGET),
+                VirtualKeyCode::Down => self.budget = (self.budget / 2).max(1),
+                _ => return,
+            }
+            window.set_title(&format!("tile stream, {} tiles per frame", self.budget));
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        // never more than the budget, whatever is left waits in the channel and holds the
+        // worker back
+        for tile in self.tiles.try_iter().take(self.budget as usize) {
+            self.upload(queue, &tile);
+            self.uploaded += 1;
+        }
+
+        self.since_stats += frame.real_delta;
+        let elapsed = self.since_stats;
+        if elapsed >= STATS_INTERVAL {
+            let tiles = self.stats.tiles.load(Ordering::Relaxed);
+            let stalls = self.stats.stalls.load(Ordering::Relaxed);
+            let (last_tiles, last_stalls, last_uploaded) = self.last_counts;
+            info!(
+                "{:.0} tiles/s made, {:.0} tiles/s uploaded, {:.0} stalls/s on a full channel",
+                (tiles - last_tiles) as f32 / elapsed,
+                (self.uploaded - last_uploaded) as f32 / elapsed,
+                (stalls - last_stalls) as f32 / elapsed
+            );
+            self.last_counts = (tiles, stalls, self.uploaded);
+            self.since_stats = 0.;
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.2,
+                    g: 0.2,
+                    b: 0.22,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.quad_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.image_bindgroup, &[]);
+            rpass.draw(0..4, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t34_tile_stream::TileStream>(t34_tile_stream::TITLE);
+}
@@ -0,0 +1,126 @@
+// the producer side: a thread that keeps zooming into the mandelbrot set and sends every image
+// as tiles through a bounded channel.
+//
+// the channel holds at most `CHANNEL_CAPACITY` tiles. when the render thread takes them slower
+// than they're made, `try_send` finds it full, the stall is counted and the worker blocks in
+// `send` until a slot frees up, so it never runs more than a few tiles ahead of the gpu.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+
+pub const IMAGE_SIZE: u32 = 512;
+/// `IMAGE_SIZE` is a multiple of it
+pub const TILE_SIZE: u32 = 64;
+const CHANNEL_CAPACITY: usize = 8;
+
+// somewhere along the seahorse valley, detailed all the way down to f64's precision
+const CENTER: (f64, f64) = (-0.743643887037151, 0.131825904205330);
+const START_WIDTH: f64 = 3.;
+// f64 runs out of digits around here, the zoom starts over
+const END_WIDTH: f64 = 1e-11;
+const ZOOM_PER_IMAGE: f64 = 0.9;
+
+/// `TILE_SIZE` x `TILE_SIZE` rgba8 texels, `x` and `y` are its top left corner in the image
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// counted by the worker, read by the render thread
+#[derive(Default)]
+pub struct Stats {
+    pub tiles: AtomicU64,
+    /// sends that found the channel full and had to wait
+    pub stalls: AtomicU64,
+}
+
+/// starts the worker, it stops by itself once the receiver is dropped
+pub fn spawn() -> (Receiver<Tile>, Arc<Stats>) {
+    let (sender, receiver) = mpsc::sync_channel(CHANNEL_CAPACITY);
+    let stats = Arc::new(Stats::default());
+
+    let worker_stats = stats.clone();
+    thread::spawn(move || run(sender, &worker_stats));
+
+    (receiver, stats)
+}
+
+fn run(sender: SyncSender<Tile>, stats: &Stats) {
+    let mut width = START_WIDTH;
+    loop {
+        for y in (0..IMAGE_SIZE).step_by(TILE_SIZE as usize) {
+            for x in (0..IMAGE_SIZE).step_by(TILE_SIZE as usize) {
+                let tile = render_tile(x, y, width);
+                match sender.try_send(tile) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(tile)) => {
+                        stats.stalls.fetch_add(1, Ordering::Relaxed);
+                        if sender.send(tile).is_err() {
+                            return;
+                        }
+                    }
+                    Err(TrySendError::Disconnected(_)) => return,
+                }
+                stats.tiles.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        width *= ZOOM_PER_IMAGE;
+        if width < END_WIDTH {
+            width = START_WIDTH;
+        }
+    }
+}
+
+// `width` is how much of the complex plane the whole image spans
+fn render_tile(x: u32, y: u32, width: f64) -> Tile {
+    let texel = width / IMAGE_SIZE as f64;
+    // deeper zooms need more iterations before the edge of the set shows
+    let max_iterations = 100 + (50. * (START_WIDTH / width).log2()) as u32;
+
+    let mut pixels = Vec::with_capacity((TILE_SIZE * TILE_SIZE * 4) as usize);
+    for ty in y..y + TILE_SIZE {
+        for tx in x..x + TILE_SIZE {
+            let cr = CENTER.0 + (tx as f64 + 0.5 - IMAGE_SIZE as f64 / 2.) * texel;
+            let ci = CENTER.1 - (ty as f64 + 0.5 - IMAGE_SIZE as f64 / 2.) * texel;
+            pixels.extend_from_slice(&color(escape_time(cr, ci, max_iterations)));
+        }
+    }
+
+    Tile { x, y, pixels }
+}
+
+// fractional iteration count where z left the radius 2 circle, None inside the set
+fn escape_time(cr: f64, ci: f64, max_iterations: u32) -> Option<f64> {
+    let (mut zr, mut zi) = (0f64, 0f64);
+    for i in 0..max_iterations {
+        let zr2 = zr * zr;
+        let zi2 = zi * zi;
+        if zr2 + zi2 > 256. {
+            // smooth coloring, removes the bands between whole iteration counts
+            let log_z = (zr2 + zi2).ln() / 2.;
+            return Some(i as f64 + 1. - (log_z / std::f64::consts::LN_2).log2());
+        }
+        zi = 2. * zr * zi + ci;
+        zr = zr2 - zi2 + cr;
+    }
+    None
+}
+
+// a cosine palette cycling every 32 iterations, black inside the set
+fn color(escape: Option<f64>) -> [u8; 4] {
+    match escape {
+        Some(t) => {
+            let t = t / 32.;
+            let channel = |phase: f64| {
+                let value = 0.5 + 0.5 * (std::f64::consts::TAU * (t + phase)).cos();
+                (value * 255.) as u8
+            };
+            [channel(0.), channel(0.1), channel(0.2), 255]
+        }
+        None => [0, 0, 0, 255],
+    }
+}
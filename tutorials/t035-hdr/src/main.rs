@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t35_hdr::Hdr>(t35_hdr::TITLE);
+}
@@ -0,0 +1,318 @@
+use bytemuck::{Pod, Zeroable};
+use framework::watchdog;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{
+        ElementState, KeyboardInput, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent,
+    },
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "fractal, mandelbrot, 256 iterations, f32, zoom 1e0";
+
+// across the window's shorter side, the whole set fits
+const START_WIDTH: f64 = 3.5;
+const MANDELBROT_CENTER: [f64; 2] = [-0.6, 0.];
+const ZOOM_PER_LINE: f64 = 0.8;
+const START_ITERATIONS: u32 = 256;
+const MAX_ITERATIONS: u32 = 8192;
+// palettes in shader.wgsl
+const PALETTES: u32 = 4;
+
+// matches `Fractal` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct FractalUniform {
+    center_hi: [f32; 2],
+    center_lo: [f32; 2],
+    julia_c: [f32; 2],
+    resolution: [f32; 2],
+    pixel_size: f32,
+    max_iterations: u32,
+    palette: u32,
+    julia: u32,
+    double_single: u32,
+    _padding: [u32; 3],
+}
+
+// `value` as hi + lo, hi the nearest f32 and lo what it missed
+fn split(value: f64) -> (f32, f32) {
+    let hi = value as f32;
+    (hi, (value - hi as f64) as f32)
+}
+
+// the part of the complex plane on screen, f64 on the cpu
+#[derive(Clone, Copy)]
+struct View {
+    center: [f64; 2],
+    pixel_size: f64,
+}
+
+impl View {
+    fn new(center: [f64; 2], (width, height): (u32, u32)) -> View {
+        View {
+            center,
+            pixel_size: START_WIDTH / width.min(height) as f64,
+        }
+    }
+}
+
+pub struct Fractal {
+    pipeline: wgpu::RenderPipeline,
+    fractal_buffer: wgpu::Buffer,
+    fractal_bindgroup: wgpu::BindGroup,
+    view: View,
+    /// where the mandelbrot view was while a julia set is showing
+    mandelbrot_view: View,
+    /// c of the julia set showing, `None` for the mandelbrot set
+    julia: Option<[f64; 2]>,
+    window_size: (u32, u32),
+    /// in window pixels
+    cursor: (f64, f64),
+    dragging: bool,
+    max_iterations: u32,
+    palette: u32,
+    double_single: bool,
+}
+
+impl Fractal {
+    // window pixels to the complex plane, y flips since the imaginary axis points up
+    fn to_plane(&self, x: f64, y: f64) -> [f64; 2] {
+        let (width, height) = self.window_size;
+        [
+            self.view.center[0] + (x - width as f64 / 2.) * self.view.pixel_size,
+            self.view.center[1] - (y - height as f64 / 2.) * self.view.pixel_size,
+        ]
+    }
+
+    fn uniform(&self) -> FractalUniform {
+        let (center_x_hi, center_x_lo) = split(self.view.center[0]);
+        let (center_y_hi, center_y_lo) = split(self.view.center[1]);
+        let julia_c = self.julia.unwrap_or_default();
+        FractalUniform {
+            center_hi: [center_x_hi, center_y_hi],
+            center_lo: [center_x_lo, center_y_lo],
+            julia_c: [julia_c[0] as f32, julia_c[1] as f32],
+            resolution: [self.window_size.0 as f32, self.window_size.1 as f32],
+            pixel_size: self.view.pixel_size as f32,
+            max_iterations: self.max_iterations,
+            palette: self.palette,
+            julia: self.julia.is_some() as u32,
+            double_single: self.double_single as u32,
+            _padding: [0; 3],
+        }
+    }
+
+    fn update_title(&self, window: &Window) {
+        let (width, height) = self.window_size;
+        window.set_title(&format!(
+            "fractal, {}, {} iterations, {}, zoom {:.0e}",
+            if self.julia.is_some() {
+                "julia"
+            } else {
+                "mandelbrot"
+            },
+            self.max_iterations,
+            if self.double_single {
+                "double-single"
+            } else {
+                "f32"
+            },
+            START_WIDTH / (self.view.pixel_size * width.min(height) as f64)
+        ));
+    }
+}
+
+impl framework::App for Fractal {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // rewritten every frame from the view, zeroes until then
+        let fractal_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Fractal Buffer"),
+            contents: bytemuck::bytes_of(&FractalUniform::zeroed()),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let fractal_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("fractal bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let fractal_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("fractal bind group"),
+            layout: &fractal_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: fractal_buffer.as_entire_binding(),
+            }],
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&fractal_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("fractal"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        info!("drag to pan, scroll to zoom");
+        info!("press j for the julia set of the point under the cursor, again to go back");
+        info!("press d for double-single math in deep zooms, p for the next palette");
+        info!("up / down double and halve the iterations");
+
+        let window_size = (config.width, config.height);
+        let view = View::new(MANDELBROT_CENTER, window_size);
+        Fractal {
+            pipeline,
+            fractal_buffer,
+            fractal_bindgroup,
+            view,
+            mandelbrot_view: view,
+            julia: None,
+            window_size,
+            cursor: (0., 0.),
+            dragging: false,
+            max_iterations: START_ITERATIONS,
+            palette: 0,
+            double_single: false,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        // the pixel size stays, a bigger window shows more of the plane
+        self.window_size = (config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                if self.dragging {
+                    // the plane follows the cursor
+                    self.view.center[0] -= (position.x - self.cursor.0) * self.view.pixel_size;
+                    self.view.center[1] += (position.y - self.cursor.1) * self.view.pixel_size;
+                }
+                self.cursor = (position.x, position.y);
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => self.dragging = state == ElementState::Pressed,
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, lines) => lines as f64,
+                    // touchpads scroll in pixels, call ~20 pixels a line
+                    MouseScrollDelta::PixelDelta(position) => position.y / 20.,
+                };
+                // the point under the cursor stays where it is
+                let anchor = self.to_plane(self.cursor.0, self.cursor.1);
+                let factor = ZOOM_PER_LINE.powf(lines);
+                for (center, anchor) in self.view.center.iter_mut().zip(anchor) {
+                    *center = anchor + (*center - anchor) * factor;
+                }
+                self.view.pixel_size *= factor;
+                self.update_title(window);
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } => {
+                match key {
+                    // the julia set of the point under the cursor, and back
+                    VirtualKeyCode::J => match self.julia {
+                        None => {
+                            self.julia = Some(self.to_plane(self.cursor.0, self.cursor.1));
+                            self.mandelbrot_view = self.view;
+                            self.view = View::new([0., 0.], self.window_size);
+                        }
+                        Some(_) => {
+                            self.julia = None;
+                            self.view = self.mandelbrot_view;
+                        }
+                    },
+                    VirtualKeyCode::D => self.double_single = !self.double_single,
+                    VirtualKeyCode::P => self.palette = (self.palette + 1) % PALETTES,
+                    VirtualKeyCode::Up => {
+                        self.max_iterations = (self.max_iterations * 2).min(MAX_ITERATIONS)
+                    }
+                    VirtualKeyCode::Down => {
+                        self.max_iterations = (self.max_iterations / 2).max(START_ITERATIONS / 8)
+                    }
+                    _ => return,
+                }
+                self.update_title(window);
+            }
+            _ => {}
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        watchdog::write_buffer(
+            queue,
+            &self.fractal_buffer,
+            0,
+            bytemuck::bytes_of(&self.uniform()),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.fractal_bindgroup, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
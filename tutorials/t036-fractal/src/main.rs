@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t36_fractal::Fractal>(t36_fractal::TITLE);
+}
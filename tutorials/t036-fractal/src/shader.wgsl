@@ -0,0 +1,170 @@
+// the mandelbrot and julia sets, one fullscreen triangle and everything else per pixel
+
+struct FragInput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Fractal {
+    // the view's center, split in two f32s whose sum is the f64 the cpu holds
+    center_hi: vec2<f32>,
+    center_lo: vec2<f32>,
+    // c of the julia set
+    julia_c: vec2<f32>,
+    resolution: vec2<f32>,
+    // how much of the complex plane one pixel covers
+    pixel_size: f32,
+    max_iterations: u32,
+    palette: u32,
+    // 0 mandelbrot, 1 julia
+    julia: u32,
+    // 0 plain f32, 1 double-single
+    double_single: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> fractal: Fractal;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return fragInput;
+}
+
+// double-single: a number is the unevaluated sum hi + lo of two f32s, lo holding the bits hi
+// has no room for. 48 bits of mantissa instead of 24, enough to zoom about 1e7 times deeper.
+// every step computes the rounding error of the f32 operation exactly and carries it along,
+// after Dekker and Knuth. a driver that treats float math as associative folds the error
+// terms to 0, and the deep zoom looks no better than plain f32
+
+// a + b as hi + lo, only valid when |a| >= |b|
+fn quick_two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = a + b;
+    return vec2<f32>(s, b - (s - a));
+}
+
+// a + b as hi + lo, for any a and b
+fn two_sum(a: f32, b: f32) -> vec2<f32> {
+    let s = a + b;
+    let v = s - a;
+    return vec2<f32>(s, (a - (s - v)) + (b - v));
+}
+
+// a into two halves of 12 bits each, so products of halves are exact in f32
+fn split(a: f32) -> vec2<f32> {
+    let t = 4097.0 * a;
+    let hi = t - (t - a);
+    return vec2<f32>(hi, a - hi);
+}
+
+// a * b as hi + lo
+fn two_prod(a: f32, b: f32) -> vec2<f32> {
+    let p = a * b;
+    let a_s = split(a);
+    let b_s = split(b);
+    let err = ((a_s.x * b_s.x - p) + a_s.x * b_s.y + a_s.y * b_s.x) + a_s.y * b_s.y;
+    return vec2<f32>(p, err);
+}
+
+fn ds_add(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let s = two_sum(a.x, b.x);
+    let t = two_sum(a.y, b.y);
+    let r = quick_two_sum(s.x, s.y + t.x);
+    return quick_two_sum(r.x, r.y + t.y);
+}
+
+fn ds_mul(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+    let p = two_prod(a.x, b.x);
+    return quick_two_sum(p.x, p.y + (a.x * b.y + a.y * b.x));
+}
+
+// escape radius 16, large enough for the smooth iteration count to be smooth
+let bailout = 256.0;
+
+// z = z * z + c until |z| passes 16, the fractional iteration count or -1 inside the set
+fn escape_f32(z0: vec2<f32>, c: vec2<f32>) -> f32 {
+    var z = z0;
+    for (var i = 0u; i < fractal.max_iterations; i = i + 1u) {
+        let z2 = z * z;
+        if (z2.x + z2.y > bailout) {
+            // smooth coloring, removes the bands between whole iteration counts
+            return f32(i) + 1.0 - log2(log2(z2.x + z2.y) / 2.0);
+        }
+        z = vec2<f32>(z2.x - z2.y, 2.0 * z.x * z.y) + c;
+    }
+    return -1.0;
+}
+
+// the same in double-single, real and imaginary parts each a hi + lo pair
+fn escape_ds(z0_re: vec2<f32>, z0_im: vec2<f32>, c_re: vec2<f32>, c_im: vec2<f32>) -> f32 {
+    var re = z0_re;
+    var im = z0_im;
+    for (var i = 0u; i < fractal.max_iterations; i = i + 1u) {
+        let re2 = ds_mul(re, re);
+        let im2 = ds_mul(im, im);
+        // |z| only decides when to stop, the hi parts are plenty for that
+        let length2 = re2.x + im2.x;
+        if (length2 > bailout) {
+            return f32(i) + 1.0 - log2(log2(length2) / 2.0);
+        }
+        // doubling is exact, both halves just shift their exponent
+        im = ds_add(2.0 * ds_mul(re, im), c_im);
+        re = ds_add(ds_add(re2, -im2), c_re);
+    }
+    return -1.0;
+}
+
+// Inigo Quilez's cosine palettes, a + b * cos(2 pi (c * t + d))
+fn palette(t: f32) -> vec3<f32> {
+    var d: vec3<f32>;
+    switch fractal.palette {
+        case 1u: {
+            d = vec3<f32>(0.0, 0.1, 0.2);
+        }
+        case 2u: {
+            d = vec3<f32>(0.3, 0.2, 0.2);
+        }
+        case 3u: {
+            d = vec3<f32>(0.8, 0.9, 0.3);
+        }
+        default: {
+            d = vec3<f32>(0.5, 0.6, 0.7);
+        }
+    }
+    return 0.5 + 0.5 * cos(6.28318 * (t + d));
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    // pixels from the middle of the window, y up like the imaginary axis
+    let offset = (input.clip_position.xy - fractal.resolution / 2.0) * vec2<f32>(1.0, -1.0)
+        * fractal.pixel_size;
+
+    var escape: f32;
+    if (fractal.double_single == 0u) {
+        // center_lo is dropped, so past ~1e-7 of zoom neighbouring pixels round to the same c
+        let p = fractal.center_hi + offset;
+        if (fractal.julia == 0u) {
+            escape = escape_f32(vec2<f32>(0.0), p);
+        } else {
+            escape = escape_f32(p, fractal.julia_c);
+        }
+    } else {
+        // only the center needs the extra precision, the offset from it is small and exact
+        let re = ds_add(vec2<f32>(fractal.center_hi.x, fractal.center_lo.x), vec2<f32>(offset.x, 0.0));
+        let im = ds_add(vec2<f32>(fractal.center_hi.y, fractal.center_lo.y), vec2<f32>(offset.y, 0.0));
+        if (fractal.julia == 0u) {
+            escape = escape_ds(vec2<f32>(0.0), vec2<f32>(0.0), re, im);
+        } else {
+            escape = escape_ds(re, im, vec2<f32>(fractal.julia_c.x, 0.0), vec2<f32>(fractal.julia_c.y, 0.0));
+        }
+    }
+
+    if (escape < 0.0) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    // one trip through the palette every 64 iterations
+    return vec4<f32>(palette(escape / 64.0), 1.0);
+}
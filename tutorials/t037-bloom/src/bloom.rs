@@ -0,0 +1,322 @@
+// bloom: light bright enough to glare bleeds into its surroundings.
+//
+// a wide blur straight on the full size scene would take hundreds of taps per pixel, instead
+// the bright part is shrunk through a chain of mips, each half the size of the one before, and
+// grown back up, each step adding a small blur on top of the last:
+// - prefilter, the scene down to the first mip, keeping only what's above the threshold
+// - downsample, each mip into the next with a 13 tap filter
+// - upsample, from the smallest mip back up, each added onto the next larger one
+// - composite, the first mip added onto the scene, scaled by the intensity
+//
+// the mips are `framework::targets::Targets`, they follow the window on their own.
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    pass_graph::PassGraph,
+    screenshot::DumpTarget,
+    targets::{TargetDesc, Targets},
+    texture::Texture,
+    watchdog,
+};
+use wgpu::include_wgsl;
+
+use wgpu::util::DeviceExt;
+
+/// the smallest is 1/64 of the window, blurry enough to reach across a good part of it
+const MIPS: usize = 6;
+const MIP_LABELS: [&str; MIPS] = [
+    "bloom_mip_1/2",
+    "bloom_mip_1/4",
+    "bloom_mip_1/8",
+    "bloom_mip_1/16",
+    "bloom_mip_1/32",
+    "bloom_mip_1/64",
+];
+
+// matches `Bloom` in bloom.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct BloomUniform {
+    threshold: f32,
+    knee: f32,
+    intensity: f32,
+    _padding: f32,
+}
+
+pub struct Bloom {
+    pub threshold: f32,
+    pub knee: f32,
+    pub intensity: f32,
+    mips: Targets,
+    layout: wgpu::BindGroupLayout,
+    prefilter: wgpu::RenderPipeline,
+    downsample: wgpu::RenderPipeline,
+    upsample: wgpu::RenderPipeline,
+    composite: wgpu::RenderPipeline,
+    buffer: wgpu::Buffer,
+    scene_bindgroup: wgpu::BindGroup,
+    /// one per mip, reading it
+    mip_bindgroups: Vec<wgpu::BindGroup>,
+}
+
+impl Bloom {
+    /// `scene` is the `format` target the scene draws into, and the one the bloom is added
+    /// onto. the mips are `format` too, [`framework::tonemap::hdr_format`] picks it
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        scene: &Texture,
+        width: u32,
+        height: u32,
+    ) -> Bloom {
+        let (threshold, knee, intensity) = (1., 0.5, 0.3);
+        let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Bloom Buffer"),
+            contents: bytemuck::bytes_of(&BloomUniform {
+                threshold,
+                knee,
+                intensity,
+                _padding: 0.,
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let descs = MIP_LABELS
+            .iter()
+            .enumerate()
+            .map(|(i, label)| TargetDesc::new(format, 2 << i, label))
+            .collect();
+        let mips = Targets::new(device, width, height, descs);
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("bloom bindgroup layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("bloom.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+
+        // the passes differ in their fragment shader and in whether they replace the target
+        // or add onto it
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let create_pipeline = |fs_entry_point, blend: Option<wgpu::BlendState>| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(fs_entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs_entry_point,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let add = Some(wgpu::BlendState {
+            color: additive,
+            alpha: additive,
+        });
+        let prefilter = create_pipeline("fs_prefilter", None);
+        let downsample = create_pipeline("fs_downsample", None);
+        let upsample = create_pipeline("fs_upsample", add);
+        let composite = create_pipeline("fs_composite", add);
+
+        let scene_bindgroup = create_bindgroup(device, &layout, scene, &buffer);
+        let mip_bindgroups = mips
+            .iter()
+            .map(|mip| create_bindgroup(device, &layout, mip, &buffer))
+            .collect();
+
+        Bloom {
+            threshold,
+            knee,
+            intensity,
+            mips,
+            layout,
+            prefilter,
+            downsample,
+            upsample,
+            composite,
+            buffer,
+            scene_bindgroup,
+            mip_bindgroups,
+        }
+    }
+
+    /// call with the recreated scene target after the window resized
+    pub fn resize(&mut self, device: &wgpu::Device, scene: &Texture, width: u32, height: u32) {
+        self.scene_bindgroup = create_bindgroup(device, &self.layout, scene, &self.buffer);
+        if self.mips.resize(device, width, height) {
+            self.mip_bindgroups = self
+                .mips
+                .iter()
+                .map(|mip| create_bindgroup(device, &self.layout, mip, &self.buffer))
+                .collect();
+        }
+    }
+
+    /// upload `threshold`, `knee` and `intensity`, call after changing them and before `render`
+    /// the mips, largest first
+    pub fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        self.mips.dump_targets()
+    }
+
+    /// the passes `render` adds to `graph`, reading and writing back into `scene`
+    pub fn pass_graph(&self, graph: PassGraph, scene: &'static str) -> PassGraph {
+        let mut graph = graph.pass("bloom prefilter", &[scene], &[MIP_LABELS[0]]);
+        for i in 1..MIPS {
+            graph = graph.pass("bloom downsample", &[MIP_LABELS[i - 1]], &[MIP_LABELS[i]]);
+        }
+        // loaded, the smaller mip is added on top of what is there
+        for i in (0..MIPS - 1).rev() {
+            graph = graph.pass(
+                "bloom upsample",
+                &[MIP_LABELS[i + 1], MIP_LABELS[i]],
+                &[MIP_LABELS[i]],
+            );
+        }
+        graph.pass("bloom composite", &[MIP_LABELS[0], scene], &[scene])
+    }
+
+    pub fn update(&self, queue: &wgpu::Queue) {
+        let uniform = BloomUniform {
+            threshold: self.threshold,
+            knee: self.knee,
+            intensity: self.intensity,
+            _padding: 0.,
+        };
+        watchdog::write_buffer(queue, &self.buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    /// every pass, after the scene was drawn into `scene` and before it gets tonemapped
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, scene: &Texture) {
+        let clear = wgpu::LoadOp::Clear(wgpu::Color::BLACK);
+
+        pass(
+            encoder,
+            &self.mips[0].view,
+            clear,
+            &self.prefilter,
+            &self.scene_bindgroup,
+        );
+        for i in 1..MIPS {
+            pass(
+                encoder,
+                &self.mips[i].view,
+                clear,
+                &self.downsample,
+                &self.mip_bindgroups[i - 1],
+            );
+        }
+        for i in (0..MIPS - 1).rev() {
+            pass(
+                encoder,
+                &self.mips[i].view,
+                wgpu::LoadOp::Load,
+                &self.upsample,
+                &self.mip_bindgroups[i + 1],
+            );
+        }
+        pass(
+            encoder,
+            &scene.view,
+            wgpu::LoadOp::Load,
+            &self.composite,
+            &self.mip_bindgroups[0],
+        );
+    }
+}
+
+fn create_bindgroup(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    source: &Texture,
+    buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("bloom bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&source.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&source.sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+// one fullscreen triangle into `target`
+fn pass(
+    encoder: &mut wgpu::CommandEncoder,
+    target: &wgpu::TextureView,
+    load: wgpu::LoadOp<wgpu::Color>,
+    pipeline: &wgpu::RenderPipeline,
+    bindgroup: &wgpu::BindGroup,
+) {
+    let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("bloom pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: wgpu::Operations { load, store: true },
+        })],
+        depth_stencil_attachment: None,
+    });
+    rpass.set_pipeline(pipeline);
+    rpass.set_bind_group(0, bindgroup, &[]);
+    rpass.draw(0..3, 0..1);
+}
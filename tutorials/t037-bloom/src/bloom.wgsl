@@ -0,0 +1,117 @@
+// bloom, after Jorge Jimenez's "next generation post processing in call of duty: advanced
+// warfare": every pass is one fullscreen triangle reading the texture of the pass before
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Bloom {
+    // brightness where bloom starts
+    threshold: f32,
+    // how far below the threshold it fades in, 0 is a hard cut
+    knee: f32,
+    // of the blurred light added back onto the scene
+    intensity: f32,
+};
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+@group(0) @binding(2)
+var<uniform> bloom: Bloom;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragInput.tex_coord = uv;
+    return fragInput;
+}
+
+fn sample_offset(uv: vec2<f32>, texel: vec2<f32>, x: f32, y: f32) -> vec3<f32> {
+    return textureSample(t_source, s_source, uv + texel * vec2<f32>(x, y)).rgb;
+}
+
+// 13 bilinear taps in five overlapping 2x2 boxes, the one in the middle weighted half and the
+// four around it an eighth each. a plain 2x2 box would let thin bright lines flicker as they
+// move between texels
+fn downsample(uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_source));
+
+    // a . b . c
+    // . d . e .
+    // f . g . h
+    // . i . j .
+    // k . l . m
+    let a = sample_offset(uv, texel, -2.0, -2.0);
+    let b = sample_offset(uv, texel, 0.0, -2.0);
+    let c = sample_offset(uv, texel, 2.0, -2.0);
+    let d = sample_offset(uv, texel, -1.0, -1.0);
+    let e = sample_offset(uv, texel, 1.0, -1.0);
+    let f = sample_offset(uv, texel, -2.0, 0.0);
+    let g = sample_offset(uv, texel, 0.0, 0.0);
+    let h = sample_offset(uv, texel, 2.0, 0.0);
+    let i = sample_offset(uv, texel, -1.0, 1.0);
+    let j = sample_offset(uv, texel, 1.0, 1.0);
+    let k = sample_offset(uv, texel, -2.0, 2.0);
+    let l = sample_offset(uv, texel, 0.0, 2.0);
+    let m = sample_offset(uv, texel, 2.0, 2.0);
+
+    return (d + e + i + j) * 0.125
+        + (a + b + f + g) * 0.03125
+        + (b + c + g + h) * 0.03125
+        + (f + g + k + l) * 0.03125
+        + (g + h + l + m) * 0.03125;
+}
+
+// a 3x3 tent, 1 2 1 / 2 4 2 / 1 2 1. reading the smaller mip through it blurs while it
+// enlarges, the blur grows with every mip it passes through
+fn upsample(uv: vec2<f32>) -> vec3<f32> {
+    let texel = 1.0 / vec2<f32>(textureDimensions(t_source));
+
+    var sum = sample_offset(uv, texel, 0.0, 0.0) * 4.0;
+    sum = sum + (sample_offset(uv, texel, -1.0, 0.0) + sample_offset(uv, texel, 1.0, 0.0)
+        + sample_offset(uv, texel, 0.0, -1.0) + sample_offset(uv, texel, 0.0, 1.0)) * 2.0;
+    sum = sum + sample_offset(uv, texel, -1.0, -1.0) + sample_offset(uv, texel, 1.0, -1.0)
+        + sample_offset(uv, texel, -1.0, 1.0) + sample_offset(uv, texel, 1.0, 1.0);
+    return sum / 16.0;
+}
+
+// keeps only what is brighter than the threshold, with a quadratic knee below it so bloom
+// fades in instead of switching on
+fn bright_part(color: vec3<f32>) -> vec3<f32> {
+    let brightness = max(color.r, max(color.g, color.b));
+    var soft = clamp(brightness - bloom.threshold + bloom.knee, 0.0, 2.0 * bloom.knee);
+    soft = soft * soft / (4.0 * bloom.knee + 0.00001);
+    let contribution = max(soft, brightness - bloom.threshold) / max(brightness, 0.00001);
+    return color * contribution;
+}
+
+// the scene into the first, half size mip
+@fragment
+fn fs_prefilter(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(bright_part(downsample(input.tex_coord)), 1.0);
+}
+
+// each mip into the next, half as large again
+@fragment
+fn fs_downsample(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(downsample(input.tex_coord), 1.0);
+}
+
+// each mip back into the one before, added on top of what the downsample left there
+@fragment
+fn fs_upsample(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(input.tex_coord), 1.0);
+}
+
+// the first mip onto the scene, added too
+@fragment
+fn fs_composite(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(upsample(input.tex_coord) * bloom.intensity, 1.0);
+}
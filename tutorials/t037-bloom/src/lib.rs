@@ -0,0 +1,579 @@
+mod bloom;
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    compat,
+    pass_graph::PassGraph,
+    screenshot::DumpTarget,
+    texture::{DepthTexture, Texture},
+    tonemap::{self, Tonemap},
+    watchdog,
+};
+use glam::Vec3;
+use tracing::info;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+// bloom: what's brighter than a threshold in the hdr target is blurred through a chain of
+// ever smaller textures and added back, so strong lights bleed into their surroundings
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "bloom, on, intensity 0.3, threshold 1.0";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+// as bright as in t35, so there's something over the threshold to bloom
+const NUM_LIGHTS: usize = 8;
+const LIGHT_RADIUS: f32 = 4.;
+const LIGHT_INTENSITY: f32 = 3.;
+const PILLARS: usize = 12;
+const INTENSITY_STEP: f32 = 0.1;
+const THRESHOLD_STEP: f32 = 0.25;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+fn vertex(pos: [f32; 3], normal: [f32; 3], color: [f32; 3]) -> Vertex {
+    Vertex { pos, normal, color }
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `PointLight` in shader.wgsl, 32 bytes so the array stride needs no padding
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct PointLight {
+    position: [f32; 3],
+    radius: f32,
+    color: [f32; 3],
+    intensity: f32,
+}
+
+// how a light moves, it circles the origin at its own height, radius and speed
+struct LightPath {
+    orbit: f32,
+    height: f32,
+    speed: f32,
+    phase: f32,
+}
+
+impl LightPath {
+    fn position(&self, time: f32) -> [f32; 3] {
+        let (sin, cos) = (self.phase + time * self.speed).sin_cos();
+        // bob up and down a little so the lights pass over and between the pillars
+        let height = self.height + (time * self.speed * 3. + self.phase).sin() * 0.3;
+        [cos * self.orbit, height, sin * self.orbit]
+    }
+}
+
+// fully saturated color for `hue` in 0..1
+fn hue_to_rgb(hue: f32) -> [f32; 3] {
+    let channel = |offset: f32| {
+        let h = (hue + offset).fract() * 6.;
+        ((h - 3.).abs() - 1.).clamp(0., 1.)
+    };
+    [channel(0.), channel(2. / 3.), channel(1. / 3.)]
+}
+
+fn create_lights() -> (Vec<LightPath>, Vec<PointLight>) {
+    let paths: Vec<LightPath> = (0..NUM_LIGHTS)
+        .map(|i| {
+            let t = i as f32 / NUM_LIGHTS as f32;
+            LightPath {
+                orbit: 1. + (i % 4) as f32 * 1.2,
+                height: 0.3 + (i % 3) as f32 * 0.4,
+                // alternate directions so the lights cross each other
+                speed: (1. + t) * if i % 2 == 0 { 0.4 } else { -0.3 },
+                phase: t * std::f32::consts::TAU,
+            }
+        })
+        .collect();
+
+    let lights = paths
+        .iter()
+        .enumerate()
+        .map(|(i, path)| PointLight {
+            position: path.position(0.),
+            radius: LIGHT_RADIUS,
+            color: hue_to_rgb(i as f32 / NUM_LIGHTS as f32),
+            intensity: LIGHT_INTENSITY,
+        })
+        .collect();
+
+    (paths, lights)
+}
+
+// a cube of `size` around `center`, as in t22
+fn push_cube(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    size: Vec3,
+    color: [f32; 3],
+) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+
+    for (normal, u, v) in faces {
+        let base = vertices.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = center + (normal + u * su + v * sv) * 0.5 * size;
+            vertices.push(vertex(pos.to_array(), normal.to_array(), color));
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+}
+
+// the unit cube comes first, the light cubes reuse its 36 indices
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+
+    let white = [0.8, 0.8, 0.8];
+    push_cube(&mut vertices, &mut indices, Vec3::ZERO, Vec3::ONE, white);
+
+    // a ring of pillars for the lights to weave through
+    for i in 0..PILLARS {
+        let angle = i as f32 / PILLARS as f32 * std::f32::consts::TAU;
+        let height = 0.8 + (i % 3) as f32 * 0.5;
+        push_cube(
+            &mut vertices,
+            &mut indices,
+            Vec3::new(angle.cos() * 3., height / 2. - 0.5, angle.sin() * 3.),
+            Vec3::new(0.4, height, 0.4),
+            white,
+        );
+    }
+
+    // ground
+    push_cube(
+        &mut vertices,
+        &mut indices,
+        Vec3::new(0., -0.6, 0.),
+        Vec3::new(12., 0.2, 12.),
+        [0.6, 0.6, 0.6],
+    );
+
+    (vertices, indices)
+}
+
+pub struct Bloom {
+    pipeline: wgpu::RenderPipeline,
+    light_pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    light_paths: Vec<LightPath>,
+    lights: Vec<PointLight>,
+    lights_buffer: wgpu::Buffer,
+    lights_bindgroup: wgpu::BindGroup,
+    vertices_buf: wgpu::Buffer,
+    indices_buf: wgpu::Buffer,
+    index_count: u32,
+    /// the scene goes here instead of the window
+    hdr: Texture,
+    // of `hdr`, for frame dumps
+    hdr_size: (u32, u32),
+    // picked for the adapter like t35-hdr's, the bloom mips share it
+    hdr_format: wgpu::TextureFormat,
+    tonemap: Tonemap,
+    tonemap_bindgroup: wgpu::BindGroup,
+    bloom: bloom::Bloom,
+    enabled: bool,
+    paused: bool,
+    time: f32,
+}
+
+impl Bloom {
+    fn update_title(&self, window: &Window) {
+        window.set_title(&format!(
+            "bloom, {}, intensity {:.1}, threshold {:.1}",
+            if self.enabled { "on" } else { "off" },
+            self.bloom.intensity,
+            self.bloom.threshold
+        ));
+    }
+}
+
+impl framework::App for Bloom {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // hdr target, the size of the window
+        let hdr_format = tonemap::hdr_format(adapter);
+        let hdr = Texture::create_render_target(
+            device,
+            config.width,
+            config.height,
+            hdr_format,
+            "hdr_target",
+        );
+        let tonemap = Tonemap::new(device, config.format);
+        let tonemap_bindgroup = tonemap.create_bind_group(device, &hdr);
+        let bloom = bloom::Bloom::new(device, hdr_format, &hdr, config.width, config.height);
+
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.6,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 9., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // t35's lights, their cubes and the ground under them are what crosses the threshold.
+        // rewritten every frame, so COPY_DST
+        let (light_paths, lights) = create_lights();
+
+        let lights_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Lights Buffer"),
+            contents: bytemuck::cast_slice(&lights),
+            usage: compat::array_usage(device),
+        });
+
+        // the light cubes' vertex shader reads the light positions too
+        let lights_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("lights bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: compat::array_binding_type(device),
+                        has_dynamic_offset: false,
+                        // all of them, a uniform array has a fixed length
+                        min_binding_size: wgpu::BufferSize::new(std::mem::size_of_val(
+                            lights.as_slice(),
+                        ) as u64),
+                    },
+                    count: None,
+                }],
+            });
+
+        let lights_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("lights bind group"),
+            layout: &lights_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: lights_buffer.as_entire_binding(),
+            }],
+        });
+
+        // shader
+        let shader = compat::array_shader(
+            device,
+            "shader.wgsl",
+            include_str!("shader.wgsl"),
+            "lights",
+            NUM_LIGHTS,
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &camera_bindgroup_layout, // group 0, camera
+                &lights_bindgroup_layout, // group 1, lights
+            ],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer_layout = wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &wgpu::vertex_attr_array![0=>Float32x3, 1=>Float32x3, 2=>Float32x3],
+        };
+
+        // lit geometry and the light cubes only differ in their entry points
+        let create_pipeline = |vs_entry_point, fs_entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(fs_entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: vs_entry_point,
+                    buffers: std::slice::from_ref(&vertex_buffer_layout),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fs_entry_point,
+                    targets: &[Some(hdr_format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DepthTexture::FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let pipeline = create_pipeline("vs_main", "fs_main");
+        let light_pipeline = create_pipeline("vs_light", "fs_light");
+
+        let (verticrs, indices) = create_vertices();
+
+        let vertices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        let indices_buf = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        info!("press b to turn bloom off and on, space to pause the lights");
+        info!("up / down change the intensity, left / right the threshold");
+
+        Bloom {
+            pipeline,
+            light_pipeline,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            light_paths,
+            lights,
+            lights_buffer,
+            lights_bindgroup,
+            vertices_buf,
+            indices_buf,
+            index_count: indices.len() as u32,
+            hdr,
+            tonemap,
+            tonemap_bindgroup,
+            hdr_size: (config.width, config.height),
+            hdr_format,
+            bloom,
+            enabled: true,
+            paused: false,
+            time: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+
+        self.hdr = Texture::create_render_target(
+            device,
+            config.width,
+            config.height,
+            self.hdr_format,
+            "hdr_target",
+        );
+        self.hdr_size = (config.width, config.height);
+        self.tonemap_bindgroup = self.tonemap.create_bind_group(device, &self.hdr);
+        self.bloom
+            .resize(device, &self.hdr, config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::Space => self.paused = !self.paused,
+                VirtualKeyCode::B => self.enabled = !self.enabled,
+                VirtualKeyCode::Up => self.bloom.intensity += INTENSITY_STEP,
+                VirtualKeyCode::Down => {
+                    self.bloom.intensity = (self.bloom.intensity - INTENSITY_STEP).max(0.)
+                }
+                VirtualKeyCode::Right => self.bloom.threshold += THRESHOLD_STEP,
+                VirtualKeyCode::Left => {
+                    self.bloom.threshold = (self.bloom.threshold - THRESHOLD_STEP).max(0.)
+                }
+                _ => return,
+            }
+            self.update_title(window);
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        // animate on the cpu and upload the whole array, cheap for a few dozen lights
+        if !self.paused {
+            self.time += frame.delta;
+        }
+        for (light, path) in self.lights.iter_mut().zip(&self.light_paths) {
+            light.position = path.position(self.time);
+        }
+        watchdog::write_buffer(
+            queue,
+            &self.lights_buffer,
+            0,
+            bytemuck::cast_slice(&self.lights),
+        );
+        self.tonemap.update(queue);
+        self.bloom.update(queue);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // the scene into the hdr target, with the window's depth buffer
+        {
+            let scene = framework::Frame {
+                view: &self.hdr.view,
+                depth: frame.depth,
+                ..*frame
+            };
+            let mut rpass = scene.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.lights_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertices_buf.slice(..));
+            rpass.set_index_buffer(self.indices_buf.slice(..), wgpu::IndexFormat::Uint16);
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.draw_indexed(0..self.index_count, 0, 0..1);
+
+            // the first 36 indices are the unit cube, one instance per light
+            rpass.set_pipeline(&self.light_pipeline);
+            rpass.draw_indexed(0..36, 0, 0..self.lights.len() as u32);
+        }
+
+        // the glow added onto the scene, still in hdr so it tonemaps along with it
+        if self.enabled {
+            self.bloom.render(&mut encoder, &self.hdr);
+        }
+
+        // then tonemapped into the window, a fullscreen pass has no use for depth
+        {
+            let window = framework::Frame {
+                view: frame.view,
+                depth: None,
+                ..*frame
+            };
+            let mut rpass = window.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+            self.tonemap.draw(&mut rpass, &self.tonemap_bindgroup);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+    fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        let (width, height) = self.hdr_size;
+        let mut targets = vec![DumpTarget::new(
+            "hdr",
+            &self.hdr.texture,
+            width,
+            height,
+            self.hdr_format,
+        )];
+        targets.extend(self.bloom.dump_targets());
+        targets
+    }
+    fn pass_graph(&self) -> Option<PassGraph> {
+        let graph = PassGraph::new().pass("scene", &[], &["hdr", "depth"]);
+        Some(
+            self.bloom
+                .pass_graph(graph, "hdr")
+                .pass("tonemap", &["hdr"], &["window"]),
+        )
+    }
+}
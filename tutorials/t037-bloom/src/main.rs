@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t37_bloom::Bloom>(t37_bloom::TITLE);
+}
@@ -0,0 +1,97 @@
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+struct PointLight {
+    position: vec3<f32>,
+    // the light reaches 0 at this distance
+    radius: f32,
+    color: vec3<f32>,
+    intensity: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// the length comes from the bound buffer size, no separate count needed
+@group(1) @binding(0)
+var<storage, read> lights: array<PointLight>;
+
+let ambient = 0.05;
+let shininess = 64.0;
+// how much brighter a light's cube is than the light it casts
+let glow = 4.0;
+
+// the geometry is already in world space, no model matrix
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    fragInput.normal = input.normal;
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let normal = normalize(input.normal);
+    let view_dir = normalize(camera.position.xyz - input.world_pos);
+
+    // every light adds its own diffuse and specular on top of the ambient
+    var color = ambient * input.color;
+    for (var i = 0u; i < arrayLength(&lights); i = i + 1u) {
+        let light = lights[i];
+
+        let to_light = light.position - input.world_pos;
+        let distance = length(to_light);
+        let light_dir = to_light / distance;
+
+        // smooth falloff that hits exactly 0 at the radius, so far lights cost nothing visible
+        let falloff = clamp(1.0 - pow(distance / light.radius, 2.0), 0.0, 1.0);
+        let attenuation = falloff * falloff * light.intensity;
+
+        let n_dot_l = max(dot(normal, light_dir), 0.0);
+        let half_dir = normalize(light_dir + view_dir);
+        let specular = pow(max(dot(normal, half_dir), 0.0), shininess) * step(0.0, dot(normal, light_dir));
+
+        color = color + (n_dot_l * input.color + specular) * light.color * attenuation;
+    }
+
+    return vec4<f32>(color, 1.0);
+}
+
+// one small unlit cube per light, the instance index picks the light. they glow far brighter
+// than 1, which only an hdr target keeps
+@vertex
+fn vs_light(input: VertexInput, @builtin(instance_index) instance: u32) -> FragInput {
+    let light = lights[instance];
+    let world_pos = input.pos * 0.1 + light.position;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = input.normal;
+    fragInput.color = light.color * light.intensity * glow;
+    return fragInput;
+}
+
+@fragment
+fn fs_light(input: FragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(input.color, 1.0);
+}
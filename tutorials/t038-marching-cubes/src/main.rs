@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t38_marching_cubes::MarchingCubes>(t38_marching_cubes::TITLE);
+}
@@ -0,0 +1,703 @@
+// marching cubes in compute, re-run every frame as the field moves. the density is sampled on a
+// grid of points, every cell between eight of them looks at which of its corners are inside
+// and puts up to five triangles across the edges where inside meets outside.
+//
+// the vertices land in a storage buffer the render pass then draws as a vertex buffer, and
+// the vertex count is written by the gpu too, into the arguments of `draw_indirect`. nothing
+// goes back to the cpu.
+//
+// without compute, the webgl2 profile, the same steps run on the cpu instead and the vertices
+// are uploaded every frame. slower, but the chapter still shows something.
+
+use bytemuck::{Pod, Zeroable};
+use framework::{autotune, compat, watchdog};
+use glam::Vec3;
+use tracing::info;
+
+use wgpu::util::DeviceExt;
+
+// matches `GRID` in marching.wgsl, cells per side
+const GRID: u32 = 32;
+const POINTS: u32 = GRID + 1;
+const CELLS: u32 = GRID * GRID * GRID;
+// five triangles in the worst case
+const MAX_VERTICES: u32 = CELLS * 15;
+// of `Vertex` in marching.wgsl, vec3s are 16 byte aligned in a storage buffer
+const VERTEX_SIZE: u64 = 32;
+
+// the four corners of each face of a cell, counter-clockwise seen from outside it. corner c
+// sits at (c & 1, c >> 1 & 1, c >> 2 & 1)
+const FACES: [[usize; 4]; 6] = [
+    [4, 6, 2, 0],
+    [1, 3, 7, 5],
+    [0, 1, 5, 4],
+    [6, 7, 3, 2],
+    [2, 3, 1, 0],
+    [4, 5, 7, 6],
+];
+
+// edge e runs along axis e / 4, e % 4 are the bits of its corners on the other two axes
+fn edge_between(a: usize, b: usize) -> usize {
+    let axis = (a ^ b).trailing_zeros() as usize;
+    let low = a & ((1 << axis) - 1);
+    let high = (a >> (axis + 1)) << axis;
+    axis * 4 + (low | high)
+}
+
+/// 16 edges for each of the 256 cases, bit c of the case set when corner c is inside. three
+/// edges make a triangle, counter-clockwise seen from outside, and -1 follows the last one.
+///
+/// built from the cell's faces instead of typed in: where the surface crosses a face, it
+/// enters through one edge and leaves through another, following those from face to face
+/// walks around each piece of surface, and each outline becomes a fan of triangles. a face
+/// with two inside corners diagonally across always cuts them off separately, it's decided
+/// by the face alone, so the cells on both sides agree and the surface has no holes
+pub fn triangle_table() -> Vec<i32> {
+    let mut table = Vec::with_capacity(256 * 16);
+    for case in 0..256usize {
+        let inside = |corner: usize| case >> corner & 1 == 1;
+
+        // the edge the outline goes on to from each edge
+        let mut next = [None; 12];
+        for face in FACES {
+            // the edges where the face's border crosses the surface, in order around the face,
+            // and whether the border goes inside there
+            let crossings: Vec<(usize, bool)> = (0..4)
+                .map(|i| (face[i], face[(i + 1) % 4]))
+                .filter(|&(a, b)| inside(a) != inside(b))
+                .map(|(a, b)| (edge_between(a, b), inside(b)))
+                .collect();
+            for (i, &(edge, enters)) in crossings.iter().enumerate() {
+                if enters {
+                    next[edge] = Some(crossings[(i + 1) % crossings.len()].0);
+                }
+            }
+        }
+
+        let mut row = Vec::with_capacity(16);
+        let mut visited = [false; 12];
+        for start in 0..12 {
+            if visited[start] || next[start].is_none() {
+                continue;
+            }
+            let mut outline = vec![start];
+            visited[start] = true;
+            let mut edge = next[start].expect("Fail to close an outline");
+            while edge != start {
+                outline.push(edge);
+                visited[edge] = true;
+                edge = next[edge].expect("Fail to close an outline");
+            }
+            for i in 1..outline.len() - 1 {
+                row.extend([outline[0], outline[i], outline[i + 1]].map(|edge| edge as i32));
+            }
+        }
+        row.resize(16, -1);
+        table.extend(row);
+    }
+    table
+}
+
+// edge e runs along axis e / 4, e % 4 are its other two bits. its two corners
+fn edge_corners(edge: usize) -> (usize, usize) {
+    let axis = edge / 4;
+    let rest = edge % 4;
+    let low = rest & ((1 << axis) - 1);
+    let high = (rest >> axis) << (axis + 1);
+    let a = low | high;
+    (a, a | 1 << axis)
+}
+
+// the cpu side of marching.wgsl, the path without compute and what the tests check the gpu
+// against
+
+// the grid spans -1..1 on every axis
+fn grid_position(point: [u32; 3]) -> [f32; 3] {
+    point.map(|p| p as f32 / GRID as f32 * 2. - 1.)
+}
+
+fn cell_corner(cell: u32, corner: usize) -> [u32; 3] {
+    let corner = corner as u32;
+    [
+        cell % GRID + (corner & 1),
+        cell / GRID % GRID + (corner >> 1 & 1),
+        cell / (GRID * GRID) + (corner >> 2 & 1),
+    ]
+}
+
+fn point_index(point: [u32; 3]) -> usize {
+    ((point[2] * POINTS + point[1]) * POINTS + point[0]) as usize
+}
+
+// `metaballs` in marching.wgsl
+fn metaballs(p: Vec3, time: f32) -> f32 {
+    (0..5)
+        .map(|i| {
+            let fi = i as f32;
+            let center = 0.55
+                * Vec3::new(
+                    (time * (0.5 + 0.13 * fi) + fi * 1.7).sin(),
+                    (time * (0.4 + 0.11 * fi) + fi * 2.3).cos(),
+                    (time * (0.3 + 0.07 * fi) + fi * 0.9).sin(),
+                );
+            let radius = 0.22 + 0.04 * fi;
+            radius * radius / (p - center).length_squared().max(0.0001)
+        })
+        .sum()
+}
+
+// cs_density, x fastest
+fn density(time: f32) -> Vec<f32> {
+    (0..POINTS * POINTS * POINTS)
+        .map(|index| {
+            let point = [
+                index % POINTS,
+                index / POINTS % POINTS,
+                index / (POINTS * POINTS),
+            ];
+            metaballs(Vec3::from(grid_position(point)), time)
+        })
+        .collect()
+}
+
+// `gradient` in marching.wgsl
+fn gradient(density: &[f32], p: [u32; 3]) -> Vec3 {
+    let mut gradient = [0.; 3];
+    for (axis, g) in gradient.iter_mut().enumerate() {
+        let (mut low, mut high) = (p, p);
+        low[axis] = p[axis].saturating_sub(1);
+        high[axis] = (p[axis] + 1).min(POINTS - 1);
+        *g = density[point_index(high)] - density[point_index(low)];
+    }
+    Vec3::from(gradient)
+}
+
+// cs_classify to cs_generate in one go, cell after cell, laid out like `Vertex`
+fn surface(density: &[f32], iso: f32, table: &[i32]) -> Vec<[f32; 8]> {
+    let mut vertices = Vec::new();
+    for cell in 0..CELLS {
+        let case = (0..8)
+            .filter(|&corner| density[point_index(cell_corner(cell, corner))] > iso)
+            .fold(0, |case, corner| case | 1 << corner);
+        let row = &table[case * 16..case * 16 + 16];
+        for &edge in row.iter().take_while(|&&edge| edge >= 0) {
+            let (a, b) = edge_corners(edge as usize);
+            let (a, b) = (cell_corner(cell, a), cell_corner(cell, b));
+            let (density_a, density_b) = (density[point_index(a)], density[point_index(b)]);
+            let t = ((iso - density_a) / (density_b - density_a)).clamp(0., 1.);
+
+            let position = Vec3::from(grid_position(a)).lerp(Vec3::from(grid_position(b)), t);
+            let normal = -gradient(density, a)
+                .lerp(gradient(density, b), t)
+                .normalize();
+            vertices.push([
+                position.x, position.y, position.z, 0., normal.x, normal.y, normal.z, 0.,
+            ]);
+        }
+    }
+    vertices
+}
+
+/// how many invocations the workgroups of the compute passes have. marching.wgsl is written
+/// with the defaults, 64 and 256, [`Marching::with_workgroup_sizes`] builds it with others
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorkgroupSizes {
+    /// grid points per workgroup in cs_density
+    pub density: u32,
+    /// cells per workgroup in the cell passes, and so per block of the prefix sum
+    pub scan_block: u32,
+}
+
+impl Default for WorkgroupSizes {
+    fn default() -> Self {
+        WorkgroupSizes {
+            density: 64,
+            scan_block: 256,
+        }
+    }
+}
+
+impl WorkgroupSizes {
+    fn blocks(&self) -> u32 {
+        CELLS / self.scan_block
+    }
+}
+
+// marching.wgsl with `sizes` in place of the defaults. wgsl only takes literals in
+// `@workgroup_size`, so they go into the text, like `autotune::with_workgroup_size` but with
+// two sizes in one file. the density size goes through a placeholder first, it could be the
+// same number as the default scan block
+fn shader_source(sizes: WorkgroupSizes) -> String {
+    let written = WorkgroupSizes::default();
+    let attribute = |size: u32| format!("@workgroup_size({})", size);
+    include_str!("marching.wgsl")
+        .replace(&attribute(written.density), "@workgroup_size(DENSITY)")
+        .replace(&attribute(written.scan_block), &attribute(sizes.scan_block))
+        .replace("@workgroup_size(DENSITY)", &attribute(sizes.density))
+        .replace(
+            &format!("let SCAN_BLOCK: u32 = {}u;", written.scan_block),
+            &format!("let SCAN_BLOCK: u32 = {}u;", sizes.scan_block),
+        )
+}
+
+// matches `Field` in marching.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct FieldUniform {
+    time: f32,
+    iso: f32,
+    _padding: [f32; 2],
+}
+
+/// how the render pipeline reads [`Marching::vertices`], position at 0 and normal at 1
+pub const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 2] = [
+    wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Float32x3,
+        offset: 0,
+        shader_location: 0,
+    },
+    wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Float32x3,
+        offset: 16,
+        shader_location: 1,
+    },
+];
+
+pub fn vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+        array_stride: VERTEX_SIZE,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &VERTEX_ATTRIBUTES,
+    }
+}
+
+pub struct Marching {
+    /// the density the surface sits at
+    pub iso: f32,
+    /// the triangle list, only the first `vertex_count` are valid
+    pub vertices: wgpu::Buffer,
+    stages: Stages,
+}
+
+enum Stages {
+    Gpu(Box<ComputeStages>),
+    /// no compute, the vertices are made here and uploaded
+    Cpu {
+        table: Vec<i32>,
+        vertex_count: u32,
+    },
+}
+
+struct ComputeStages {
+    /// for `draw_indirect`
+    draw_args: wgpu::Buffer,
+    field_buffer: wgpu::Buffer,
+    // the tests check the vertices against the density the gpu computed
+    #[cfg_attr(not(test), allow(dead_code))]
+    density_buffer: wgpu::Buffer,
+    sizes: WorkgroupSizes,
+    bindgroup: wgpu::BindGroup,
+    density: wgpu::ComputePipeline,
+    classify: wgpu::ComputePipeline,
+    scan_blocks: wgpu::ComputePipeline,
+    scan_sums: wgpu::ComputePipeline,
+    generate: wgpu::ComputePipeline,
+}
+
+impl Marching {
+    pub fn new(device: &wgpu::Device) -> Marching {
+        Marching::with_workgroup_sizes(device, WorkgroupSizes::default())
+    }
+
+    /// the compute passes with other workgroup sizes, powers of two within the device's limits
+    pub fn with_workgroup_sizes(device: &wgpu::Device, sizes: WorkgroupSizes) -> Marching {
+        let limits = device.limits();
+        assert!(
+            [sizes.density, sizes.scan_block]
+                .iter()
+                .all(|&size| size.is_power_of_two() && autotune::fits(&limits, [size, 1, 1])),
+            "Fail to use workgroup sizes {:?}, they aren't powers of two the device runs",
+            sizes
+        );
+        let iso = 1.;
+        if !compat::compute(device) {
+            info!("no compute shaders, marching cubes runs on the cpu");
+            return Marching {
+                iso,
+                vertices: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Vertices Buffer"),
+                    size: VERTEX_SIZE * MAX_VERTICES as u64,
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                stages: Stages::Cpu {
+                    table: triangle_table(),
+                    vertex_count: 0,
+                },
+            };
+        }
+
+        let field_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Field Buffer"),
+            contents: bytemuck::bytes_of(&FieldUniform {
+                time: 0.,
+                iso,
+                _padding: [0.; 2],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let table_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Triangle Table Buffer"),
+            contents: bytemuck::cast_slice(&triangle_table()),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+
+        // only ever touched by the gpu, and copied out by the tests
+        let storage = |label, size: u64, usage: wgpu::BufferUsages| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC | usage,
+                mapped_at_creation: false,
+            })
+        };
+        let empty = wgpu::BufferUsages::empty();
+        let density_buffer = storage(
+            "Density Buffer",
+            4 * (POINTS * POINTS * POINTS) as u64,
+            empty,
+        );
+        let counts = storage("Counts Buffer", 4 * CELLS as u64, empty);
+        let offsets = storage("Offsets Buffer", 4 * CELLS as u64, empty);
+        let block_sums = storage("Block Sums Buffer", 4 * sizes.blocks() as u64, empty);
+        let vertices = storage(
+            "Vertices Buffer",
+            VERTEX_SIZE * MAX_VERTICES as u64,
+            wgpu::BufferUsages::VERTEX,
+        );
+        let draw_args = storage("Draw Args Buffer", 16, wgpu::BufferUsages::INDIRECT);
+
+        let buffers = [
+            &field_buffer,
+            &density_buffer,
+            &table_buffer,
+            &counts,
+            &offsets,
+            &block_sums,
+            &vertices,
+            &draw_args,
+        ];
+
+        // binding 0 is the uniform, 2 the table only read, the rest read and written
+        let entries: Vec<wgpu::BindGroupLayoutEntry> = (0..buffers.len() as u32)
+            .map(|binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: wgpu::BindingType::Buffer {
+                    ty: match binding {
+                        0 => wgpu::BufferBindingType::Uniform,
+                        2 => wgpu::BufferBindingType::Storage { read_only: true },
+                        _ => wgpu::BufferBindingType::Storage { read_only: false },
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            })
+            .collect();
+        let bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("marching bindgroup layout"),
+            entries: &entries,
+        });
+
+        let entries: Vec<wgpu::BindGroupEntry> = buffers
+            .iter()
+            .enumerate()
+            .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                binding: binding as u32,
+                resource: buffer.as_entire_binding(),
+            })
+            .collect();
+        let bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("marching bind group"),
+            layout: &bindgroup_layout,
+            entries: &entries,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("marching.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(shader_source(sizes).into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("marching pipeline layout"),
+            bind_group_layouts: &[&bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                module: &shader,
+                entry_point,
+            })
+        };
+
+        Marching {
+            iso,
+            vertices,
+            stages: Stages::Gpu(Box::new(ComputeStages {
+                draw_args,
+                field_buffer,
+                density_buffer,
+                sizes,
+                bindgroup,
+                density: create_pipeline("cs_density"),
+                classify: create_pipeline("cs_classify"),
+                scan_blocks: create_pipeline("cs_scan_blocks"),
+                scan_sums: create_pipeline("cs_scan_sums"),
+                generate: create_pipeline("cs_generate"),
+            })),
+        }
+    }
+
+    /// the surface of the field at `time`, into `vertices`
+    pub fn run(&mut self, encoder: &mut wgpu::CommandEncoder, queue: &wgpu::Queue, time: f32) {
+        match &mut self.stages {
+            Stages::Gpu(stages) => stages.run(encoder, queue, time, self.iso),
+            Stages::Cpu {
+                table,
+                vertex_count,
+            } => {
+                let vertices = surface(&density(time), self.iso, table);
+                // counted by the watchdog, a few MiB a frame at iso 1
+                watchdog::write_buffer(queue, &self.vertices, 0, bytemuck::cast_slice(&vertices));
+                *vertex_count = vertices.len() as u32;
+            }
+        }
+    }
+
+    /// `vertices`, as many as the last `run` made
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        rpass.set_vertex_buffer(0, self.vertices.slice(..));
+        match &self.stages {
+            // the vertex count is whatever the prefix sum came to
+            Stages::Gpu(stages) => rpass.draw_indirect(&stages.draw_args, 0),
+            Stages::Cpu { vertex_count, .. } => rpass.draw(0..*vertex_count, 0..1),
+        }
+    }
+}
+
+impl ComputeStages {
+    fn run(&self, encoder: &mut wgpu::CommandEncoder, queue: &wgpu::Queue, time: f32, iso: f32) {
+        let field = FieldUniform {
+            time,
+            iso,
+            _padding: [0.; 2],
+        };
+        watchdog::write_buffer(queue, &self.field_buffer, 0, bytemuck::bytes_of(&field));
+
+        // one pass, wgpu puts a barrier between dispatches writing and reading the same buffer
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("marching cubes pass"),
+        });
+        cpass.set_bind_group(0, &self.bindgroup, &[]);
+
+        cpass.set_pipeline(&self.density);
+        let points = POINTS * POINTS * POINTS;
+        let blocks = self.sizes.blocks();
+        cpass.dispatch_workgroups(points.div_ceil(self.sizes.density), 1, 1);
+        cpass.set_pipeline(&self.classify);
+        cpass.dispatch_workgroups(blocks, 1, 1);
+        cpass.set_pipeline(&self.scan_blocks);
+        cpass.dispatch_workgroups(blocks, 1, 1);
+        // a single workgroup for the block sums, in runs if there are more than invocations
+        cpass.set_pipeline(&self.scan_sums);
+        cpass.dispatch_workgroups(1, 1, 1);
+        cpass.set_pipeline(&self.generate);
+        cpass.dispatch_workgroups(blocks, 1, 1);
+    }
+}
+
+// the compute passes against a cpu version of the same steps, on a device without a window.
+// without an adapter, e.g. on ci without a gpu, they print why and pass
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use framework::testing::{headless_device, read_back};
+
+    fn compute_stages(marching: &Marching) -> &ComputeStages {
+        match &marching.stages {
+            Stages::Gpu(stages) => stages,
+            Stages::Cpu { .. } => panic!("the test device has compute"),
+        }
+    }
+
+    #[test]
+    fn edges_and_corners_agree() {
+        for edge in 0..12 {
+            let (a, b) = edge_corners(edge);
+            assert_eq!(edge_between(a, b), edge);
+            assert_eq!(edge_between(b, a), edge);
+        }
+    }
+
+    #[test]
+    fn triangle_table_rows_are_whole_triangles() {
+        let table = triangle_table();
+        assert_eq!(table.len(), 256 * 16);
+        for (case, row) in table.chunks(16).enumerate() {
+            let count = row.iter().take_while(|&&edge| edge >= 0).count();
+            assert_eq!(count % 3, 0, "case {} ends mid triangle", case);
+            assert!(count <= 15, "case {} has more than 5 triangles", case);
+            assert!(row[count..].iter().all(|&edge| edge == -1));
+            // every corner on the same side, nothing to cross
+            if case == 0 || case == 255 {
+                assert_eq!(count, 0);
+            } else {
+                assert!(
+                    count > 0,
+                    "case {} crosses the surface without triangles",
+                    case
+                );
+            }
+        }
+    }
+
+    // the vertex count is the scan's total and every cell's vertices sit where the scan put
+    // them, at the edge crossings the cpu finds in the same density. with the default workgroup
+    // sizes, and smaller and bigger ones, the scan of the block sums in runs among them
+    #[test]
+    fn vertices_match_the_cpu() {
+        let Some((device, queue)) = headless_device() else {
+            return;
+        };
+        let table = triangle_table();
+
+        let sizes = [
+            WorkgroupSizes::default(),
+            WorkgroupSizes {
+                density: 32,
+                scan_block: 32,
+            },
+            WorkgroupSizes {
+                density: 256,
+                scan_block: 64,
+            },
+        ];
+        for sizes in sizes {
+            let mut marching = Marching::with_workgroup_sizes(&device, sizes);
+
+            // the balls as the chapter shows them, only their cores (iso 40) and the whole grid
+            // inside, no surface at all (iso 0.05)
+            for (time, iso) in [(0., 1.), (1.3, 1.), (4., 0.6), (2.5, 40.), (0.7, 0.05)] {
+                marching.iso = iso;
+                let mut encoder =
+                    device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+                marching.run(&mut encoder, &queue, time);
+                queue.submit(Some(encoder.finish()));
+
+                let stages = compute_stages(&marching);
+                let density: Vec<f32> = read_back(&device, &queue, &stages.density_buffer);
+                let draw_args: Vec<u32> = read_back(&device, &queue, &stages.draw_args);
+                let vertices: Vec<[f32; 8]> = read_back(&device, &queue, &marching.vertices);
+
+                let mut first = 0;
+                for cell in 0..CELLS {
+                    let case = (0..8)
+                        .filter(|&corner| density[point_index(cell_corner(cell, corner))] > iso)
+                        .fold(0, |case, corner| case | 1 << corner);
+                    let row = &table[case * 16..case * 16 + 16];
+                    let count = row.iter().take_while(|&&edge| edge >= 0).count();
+
+                    for (i, &edge) in row[..count].iter().enumerate() {
+                        let (a, b) = edge_corners(edge as usize);
+                        let (a, b) = (cell_corner(cell, a), cell_corner(cell, b));
+                        let (density_a, density_b) =
+                            (density[point_index(a)], density[point_index(b)]);
+                        let t = ((iso - density_a) / (density_b - density_a)).clamp(0., 1.);
+                        let (pa, pb) = (grid_position(a), grid_position(b));
+
+                        let vertex = vertices[first + i];
+                        for axis in 0..3 {
+                            let expected = pa[axis] + (pb[axis] - pa[axis]) * t;
+                            assert!(
+                                (vertex[axis] - expected).abs() < 1e-4,
+                                "{:?} time {} iso {}: cell {} vertex {} at {:?}, expected {}",
+                                sizes,
+                                time,
+                                iso,
+                                cell,
+                                i,
+                                &vertex[..3],
+                                expected
+                            );
+                        }
+                        let normal =
+                            vertex[4] * vertex[4] + vertex[5] * vertex[5] + vertex[6] * vertex[6];
+                        assert!((normal - 1.).abs() < 1e-3, "normal isn't unit length");
+                    }
+                    first += count;
+                }
+
+                assert_eq!(
+                    draw_args[..2],
+                    [first as u32, 1],
+                    "{:?} time {} iso {}: wrong vertex count",
+                    sizes,
+                    time,
+                    iso
+                );
+            }
+        }
+    }
+
+    // the webgl2 profile's path: the same density up to float differences in sin and cos, and
+    // from the same density the same vertices, normals too, in the same order
+    #[test]
+    fn cpu_path_matches_the_gpu() {
+        let Some((device, queue)) = headless_device() else {
+            return;
+        };
+        let table = triangle_table();
+        let mut marching = Marching::new(&device);
+
+        for (time, iso) in [(0., 1.), (1.3, 1.), (4., 0.6)] {
+            marching.iso = iso;
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            marching.run(&mut encoder, &queue, time);
+            queue.submit(Some(encoder.finish()));
+
+            let stages = compute_stages(&marching);
+            let gpu_density: Vec<f32> = read_back(&device, &queue, &stages.density_buffer);
+            let draw_args: Vec<u32> = read_back(&device, &queue, &stages.draw_args);
+            let gpu_vertices: Vec<[f32; 8]> = read_back(&device, &queue, &marching.vertices);
+
+            for (index, (cpu, gpu)) in density(time).iter().zip(&gpu_density).enumerate() {
+                assert!(
+                    (cpu - gpu).abs() <= 1e-3 * gpu.max(1.),
+                    "time {}: density at {} is {} on the cpu, {} on the gpu",
+                    time,
+                    index,
+                    cpu,
+                    gpu
+                );
+            }
+
+            let cpu_vertices = surface(&gpu_density, iso, &table);
+            assert_eq!(cpu_vertices.len(), draw_args[0] as usize);
+            for (i, (cpu, gpu)) in cpu_vertices.iter().zip(&gpu_vertices).enumerate() {
+                assert!(
+                    cpu.iter().zip(gpu).all(|(c, g)| (c - g).abs() < 1e-3),
+                    "time {} iso {}: vertex {} is {:?} on the cpu, {:?} on the gpu",
+                    time,
+                    iso,
+                    i,
+                    cpu,
+                    gpu
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,250 @@
+// marching cubes on the gpu, one dispatch per stage:
+// - cs_density, the field at every grid point
+// - cs_classify, which corners of each cell are inside and how many vertices that makes
+// - cs_scan_blocks and cs_scan_sums, a prefix sum over those counts, so every cell knows
+//   where its vertices start without any two cells racing for the same place
+// - cs_generate, the vertices themselves, written where the prefix sum said
+
+struct Field {
+    time: f32,
+    // the surface is where the density crosses it
+    iso: f32,
+};
+
+// matches `Vertex` in marching.rs, the vertex buffer the render pass draws
+struct Vertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+};
+
+// the layout `draw_indirect` reads
+struct DrawArgs {
+    vertex_count: u32,
+    instance_count: u32,
+    first_vertex: u32,
+    first_instance: u32,
+};
+
+// cells per side, the grid has one more point than cells along each axis
+let GRID: u32 = 32u;
+let POINTS: u32 = 33u;
+// cells per workgroup in the cell passes. this and the `@workgroup_size`s are the defaults of
+// `WorkgroupSizes` in marching.rs, which splices its own in before building the module
+let SCAN_BLOCK: u32 = 256u;
+
+@group(0) @binding(0)
+var<uniform> field: Field;
+@group(0) @binding(1)
+var<storage, read_write> density: array<f32>;
+// 16 edges per case, the corners' inside bits as the case index, -1 after the last triangle
+@group(0) @binding(2)
+var<storage, read> triangle_table: array<i32>;
+@group(0) @binding(3)
+var<storage, read_write> counts: array<u32>;
+@group(0) @binding(4)
+var<storage, read_write> offsets: array<u32>;
+@group(0) @binding(5)
+var<storage, read_write> block_sums: array<u32>;
+@group(0) @binding(6)
+var<storage, read_write> vertices: array<Vertex>;
+@group(0) @binding(7)
+var<storage, read_write> draw_args: DrawArgs;
+
+// the grid spans -1..1 on every axis
+fn grid_position(grid_point: vec3<u32>) -> vec3<f32> {
+    return vec3<f32>(grid_point) / f32(GRID) * 2.0 - 1.0;
+}
+
+fn point_index(grid_point: vec3<u32>) -> u32 {
+    return (grid_point.z * POINTS + grid_point.y) * POINTS + grid_point.x;
+}
+
+fn density_at(grid_point: vec3<u32>) -> f32 {
+    return density[point_index(grid_point)];
+}
+
+// a few metaballs drifting through each other, the density is 1 on each ball's surface
+fn metaballs(p: vec3<f32>) -> f32 {
+    let t = field.time;
+    var sum = 0.0;
+    for (var i = 0u; i < 5u; i = i + 1u) {
+        let fi = f32(i);
+        let center = 0.55 * vec3<f32>(
+            sin(t * (0.5 + 0.13 * fi) + fi * 1.7),
+            cos(t * (0.4 + 0.11 * fi) + fi * 2.3),
+            sin(t * (0.3 + 0.07 * fi) + fi * 0.9)
+        );
+        let radius = 0.22 + 0.04 * fi;
+        let d = p - center;
+        sum = sum + radius * radius / max(dot(d, d), 0.0001);
+    }
+    return sum;
+}
+
+@compute
+@workgroup_size(64)
+fn cs_density(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if (index >= POINTS * POINTS * POINTS) {
+        return;
+    }
+    let grid_point = vec3<u32>(index % POINTS, (index / POINTS) % POINTS, index / (POINTS * POINTS));
+    density[index] = metaballs(grid_position(grid_point));
+}
+
+// corner c of a cell is one step along x, y and z for each of its three bits
+fn corner_offset(corner: u32) -> vec3<u32> {
+    return vec3<u32>(corner & 1u, (corner >> 1u) & 1u, (corner >> 2u) & 1u);
+}
+
+fn cell_point(cell: u32) -> vec3<u32> {
+    return vec3<u32>(cell % GRID, (cell / GRID) % GRID, cell / (GRID * GRID));
+}
+
+// bit c set when corner c is inside the surface
+fn cell_case(cell: u32) -> u32 {
+    let grid_point = cell_point(cell);
+    var case_index = 0u;
+    for (var corner = 0u; corner < 8u; corner = corner + 1u) {
+        if (density_at(grid_point + corner_offset(corner)) > field.iso) {
+            case_index = case_index | (1u << corner);
+        }
+    }
+    return case_index;
+}
+
+@compute
+@workgroup_size(256)
+fn cs_classify(@builtin(global_invocation_id) id: vec3<u32>) {
+    let cell = id.x;
+    let row = cell_case(cell) * 16u;
+    var count = 0u;
+    loop {
+        if (count == 16u || triangle_table[row + count] < 0) {
+            break;
+        }
+        count = count + 1u;
+    }
+    counts[cell] = count;
+}
+
+var<workgroup> scan: array<u32, SCAN_BLOCK>;
+
+// inclusive hillis-steele scan of `scan`, log2(SCAN_BLOCK) rounds of adding the value `offset` back
+fn scan_workgroup(local: u32) {
+    for (var offset = 1u; offset < SCAN_BLOCK; offset = offset * 2u) {
+        var value = scan[local];
+        if (local >= offset) {
+            value = value + scan[local - offset];
+        }
+        workgroupBarrier();
+        scan[local] = value;
+        workgroupBarrier();
+    }
+}
+
+// every block of cells on its own: where each cell starts within its block, and the block's
+// total for cs_scan_sums
+@compute
+@workgroup_size(256)
+fn cs_scan_blocks(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(local_invocation_index) local: u32,
+    @builtin(workgroup_id) block: vec3<u32>,
+) {
+    let count = counts[id.x];
+    scan[local] = count;
+    workgroupBarrier();
+    scan_workgroup(local);
+
+    // inclusive to exclusive, the first vertex of this cell rather than one past its last
+    offsets[id.x] = scan[local] - count;
+    if (local == SCAN_BLOCK - 1u) {
+        block_sums[block.x] = scan[local];
+    }
+}
+
+// the block totals, one workgroup for all of them. afterwards each holds where its block
+// starts, and the running total is how many vertices there are to draw
+@compute
+@workgroup_size(256)
+fn cs_scan_sums(@builtin(local_invocation_index) local: u32) {
+    let blocks = GRID * GRID * GRID / SCAN_BLOCK;
+    // with smaller workgroups there are more blocks than invocations, each takes a run of them
+    let run = (blocks + SCAN_BLOCK - 1u) / SCAN_BLOCK;
+    let first = min(local * run, blocks);
+    let last = min(first + run, blocks);
+    var sum = 0u;
+    for (var i = first; i < last; i = i + 1u) {
+        sum = sum + block_sums[i];
+    }
+    scan[local] = sum;
+    workgroupBarrier();
+    scan_workgroup(local);
+
+    // where the run starts, then every block in it after the ones before
+    var start = scan[local] - sum;
+    for (var i = first; i < last; i = i + 1u) {
+        let total = block_sums[i];
+        block_sums[i] = start;
+        start = start + total;
+    }
+    if (local == SCAN_BLOCK - 1u) {
+        draw_args.vertex_count = scan[local];
+        draw_args.instance_count = 1u;
+        draw_args.first_vertex = 0u;
+        draw_args.first_instance = 0u;
+    }
+}
+
+// points toward higher density, central differences clamped at the edge of the grid
+fn gradient(p: vec3<u32>) -> vec3<f32> {
+    let low = max(p, vec3<u32>(1u)) - vec3<u32>(1u);
+    let high = min(p + vec3<u32>(1u), vec3<u32>(POINTS - 1u));
+    return vec3<f32>(
+        density_at(vec3<u32>(high.x, p.y, p.z)) - density_at(vec3<u32>(low.x, p.y, p.z)),
+        density_at(vec3<u32>(p.x, high.y, p.z)) - density_at(vec3<u32>(p.x, low.y, p.z)),
+        density_at(vec3<u32>(p.x, p.y, high.z)) - density_at(vec3<u32>(p.x, p.y, low.z))
+    );
+}
+
+// edge e runs along axis e / 4, e % 4 are its other two bits. returns its two corners
+fn edge_corners(edge: u32) -> vec2<u32> {
+    let axis = edge / 4u;
+    let rest = edge % 4u;
+    // slot a zero in at the axis' bit
+    let low = rest & ((1u << axis) - 1u);
+    let high = (rest >> axis) << (axis + 1u);
+    let a = low | high;
+    return vec2<u32>(a, a | (1u << axis));
+}
+
+@compute
+@workgroup_size(256)
+fn cs_generate(@builtin(global_invocation_id) id: vec3<u32>) {
+    let cell = id.x;
+    let count = counts[cell];
+    if (count == 0u) {
+        return;
+    }
+
+    let grid_point = cell_point(cell);
+    let row = cell_case(cell) * 16u;
+    let first = offsets[cell] + block_sums[cell / SCAN_BLOCK];
+    for (var i = 0u; i < count; i = i + 1u) {
+        let corners = edge_corners(u32(triangle_table[row + i]));
+        let a = grid_point + corner_offset(corners.x);
+        let b = grid_point + corner_offset(corners.y);
+        let density_a = density_at(a);
+        let density_b = density_at(b);
+
+        // where along the edge the density crosses iso, assuming it's linear in between
+        let t = clamp((field.iso - density_a) / (density_b - density_a), 0.0, 1.0);
+
+        var vertex: Vertex;
+        vertex.position = mix(grid_position(a), grid_position(b), t);
+        // density falls off outward, the surface faces down the gradient
+        vertex.normal = -normalize(mix(gradient(a), gradient(b), t));
+        vertices[first + i] = vertex;
+    }
+}
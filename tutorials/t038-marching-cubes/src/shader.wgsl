@@ -0,0 +1,47 @@
+// draws the triangles marching.wgsl left in the vertex buffer
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+let light_dir = vec3<f32>(0.4, 0.8, 0.45);
+let ambient = 0.15;
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    fragInput.normal = input.normal;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let normal = normalize(input.normal);
+    let view_dir = normalize(camera.position.xyz - input.world_pos);
+
+    // tinted by the normal, so the separate blobs and their seams are easy to follow
+    let base = 0.55 + 0.35 * normal;
+    let diffuse = max(dot(normal, normalize(light_dir)), 0.0);
+    // a rim where the surface turns away from the eye
+    let rim = pow(1.0 - max(dot(normal, view_dir), 0.0), 3.0) * 0.4;
+
+    return vec4<f32>(base * (ambient + diffuse) + rim, 1.0);
+}
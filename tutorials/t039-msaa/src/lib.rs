@@ -0,0 +1,527 @@
+// multisample anti-aliasing. every pixel keeps 4 samples at different spots inside it, the
+// rasterizer decides coverage per sample while the fragment shader still runs once per pixel,
+// so only the edges of triangles cost more. at the end of the pass the samples are averaged,
+// "resolved", into the swapchain texture.
+//
+// the multisampled color and depth textures belong to the chapter, the framework's depth
+// buffer has a single sample and can't be used next to a 4 sample color target. pipelines
+// bake the sample count in too, so switching means another pipeline and new textures. both
+// pipelines are built behind the framework's warm-up progress bar before the first frame,
+// the switch itself only makes textures.
+//
+// fxaa is the other way to get there, a pass over the finished image that blurs along the
+// edges it can find. the scene goes into an offscreen target first then, msaa or not.
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    fxaa::Fxaa,
+    targets::{TargetDesc, Targets},
+    texture::DepthTexture,
+    warmup::{Pipelines, Progress},
+    watchdog,
+};
+use glam::Vec3;
+use tracing::{info, warn};
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "msaa, 4x";
+
+// the count every adapter has to support for the usual color and depth formats
+const SAMPLE_COUNT: u32 = 4;
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const SPOKES: usize = 64;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    position: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// a wheel of thin spokes lying flat, nearly every edge is a long shallow slope where aliasing
+// shows the most, and a cube standing in the middle of it so depth has something to do
+fn build_scene() -> Vec<Vertex> {
+    let mut verticrs = Vec::new();
+
+    let step = std::f32::consts::TAU / SPOKES as f32;
+    for i in 0..SPOKES {
+        let angle = i as f32 * step;
+        let color = if i % 2 == 0 {
+            [0.95, 0.95, 0.9]
+        } else {
+            [0.95, 0.55, 0.15]
+        };
+        // a sliver a quarter of the spacing wide at the rim, meeting in a point near the middle
+        let rim = |angle: f32| [angle.cos() * 1.6, 0., angle.sin() * 1.6];
+        verticrs.extend(
+            [
+                [angle.cos() * 0.1, 0., angle.sin() * 0.1],
+                rim(angle - step / 8.),
+                rim(angle + step / 8.),
+            ]
+            .map(|position| Vertex { position, color }),
+        );
+    }
+
+    let center = Vec3::new(0., 0.3, 0.);
+    let half = 0.3;
+    for axis in 0..3 {
+        for sign in [-1., 1.] {
+            let normal = Vec3::AXES[axis] * sign;
+            let u = Vec3::AXES[(axis + 1) % 3];
+            let v = Vec3::AXES[(axis + 2) % 3];
+            let corner = |a: f32, b: f32| (center + (normal + u * a + v * b) * half).to_array();
+            // one shade per axis, darker on the negative side
+            let shade = if sign > 0. { 1. } else { 0.6 };
+            let mut color = [0.2; 3];
+            color[axis] = 0.8;
+            let color = color.map(|c| c * shade);
+            verticrs.extend(
+                [
+                    corner(-1., -1.),
+                    corner(1., -1.),
+                    corner(1., 1.),
+                    corner(-1., -1.),
+                    corner(1., 1.),
+                    corner(-1., 1.),
+                ]
+                .map(|position| Vertex { position, color }),
+            );
+        }
+    }
+
+    verticrs
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+        }
+    }
+}
+
+// what the pass draws into besides the swapchain
+struct Attachments {
+    // `None` with a single sample, the pass draws straight into the swapchain then
+    color: Option<wgpu::TextureView>,
+    depth: wgpu::TextureView,
+}
+
+impl Attachments {
+    fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+    ) -> Attachments {
+        let create_view = |format, label| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width: config.width,
+                        height: config.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format,
+                    // multisampled textures can't be sampled like usual ones, only rendered to
+                    // and resolved
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        };
+
+        Attachments {
+            color: (sample_count > 1).then(|| create_view(config.format, "msaa color")),
+            depth: create_view(DepthTexture::FORMAT, "msaa depth"),
+        }
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("msaa"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState {
+            // the spokes are seen from both sides
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DepthTexture::FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        // has to match the sample count of every attachment the pipeline draws into
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+    })
+}
+
+pub struct Msaa {
+    // by sample count, msaa off and on
+    pipelines: Pipelines<u32>,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader: wgpu::ShaderModule,
+    attachments: Attachments,
+    config: wgpu::SurfaceConfiguration,
+    // what the adapter allows for both formats, 1 if it can't multisample them
+    max_sample_count: u32,
+    sample_count: u32,
+    // set by the key, the attachments are remade in `render` where the device is
+    rebuild: bool,
+    vertex_buffer: wgpu::Buffer,
+    num_vertices: u32,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    fxaa: Fxaa,
+    fxaa_enabled: bool,
+    // what the scene resolves into when fxaa is on, and fxaa reads
+    scene: Targets,
+    scene_bindgroup: wgpu::BindGroup,
+}
+
+impl Msaa {
+    fn title(&self) -> String {
+        let msaa = match self.sample_count {
+            1 => "msaa, off".to_string(),
+            count => format!("msaa, {}x", count),
+        };
+        if self.fxaa_enabled {
+            format!("{}, fxaa", msaa)
+        } else {
+            msaa
+        }
+    }
+}
+
+impl framework::App for Msaa {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // the swapchain format has to resolve too, depth only needs the samples
+        let color_flags = adapter.get_texture_format_features(config.format).flags;
+        let depth_flags = adapter
+            .get_texture_format_features(DepthTexture::FORMAT)
+            .flags;
+        let max_sample_count = if color_flags.contains(
+            wgpu::TextureFormatFeatureFlags::MULTISAMPLE
+                | wgpu::TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE,
+        ) && depth_flags
+            .contains(wgpu::TextureFormatFeatureFlags::MULTISAMPLE)
+        {
+            SAMPLE_COUNT
+        } else {
+            warn!(
+                "{:?} can't be multisampled here, msaa stays off",
+                config.format
+            );
+            1
+        };
+        let sample_count = max_sample_count;
+
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.5,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 3.5, CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // vertex buffer
+        let verticrs = build_scene();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        // built in `warmup`, the one it starts with first
+        let pipelines = Pipelines::new([sample_count, 1]);
+        let attachments = Attachments::new(device, config, sample_count);
+
+        // fxaa, its input has the swapchain's format so the scene pipeline fits both
+        let fxaa = Fxaa::new(device, config.format);
+        let scene = Targets::new(
+            device,
+            config.width,
+            config.height,
+            vec![TargetDesc::new(config.format, 1, "scene")],
+        );
+        let scene_bindgroup = fxaa.create_bind_group(device, &scene[0]);
+
+        info!("press m to turn msaa on and off, f for fxaa");
+
+        Msaa {
+            pipelines,
+            pipeline_layout,
+            shader,
+            attachments,
+            config: config.clone(),
+            max_sample_count,
+            sample_count,
+            rebuild: false,
+            vertex_buffer,
+            num_vertices: verticrs.len() as u32,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            fxaa,
+            fxaa_enabled: false,
+            scene,
+            scene_bindgroup,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+        self.config = config.clone();
+        self.attachments = Attachments::new(device, config, self.sample_count);
+        if self.scene.resize(device, config.width, config.height) {
+            self.scene_bindgroup = self.fxaa.create_bind_group(device, &self.scene[0]);
+        }
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::M if self.max_sample_count == 1 => {
+                    warn!("msaa isn't supported for {:?}", self.config.format);
+                    return;
+                }
+                VirtualKeyCode::M => {
+                    self.sample_count = if self.sample_count == 1 {
+                        self.max_sample_count
+                    } else {
+                        1
+                    };
+                    self.rebuild = true;
+                }
+                VirtualKeyCode::F => self.fxaa_enabled = !self.fxaa_enabled,
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn warmup(&mut self, device: &wgpu::Device) -> Option<Progress> {
+        self.pipelines.step(|&sample_count| {
+            create_pipeline(
+                device,
+                &self.pipeline_layout,
+                &self.shader,
+                self.config.format,
+                sample_count,
+            )
+        })
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.rebuild {
+            self.rebuild = false;
+            self.attachments = Attachments::new(device, &self.config, self.sample_count);
+        }
+
+        self.camera_controller.update_camera(&mut self.camera, 0.);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // with fxaa the scene is only the input of another pass
+        let output = if self.fxaa_enabled {
+            &self.scene[0].view
+        } else {
+            frame.view
+        };
+
+        {
+            // with msaa the samples go to the multisampled texture and are averaged into the
+            // output when the pass ends. only the resolved pixels are kept, storing the
+            // samples would just be memory traffic
+            let (view, resolve_target) = match &self.attachments.color {
+                Some(samples) => (samples, Some(output)),
+                None => (output, None),
+            };
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("msaa pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.05,
+                            g: 0.05,
+                            b: 0.07,
+                            a: 1.,
+                        }),
+                        store: resolve_target.is_none(),
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.attachments.depth,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+
+            let pipeline = self.pipelines.get(self.sample_count, |&sample_count| {
+                create_pipeline(
+                    device,
+                    &self.pipeline_layout,
+                    &self.shader,
+                    self.config.format,
+                    sample_count,
+                )
+            });
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.draw(0..self.num_vertices, 0..1);
+        }
+
+        if self.fxaa_enabled {
+            let frame = framework::Frame {
+                view: frame.view,
+                depth: None,
+                ..*frame
+            };
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+            self.fxaa.draw(&mut rpass, &self.scene_bindgroup);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t39_msaa::Msaa>(t39_msaa::TITLE);
+}
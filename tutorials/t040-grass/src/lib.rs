@@ -0,0 +1,610 @@
+// a field of grass, up to hundreds of thousands of blades in a single instanced draw. each
+// blade is the same handful of vertices, the instance buffer says where it stands, which way it
+// faces, how tall and how wide it is. everything that moves happens in the vertex shader: the
+// wind sways the whole field in slow waves and bends the blades further by a noise texture
+// scrolling over it, and blades far from the camera are dropped.
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, FpsCameraController},
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::Vec3;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "grass, 262144 blades, wind 1.00, falloff on";
+
+const CAMERA_SPEED: f32 = 4.;
+const CAMERA_SENSITIVITY: f32 = 0.003;
+
+// the grass covers FIELD_SIZE x FIELD_SIZE, a grid of blades this many to a side: 65,536,
+// 262,144 or 589,824 of them. b steps through, starting at the second
+const FIELD_SIZE: f32 = 40.;
+const BLADES_PER_SIDE: [u32; 3] = [256, 512, 768];
+// the ground reaches past the grass, into the fog
+const TERRAIN_SIZE: f32 = 80.;
+const TERRAIN_CELLS: u32 = 160;
+// segments up each blade, more bend smoother
+const BLADE_SEGMENTS: u32 = 4;
+const NOISE_SIZE: u32 = 128;
+
+const WIND_DIRECTION: [f32; 2] = [0.8, 0.6];
+const WIND_STEP: f32 = 0.25;
+const MAX_WIND: f32 = 3.;
+const FALLOFF_START: f32 = 8.;
+const FALLOFF_END: f32 = 25.;
+
+fn hash(a: u32, b: u32) -> f32 {
+    let mut h = a.wrapping_mul(0x27d4_eb2d) ^ b.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    (h & 0xffff) as f32 / 65535.
+}
+
+// gentle hills, the blades are planted at this height
+fn terrain_height(x: f32, z: f32) -> f32 {
+    0.6 * (x * 0.15).sin() * (z * 0.12).cos() + 0.3 * ((x + z) * 0.25).sin()
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct TerrainVertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+}
+
+impl TerrainVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TerrainVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// a grid over the height function, normals from its slope
+fn create_terrain() -> (Vec<TerrainVertex>, Vec<u16>) {
+    let points = TERRAIN_CELLS + 1;
+    let step = TERRAIN_SIZE / TERRAIN_CELLS as f32;
+    let mut verticrs = Vec::with_capacity((points * points) as usize);
+    for j in 0..points {
+        for i in 0..points {
+            let x = i as f32 * step - TERRAIN_SIZE / 2.;
+            let z = j as f32 * step - TERRAIN_SIZE / 2.;
+            let e = 0.01;
+            let dx = (terrain_height(x + e, z) - terrain_height(x - e, z)) / (2. * e);
+            let dz = (terrain_height(x, z + e) - terrain_height(x, z - e)) / (2. * e);
+            verticrs.push(TerrainVertex {
+                pos: [x, terrain_height(x, z), z],
+                normal: Vec3::new(-dx, 1., -dz).normalize().to_array(),
+            });
+        }
+    }
+
+    let mut indices = Vec::with_capacity((TERRAIN_CELLS * TERRAIN_CELLS * 6) as usize);
+    for j in 0..TERRAIN_CELLS {
+        for i in 0..TERRAIN_CELLS {
+            let a = (j * points + i) as u16;
+            let b = a + 1;
+            let c = a + points as u16;
+            let d = c + 1;
+            // counter clockwise seen from above
+            indices.extend([a, c, b, b, c, d]);
+        }
+    }
+    (verticrs, indices)
+}
+
+// a blade standing at the origin, facing +z, as (side, height) pairs for the shader to scale
+fn create_blade() -> (Vec<[f32; 2]>, Vec<u16>) {
+    let mut verticrs = Vec::new();
+    for segment in 0..BLADE_SEGMENTS {
+        let t = segment as f32 / BLADE_SEGMENTS as f32;
+        verticrs.extend([[-1., t], [1., t]]);
+    }
+    // the tip is a single point
+    verticrs.push([0., 1.]);
+
+    let mut indices = Vec::new();
+    for segment in 0..BLADE_SEGMENTS as u16 - 1 {
+        let base = segment * 2;
+        indices.extend([base, base + 1, base + 3, base, base + 3, base + 2]);
+    }
+    let base = (BLADE_SEGMENTS as u16 - 1) * 2;
+    indices.extend([base, base + 1, base + 2]);
+    (verticrs, indices)
+}
+
+// matches `InstanceInput` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct BladeInstance {
+    root: [f32; 3],
+    rotation: f32,
+    height: f32,
+    width: f32,
+    random: f32,
+}
+
+impl BladeInstance {
+    // locations continue after the blade's 0
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        1 => Float32x3, 2 => Float32, 3 => Float32, 4 => Float32, 5 => Float32
+    ];
+
+    fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<BladeInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// one blade in every cell of a `per_side` x `per_side` grid, jittered inside it so no rows show
+fn create_instances(per_side: u32) -> Vec<BladeInstance> {
+    let spacing = FIELD_SIZE / per_side as f32;
+    let mut seed = 0;
+    let mut random = || {
+        seed += 1;
+        hash(seed, 0x5eed)
+    };
+
+    // the denser the field the thinner the blades, or it turns into a carpet
+    let width = (256. / per_side as f32).sqrt();
+    let mut instances = Vec::with_capacity((per_side * per_side) as usize);
+    for j in 0..per_side {
+        for i in 0..per_side {
+            let x = (i as f32 + random()) * spacing - FIELD_SIZE / 2.;
+            let z = (j as f32 + random()) * spacing - FIELD_SIZE / 2.;
+            instances.push(BladeInstance {
+                root: [x, terrain_height(x, z), z],
+                rotation: random() * std::f32::consts::TAU,
+                height: 0.4 + 0.5 * random(),
+                width: width * (0.7 + 0.6 * random()),
+                random: random(),
+            });
+        }
+    }
+    instances
+}
+
+fn create_instance_buffer(device: &wgpu::Device, instances: &[BladeInstance]) -> wgpu::Buffer {
+    device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Instance Buffer"),
+        contents: bytemuck::cast_slice(instances),
+        usage: wgpu::BufferUsages::VERTEX,
+    })
+}
+
+// three octaves of value noise. the lattices divide the texture evenly and wrap around, so
+// it tiles like the repeating sampler expects
+fn create_noise() -> Vec<u8> {
+    let lattice = |cells: u32, x: u32, y: u32| {
+        let cell_size = NOISE_SIZE as f32 / cells as f32;
+        let (fx, fy) = (x as f32 / cell_size, y as f32 / cell_size);
+        let (x0, y0) = (fx as u32, fy as u32);
+        let smooth = |t: f32| t * t * (3. - 2. * t);
+        let (tx, ty) = (smooth(fx.fract()), smooth(fy.fract()));
+        // every octave its own values
+        let corner = |dx: u32, dy: u32| hash((x0 + dx) % cells + cells * 1000, (y0 + dy) % cells);
+        let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * tx;
+        let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * tx;
+        top + (bottom - top) * ty
+    };
+
+    let mut texels = Vec::with_capacity((NOISE_SIZE * NOISE_SIZE) as usize);
+    for y in 0..NOISE_SIZE {
+        for x in 0..NOISE_SIZE {
+            let value = 0.5 * lattice(4, x, y) + 0.3 * lattice(8, x, y) + 0.2 * lattice(16, x, y);
+            texels.push((value * 255.) as u8);
+        }
+    }
+    texels
+}
+
+// matches `Scene` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct SceneUniform {
+    view_proj: [[f32; 4]; 4],
+    camera_position: [f32; 4],
+    wind_direction: [f32; 2],
+    wind_strength: f32,
+    time: f32,
+    falloff_start: f32,
+    falloff_end: f32,
+    falloff_enabled: u32,
+    _padding: u32,
+}
+
+pub struct Grass {
+    terrain_pipeline: wgpu::RenderPipeline,
+    grass_pipeline: wgpu::RenderPipeline,
+    terrain_vertex_buffer: wgpu::Buffer,
+    terrain_index_buffer: wgpu::Buffer,
+    num_terrain_indices: u32,
+    blade_vertex_buffer: wgpu::Buffer,
+    blade_index_buffer: wgpu::Buffer,
+    num_blade_indices: u32,
+    instance_buffer: wgpu::Buffer,
+    num_instances: u32,
+    /// index into BLADES_PER_SIDE. the instance buffer is made again when it doesn't match
+    density: usize,
+    scene_buffer: wgpu::Buffer,
+    scene_bindgroup: wgpu::BindGroup,
+    camera: Camera,
+    camera_controller: FpsCameraController,
+    wind_strength: f32,
+    falloff: bool,
+    paused: bool,
+    time: f32,
+}
+
+impl Grass {
+    fn title(&self) -> String {
+        format!(
+            "grass, {} blades, wind {:.2}, falloff {}",
+            BLADES_PER_SIDE[self.density].pow(2),
+            self.wind_strength,
+            if self.falloff { "on" } else { "off" }
+        )
+    }
+
+    fn scene_uniform(&self) -> SceneUniform {
+        SceneUniform {
+            view_proj: self.camera.view_proj().to_cols_array_2d(),
+            camera_position: self.camera.position.extend(1.).to_array(),
+            wind_direction: WIND_DIRECTION,
+            wind_strength: self.wind_strength,
+            time: self.time,
+            falloff_start: FALLOFF_START,
+            falloff_end: FALLOFF_END,
+            falloff_enabled: self.falloff as u32,
+            _padding: 0,
+        }
+    }
+}
+
+impl framework::App for Grass {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // camera, standing at the edge of the field
+        let camera = Camera::new(
+            Vec3::new(0., 2., FIELD_SIZE / 2. + 2.),
+            0.,
+            -0.2,
+            config.width as f32 / config.height as f32,
+        );
+        let camera_controller = FpsCameraController::new(CAMERA_SPEED, CAMERA_SENSITIVITY);
+
+        let scene_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Scene Buffer"),
+            size: std::mem::size_of::<SceneUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // the noise the wind reads, sampled in the vertex shader
+        let noise_texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("noise_texture"),
+                size: wgpu::Extent3d {
+                    width: NOISE_SIZE,
+                    height: NOISE_SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::R8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            },
+            &create_noise(),
+        );
+        let noise_view = noise_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let noise_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("noise sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let scene_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("scene bindgroup layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: wgpu::BufferSize::new(
+                                std::mem::size_of::<SceneUniform>() as u64,
+                            ),
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let scene_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("scene bind group"),
+            layout: &scene_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: scene_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&noise_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&noise_sampler),
+                },
+            ],
+        });
+
+        // terrain
+        let (verticrs, indices) = create_terrain();
+        let terrain_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Terrain Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let terrain_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Terrain Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let num_terrain_indices = indices.len() as u32;
+
+        // one blade, and where all of them go
+        let (verticrs, indices) = create_blade();
+        let blade_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Blade Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let blade_index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Blade Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let num_blade_indices = indices.len() as u32;
+
+        let density = 1;
+        let instances = create_instances(BLADES_PER_SIDE[density]);
+        let instance_buffer = create_instance_buffer(device, &instances);
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&scene_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline =
+            |label, vs_entry, fs_entry, buffers: &[wgpu::VertexBufferLayout], cull_mode| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: vs_entry,
+                        buffers,
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: fs_entry,
+                        targets: &[Some(config.format.into())],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        cull_mode,
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: DepthTexture::FORMAT,
+                        depth_write_enabled: true,
+                        depth_compare: wgpu::CompareFunction::Less,
+                        stencil: wgpu::StencilState::default(),
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                })
+            };
+
+        let terrain_pipeline = create_pipeline(
+            "terrain",
+            "vs_terrain",
+            "fs_terrain",
+            &[TerrainVertex::desc()],
+            Some(wgpu::Face::Back),
+        );
+        // blades are flat, both sides show
+        let grass_pipeline = create_pipeline(
+            "grass",
+            "vs_grass",
+            "fs_grass",
+            &[
+                wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+                },
+                BladeInstance::buffer_layout(),
+            ],
+            None,
+        );
+
+        info!("press up / down to change the wind, b for more or fewer blades, f to turn the distance falloff off and on, p to pause");
+
+        Grass {
+            terrain_pipeline,
+            grass_pipeline,
+            terrain_vertex_buffer,
+            terrain_index_buffer,
+            num_terrain_indices,
+            blade_vertex_buffer,
+            blade_index_buffer,
+            num_blade_indices,
+            instance_buffer,
+            num_instances: instances.len() as u32,
+            density,
+            scene_buffer,
+            scene_bindgroup,
+            camera,
+            camera_controller,
+            wind_strength: 1.,
+            falloff: true,
+            paused: false,
+            time: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::Up => {
+                    self.wind_strength = (self.wind_strength + WIND_STEP).min(MAX_WIND)
+                }
+                VirtualKeyCode::Down => {
+                    self.wind_strength = (self.wind_strength - WIND_STEP).max(0.)
+                }
+                VirtualKeyCode::B => self.density = (self.density + 1) % BLADES_PER_SIDE.len(),
+                VirtualKeyCode::F => self.falloff = !self.falloff,
+                VirtualKeyCode::P => self.paused = !self.paused,
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        // b changed the density, the field is planted again
+        let per_side = BLADES_PER_SIDE[self.density];
+        if self.num_instances != per_side * per_side {
+            let instances = create_instances(per_side);
+            self.instance_buffer = create_instance_buffer(device, &instances);
+            self.num_instances = instances.len() as u32;
+        }
+
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        if !self.paused {
+            self.time += frame.delta;
+        }
+        watchdog::write_buffer(
+            queue,
+            &self.scene_buffer,
+            0,
+            bytemuck::bytes_of(&self.scene_uniform()),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.55,
+                    g: 0.7,
+                    b: 0.85,
+                    a: 1.,
+                },
+            );
+            rpass.set_bind_group(0, &self.scene_bindgroup, &[]);
+
+            rpass.set_pipeline(&self.terrain_pipeline);
+            rpass.set_vertex_buffer(0, self.terrain_vertex_buffer.slice(..));
+            rpass.set_index_buffer(
+                self.terrain_index_buffer.slice(..),
+                wgpu::IndexFormat::Uint16,
+            );
+            rpass.draw_indexed(0..self.num_terrain_indices, 0, 0..1);
+
+            // every blade in one call
+            rpass.set_pipeline(&self.grass_pipeline);
+            rpass.set_vertex_buffer(0, self.blade_vertex_buffer.slice(..));
+            rpass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            rpass.set_index_buffer(self.blade_index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_blade_indices, 0, 0..self.num_instances);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
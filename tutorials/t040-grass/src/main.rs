@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t40_grass::Grass>(t40_grass::TITLE);
+}
@@ -0,0 +1,160 @@
+// the terrain and the grass on it. the blades are one small mesh drawn once per instance, the
+// vertex shader places, bends and thins them out
+
+struct Scene {
+    view_proj: mat4x4<f32>,
+    // w unused
+    camera_position: vec4<f32>,
+    // on the ground plane, x and z
+    wind_direction: vec2<f32>,
+    wind_strength: f32,
+    time: f32,
+    // blades thin out between these distances from the camera
+    falloff_start: f32,
+    falloff_end: f32,
+    falloff_enabled: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> scene: Scene;
+// tiling value noise, the gusts that roll over the field
+@group(0) @binding(1)
+var t_noise: texture_2d<f32>;
+@group(0) @binding(2)
+var s_noise: sampler;
+
+let sky = vec3<f32>(0.55, 0.7, 0.85);
+let light_dir = vec3<f32>(0.3, 0.8, 0.5);
+// half a blade's width at the root
+let BLADE_WIDTH: f32 = 0.06;
+// how many times the noise repeats per unit, and how fast the gusts travel
+let NOISE_SCALE: f32 = 0.04;
+let NOISE_SPEED: f32 = 0.03;
+// the sway, waves that roll downwind across the whole field: how far apart in units, how often
+// a blade swings back and forth a second, and how far compared to a full gust
+let SWAY_WAVELENGTH: f32 = 9.0;
+let SWAY_FREQUENCY: f32 = 0.35;
+let SWAY_AMOUNT: f32 = 0.3;
+// the fewest blades the falloff keeps, far away grass gets sparse but never bare
+let MIN_DENSITY: f32 = 0.15;
+
+// fades into the sky color with distance, hides where the field ends
+fn fog(color: vec3<f32>, world_pos: vec3<f32>) -> vec3<f32> {
+    let distance = length(world_pos - scene.camera_position.xyz);
+    let amount = clamp((distance - 15.0) / 30.0, 0.0, 1.0);
+    return mix(color, sky, amount);
+}
+
+// terrain
+
+struct TerrainInput {
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+struct TerrainFragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_terrain(input: TerrainInput) -> TerrainFragInput {
+    var fragInput : TerrainFragInput;
+    fragInput.clip_position = scene.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    fragInput.normal = input.normal;
+    return fragInput;
+}
+
+@fragment
+fn fs_terrain(input: TerrainFragInput) -> @location(0) vec4<f32> {
+    let diffuse = max(dot(normalize(input.normal), normalize(light_dir)), 0.0);
+    let color = vec3<f32>(0.22, 0.3, 0.1) * (0.4 + 0.6 * diffuse);
+    return vec4<f32>(fog(color, input.world_pos), 1.0);
+}
+
+// grass
+
+struct BladeInput {
+    // x is -1 on the left edge and 1 on the right, y goes from 0 at the root to 1 at the tip
+    @location(0) coord: vec2<f32>,
+};
+
+// matches `BladeInstance` in lib.rs
+struct InstanceInput {
+    @location(1) root: vec3<f32>,
+    // radians around +y
+    @location(2) rotation: f32,
+    @location(3) height: f32,
+    // scales BLADE_WIDTH
+    @location(4) width: f32,
+    // 0..1, decides when the blade is dropped and varies its color
+    @location(5) random: f32,
+};
+
+struct BladeFragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// the share of blades kept at `root`, 1 close to the camera
+fn density(root: vec3<f32>) -> f32 {
+    if (scene.falloff_enabled == 0u) {
+        return 1.0;
+    }
+    let distance = length(root.xz - scene.camera_position.xz);
+    let fade = clamp((distance - scene.falloff_start) / (scene.falloff_end - scene.falloff_start), 0.0, 1.0);
+    return mix(1.0, MIN_DENSITY, fade);
+}
+
+@vertex
+fn vs_grass(blade: BladeInput, instance: InstanceInput) -> BladeFragInput {
+    var fragInput : BladeFragInput;
+
+    // every blade has its own random number, the ones above the density are dropped. all
+    // their vertices land on the same spot outside the depth range, nothing is rasterized
+    let keep = density(instance.root);
+    if (instance.random > keep) {
+        fragInput.clip_position = vec4<f32>(0.0, 0.0, 2.0, 1.0);
+        return fragInput;
+    }
+
+    let t = blade.coord.y;
+    // the blades left are wider, so a thinner field still covers the ground
+    let width = BLADE_WIDTH * instance.width * (1.0 - t) / sqrt(keep);
+    let across = vec3<f32>(cos(instance.rotation), 0.0, -sin(instance.rotation));
+
+    // the sway where the blade stands, the gust at the root scrolling downwind, plus a little
+    // flutter of its own
+    let along = dot(instance.root.xz, scene.wind_direction) / SWAY_WAVELENGTH;
+    let sway = SWAY_AMOUNT * (0.5 + 0.5 * sin(6.2831855 * (along - scene.time * SWAY_FREQUENCY)));
+    let uv = instance.root.xz * NOISE_SCALE - scene.wind_direction * scene.time * NOISE_SPEED;
+    let gust = textureSampleLevel(t_noise, s_noise, uv, 0.0).r;
+    let flutter = 0.15 * sin(scene.time * 4.0 + instance.random * 40.0);
+    let bend = scene.wind_strength * (sway + gust + flutter);
+
+    // bent more toward the tip, and lower the further it leans so the blade keeps its length
+    let lean = bend * t * t;
+    let downwind = vec3<f32>(scene.wind_direction.x, 0.0, scene.wind_direction.y);
+    let up = t * max(1.0 - 0.4 * lean * lean, 0.2);
+    let world_pos = instance.root
+        + across * blade.coord.x * width
+        + (vec3<f32>(0.0, up, 0.0) + downwind * lean) * instance.height;
+
+    // the random number scrambled, or the far away survivors would all share one shade
+    let shade = fract(instance.random * 17.0);
+    let tint = mix(vec3<f32>(0.3, 0.45, 0.1), vec3<f32>(0.45, 0.55, 0.15), shade);
+    // dark at the root where the blades shade each other, lighter at the tip
+    fragInput.color = tint * mix(0.35, 1.1, t);
+
+    fragInput.clip_position = scene.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    return fragInput;
+}
+
+@fragment
+fn fs_grass(input: BladeFragInput) -> @location(0) vec4<f32> {
+    return vec4<f32>(fog(input.color, input.world_pos), 1.0);
+}
@@ -0,0 +1,223 @@
+// the impostor of a mesh: pictures of it taken from ANGLES directions around y once at
+// startup, and a pipeline drawing each far away copy as a single quad with the nearest
+// picture on it. lighting is baked into the pictures along with everything else, which only
+// holds because the trees never turn and the sun never moves.
+
+use framework::texture::{DepthTexture, Texture};
+use glam::{Mat4, Vec3};
+use wgpu::include_wgsl;
+
+use wgpu::util::DeviceExt;
+
+use crate::{CameraUniform, Mesh, TreeInstance};
+
+// matches `ANGLES` in impostor.wgsl
+pub const ANGLES: u32 = 16;
+// matches `RADIUS` and `CENTER_Y` in impostor.wgsl, a sphere around the tree every picture
+// is framed on
+pub const RADIUS: f32 = 1.75;
+pub const CENTER_Y: f32 = 1.7;
+// texels per side of one picture
+const CELL_SIZE: u32 = 128;
+// srgb like the window, the pictures come out as they'd look on screen
+pub const ATLAS_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// renders `mesh` at the origin from every angle into one row of pictures. `pipeline` draws
+/// `mesh` into [`ATLAS_FORMAT`] and takes a camera at group 0 of `camera_layout`
+pub fn bake(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    pipeline: &wgpu::RenderPipeline,
+    camera_layout: &wgpu::BindGroupLayout,
+    mesh: &Mesh,
+) -> Texture {
+    let width = CELL_SIZE * ANGLES;
+    let atlas = Texture::create_render_target(device, width, CELL_SIZE, ATLAS_FORMAT, "atlas");
+    let depth = Texture::create_depth(device, width, CELL_SIZE, "atlas depth");
+
+    let origin = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some("Bake Instance Buffer"),
+        contents: bytemuck::bytes_of(&TreeInstance {
+            offset: [0.; 3],
+            scale: 1.,
+        }),
+        usage: wgpu::BufferUsages::VERTEX,
+    });
+
+    // an orthographic camera per angle, looking at the center from outside the sphere. no
+    // perspective, the quad the picture ends up on adds its own
+    let center = Vec3::new(0., CENTER_Y, 0.);
+    let bindgroups: Vec<wgpu::BindGroup> = (0..ANGLES)
+        .map(|view| {
+            let angle = view as f32 * std::f32::consts::TAU / ANGLES as f32;
+            let eye = center + Vec3::new(angle.sin(), 0., angle.cos()) * RADIUS * 2.;
+            let view_proj =
+                Mat4::orthographic_rh(-RADIUS, RADIUS, -RADIUS, RADIUS, 0.1, RADIUS * 4.)
+                    * Mat4::look_at_rh(eye, center, Vec3::Y);
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Bake Camera Buffer"),
+                contents: bytemuck::bytes_of(&CameraUniform {
+                    view_proj: view_proj.to_cols_array_2d(),
+                    position: eye.extend(1.).to_array(),
+                }),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("bake camera bind group"),
+                layout: camera_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            })
+        })
+        .collect();
+
+    let mut encoder =
+        device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+    {
+        // transparent where the tree isn't, the billboards cut that out
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("impostor bake"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &atlas.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &depth.view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        rpass.set_pipeline(pipeline);
+        rpass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        rpass.set_vertex_buffer(1, origin.slice(..));
+        rpass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        // every picture in its own square of the same target
+        for (view, bindgroup) in bindgroups.iter().enumerate() {
+            let x = (view as u32 * CELL_SIZE) as f32;
+            rpass.set_viewport(x, 0., CELL_SIZE as f32, CELL_SIZE as f32, 0., 1.);
+            rpass.set_bind_group(0, bindgroup, &[]);
+            rpass.draw_indexed(0..mesh.num_indices, 0, 0..1);
+        }
+    }
+    queue.submit(Some(encoder.finish()));
+
+    atlas
+}
+
+/// draws impostors from an atlas [`bake`] made, instanced like the meshes they stand in for
+pub struct Billboards {
+    pipeline: wgpu::RenderPipeline,
+    atlas_bindgroup: wgpu::BindGroup,
+}
+
+impl Billboards {
+    pub fn new(
+        device: &wgpu::Device,
+        camera_layout: &wgpu::BindGroupLayout,
+        atlas: &Texture,
+        format: wgpu::TextureFormat,
+    ) -> Billboards {
+        let atlas_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("atlas_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let atlas_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("atlas_bind_group"),
+            layout: &atlas_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&atlas.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&atlas.sampler),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("impostor.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[camera_layout, &atlas_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("impostor"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                // only the instances, the corners come from the vertex index
+                buffers: &[TreeInstance::buffer_layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                // always faces the camera anyway
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Billboards {
+            pipeline,
+            atlas_bindgroup,
+        }
+    }
+
+    /// the first `count` instances of `instances` as impostors
+    pub fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        camera_bindgroup: &'a wgpu::BindGroup,
+        instances: &'a wgpu::Buffer,
+        count: u32,
+    ) {
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, camera_bindgroup, &[]);
+        rpass.set_bind_group(1, &self.atlas_bindgroup, &[]);
+        rpass.set_vertex_buffer(0, instances.slice(..));
+        rpass.draw(0..6, 0..count);
+    }
+}
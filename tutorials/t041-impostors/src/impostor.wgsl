@@ -0,0 +1,80 @@
+// impostors, one quad per tree turned toward the camera around y, showing the baked view
+// closest to the direction it's seen from
+
+// matches `TreeInstance` in lib.rs, at the locations the meshes read it from so one buffer
+// layout serves both pipelines
+struct InstanceInput {
+    @location(3) offset: vec3<f32>,
+    @location(4) scale: f32,
+};
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// ANGLES views side by side, view k seen from angle k * TAU / ANGLES around y, 0 from +z
+@group(1) @binding(0)
+var t_atlas: texture_2d<f32>;
+@group(1) @binding(1)
+var s_atlas: sampler;
+
+// match `ANGLES`, `RADIUS` and `CENTER_Y` in impostor.rs
+let ANGLES: u32 = 16u;
+let RADIUS: f32 = 1.75;
+let CENTER_Y: f32 = 1.7;
+let TAU: f32 = 6.283185307;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> FragInput {
+    // two triangles, corners from -1 to 1
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[index];
+
+    let center = instance.offset + vec3<f32>(0.0, CENTER_Y, 0.0) * instance.scale;
+    // toward the camera on the ground plane, the view baked from nearest that way
+    let to_camera = normalize(camera.position.xz - center.xz);
+    let angle = atan2(to_camera.x, to_camera.y);
+    let step = TAU / f32(ANGLES);
+    let view = u32(i32(round(angle / step)) + i32(ANGLES)) % ANGLES;
+
+    // the bake camera's right, so the quad lines up with the picture on it
+    let right = vec3<f32>(to_camera.y, 0.0, -to_camera.x);
+    let size = RADIUS * instance.scale;
+    let world_pos = center + right * corner.x * size + vec3<f32>(0.0, corner.y * size, 0.0);
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    // the atlas is one row, y flips from up in the world to down in the texture
+    fragInput.tex_coord = vec2<f32>(
+        (f32(view) + corner.x * 0.5 + 0.5) / f32(ANGLES),
+        0.5 - corner.y * 0.5,
+    );
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_atlas, s_atlas, input.tex_coord);
+    // around the tree the bake left nothing, cut out instead of blended so depth still works
+    if (color.a < 0.5) {
+        discard;
+    }
+    return vec4<f32>(color.rgb, 1.0);
+}
@@ -0,0 +1,588 @@
+// a forest too big to draw in full detail. trees near the camera are meshes, the ones past a
+// distance are impostors: a single quad showing a picture of the tree baked at startup, from
+// the side it's seen from. both kinds are instanced, every frame sorts the trees into the two
+// instance buffers.
+//
+// a tree right at the threshold would flip between mesh and picture as the camera wobbles,
+// so there are two thresholds: it turns into an impostor a little past the distance and back
+// into a mesh a little before it.
+
+mod impostor;
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, FpsCameraController},
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::Vec3;
+use impostor::Billboards;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "impostors past 30m, hysteresis on";
+
+const CAMERA_SPEED: f32 = 8.;
+const CAMERA_SENSITIVITY: f32 = 0.003;
+
+// GRID_SIZE * GRID_SIZE trees, 2,304 of them
+const GRID_SIZE: u32 = 48;
+const GRID_SPACING: f32 = 4.;
+const START_LOD_DISTANCE: f32 = 30.;
+const LOD_STEP: f32 = 5.;
+const MIN_LOD_DISTANCE: f32 = 10.;
+// how far past the distance a tree has to go to change, either way
+const HYSTERESIS: f32 = 3.;
+
+fn hash(a: u32, b: u32) -> f32 {
+    let mut h = a.wrapping_mul(0x27d4_eb2d) ^ b.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    (h & 0xffff) as f32 / 65535.
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// where a tree stands and how big it is, for meshes and impostors alike
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct TreeInstance {
+    offset: [f32; 3],
+    scale: f32,
+}
+
+impl TreeInstance {
+    // locations continue after the vertex buffer's 0, 1 and 2
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![3 => Float32x3, 4 => Float32];
+
+    fn buffer_layout<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TreeInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// matches `Camera` in shader.wgsl and impostor.wgsl, the impostors turn toward the position
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+struct Mesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+}
+
+impl Mesh {
+    fn new(device: &wgpu::Device, verticrs: &[Vertex], indices: &[u16]) -> Mesh {
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Mesh {
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+        }
+    }
+
+    fn draw<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        instances: &'a wgpu::Buffer,
+        count: u32,
+    ) {
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.set_vertex_buffer(1, instances.slice(..));
+        rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        rpass.draw_indexed(0..self.num_indices, 0, 0..count);
+    }
+}
+
+// a box with its own 4 vertices per face for flat normals, wound counter clockwise from outside
+fn push_box(
+    verticrs: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    half: Vec3,
+    color: [f32; 3],
+) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+    for (normal, u, v) in faces {
+        let base = verticrs.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = center + (normal + u * su + v * sv) * half;
+            verticrs.push(Vertex {
+                pos: pos.to_array(),
+                normal: normal.to_array(),
+                color,
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+}
+
+// a blocky tree, lopsided on purpose so the pictures from different sides differ. it fits the
+// sphere of `impostor::RADIUS` around `impostor::CENTER_Y`
+fn create_tree() -> (Vec<Vertex>, Vec<u16>) {
+    let bark = [0.4, 0.26, 0.13];
+    let mut verticrs = Vec::new();
+    let mut indices = Vec::new();
+    let mut add = |center: [f32; 3], half: [f32; 3], color| {
+        push_box(
+            &mut verticrs,
+            &mut indices,
+            Vec3::from(center),
+            Vec3::from(half),
+            color,
+        )
+    };
+    add([0., 0.75, 0.], [0.15, 0.75, 0.15], bark);
+    add([0.6, 1.3, 0.], [0.4, 0.08, 0.08], bark);
+    add([0.95, 1.45, 0.], [0.2, 0.2, 0.2], [0.3, 0.6, 0.2]);
+    add([0., 1.8, 0.], [0.8, 0.5, 0.8], [0.2, 0.5, 0.15]);
+    add([0.15, 2.5, -0.1], [0.55, 0.4, 0.55], [0.25, 0.55, 0.18]);
+    add([0.1, 3.05, -0.15], [0.3, 0.3, 0.3], [0.3, 0.6, 0.2]);
+    (verticrs, indices)
+}
+
+fn create_ground() -> (Vec<Vertex>, Vec<u16>) {
+    let size = GRID_SIZE as f32 * GRID_SPACING;
+    let mut verticrs = Vec::new();
+    let mut indices = Vec::new();
+    push_box(
+        &mut verticrs,
+        &mut indices,
+        Vec3::new(0., -0.5, 0.),
+        Vec3::new(size, 0.5, size),
+        [0.3, 0.4, 0.2],
+    );
+    (verticrs, indices)
+}
+
+// a jittered grid of trees of different sizes
+fn create_trees() -> Vec<TreeInstance> {
+    let half = GRID_SIZE as f32 * GRID_SPACING * 0.5;
+    (0..GRID_SIZE * GRID_SIZE)
+        .map(|i| {
+            let (x, z) = ((i % GRID_SIZE) as f32, (i / GRID_SIZE) as f32);
+            let jitter = |seed| (hash(i, seed) - 0.5) * GRID_SPACING * 0.6;
+            TreeInstance {
+                offset: [
+                    x * GRID_SPACING - half + jitter(1),
+                    0.,
+                    z * GRID_SPACING - half + jitter(2),
+                ],
+                scale: 0.8 + 0.5 * hash(i, 3),
+            }
+        })
+        .collect()
+}
+
+fn create_mesh_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("mesh"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc(), TreeInstance::buffer_layout()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState {
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DepthTexture::FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+pub struct Impostors {
+    mesh_pipeline: wgpu::RenderPipeline,
+    billboards: Billboards,
+    tree_mesh: Mesh,
+    ground_mesh: Mesh,
+    // the ground is one instance at the origin
+    ground_instance: wgpu::Buffer,
+    trees: Vec<TreeInstance>,
+    // which trees are impostors right now, decided against the previous frame's choice
+    is_impostor: Vec<bool>,
+    // rewritten every frame, the meshes and impostors among `trees`
+    mesh_instances: Vec<TreeInstance>,
+    impostor_instances: Vec<TreeInstance>,
+    mesh_instance_buffer: wgpu::Buffer,
+    impostor_instance_buffer: wgpu::Buffer,
+    lod_distance: f32,
+    hysteresis: bool,
+    impostors_enabled: bool,
+    camera: Camera,
+    camera_controller: FpsCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    // logged once a second
+    swaps: u32,
+    // real seconds since the last report
+    since_report: f32,
+}
+
+impl Impostors {
+    fn title(&self) -> String {
+        if !self.impostors_enabled {
+            return "impostors off".to_string();
+        }
+        format!(
+            "impostors past {}m, hysteresis {}",
+            self.lod_distance,
+            if self.hysteresis { "on" } else { "off" }
+        )
+    }
+
+    // sort the trees into meshes and impostors. a mesh becomes an impostor once it's past the
+    // far threshold, an impostor turns back only inside the near one, in between it stays
+    // what it was
+    fn update_lod(&mut self) {
+        let band = if self.hysteresis { HYSTERESIS } else { 0. };
+        let (near, far) = (self.lod_distance - band, self.lod_distance + band);
+
+        self.mesh_instances.clear();
+        self.impostor_instances.clear();
+        for (tree, is_impostor) in self.trees.iter().zip(&mut self.is_impostor) {
+            let distance = self.camera.position.distance(Vec3::from(tree.offset));
+            let impostor = if !self.impostors_enabled {
+                false
+            } else if *is_impostor {
+                distance > near
+            } else {
+                distance > far
+            };
+            if impostor != *is_impostor {
+                *is_impostor = impostor;
+                self.swaps += 1;
+            }
+
+            if impostor {
+                self.impostor_instances.push(*tree);
+            } else {
+                self.mesh_instances.push(*tree);
+            }
+        }
+    }
+}
+
+impl framework::App for Impostors {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // camera, at the edge of the forest looking in
+        let mut camera = Camera::new(
+            Vec3::new(0., 4., GRID_SIZE as f32 * GRID_SPACING * 0.5 + 5.),
+            0.,
+            -0.1,
+            config.width as f32 / config.height as f32,
+        );
+        // far enough to see across the whole forest
+        camera.zfar = 300.;
+        let camera_controller = FpsCameraController::new(CAMERA_SPEED, CAMERA_SENSITIVITY);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // meshes
+        let (verticrs, indices) = create_tree();
+        let tree_mesh = Mesh::new(device, &verticrs, &indices);
+        let (verticrs, indices) = create_ground();
+        let ground_mesh = Mesh::new(device, &verticrs, &indices);
+        let ground_instance = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Ground Instance Buffer"),
+            contents: bytemuck::bytes_of(&TreeInstance {
+                offset: [0.; 3],
+                scale: 1.,
+            }),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        // every tree fits both buffers, however they are split
+        let trees = create_trees();
+        let create_instance_buffer = |label| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size: (trees.len() * std::mem::size_of::<TreeInstance>()) as u64,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            })
+        };
+        let mesh_instance_buffer = create_instance_buffer("Mesh Instance Buffer");
+        let impostor_instance_buffer = create_instance_buffer("Impostor Instance Buffer");
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+        let mesh_pipeline = create_mesh_pipeline(device, &pipeline_layout, &shader, config.format);
+
+        // the pictures, taken with the same shader into the atlas
+        let bake_pipeline =
+            create_mesh_pipeline(device, &pipeline_layout, &shader, impostor::ATLAS_FORMAT);
+        let atlas = impostor::bake(
+            device,
+            queue,
+            &bake_pipeline,
+            &camera_bindgroup_layout,
+            &tree_mesh,
+        );
+        let billboards = Billboards::new(device, &camera_bindgroup_layout, &atlas, config.format);
+
+        info!(
+            "{} trees. press up / down to move the impostor distance, h to turn hysteresis off and on, i to draw every tree as a mesh",
+            trees.len()
+        );
+
+        Impostors {
+            mesh_pipeline,
+            billboards,
+            tree_mesh,
+            ground_mesh,
+            ground_instance,
+            is_impostor: vec![false; trees.len()],
+            mesh_instances: Vec::with_capacity(trees.len()),
+            impostor_instances: Vec::with_capacity(trees.len()),
+            trees,
+            mesh_instance_buffer,
+            impostor_instance_buffer,
+            lod_distance: START_LOD_DISTANCE,
+            hysteresis: true,
+            impostors_enabled: true,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            swaps: 0,
+            since_report: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::Up => self.lod_distance += LOD_STEP,
+                VirtualKeyCode::Down => {
+                    self.lod_distance = (self.lod_distance - LOD_STEP).max(MIN_LOD_DISTANCE)
+                }
+                VirtualKeyCode::H => self.hysteresis = !self.hysteresis,
+                VirtualKeyCode::I => self.impostors_enabled = !self.impostors_enabled,
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        self.update_lod();
+        watchdog::write_buffer(
+            queue,
+            &self.mesh_instance_buffer,
+            0,
+            bytemuck::cast_slice(&self.mesh_instances),
+        );
+        watchdog::write_buffer(
+            queue,
+            &self.impostor_instance_buffer,
+            0,
+            bytemuck::cast_slice(&self.impostor_instances),
+        );
+
+        // a swap is a tree changing kind, without hysteresis standing still near the
+        // threshold is enough to keep them coming
+        self.since_report += frame.real_delta;
+        if self.since_report >= 1. {
+            info!(
+                "{} meshes, {} impostors, {} swaps",
+                self.mesh_instances.len(),
+                self.impostor_instances.len(),
+                self.swaps
+            );
+            self.swaps = 0;
+            self.since_report = 0.;
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.55,
+                    g: 0.7,
+                    b: 0.85,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_pipeline(&self.mesh_pipeline);
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            self.ground_mesh.draw(&mut rpass, &self.ground_instance, 1);
+            self.tree_mesh.draw(
+                &mut rpass,
+                &self.mesh_instance_buffer,
+                self.mesh_instances.len() as u32,
+            );
+
+            self.billboards.draw(
+                &mut rpass,
+                &self.camera_bindgroup,
+                &self.impostor_instance_buffer,
+                self.impostor_instances.len() as u32,
+            );
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t41_impostors::Impostors>(t41_impostors::TITLE);
+}
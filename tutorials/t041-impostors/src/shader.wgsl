@@ -0,0 +1,49 @@
+// the full tree meshes and the ground, lit by one fixed sun. the same pipeline, pointed at
+// the atlas, bakes the impostors
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+// matches `TreeInstance` in lib.rs
+struct InstanceInput {
+    @location(3) offset: vec3<f32>,
+    @location(4) scale: f32,
+};
+
+struct FragInput {
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+let light_dir = vec3<f32>(0.4, 0.8, 0.45);
+let ambient = 0.3;
+
+@vertex
+fn vs_main(input: VertexInput, instance: InstanceInput) -> FragInput {
+    var fragInput : FragInput;
+    let world_pos = input.pos * instance.scale + instance.offset;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    // uniform scale and no rotation, the normal stays as it is
+    fragInput.normal = input.normal;
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let diffuse = max(dot(normalize(input.normal), normalize(light_dir)), 0.0);
+    return vec4<f32>(input.color * (ambient + diffuse), 1.0);
+}
@@ -0,0 +1,615 @@
+// screen space ambient occlusion. creases, corners and the ground around the foot of a wall
+// get less of the sky's light than open surfaces, which a flat ambient term knows nothing
+// about. here the scene first goes into a g-buffer, view space normals and colors in two
+// targets written by one pass plus the depth, the occlusion passes in ssao.rs work out from
+// depth and normals how buried each pixel is, and a last fullscreen pass lights the g-buffer
+// with the ambient dimmed by it.
+
+mod ssao;
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    pass_graph::PassGraph,
+    targets::{TargetDesc, Targets},
+    texture::{DepthTexture, Texture},
+    watchdog,
+};
+use glam::Vec3;
+use ssao::Ssao;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "ssao, on, radius 0.50";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+
+// GRID_SIZE * GRID_SIZE blocks, a few of them left out
+const GRID_SIZE: u32 = 8;
+const GRID_SPACING: f32 = 1.2;
+const RADIUS_STEP: f32 = 0.1;
+const MIN_RADIUS: f32 = 0.1;
+
+const NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+const ALBEDO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+// matches `mode` in shader.wgsl
+const MODES: [&str; 3] = ["on", "off", "occlusion only"];
+
+fn hash(a: u32, b: u32) -> f32 {
+    let mut h = a.wrapping_mul(0x27d4_eb2d) ^ b.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    (h & 0xffff) as f32 / 65535.
+}
+
+fn uniform_entry(binding: u32) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+// every pass here reads its inputs texel for texel with textureLoad, no samplers
+fn texture_entry(binding: u32, sample_type: wgpu::TextureSampleType) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            sample_type,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        },
+        count: None,
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// matches `Camera` in shader.wgsl and ssao.wgsl. the occlusion pass goes back and forth
+// between view space and the screen, so it gets the pieces of `view_proj` too
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    view: [[f32; 4]; 4],
+    proj: [[f32; 4]; 4],
+    inv_proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        let proj = camera.projection();
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            view: camera.view().to_cols_array_2d(),
+            proj: proj.to_cols_array_2d(),
+            inv_proj: proj.inverse().to_cols_array_2d(),
+        }
+    }
+}
+
+// matches `Lighting` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LightingUniform {
+    mode: u32,
+    _padding: [u32; 3],
+}
+
+// a box with its own 4 vertices per face for flat normals, wound counter clockwise from outside
+fn push_box(
+    verticrs: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    half: Vec3,
+    color: [f32; 3],
+) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+    for (normal, u, v) in faces {
+        let base = verticrs.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = center + (normal + u * su + v * sv) * half;
+            verticrs.push(Vertex {
+                pos: pos.to_array(),
+                normal: normal.to_array(),
+                color,
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+}
+
+// blocks of different heights packed close on a ground plate, with narrow alleys between
+// them and small cubes on some of the roofs. plenty of corners to darken
+fn create_scene() -> (Vec<Vertex>, Vec<u16>) {
+    let mut verticrs = Vec::new();
+    let mut indices = Vec::new();
+    let half = GRID_SIZE as f32 * GRID_SPACING * 0.5;
+
+    push_box(
+        &mut verticrs,
+        &mut indices,
+        Vec3::new(0., -0.25, 0.),
+        Vec3::new(half + 2., 0.25, half + 2.),
+        [0.8, 0.8, 0.78],
+    );
+    for i in 0..GRID_SIZE * GRID_SIZE {
+        if hash(i, 1) < 0.15 {
+            continue;
+        }
+        let (x, z) = ((i % GRID_SIZE) as f32, (i / GRID_SIZE) as f32);
+        let height = 0.3 + 1.7 * hash(i, 2);
+        let center = Vec3::new(
+            (x + 0.5) * GRID_SPACING - half,
+            height * 0.5,
+            (z + 0.5) * GRID_SPACING - half,
+        );
+        let tint = 0.1 * hash(i, 3);
+        let color = [0.85 - tint, 0.8, 0.7 + tint];
+        push_box(
+            &mut verticrs,
+            &mut indices,
+            center,
+            Vec3::new(0.5, height * 0.5, 0.5),
+            color,
+        );
+        if hash(i, 4) < 0.4 {
+            push_box(
+                &mut verticrs,
+                &mut indices,
+                center + Vec3::new(0.2, height * 0.5 + 0.15, -0.1),
+                Vec3::splat(0.15),
+                color,
+            );
+        }
+    }
+    (verticrs, indices)
+}
+
+fn create_gbuffer(device: &wgpu::Device, width: u32, height: u32) -> Targets {
+    Targets::new(
+        device,
+        width,
+        height,
+        vec![
+            TargetDesc::new(NORMAL_FORMAT, 1, "gbuffer normal"),
+            TargetDesc::new(ALBEDO_FORMAT, 1, "gbuffer albedo"),
+        ],
+    )
+}
+
+fn create_lighting_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    camera_buffer: &wgpu::Buffer,
+    lighting_buffer: &wgpu::Buffer,
+    gbuffer: &Targets,
+    ao: &Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("lighting bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: lighting_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&gbuffer[0].view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&gbuffer[1].view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(&ao.view),
+            },
+        ],
+    })
+}
+
+pub struct AmbientOcclusion {
+    gbuffer_pipeline: wgpu::RenderPipeline,
+    lighting_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    // normals and colors, the depth next to them is a `Texture` of its own so the occlusion
+    // pass can read it
+    gbuffer: Targets,
+    depth: Texture,
+    ssao: Ssao,
+    mode: usize,
+    lighting_layout: wgpu::BindGroupLayout,
+    lighting_buffer: wgpu::Buffer,
+    lighting_bindgroup: wgpu::BindGroup,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+}
+
+impl AmbientOcclusion {
+    fn title(&self) -> String {
+        format!("ssao, {}, radius {:.2}", MODES[self.mode], self.ssao.radius)
+    }
+}
+
+impl framework::App for AmbientOcclusion {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.6,
+            -0.6,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 12., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[uniform_entry(0)],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // scene
+        let (verticrs, indices) = create_scene();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // g-buffer and occlusion
+        let gbuffer = create_gbuffer(device, config.width, config.height);
+        let depth = Texture::create_depth(device, config.width, config.height, "gbuffer depth");
+        let ssao = Ssao::new(
+            device,
+            queue,
+            &camera_buffer,
+            &depth,
+            &gbuffer[0],
+            config.width,
+            config.height,
+        );
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        // one pass, two color targets. the fragment shader's outputs go to them in order
+        let gbuffer_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+        let gbuffer_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("gbuffer"),
+            layout: Some(&gbuffer_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_gbuffer",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_gbuffer",
+                targets: &[Some(NORMAL_FORMAT.into()), Some(ALBEDO_FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // lighting
+        let lighting_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Lighting Buffer"),
+            contents: bytemuck::bytes_of(&LightingUniform {
+                mode: 0,
+                _padding: [0; 3],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let float = wgpu::TextureSampleType::Float { filterable: false };
+        let lighting_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("lighting bindgroup layout"),
+            entries: &[
+                uniform_entry(0),
+                uniform_entry(1),
+                texture_entry(2, float),
+                texture_entry(3, float),
+                texture_entry(4, float),
+            ],
+        });
+        let lighting_bindgroup = create_lighting_bind_group(
+            device,
+            &lighting_layout,
+            &camera_buffer,
+            &lighting_buffer,
+            &gbuffer,
+            ssao.output(),
+        );
+        let lighting_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&lighting_layout],
+                push_constant_ranges: &[],
+            });
+        let lighting_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("lighting"),
+            layout: Some(&lighting_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_fullscreen",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_lighting",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        info!("press o to cycle occlusion on, off and alone, up / down to change the radius");
+
+        AmbientOcclusion {
+            gbuffer_pipeline,
+            lighting_pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            gbuffer,
+            depth,
+            ssao,
+            mode: 0,
+            lighting_layout,
+            lighting_buffer,
+            lighting_bindgroup,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+        // everything reads the g-buffer texel for texel, it all follows the window size
+        if self.gbuffer.resize(device, config.width, config.height) {
+            self.depth =
+                Texture::create_depth(device, config.width, config.height, "gbuffer depth");
+            self.ssao.resize(
+                device,
+                &self.camera_buffer,
+                &self.depth,
+                &self.gbuffer[0],
+                config.width,
+                config.height,
+            );
+            self.lighting_bindgroup = create_lighting_bind_group(
+                device,
+                &self.lighting_layout,
+                &self.camera_buffer,
+                &self.lighting_buffer,
+                &self.gbuffer,
+                self.ssao.output(),
+            );
+        }
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::O => self.mode = (self.mode + 1) % MODES.len(),
+                VirtualKeyCode::Up => self.ssao.radius += RADIUS_STEP,
+                VirtualKeyCode::Down => {
+                    self.ssao.radius = (self.ssao.radius - RADIUS_STEP).max(MIN_RADIUS)
+                }
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller.update_camera(&mut self.camera, 0.);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+        self.ssao.update(queue);
+        watchdog::write_buffer(
+            queue,
+            &self.lighting_buffer,
+            0,
+            bytemuck::bytes_of(&LightingUniform {
+                mode: self.mode as u32,
+                _padding: [0; 3],
+            }),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("gbuffer"),
+                // albedo clears to transparent, alpha 0 tells the lighting pass there's only sky
+                color_attachments: &[
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: &self.gbuffer[0].view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: true,
+                        },
+                    }),
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: &self.gbuffer[1].view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: true,
+                        },
+                    }),
+                ],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        // the occlusion pass reads it afterwards
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            rpass.set_pipeline(&self.gbuffer_pipeline);
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+
+        self.ssao.render(frame, &mut encoder);
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+            rpass.set_pipeline(&self.lighting_pipeline);
+            rpass.set_bind_group(0, &self.lighting_bindgroup, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+    fn pass_graph(&self) -> Option<PassGraph> {
+        Some(
+            PassGraph::new()
+                .pass(
+                    "gbuffer",
+                    &[],
+                    &["gbuffer normal", "gbuffer albedo", "depth"],
+                )
+                .pass(
+                    "ssao",
+                    &["depth", "gbuffer normal", "noise", "kernel"],
+                    &["ao"],
+                )
+                .pass("ssao blur", &["ao"], &["ao blurred"])
+                .pass(
+                    "lighting",
+                    &["gbuffer normal", "gbuffer albedo", "ao blurred"],
+                    &["window"],
+                ),
+        )
+    }
+}
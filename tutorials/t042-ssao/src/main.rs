@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t42_ssao::AmbientOcclusion>(t42_ssao::TITLE);
+}
@@ -0,0 +1,112 @@
+// the g-buffer pass, writing view space normals and colors into two targets at once, and the
+// lighting pass reading them back along with the ambient occlusion
+
+// matches `CameraUniform` in lib.rs
+struct Camera {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// g-buffer
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct GBufferInput {
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// one member per color attachment, in the order of the pipeline's targets
+struct GBufferOutput {
+    @location(0) normal: vec4<f32>,
+    @location(1) albedo: vec4<f32>,
+};
+
+@vertex
+fn vs_gbuffer(input: VertexInput) -> GBufferInput {
+    var gbufferInput : GBufferInput;
+    gbufferInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    // the occlusion pass works in view space, the normals are stored that way
+    gbufferInput.normal = (camera.view * vec4<f32>(input.normal, 0.0)).xyz;
+    gbufferInput.color = input.color;
+    return gbufferInput;
+}
+
+@fragment
+fn fs_gbuffer(input: GBufferInput) -> GBufferOutput {
+    var output : GBufferOutput;
+    output.normal = vec4<f32>(normalize(input.normal), 0.0);
+    // alpha 1 marks geometry, the clear leaves 0 where there's only sky
+    output.albedo = vec4<f32>(input.color, 1.0);
+    return output;
+}
+
+// lighting
+
+struct FragInput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return fragInput;
+}
+
+// matches `LightingUniform` in lib.rs
+struct Lighting {
+    // 0 lit with occlusion, 1 lit without, 2 only the occlusion
+    mode: u32,
+};
+
+@group(0) @binding(1)
+var<uniform> lighting: Lighting;
+@group(0) @binding(2)
+var t_normal: texture_2d<f32>;
+@group(0) @binding(3)
+var t_albedo: texture_2d<f32>;
+@group(0) @binding(4)
+var t_ao: texture_2d<f32>;
+
+let light_dir = vec3<f32>(0.4, 0.8, 0.45);
+let sky = vec3<f32>(0.55, 0.7, 0.85);
+// a bright, flat ambient so there's plenty for the occlusion to take away
+let ambient = 0.6;
+
+@fragment
+fn fs_lighting(input: FragInput) -> @location(0) vec4<f32> {
+    let coord = vec2<i32>(input.clip_position.xy);
+    let albedo = textureLoad(t_albedo, coord, 0);
+    if (albedo.a == 0.0) {
+        return vec4<f32>(sky, 1.0);
+    }
+
+    var ao = textureLoad(t_ao, coord, 0).r;
+    if (lighting.mode == 2u) {
+        return vec4<f32>(ao, ao, ao, 1.0);
+    }
+    if (lighting.mode == 1u) {
+        ao = 1.0;
+    }
+
+    // the normal is in view space, so the light has to be too
+    let normal = normalize(textureLoad(t_normal, coord, 0).xyz);
+    let light = normalize((camera.view * vec4<f32>(light_dir, 0.0)).xyz);
+    let diffuse = max(dot(normal, light), 0.0) * 0.5;
+    // occlusion only dims the ambient, direct light isn't blocked by nearby creases
+    return vec4<f32>(albedo.rgb * (ambient * ao + diffuse), 1.0);
+}
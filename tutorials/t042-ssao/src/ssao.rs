@@ -0,0 +1,311 @@
+// the ambient occlusion passes. the first one looks at points in a small hemisphere above
+// each pixel and counts how many of them end up behind what the depth buffer holds, those
+// are inside some nearby surface. the second blurs the noisy result.
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    targets::{TargetDesc, Targets},
+    texture::Texture,
+    watchdog,
+};
+use wgpu::include_wgsl;
+
+use wgpu::util::DeviceExt;
+
+use crate::{hash, texture_entry, uniform_entry};
+
+// matches `KERNEL_SIZE` in ssao.wgsl, samples per pixel
+const KERNEL_SIZE: usize = 32;
+// matches `NOISE_SIZE` in ssao.wgsl, the random rotations repeat every this many pixels
+const NOISE_SIZE: u32 = 4;
+const AO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R8Unorm;
+
+// matches `Ssao` in ssao.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct SsaoUniform {
+    kernel: [[f32; 4]; KERNEL_SIZE],
+    radius: f32,
+    bias: f32,
+    power: f32,
+    _padding: f32,
+}
+
+// points in the unit hemisphere above +z. close ones matter more for the look, so the lengths
+// grow with the square of the index
+fn create_kernel() -> [[f32; 4]; KERNEL_SIZE] {
+    let mut kernel = [[0.; 4]; KERNEL_SIZE];
+    for (i, sample) in kernel.iter_mut().enumerate() {
+        let i = i as u32;
+        let direction = glam::Vec3::new(
+            hash(i, 1) * 2. - 1.,
+            hash(i, 2) * 2. - 1.,
+            hash(i, 3).max(0.05),
+        )
+        .normalize();
+        let t = i as f32 / KERNEL_SIZE as f32;
+        let length = hash(i, 4) * (0.1 + 0.9 * t * t);
+        *sample = (direction * length).extend(0.).to_array();
+    }
+    kernel
+}
+
+// directions in the xy plane, stored as 0..1
+fn create_noise() -> Vec<u8> {
+    (0..NOISE_SIZE * NOISE_SIZE)
+        .flat_map(|i| {
+            let x = hash(i, 5) * 2. - 1.;
+            let y = hash(i, 6) * 2. - 1.;
+            let to_byte = |v: f32| ((v * 0.5 + 0.5) * 255.) as u8;
+            [to_byte(x), to_byte(y), 128, 255]
+        })
+        .collect()
+}
+
+pub struct Ssao {
+    /// how far around each pixel to look for occluders, in world units
+    pub radius: f32,
+    kernel: [[f32; 4]; KERNEL_SIZE],
+    buffer: wgpu::Buffer,
+    noise_view: wgpu::TextureView,
+    layout: wgpu::BindGroupLayout,
+    blur_layout: wgpu::BindGroupLayout,
+    ssao_pipeline: wgpu::RenderPipeline,
+    blur_pipeline: wgpu::RenderPipeline,
+    // the raw occlusion and the blurred one
+    targets: Targets,
+    bindgroup: wgpu::BindGroup,
+    blur_bindgroup: wgpu::BindGroup,
+}
+
+impl Ssao {
+    /// reads the camera from `camera_buffer`, depth and view space normals from the g-buffer
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera_buffer: &wgpu::Buffer,
+        depth: &Texture,
+        normal: &Texture,
+        width: u32,
+        height: u32,
+    ) -> Ssao {
+        let radius = 0.5;
+        let kernel = create_kernel();
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Ssao Buffer"),
+            size: std::mem::size_of::<SsaoUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let noise_texture = device.create_texture_with_data(
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("ssao_noise"),
+                size: wgpu::Extent3d {
+                    width: NOISE_SIZE,
+                    height: NOISE_SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            },
+            &create_noise(),
+        );
+        let noise_view = noise_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let float = wgpu::TextureSampleType::Float { filterable: false };
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("ssao_bind_group_layout"),
+            entries: &[
+                uniform_entry(0),
+                uniform_entry(1),
+                texture_entry(2, wgpu::TextureSampleType::Depth),
+                texture_entry(3, float),
+                texture_entry(4, float),
+            ],
+        });
+        let blur_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("blur_bind_group_layout"),
+            entries: &[texture_entry(0, float)],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("ssao.wgsl"));
+        let create_pipeline = |layout: &wgpu::BindGroupLayout, entry_point| {
+            let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[layout],
+                push_constant_ranges: &[],
+            });
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(AO_FORMAT.into())],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        let ssao_pipeline = create_pipeline(&layout, "fs_ssao");
+        let blur_pipeline = create_pipeline(&blur_layout, "fs_blur");
+
+        let targets = Targets::new(
+            device,
+            width,
+            height,
+            vec![
+                TargetDesc::new(AO_FORMAT, 1, "ao"),
+                TargetDesc::new(AO_FORMAT, 1, "ao blurred"),
+            ],
+        );
+        let (bindgroup, blur_bindgroup) = create_bind_groups(
+            device,
+            &layout,
+            &blur_layout,
+            camera_buffer,
+            &buffer,
+            depth,
+            normal,
+            &noise_view,
+            &targets,
+        );
+
+        let ssao = Ssao {
+            radius,
+            kernel,
+            buffer,
+            noise_view,
+            layout,
+            blur_layout,
+            ssao_pipeline,
+            blur_pipeline,
+            targets,
+            bindgroup,
+            blur_bindgroup,
+        };
+        ssao.update(queue);
+        ssao
+    }
+
+    /// follow the g-buffer after it was recreated at a new size
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        camera_buffer: &wgpu::Buffer,
+        depth: &Texture,
+        normal: &Texture,
+        width: u32,
+        height: u32,
+    ) {
+        self.targets.resize(device, width, height);
+        (self.bindgroup, self.blur_bindgroup) = create_bind_groups(
+            device,
+            &self.layout,
+            &self.blur_layout,
+            camera_buffer,
+            &self.buffer,
+            depth,
+            normal,
+            &self.noise_view,
+            &self.targets,
+        );
+    }
+
+    /// upload `radius`, once a frame before `render`
+    pub fn update(&self, queue: &wgpu::Queue) {
+        let uniform = SsaoUniform {
+            kernel: self.kernel,
+            radius: self.radius,
+            // keeps flat surfaces from shadowing themselves through depth precision
+            bias: 0.025,
+            power: 1.5,
+            _padding: 0.,
+        };
+        watchdog::write_buffer(queue, &self.buffer, 0, bytemuck::bytes_of(&uniform));
+    }
+
+    /// the blurred occlusion, 1 where nothing is in the way
+    pub fn output(&self) -> &Texture {
+        &self.targets[1]
+    }
+
+    pub fn render(&self, frame: &framework::Frame, encoder: &mut wgpu::CommandEncoder) {
+        let passes = [
+            (&self.ssao_pipeline, &self.bindgroup, &self.targets[0]),
+            (&self.blur_pipeline, &self.blur_bindgroup, &self.targets[1]),
+        ];
+        for (pipeline, bindgroup, target) in passes {
+            let frame = framework::Frame {
+                view: &target.view,
+                depth: None,
+                ..*frame
+            };
+            let mut rpass = frame.begin_render_pass(encoder, wgpu::Color::WHITE);
+            rpass.set_pipeline(pipeline);
+            rpass.set_bind_group(0, bindgroup, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_bind_groups(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    blur_layout: &wgpu::BindGroupLayout,
+    camera_buffer: &wgpu::Buffer,
+    buffer: &wgpu::Buffer,
+    depth: &Texture,
+    normal: &Texture,
+    noise_view: &wgpu::TextureView,
+    targets: &Targets,
+) -> (wgpu::BindGroup, wgpu::BindGroup) {
+    let bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("ssao_bind_group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: buffer.as_entire_binding(),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::TextureView(&depth.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: wgpu::BindingResource::TextureView(&normal.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: wgpu::BindingResource::TextureView(noise_view),
+            },
+        ],
+    });
+    let blur_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("blur_bind_group"),
+        layout: blur_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(&targets[0].view),
+        }],
+    });
+    (bindgroup, blur_bindgroup)
+}
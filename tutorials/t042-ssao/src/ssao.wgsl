@@ -0,0 +1,125 @@
+// screen space ambient occlusion, two fullscreen passes:
+// - fs_ssao, how much of the hemisphere above each pixel is buried in the depth buffer
+// - fs_blur, a 4x4 box blur that hides the repeating pattern the 4x4 noise leaves
+
+struct FragInput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return fragInput;
+}
+
+// matches `CameraUniform` in lib.rs
+struct Camera {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    proj: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+};
+
+// matches `SsaoUniform` in ssao.rs
+struct Ssao {
+    // points in the hemisphere around +z, more of them close to the center
+    kernel: array<vec4<f32>, 32>,
+    radius: f32,
+    bias: f32,
+    power: f32,
+};
+
+// matches `KERNEL_SIZE` and `NOISE_SIZE` in ssao.rs
+let KERNEL_SIZE: u32 = 32u;
+let NOISE_SIZE: i32 = 4;
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+@group(0) @binding(1)
+var<uniform> ssao: Ssao;
+@group(0) @binding(2)
+var t_depth: texture_depth_2d;
+// view space, from the g-buffer pass
+@group(0) @binding(3)
+var t_normal: texture_2d<f32>;
+// random directions in the xy plane, 0..1 standing for -1..1, tiled over the screen
+@group(0) @binding(4)
+var t_noise: texture_2d<f32>;
+
+// back from depth to where the point is in view space
+fn view_position(uv: vec2<f32>, depth: f32) -> vec3<f32> {
+    let ndc = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let position = camera.inv_proj * ndc;
+    return position.xyz / position.w;
+}
+
+@fragment
+fn fs_ssao(input: FragInput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(t_depth));
+    let coord = vec2<i32>(input.clip_position.xy);
+    let depth = textureLoad(t_depth, coord, 0);
+    // the sky is never occluded
+    if (depth >= 1.0) {
+        return vec4<f32>(1.0);
+    }
+
+    let position = view_position(input.clip_position.xy / size, depth);
+    let normal = normalize(textureLoad(t_normal, coord, 0).xyz);
+
+    // a basis around the normal, turned by the noise so neighbouring pixels sample different
+    // directions, 16 rotations of the kernel instead of one
+    let random = textureLoad(t_noise, coord % vec2<i32>(NOISE_SIZE), 0).xyz * 2.0 - 1.0;
+    let tangent = normalize(random - normal * dot(random, normal));
+    let bitangent = cross(normal, tangent);
+    let tbn = mat3x3<f32>(tangent, bitangent, normal);
+
+    var occlusion = 0.0;
+    for (var i = 0u; i < KERNEL_SIZE; i = i + 1u) {
+        let sample_position = position + tbn * ssao.kernel[i].xyz * ssao.radius;
+
+        // where that point lands on screen, and what the depth buffer has there
+        let clip = camera.proj * vec4<f32>(sample_position, 1.0);
+        let ndc = clip.xy / clip.w;
+        let sample_uv = vec2<f32>(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5);
+        if (any(sample_uv < vec2<f32>(0.0)) || any(sample_uv >= vec2<f32>(1.0))) {
+            continue;
+        }
+        let scene_depth = textureLoad(t_depth, vec2<i32>(sample_uv * size), 0);
+        let scene_z = view_position(sample_uv, scene_depth).z;
+
+        // the camera looks down -z, a surface in front of the sample point buries it. ones
+        // much further in front than the radius are something else entirely, a wall in
+        // front of the pixel rather than a crease around it, and fade out
+        let in_range = smoothstep(0.0, 1.0, ssao.radius / abs(position.z - scene_z));
+        if (scene_z >= sample_position.z + ssao.bias) {
+            occlusion = occlusion + in_range;
+        }
+    }
+
+    let ao = pow(1.0 - occlusion / f32(KERNEL_SIZE), ssao.power);
+    return vec4<f32>(ao, ao, ao, 1.0);
+}
+
+@group(0) @binding(0)
+var t_ao: texture_2d<f32>;
+
+@fragment
+fn fs_blur(input: FragInput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_ao));
+    let coord = vec2<i32>(input.clip_position.xy);
+
+    // exactly one noise tile, so every rotation counts once
+    var sum = 0.0;
+    for (var y = -2; y < 2; y = y + 1) {
+        for (var x = -2; x < 2; x = x + 1) {
+            let texel = clamp(coord + vec2<i32>(x, y), vec2<i32>(0), size - 1);
+            sum = sum + textureLoad(t_ao, texel, 0).r;
+        }
+    }
+    let ao = sum / 16.0;
+    return vec4<f32>(ao, ao, ao, 1.0);
+}
@@ -0,0 +1,663 @@
+// deferred shading. forward rendering lights every fragment of every object as it's drawn,
+// so overdraw pays for all the lights again and the cost grows with objects times lights.
+// here the scene is drawn once into a g-buffer, color, normal and position in three targets
+// written by the same pass, and the lights are added up afterwards in one fullscreen pass
+// that reads those targets, so each pixel on screen is lit exactly once.
+//
+// the price is memory and bandwidth for the targets, and everything has to be lit the same
+// way: the lighting pass only knows what the g-buffer holds.
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    pass_graph::PassGraph,
+    screenshot::DumpTarget,
+    targets::{TargetDesc, Targets},
+    texture::{DepthTexture, Texture},
+    watchdog,
+};
+use glam::Vec3;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "deferred, 32 lights, lit";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+
+// matches the size of `lights` in shader.wgsl
+const MAX_LIGHTS: usize = 64;
+const START_LIGHTS: usize = 32;
+const LIGHT_STEP: usize = 8;
+
+// GRID_SIZE * GRID_SIZE pillars
+const GRID_SIZE: u32 = 5;
+const GRID_SPACING: f32 = 4.;
+
+// in the order of the g-buffer pass's targets and `GBufferOutput` in shader.wgsl
+const ALBEDO_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+const NORMAL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+// world positions, 16 bit floats are still good to a few millimeters this close to the origin
+const POSITION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// matches `view` in shader.wgsl
+const VIEWS: [&str; 4] = ["lit", "albedo", "normal", "position"];
+
+fn hash(a: u32, b: u32) -> f32 {
+    let mut h = a.wrapping_mul(0x27d4_eb2d) ^ b.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    (h & 0xffff) as f32 / 65535.
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// matches `Camera` in shader.wgsl, the position is for the highlights
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `Light` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LightUniform {
+    // xyz, and the reach in w
+    position: [f32; 4],
+    color: [f32; 4],
+}
+
+// matches `Lights` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LightsUniform {
+    count: u32,
+    view: u32,
+    _padding: [u32; 2],
+    lights: [LightUniform; MAX_LIGHTS],
+}
+
+// a light circling the middle of the scene at its own height and pace
+struct Light {
+    orbit: f32,
+    height: f32,
+    speed: f32,
+    phase: f32,
+    reach: f32,
+    color: [f32; 4],
+}
+
+impl Light {
+    fn uniform(&self, time: f32) -> LightUniform {
+        let angle = self.phase + time * self.speed;
+        LightUniform {
+            position: [
+                angle.cos() * self.orbit,
+                self.height,
+                angle.sin() * self.orbit,
+                self.reach,
+            ],
+            color: self.color,
+        }
+    }
+}
+
+fn create_lights() -> Vec<Light> {
+    (0..MAX_LIGHTS as u32)
+        .map(|i| {
+            // a saturated color from a random hue
+            let hue = hash(i, 1) * std::f32::consts::TAU;
+            let color = [0., 1., 2.].map(|offset: f32| {
+                let channel = 0.5 + 0.5 * (hue - offset * std::f32::consts::TAU / 3.).cos();
+                channel * 1.5
+            });
+            // half of them one way round, half the other
+            let direction = if i % 2 == 0 { 1. } else { -1. };
+            Light {
+                orbit: 1. + hash(i, 2) * 10.,
+                height: 0.3 + hash(i, 3) * 1.5,
+                speed: direction * (0.2 + hash(i, 4) * 0.6),
+                phase: hash(i, 5) * std::f32::consts::TAU,
+                reach: 2.5 + hash(i, 6) * 2.,
+                color: [color[0], color[1], color[2], 1.],
+            }
+        })
+        .collect()
+}
+
+// a box with its own 4 vertices per face for flat normals, wound counter clockwise from outside
+fn push_box(
+    verticrs: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    half: Vec3,
+    color: [f32; 3],
+) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+    for (normal, u, v) in faces {
+        let base = verticrs.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = center + (normal + u * su + v * sv) * half;
+            verticrs.push(Vertex {
+                pos: pos.to_array(),
+                normal: normal.to_array(),
+                color,
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+}
+
+// a pale floor with a grid of pillars for the lights to weave between
+fn create_scene() -> (Vec<Vertex>, Vec<u16>) {
+    let mut verticrs = Vec::new();
+    let mut indices = Vec::new();
+    let half = GRID_SIZE as f32 * GRID_SPACING * 0.5;
+
+    push_box(
+        &mut verticrs,
+        &mut indices,
+        Vec3::new(0., -0.25, 0.),
+        Vec3::new(half + 2., 0.25, half + 2.),
+        [0.7, 0.7, 0.7],
+    );
+    for i in 0..GRID_SIZE * GRID_SIZE {
+        let (x, z) = ((i % GRID_SIZE) as f32, (i / GRID_SIZE) as f32);
+        let height = 1. + 2. * hash(i, 7);
+        push_box(
+            &mut verticrs,
+            &mut indices,
+            Vec3::new(
+                (x + 0.5) * GRID_SPACING - half,
+                height * 0.5,
+                (z + 0.5) * GRID_SPACING - half,
+            ),
+            Vec3::new(0.4, height * 0.5, 0.4),
+            [0.9, 0.88, 0.85],
+        );
+    }
+    (verticrs, indices)
+}
+
+fn create_gbuffer(device: &wgpu::Device, width: u32, height: u32) -> Targets {
+    Targets::new(
+        device,
+        width,
+        height,
+        vec![
+            TargetDesc::new(ALBEDO_FORMAT, 1, "gbuffer albedo"),
+            TargetDesc::new(NORMAL_FORMAT, 1, "gbuffer normal"),
+            TargetDesc::new(POSITION_FORMAT, 1, "gbuffer position"),
+        ],
+    )
+}
+
+fn create_lighting_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    camera_buffer: &wgpu::Buffer,
+    lights_buffer: &wgpu::Buffer,
+    gbuffer: &Targets,
+) -> wgpu::BindGroup {
+    let mut entries = vec![
+        wgpu::BindGroupEntry {
+            binding: 0,
+            resource: camera_buffer.as_entire_binding(),
+        },
+        wgpu::BindGroupEntry {
+            binding: 1,
+            resource: lights_buffer.as_entire_binding(),
+        },
+    ];
+    // the targets follow at 2, 3 and 4
+    entries.extend(
+        gbuffer
+            .iter()
+            .enumerate()
+            .map(|(i, target)| wgpu::BindGroupEntry {
+                binding: 2 + i as u32,
+                resource: wgpu::BindingResource::TextureView(&target.view),
+            }),
+    );
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("lighting bind group"),
+        layout,
+        entries: &entries,
+    })
+}
+
+pub struct Deferred {
+    gbuffer_pipeline: wgpu::RenderPipeline,
+    lighting_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    gbuffer: Targets,
+    depth: Texture,
+    lights: Vec<Light>,
+    light_count: usize,
+    view: usize,
+    lights_buffer: wgpu::Buffer,
+    lighting_layout: wgpu::BindGroupLayout,
+    lighting_bindgroup: wgpu::BindGroup,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    paused: bool,
+    time: f32,
+}
+
+impl Deferred {
+    fn title(&self) -> String {
+        format!(
+            "deferred, {} lights, {}",
+            self.light_count, VIEWS[self.view]
+        )
+    }
+}
+
+impl framework::App for Deferred {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.4,
+            -0.7,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 22., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[uniform_entry(0)],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // scene
+        let (verticrs, indices) = create_scene();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // g-buffer
+        let gbuffer = create_gbuffer(device, config.width, config.height);
+        let depth = Texture::create_depth(device, config.width, config.height, "gbuffer depth");
+
+        // lights, rewritten every frame as they move
+        let lights = create_lights();
+        let lights_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Lights Buffer"),
+            size: std::mem::size_of::<LightsUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // the lighting pass reads the targets with textureLoad, one texel per pixel
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let lighting_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("lighting bindgroup layout"),
+            entries: &[
+                uniform_entry(0),
+                uniform_entry(1),
+                texture_entry(2),
+                texture_entry(3),
+                texture_entry(4),
+            ],
+        });
+        let lighting_bindgroup = create_lighting_bind_group(
+            device,
+            &lighting_layout,
+            &camera_buffer,
+            &lights_buffer,
+            &gbuffer,
+        );
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        // one pass, three color targets. the fragment shader's outputs go to them in order
+        let gbuffer_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+        let gbuffer_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("gbuffer"),
+            layout: Some(&gbuffer_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_gbuffer",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_gbuffer",
+                targets: &[
+                    Some(ALBEDO_FORMAT.into()),
+                    Some(NORMAL_FORMAT.into()),
+                    Some(POSITION_FORMAT.into()),
+                ],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let lighting_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&lighting_layout],
+                push_constant_ranges: &[],
+            });
+        let lighting_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("lighting"),
+            layout: Some(&lighting_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_fullscreen",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_lighting",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        info!("press up / down to change the number of lights, g to step through the g-buffer targets, p to pause the lights");
+
+        Deferred {
+            gbuffer_pipeline,
+            lighting_pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            gbuffer,
+            depth,
+            lights,
+            light_count: START_LIGHTS,
+            view: 0,
+            lights_buffer,
+            lighting_layout,
+            lighting_bindgroup,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            paused: false,
+            time: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+        if self.gbuffer.resize(device, config.width, config.height) {
+            self.depth =
+                Texture::create_depth(device, config.width, config.height, "gbuffer depth");
+            self.lighting_bindgroup = create_lighting_bind_group(
+                device,
+                &self.lighting_layout,
+                &self.camera_buffer,
+                &self.lights_buffer,
+                &self.gbuffer,
+            );
+        }
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::Up => {
+                    self.light_count = (self.light_count + LIGHT_STEP).min(MAX_LIGHTS)
+                }
+                VirtualKeyCode::Down => {
+                    self.light_count = self.light_count.saturating_sub(LIGHT_STEP)
+                }
+                VirtualKeyCode::G => self.view = (self.view + 1) % VIEWS.len(),
+                VirtualKeyCode::P => self.paused = !self.paused,
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.paused {
+            self.time += frame.delta;
+        }
+
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        let mut uniform = LightsUniform {
+            count: self.light_count as u32,
+            view: self.view as u32,
+            _padding: [0; 2],
+            lights: [LightUniform::zeroed(); MAX_LIGHTS],
+        };
+        for (light, slot) in self.lights.iter().zip(&mut uniform.lights) {
+            *slot = light.uniform(self.time);
+        }
+        watchdog::write_buffer(queue, &self.lights_buffer, 0, bytemuck::bytes_of(&uniform));
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            // albedo clears to transparent, alpha 0 tells the lighting pass nothing is there
+            let color_attachments: Vec<_> = self
+                .gbuffer
+                .iter()
+                .map(|target| {
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: &target.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                            store: true,
+                        },
+                    })
+                })
+                .collect();
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("gbuffer"),
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        // nothing reads it later, but a frame dump shows it
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            rpass.set_pipeline(&self.gbuffer_pipeline);
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+            rpass.set_pipeline(&self.lighting_pipeline);
+            rpass.set_bind_group(0, &self.lighting_bindgroup, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+    fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        let mut targets = self.gbuffer.dump_targets();
+        let (width, height) = self.gbuffer.size(0);
+        targets.push(DumpTarget::new(
+            "gbuffer depth",
+            &self.depth.texture,
+            width,
+            height,
+            DepthTexture::FORMAT,
+        ));
+        targets
+    }
+    fn pass_graph(&self) -> Option<PassGraph> {
+        Some(
+            PassGraph::new()
+                .pass(
+                    "gbuffer",
+                    &[],
+                    &[
+                        "gbuffer albedo",
+                        "gbuffer normal",
+                        "gbuffer position",
+                        "gbuffer depth",
+                    ],
+                )
+                .pass(
+                    "lighting",
+                    &[
+                        "gbuffer albedo",
+                        "gbuffer normal",
+                        "gbuffer position",
+                        "lights",
+                    ],
+                    &["window"],
+                ),
+        )
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t43_deferred::Deferred>(t43_deferred::TITLE);
+}
@@ -0,0 +1,143 @@
+// deferred shading, two passes:
+// - vs_gbuffer / fs_gbuffer draw the scene once, writing what lighting needs per pixel into
+//   three targets at the same time
+// - vs_fullscreen / fs_lighting read them back and add up every light, once per pixel on
+//   screen no matter how much geometry was drawn
+
+// matches `CameraUniform` in lib.rs
+struct Camera {
+    view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+// g-buffer
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct GBufferInput {
+    @location(0) world_position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// one member per color attachment, in the order of the pipeline's targets
+struct GBufferOutput {
+    @location(0) albedo: vec4<f32>,
+    @location(1) normal: vec4<f32>,
+    @location(2) position: vec4<f32>,
+};
+
+@vertex
+fn vs_gbuffer(input: VertexInput) -> GBufferInput {
+    var gbufferInput : GBufferInput;
+    gbufferInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    gbufferInput.world_position = input.pos;
+    gbufferInput.normal = input.normal;
+    gbufferInput.color = input.color;
+    return gbufferInput;
+}
+
+@fragment
+fn fs_gbuffer(input: GBufferInput) -> GBufferOutput {
+    var output : GBufferOutput;
+    // alpha 1 marks geometry, the clear leaves 0 where there's nothing
+    output.albedo = vec4<f32>(input.color, 1.0);
+    output.normal = vec4<f32>(normalize(input.normal), 0.0);
+    output.position = vec4<f32>(input.world_position, 1.0);
+    return output;
+}
+
+// lighting
+
+struct FragInput {
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    return fragInput;
+}
+
+// matches `LightUniform` in lib.rs
+struct Light {
+    // xyz, and how far the light reaches in w
+    position: vec4<f32>,
+    color: vec4<f32>,
+};
+
+// matches `LightsUniform` in lib.rs
+struct Lights {
+    count: u32,
+    // 0 lit, 1 albedo, 2 normal, 3 position
+    view: u32,
+    lights: array<Light, 64>,
+};
+
+@group(0) @binding(1)
+var<uniform> lights: Lights;
+@group(0) @binding(2)
+var t_albedo: texture_2d<f32>;
+@group(0) @binding(3)
+var t_normal: texture_2d<f32>;
+@group(0) @binding(4)
+var t_position: texture_2d<f32>;
+
+let background = vec3<f32>(0.01, 0.01, 0.02);
+let ambient = 0.03;
+
+@fragment
+fn fs_lighting(input: FragInput) -> @location(0) vec4<f32> {
+    let coord = vec2<i32>(input.clip_position.xy);
+    let albedo = textureLoad(t_albedo, coord, 0);
+    if (albedo.a == 0.0) {
+        return vec4<f32>(background, 1.0);
+    }
+    let normal = normalize(textureLoad(t_normal, coord, 0).xyz);
+    let position = textureLoad(t_position, coord, 0).xyz;
+
+    // the targets themselves, to see what the first pass left behind
+    if (lights.view == 1u) {
+        return vec4<f32>(albedo.rgb, 1.0);
+    }
+    if (lights.view == 2u) {
+        return vec4<f32>(normal * 0.5 + 0.5, 1.0);
+    }
+    if (lights.view == 3u) {
+        return vec4<f32>(fract(position * 0.25), 1.0);
+    }
+
+    let view_dir = normalize(camera.position.xyz - position);
+    var color = albedo.rgb * ambient;
+    for (var i = 0u; i < lights.count; i = i + 1u) {
+        let light = lights.lights[i];
+        let to_light = light.position.xyz - position;
+        let distance = length(to_light);
+        if (distance >= light.position.w) {
+            continue;
+        }
+        let light_dir = to_light / distance;
+
+        // falls to exactly 0 at the light's reach, so lights past it can be skipped
+        let falloff = clamp(1.0 - pow(distance / light.position.w, 2.0), 0.0, 1.0);
+        let attenuation = falloff * falloff;
+
+        let diffuse = max(dot(normal, light_dir), 0.0);
+        let half_dir = normalize(view_dir + light_dir);
+        let specular = pow(max(dot(normal, half_dir), 0.0), 32.0) * 0.3;
+        color = color + light.color.rgb * (albedo.rgb * diffuse + specular) * attenuation;
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,454 @@
+// the compute side: a light list per screen tile, rebuilt every frame from the prepass depth
+// and the lights where they are now. the forward pass reads the lists from `tiles`.
+
+use framework::{autotune, texture::Texture};
+
+// matches `TILE_SIZE` in cull.wgsl and shader.wgsl, pixels per side of a tile
+pub const TILE_SIZE: u32 = 16;
+// matches `TILE_STRIDE` in cull.wgsl and shader.wgsl, a count and up to 127 light indices
+pub const TILE_STRIDE: u32 = 128;
+// what cull.wgsl is written with, a thread per pixel of a tile
+pub const WORKGROUP_SIZE: [u32; 3] = [TILE_SIZE, TILE_SIZE, 1];
+
+pub struct LightCulling {
+    pipeline: wgpu::ComputePipeline,
+    layout: wgpu::BindGroupLayout,
+    bindgroup: wgpu::BindGroup,
+    /// the lists, `TILE_STRIDE` u32s per tile, row by row
+    pub tiles: wgpu::Buffer,
+    pub tiles_x: u32,
+    pub tiles_y: u32,
+}
+
+impl LightCulling {
+    /// `camera`, `culling` and `lights` are the buffers the forward pass reads too, `depth`
+    /// the prepass depth of a `width` x `height` window
+    pub fn new(
+        device: &wgpu::Device,
+        buffers: [&wgpu::Buffer; 3],
+        depth: &Texture,
+        width: u32,
+        height: u32,
+    ) -> LightCulling {
+        Self::with_workgroup_size(device, buffers, depth, width, height, WORKGROUP_SIZE)
+    }
+
+    /// [`LightCulling::new`] with `workgroup_size` threads per tile instead of one per pixel,
+    /// within the device's limits
+    pub fn with_workgroup_size(
+        device: &wgpu::Device,
+        buffers: [&wgpu::Buffer; 3],
+        depth: &Texture,
+        width: u32,
+        height: u32,
+        workgroup_size: [u32; 3],
+    ) -> LightCulling {
+        assert!(
+            autotune::fits(&device.limits(), workgroup_size),
+            "Fail to use workgroup size {:?}, the device doesn't run it",
+            workgroup_size
+        );
+        // the uniforms, the lights only read, the lists written, and the depth
+        let entries: Vec<wgpu::BindGroupLayoutEntry> = (0..5)
+            .map(|binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: wgpu::ShaderStages::COMPUTE,
+                ty: match binding {
+                    0 | 1 => wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    2 | 3 => wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage {
+                            read_only: binding == 2,
+                        },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    _ => wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Depth,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                },
+                count: None,
+            })
+            .collect();
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("culling bindgroup layout"),
+            entries: &entries,
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("cull.wgsl"),
+            source: wgpu::ShaderSource::Wgsl(shader_source(workgroup_size).into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("culling pipeline layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("cs_main"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        let (tiles_x, tiles_y, tiles) = create_tiles(device, width, height);
+        let bindgroup = create_bind_group(device, &layout, buffers, &tiles, depth);
+
+        LightCulling {
+            pipeline,
+            layout,
+            bindgroup,
+            tiles,
+            tiles_x,
+            tiles_y,
+        }
+    }
+
+    /// follow the window, `depth` has been recreated at the new size. the forward pass has to
+    /// pick up the new `tiles` buffer
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        buffers: [&wgpu::Buffer; 3],
+        depth: &Texture,
+        width: u32,
+        height: u32,
+    ) {
+        (self.tiles_x, self.tiles_y, self.tiles) = create_tiles(device, width, height);
+        self.bindgroup = create_bind_group(device, &self.layout, buffers, &self.tiles, depth);
+    }
+
+    pub fn run(&self, encoder: &mut wgpu::CommandEncoder) {
+        let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("light culling pass"),
+        });
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.bindgroup, &[]);
+        cpass.dispatch_workgroups(self.tiles_x, self.tiles_y, 1);
+    }
+}
+
+// cull.wgsl with `workgroup_size` threads per tile, in `@workgroup_size` and the constants the
+// loops step by
+fn shader_source(workgroup_size: [u32; 3]) -> String {
+    let [x, y, _] = workgroup_size;
+    let [written_x, written_y, _] = WORKGROUP_SIZE;
+    autotune::with_workgroup_size(include_str!("cull.wgsl"), [x, y, 1])
+        .replace(
+            &format!("let WORKGROUP_X: u32 = {}u;", written_x),
+            &format!("let WORKGROUP_X: u32 = {}u;", x),
+        )
+        .replace(
+            &format!("let WORKGROUP_Y: u32 = {}u;", written_y),
+            &format!("let WORKGROUP_Y: u32 = {}u;", y),
+        )
+}
+
+// enough tiles to cover the window, the last row and column hang over its edges
+fn create_tiles(device: &wgpu::Device, width: u32, height: u32) -> (u32, u32, wgpu::Buffer) {
+    let tiles_x = width.div_ceil(TILE_SIZE);
+    let tiles_y = height.div_ceil(TILE_SIZE);
+    let tiles = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Tiles Buffer"),
+        size: (tiles_x * tiles_y * TILE_STRIDE * 4) as u64,
+        // COPY_SRC for the tests
+        usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+        mapped_at_creation: false,
+    });
+    (tiles_x, tiles_y, tiles)
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffers: [&wgpu::Buffer; 3],
+    tiles: &wgpu::Buffer,
+    depth: &Texture,
+) -> wgpu::BindGroup {
+    let mut entries: Vec<wgpu::BindGroupEntry> = buffers
+        .iter()
+        .chain([&tiles])
+        .enumerate()
+        .map(|(binding, buffer)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: buffer.as_entire_binding(),
+        })
+        .collect();
+    entries.push(wgpu::BindGroupEntry {
+        binding: 4,
+        resource: wgpu::BindingResource::TextureView(&depth.view),
+    });
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("culling bind group"),
+        layout,
+        entries: &entries,
+    })
+}
+
+// the culling pass against the same box test on the cpu, on a device without a window. without
+// an adapter, e.g. on ci without a gpu, or on gl, it passes
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{hash, CameraUniform, CullingUniform, LightUniform};
+    use framework::testing::{headless, read_back};
+    use glam::{Mat4, Vec3, Vec4, Vec4Swizzles};
+    use tracing::warn;
+    use wgpu::util::DeviceExt;
+
+    // a window the tiles don't divide, its top right corner sky
+    const WIDTH: u32 = 100;
+    const HEIGHT: u32 = 70;
+    const LIGHTS: u32 = 100;
+
+    // in 1/128ths so the gpu writes exactly what the cpu reads
+    fn depth(x: u32, y: u32) -> f32 {
+        if x >= 80 && y < 16 {
+            1.
+        } else {
+            ((x * 7 + y * 13) % 97 + 24) as f32 / 128.
+        }
+    }
+
+    // writes `depth` into the depth target, the prepass of the test
+    const DEPTH_SHADER: &str = "
+        @vertex
+        fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+            let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+            return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+        }
+
+        @fragment
+        fn fs_main(@builtin(position) position: vec4<f32>) -> @builtin(frag_depth) f32 {
+            let x = u32(position.x);
+            let y = u32(position.y);
+            if (x >= 80u && y < 16u) {
+                return 1.0;
+            }
+            return f32((x * 7u + y * 13u) % 97u + 24u) / 128.0;
+        }
+    ";
+
+    fn draw_depth(device: &wgpu::Device, queue: &wgpu::Queue, depth: &Texture) {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("depth"),
+            source: wgpu::ShaderSource::Wgsl(DEPTH_SHADER.into()),
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("depth"),
+            layout: None,
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            rpass.set_pipeline(&pipeline);
+            rpass.draw(0..3, 0..1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    // the lights whose sphere reaches the box cull.wgsl makes for `tile`, and the ones it may
+    // go either way for, they touch the box within rounding
+    fn cpu_lights(
+        view: Mat4,
+        inv_proj: Mat4,
+        lights: &[LightUniform],
+        (tile_x, tile_y): (u32, u32),
+    ) -> (Vec<u32>, Vec<u32>) {
+        let pixels = (0..TILE_SIZE).flat_map(|y| (0..TILE_SIZE).map(move |x| (x, y)));
+        let depths: Vec<f32> = pixels
+            .map(|(x, y)| (tile_x * TILE_SIZE + x, tile_y * TILE_SIZE + y))
+            .filter(|&(x, y)| x < WIDTH && y < HEIGHT)
+            .map(|(x, y)| depth(x, y))
+            .filter(|&depth| depth < 1.)
+            .collect();
+        if depths.is_empty() {
+            return (Vec::new(), Vec::new());
+        }
+        let near = depths.iter().copied().fold(1., f32::min);
+        let far = depths.iter().copied().fold(0., f32::max);
+
+        let size = Vec3::new(WIDTH as f32, HEIGHT as f32, 1.);
+        let first = Vec3::new((tile_x * TILE_SIZE) as f32, (tile_y * TILE_SIZE) as f32, 1.) / size;
+        let last = Vec3::new(
+            ((tile_x + 1) * TILE_SIZE) as f32,
+            ((tile_y + 1) * TILE_SIZE) as f32,
+            1.,
+        ) / size;
+        let (mut box_min, mut box_max) = (Vec3::splat(1e30), Vec3::splat(-1e30));
+        for corner in 0..8 {
+            let u = if corner & 1 != 0 { last.x } else { first.x };
+            let v = if corner & 2 != 0 { last.y } else { first.y };
+            let depth = if corner & 4 != 0 { far } else { near };
+            let p = inv_proj * Vec4::new(u * 2. - 1., 1. - v * 2., depth, 1.);
+            let p = p.xyz() / p.w;
+            box_min = box_min.min(p);
+            box_max = box_max.max(p);
+        }
+
+        let (mut inside, mut either) = (Vec::new(), Vec::new());
+        for (i, light) in lights.iter().enumerate() {
+            let position = Vec4::from(light.position);
+            let center = (view * position.xyz().extend(1.)).xyz();
+            let offset = center - center.clamp(box_min, box_max);
+            let (distance, reach) = (offset.length_squared(), position.w * position.w);
+            if (distance - reach).abs() < 1e-5 + 1e-3 * reach {
+                either.push(i as u32);
+            } else if distance < reach {
+                inside.push(i as u32);
+            }
+        }
+        (inside, either)
+    }
+
+    // every tile's list against the cpu, with a thread per pixel, fewer threads than pixels,
+    // and a workgroup wider than the tile
+    #[test]
+    fn tiles_match_the_cpu() {
+        let Some((adapter, device, queue)) = headless() else {
+            return;
+        };
+        // naga can't write glsl that loads from a depth texture, the chapter doesn't run on gl
+        if adapter.get_info().backend == wgpu::Backend::Gl {
+            warn!("no light culling on gl, skipping the test");
+            return;
+        }
+
+        let view = Mat4::look_at_rh(Vec3::new(1., 3., 6.), Vec3::ZERO, Vec3::Y);
+        let projection = Mat4::perspective_rh(1., WIDTH as f32 / HEIGHT as f32, 0.1, 100.);
+        let inv_proj = projection.inverse();
+        let camera = CameraUniform {
+            view_proj: (projection * view).to_cols_array_2d(),
+            view: view.to_cols_array_2d(),
+            inv_proj: inv_proj.to_cols_array_2d(),
+            position: [1., 3., 6., 1.],
+        };
+        // spread over what the camera sees of the depths above, up to a few units away
+        let lights: Vec<LightUniform> = (0..LIGHTS)
+            .map(|i| {
+                let in_view = Vec3::new(
+                    (hash(i, 1) - 0.5) * 1.5,
+                    (hash(i, 2) - 0.5) * 1.,
+                    -0.1 - hash(i, 3) * 0.8,
+                );
+                let position = view.inverse().transform_point3(in_view);
+                LightUniform {
+                    position: [position.x, position.y, position.z, 0.02 + hash(i, 4) * 0.2],
+                    color: [1.; 4],
+                }
+            })
+            .collect();
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&camera),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let culling_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Culling Buffer"),
+            contents: bytemuck::bytes_of(&CullingUniform {
+                light_count: LIGHTS,
+                tiles_x: WIDTH.div_ceil(TILE_SIZE),
+                mode: 0,
+                _padding: 0,
+            }),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let light_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Light Buffer"),
+            contents: bytemuck::cast_slice(&lights),
+            usage: wgpu::BufferUsages::STORAGE,
+        });
+        let depth = Texture::create_depth(&device, WIDTH, HEIGHT, "depth");
+        draw_depth(&device, &queue, &depth);
+
+        let buffers = [&camera_buffer, &culling_buffer, &light_buffer];
+        let mut lit_tiles = 0;
+        for workgroup_size in [WORKGROUP_SIZE, [8, 8, 1], [4, 16, 1], [32, 4, 1]] {
+            let culling = LightCulling::with_workgroup_size(
+                &device,
+                buffers,
+                &depth,
+                WIDTH,
+                HEIGHT,
+                workgroup_size,
+            );
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            culling.run(&mut encoder);
+            queue.submit(Some(encoder.finish()));
+
+            let tiles: Vec<u32> = read_back(&device, &queue, &culling.tiles);
+            for tile_y in 0..culling.tiles_y {
+                for tile_x in 0..culling.tiles_x {
+                    let list =
+                        &tiles[((tile_y * culling.tiles_x + tile_x) * TILE_STRIDE) as usize..];
+                    let mut gpu = list[1..=list[0] as usize].to_vec();
+                    gpu.sort_unstable();
+                    let (inside, either) = cpu_lights(view, inv_proj, &lights, (tile_x, tile_y));
+                    let gpu_only: Vec<u32> = gpu
+                        .iter()
+                        .copied()
+                        .filter(|i| !inside.contains(i) && !either.contains(i))
+                        .collect();
+                    let missing: Vec<u32> = inside
+                        .iter()
+                        .copied()
+                        .filter(|i| !gpu.contains(i))
+                        .collect();
+                    assert!(
+                        gpu_only.is_empty() && missing.is_empty(),
+                        "{:?}: tile ({}, {}) has {:?} it shouldn't and lacks {:?}",
+                        workgroup_size,
+                        tile_x,
+                        tile_y,
+                        gpu_only,
+                        missing
+                    );
+                    if !inside.is_empty() {
+                        lit_tiles += 1;
+                    }
+                }
+            }
+        }
+        // the lights are where the test can see them, not all culled away
+        assert!(lit_tiles > 0);
+    }
+}
@@ -0,0 +1,131 @@
+// light culling, one workgroup per TILE_SIZE x TILE_SIZE tile of the screen. the threads
+// first find the nearest and farthest depth the prepass left in the tile, which together with
+// the tile's corners bound a box in view space, then split the lights between them and keep
+// the ones whose sphere touches that box.
+
+// matches `CameraUniform` in lib.rs
+struct Camera {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+
+// matches `CullingUniform` in lib.rs
+struct Culling {
+    light_count: u32,
+    tiles_x: u32,
+    mode: u32,
+};
+
+// matches `LightUniform` in lib.rs
+struct Light {
+    // xyz, and how far the light reaches in w
+    position: vec4<f32>,
+    color: vec4<f32>,
+};
+
+// matches `TILE_SIZE` and `TILE_STRIDE` in lib.rs. a tile's list is its light count followed
+// by up to TILE_STRIDE - 1 light indices
+let TILE_SIZE: u32 = 16u;
+let TILE_STRIDE: u32 = 128u;
+
+// threads per side of a workgroup, as in `@workgroup_size`. these are the defaults,
+// `LightCulling::with_workgroup_size` rewrites both. each thread reads the pixels of the tile
+// a workgroup apart, one each with the defaults
+let WORKGROUP_X: u32 = 16u;
+let WORKGROUP_Y: u32 = 16u;
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+@group(0) @binding(1)
+var<uniform> culling: Culling;
+@group(0) @binding(2)
+var<storage, read> lights: array<Light>;
+@group(0) @binding(3)
+var<storage, read_write> tiles: array<u32>;
+@group(0) @binding(4)
+var t_depth: texture_depth_2d;
+
+// bits of positive floats, those sort the same as the floats do
+var<workgroup> min_depth: atomic<u32>;
+var<workgroup> max_depth: atomic<u32>;
+var<workgroup> tile_count: atomic<u32>;
+
+// back from the screen to where the point is in view space
+fn view_position(ndc: vec2<f32>, depth: f32) -> vec3<f32> {
+    let position = camera.inv_proj * vec4<f32>(ndc, depth, 1.0);
+    return position.xyz / position.w;
+}
+
+@compute
+@workgroup_size(16, 16)
+fn cs_main(
+    @builtin(workgroup_id) tile: vec3<u32>,
+    @builtin(local_invocation_id) local: vec3<u32>,
+    @builtin(local_invocation_index) local_index: u32,
+) {
+    if (local_index == 0u) {
+        atomicStore(&min_depth, bitcast<u32>(1.0));
+        atomicStore(&max_depth, 0u);
+        atomicStore(&tile_count, 0u);
+    }
+    workgroupBarrier();
+
+    let size = vec2<u32>(textureDimensions(t_depth));
+    for (var y = local.y; y < TILE_SIZE; y = y + WORKGROUP_Y) {
+        for (var x = local.x; x < TILE_SIZE; x = x + WORKGROUP_X) {
+            let pixel = tile.xy * TILE_SIZE + vec2<u32>(x, y);
+            if (all(pixel < size)) {
+                let depth = textureLoad(t_depth, vec2<i32>(pixel), 0);
+                // the cleared far plane is sky, nothing there to light
+                if (depth < 1.0) {
+                    atomicMin(&min_depth, bitcast<u32>(depth));
+                    atomicMax(&max_depth, bitcast<u32>(depth));
+                }
+            }
+        }
+    }
+    workgroupBarrier();
+
+    let near = bitcast<f32>(atomicLoad(&min_depth));
+    let far = bitcast<f32>(atomicLoad(&max_depth));
+    // still the starting values when the whole tile is sky
+    if (near <= far) {
+        // the tile's corners at both depths, in view space, and the box around them
+        let first = vec2<f32>(tile.xy * TILE_SIZE) / vec2<f32>(size);
+        let last = vec2<f32>((tile.xy + 1u) * TILE_SIZE) / vec2<f32>(size);
+        var box_min = vec3<f32>(1e30);
+        var box_max = vec3<f32>(-1e30);
+        for (var corner = 0u; corner < 8u; corner = corner + 1u) {
+            let uv = vec2<f32>(
+                select(first.x, last.x, (corner & 1u) != 0u),
+                select(first.y, last.y, (corner & 2u) != 0u),
+            );
+            let depth = select(near, far, (corner & 4u) != 0u);
+            let p = view_position(vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0), depth);
+            box_min = min(box_min, p);
+            box_max = max(box_max, p);
+        }
+
+        // every thread takes every (WORKGROUP_X * WORKGROUP_Y)th light
+        for (var i = local_index; i < culling.light_count; i = i + WORKGROUP_X * WORKGROUP_Y) {
+            let light = lights[i];
+            let center = (camera.view * vec4<f32>(light.position.xyz, 1.0)).xyz;
+            let offset = center - clamp(center, box_min, box_max);
+            if (dot(offset, offset) < light.position.w * light.position.w) {
+                let slot = atomicAdd(&tile_count, 1u);
+                // a full list drops the rest, those lights go missing in this tile
+                if (slot < TILE_STRIDE - 1u) {
+                    tiles[(tile.y * culling.tiles_x + tile.x) * TILE_STRIDE + 1u + slot] = i;
+                }
+            }
+        }
+    }
+    workgroupBarrier();
+
+    if (local_index == 0u) {
+        let count = min(atomicLoad(&tile_count), TILE_STRIDE - 1u);
+        tiles[(tile.y * culling.tiles_x + tile.x) * TILE_STRIDE] = count;
+    }
+}
@@ -0,0 +1,599 @@
+// forward+, or tiled forward shading. plain forward shading makes every fragment loop over
+// every light, and with hundreds of small lights almost all of that work adds nothing. here
+// the screen is cut into 16x16 pixel tiles, and a compute pass works out which lights can
+// reach anything visible in each tile, so the fragments only loop over those.
+//
+// a frame is three passes:
+// - a depth prepass, only the depth of the scene
+// - light culling in compute (cull.rs), one workgroup per tile, a light list per tile into a
+//   storage buffer
+// - the forward pass, the scene again with the depth already there, each fragment reading
+//   its tile's list
+//
+// unlike deferred (t43) the scene is still lit as it's drawn, so materials can stay as
+// varied as forward shading allows and msaa keeps working.
+
+mod cull;
+
+use bytemuck::{Pod, Zeroable};
+use cull::{LightCulling, TILE_SIZE};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    pass_graph::PassGraph,
+    texture::{DepthTexture, Texture},
+    watchdog,
+};
+use glam::Vec3;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "forward+, 256 lights, culled";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+
+const MAX_LIGHTS: usize = 1024;
+const START_LIGHTS: usize = 256;
+const LIGHT_STEP: usize = 128;
+
+// GRID_SIZE * GRID_SIZE pillars
+const GRID_SIZE: u32 = 10;
+const GRID_SPACING: f32 = 5.;
+
+// matches `mode` in shader.wgsl
+const MODES: [&str; 3] = ["culled", "every light", "light counts"];
+
+fn hash(a: u32, b: u32) -> f32 {
+    let mut h = a.wrapping_mul(0x27d4_eb2d) ^ b.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    (h & 0xffff) as f32 / 65535.
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// matches `Camera` in shader.wgsl and cull.wgsl. culling happens in view space, starting from
+// screen positions and depths
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    view: [[f32; 4]; 4],
+    inv_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            view: camera.view().to_cols_array_2d(),
+            inv_proj: camera.projection().inverse().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `Culling` in shader.wgsl and cull.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CullingUniform {
+    light_count: u32,
+    tiles_x: u32,
+    mode: u32,
+    _padding: u32,
+}
+
+// matches `Light` in shader.wgsl and cull.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct LightUniform {
+    // xyz, and the reach in w
+    position: [f32; 4],
+    color: [f32; 4],
+}
+
+// a light circling the middle of the scene at its own height and pace
+struct Light {
+    orbit: f32,
+    height: f32,
+    speed: f32,
+    phase: f32,
+    reach: f32,
+    color: [f32; 4],
+}
+
+impl Light {
+    fn uniform(&self, time: f32) -> LightUniform {
+        let angle = self.phase + time * self.speed / self.orbit;
+        LightUniform {
+            position: [
+                angle.cos() * self.orbit,
+                self.height,
+                angle.sin() * self.orbit,
+                self.reach,
+            ],
+            color: self.color,
+        }
+    }
+}
+
+fn create_lights() -> Vec<Light> {
+    let extent = GRID_SIZE as f32 * GRID_SPACING * 0.5;
+    (0..MAX_LIGHTS as u32)
+        .map(|i| {
+            // a saturated color from a random hue
+            let hue = hash(i, 1) * std::f32::consts::TAU;
+            let color = [0., 1., 2.].map(|offset: f32| {
+                let channel = 0.5 + 0.5 * (hue - offset * std::f32::consts::TAU / 3.).cos();
+                channel * 1.5
+            });
+            // half of them one way round, half the other
+            let direction = if i % 2 == 0 { 1. } else { -1. };
+            Light {
+                // spread evenly over the floor's area rather than bunched in the middle
+                orbit: extent * hash(i, 2).sqrt() + 0.5,
+                height: 0.3 + hash(i, 3) * 1.5,
+                // in units per second along the circle
+                speed: direction * (1. + hash(i, 4) * 2.),
+                phase: hash(i, 5) * std::f32::consts::TAU,
+                reach: 1.5 + hash(i, 6) * 2.,
+                color: [color[0], color[1], color[2], 1.],
+            }
+        })
+        .collect()
+}
+
+// a box with its own 4 vertices per face for flat normals, wound counter clockwise from outside
+fn push_box(
+    verticrs: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    half: Vec3,
+    color: [f32; 3],
+) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+    for (normal, u, v) in faces {
+        let base = verticrs.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = center + (normal + u * su + v * sv) * half;
+            verticrs.push(Vertex {
+                pos: pos.to_array(),
+                normal: normal.to_array(),
+                color,
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+}
+
+// a pale floor with a grid of pillars for the lights to weave between
+fn create_scene() -> (Vec<Vertex>, Vec<u16>) {
+    let mut verticrs = Vec::new();
+    let mut indices = Vec::new();
+    let half = GRID_SIZE as f32 * GRID_SPACING * 0.5;
+
+    push_box(
+        &mut verticrs,
+        &mut indices,
+        Vec3::new(0., -0.25, 0.),
+        Vec3::new(half + 2., 0.25, half + 2.),
+        [0.7, 0.7, 0.7],
+    );
+    for i in 0..GRID_SIZE * GRID_SIZE {
+        let (x, z) = ((i % GRID_SIZE) as f32, (i / GRID_SIZE) as f32);
+        let height = 1. + 3. * hash(i, 7);
+        push_box(
+            &mut verticrs,
+            &mut indices,
+            Vec3::new(
+                (x + 0.5) * GRID_SPACING - half,
+                height * 0.5,
+                (z + 0.5) * GRID_SPACING - half,
+            ),
+            Vec3::new(0.5, height * 0.5, 0.5),
+            [0.9, 0.88, 0.85],
+        );
+    }
+    (verticrs, indices)
+}
+
+fn create_scene_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    buffers: [&wgpu::Buffer; 3],
+    tiles: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    let entries: Vec<wgpu::BindGroupEntry> = buffers
+        .iter()
+        .chain([&tiles])
+        .enumerate()
+        .map(|(binding, buffer)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource: buffer.as_entire_binding(),
+        })
+        .collect();
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("scene bind group"),
+        layout,
+        entries: &entries,
+    })
+}
+
+pub struct ForwardPlus {
+    prepass_pipeline: wgpu::RenderPipeline,
+    forward_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    // written by the prepass, read by culling, tested against by the forward pass
+    depth: Texture,
+    culling: LightCulling,
+    lights: Vec<Light>,
+    light_count: usize,
+    mode: usize,
+    lights_buffer: wgpu::Buffer,
+    culling_buffer: wgpu::Buffer,
+    scene_layout: wgpu::BindGroupLayout,
+    scene_bindgroup: wgpu::BindGroup,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    paused: bool,
+    time: f32,
+}
+
+impl ForwardPlus {
+    fn title(&self) -> String {
+        format!(
+            "forward+, {} lights, {}",
+            self.light_count, MODES[self.mode]
+        )
+    }
+}
+
+impl framework::App for ForwardPlus {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.4,
+            -0.7,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 40., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let culling_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Culling Buffer"),
+            size: std::mem::size_of::<CullingUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // rewritten every frame as they move
+        let lights = create_lights();
+        let lights_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Lights Buffer"),
+            size: (MAX_LIGHTS * std::mem::size_of::<LightUniform>()) as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // scene
+        let (verticrs, indices) = create_scene();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // the depth the culling reads, and the culling itself
+        let depth = Texture::create_depth(device, config.width, config.height, "prepass depth");
+        let buffers = [&camera_buffer, &culling_buffer, &lights_buffer];
+        let culling = LightCulling::new(device, buffers, &depth, config.width, config.height);
+
+        // both render passes share one bind group: the uniforms, the lights and the lists
+        let scene_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("scene bindgroup layout"),
+            entries: &[0, 1, 2, 3].map(|binding| wgpu::BindGroupLayoutEntry {
+                binding,
+                visibility: if binding == 0 {
+                    wgpu::ShaderStages::VERTEX_FRAGMENT
+                } else {
+                    wgpu::ShaderStages::FRAGMENT
+                },
+                ty: wgpu::BindingType::Buffer {
+                    ty: if binding < 2 {
+                        wgpu::BufferBindingType::Uniform
+                    } else {
+                        wgpu::BufferBindingType::Storage { read_only: true }
+                    },
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }),
+        });
+        let scene_bindgroup =
+            create_scene_bind_group(device, &scene_layout, buffers, &culling.tiles);
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&scene_layout],
+            push_constant_ranges: &[],
+        });
+        let create_pipeline = |label, fragment, depth_write_enabled, depth_compare| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::desc()],
+                },
+                fragment,
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DepthTexture::FORMAT,
+                    depth_write_enabled,
+                    depth_compare,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+        // no fragment stage, only the depth
+        let prepass_pipeline = create_pipeline("prepass", None, true, wgpu::CompareFunction::Less);
+        // the depth is final already, only the fragment that wrote it passes, so each pixel
+        // is lit once however much overdraw there is
+        let targets = [Some(config.format.into())];
+        let forward_pipeline = create_pipeline(
+            "forward",
+            Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &targets,
+            }),
+            false,
+            wgpu::CompareFunction::LessEqual,
+        );
+
+        info!(
+            "{}x{} pixel tiles. press up / down to change the number of lights, c to switch between culled, every light and the light count per tile, p to pause the lights",
+            TILE_SIZE, TILE_SIZE
+        );
+
+        ForwardPlus {
+            prepass_pipeline,
+            forward_pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            depth,
+            culling,
+            lights,
+            light_count: START_LIGHTS,
+            mode: 0,
+            lights_buffer,
+            culling_buffer,
+            scene_layout,
+            scene_bindgroup,
+            camera,
+            camera_controller,
+            camera_buffer,
+            paused: false,
+            time: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+        // a new depth and a different number of tiles
+        self.depth = Texture::create_depth(device, config.width, config.height, "prepass depth");
+        let buffers = [
+            &self.camera_buffer,
+            &self.culling_buffer,
+            &self.lights_buffer,
+        ];
+        self.culling
+            .resize(device, buffers, &self.depth, config.width, config.height);
+        self.scene_bindgroup =
+            create_scene_bind_group(device, &self.scene_layout, buffers, &self.culling.tiles);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::Up => {
+                    self.light_count = (self.light_count + LIGHT_STEP).min(MAX_LIGHTS)
+                }
+                VirtualKeyCode::Down => {
+                    self.light_count = self.light_count.saturating_sub(LIGHT_STEP)
+                }
+                VirtualKeyCode::C => self.mode = (self.mode + 1) % MODES.len(),
+                VirtualKeyCode::P => self.paused = !self.paused,
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.paused {
+            self.time += frame.delta;
+        }
+
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+        watchdog::write_buffer(
+            queue,
+            &self.culling_buffer,
+            0,
+            bytemuck::bytes_of(&CullingUniform {
+                light_count: self.light_count as u32,
+                tiles_x: self.culling.tiles_x,
+                mode: self.mode as u32,
+                _padding: 0,
+            }),
+        );
+        let lights: Vec<LightUniform> = self.lights[..self.light_count]
+            .iter()
+            .map(|light| light.uniform(self.time))
+            .collect();
+        watchdog::write_buffer(queue, &self.lights_buffer, 0, bytemuck::cast_slice(&lights));
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("prepass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: true,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            rpass.set_pipeline(&self.prepass_pipeline);
+            rpass.set_bind_group(0, &self.scene_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+
+        // every mode builds the lists, so switching to every light shows the fragment side
+        // of the difference
+        self.culling.run(&mut encoder);
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("forward"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.01,
+                            g: 0.01,
+                            b: 0.02,
+                            a: 1.,
+                        }),
+                        store: true,
+                    },
+                })],
+                // the prepass depth, kept as it is
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            rpass.set_pipeline(&self.forward_pipeline);
+            rpass.set_bind_group(0, &self.scene_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+    fn pass_graph(&self) -> Option<PassGraph> {
+        Some(
+            PassGraph::new()
+                .pass("depth prepass", &[], &["depth"])
+                .pass("light culling", &["depth", "lights"], &["tile lists"])
+                .pass("forward", &["depth", "lights", "tile lists"], &["window"]),
+        )
+    }
+}
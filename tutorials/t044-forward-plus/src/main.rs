@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t44_forward_plus::ForwardPlus>(t44_forward_plus::TITLE);
+}
@@ -0,0 +1,114 @@
+// the scene, drawn twice: once by the depth prepass, vertex stage only, and once more by the
+// forward pass, whose fragments light themselves with the lights cull.wgsl left for their tile
+
+// matches `CameraUniform` in lib.rs
+struct Camera {
+    view_proj: mat4x4<f32>,
+    view: mat4x4<f32>,
+    inv_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+
+// matches `CullingUniform` in lib.rs
+struct Culling {
+    light_count: u32,
+    tiles_x: u32,
+    // 0 the tile's lights, 1 every light, 2 the tile's lights with their count on top
+    mode: u32,
+};
+
+// matches `LightUniform` in lib.rs
+struct Light {
+    // xyz, and how far the light reaches in w
+    position: vec4<f32>,
+    color: vec4<f32>,
+};
+
+// matches `TILE_SIZE` and `TILE_STRIDE` in lib.rs
+let TILE_SIZE: u32 = 16u;
+let TILE_STRIDE: u32 = 128u;
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+@group(0) @binding(1)
+var<uniform> culling: Culling;
+@group(0) @binding(2)
+var<storage, read> lights: array<Light>;
+@group(0) @binding(3)
+var<storage, read> tiles: array<u32>;
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_position = input.pos;
+    fragInput.normal = input.normal;
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+let ambient = 0.03;
+
+fn shade(light: Light, position: vec3<f32>, normal: vec3<f32>, view_dir: vec3<f32>, albedo: vec3<f32>) -> vec3<f32> {
+    let to_light = light.position.xyz - position;
+    let distance = length(to_light);
+    if (distance >= light.position.w) {
+        return vec3<f32>(0.0);
+    }
+    let light_dir = to_light / distance;
+
+    // falls to exactly 0 at the light's reach, past it the light can be left out
+    let falloff = clamp(1.0 - pow(distance / light.position.w, 2.0), 0.0, 1.0);
+    let attenuation = falloff * falloff;
+
+    let diffuse = max(dot(normal, light_dir), 0.0);
+    let half_dir = normalize(view_dir + light_dir);
+    let specular = pow(max(dot(normal, half_dir), 0.0), 32.0) * 0.3;
+    return light.color.rgb * (albedo * diffuse + specular) * attenuation;
+}
+
+// blue for no lights through green to red for 32 or more
+fn heat(count: u32) -> vec3<f32> {
+    let t = min(f32(count) / 32.0, 1.0) * 2.0 - 1.0;
+    return clamp(vec3<f32>(t, 1.0 - abs(t), -t), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let normal = normalize(input.normal);
+    let view_dir = normalize(camera.position.xyz - input.world_position);
+    var color = input.color * ambient;
+
+    if (culling.mode == 1u) {
+        for (var i = 0u; i < culling.light_count; i = i + 1u) {
+            color = color + shade(lights[i], input.world_position, normal, view_dir, input.color);
+        }
+        return vec4<f32>(color, 1.0);
+    }
+
+    let tile = vec2<u32>(input.clip_position.xy) / TILE_SIZE;
+    let list = (tile.y * culling.tiles_x + tile.x) * TILE_STRIDE;
+    let count = tiles[list];
+    for (var i = 0u; i < count; i = i + 1u) {
+        let light = lights[tiles[list + 1u + i]];
+        color = color + shade(light, input.world_position, normal, view_dir, input.color);
+    }
+
+    if (culling.mode == 2u) {
+        color = mix(color, heat(count), 0.5);
+    }
+    return vec4<f32>(color, 1.0);
+}
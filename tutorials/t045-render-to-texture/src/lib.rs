@@ -0,0 +1,456 @@
+// render to texture. the spinning quad from t006 is drawn twice a frame: first into an
+// offscreen texture of its own, with its own camera and its own clear color, and then that
+// texture becomes the picture on a second quad, a tv standing in the window.
+//
+// the first pass doesn't know it isn't drawing into the window, a color attachment is a
+// texture view either way. what makes the texture usable afterwards is TEXTURE_BINDING next
+// to RENDER_ATTACHMENT in its usage, and that the second pass starts after the first one
+// ended: a texture can't be drawn into and sampled in the same pass. every post processing
+// effect, shadow map and mirror is this pattern with a different second pass.
+//
+// the screen texture has a size of its own, nothing ties it to the window. r steps it down,
+// the tv gets blurrier while the window stays as sharp as before.
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    pass_graph::PassGraph,
+    screenshot::DumpTarget,
+    texture::Texture,
+    watchdog,
+};
+use glam::{Mat4, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "render to texture, 512x512";
+
+// square, like the tv's screen. r steps through them
+const SCREEN_SIZES: [u32; 3] = [512, 128, 32];
+const SCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+const CAMERA_SENSITIVITY: f32 = 0.005;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coord: [f32; 2],
+}
+
+fn vertex(pos: [f32; 3], tex_coord: [f32; 2]) -> Vertex {
+    Vertex { pos, tex_coord }
+}
+
+// the quad from t006, both passes draw it
+fn create_vertices() -> (Vec<Vertex>, Vec<u16>) {
+    let vertices = vec![
+        vertex([-0.5, -0.5, 0.], [0., 1.]), // left bottom front
+        vertex([0.5, -0.5, 0.], [1., 1.]),  // right bottom front
+        vertex([0.5, 0.5, 0.], [1., 0.]),   // top right front
+        vertex([-0.5, 0.5, 0.], [0., 0.]),  // top left front
+    ];
+
+    let indices = vec![
+        0, 1, 3, // first triangle
+        1, 2, 3, // second triangle
+    ];
+
+    (vertices, indices)
+}
+
+// sampled by the tv and drawn into by the first pass, `create_render_target` asks for both
+fn create_screen(device: &wgpu::Device, size: u32) -> Texture {
+    Texture::create_render_target(device, size, size, SCREEN_FORMAT, "screen")
+}
+
+fn create_texture_bindgroup(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture: &Texture,
+    label: &str,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&texture.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&texture.sampler),
+            },
+        ],
+    })
+}
+
+// the two passes only differ in the fragment shader and the format they draw into
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fs_entry: &str,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(fs_entry),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fs_entry,
+            targets: &[Some(format.into())],
+        }),
+        // the quad spins and the tv can be walked around, both are seen from behind
+        primitive: wgpu::PrimitiveState {
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+pub struct RenderToTexture {
+    quad_pipeline: wgpu::RenderPipeline,
+    screen_pipeline: wgpu::RenderPipeline,
+    texture_layout: wgpu::BindGroupLayout,
+    diffuse_bindgroup: wgpu::BindGroup,
+    // the offscreen target, its size is `SCREEN_SIZES[screen_size]`
+    screen: Texture,
+    screen_bindgroup: wgpu::BindGroup,
+    screen_size: usize,
+    // set by the key, the texture is remade in `render` where the device is
+    rebuild: bool,
+    // one mvp per quad, the inner camera's and the window camera's
+    quad_buffer: wgpu::Buffer,
+    quad_bindgroup: wgpu::BindGroup,
+    tv_buffer: wgpu::Buffer,
+    tv_bindgroup: wgpu::BindGroup,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    // looks at the spinning quad, square like the screen
+    inner_camera: Camera,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    time: f32,
+    paused: bool,
+}
+
+impl RenderToTexture {
+    fn title(&self) -> String {
+        let size = SCREEN_SIZES[self.screen_size];
+        format!("render to texture, {}x{}", size, size)
+    }
+}
+
+impl framework::App for RenderToTexture {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // texture
+        // embedded by default, LEARNWGPU_ASSETS=disk reads it from src/ instead
+        let assets = framework::assets!("src": "spengebob.jpeg");
+        let diffuse_bytes = assets
+            .load("spengebob.jpeg")
+            .expect("Fail to load spengebob.jpeg");
+        let diffuse = Texture::from_bytes(device, queue, &diffuse_bytes, "diffuse_texture")
+            .expect("Fail to decode spengebob.jpeg");
+
+        // the picture of the first pass and the screen of the second go through the same layout
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("texture_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let diffuse_bindgroup =
+            create_texture_bindgroup(device, &texture_layout, &diffuse, "diffuse_bind_group");
+
+        let screen_size = 0;
+        let screen = create_screen(device, SCREEN_SIZES[screen_size]);
+        let screen_bindgroup =
+            create_texture_bindgroup(device, &texture_layout, &screen, "screen bind group");
+
+        // mvp
+        let mvp_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("mvp bindgroup layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(64),
+                },
+                count: None,
+            }],
+        });
+        let create_mvp = |label| {
+            let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents: bytemuck::cast_slice(Mat4::IDENTITY.as_ref()),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+            let bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some(label),
+                layout: &mvp_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            (buffer, bindgroup)
+        };
+        let (quad_buffer, quad_bindgroup) = create_mvp("Quad Mvp Buffer");
+        let (tv_buffer, tv_bindgroup) = create_mvp("Tv Mvp Buffer");
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[
+                &texture_layout, // group 0, texture
+                &mvp_layout,     // group 1, mvp
+            ],
+            push_constant_ranges: &[],
+        });
+        let quad_pipeline =
+            create_pipeline(device, &pipeline_layout, &shader, "fs_main", SCREEN_FORMAT);
+        let screen_pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            &shader,
+            "fs_screen",
+            config.format,
+        );
+
+        let (verticrs, indices) = create_vertices();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertices Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // camera, the inner one never moves, the window's orbits the tv
+        let inner_camera = Camera::new(Vec3::new(0., 0., 2.), 0., 0., 1.);
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            0.,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 3., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        info!("press r to change the size of the screen texture, p to pause the quad");
+
+        RenderToTexture {
+            quad_pipeline,
+            screen_pipeline,
+            texture_layout,
+            diffuse_bindgroup,
+            screen,
+            screen_bindgroup,
+            screen_size,
+            rebuild: false,
+            quad_buffer,
+            quad_bindgroup,
+            tv_buffer,
+            tv_bindgroup,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            inner_camera,
+            camera,
+            camera_controller,
+            time: 0.,
+            paused: false,
+        }
+    }
+
+    // the screen texture keeps its size, only the window camera follows
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::R => {
+                    self.screen_size = (self.screen_size + 1) % SCREEN_SIZES.len();
+                    self.rebuild = true;
+                }
+                VirtualKeyCode::P => self.paused = !self.paused,
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.rebuild {
+            self.rebuild = false;
+            self.screen = create_screen(device, SCREEN_SIZES[self.screen_size]);
+            // the old bind group still points at the old texture
+            self.screen_bindgroup = create_texture_bindgroup(
+                device,
+                &self.texture_layout,
+                &self.screen,
+                "screen bind group",
+            );
+        }
+
+        if !self.paused {
+            self.time += frame.delta;
+        }
+
+        // projection * view * model, the quad tumbles like in t006
+        let model = Mat4::from_rotation_y(self.time * 0.7) * Mat4::from_rotation_x(self.time);
+        let quad_mvp = self.inner_camera.view_proj() * model;
+        watchdog::write_buffer(
+            queue,
+            &self.quad_buffer,
+            0,
+            bytemuck::cast_slice(quad_mvp.as_ref()),
+        );
+
+        // the same quad twice the size is the tv
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        let tv_mvp = self.camera.view_proj() * Mat4::from_scale(Vec3::splat(2.));
+        watchdog::write_buffer(
+            queue,
+            &self.tv_buffer,
+            0,
+            bytemuck::cast_slice(tv_mvp.as_ref()),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // first pass, into the screen texture instead of the window
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("screen pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.screen.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.1,
+                            g: 0.2,
+                            b: 0.5,
+                            a: 1.,
+                        }),
+                        // the second pass samples it
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.quad_pipeline);
+            rpass.set_bind_group(0, &self.diffuse_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.quad_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+
+        // second pass, the tv in the window with the screen texture as its picture
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+            rpass.set_pipeline(&self.screen_pipeline);
+            rpass.set_bind_group(0, &self.screen_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.tv_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        let size = SCREEN_SIZES[self.screen_size];
+        vec![DumpTarget::new(
+            "screen",
+            &self.screen.texture,
+            size,
+            size,
+            SCREEN_FORMAT,
+        )]
+    }
+
+    fn pass_graph(&self) -> Option<PassGraph> {
+        Some(
+            PassGraph::new()
+                .pass("screen", &["spengebob.jpeg"], &["screen"])
+                .pass("tv", &["screen"], &["window"]),
+        )
+    }
+}
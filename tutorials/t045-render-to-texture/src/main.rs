@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t45_render_to_texture::RenderToTexture>(t45_render_to_texture::TITLE);
+}
@@ -0,0 +1,53 @@
+// both quads go through here: the spinning picture into the offscreen screen texture with
+// fs_main, and the tv in the window with fs_screen, which samples that texture
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) tex_coord: vec2<f32>,
+};
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1)
+var s_diffuse: sampler;
+
+// projection * view * model, one buffer per quad
+@group(1) @binding(0)
+var<uniform> mvp: mat4x4<f32>;
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = mvp * vec4<f32>(input.pos, 1.0);
+    fragInput.tex_coord = input.tex_coord;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    return textureSample(t_diffuse, s_diffuse, input.tex_coord);
+}
+
+// how much of the quad's edge is the tv's frame
+let BEZEL = 0.06;
+
+@fragment
+fn fs_screen(input: FragInput) -> @location(0) vec4<f32> {
+    // the picture inside the frame, brightest on the rows of the screen texture and darker
+    // between them. sampled before the frame is known, textureSample has to run in uniform
+    // control flow
+    let screen_uv = (input.tex_coord - BEZEL) / (1.0 - 2.0 * BEZEL);
+    let color = textureSample(t_diffuse, s_diffuse, screen_uv);
+    let rows = f32(textureDimensions(t_diffuse).y);
+    let scanline = 0.85 + 0.15 * cos((screen_uv.y * rows - 0.5) * 6.2831853);
+
+    if (any(screen_uv < vec2<f32>(0.0)) || any(screen_uv > vec2<f32>(1.0))) {
+        return vec4<f32>(0.08, 0.08, 0.09, 1.0);
+    }
+    return vec4<f32>(color.rgb * scanline, 1.0);
+}
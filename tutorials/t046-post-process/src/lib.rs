@@ -0,0 +1,489 @@
+// post processing with `framework::post_process::PostProcessChain`. the scene draws into the
+// chain's input instead of the window, then each enabled effect is one fullscreen pass that
+// reads the picture so far and writes it out changed, back and forth between two textures,
+// the last pass into the window.
+//
+// the order is the order they were pushed in and it matters: here blur runs before pixelate
+// and gives soft blocks, the other way round the blocks' edges would be blurred away. an
+// effect can be pushed twice too, the second blur smooths out what the first one left. 1 to 7
+// turn them on and off, the title shows the chain as it runs.
+//
+// vignette and chromatic aberration have a strength, one uniform the chain's shader reads,
+// so turning them up or down is a buffer write and not another pipeline. up and down change
+// the vignette's, right and left the aberration's.
+//
+// the console (`~`) has the same controls as commands: `toggle` with an effect's number or name,
+// `set vignette` or `set aberration` with a strength.
+//
+// pixelate isn't one of the chain's own, it comes from pixelate.wgsl next to this file. any
+// shader that reads its source from @group(0) @binding(0) and (1) can join the chain.
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    console::{self, Commands},
+    pass_graph::PassGraph,
+    post_process::{Effect, PostProcessChain, Strengths},
+    screenshot::DumpTarget,
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::Vec3;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "post process, vignette 1.00";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const STRENGTH_STEP: f32 = 0.25;
+const MAX_STRENGTH: f32 = 4.;
+// boxes per side of the grid
+const GRID: u32 = 5;
+
+fn hash(a: u32, b: u32) -> f32 {
+    let mut h = a.wrapping_mul(0x27d4_eb2d) ^ b.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    (h & 0xffff) as f32 / 65535.
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+        }
+    }
+}
+
+fn push_box(
+    verticrs: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    half: Vec3,
+    color: [f32; 3],
+) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+    for (normal, u, v) in faces {
+        let base = verticrs.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = center + (normal + u * su + v * sv) * half;
+            verticrs.push(Vertex {
+                pos: pos.to_array(),
+                normal: normal.to_array(),
+                color,
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+}
+
+// a floor with a grid of boxes of every height and color, plenty of edges and colors for the
+// effects to work on
+fn create_scene() -> (Vec<Vertex>, Vec<u16>) {
+    let mut verticrs = Vec::new();
+    let mut indices = Vec::new();
+
+    push_box(
+        &mut verticrs,
+        &mut indices,
+        Vec3::new(0., -0.05, 0.),
+        Vec3::new(3.5, 0.05, 3.5),
+        [0.8, 0.8, 0.75],
+    );
+
+    let spacing = 1.2;
+    let offset = (GRID - 1) as f32 * spacing / 2.;
+    for z in 0..GRID {
+        for x in 0..GRID {
+            let height = 0.2 + hash(x, z) * 0.8;
+            let center = Vec3::new(
+                x as f32 * spacing - offset,
+                height,
+                z as f32 * spacing - offset,
+            );
+            let color = [hash(x, z + 7), hash(x + 7, z), hash(x + 7, z + 7)];
+            push_box(
+                &mut verticrs,
+                &mut indices,
+                center,
+                Vec3::new(0.35, height, 0.35),
+                color,
+            );
+        }
+    }
+
+    (verticrs, indices)
+}
+
+pub struct PostProcess {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    chain: PostProcessChain,
+}
+
+impl PostProcess {
+    fn title(&self) -> String {
+        let Strengths {
+            vignette,
+            chromatic_aberration,
+        } = self.chain.strengths;
+        let enabled: Vec<String> = self
+            .chain
+            .enabled()
+            .into_iter()
+            .map(|name| match name {
+                "vignette" => format!("vignette {:.2}", vignette),
+                "chromatic aberration" => {
+                    format!("chromatic aberration {:.2}", chromatic_aberration)
+                }
+                _ => name.to_string(),
+            })
+            .collect();
+        if enabled.is_empty() {
+            "post process, none".to_string()
+        } else {
+            format!("post process, {}", enabled.join(" > "))
+        }
+    }
+}
+
+impl framework::App for PostProcess {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.6,
+            -0.6,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 9., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        let (verticrs, indices) = create_scene();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        // the chain's input has the window's format, the scene pipeline can't tell the two apart
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("scene"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // the chain, in the order the keys number them. only the vignette starts on
+        let mut chain = PostProcessChain::new(device, config.format, config.width, config.height);
+        for effect in [
+            Effect::Grayscale,
+            Effect::Invert,
+            Effect::Blur,
+            Effect::Blur,
+        ] {
+            let index = chain.push(device, effect);
+            chain.set_enabled(index, false);
+        }
+        chain.push(device, Effect::Vignette);
+        let pixelate = device.create_shader_module(include_wgsl!("pixelate.wgsl"));
+        let index = chain.push_shader(device, "pixelate", &pixelate, "fs_pixelate");
+        chain.set_enabled(index, false);
+        let index = chain.push(device, Effect::ChromaticAberration);
+        chain.set_enabled(index, false);
+
+        info!("press 1 to 7 to turn grayscale, invert, blur, blur again, vignette, pixelate and chromatic aberration on and off");
+        info!("press up / down to change the vignette's strength, right / left the chromatic aberration's");
+
+        PostProcess {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            chain,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+        self.chain.resize(device, config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            let strengths = &mut self.chain.strengths;
+            let step = |strength: &mut f32, by: f32| {
+                *strength = (*strength + by).clamp(0., MAX_STRENGTH);
+            };
+            match key {
+                VirtualKeyCode::Up => step(&mut strengths.vignette, STRENGTH_STEP),
+                VirtualKeyCode::Down => step(&mut strengths.vignette, -STRENGTH_STEP),
+                VirtualKeyCode::Right => step(&mut strengths.chromatic_aberration, STRENGTH_STEP),
+                VirtualKeyCode::Left => step(&mut strengths.chromatic_aberration, -STRENGTH_STEP),
+                _ => {
+                    let index = match key {
+                        VirtualKeyCode::Key1 => 0,
+                        VirtualKeyCode::Key2 => 1,
+                        VirtualKeyCode::Key3 => 2,
+                        VirtualKeyCode::Key4 => 3,
+                        VirtualKeyCode::Key5 => 4,
+                        VirtualKeyCode::Key6 => 5,
+                        VirtualKeyCode::Key7 => 6,
+                        _ => return,
+                    };
+                    self.chain.set_enabled(index, !self.chain.is_enabled(index));
+                }
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller.update_camera(&mut self.camera, 0.);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // the scene, into the chain's input with the window's depth buffer
+        {
+            let scene = framework::Frame {
+                view: self.chain.input(),
+                depth: frame.depth,
+                ..*frame
+            };
+            let mut rpass = scene.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.35,
+                    g: 0.5,
+                    b: 0.7,
+                    a: 1.,
+                },
+            );
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+
+        self.chain.update(queue);
+        self.chain.render(&mut encoder, frame.view);
+
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        self.chain.dump_targets()
+    }
+
+    fn pass_graph(&self) -> Option<PassGraph> {
+        let graph = PassGraph::new().pass("scene", &[], &["post process ping", "depth"]);
+        Some(self.chain.pass_graph(graph, "window"))
+    }
+
+    fn commands(commands: &mut Commands<Self>) {
+        commands
+            .add(
+                "toggle",
+                "[number or name]",
+                "turn an effect on or off, without one list them",
+                |app, window, args| {
+                    let chain = &mut app.chain;
+                    if args.is_empty() {
+                        let effects: Vec<String> = (0..chain.len())
+                            .map(|i| {
+                                let state = if chain.is_enabled(i) { "on" } else { "off" };
+                                format!("{} {} {}", i + 1, chain.name(i), state)
+                            })
+                            .collect();
+                        return Ok(effects.join("\n"));
+                    }
+                    let name = args.join(" ");
+                    // the first of the effects pushed twice, its number picks the second
+                    let index = match name.parse::<usize>() {
+                        Ok(number) if (1..=chain.len()).contains(&number) => number - 1,
+                        _ => (0..chain.len())
+                            .find(|&i| chain.name(i) == name)
+                            .ok_or_else(|| format!("no effect is called `{}`", name))?,
+                    };
+                    chain.set_enabled(index, !chain.is_enabled(index));
+                    window.set_title(&app.title());
+                    Ok(app.title())
+                },
+            )
+            .add(
+                "set",
+                "<vignette|aberration> <strength>",
+                "change an effect's strength",
+                |app, window, args| {
+                    let strength = console::parse::<f32>(args, 1)?.clamp(0., MAX_STRENGTH);
+                    let strengths = &mut app.chain.strengths;
+                    match args[0] {
+                        "vignette" => strengths.vignette = strength,
+                        "aberration" => strengths.chromatic_aberration = strength,
+                        other => return Err(format!("`{}` has no strength", other)),
+                    }
+                    window.set_title(&app.title());
+                    Ok(app.title())
+                },
+            );
+    }
+}
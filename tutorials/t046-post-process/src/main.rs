@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t46_post_process::PostProcess>(t46_post_process::TITLE);
+}
@@ -0,0 +1,35 @@
+// an effect of the chapter's own, pushed with `PostProcessChain::push_shader`. the chain only
+// asks for the source at @group(0) @binding(0) and (1), vs_main is post_process.wgsl's
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> FragInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    fragInput.tex_coord = uv;
+    return fragInput;
+}
+
+@group(0) @binding(0)
+var t_source: texture_2d<f32>;
+@group(0) @binding(1)
+var s_source: sampler;
+
+// how many pixels of the window one block covers
+let BLOCK: f32 = 8.0;
+
+// every pixel of a block takes the color at the block's center
+@fragment
+fn fs_pixelate(input: FragInput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(t_source));
+    let block = floor(input.clip_position.xy / BLOCK);
+    let uv = (block + 0.5) * BLOCK / size;
+    return textureSampleLevel(t_source, s_source, uv, 0.0);
+}
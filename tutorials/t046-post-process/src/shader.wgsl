@@ -0,0 +1,37 @@
+// the scene, boxes lit by one sun and drawn into the chain's input instead of the window
+
+// matches `CameraUniform` in lib.rs
+struct Camera {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.normal = input.normal;
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let sun = normalize(vec3<f32>(0.4, 1.0, 0.6));
+    let diffuse = max(dot(normalize(input.normal), sun), 0.0);
+    return vec4<f32>(input.color * (0.2 + 0.8 * diffuse), 1.0);
+}
@@ -0,0 +1,640 @@
+// motion blur from a velocity buffer. every object keeps the matrix it was drawn with last
+// frame, the scene pass gets both and writes, next to the color, how far each pixel moved on
+// screen since then. a fullscreen pass afterwards averages the color along that line, as a
+// camera with its shutter open for part of the frame would have seen it.
+//
+// the previous matrix includes the previous camera, so orbiting the camera blurs the still
+// boxes too. the sky is cleared to zero velocity and never blurs, and since a box only writes
+// velocity where it covers the screen now, its streak stays inside its own outline instead of
+// trailing behind it. that is the usual trade of the per object velocity buffer.
+//
+// the velocity is per frame, so the same motion blurs less at a higher frame rate.
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    pass_graph::PassGraph,
+    screenshot::DumpTarget,
+    targets::{TargetDesc, Targets},
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::{Mat4, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "motion blur, shutter 1.00";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+// two channels of screen space motion, halves are plenty for fractions of the screen
+const VELOCITY_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rg16Float;
+const NUM_ORBITERS: u32 = 6;
+const SHUTTER_STEP: f32 = 0.25;
+const MAX_SHUTTER: f32 = 4.;
+
+fn hash(a: u32, b: u32) -> f32 {
+    let mut h = a.wrapping_mul(0x27d4_eb2d) ^ b.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    (h & 0xffff) as f32 / 65535.
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// matches `Object` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ObjectUniform {
+    mvp: [[f32; 4]; 4],
+    prev_mvp: [[f32; 4]; 4],
+    model: [[f32; 4]; 4],
+    color: [f32; 4],
+}
+
+// matches `Blur` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct BlurUniform {
+    shutter: f32,
+    show_velocity: u32,
+    _padding: [f32; 2],
+}
+
+// a unit box around the origin, every object is this scaled and moved
+fn create_box() -> (Vec<Vertex>, Vec<u16>) {
+    let mut verticrs = Vec::new();
+    let mut indices = Vec::new();
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+    for (normal, u, v) in faces {
+        let base = verticrs.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = (normal + u * su + v * sv) * 0.5;
+            verticrs.push(Vertex {
+                pos: pos.to_array(),
+                normal: normal.to_array(),
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+    (verticrs, indices)
+}
+
+// how an object moves: around the y axis at `radius`, and spinning around its own
+struct Motion {
+    scale: Vec3,
+    radius: f32,
+    height: f32,
+    phase: f32,
+    orbit_speed: f32,
+    spin_speed: f32,
+}
+
+impl Motion {
+    fn model(&self, time: f32) -> Mat4 {
+        Mat4::from_rotation_y(self.phase + time * self.orbit_speed)
+            * Mat4::from_translation(Vec3::new(self.radius, self.height, 0.))
+            * Mat4::from_rotation_y(time * self.spin_speed)
+            * Mat4::from_scale(self.scale)
+    }
+}
+
+struct Object {
+    motion: Motion,
+    color: [f32; 4],
+    buffer: wgpu::Buffer,
+    bindgroup: wgpu::BindGroup,
+    /// what it was drawn with last frame, none right after a resize
+    prev_mvp: Option<Mat4>,
+}
+
+// the ground, a pillar spinning in place and a ring of boxes going around it, each at its
+// own speed. the last orbiter stands still for comparison
+fn create_motions() -> Vec<(Motion, [f32; 4])> {
+    let mut motions = vec![
+        (
+            Motion {
+                scale: Vec3::new(12., 0.1, 12.),
+                radius: 0.,
+                height: -0.05,
+                phase: 0.,
+                orbit_speed: 0.,
+                spin_speed: 0.,
+            },
+            [0.8, 0.8, 0.75, 1.],
+        ),
+        (
+            Motion {
+                scale: Vec3::new(0.4, 2., 1.6),
+                radius: 0.,
+                height: 1.,
+                phase: 0.,
+                orbit_speed: 0.,
+                spin_speed: 4.,
+            },
+            [0.9, 0.3, 0.2, 1.],
+        ),
+    ];
+    for i in 0..NUM_ORBITERS {
+        let still = i + 1 == NUM_ORBITERS;
+        let speed = if still { 0. } else { 0.5 + hash(i, 1) * 1.5 };
+        motions.push((
+            Motion {
+                scale: Vec3::splat(0.6),
+                radius: 3.5,
+                height: 0.3 + hash(i, 2) * 0.6,
+                phase: i as f32 / NUM_ORBITERS as f32 * std::f32::consts::TAU,
+                orbit_speed: speed,
+                spin_speed: speed * 2.,
+            },
+            [hash(i, 3), hash(i, 4), hash(i, 5), 1.],
+        ));
+    }
+    motions
+}
+
+pub struct MotionBlur {
+    scene_pipeline: wgpu::RenderPipeline,
+    blur_pipeline: wgpu::RenderPipeline,
+    blur_layout: wgpu::BindGroupLayout,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    objects: Vec<Object>,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    /// scene color and velocity, both the window's size
+    targets: Targets,
+    blur_buffer: wgpu::Buffer,
+    blur_bindgroup: wgpu::BindGroup,
+    shutter: f32,
+    show_velocity: bool,
+    paused: bool,
+    time: f32,
+}
+
+impl MotionBlur {
+    fn title(&self) -> String {
+        if self.show_velocity {
+            "motion blur, velocity".to_string()
+        } else {
+            format!("motion blur, shutter {:.2}", self.shutter)
+        }
+    }
+
+    fn blur_uniform(&self) -> BlurUniform {
+        BlurUniform {
+            shutter: self.shutter,
+            show_velocity: self.show_velocity as u32,
+            _padding: [0.; 2],
+        }
+    }
+}
+
+fn create_blur_bindgroup(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    targets: &Targets,
+    blur_buffer: &wgpu::Buffer,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("blur bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&targets[0].view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::TextureView(&targets[1].view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 2,
+                resource: wgpu::BindingResource::Sampler(&targets[0].sampler),
+            },
+            wgpu::BindGroupEntry {
+                binding: 3,
+                resource: blur_buffer.as_entire_binding(),
+            },
+        ],
+    })
+}
+
+impl framework::App for MotionBlur {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.6,
+            -0.5,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller =
+            OrbitCameraController::new(Vec3::new(0., 0.5, 0.), 9., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        // one uniform buffer per object, both of its matrices change every frame
+        let object_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("object bindgroup layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<ObjectUniform>() as u64
+                    ),
+                },
+                count: None,
+            }],
+        });
+
+        let objects = create_motions()
+            .into_iter()
+            .map(|(motion, color)| {
+                let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Object Buffer"),
+                    size: std::mem::size_of::<ObjectUniform>() as u64,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                });
+                let bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("object bind group"),
+                    layout: &object_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: buffer.as_entire_binding(),
+                    }],
+                });
+                Object {
+                    motion,
+                    color,
+                    buffer,
+                    bindgroup,
+                    prev_mvp: None,
+                }
+            })
+            .collect();
+
+        let (verticrs, indices) = create_box();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let targets = Targets::new(
+            device,
+            config.width,
+            config.height,
+            vec![
+                TargetDesc::new(config.format, 1, "scene color"),
+                TargetDesc::new(VELOCITY_FORMAT, 1, "velocity"),
+            ],
+        );
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let scene_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&object_layout],
+                push_constant_ranges: &[],
+            });
+        let scene_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("scene"),
+            layout: Some(&scene_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into()), Some(VELOCITY_FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        // the blur reads both targets
+        let blur_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("blur bindgroup layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<BlurUniform>() as u64
+                        ),
+                    },
+                    count: None,
+                },
+            ],
+        });
+
+        let shutter = 1.;
+        let blur_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Blur Buffer"),
+            contents: bytemuck::bytes_of(&BlurUniform {
+                shutter,
+                show_velocity: 0,
+                _padding: [0.; 2],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let blur_bindgroup = create_blur_bindgroup(device, &blur_layout, &targets, &blur_buffer);
+
+        let blur_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&blur_layout],
+            push_constant_ranges: &[],
+        });
+        let blur_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("motion blur"),
+            layout: Some(&blur_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_fullscreen",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_blur",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        info!("press up / down to open and close the shutter, v to show the velocity buffer");
+        info!("press space to pause the boxes");
+
+        MotionBlur {
+            scene_pipeline,
+            blur_pipeline,
+            blur_layout,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            objects,
+            camera,
+            camera_controller,
+            targets,
+            blur_buffer,
+            blur_bindgroup,
+            shutter,
+            show_velocity: false,
+            paused: false,
+            time: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+        if self.targets.resize(device, config.width, config.height) {
+            self.blur_bindgroup =
+                create_blur_bindgroup(device, &self.blur_layout, &self.targets, &self.blur_buffer);
+        }
+        // the projection changed, last frame's matrices would read as a jump
+        for object in &mut self.objects {
+            object.prev_mvp = None;
+        }
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::Up => {
+                    self.shutter = (self.shutter + SHUTTER_STEP).min(MAX_SHUTTER);
+                }
+                VirtualKeyCode::Down => {
+                    self.shutter = (self.shutter - SHUTTER_STEP).max(0.);
+                }
+                VirtualKeyCode::V => self.show_velocity = !self.show_velocity,
+                VirtualKeyCode::Space => self.paused = !self.paused,
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.paused {
+            self.time += frame.delta;
+        }
+
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        let view_proj = self.camera.view_proj();
+        for object in &mut self.objects {
+            let model = object.motion.model(self.time);
+            let mvp = view_proj * model;
+            let uniform = ObjectUniform {
+                mvp: mvp.to_cols_array_2d(),
+                prev_mvp: object.prev_mvp.unwrap_or(mvp).to_cols_array_2d(),
+                model: model.to_cols_array_2d(),
+                color: object.color,
+            };
+            watchdog::write_buffer(queue, &object.buffer, 0, bytemuck::bytes_of(&uniform));
+            object.prev_mvp = Some(mvp);
+        }
+        watchdog::write_buffer(
+            queue,
+            &self.blur_buffer,
+            0,
+            bytemuck::bytes_of(&self.blur_uniform()),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // the scene, color and velocity at once, with the window's depth buffer
+        {
+            let clear = [
+                wgpu::Color {
+                    r: 0.35,
+                    g: 0.5,
+                    b: 0.7,
+                    a: 1.,
+                },
+                wgpu::Color::TRANSPARENT,
+            ];
+            let color_attachments: Vec<_> = self
+                .targets
+                .iter()
+                .zip(clear)
+                .map(|(target, clear)| {
+                    Some(wgpu::RenderPassColorAttachment {
+                        view: &target.view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(clear),
+                            store: true,
+                        },
+                    })
+                })
+                .collect();
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("scene pass"),
+                color_attachments: &color_attachments,
+                depth_stencil_attachment: frame.depth.map(DepthTexture::attachment),
+            });
+            rpass.set_pipeline(&self.scene_pipeline);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            for object in &self.objects {
+                rpass.set_bind_group(0, &object.bindgroup, &[]);
+                rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+            }
+        }
+
+        // the blur, straight into the window
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("motion blur pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // every pixel gets written, nothing to clear
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.blur_pipeline);
+            rpass.set_bind_group(0, &self.blur_bindgroup, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        self.targets.dump_targets()
+    }
+
+    fn pass_graph(&self) -> Option<PassGraph> {
+        Some(
+            PassGraph::new()
+                .pass("scene", &[], &["scene color", "velocity", "depth"])
+                .pass("motion blur", &["scene color", "velocity"], &["window"]),
+        )
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t47_motion_blur::MotionBlur>(t47_motion_blur::TITLE);
+}
@@ -0,0 +1,113 @@
+// the scene pass draws each object with this frame's and last frame's matrices and writes the
+// color and how far the pixel moved on screen, fs_blur then smears the color along that
+
+// matches `ObjectUniform` in lib.rs
+struct Object {
+    mvp: mat4x4<f32>,
+    prev_mvp: mat4x4<f32>,
+    model: mat4x4<f32>,
+    color: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> object: Object;
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) normal: vec3<f32>,
+    // both clip positions, divided per pixel and not per vertex
+    @location(1) current: vec4<f32>,
+    @location(2) previous: vec4<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = object.mvp * vec4<f32>(input.pos, 1.0);
+    fragInput.current = fragInput.clip_position;
+    fragInput.previous = object.prev_mvp * vec4<f32>(input.pos, 1.0);
+    // the boxes are only rotated and moved, no need for the inverse transpose
+    fragInput.normal = (object.model * vec4<f32>(input.normal, 0.0)).xyz;
+    return fragInput;
+}
+
+struct SceneOutput {
+    @location(0) color: vec4<f32>,
+    @location(1) velocity: vec2<f32>,
+};
+
+@fragment
+fn fs_main(input: FragInput) -> SceneOutput {
+    let sun = normalize(vec3<f32>(0.4, 1.0, 0.6));
+    let diffuse = max(dot(normalize(input.normal), sun), 0.0);
+
+    var output: SceneOutput;
+    output.color = vec4<f32>(object.color.rgb * (0.2 + 0.8 * diffuse), 1.0);
+    // in uv, where the blur samples: y points down there
+    let ndc = input.current.xy / input.current.w - input.previous.xy / input.previous.w;
+    output.velocity = ndc * vec2<f32>(0.5, -0.5);
+    return output;
+}
+
+// matches `BlurUniform` in lib.rs
+struct Blur {
+    // how much of the frame's motion the shutter sees, 0 turns the blur off
+    shutter: f32,
+    show_velocity: u32,
+};
+
+@group(0) @binding(0)
+var t_color: texture_2d<f32>;
+@group(0) @binding(1)
+var t_velocity: texture_2d<f32>;
+@group(0) @binding(2)
+var s_screen: sampler;
+@group(0) @binding(3)
+var<uniform> blur: Blur;
+
+struct ScreenInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) index: u32) -> ScreenInput {
+    // (0, 0), (2, 0), (0, 2) in uv, covers the [0, 1] square and then some
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var screenInput : ScreenInput;
+    screenInput.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    screenInput.tex_coord = uv;
+    return screenInput;
+}
+
+let SAMPLES = 12;
+// a tenth of the screen, past that the samples are too far apart to read as a streak
+let MAX_VELOCITY = 0.1;
+
+@fragment
+fn fs_blur(input: ScreenInput) -> @location(0) vec4<f32> {
+    var velocity = textureSampleLevel(t_velocity, s_screen, input.tex_coord, 0.0).xy;
+    if (blur.show_velocity != 0u) {
+        return vec4<f32>(abs(velocity) * 20.0, 0.0, 1.0);
+    }
+
+    velocity = velocity * blur.shutter;
+    let speed = length(velocity);
+    if (speed > MAX_VELOCITY) {
+        velocity = velocity * (MAX_VELOCITY / speed);
+    }
+
+    // centered on the pixel, half toward where it was and half toward where it's going
+    var sum = vec4<f32>(0.0);
+    for (var i = 0; i < SAMPLES; i = i + 1) {
+        let t = (f32(i) + 0.5) / f32(SAMPLES) - 0.5;
+        sum = sum + textureSampleLevel(t_color, s_screen, input.tex_coord + velocity * t, 0.0);
+    }
+    return sum / f32(SAMPLES);
+}
@@ -0,0 +1,465 @@
+// color grading with a 3d lookup table from `framework::color_grading`. the scene draws into a
+// texture, then one fullscreen pass looks each pixel's color up in a small cube of colors and
+// draws what it finds into the window. the table is a 3d texture, sampled with all three
+// coordinates at once and blended between its eight nearest texels.
+//
+// three tables to swap between with l: the identity, built in code, which changes nothing,
+// warm.cube, a .cube file as grading tools export them, and teal-orange.png, the same kind of
+// table laid out as a strip of squares. up / down blend between the original and the graded
+// colors.
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    color_grading::{self, Lut},
+    pass_graph::PassGraph,
+    screenshot::DumpTarget,
+    texture::{DepthTexture, Texture},
+    watchdog,
+};
+use glam::Vec3;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "color grading, warm, 100%";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+// boxes per side of the grid
+const GRID: u32 = 5;
+const AMOUNT_STEP: f32 = 0.25;
+
+fn hash(a: u32, b: u32) -> f32 {
+    let mut h = a.wrapping_mul(0x27d4_eb2d) ^ b.wrapping_mul(0x1656_67b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    (h & 0xffff) as f32 / 65535.
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+        }
+    }
+}
+
+fn push_box(
+    verticrs: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    half: Vec3,
+    color: [f32; 3],
+) {
+    let faces = [
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y),     // right
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y),     // left
+        (Vec3::Y, Vec3::X, Vec3::NEG_Z),     // top
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z),     // bottom
+        (Vec3::Z, Vec3::X, Vec3::Y),         // front
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y), // back
+    ];
+    for (normal, u, v) in faces {
+        let base = verticrs.len() as u16;
+        for (su, sv) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            let pos = center + (normal + u * su + v * sv) * half;
+            verticrs.push(Vertex {
+                pos: pos.to_array(),
+                normal: normal.to_array(),
+                color,
+            });
+        }
+        indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+}
+
+// a floor with a grid of boxes of every height and color, plenty of edges and colors for the
+// effects to work on
+fn create_scene() -> (Vec<Vertex>, Vec<u16>) {
+    let mut verticrs = Vec::new();
+    let mut indices = Vec::new();
+
+    push_box(
+        &mut verticrs,
+        &mut indices,
+        Vec3::new(0., -0.05, 0.),
+        Vec3::new(3.5, 0.05, 3.5),
+        [0.8, 0.8, 0.75],
+    );
+
+    let spacing = 1.2;
+    let offset = (GRID - 1) as f32 * spacing / 2.;
+    for z in 0..GRID {
+        for x in 0..GRID {
+            let height = 0.2 + hash(x, z) * 0.8;
+            let center = Vec3::new(
+                x as f32 * spacing - offset,
+                height,
+                z as f32 * spacing - offset,
+            );
+            let color = [hash(x, z + 7), hash(x + 7, z), hash(x + 7, z + 7)];
+            push_box(
+                &mut verticrs,
+                &mut indices,
+                center,
+                Vec3::new(0.35, height, 0.35),
+                color,
+            );
+        }
+    }
+
+    (verticrs, indices)
+}
+
+pub struct ColorGrading {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    /// the scene goes here instead of the window, its size and format are the window's
+    scene: Texture,
+    scene_size: (u32, u32),
+    format: wgpu::TextureFormat,
+    grading: color_grading::ColorGrading,
+    luts: Vec<Lut>,
+    /// one per lut, all reading `scene`
+    grading_bindgroups: Vec<wgpu::BindGroup>,
+    lut: usize,
+}
+
+impl ColorGrading {
+    fn title(&self) -> String {
+        format!(
+            "color grading, {}, {:.0}%",
+            self.luts[self.lut].name,
+            self.grading.amount * 100.
+        )
+    }
+}
+
+fn create_grading_bindgroups(
+    device: &wgpu::Device,
+    grading: &color_grading::ColorGrading,
+    scene: &Texture,
+    luts: &[Lut],
+) -> Vec<wgpu::BindGroup> {
+    luts.iter()
+        .map(|lut| grading.create_bind_group(device, scene, lut))
+        .collect()
+}
+
+impl framework::App for ColorGrading {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.6,
+            -0.6,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 9., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        let (verticrs, indices) = create_scene();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        // the scene texture has the window's format, the scene pipeline can't tell the two apart
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("scene"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let scene = Texture::create_render_target(
+            device,
+            config.width,
+            config.height,
+            config.format,
+            "scene",
+        );
+
+        // the three tables, the file ones at 17 and 16 texels a side
+        let luts = vec![
+            Lut::identity(device, queue, 17),
+            Lut::from_cube(device, queue, "warm", include_str!("warm.cube"))
+                .expect("Fail to load warm.cube"),
+            Lut::from_strip(
+                device,
+                queue,
+                "teal orange",
+                include_bytes!("teal-orange.png"),
+            )
+            .expect("Fail to load teal-orange.png"),
+        ];
+        let grading = color_grading::ColorGrading::new(device, config.format);
+        let grading_bindgroups = create_grading_bindgroups(device, &grading, &scene, &luts);
+
+        info!("press l to swap the lut, up / down to change how much of it is applied");
+
+        ColorGrading {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            scene,
+            scene_size: (config.width, config.height),
+            format: config.format,
+            grading,
+            luts,
+            grading_bindgroups,
+            lut: 1,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+        self.scene = Texture::create_render_target(
+            device,
+            config.width,
+            config.height,
+            config.format,
+            "scene",
+        );
+        self.scene_size = (config.width, config.height);
+        self.grading_bindgroups =
+            create_grading_bindgroups(device, &self.grading, &self.scene, &self.luts);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::L => self.lut = (self.lut + 1) % self.luts.len(),
+                VirtualKeyCode::Up => {
+                    self.grading.amount = (self.grading.amount + AMOUNT_STEP).min(1.);
+                }
+                VirtualKeyCode::Down => {
+                    self.grading.amount = (self.grading.amount - AMOUNT_STEP).max(0.);
+                }
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller.update_camera(&mut self.camera, 0.);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+        self.grading.update(queue);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // the scene, into its texture with the window's depth buffer
+        {
+            let scene = framework::Frame {
+                view: &self.scene.view,
+                depth: frame.depth,
+                ..*frame
+            };
+            let mut rpass = scene.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.35,
+                    g: 0.5,
+                    b: 0.7,
+                    a: 1.,
+                },
+            );
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+
+        // graded, into the window
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("color grading pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        // every pixel gets written, nothing to clear
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.grading
+                .draw(&mut rpass, &self.grading_bindgroups[self.lut]);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+
+    fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        vec![DumpTarget::new(
+            "scene",
+            &self.scene.texture,
+            self.scene_size.0,
+            self.scene_size.1,
+            self.format,
+        )]
+    }
+
+    fn pass_graph(&self) -> Option<PassGraph> {
+        Some(
+            PassGraph::new()
+                .pass("scene", &[], &["scene", "depth"])
+                .pass("color grading", &["scene", "lut"], &["window"]),
+        )
+    }
+}
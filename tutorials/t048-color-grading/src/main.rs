@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t48_color_grading::ColorGrading>(t48_color_grading::TITLE);
+}
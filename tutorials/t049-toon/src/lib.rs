@@ -0,0 +1,489 @@
+// toon, or cel, shading: light that jumps between a few flat tones instead of falling off
+// smoothly, like paint on an animation cel. the mesh, camera and light are the same as the
+// realistic version's, only the fragment shader differs, so t switches between two pipelines
+// built from the same shader module.
+//
+// the toon shader takes the diffuse term apart three ways:
+// - bands, n·l cut into a few steps with floor, up / down change how many
+// - a ramp, ramp.png looked up at n·l, the artist picks the tones and where they change,
+//   shadows can be tinted blue. r switches between the ramp and the bands
+// - a hard highlight and a rim light on the lit side's silhouette, both a step instead of a
+//   curve
+
+use std::f32::consts::PI;
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::{Mat3, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "toon, ramp texture";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const SPHERE_STACKS: u32 = 32;
+const SPHERE_SECTORS: u32 = 64;
+const TORUS_RINGS: u32 = 64;
+const TORUS_SIDES: u32 = 32;
+const MIN_BANDS: u32 = 2;
+const MAX_BANDS: u32 = 8;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// matches `Camera` in shader.wgsl, the eye position is needed for the highlight and the rim
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `Toon` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ToonUniform {
+    bands: f32,
+    use_ramp: u32,
+    _padding: [u32; 2],
+}
+
+// theta runs down from the north pole and phi around the y axis
+fn push_sphere(
+    verticrs: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    radius: f32,
+    color: [f32; 3],
+) {
+    let base = verticrs.len() as u16;
+    for stack in 0..=SPHERE_STACKS {
+        let (sin_theta, cos_theta) = (stack as f32 / SPHERE_STACKS as f32 * PI).sin_cos();
+        for sector in 0..=SPHERE_SECTORS {
+            let (sin_phi, cos_phi) = (sector as f32 / SPHERE_SECTORS as f32 * 2. * PI).sin_cos();
+            let normal = Vec3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+            verticrs.push(Vertex {
+                pos: (center + normal * radius).to_array(),
+                normal: normal.to_array(),
+                color,
+            });
+        }
+    }
+    push_grid_indices(indices, base, SPHERE_STACKS, SPHERE_SECTORS);
+}
+
+// a ring of `TORUS_RINGS` circles around the y axis, turned by `rotation`
+fn push_torus(
+    verticrs: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    rotation: Mat3,
+    radii: (f32, f32),
+    color: [f32; 3],
+) {
+    let (major, minor) = radii;
+    let base = verticrs.len() as u16;
+    for ring in 0..=TORUS_RINGS {
+        let (sin_u, cos_u) = (ring as f32 / TORUS_RINGS as f32 * 2. * PI).sin_cos();
+        let out = Vec3::new(cos_u, 0., sin_u);
+        for side in 0..=TORUS_SIDES {
+            let (sin_v, cos_v) = (side as f32 / TORUS_SIDES as f32 * 2. * PI).sin_cos();
+            let normal = rotation * (out * cos_v + Vec3::Y * sin_v);
+            verticrs.push(Vertex {
+                pos: (center + rotation * (out * major) + normal * minor).to_array(),
+                normal: normal.to_array(),
+                color,
+            });
+        }
+    }
+    push_grid_indices(indices, base, TORUS_RINGS, TORUS_SIDES);
+}
+
+// two triangles per quad of a (rows + 1) x (columns + 1) grid of vertices starting at `base`,
+// counter-clockwise seen from outside for both meshes above
+fn push_grid_indices(indices: &mut Vec<u16>, base: u16, rows: u32, columns: u32) {
+    for row in 0..rows {
+        for column in 0..columns {
+            let top = base + (row * (columns + 1) + column) as u16;
+            let bottom = top + columns as u16 + 1;
+            indices.extend_from_slice(&[top, top + 1, bottom, top + 1, bottom + 1, bottom]);
+        }
+    }
+}
+
+// a sphere and a torus standing on its side, round shapes show the bands best
+fn create_scene() -> (Vec<Vertex>, Vec<u16>) {
+    let mut verticrs = Vec::new();
+    let mut indices = Vec::new();
+    push_sphere(
+        &mut verticrs,
+        &mut indices,
+        Vec3::new(-1.2, 0., 0.),
+        0.9,
+        [0.9, 0.35, 0.3],
+    );
+    push_torus(
+        &mut verticrs,
+        &mut indices,
+        Vec3::new(1.3, 0., 0.),
+        Mat3::from_rotation_x(1.1),
+        (0.75, 0.3),
+        [0.3, 0.6, 0.9],
+    );
+    (verticrs, indices)
+}
+
+pub struct Toon {
+    realistic_pipeline: wgpu::RenderPipeline,
+    toon_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    toon_buffer: wgpu::Buffer,
+    uniform_bindgroup: wgpu::BindGroup,
+    ramp_bindgroup: wgpu::BindGroup,
+    toon: bool,
+    use_ramp: bool,
+    bands: u32,
+}
+
+impl Toon {
+    fn title(&self) -> String {
+        if !self.toon {
+            "toon, realistic".to_string()
+        } else if self.use_ramp {
+            "toon, ramp texture".to_string()
+        } else {
+            format!("toon, {} bands", self.bands)
+        }
+    }
+}
+
+impl framework::App for Toon {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.3,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 5., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let bands = 3;
+        let use_ramp = true;
+        let toon_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Toon Buffer"),
+            contents: bytemuck::bytes_of(&ToonUniform {
+                bands: bands as f32,
+                use_ramp: use_ramp as u32,
+                _padding: [0; 2],
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_entry = |binding, visibility, size: usize| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(size as u64),
+            },
+            count: None,
+        };
+        let uniform_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("uniform bindgroup layout"),
+                entries: &[
+                    uniform_entry(
+                        0,
+                        wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        std::mem::size_of::<CameraUniform>(),
+                    ),
+                    uniform_entry(
+                        1,
+                        wgpu::ShaderStages::FRAGMENT,
+                        std::mem::size_of::<ToonUniform>(),
+                    ),
+                ],
+            });
+
+        let uniform_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("uniform bind group"),
+            layout: &uniform_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: toon_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        // the ramp, a color image with the sampler's linear filter softening its steps by a
+        // texel. embedded by default, LEARNWGPU_ASSETS=disk reads it from src/ instead
+        let assets = framework::assets!("src": "ramp.png");
+        let ramp = assets
+            .load_texture(device, queue, "ramp.png")
+            .expect("Fail to load ramp.png");
+
+        let ramp_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("ramp bindgroup layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let ramp_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("ramp bind group"),
+            layout: &ramp_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&ramp.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&ramp.sampler),
+                },
+            ],
+        });
+
+        let (verticrs, indices) = create_scene();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        // fs_realistic never reads the ramp, both pipelines share the layout anyway
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&uniform_bindgroup_layout, &ramp_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline = |entry_point| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[Vertex::desc()],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point,
+                    targets: &[Some(config.format.into())],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    cull_mode: Some(wgpu::Face::Back),
+                    ..Default::default()
+                },
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DepthTexture::FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+                multiview: None,
+            })
+        };
+
+        info!("press t to switch between the toon and the realistic pipeline");
+        info!("press r to switch between the ramp texture and bands, up / down for more or fewer bands");
+
+        Toon {
+            realistic_pipeline: create_pipeline("fs_realistic"),
+            toon_pipeline: create_pipeline("fs_toon"),
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            camera,
+            camera_controller,
+            camera_buffer,
+            toon_buffer,
+            uniform_bindgroup,
+            ramp_bindgroup,
+            toon: true,
+            use_ramp,
+            bands,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::T => self.toon = !self.toon,
+                VirtualKeyCode::R => self.use_ramp = !self.use_ramp,
+                VirtualKeyCode::Up => self.bands = (self.bands + 1).min(MAX_BANDS),
+                VirtualKeyCode::Down => self.bands = (self.bands - 1).max(MIN_BANDS),
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller.update_camera(&mut self.camera, 0.);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+        watchdog::write_buffer(
+            queue,
+            &self.toon_buffer,
+            0,
+            bytemuck::bytes_of(&ToonUniform {
+                bands: self.bands as f32,
+                use_ramp: self.use_ramp as u32,
+                _padding: [0; 2],
+            }),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.85,
+                    g: 0.88,
+                    b: 0.92,
+                    a: 1.,
+                },
+            );
+
+            // the same mesh and bind groups either way, only the pipeline changes
+            rpass.set_pipeline(if self.toon {
+                &self.toon_pipeline
+            } else {
+                &self.realistic_pipeline
+            });
+            rpass.set_bind_group(0, &self.uniform_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.ramp_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
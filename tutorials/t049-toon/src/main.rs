@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t49_toon::Toon>(t49_toon::TITLE);
+}
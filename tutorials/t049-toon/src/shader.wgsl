@@ -0,0 +1,92 @@
+// one vertex shader, two fragment shaders: fs_realistic lights the mesh smoothly, fs_toon in
+// flat bands with a hard highlight and a rim. lib.rs builds a pipeline for each
+
+// matches `CameraUniform` in lib.rs, the eye position is needed for the highlight and the rim
+struct Camera {
+    view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+
+// matches `ToonUniform` in lib.rs
+struct Toon {
+    // how many flat steps the diffuse light is cut into, without the ramp
+    bands: f32,
+    // 1 to look the light up in the ramp texture instead
+    use_ramp: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+@group(0) @binding(1)
+var<uniform> toon: Toon;
+
+// light from dark to lit, left to right, read at the half lambert term
+@group(1) @binding(0)
+var t_ramp: texture_2d<f32>;
+@group(1) @binding(1)
+var s_ramp: sampler;
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    fragInput.normal = input.normal;
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+fn sun() -> vec3<f32> {
+    return normalize(vec3<f32>(0.4, 1.0, 0.6));
+}
+
+// blinn-phong, for comparison
+@fragment
+fn fs_realistic(input: FragInput) -> @location(0) vec4<f32> {
+    let n = normalize(input.normal);
+    let v = normalize(camera.position.xyz - input.world_pos);
+    let h = normalize(sun() + v);
+
+    let diffuse = max(dot(n, sun()), 0.0);
+    let specular = pow(max(dot(n, h), 0.0), 64.0) * 0.5;
+    return vec4<f32>(input.color * (0.15 + 0.85 * diffuse) + vec3<f32>(specular), 1.0);
+}
+
+@fragment
+fn fs_toon(input: FragInput) -> @location(0) vec4<f32> {
+    let n = normalize(input.normal);
+    let v = normalize(camera.position.xyz - input.world_pos);
+    let h = normalize(sun() + v);
+    let n_dot_l = dot(n, sun());
+
+    // sampled before any branching, textureSample needs every pixel of a quad to get here.
+    // half lambert spreads the whole sphere over the ramp, the back isn't all one color
+    let ramp = textureSample(t_ramp, s_ramp, vec2<f32>(n_dot_l * 0.5 + 0.5, 0.5)).rgb;
+
+    // without the ramp, cut the diffuse term into `bands` steps, the darkest isn't black
+    let level = floor(max(n_dot_l, 0.0) * toon.bands) / max(toon.bands - 1.0, 1.0);
+    let banded = vec3<f32>(mix(0.3, 1.0, min(level, 1.0)));
+    let light = select(banded, ramp, toon.use_ramp == 1u);
+
+    // the highlight is there or not, no falloff
+    let specular = step(0.97, dot(n, h)) * step(0.0, n_dot_l) * 0.6;
+
+    // a bright edge where the surface turns away from the eye, only on the lit side
+    let rim_dot = 1.0 - max(dot(n, v), 0.0);
+    let rim = smoothstep(0.70, 0.72, rim_dot * pow(max(n_dot_l, 0.0), 0.1)) * 0.5;
+
+    return vec4<f32>(input.color * light + vec3<f32>(specular + rim), 1.0);
+}
@@ -0,0 +1,589 @@
+// a selection outline with the stencil buffer. the scene draws as usual, except that every pixel
+// the selected object covers also writes 1 into the stencil. then the selected object draws a
+// second time, pushed out along its normals and in a flat color, with a stencil test that only
+// passes where the stencil isn't 1: what's left of the bigger copy is a rim around the object.
+//
+// the stencil lives next to depth in one Depth24PlusStencil8 texture, the framework's depth
+// buffer is depth only, so the chapter keeps its own. what a pipeline does with the stencil is
+// in `DepthStencilState::stencil`, the value it writes or compares against is set per draw with
+// `set_stencil_reference`.
+//
+// tab selects the next object, x draws the outline over what's in front of the object too,
+// up / down make it wider or thinner
+
+use std::{f32::consts::PI, ops::Range};
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    watchdog,
+};
+use glam::{Mat3, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "outline, sphere selected";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const SPHERE_STACKS: u32 = 32;
+const SPHERE_SECTORS: u32 = 64;
+const TORUS_RINGS: u32 = 64;
+const TORUS_SIDES: u32 = 32;
+const OUTLINE_COLOR: [f32; 4] = [1., 0.6, 0.1, 1.];
+const OUTLINE_WIDTH_STEP: f32 = 0.01;
+const MAX_OUTLINE_WIDTH: f32 = 0.1;
+// what the selected object writes into the stencil, the rest of the scene writes 0
+const SELECTED: u32 = 1;
+const DEPTH_STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth24PlusStencil8;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x3];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// matches `Outline` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct OutlineUniform {
+    color: [f32; 4],
+    width: f32,
+    _padding: [f32; 3],
+}
+
+impl OutlineUniform {
+    fn new(width: f32) -> OutlineUniform {
+        OutlineUniform {
+            color: OUTLINE_COLOR,
+            width,
+            _padding: [0.; 3],
+        }
+    }
+}
+
+// a range of the index buffer that can be selected
+struct Object {
+    name: &'static str,
+    indices: Range<u32>,
+}
+
+// theta runs down from the north pole and phi around the y axis
+fn push_sphere(
+    verticrs: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    radius: f32,
+    color: [f32; 3],
+) {
+    let base = verticrs.len() as u16;
+    for stack in 0..=SPHERE_STACKS {
+        let (sin_theta, cos_theta) = (stack as f32 / SPHERE_STACKS as f32 * PI).sin_cos();
+        for sector in 0..=SPHERE_SECTORS {
+            let (sin_phi, cos_phi) = (sector as f32 / SPHERE_SECTORS as f32 * 2. * PI).sin_cos();
+            let normal = Vec3::new(sin_theta * cos_phi, cos_theta, sin_theta * sin_phi);
+            verticrs.push(Vertex {
+                pos: (center + normal * radius).to_array(),
+                normal: normal.to_array(),
+                color,
+            });
+        }
+    }
+    push_grid_indices(indices, base, SPHERE_STACKS, SPHERE_SECTORS);
+}
+
+// a ring of `TORUS_RINGS` circles around the y axis, turned by `rotation`
+fn push_torus(
+    verticrs: &mut Vec<Vertex>,
+    indices: &mut Vec<u16>,
+    center: Vec3,
+    rotation: Mat3,
+    radii: (f32, f32),
+    color: [f32; 3],
+) {
+    let (major, minor) = radii;
+    let base = verticrs.len() as u16;
+    for ring in 0..=TORUS_RINGS {
+        let (sin_u, cos_u) = (ring as f32 / TORUS_RINGS as f32 * 2. * PI).sin_cos();
+        let out = Vec3::new(cos_u, 0., sin_u);
+        for side in 0..=TORUS_SIDES {
+            let (sin_v, cos_v) = (side as f32 / TORUS_SIDES as f32 * 2. * PI).sin_cos();
+            let normal = rotation * (out * cos_v + Vec3::Y * sin_v);
+            verticrs.push(Vertex {
+                pos: (center + rotation * (out * major) + normal * minor).to_array(),
+                normal: normal.to_array(),
+                color,
+            });
+        }
+    }
+    push_grid_indices(indices, base, TORUS_RINGS, TORUS_SIDES);
+}
+
+// a square facing up, `size` wide
+fn push_ground(verticrs: &mut Vec<Vertex>, indices: &mut Vec<u16>, y: f32, size: f32) {
+    let base = verticrs.len() as u16;
+    let half = size / 2.;
+    for (x, z) in [(-half, half), (half, half), (-half, -half), (half, -half)] {
+        verticrs.push(Vertex {
+            pos: [x, y, z],
+            normal: [0., 1., 0.],
+            color: [0.6, 0.6, 0.6],
+        });
+    }
+    push_grid_indices(indices, base, 1, 1);
+}
+
+// two triangles per quad of a (rows + 1) x (columns + 1) grid of vertices starting at `base`,
+// counter-clockwise seen from outside for the meshes above
+fn push_grid_indices(indices: &mut Vec<u16>, base: u16, rows: u32, columns: u32) {
+    for row in 0..rows {
+        for column in 0..columns {
+            let top = base + (row * (columns + 1) + column) as u16;
+            let bottom = top + columns as u16 + 1;
+            indices.extend_from_slice(&[top, top + 1, bottom, top + 1, bottom + 1, bottom]);
+        }
+    }
+}
+
+// a sphere, a torus and a smaller sphere partly behind it, on the ground. the ground can't be
+// selected, it's everything outside `objects`
+fn create_scene() -> (Vec<Vertex>, Vec<u16>, Vec<Object>) {
+    let mut verticrs = Vec::new();
+    let mut indices = Vec::new();
+    let mut objects = Vec::new();
+    let mut push_object = |name, push: &dyn Fn(&mut Vec<Vertex>, &mut Vec<u16>)| {
+        let start = indices.len() as u32;
+        push(&mut verticrs, &mut indices);
+        objects.push(Object {
+            name,
+            indices: start..indices.len() as u32,
+        });
+    };
+    push_object("sphere", &|v, i| {
+        push_sphere(v, i, Vec3::new(-1.6, 0., 0.), 0.8, [0.9, 0.35, 0.3])
+    });
+    push_object("torus", &|v, i| {
+        push_torus(
+            v,
+            i,
+            Vec3::new(0.4, 0., 0.3),
+            Mat3::from_rotation_x(1.1),
+            (0.7, 0.28),
+            [0.3, 0.6, 0.9],
+        )
+    });
+    push_object("small sphere", &|v, i| {
+        push_sphere(v, i, Vec3::new(1.2, -0.4, -1.), 0.6, [0.4, 0.8, 0.4])
+    });
+    push_ground(&mut verticrs, &mut indices, -1., 8.);
+    (verticrs, indices, objects)
+}
+
+// the chapter's own depth buffer, with room for the stencil
+fn create_depth_stencil(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> wgpu::TextureView {
+    device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth stencil texture"),
+            size: wgpu::Extent3d {
+                width: config.width,
+                height: config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_STENCIL_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        })
+        .create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+pub struct Outline {
+    scene_pipeline: wgpu::RenderPipeline,
+    outline_pipeline: wgpu::RenderPipeline,
+    xray_outline_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    objects: Vec<Object>,
+    depth_stencil: wgpu::TextureView,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    outline_buffer: wgpu::Buffer,
+    uniform_bindgroup: wgpu::BindGroup,
+    selected: usize,
+    xray: bool,
+    width: f32,
+}
+
+impl Outline {
+    fn title(&self) -> String {
+        format!(
+            "outline, {} selected{}",
+            self.objects[self.selected].name,
+            if self.xray { ", x-ray" } else { "" }
+        )
+    }
+}
+
+impl framework::App for Outline {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.4,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 6., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let width = 0.04;
+        let outline_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Outline Buffer"),
+            contents: bytemuck::bytes_of(&OutlineUniform::new(width)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let uniform_entry = |binding, visibility, size: usize| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(size as u64),
+            },
+            count: None,
+        };
+        let uniform_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("uniform bindgroup layout"),
+                entries: &[
+                    uniform_entry(
+                        0,
+                        wgpu::ShaderStages::VERTEX,
+                        std::mem::size_of::<CameraUniform>(),
+                    ),
+                    uniform_entry(
+                        1,
+                        wgpu::ShaderStages::VERTEX_FRAGMENT,
+                        std::mem::size_of::<OutlineUniform>(),
+                    ),
+                ],
+            });
+
+        let uniform_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("uniform bind group"),
+            layout: &uniform_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: camera_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: outline_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let (verticrs, indices, objects) = create_scene();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&uniform_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let create_pipeline =
+            |label, vs_entry_point, fs_entry_point, depth_write_enabled, depth_compare, stencil| {
+                device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                    label: Some(label),
+                    layout: Some(&pipeline_layout),
+                    vertex: wgpu::VertexState {
+                        module: &shader,
+                        entry_point: vs_entry_point,
+                        buffers: &[Vertex::desc()],
+                    },
+                    fragment: Some(wgpu::FragmentState {
+                        module: &shader,
+                        entry_point: fs_entry_point,
+                        targets: &[Some(config.format.into())],
+                    }),
+                    primitive: wgpu::PrimitiveState {
+                        cull_mode: Some(wgpu::Face::Back),
+                        ..Default::default()
+                    },
+                    depth_stencil: Some(wgpu::DepthStencilState {
+                        format: DEPTH_STENCIL_FORMAT,
+                        depth_write_enabled,
+                        depth_compare,
+                        stencil,
+                        bias: wgpu::DepthBiasState::default(),
+                    }),
+                    multisample: wgpu::MultisampleState::default(),
+                    multiview: None,
+                })
+            };
+
+        // every fragment writes the reference, also those behind something: the selected
+        // object's whole silhouette ends up in the stencil, hidden parts too
+        let write_reference = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Always,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Replace,
+            pass_op: wgpu::StencilOperation::Replace,
+        };
+        let scene_pipeline = create_pipeline(
+            "scene",
+            "vs_main",
+            "fs_main",
+            true,
+            wgpu::CompareFunction::Less,
+            wgpu::StencilState {
+                front: write_reference,
+                back: write_reference,
+                read_mask: 0xff,
+                write_mask: 0xff,
+            },
+        );
+
+        // only where the stencil isn't the reference, outside the selected object. the outline
+        // leaves the stencil and the depth as they are
+        let outside_reference = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::NotEqual,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Keep,
+        };
+        let outline_stencil = wgpu::StencilState {
+            front: outside_reference,
+            back: outside_reference,
+            read_mask: 0xff,
+            write_mask: 0,
+        };
+        let outline_pipeline = create_pipeline(
+            "outline",
+            "vs_outline",
+            "fs_outline",
+            false,
+            wgpu::CompareFunction::Less,
+            outline_stencil.clone(),
+        );
+        // without the depth test the outline shows through whatever is in front
+        let xray_outline_pipeline = create_pipeline(
+            "x-ray outline",
+            "vs_outline",
+            "fs_outline",
+            false,
+            wgpu::CompareFunction::Always,
+            outline_stencil,
+        );
+
+        info!("press tab to select the next object, x to draw the outline through what's in front");
+        info!("press up / down for a wider or thinner outline");
+
+        Outline {
+            scene_pipeline,
+            outline_pipeline,
+            xray_outline_pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            objects,
+            depth_stencil: create_depth_stencil(device, config),
+            camera,
+            camera_controller,
+            camera_buffer,
+            outline_buffer,
+            uniform_bindgroup,
+            selected: 0,
+            xray: false,
+            width,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+        self.depth_stencil = create_depth_stencil(device, config);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::Tab => self.selected = (self.selected + 1) % self.objects.len(),
+                VirtualKeyCode::X => self.xray = !self.xray,
+                VirtualKeyCode::Up => {
+                    self.width = (self.width + OUTLINE_WIDTH_STEP).min(MAX_OUTLINE_WIDTH)
+                }
+                VirtualKeyCode::Down => {
+                    self.width = (self.width - OUTLINE_WIDTH_STEP).max(OUTLINE_WIDTH_STEP)
+                }
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller.update_camera(&mut self.camera, 0.);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+        watchdog::write_buffer(
+            queue,
+            &self.outline_buffer,
+            0,
+            bytemuck::bytes_of(&OutlineUniform::new(self.width)),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: None,
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: frame.view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color {
+                            r: 0.85,
+                            g: 0.88,
+                            b: 0.92,
+                            a: 1.,
+                        }),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_stencil,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: true,
+                    }),
+                    // nothing is selected until the scene says so
+                    stencil_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0),
+                        store: true,
+                    }),
+                }),
+            });
+
+            rpass.set_bind_group(0, &self.uniform_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+            // everything else first, writing 0, then the selected object writing 1. in the other
+            // order whatever is in front of it would write its 0 over the 1
+            let selected = &self.objects[self.selected];
+            rpass.set_pipeline(&self.scene_pipeline);
+            rpass.set_stencil_reference(0);
+            rpass.draw_indexed(0..selected.indices.start, 0, 0..1);
+            rpass.draw_indexed(selected.indices.end..self.num_indices, 0, 0..1);
+            rpass.set_stencil_reference(SELECTED);
+            rpass.draw_indexed(selected.indices.clone(), 0, 0..1);
+
+            // the bigger copy, the stencil test cuts the object itself out of it
+            rpass.set_pipeline(if self.xray {
+                &self.xray_outline_pipeline
+            } else {
+                &self.outline_pipeline
+            });
+            rpass.draw_indexed(selected.indices.clone(), 0, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t50_outline::Outline>(t50_outline::TITLE);
+}
@@ -0,0 +1,62 @@
+// vs_main / fs_main draw the scene lit, and mark the selected object's pixels in the stencil.
+// vs_outline / fs_outline draw the selected object again, pushed out along its normals, in one
+// flat color. the stencil test throws away what covers the object itself, the rim is left
+
+// matches `CameraUniform` in lib.rs
+struct Camera {
+    view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+
+// matches `OutlineUniform` in lib.rs
+struct Outline {
+    color: vec4<f32>,
+    // how far the copy is pushed out, in world units
+    width: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+@group(0) @binding(1)
+var<uniform> outline: Outline;
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.normal = input.normal;
+    fragInput.color = input.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let sun = normalize(vec3<f32>(0.4, 1.0, 0.6));
+    let diffuse = max(dot(normalize(input.normal), sun), 0.0);
+    return vec4<f32>(input.color * (0.2 + 0.8 * diffuse), 1.0);
+}
+
+// along the normal instead of scaled about the center, the rim is as wide all around and a
+// torus gets one on the inside of its hole too
+@vertex
+fn vs_outline(input: VertexInput) -> @builtin(position) vec4<f32> {
+    let pos = input.pos + normalize(input.normal) * outline.width;
+    return camera.view_proj * vec4<f32>(pos, 1.0);
+}
+
+@fragment
+fn fs_outline() -> @location(0) vec4<f32> {
+    return outline.color;
+}
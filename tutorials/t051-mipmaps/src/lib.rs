@@ -0,0 +1,349 @@
+// mipmaps: a texture seen from far away covers many texels per pixel, and a sampler reading
+// only the nearest four of them picks a different few every frame the camera moves, the ground
+// shimmers. mips are the texture shrunk by half again and again, down to 1x1, and the sampler
+// reads the level whose texels are about a pixel big instead.
+//
+// both halves of the window show the same checkered ground gliding past:
+// - left, a texture with only level 0, what `Texture::from_image` makes
+// - right, the full chain from `Texture::from_image_mipmapped`, each level drawn on the gpu from
+//   the one above by `framework::mipmap::MipmapGenerator`
+//
+// the right sampler is trilinear, it blends the two nearest levels too. n switches it to the
+// nearest level only, the seams where one level hands over to the next show up as lines across
+// the ground. space stops and starts the camera
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraBinding},
+    mipmap::MipmapGenerator,
+    texture::Texture,
+};
+use glam::Vec3;
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "mipmaps, left none, right trilinear";
+
+const TEXTURE_SIZE: u32 = 512;
+// how many world units one copy of the texture covers
+const TILE: f32 = 2.;
+const GROUND_SIZE: f32 = 200.;
+const EYE_HEIGHT: f32 = 1.2;
+// world units per second
+const SPEED: f32 = 0.5;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coord: [f32; 2],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2];
+
+    fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// one quad, the texture repeats every `TILE` units across it
+fn create_ground() -> (Vec<Vertex>, Vec<u16>) {
+    let half = GROUND_SIZE / 2.;
+    let verticrs = [(-half, half), (half, half), (half, -half), (-half, -half)]
+        .iter()
+        .map(|&(x, z)| Vertex {
+            pos: [x, 0., z],
+            tex_coord: [x / TILE, z / TILE],
+        })
+        .collect();
+    (verticrs, vec![0, 1, 2, 0, 2, 3])
+}
+
+// small squares with thin lines between every few of them, fine detail that aliases quickly
+fn create_checker() -> image::DynamicImage {
+    image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(
+        TEXTURE_SIZE,
+        TEXTURE_SIZE,
+        |x, y| {
+            if x % 64 < 2 || y % 64 < 2 {
+                image::Rgba([240, 150, 40, 255])
+            } else if (x / 16 + y / 16) % 2 == 0 {
+                image::Rgba([230, 230, 235, 255])
+            } else {
+                image::Rgba([40, 50, 70, 255])
+            }
+        },
+    ))
+}
+
+// repeating, linear inside and between levels unless `mipmap_filter` says otherwise
+fn create_sampler(device: &wgpu::Device, mipmap_filter: wgpu::FilterMode) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("ground sampler"),
+        address_mode_u: wgpu::AddressMode::Repeat,
+        address_mode_v: wgpu::AddressMode::Repeat,
+        address_mode_w: wgpu::AddressMode::Repeat,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+        mipmap_filter,
+        ..Default::default()
+    })
+}
+
+pub struct Mipmaps {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    camera: Camera,
+    camera_binding: CameraBinding,
+    plain_bindgroup: wgpu::BindGroup,
+    trilinear_bindgroup: wgpu::BindGroup,
+    nearest_mip_bindgroup: wgpu::BindGroup,
+    trilinear: bool,
+    moving: bool,
+    // how far the camera has come, wrapped at `TILE` so the ground looks the same forever
+    travelled: f32,
+    size: (u32, u32),
+}
+
+impl Mipmaps {
+    fn title(&self) -> String {
+        format!(
+            "mipmaps, left none, right {}",
+            if self.trilinear {
+                "trilinear"
+            } else {
+                "nearest level"
+            }
+        )
+    }
+}
+
+impl framework::App for Mipmaps {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // camera, low over the ground looking towards the horizon. each half of the window
+        // gets half its width
+        let camera = Camera::new(
+            Vec3::new(0., EYE_HEIGHT, 0.),
+            0.,
+            -0.1,
+            config.width as f32 / 2. / config.height as f32,
+        );
+        let camera_binding = CameraBinding::new(device, &camera);
+
+        // the same image twice, with and without mips
+        let checker = create_checker();
+        let plain = Texture::from_image(device, queue, &checker, "plain checker");
+        let mut mipmaps = MipmapGenerator::new(device);
+        let mipmapped = Texture::from_image_mipmapped(
+            device,
+            queue,
+            &mut mipmaps,
+            &checker,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            "mipmapped checker",
+        );
+
+        let texture_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("texture bindgroup layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        // the textures' own samplers clamp, the ground needs them to repeat
+        let trilinear = create_sampler(device, wgpu::FilterMode::Linear);
+        let nearest_mip = create_sampler(device, wgpu::FilterMode::Nearest);
+        let create_bindgroup = |texture: &Texture, sampler: &wgpu::Sampler| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("texture bind group"),
+                layout: &texture_bindgroup_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&texture.view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(sampler),
+                    },
+                ],
+            })
+        };
+        // with a single level there is nothing to blend, trilinear is bilinear here
+        let plain_bindgroup = create_bindgroup(&plain, &trilinear);
+        let trilinear_bindgroup = create_bindgroup(&mipmapped, &trilinear);
+        let nearest_mip_bindgroup = create_bindgroup(&mipmapped, &nearest_mip);
+
+        let (verticrs, indices) = create_ground();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_binding.layout, &texture_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        info!("press n to switch the right half between trilinear and the nearest level");
+        info!("press space to stop or start the camera");
+
+        Mipmaps {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            camera,
+            camera_binding,
+            plain_bindgroup,
+            trilinear_bindgroup,
+            nearest_mip_bindgroup,
+            trilinear: true,
+            moving: true,
+            travelled: 0.,
+            size: (config.width, config.height),
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width / 2, config.height);
+        self.size = (config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::N => self.trilinear = !self.trilinear,
+                VirtualKeyCode::Space => self.moving = !self.moving,
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.moving {
+            self.travelled = (self.travelled + frame.delta * SPEED) % TILE;
+        }
+        self.camera.position.z = -self.travelled;
+        self.camera_binding.update(queue, &self.camera);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.6,
+                    g: 0.75,
+                    b: 0.9,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.camera_binding.bind_group, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+
+            // a pixel of sky between the halves
+            let (width, height) = (self.size.0 as f32, self.size.1 as f32);
+            let half = (width / 2. - 1.).max(1.);
+            let right = if self.trilinear {
+                &self.trilinear_bindgroup
+            } else {
+                &self.nearest_mip_bindgroup
+            };
+            for (x, bind_group) in [(0., &self.plain_bindgroup), (width - half, right)] {
+                rpass.set_viewport(x, 0., half, height, 0., 1.);
+                rpass.set_bind_group(1, bind_group, &[]);
+                rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
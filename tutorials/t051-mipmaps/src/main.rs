@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t51_mipmaps::Mipmaps>(t51_mipmaps::TITLE);
+}
@@ -0,0 +1,32 @@
+// a textured ground plane, drawn once per half of the window with another texture bound
+
+@group(0) @binding(0)
+var<uniform> view_proj: mat4x4<f32>;
+
+@group(1) @binding(0)
+var t_ground: texture_2d<f32>;
+@group(1) @binding(1)
+var s_ground: sampler;
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) tex_coord: vec2<f32>,
+};
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.tex_coord = input.tex_coord;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    return textureSample(t_ground, s_ground, input.tex_coord);
+}
@@ -0,0 +1,433 @@
+// texture arrays: one texture with several images of the same size stacked as layers. it's
+// bound once, like any texture, and the shader picks a layer with one more coordinate, so
+// things drawn with different images don't need a bind group each, nor an atlas with its
+// bleeding edges and padded uvs.
+//
+// the window is a tile map scrolling past. there are five terrain images: water, sand, grass,
+// stone and snow. they're uploaded into the layers of one `D2` texture with
+// `depth_or_array_layers: 5`, viewed as `D2Array` and read as `texture_2d_array` in the
+// shader. every tile is an instance of the same quad, and its layer comes in with its offset
+// as a per instance vertex attribute. moving the map only rewrites the instance buffer, the
+// texture and its bind group stay as they are. space stops and starts the scrolling
+
+use bytemuck::{Pod, Zeroable};
+use framework::{vertex_layout, watchdog};
+use tracing::info;
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "texture array";
+
+// texels a side, every layer has to be the same size
+const TILE_SIZE: u32 = 32;
+const LAYERS: [&str; 5] = ["water", "sand", "grass", "stone", "snow"];
+const COLUMNS: usize = 16;
+const ROWS: usize = 12;
+// tiles per second
+const SPEED: f32 = 1.5;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 2],
+    tex_coord: [f32; 2],
+}
+
+// the shader's `VertexInput` and `Tile` are generated from these, see `vertex_layout`
+framework::vertex_layout! {
+    Vertex as "VertexInput", Vertex {
+        0 => pos: Float32x2,
+        1 => tex_coord: Float32x2,
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Tile {
+    // of the tile's bottom left corner, in clip space
+    offset: [f32; 2],
+    layer: u32,
+}
+
+framework::vertex_layout! {
+    Tile as "Tile", Instance {
+        2 => offset: Float32x2,
+        3 => layer: Uint32,
+    }
+}
+
+// one tile in clip space with its corner at the origin, the instances move it into place
+fn create_quad() -> (Vec<Vertex>, Vec<u16>) {
+    let (width, height) = (2. / COLUMNS as f32, 2. / ROWS as f32);
+    let verticrs = vec![
+        Vertex {
+            pos: [0., 0.],
+            tex_coord: [0., 1.],
+        },
+        Vertex {
+            pos: [width, 0.],
+            tex_coord: [1., 1.],
+        },
+        Vertex {
+            pos: [width, height],
+            tex_coord: [1., 0.],
+        },
+        Vertex {
+            pos: [0., height],
+            tex_coord: [0., 0.],
+        },
+    ];
+    (verticrs, vec![0, 1, 2, 0, 2, 3])
+}
+
+// a repeatable 0..1 value for every texel, the grain the images are drawn with
+fn hash(x: u32, y: u32, seed: u32) -> f32 {
+    let mut h = x
+        .wrapping_mul(374_761_393)
+        .wrapping_add(y.wrapping_mul(668_265_263))
+        .wrapping_add(seed.wrapping_mul(2_246_822_519));
+    h = (h ^ (h >> 13)).wrapping_mul(1_274_126_177);
+    (h ^ (h >> 16)) as f32 / u32::MAX as f32
+}
+
+// a base color with some noise, darker or lighter by up to `amount`
+fn speckle(base: [u8; 3], amount: f32, x: u32, y: u32, seed: u32) -> image::Rgba<u8> {
+    let shade = 1. + (hash(x, y, seed) - 0.5) * 2. * amount;
+    let channel = |c: u8| (c as f32 * shade).clamp(0., 255.) as u8;
+    image::Rgba([channel(base[0]), channel(base[1]), channel(base[2]), 255])
+}
+
+// the five terrain images, in `LAYERS` order
+fn create_layers() -> Vec<image::RgbaImage> {
+    let water = image::RgbaImage::from_fn(TILE_SIZE, TILE_SIZE, |x, y| {
+        // light wave crests across the tile
+        if (x + 2 * y) % 11 == 0 && y % 8 < 3 {
+            image::Rgba([140, 190, 230, 255])
+        } else {
+            speckle([40, 90, 170], 0.08, x, y, 1)
+        }
+    });
+    let sand = image::RgbaImage::from_fn(TILE_SIZE, TILE_SIZE, |x, y| {
+        speckle([220, 200, 140], 0.12, x, y, 2)
+    });
+    let grass = image::RgbaImage::from_fn(TILE_SIZE, TILE_SIZE, |x, y| {
+        // a few darker blades
+        if hash(x, y, 3) > 0.85 {
+            image::Rgba([40, 95, 35, 255])
+        } else {
+            speckle([75, 145, 60], 0.1, x, y, 4)
+        }
+    });
+    let stone = image::RgbaImage::from_fn(TILE_SIZE, TILE_SIZE, |x, y| {
+        // cobbles, every other row shifted by half a stone
+        let shift = if (y / 8) % 2 == 0 { 0 } else { 4 };
+        if y % 8 == 0 || (x + shift) % 8 == 0 {
+            image::Rgba([70, 70, 75, 255])
+        } else {
+            speckle([135, 135, 140], 0.1, x, y, 5)
+        }
+    });
+    let snow = image::RgbaImage::from_fn(TILE_SIZE, TILE_SIZE, |x, y| {
+        speckle([235, 240, 250], 0.04, x, y, 6)
+    });
+    vec![water, sand, grass, stone, snow]
+}
+
+// a `D2` texture with a layer per image, each written on its own with the layer as the z of
+// its origin
+fn create_texture_array(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    layers: &[image::RgbaImage],
+) -> wgpu::Texture {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("terrain array"),
+        size: wgpu::Extent3d {
+            width: TILE_SIZE,
+            height: TILE_SIZE,
+            depth_or_array_layers: layers.len() as u32,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+    });
+
+    for (layer, image) in layers.iter().enumerate() {
+        assert_eq!(
+            image.dimensions(),
+            (TILE_SIZE, TILE_SIZE),
+            "every layer of an array is the same size"
+        );
+        watchdog::write_texture(
+            queue,
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer as u32,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            image,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(4 * TILE_SIZE),
+                rows_per_image: std::num::NonZeroU32::new(TILE_SIZE),
+            },
+            wgpu::Extent3d {
+                width: TILE_SIZE,
+                height: TILE_SIZE,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+    texture
+}
+
+// the terrain at a tile of the endless map, rolling hills from a few waves
+fn terrain_layer(column: i32, row: i32) -> u32 {
+    let (x, y) = (column as f32, row as f32);
+    let height = (x * 0.31).sin() * (y * 0.42).cos()
+        + 0.6 * (x * 0.13 + y * 0.21).sin()
+        + 0.3 * (x * 0.7 - y * 0.5).cos();
+    match height {
+        h if h < -0.5 => 0,
+        h if h < -0.2 => 1,
+        h if h < 0.6 => 2,
+        h if h < 1.1 => 3,
+        _ => 4,
+    }
+}
+
+// the tiles in view when the map has scrolled `scroll` tiles to the left, one more column
+// than fits so the right edge is never empty
+fn create_tiles(scroll: f32) -> Vec<Tile> {
+    let (width, height) = (2. / COLUMNS as f32, 2. / ROWS as f32);
+    let first = scroll.floor() as i32;
+    let shift = scroll.fract() * width;
+    (0..ROWS)
+        .flat_map(|row| {
+            (0..=COLUMNS).map(move |column| Tile {
+                offset: [
+                    -1. + column as f32 * width - shift,
+                    -1. + row as f32 * height,
+                ],
+                layer: terrain_layer(first + column as i32, row as i32),
+            })
+        })
+        .collect()
+}
+
+pub struct TextureArray {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    tile_buffer: wgpu::Buffer,
+    num_tiles: u32,
+    bind_group: wgpu::BindGroup,
+    scrolling: bool,
+    scroll: f32,
+}
+
+impl TextureArray {
+    fn title(&self) -> String {
+        if self.scrolling {
+            TITLE.to_string()
+        } else {
+            format!("{}, paused", TITLE)
+        }
+    }
+}
+
+impl framework::App for TextureArray {
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // texture, and a view of all its layers
+        let texture = create_texture_array(device, queue, &create_layers());
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("terrain array view"),
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+        // nearest keeps the texels sharp, clamp keeps a tile's edge from reading the other side
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("terrain sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("terrain bindgroup layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("terrain bind group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        // buffers, the tiles are rewritten every frame
+        let (verticrs, indices) = create_quad();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let tiles = create_tiles(0.);
+        let tile_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tile Buffer"),
+            contents: bytemuck::cast_slice(&tiles),
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        });
+
+        // shader
+        let shader = vertex_layout::shader(
+            device,
+            "shader.wgsl",
+            include_str!("shader.wgsl"),
+            &[Vertex::WGSL, Tile::WGSL],
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::buffer_layout(), Tile::buffer_layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        info!("{} layers: {}", LAYERS.len(), LAYERS.join(", "));
+        info!("press space to stop or start the scrolling");
+
+        TextureArray {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            tile_buffer,
+            num_tiles: tiles.len() as u32,
+            bind_group,
+            scrolling: true,
+            scroll: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        _config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Space),
+                    ..
+                },
+            ..
+        } = event
+        {
+            self.scrolling = !self.scrolling;
+            window.set_title(&self.title());
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.scrolling {
+            self.scroll += frame.delta * SPEED;
+        }
+        watchdog::write_buffer(
+            queue,
+            &self.tile_buffer,
+            0,
+            bytemuck::cast_slice(&create_tiles(self.scroll)),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, wgpu::Color::BLACK);
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.bind_group, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_vertex_buffer(1, self.tile_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            // every tile in one draw, whatever its layer
+            rpass.draw_indexed(0..self.num_indices, 0, 0..self.num_tiles);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t52_texture_array::TextureArray>(t52_texture_array::TITLE);
+}
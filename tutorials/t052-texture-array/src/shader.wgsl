@@ -0,0 +1,40 @@
+// a tile map, one instance per tile. every tile reads the same texture array, the instance
+// says which of its layers
+
+// the structs in front of the entry points are generated from `Vertex` and `Tile` in lib.rs
+struct VertexInput {
+    @location(0) pos: vec2<f32>,
+    @location(1) tex_coord: vec2<f32>,
+};
+
+struct Tile {
+    @location(2) offset: vec2<f32>,
+    @location(3) layer: u32,
+};
+
+struct FragInput {
+    @location(0) tex_coord: vec2<f32>,
+    // integers can't be interpolated, every fragment gets the provoking vertex's
+    @location(1) @interpolate(flat) layer: u32,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var t_tiles: texture_2d_array<f32>;
+@group(0) @binding(1)
+var s_tiles: sampler;
+
+@vertex
+fn vs_main(input: VertexInput, tile: Tile) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = vec4<f32>(input.pos + tile.offset, 0.0, 1.0);
+    fragInput.tex_coord = input.tex_coord;
+    fragInput.layer = tile.layer;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    // the layer is one more coordinate, it picks an image and is never blended between two
+    return textureSample(t_tiles, s_tiles, input.tex_coord, i32(input.layer));
+}
@@ -0,0 +1,453 @@
+// samplers: everything about how a texture is read besides the texture itself. the filters
+// say how texels are blended when a pixel falls between them (mag, up close), covers many of
+// them (min, far away), or lands between two mip levels (mipmap). the address modes say what
+// coordinates outside 0..1 read. anisotropy is for surfaces seen at a grazing angle, where a
+// pixel covers a long thin strip of the texture: plain trilinear picks the mip level for the
+// long side and blurs the ground into mush, anisotropic filtering takes up to
+// `anisotropy_clamp` samples along the strip from a sharper level instead.
+//
+// the window looks along a long checkered ground. the texture's first copy lies just in front
+// of the camera, the rest of the ground is what the address mode makes of coordinates past it.
+// - a cycles the anisotropy clamp: 1, 2, 4, 8, 16. webgpu only allows it with all three filters
+//   linear, the others leave it out
+// - f cycles the filters: nearest, bilinear (linear inside a level, nearest level), trilinear
+// - m cycles the address modes: repeat, mirror repeat, clamp to edge, and clamp to border
+//   where the adapter has `ADDRESS_MODE_CLAMP_TO_BORDER`
+//
+// a sampler can't be changed once it's made, every key makes a new one and a new bind group
+// with it
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraBinding},
+    mipmap::MipmapGenerator,
+    texture::Texture,
+    vertex_layout,
+};
+use glam::Vec3;
+use tracing::{info, warn};
+use winit::{
+    event::{ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "sampler, trilinear, 16x anisotropy, repeat";
+
+const TEXTURE_SIZE: u32 = 512;
+// how many world units one copy of the texture covers
+const TILE: f32 = 2.;
+// where the texture's first copy starts, a little ahead of the camera
+const TILE_ORIGIN: (f32, f32) = (-1., -5.);
+const GROUND_SIZE: f32 = 400.;
+const EYE_HEIGHT: f32 = 1.2;
+
+const ANISOTROPY: [u8; 5] = [1, 2, 4, 8, 16];
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    pos: [f32; 3],
+    tex_coord: [f32; 2],
+}
+
+framework::vertex_layout! {
+    Vertex as "VertexInput", Vertex {
+        0 => pos: Float32x3,
+        1 => tex_coord: Float32x2,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Filter {
+    Nearest,
+    Bilinear,
+    Trilinear,
+}
+
+impl Filter {
+    fn next(self) -> Filter {
+        match self {
+            Filter::Nearest => Filter::Bilinear,
+            Filter::Bilinear => Filter::Trilinear,
+            Filter::Trilinear => Filter::Nearest,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Filter::Nearest => "nearest",
+            Filter::Bilinear => "bilinear",
+            Filter::Trilinear => "trilinear",
+        }
+    }
+
+    /// mag, min and mipmap filters
+    fn modes(self) -> (wgpu::FilterMode, wgpu::FilterMode, wgpu::FilterMode) {
+        use wgpu::FilterMode::{Linear, Nearest};
+        match self {
+            Filter::Nearest => (Nearest, Nearest, Nearest),
+            Filter::Bilinear => (Linear, Linear, Nearest),
+            Filter::Trilinear => (Linear, Linear, Linear),
+        }
+    }
+}
+
+fn address_mode_name(mode: wgpu::AddressMode) -> &'static str {
+    match mode {
+        wgpu::AddressMode::Repeat => "repeat",
+        wgpu::AddressMode::MirrorRepeat => "mirror repeat",
+        wgpu::AddressMode::ClampToEdge => "clamp to edge",
+        wgpu::AddressMode::ClampToBorder => "clamp to border",
+    }
+}
+
+// one quad, the texture's first copy at `TILE_ORIGIN`
+fn create_ground() -> (Vec<Vertex>, Vec<u16>) {
+    let half = GROUND_SIZE / 2.;
+    let verticrs = [(-half, half), (half, half), (half, -half), (-half, -half)]
+        .iter()
+        .map(|&(x, z)| Vertex {
+            pos: [x, 0., z],
+            tex_coord: [(x - TILE_ORIGIN.0) / TILE, (z - TILE_ORIGIN.1) / TILE],
+        })
+        .collect();
+    (verticrs, vec![0, 1, 2, 0, 2, 3])
+}
+
+// squares with thin lines running away from the camera, the detail grazing angles smear first.
+// the top left corner is marked, so mirroring and clamping are easy to tell apart
+fn create_checker() -> image::DynamicImage {
+    image::DynamicImage::ImageRgba8(image::RgbaImage::from_fn(
+        TEXTURE_SIZE,
+        TEXTURE_SIZE,
+        |x, y| {
+            if x < 96 && y < 96 {
+                image::Rgba([200, 40, 40, 255])
+            } else if x % 64 < 3 || y % 64 < 3 {
+                image::Rgba([240, 150, 40, 255])
+            } else if (x / 32 + y / 32) % 2 == 0 {
+                image::Rgba([230, 230, 235, 255])
+            } else {
+                image::Rgba([40, 50, 70, 255])
+            }
+        },
+    ))
+}
+
+fn create_sampler(
+    device: &wgpu::Device,
+    filter: Filter,
+    anisotropy: u8,
+    address_mode: wgpu::AddressMode,
+) -> wgpu::Sampler {
+    let (mag_filter, min_filter, mipmap_filter) = filter.modes();
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("ground sampler"),
+        address_mode_u: address_mode,
+        address_mode_v: address_mode,
+        address_mode_w: address_mode,
+        mag_filter,
+        min_filter,
+        mipmap_filter,
+        // only with every filter linear, and only above 1
+        anisotropy_clamp: if filter == Filter::Trilinear {
+            std::num::NonZeroU8::new(anisotropy).filter(|clamp| clamp.get() > 1)
+        } else {
+            None
+        },
+        // what clamp to border reads outside 0..1
+        border_color: (address_mode == wgpu::AddressMode::ClampToBorder)
+            .then_some(wgpu::SamplerBorderColor::OpaqueWhite),
+        ..Default::default()
+    })
+}
+
+pub struct Sampler {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    camera: Camera,
+    camera_binding: CameraBinding,
+    texture: Texture,
+    texture_bindgroup_layout: wgpu::BindGroupLayout,
+    texture_bindgroup: wgpu::BindGroup,
+    filter: Filter,
+    // into `ANISOTROPY`
+    anisotropy: usize,
+    // the ones the device has, into `address_modes`
+    address_modes: Vec<wgpu::AddressMode>,
+    address_mode: usize,
+    // a key changed the settings, the sampler is made again before the next frame
+    dirty: bool,
+}
+
+impl Sampler {
+    fn title(&self) -> String {
+        let anisotropy = ANISOTROPY[self.anisotropy];
+        format!(
+            "sampler, {}, {}x anisotropy{}, {}",
+            self.filter.name(),
+            anisotropy,
+            if self.filter != Filter::Trilinear && anisotropy > 1 {
+                " (off, needs trilinear)"
+            } else {
+                ""
+            },
+            address_mode_name(self.address_modes[self.address_mode])
+        )
+    }
+
+    fn create_bindgroup(&self, device: &wgpu::Device) -> wgpu::BindGroup {
+        let sampler = create_sampler(
+            device,
+            self.filter,
+            ANISOTROPY[self.anisotropy],
+            self.address_modes[self.address_mode],
+        );
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("texture bind group"),
+            layout: &self.texture_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        })
+    }
+}
+
+impl framework::App for Sampler {
+    fn optional_features() -> wgpu::Features {
+        wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Self {
+        // wgpu leaves the clamp out where the adapter can't filter anisotropically
+        if !adapter
+            .get_downlevel_capabilities()
+            .flags
+            .contains(wgpu::DownlevelFlags::ANISOTROPIC_FILTERING)
+        {
+            warn!("the adapter has no anisotropic filtering, a changes nothing");
+        }
+        let mut address_modes = vec![
+            wgpu::AddressMode::Repeat,
+            wgpu::AddressMode::MirrorRepeat,
+            wgpu::AddressMode::ClampToEdge,
+        ];
+        if device
+            .features()
+            .contains(wgpu::Features::ADDRESS_MODE_CLAMP_TO_BORDER)
+        {
+            address_modes.push(wgpu::AddressMode::ClampToBorder);
+        }
+
+        // camera, low over the ground looking towards the horizon
+        let camera = Camera::new(
+            Vec3::new(0., EYE_HEIGHT, 0.),
+            0.,
+            -0.1,
+            config.width as f32 / config.height as f32,
+        );
+        let camera_binding = CameraBinding::new(device, &camera);
+
+        // texture, with mips so the filters have levels to pick from
+        let mut mipmaps = MipmapGenerator::new(device);
+        let texture = Texture::from_image_mipmapped(
+            device,
+            queue,
+            &mut mipmaps,
+            &create_checker(),
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            "checker",
+        );
+
+        let texture_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("texture bindgroup layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+
+        let (verticrs, indices) = create_ground();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&verticrs),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // shader
+        let shader = vertex_layout::shader(
+            device,
+            "shader.wgsl",
+            include_str!("shader.wgsl"),
+            &[Vertex::WGSL],
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_binding.layout, &texture_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: None,
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::buffer_layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        info!("press a to cycle the anisotropy clamp");
+        info!("press f to cycle nearest, bilinear and trilinear filtering");
+        info!("press m to cycle the address modes");
+
+        // the bind group needs the rest of the settings, it starts as a stand-in
+        let texture_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("texture bind group"),
+            layout: &texture_bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&texture.sampler),
+                },
+            ],
+        });
+        let mut sampler = Sampler {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            camera,
+            camera_binding,
+            texture,
+            texture_bindgroup_layout,
+            texture_bindgroup,
+            filter: Filter::Trilinear,
+            anisotropy: ANISOTROPY.len() - 1,
+            address_modes,
+            address_mode: 0,
+            dirty: false,
+        };
+        sampler.texture_bindgroup = sampler.create_bindgroup(device);
+        sampler
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::A => self.anisotropy = (self.anisotropy + 1) % ANISOTROPY.len(),
+                VirtualKeyCode::F => self.filter = self.filter.next(),
+                VirtualKeyCode::M => {
+                    self.address_mode = (self.address_mode + 1) % self.address_modes.len()
+                }
+                _ => return,
+            }
+            self.dirty = true;
+            window.set_title(&self.title());
+        }
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if self.dirty {
+            self.texture_bindgroup = self.create_bindgroup(device);
+            self.dirty = false;
+        }
+        self.camera_binding.update(queue, &self.camera);
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.6,
+                    g: 0.75,
+                    b: 0.9,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.camera_binding.bind_group, &[]);
+            rpass.set_bind_group(1, &self.texture_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t53_sampler::Sampler>(t53_sampler::TITLE);
+}
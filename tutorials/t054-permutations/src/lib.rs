@@ -0,0 +1,336 @@
+// shader permutations: one wgsl file with optional features, compiled once for each
+// combination a material asks for. the features are `#ifdef` blocks in shader.wgsl, a set of
+// flags picks which are on, and framework::permutation preprocesses and compiles the file per
+// set of flags and keeps the pipeline built from it. three features would be eight hand-written
+// files otherwise, each fix made eight times.
+//
+// eight spheres, one per combination. the index of a sphere is its flags: top row left to right
+// plain, textured, normal mapped, both, the bottom row the same with a specular highlight. all
+// eight pipelines are built behind the warm-up bar before the first frame. drag to orbit.
+
+use bitflags::bitflags;
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    permutation::{Permutation, Permutations},
+    primitives::{self, Mesh},
+    texture::DepthTexture,
+    warmup::Progress,
+    watchdog,
+};
+use glam::Vec3;
+use winit::{
+    event::{DeviceEvent, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "shader permutations";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const COLUMNS: u32 = 4;
+const SPACING: f32 = 2.6;
+
+bitflags! {
+    /// the `#ifdef`s of shader.wgsl
+    struct Features: u32 {
+        const TEXTURED = 1 << 0;
+        const NORMAL_MAP = 1 << 1;
+        const SPECULAR = 1 << 2;
+    }
+}
+
+impl Permutation for Features {
+    fn defines(&self) -> Vec<&'static str> {
+        [
+            (Features::TEXTURED, "TEXTURED"),
+            (Features::NORMAL_MAP, "NORMAL_MAP"),
+            (Features::SPECULAR, "SPECULAR"),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| self.contains(*flag))
+        .map(|(_, name)| name)
+        .collect()
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Instance {
+    offset: [f32; 3],
+}
+
+framework::vertex_layout! {
+    Instance as "Instance", Instance {
+        3 => offset: Float32x3,
+    }
+}
+
+// matches `Camera` in shader.wgsl, the eye position is needed for the specular term
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+// every combination of the flags, in the order of their bits
+fn all_features() -> Vec<Features> {
+    (0..=Features::all().bits())
+        .map(Features::from_bits_truncate)
+        .collect()
+}
+
+// instance i is the sphere drawn with the flags whose bits are i
+fn create_instances() -> Vec<Instance> {
+    let count = all_features().len() as u32;
+    let rows = count / COLUMNS;
+    (0..count)
+        .map(|i| {
+            let (row, column) = (i / COLUMNS, i % COLUMNS);
+            Instance {
+                offset: [
+                    (column as f32 - (COLUMNS - 1) as f32 / 2.) * SPACING,
+                    ((rows - 1) as f32 / 2. - row as f32) * SPACING,
+                    0.,
+                ],
+            }
+        })
+        .collect()
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("permutation"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[
+                primitives::Vertex::buffer_layout(),
+                Instance::buffer_layout(),
+            ],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(format.into())],
+        }),
+        primitive: wgpu::PrimitiveState {
+            cull_mode: Some(wgpu::Face::Back),
+            ..Default::default()
+        },
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: DepthTexture::FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+pub struct ShaderPermutations {
+    shaders: Permutations<Features>,
+    pipeline_layout: wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    instance_buffer: wgpu::Buffer,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+}
+
+impl framework::App for ShaderPermutations {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            0.,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 11., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // one sphere, drawn once per permutation
+        let sphere = Mesh::sphere(48, 24);
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&sphere.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&sphere.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: bytemuck::cast_slice(&create_instances()),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+
+        // shaders, compiled per permutation in `warmup`
+        let shaders = Permutations::new(
+            "shader.wgsl",
+            include_str!("shader.wgsl"),
+            &[primitives::Vertex::WGSL, Instance::WGSL],
+            all_features(),
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        ShaderPermutations {
+            shaders,
+            pipeline_layout,
+            format: config.format,
+            vertex_buffer,
+            index_buffer,
+            num_indices: sphere.indices.len() as u32,
+            instance_buffer,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, _window: &Window, event: WindowEvent) {
+        self.camera_controller.process_window_event(&event);
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn warmup(&mut self, device: &wgpu::Device) -> Option<Progress> {
+        let (layout, format) = (&self.pipeline_layout, self.format);
+        self.shaders.warmup(device, |shader, _| {
+            create_pipeline(device, layout, shader, format)
+        })
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller.update_camera(&mut self.camera, 0.);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        // normally built in warmup already, `built` below needs them all there
+        let (layout, format) = (&self.pipeline_layout, self.format);
+        for features in all_features() {
+            self.shaders.get(device, features, |shader, _| {
+                create_pipeline(device, layout, shader, format)
+            });
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.05,
+                    g: 0.05,
+                    b: 0.07,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            for (i, features) in all_features().into_iter().enumerate() {
+                let pipeline = self
+                    .shaders
+                    .built(features)
+                    .expect("Fail to build pipeline");
+                rpass.set_pipeline(pipeline);
+                rpass.draw_indexed(0..self.num_indices, 0, i as u32..i as u32 + 1);
+            }
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
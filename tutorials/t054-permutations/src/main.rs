@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t54_permutations::ShaderPermutations>(t54_permutations::TITLE);
+}
@@ -0,0 +1,83 @@
+// one file for every sphere. the `#ifdef` blocks are the optional features, framework::permutation
+// compiles the file once per combination the chapter asks for, and the build checks all eight
+
+// generated from `primitives::Vertex` and `Instance` in lib.rs
+struct VertexInput {
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+struct Instance {
+    @location(3) offset: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// matches `CameraUniform` in lib.rs
+struct Camera {
+    view_proj: mat4x4<f32>,
+    position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@vertex
+fn vs_main(input: VertexInput, instance: Instance) -> FragInput {
+    let world_pos = input.pos + instance.offset;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = input.normal;
+    fragInput.uv = input.uv;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let light_dir = normalize(vec3<f32>(0.5, 0.8, 0.6));
+    var normal = normalize(input.normal);
+    var albedo = vec3<f32>(0.8, 0.35, 0.25);
+
+#ifdef TEXTURED
+    // a checker over the uv square, 16 cells around and 8 from pole to pole
+    let cell = floor(input.uv * vec2<f32>(16.0, 8.0));
+    if ((cell.x + cell.y) % 2.0 < 0.5) {
+        albedo = vec3<f32>(0.9, 0.85, 0.7);
+    }
+#endif
+
+#ifdef NORMAL_MAP
+    // bumps from a height field over the uv square. its slope tilts the normal along the
+    // surface, around the sphere and from pole to pole
+    var tangent = cross(vec3<f32>(0.0, 1.0, 0.0), normal);
+    if (length(tangent) < 0.001) {
+        tangent = vec3<f32>(1.0, 0.0, 0.0);
+    }
+    tangent = normalize(tangent);
+    let bitangent = cross(normal, tangent);
+    let frequency = vec2<f32>(24.0, 12.0) * 3.14159;
+    let waves = input.uv * frequency;
+    let slope = 0.02 * frequency * vec2<f32>(cos(waves.x) * sin(waves.y), sin(waves.x) * cos(waves.y));
+    normal = normalize(normal - tangent * slope.x - bitangent * slope.y);
+#endif
+
+    let diffuse = max(dot(normal, light_dir), 0.0);
+    var color = albedo * (0.1 + diffuse);
+
+#ifdef SPECULAR
+    // blinn-phong, the highlight sits where the normal points halfway between light and eye
+    let view_dir = normalize(camera.position.xyz - input.world_pos);
+    let half_dir = normalize(light_dir + view_dir);
+    color = color + vec3<f32>(0.6) * pow(max(dot(normal, half_dir), 0.0), 64.0);
+#endif
+
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,406 @@
+// the terrain's vertices from a compute pass, re-run every frame as the terrain scrolls. each
+// invocation evaluates layered simplex noise at its grid point and writes position and normal
+// into a storage buffer, which the render pass then binds as its vertex buffer. the index
+// buffer never changes, it's made once on the cpu, and nothing goes back to the cpu.
+//
+// without compute, the webgl2 profile, the same noise runs on the cpu instead and the vertices
+// are uploaded every frame.
+
+use bytemuck::{Pod, Zeroable};
+use framework::{compat, watchdog};
+use glam::{Vec2, Vec3};
+use tracing::info;
+use wgpu::include_wgsl;
+
+use wgpu::util::DeviceExt;
+
+// matches the consts in heightfield.wgsl
+const GRID: u32 = 256;
+const SIZE: f32 = 48.;
+const HEIGHT: f32 = 7.;
+const FREQUENCY: f32 = 0.045;
+const WATER: f32 = -0.6;
+// matches `@workgroup_size` in heightfield.wgsl, per side
+const WORKGROUP: u32 = 8;
+// of `Vertex` in heightfield.wgsl, vec3s are 16 byte aligned in a storage buffer
+const VERTEX_SIZE: u64 = 32;
+
+/// the most octaves worth adding, the next one is finer than the grid
+pub const MAX_OCTAVES: u32 = 7;
+
+// the cpu side of heightfield.wgsl, the path without compute and what the tests check the gpu
+// against
+
+fn mod289(x: f32) -> f32 {
+    x - (x * (1. / 289.)).floor() * 289.
+}
+
+fn permute(x: f32) -> f32 {
+    mod289((x * 34. + 1.) * x)
+}
+
+// `simplex` in heightfield.wgsl, a component at a time
+fn simplex(v: Vec2) -> f32 {
+    const C: [f32; 4] = [0.21132487, 0.36602542, -0.57735026, 0.024390243];
+
+    let i = (v + v.dot(Vec2::splat(C[1]))).floor();
+    let x0 = v - i + i.dot(Vec2::splat(C[0]));
+    let i1 = if x0.x > x0.y {
+        Vec2::new(1., 0.)
+    } else {
+        Vec2::new(0., 1.)
+    };
+    let x1 = x0 + Vec2::splat(C[0]) - i1;
+    let x2 = x0 + Vec2::splat(C[2]);
+
+    let i = Vec2::new(mod289(i.x), mod289(i.y));
+    let corners = [Vec2::ZERO, i1, Vec2::ONE];
+    let offsets = [x0, x1, x2];
+
+    let mut sum = 0.;
+    for (corner, x) in corners.into_iter().zip(offsets) {
+        let p = permute(permute(i.y + corner.y) + i.x + corner.x);
+        let m = (0.5 - x.dot(x)).max(0.);
+        let m = m * m * m * m;
+
+        let gx = 2. * (p * C[3]).fract() - 1.;
+        let h = gx.abs() - 0.5;
+        let a0 = gx - (gx + 0.5).floor();
+        let m = m * (1.7928429 - 0.85373473 * (a0 * a0 + h * h));
+        sum += m * (a0 * x.x + h * x.y);
+    }
+    130. * sum
+}
+
+// `fbm` in heightfield.wgsl
+fn fbm(p: Vec2, octaves: u32) -> f32 {
+    let mut sum = 0.;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.;
+    for _ in 0..octaves {
+        sum += amplitude * simplex(p * frequency);
+        frequency *= 2.;
+        amplitude *= 0.5;
+    }
+    sum
+}
+
+// `height` in heightfield.wgsl
+fn height(xz: Vec2, offset: Vec2, octaves: u32) -> f32 {
+    let noise = fbm(xz * FREQUENCY + offset, octaves);
+    let shaped = if noise > 0. {
+        noise * noise.abs() * 2.
+    } else {
+        noise
+    };
+    (shaped * HEIGHT).max(WATER)
+}
+
+// `cs_main` for every grid point, row after row, laid out like `Vertex`
+fn vertices(offset: Vec2, octaves: u32) -> Vec<[f32; 8]> {
+    let spacing = SIZE / (GRID - 1) as f32;
+    (0..GRID * GRID)
+        .map(|index| {
+            let point = Vec2::new((index % GRID) as f32, (index / GRID) as f32);
+            let xz = (point - (GRID - 1) as f32 * 0.5) * spacing;
+            let y = height(xz, offset, octaves);
+            let dx = height(xz + Vec2::new(spacing, 0.), offset, octaves)
+                - height(xz - Vec2::new(spacing, 0.), offset, octaves);
+            let dz = height(xz + Vec2::new(0., spacing), offset, octaves)
+                - height(xz - Vec2::new(0., spacing), offset, octaves);
+            let normal = Vec3::new(-dx, 2. * spacing, -dz).normalize();
+            [xz.x, y, xz.y, 0., normal.x, normal.y, normal.z, 0.]
+        })
+        .collect()
+}
+
+// two triangles per grid cell, counter-clockwise seen from above
+fn indices() -> Vec<u16> {
+    let mut indices = Vec::with_capacity(((GRID - 1) * (GRID - 1) * 6) as usize);
+    for z in 0..GRID - 1 {
+        for x in 0..GRID - 1 {
+            let corner = |dx: u32, dz: u32| ((z + dz) * GRID + x + dx) as u16;
+            indices.extend([corner(0, 0), corner(0, 1), corner(1, 1)]);
+            indices.extend([corner(0, 0), corner(1, 1), corner(1, 0)]);
+        }
+    }
+    indices
+}
+
+// matches `Params` in heightfield.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct ParamsUniform {
+    offset: [f32; 2],
+    octaves: u32,
+    _padding: u32,
+}
+
+/// how the render pipeline reads [`Heightfield::vertices`], position at 0 and normal at 1
+pub const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 2] = [
+    wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Float32x3,
+        offset: 0,
+        shader_location: 0,
+    },
+    wgpu::VertexAttribute {
+        format: wgpu::VertexFormat::Float32x3,
+        offset: 16,
+        shader_location: 1,
+    },
+];
+
+pub fn vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+    wgpu::VertexBufferLayout {
+        array_stride: VERTEX_SIZE,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &VERTEX_ATTRIBUTES,
+    }
+}
+
+pub struct Heightfield {
+    /// octaves of noise, each adds finer detail
+    pub octaves: u32,
+    /// a vertex per grid point, row after row
+    pub vertices: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    num_indices: u32,
+    stages: Stages,
+}
+
+enum Stages {
+    Gpu(Box<ComputeStages>),
+    /// no compute, the vertices are made here and uploaded
+    Cpu,
+}
+
+struct ComputeStages {
+    params_buffer: wgpu::Buffer,
+    bindgroup: wgpu::BindGroup,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl Heightfield {
+    pub fn new(device: &wgpu::Device) -> Heightfield {
+        let octaves = 5;
+        let vertices_size = VERTEX_SIZE * (GRID * GRID) as u64;
+
+        let indices = indices();
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        let num_indices = indices.len() as u32;
+
+        if !compat::compute(device) {
+            info!("no compute shaders, the terrain is made on the cpu");
+            return Heightfield {
+                octaves,
+                vertices: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Vertices Buffer"),
+                    size: vertices_size,
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+                index_buffer,
+                num_indices,
+                stages: Stages::Cpu,
+            };
+        }
+
+        let params_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Params Buffer"),
+            contents: bytemuck::bytes_of(&ParamsUniform {
+                offset: [0.; 2],
+                octaves,
+                _padding: 0,
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        // written by the compute pass, read as vertices, and copied out by the tests
+        let vertices = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Vertices Buffer"),
+            size: vertices_size,
+            usage: wgpu::BufferUsages::STORAGE
+                | wgpu::BufferUsages::VERTEX
+                | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        let bindgroup_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("heightfield bindgroup layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("heightfield bind group"),
+            layout: &bindgroup_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: vertices.as_entire_binding(),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(include_wgsl!("heightfield.wgsl"));
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("heightfield pipeline layout"),
+            bind_group_layouts: &[&bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("heightfield"),
+            layout: Some(&pipeline_layout),
+            module: &shader,
+            entry_point: "cs_main",
+        });
+
+        Heightfield {
+            octaves,
+            vertices,
+            index_buffer,
+            num_indices,
+            stages: Stages::Gpu(Box::new(ComputeStages {
+                params_buffer,
+                bindgroup,
+                pipeline,
+            })),
+        }
+    }
+
+    /// the terrain with its center at `offset` in the noise, into `vertices`
+    pub fn run(&self, encoder: &mut wgpu::CommandEncoder, queue: &wgpu::Queue, offset: Vec2) {
+        match &self.stages {
+            Stages::Gpu(stages) => {
+                let params = ParamsUniform {
+                    offset: offset.to_array(),
+                    octaves: self.octaves,
+                    _padding: 0,
+                };
+                watchdog::write_buffer(
+                    queue,
+                    &stages.params_buffer,
+                    0,
+                    bytemuck::bytes_of(&params),
+                );
+
+                let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("heightfield pass"),
+                });
+                cpass.set_pipeline(&stages.pipeline);
+                cpass.set_bind_group(0, &stages.bindgroup, &[]);
+                // GRID is a multiple of WORKGROUP
+                let groups = GRID / WORKGROUP;
+                cpass.dispatch_workgroups(groups, groups, 1);
+            }
+            Stages::Cpu => {
+                let vertices = vertices(offset, self.octaves);
+                // counted by the watchdog, 2 MiB a frame
+                watchdog::write_buffer(queue, &self.vertices, 0, bytemuck::cast_slice(&vertices));
+            }
+        }
+    }
+
+    pub fn draw<'a>(&'a self, rpass: &mut wgpu::RenderPass<'a>) {
+        rpass.set_vertex_buffer(0, self.vertices.slice(..));
+        rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        rpass.draw_indexed(0..self.num_indices, 0, 0..1);
+    }
+}
+
+// the compute pass against the cpu version of the same noise, on a device without a window.
+// without an adapter, e.g. on ci without a gpu, they print why and pass
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use framework::testing::{headless_device, read_back};
+
+    #[test]
+    fn simplex_stays_in_range_and_varies() {
+        let samples: Vec<f32> = (0..10_000)
+            .map(|i| simplex(Vec2::new(i as f32 * 0.173, (i / 100) as f32 * 0.291)))
+            .collect();
+        assert!(samples.iter().all(|n| n.abs() <= 1.));
+        let (min, max) = samples.iter().fold((f32::MAX, f32::MIN), |(min, max), &n| {
+            (min.min(n), max.max(n))
+        });
+        assert!(min < -0.6 && max > 0.6, "noise spans {}..{}", min, max);
+        // zero at the lattice's corners
+        assert!(simplex(Vec2::ZERO).abs() < 1e-6);
+    }
+
+    #[test]
+    fn indices_stay_in_the_grid() {
+        let indices = indices();
+        assert_eq!(indices.len() as u32, (GRID - 1) * (GRID - 1) * 6);
+        assert_eq!(
+            indices.iter().max().copied(),
+            Some((GRID * GRID - 1) as u16)
+        );
+    }
+
+    // the same heights and normals on both paths, up to float differences in the noise. the
+    // normals come from differences of heights a grid spacing apart, which magnifies those
+    #[test]
+    fn vertices_match_the_cpu() {
+        let Some((device, queue)) = headless_device() else {
+            return;
+        };
+        let mut heightfield = Heightfield::new(&device);
+
+        for (offset, octaves) in [
+            (Vec2::ZERO, 5),
+            (Vec2::new(3.7, -12.2), 1),
+            (Vec2::new(250., 80.5), MAX_OCTAVES),
+        ] {
+            heightfield.octaves = octaves;
+            let mut encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            heightfield.run(&mut encoder, &queue, offset);
+            queue.submit(Some(encoder.finish()));
+
+            let gpu: Vec<[f32; 8]> = read_back(&device, &queue, &heightfield.vertices);
+            let cpu = vertices(offset, octaves);
+            for (i, (cpu, gpu)) in cpu.iter().zip(&gpu).enumerate() {
+                let close = |mut range: std::ops::Range<usize>, tolerance: f32| {
+                    range.all(|i| (cpu[i] - gpu[i]).abs() < tolerance)
+                };
+                assert!(
+                    close(0..4, 2e-3) && close(4..8, 1e-2),
+                    "offset {} octaves {}: vertex {} is {:?} on the cpu, {:?} on the gpu",
+                    offset,
+                    octaves,
+                    i,
+                    cpu,
+                    gpu
+                );
+            }
+        }
+    }
+}
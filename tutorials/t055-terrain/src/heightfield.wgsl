@@ -0,0 +1,115 @@
+// the terrain's vertices, one invocation per grid point: layered simplex noise for the height,
+// the slope of the same noise for the normal, written straight into the vertex buffer
+
+struct Params {
+    // where the grid's center is in the noise, moving it flies over the terrain
+    offset: vec2<f32>,
+    octaves: u32,
+};
+
+// matches `VERTEX_ATTRIBUTES` in heightfield.rs, the vertex buffer the render pass draws
+struct Vertex {
+    position: vec3<f32>,
+    normal: vec3<f32>,
+};
+
+// matches the consts in heightfield.rs: points per side, the grid's size and height in world
+// units, noise cells per world unit and the water level the ground is cut off at
+let GRID: u32 = 256u;
+let SIZE: f32 = 48.0;
+let HEIGHT: f32 = 7.0;
+let FREQUENCY: f32 = 0.045;
+let WATER: f32 = -0.6;
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+@group(0) @binding(1)
+var<storage, read_write> vertices: array<Vertex>;
+
+fn mod289_2(x: vec2<f32>) -> vec2<f32> {
+    return x - floor(x * (1.0 / 289.0)) * 289.0;
+}
+
+fn mod289_3(x: vec3<f32>) -> vec3<f32> {
+    return x - floor(x * (1.0 / 289.0)) * 289.0;
+}
+
+// a hash of the corners' cell coordinates, exact in f32 for everything below 289
+fn permute(x: vec3<f32>) -> vec3<f32> {
+    return mod289_3((x * 34.0 + 1.0) * x);
+}
+
+// 2d simplex noise after Gustavson and McEwan, -1..1. the plane is cut into triangles, each of
+// the three corners of the one `v` is in has a random gradient and a falloff around it
+fn simplex(v: vec2<f32>) -> f32 {
+    // (3 - sqrt(3)) / 6, (sqrt(3) - 1) / 2, -1 + 2 * the first, 1 / 41
+    let C = vec4<f32>(0.211324865405187, 0.366025403784439, -0.577350269189626, 0.024390243902439);
+
+    // the triangle's first corner, skewed onto a square grid and back
+    var i = floor(v + dot(v, C.yy));
+    let x0 = v - i + dot(i, C.xx);
+    // the second corner is along x or y, whichever half of the square `v` is in
+    var i1 = vec2<f32>(0.0, 1.0);
+    if (x0.x > x0.y) {
+        i1 = vec2<f32>(1.0, 0.0);
+    }
+    let x1 = x0 + C.xx - i1;
+    let x2 = x0 + C.zz;
+
+    i = mod289_2(i);
+    let p = permute(permute(i.y + vec3<f32>(0.0, i1.y, 1.0)) + i.x + vec3<f32>(0.0, i1.x, 1.0));
+
+    var m = max(0.5 - vec3<f32>(dot(x0, x0), dot(x1, x1), dot(x2, x2)), vec3<f32>(0.0));
+    m = m * m;
+    m = m * m;
+
+    // 41 gradients around a diamond, normalized on the fly by the 1.79 - 0.85 * length²
+    let x = 2.0 * fract(p * C.www) - 1.0;
+    let h = abs(x) - 0.5;
+    let a0 = x - floor(x + 0.5);
+    m = m * (1.79284291400159 - 0.85373472095314 * (a0 * a0 + h * h));
+
+    let g = vec3<f32>(a0.x * x0.x + h.x * x0.y, a0.y * x1.x + h.y * x1.y, a0.z * x2.x + h.z * x2.y);
+    return 130.0 * dot(m, g);
+}
+
+// octaves of simplex noise, each twice the frequency and half the amplitude of the last
+fn fbm(p: vec2<f32>) -> f32 {
+    var sum = 0.0;
+    var amplitude = 0.5;
+    var frequency = 1.0;
+    for (var octave = 0u; octave < params.octaves; octave = octave + 1u) {
+        sum = sum + amplitude * simplex(p * frequency);
+        frequency = frequency * 2.0;
+        amplitude = amplitude * 0.5;
+    }
+    return sum;
+}
+
+// the ground's height at `xz` in world units, flat where it's under water
+fn height(xz: vec2<f32>) -> f32 {
+    let noise = fbm(xz * FREQUENCY + params.offset);
+    // squaring the highs sharpens the peaks and leaves wide valleys
+    let shaped = select(noise, noise * abs(noise) * 2.0, noise > 0.0);
+    return max(shaped * HEIGHT, WATER);
+}
+
+@compute @workgroup_size(8, 8)
+fn cs_main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= GRID || id.y >= GRID) {
+        return;
+    }
+
+    let spacing = SIZE / f32(GRID - 1u);
+    let xz = (vec2<f32>(id.xy) - f32(GRID - 1u) * 0.5) * spacing;
+    let y = height(xz);
+
+    // central differences a grid spacing apart
+    let dx = height(xz + vec2<f32>(spacing, 0.0)) - height(xz - vec2<f32>(spacing, 0.0));
+    let dz = height(xz + vec2<f32>(0.0, spacing)) - height(xz - vec2<f32>(0.0, spacing));
+
+    var vertex: Vertex;
+    vertex.position = vec3<f32>(xz.x, y, xz.y);
+    vertex.normal = normalize(vec3<f32>(-dx, 2.0 * spacing, -dz));
+    vertices[id.y * GRID + id.x] = vertex;
+}
@@ -0,0 +1,282 @@
+// terrain from layered simplex noise, generated on the gpu every frame. a compute pass writes
+// the heights and normals of a 256x256 grid into a storage buffer, and the render pass draws that
+// same buffer as its vertex buffer, so the terrain never exists on the cpu. scrolling where the
+// grid samples the noise flies over it
+mod heightfield;
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    console::{self, Commands},
+    texture::DepthTexture,
+    watchdog,
+};
+use glam::{Vec2, Vec3};
+use heightfield::{Heightfield, MAX_OCTAVES};
+use tracing::info;
+use wgpu::include_wgsl;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "terrain, 5 octaves";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+// noise units per second, toward the camera
+const FLY_SPEED: f32 = 0.08;
+const MAX_FLY_SPEED: f32 = 1.;
+// matches `sky` in shader.wgsl, the fog fades into it
+const SKY: wgpu::Color = wgpu::Color {
+    r: 0.55,
+    g: 0.7,
+    b: 0.85,
+    a: 1.,
+};
+
+// matches `Camera` in shader.wgsl, the eye position is needed for the fog
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    position: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            position: camera.position.extend(1.).to_array(),
+        }
+    }
+}
+
+pub struct Terrain {
+    pipeline: wgpu::RenderPipeline,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    heightfield: Heightfield,
+    offset: Vec2,
+    paused: bool,
+    speed: f32,
+}
+
+impl framework::App for Terrain {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera, above the near edge looking down across the grid
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.45,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller =
+            OrbitCameraController::new(Vec3::new(0., 0., -4.), 26., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform::new(&camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // the heightfield, the compute side, or the cpu's without compute
+        let heightfield = Heightfield::new(device);
+
+        // shader
+        let shader = device.create_shader_module(include_wgsl!("shader.wgsl"));
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("terrain"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[heightfield::vertex_buffer_layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                // the grid is only ever seen from above
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        info!("press space to stop flying, up / down to add or remove octaves of noise");
+
+        Terrain {
+            pipeline,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            heightfield,
+            offset: Vec2::ZERO,
+            paused: false,
+            speed: FLY_SPEED,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            let octaves = &mut self.heightfield.octaves;
+            match key {
+                VirtualKeyCode::Space => self.paused = !self.paused,
+                VirtualKeyCode::Up => *octaves = (*octaves + 1).min(MAX_OCTAVES),
+                VirtualKeyCode::Down => *octaves = (*octaves - 1).max(1),
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn commands(commands: &mut Commands<Self>) {
+        commands
+            .add(
+                "octaves",
+                "<1 to 7>",
+                "how many octaves of noise make the terrain",
+                |app, window, args| {
+                    let octaves = console::parse::<u32>(args, 0)?;
+                    if !(1..=MAX_OCTAVES).contains(&octaves) {
+                        return Err(format!("octaves go from 1 to {}", MAX_OCTAVES));
+                    }
+                    app.heightfield.octaves = octaves;
+                    window.set_title(&app.title());
+                    Ok(app.title())
+                },
+            )
+            .add(
+                "speed",
+                "<speed>",
+                "how fast the terrain flies by, 0.08 to start with",
+                |app, _, args| {
+                    app.speed = console::parse::<f32>(args, 0)?.clamp(0., MAX_FLY_SPEED);
+                    Ok(format!("speed {:.2}", app.speed))
+                },
+            );
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera)),
+        );
+
+        // the noise slides toward +z, the terrain comes at the camera
+        if !self.paused {
+            self.offset.y -= self.speed * frame.delta;
+        }
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // this frame's vertices, then straight into the render pass
+        self.heightfield.run(&mut encoder, queue, self.offset);
+
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, SKY);
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            self.heightfield.draw(&mut rpass);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+impl Terrain {
+    fn title(&self) -> String {
+        format!("terrain, {} octaves", self.heightfield.octaves)
+    }
+}
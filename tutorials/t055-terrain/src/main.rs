@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t55_terrain::Terrain>(t55_terrain::TITLE);
+}
@@ -0,0 +1,69 @@
+// draws the grid heightfield.wgsl left in the vertex buffer, colored by height and slope and
+// fading into the sky toward the edges
+
+struct VertexInput{
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // w unused
+    position: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+let light_dir = vec3<f32>(0.5, 0.6, -0.4);
+let ambient = 0.25;
+// matches `SKY` in lib.rs, the clear color
+let sky = vec3<f32>(0.55, 0.7, 0.85);
+// matches `WATER` in heightfield.wgsl
+let water_level = -0.6;
+// where the fog starts and where it's all sky, in world units from the camera
+let fog_start = 18.0;
+let fog_end = 34.0;
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * vec4<f32>(input.pos, 1.0);
+    fragInput.world_pos = input.pos;
+    fragInput.normal = input.normal;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let normal = normalize(input.normal);
+    let height = input.world_pos.y;
+    // 0 on flat ground, 1 on a cliff
+    let steepness = 1.0 - normal.y;
+
+    var albedo = vec3<f32>(0.1, 0.3, 0.5);
+    if (height > water_level + 0.01) {
+        let sand = vec3<f32>(0.76, 0.7, 0.5);
+        let grass = vec3<f32>(0.25, 0.5, 0.2);
+        let rock = vec3<f32>(0.42, 0.38, 0.35);
+        let snow = vec3<f32>(0.95, 0.95, 0.97);
+        albedo = mix(sand, grass, smoothstep(-0.4, 0.0, height));
+        albedo = mix(albedo, rock, smoothstep(2.0, 3.5, height));
+        albedo = mix(albedo, snow, smoothstep(4.0, 4.8, height));
+        // too steep for grass or snow to stay on
+        albedo = mix(albedo, rock, smoothstep(0.25, 0.45, steepness));
+    }
+
+    let diffuse = max(dot(normal, normalize(light_dir)), 0.0);
+    let color = albedo * (ambient + diffuse);
+
+    let distance = length(input.world_pos - camera.position.xyz);
+    let fog = smoothstep(fog_start, fog_end, distance);
+    return vec4<f32>(mix(color, sky, fog), 1.0);
+}
@@ -0,0 +1,563 @@
+// two windows sharing a scene over the network. start the chapter twice: the first instance
+// gets the port and is the host, it moves the cubes and sends every cube's transform at a fixed
+// tick rate. the second finds the port taken, becomes a client and draws what it receives.
+//
+// the render loop and the network meet only at a channel, a thread in net.rs owns the socket.
+// the host draws every frame from its own clock and sends a snapshot whenever a tick is due,
+// however many frames that is. the client gets 20 snapshots a second and draws at 60 or more
+// frames, so it plays them back a little in the past and interpolates between the two around
+// its clock. without interpolation it jumps from snapshot to snapshot, and when one is lost it
+// stalls a tick.
+//
+// on the host up / down change the tick rate and l cycles through simulated loss. on the
+// client i turns interpolation on and off. drag to orbit.
+mod net;
+
+use std::sync::atomic::Ordering;
+
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    primitives::{self, Mesh},
+    texture::DepthTexture,
+    vertex_layout, watchdog,
+};
+use glam::{Mat4, Quat, Vec3};
+use net::{Buffer, Role, Snapshot, Transform};
+use tracing::{info, warn};
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with, host or client is only known once it's up
+pub const TITLE: &str = "netsync";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const CUBES: usize = 8;
+const TICK_RATES: [u16; 5] = [5, 10, 20, 30, 60];
+// percent of snapshots the host doesn't send
+const LOSSES: [u32; 3] = [0, 20, 50];
+// how far behind the latest snapshot the client plays, in ticks. a lost snapshot is still
+// bridged by the two around it
+const DELAY_TICKS: f64 = 2.;
+// further off than this the client's clock jumps, closer it's pulled in a bit every frame
+const RESYNC_TICKS: f64 = 4.;
+const CATCH_UP: f64 = 0.05;
+const STATS_INTERVAL: f32 = 2.;
+// below the lowest a cube bobs to
+const FLOOR: f32 = -0.5;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Instance {
+    model0: [f32; 4],
+    model1: [f32; 4],
+    model2: [f32; 4],
+    model3: [f32; 4],
+    color: [f32; 3],
+}
+
+vertex_layout! {
+    Instance as "Instance", Instance {
+        3 => model0: Float32x4,
+        4 => model1: Float32x4,
+        5 => model2: Float32x4,
+        6 => model3: Float32x4,
+        7 => color: Float32x3,
+    }
+}
+
+impl Instance {
+    fn new(model: Mat4, color: [f32; 3]) -> Instance {
+        let [model0, model1, model2, model3] = model.to_cols_array_2d();
+        Instance {
+            model0,
+            model1,
+            model2,
+            model3,
+            color,
+        }
+    }
+}
+
+// matches `Camera` in shader.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+}
+
+// the host's scene at `time`: cubes on two rings going opposite ways, bobbing and tumbling
+fn simulate(time: f32) -> Vec<Transform> {
+    (0..CUBES)
+        .map(|i| {
+            let ring = (i % 2) as f32;
+            let radius = 2.5 + ring * 1.8;
+            let speed = if i % 2 == 0 { 0.9 } else { -0.6 };
+            let angle = time * speed + i as f32 * std::f32::consts::TAU / CUBES as f32;
+            Transform {
+                position: Vec3::new(
+                    radius * angle.cos(),
+                    0.6 + 0.4 * (time * 2. + i as f32).sin(),
+                    radius * angle.sin(),
+                ),
+                rotation: Quat::from_rotation_y(time * 1.3 + i as f32)
+                    * Quat::from_rotation_x(time * 0.7),
+            }
+        })
+        .collect()
+}
+
+fn color(index: usize) -> [f32; 3] {
+    let hue = index as f32 / CUBES as f32;
+    let channel = |phase: f32| 0.55 + 0.4 * (std::f32::consts::TAU * (hue + phase)).cos();
+    [channel(0.), channel(1. / 3.), channel(2. / 3.)]
+}
+
+// whether the host drops the snapshot of `tick`, `loss` percent of them spread evenly
+fn lost(tick: u32, loss: u32) -> bool {
+    tick.wrapping_mul(2_654_435_761) % 100 < loss
+}
+
+enum Side {
+    Host {
+        host: net::Host,
+        tick: u32,
+        tick_rate: usize,
+        loss: usize,
+        /// seconds since the last tick went out
+        since_tick: f32,
+        dropped: u64,
+    },
+    Client {
+        client: net::Client,
+        buffer: Buffer,
+        /// the host tick being drawn, fractional between two
+        playback: f64,
+        interpolate: bool,
+    },
+    /// the network couldn't be set up, the scene runs on its own
+    Offline,
+}
+
+pub struct NetSync {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    cube_indices: u32,
+    num_indices: u32,
+    instance_buffer: wgpu::Buffer,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    side: Side,
+    time: f32,
+    /// sent or received when the stats were last logged
+    last_count: u64,
+    /// real seconds since then
+    since_stats: f32,
+}
+
+impl NetSync {
+    fn title(&self) -> String {
+        match &self.side {
+            Side::Host {
+                tick_rate, loss, ..
+            } => format!(
+                "netsync, host, {} ticks/s, {}% loss",
+                TICK_RATES[*tick_rate], LOSSES[*loss]
+            ),
+            Side::Client { interpolate, .. } => format!(
+                "netsync, client, {}",
+                if *interpolate {
+                    "interpolating"
+                } else {
+                    "latest snapshot"
+                }
+            ),
+            Side::Offline => "netsync, offline".to_string(),
+        }
+    }
+
+    // this frame's transforms, and on the host the ticks that came due go out. the host's
+    // scene and ticks follow the timeline, a client plays back at the rate the snapshots come
+    // in, paused or not
+    fn advance(&mut self, dt: f32, real_dt: f32) -> Option<Vec<Transform>> {
+        self.time += dt;
+        match &mut self.side {
+            Side::Host {
+                host,
+                tick,
+                tick_rate,
+                loss,
+                since_tick,
+                dropped,
+            } => {
+                let tick_rate = TICK_RATES[*tick_rate];
+                let period = 1. / tick_rate as f32;
+                *since_tick += dt;
+                while *since_tick >= period {
+                    *since_tick -= period;
+                    *tick += 1;
+                    if lost(*tick, LOSSES[*loss]) {
+                        *dropped += 1;
+                        continue;
+                    }
+                    // the scene as of that tick, not of this frame
+                    host.send(Snapshot {
+                        tick: *tick,
+                        tick_rate,
+                        transforms: simulate(self.time - *since_tick),
+                    });
+                }
+                Some(simulate(self.time))
+            }
+            Side::Client {
+                client,
+                buffer,
+                playback,
+                interpolate,
+            } => {
+                for snapshot in client.receive() {
+                    buffer.push(snapshot);
+                }
+                let latest = buffer.latest()?;
+                let target = latest.tick as f64 - DELAY_TICKS;
+                *playback += real_dt as f64 * latest.tick_rate as f64;
+                if (*playback - target).abs() > RESYNC_TICKS {
+                    *playback = target;
+                } else {
+                    *playback += (target - *playback) * CATCH_UP;
+                }
+
+                if *interpolate {
+                    buffer.sample(*playback)
+                } else {
+                    Some(latest.transforms.clone())
+                }
+            }
+            Side::Offline => Some(simulate(self.time)),
+        }
+    }
+
+    fn log_stats(&mut self, real_dt: f32) {
+        self.since_stats += real_dt;
+        let elapsed = self.since_stats;
+        if elapsed < STATS_INTERVAL {
+            return;
+        }
+        match &self.side {
+            Side::Host { host, dropped, .. } => {
+                let sent = host.stats.sent.load(Ordering::Relaxed);
+                info!(
+                    "{} clients, {:.0} snapshots/s sent, {} dropped so far",
+                    host.stats.clients.load(Ordering::Relaxed),
+                    (sent - self.last_count) as f32 / elapsed,
+                    dropped
+                );
+                self.last_count = sent;
+            }
+            Side::Client {
+                client,
+                buffer,
+                playback,
+                ..
+            } => {
+                let received = client.stats.received.load(Ordering::Relaxed);
+                match buffer.latest() {
+                    Some(latest) => info!(
+                        "{:.0} snapshots/s received, {} buffered, {:.1} ticks behind the latest",
+                        (received - self.last_count) as f32 / elapsed,
+                        buffer.len(),
+                        latest.tick as f64 - playback
+                    ),
+                    None => info!("waiting for the host on port {}", net::PORT),
+                }
+                self.last_count = received;
+            }
+            Side::Offline => {}
+        }
+        self.since_stats = 0.;
+    }
+}
+
+impl framework::App for NetSync {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        let side = match net::connect() {
+            Ok(Role::Host(host)) => {
+                info!(
+                    "hosting on port {}, start the chapter again for a client",
+                    net::PORT
+                );
+                info!("press up / down to change the tick rate, l to lose snapshots");
+                Side::Host {
+                    host,
+                    tick: 0,
+                    tick_rate: 2,
+                    loss: 0,
+                    since_tick: 0.,
+                    dropped: 0,
+                }
+            }
+            Ok(Role::Client(client)) => {
+                info!("port {} is taken, joining its host as a client", net::PORT);
+                info!("press i to turn interpolation on and off");
+                Side::Client {
+                    client,
+                    buffer: Buffer::default(),
+                    playback: 0.,
+                    interpolate: true,
+                }
+            }
+            Err(err) => {
+                warn!("Fail to open a socket, running offline: {}", err);
+                Side::Offline
+            }
+        };
+
+        // camera
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.5,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller = OrbitCameraController::new(Vec3::ZERO, 12., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniform {
+                view_proj: camera.view_proj().to_cols_array_2d(),
+            }),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("camera bind group"),
+            layout: &camera_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        // the cube, then the floor after it in the same buffers
+        let mut mesh = Mesh::cube(1).transformed(Mat4::from_scale(Vec3::splat(0.7)));
+        let cube_indices = mesh.indices.len() as u32;
+        mesh.append(Mesh::plane(14., 1));
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&mesh.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // the floor first, then a cube each, rewritten every frame
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (std::mem::size_of::<Instance>() * (CUBES + 1)) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // shader
+        let shader = vertex_layout::shader(
+            device,
+            "shader.wgsl",
+            include_str!("shader.wgsl"),
+            &[primitives::Vertex::WGSL, Instance::WGSL],
+        );
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: None,
+            bind_group_layouts: &[&camera_bindgroup_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("netsync"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    primitives::Vertex::buffer_layout(),
+                    Instance::buffer_layout(),
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: DepthTexture::FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        NetSync {
+            pipeline,
+            vertex_buffer,
+            index_buffer,
+            cube_indices,
+            num_indices: mesh.indices.len() as u32,
+            instance_buffer,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            side,
+            time: 0.,
+            last_count: 0,
+            since_stats: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match (&mut self.side, key) {
+                (Side::Host { tick_rate, .. }, VirtualKeyCode::Up) => {
+                    *tick_rate = (*tick_rate + 1).min(TICK_RATES.len() - 1)
+                }
+                (Side::Host { tick_rate, .. }, VirtualKeyCode::Down) => {
+                    *tick_rate = tick_rate.saturating_sub(1)
+                }
+                (Side::Host { loss, .. }, VirtualKeyCode::L) => *loss = (*loss + 1) % LOSSES.len(),
+                (Side::Client { interpolate, .. }, VirtualKeyCode::I) => {
+                    *interpolate = !*interpolate
+                }
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform {
+                view_proj: self.camera.view_proj().to_cols_array_2d(),
+            }),
+        );
+
+        // nothing to draw on a client before the first snapshot, just the floor
+        let transforms = self
+            .advance(frame.delta, frame.real_delta)
+            .unwrap_or_default();
+        self.log_stats(frame.real_delta);
+
+        let floor = Mat4::from_translation(Vec3::new(0., FLOOR, 0.));
+        let mut instances = vec![Instance::new(floor, [0.35, 0.37, 0.4])];
+        instances.extend(transforms.iter().enumerate().map(|(i, transform)| {
+            let model = Mat4::from_rotation_translation(transform.rotation, transform.position);
+            Instance::new(model, color(i))
+        }));
+        watchdog::write_buffer(
+            queue,
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&instances),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        {
+            let mut rpass = frame.begin_render_pass(
+                &mut encoder,
+                wgpu::Color {
+                    r: 0.05,
+                    g: 0.05,
+                    b: 0.07,
+                    a: 1.,
+                },
+            );
+
+            rpass.set_pipeline(&self.pipeline);
+            rpass.set_bind_group(0, &self.camera_bindgroup, &[]);
+            rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            rpass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            // the floor is instance 0, the cubes the rest
+            rpass.draw_indexed(self.cube_indices..self.num_indices, 0, 0..1);
+            rpass.draw_indexed(0..self.cube_indices, 0, 1..instances.len() as u32);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t56_netsync::NetSync>(t56_netsync::TITLE);
+}
@@ -0,0 +1,378 @@
+// the network side: a thread that owns a udp socket, so the render loop never waits on the
+// network. on the host the render thread hands it a snapshot every tick through a channel and it
+// sends that to every client it has heard from lately. on a client it receives the snapshots and
+// passes them on through a channel, the render thread takes whatever arrived with `try_recv` and
+// keeps a few in a `Buffer` to interpolate between.
+//
+// udp, since a snapshot that's late is worth nothing once the next one is there. a lost one
+// isn't sent again, the client interpolates across the gap.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use glam::{Quat, Vec3};
+use tracing::warn;
+
+/// the host's port on localhost, the first instance gets it
+pub const PORT: u16 = 47560;
+/// the most objects a snapshot carries, it stays well inside one datagram
+pub const MAX_OBJECTS: usize = 32;
+
+const MAGIC: [u8; 4] = *b"t56n";
+const HELLO: u8 = 0;
+const SNAPSHOT: u8 = 1;
+// magic, kind, tick, tick rate, count
+const HEADER_SIZE: usize = 4 + 1 + 4 + 2 + 2;
+// position and rotation, 7 f32s
+const TRANSFORM_SIZE: usize = 7 * 4;
+
+// a client says hello this often, the host forgets it after a few missed ones
+const HELLO_EVERY: Duration = Duration::from_secs(1);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(3);
+// how long the threads wait for something to do before looking again
+const POLL: Duration = Duration::from_millis(10);
+// snapshots a client keeps, a second's worth at 32 ticks/s
+const BUFFERED: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub position: Vec3,
+    pub rotation: Quat,
+}
+
+impl Transform {
+    fn lerp(&self, other: &Transform, t: f32) -> Transform {
+        Transform {
+            position: self.position.lerp(other.position, t),
+            rotation: self.rotation.slerp(other.rotation, t),
+        }
+    }
+}
+
+/// every object's transform at one tick of the host
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub tick: u32,
+    /// ticks per second when it was sent
+    pub tick_rate: u16,
+    pub transforms: Vec<Transform>,
+}
+
+impl Snapshot {
+    fn encode(&self) -> Vec<u8> {
+        let count = self.transforms.len().min(MAX_OBJECTS);
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + count * TRANSFORM_SIZE);
+        bytes.extend_from_slice(&MAGIC);
+        bytes.push(SNAPSHOT);
+        bytes.extend_from_slice(&self.tick.to_le_bytes());
+        bytes.extend_from_slice(&self.tick_rate.to_le_bytes());
+        bytes.extend_from_slice(&(count as u16).to_le_bytes());
+        for transform in &self.transforms[..count] {
+            let floats = transform
+                .position
+                .to_array()
+                .into_iter()
+                .chain(transform.rotation.to_array());
+            for float in floats {
+                bytes.extend_from_slice(&float.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    // `None` for anything that isn't a whole snapshot
+    fn decode(bytes: &[u8]) -> Option<Snapshot> {
+        if bytes.len() < HEADER_SIZE || bytes[..4] != MAGIC || bytes[4] != SNAPSHOT {
+            return None;
+        }
+        let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+        let tick = u32::from_le_bytes(bytes[5..9].try_into().ok()?);
+        let tick_rate = u16_at(9);
+        let count = u16_at(11) as usize;
+        if count > MAX_OBJECTS || bytes.len() != HEADER_SIZE + count * TRANSFORM_SIZE {
+            return None;
+        }
+
+        let floats: Vec<f32> = bytes[HEADER_SIZE..]
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        let transforms = floats
+            .chunks_exact(7)
+            .map(|f| Transform {
+                position: Vec3::new(f[0], f[1], f[2]),
+                rotation: Quat::from_xyzw(f[3], f[4], f[5], f[6]),
+            })
+            .collect();
+        Some(Snapshot {
+            tick,
+            tick_rate: tick_rate.max(1),
+            transforms,
+        })
+    }
+}
+
+/// counted by the network thread, read by the render thread
+#[derive(Default)]
+pub struct Stats {
+    /// snapshots sent, once per client
+    pub sent: AtomicU64,
+    pub received: AtomicU64,
+    /// clients heard from lately, on the host
+    pub clients: AtomicU64,
+}
+
+pub struct Host {
+    snapshots: Sender<Snapshot>,
+    pub stats: Arc<Stats>,
+}
+
+impl Host {
+    /// queue `snapshot` for every client, never blocks
+    pub fn send(&self, snapshot: Snapshot) {
+        // the thread only stops once `self` is dropped
+        let _ = self.snapshots.send(snapshot);
+    }
+}
+
+pub struct Client {
+    snapshots: Receiver<Snapshot>,
+    stop: Arc<AtomicBool>,
+    pub stats: Arc<Stats>,
+}
+
+impl Client {
+    /// the snapshots that arrived since the last call
+    pub fn receive(&self) -> impl Iterator<Item = Snapshot> + '_ {
+        self.snapshots.try_iter()
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+pub enum Role {
+    Host(Host),
+    Client(Client),
+}
+
+/// host on [`PORT`], or a client of the instance that already is. the thread stops by itself
+/// once the returned side is dropped
+pub fn connect() -> std::io::Result<Role> {
+    let host_address = SocketAddr::from((Ipv4Addr::LOCALHOST, PORT));
+    let stats = Arc::new(Stats::default());
+
+    match UdpSocket::bind(host_address) {
+        Ok(socket) => {
+            socket.set_nonblocking(true)?;
+            let (sender, receiver) = mpsc::channel();
+            let host_stats = stats.clone();
+            thread::spawn(move || host(socket, receiver, &host_stats));
+            Ok(Role::Host(Host {
+                snapshots: sender,
+                stats,
+            }))
+        }
+        Err(err) if err.kind() == ErrorKind::AddrInUse => {
+            let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0))?;
+            socket.connect(host_address)?;
+            socket.set_read_timeout(Some(POLL))?;
+            let (sender, receiver) = mpsc::channel();
+            let stop = Arc::new(AtomicBool::new(false));
+            let (client_stop, client_stats) = (stop.clone(), stats.clone());
+            thread::spawn(move || client(socket, sender, &client_stop, &client_stats));
+            Ok(Role::Client(Client {
+                snapshots: receiver,
+                stop,
+                stats,
+            }))
+        }
+        Err(err) => Err(err),
+    }
+}
+
+fn host(socket: UdpSocket, snapshots: Receiver<Snapshot>, stats: &Stats) {
+    let mut clients: HashMap<SocketAddr, Instant> = HashMap::new();
+    let mut buffer = [0; 64];
+    loop {
+        // whoever said hello since the last look
+        loop {
+            match socket.recv_from(&mut buffer) {
+                Ok((len, from)) if buffer[..len] == hello() => {
+                    clients.insert(from, Instant::now());
+                }
+                Ok(_) => {}
+                Err(err) if err.kind() == ErrorKind::WouldBlock => break,
+                // windows reports an earlier send to a closed port here
+                Err(err) if err.kind() == ErrorKind::ConnectionReset => {}
+                Err(err) => {
+                    warn!("Fail to receive: {}", err);
+                    break;
+                }
+            }
+        }
+        clients.retain(|_, seen| seen.elapsed() < CLIENT_TIMEOUT);
+        stats.clients.store(clients.len() as u64, Ordering::Relaxed);
+
+        let snapshot = match snapshots.recv_timeout(POLL) {
+            Ok(snapshot) => snapshot,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        let bytes = snapshot.encode();
+        for client in clients.keys() {
+            match socket.send_to(&bytes, client) {
+                Ok(_) => {
+                    stats.sent.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(err) => warn!("Fail to send to {}: {}", client, err),
+            }
+        }
+    }
+}
+
+fn client(socket: UdpSocket, snapshots: Sender<Snapshot>, stop: &AtomicBool, stats: &Stats) {
+    let mut last_hello: Option<Instant> = None;
+    let mut buffer = [0; HEADER_SIZE + MAX_OBJECTS * TRANSFORM_SIZE];
+    while !stop.load(Ordering::Relaxed) {
+        let hello_due = match last_hello {
+            Some(at) => at.elapsed() >= HELLO_EVERY,
+            None => true,
+        };
+        if hello_due {
+            // nobody is listening until the host is up again, the hellos go on until it is
+            let _ = socket.send(&hello());
+            last_hello = Some(Instant::now());
+        }
+
+        match socket.recv(&mut buffer) {
+            Ok(len) => {
+                if let Some(snapshot) = Snapshot::decode(&buffer[..len]) {
+                    stats.received.fetch_add(1, Ordering::Relaxed);
+                    if snapshots.send(snapshot).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::ConnectionRefused
+                ) => {}
+            Err(err) => warn!("Fail to receive: {}", err),
+        }
+    }
+}
+
+fn hello() -> [u8; 5] {
+    [MAGIC[0], MAGIC[1], MAGIC[2], MAGIC[3], HELLO]
+}
+
+/// the last snapshots a client received, in tick order, to sample between
+#[derive(Default)]
+pub struct Buffer {
+    snapshots: VecDeque<Snapshot>,
+}
+
+impl Buffer {
+    /// keeps it in order, a snapshot that arrived after a newer one still fills its gap
+    pub fn push(&mut self, snapshot: Snapshot) {
+        let index = self
+            .snapshots
+            .partition_point(|other| other.tick < snapshot.tick);
+        if self.snapshots.get(index).map(|other| other.tick) == Some(snapshot.tick) {
+            return;
+        }
+        self.snapshots.insert(index, snapshot);
+        if self.snapshots.len() > BUFFERED {
+            self.snapshots.pop_front();
+        }
+    }
+
+    pub fn latest(&self) -> Option<&Snapshot> {
+        self.snapshots.back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// the transforms at `tick`, between the snapshots on either side of it. before the first
+    /// they're the first's, after the last the last's, nothing is guessed past what arrived
+    pub fn sample(&self, tick: f64) -> Option<Vec<Transform>> {
+        let after = self
+            .snapshots
+            .partition_point(|snapshot| (snapshot.tick as f64) <= tick);
+        let (a, b) = match (after.checked_sub(1), self.snapshots.get(after)) {
+            (Some(before), Some(b)) => (&self.snapshots[before], b),
+            (Some(before), None) => return Some(self.snapshots[before].transforms.clone()),
+            (None, Some(b)) => return Some(b.transforms.clone()),
+            (None, None) => return None,
+        };
+
+        let t = ((tick - a.tick as f64) / (b.tick - a.tick) as f64) as f32;
+        Some(
+            a.transforms
+                .iter()
+                .zip(&b.transforms)
+                .map(|(a, b)| a.lerp(b, t))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(tick: u32, x: f32) -> Snapshot {
+        Snapshot {
+            tick,
+            tick_rate: 20,
+            transforms: vec![Transform {
+                position: Vec3::new(x, 1., -2.),
+                rotation: Quat::from_rotation_y(x),
+            }],
+        }
+    }
+
+    #[test]
+    fn snapshots_survive_the_wire() {
+        let sent = snapshot(123_456, 0.5);
+        assert_eq!(Snapshot::decode(&sent.encode()), Some(sent.clone()));
+
+        let bytes = sent.encode();
+        assert_eq!(Snapshot::decode(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(Snapshot::decode(&hello()), None);
+        assert_eq!(Snapshot::decode(b"something else entirely"), None);
+    }
+
+    #[test]
+    fn samples_between_the_ticks_around() {
+        let mut buffer = Buffer::default();
+        assert_eq!(buffer.sample(0.), None);
+
+        // out of order, and one twice
+        for (tick, x) in [(10, 0.), (14, 4.), (12, 2.), (12, 2.)] {
+            buffer.push(snapshot(tick, x));
+        }
+        assert_eq!(buffer.len(), 3);
+        assert_eq!(buffer.latest().map(|s| s.tick), Some(14));
+
+        let x = |tick: f64| buffer.sample(tick).unwrap()[0].position.x;
+        assert_eq!(x(11.), 1.);
+        assert_eq!(x(13.5), 3.5);
+        // held at either end
+        assert_eq!(x(5.), 0.);
+        assert_eq!(x(20.), 4.);
+    }
+}
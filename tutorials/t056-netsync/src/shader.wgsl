@@ -0,0 +1,49 @@
+// the floor and the cubes, each instance a model matrix and a flat color, lit by one light
+
+// generated from `primitives::Vertex` and `Instance` in lib.rs
+struct VertexInput {
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+struct Instance {
+    @location(3) model0: vec4<f32>,
+    @location(4) model1: vec4<f32>,
+    @location(5) model2: vec4<f32>,
+    @location(6) model3: vec4<f32>,
+    @location(7) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) normal: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// matches `CameraUniform` in lib.rs
+struct Camera {
+    view_proj: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@vertex
+fn vs_main(input: VertexInput, instance: Instance) -> FragInput {
+    let model = mat4x4<f32>(instance.model0, instance.model1, instance.model2, instance.model3);
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * model * vec4<f32>(input.pos, 1.0);
+    // rotations and translations only, the model matrix turns normals as it is
+    fragInput.normal = (model * vec4<f32>(input.normal, 0.0)).xyz;
+    fragInput.color = instance.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let light_dir = normalize(vec3<f32>(0.4, 0.9, 0.3));
+    let diffuse = max(dot(normalize(input.normal), light_dir), 0.0);
+    return vec4<f32>(input.color * (0.2 + 0.8 * diffuse), 1.0);
+}
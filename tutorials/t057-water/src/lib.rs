@@ -0,0 +1,767 @@
+// water as a flat grid that the vertex shader moves with a few gerstner waves, mirroring what
+// stands around it. every frame the scene is drawn twice: first into a half size reflection
+// target, from the camera mirrored under the water and with what's under the water clipped,
+// then into the window with the water on top. the water samples the reflection where it lands
+// on screen, pushed around a bit by the waves' normals, and blends it with its own deep or
+// shallow tint by fresnel, so it's clear looking down and a mirror toward the horizon.
+//
+// space pauses, up / down make the waves bigger or smaller, r turns the reflection off to show
+// what the sky's color alone looks like. drag to orbit. built with `--features rhai`,
+// `script tutorials/t057-water/demo.rhai` moves, recolors and adds objects from a script.
+use bytemuck::{Pod, Zeroable};
+use framework::{
+    camera::{Camera, CameraController, OrbitCameraController},
+    pass_graph::PassGraph,
+    primitives::{self, Mesh},
+    scene::{Scene, Transform},
+    screenshot::DumpTarget,
+    targets::{TargetDesc, Targets},
+    texture::{DepthTexture, Texture},
+    vertex_layout, watchdog,
+};
+use glam::{Mat4, Quat, Vec3, Vec4};
+use std::f32::consts::FRAC_PI_2;
+use tracing::info;
+use winit::{
+    event::{DeviceEvent, ElementState, KeyboardInput, VirtualKeyCode, WindowEvent},
+    window::Window,
+};
+
+use wgpu::util::DeviceExt;
+
+/// the window title the chapter starts with
+pub const TITLE: &str = "water, waves 1.00, reflections on";
+
+const CAMERA_SENSITIVITY: f32 = 0.005;
+const MAX_WAVE_SCALE: f32 = 1.25;
+const WAVE_SCALE_STEP: f32 = 0.25;
+// matches `sky` in water.wgsl
+const SKY: wgpu::Color = wgpu::Color {
+    r: 0.55,
+    g: 0.7,
+    b: 0.88,
+    a: 1.,
+};
+// the reflection is blurred by the waves anyway, half the window's size is plenty
+const REFLECTION_DIVISOR: u32 = 2;
+// the objects `objects` animates, and which of the shapes in `Water::ranges` each one is
+const NAMES: [&str; 6] = ["torus", "ball", "pillar 1", "pillar 2", "pillar 3", "rock"];
+const SHAPES: [usize; 6] = [0, 1, 2, 2, 2, 3];
+// what a spawned copy of each shape is called, with a number after it
+const SHAPE_NAMES: [&str; 4] = ["torus", "ball", "pillar", "rock"];
+// with the ones a script spawns
+const MAX_OBJECTS: usize = 64;
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Instance {
+    model0: [f32; 4],
+    model1: [f32; 4],
+    model2: [f32; 4],
+    model3: [f32; 4],
+    color: [f32; 3],
+}
+
+vertex_layout! {
+    Instance as "Instance", Instance {
+        3 => model0: Float32x4,
+        4 => model1: Float32x4,
+        5 => model2: Float32x4,
+        6 => model3: Float32x4,
+        7 => color: Float32x3,
+    }
+}
+
+impl Instance {
+    fn new(model: Mat4, color: [f32; 3]) -> Instance {
+        let [model0, model1, model2, model3] = model.to_cols_array_2d();
+        Instance {
+            model0,
+            model1,
+            model2,
+            model3,
+            color,
+        }
+    }
+}
+
+// matches `Camera` in scene.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct CameraUniform {
+    view_proj: [[f32; 4]; 4],
+    clip_plane: [f32; 4],
+}
+
+impl CameraUniform {
+    fn new(camera: &Camera, clip_plane: Vec4) -> CameraUniform {
+        CameraUniform {
+            view_proj: camera.view_proj().to_cols_array_2d(),
+            clip_plane: clip_plane.to_array(),
+        }
+    }
+}
+
+// matches `Water` in water.wgsl
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct WaterUniform {
+    view_proj: [[f32; 4]; 4],
+    camera_position: [f32; 3],
+    time: f32,
+    screen_size: [f32; 2],
+    wave_scale: f32,
+    reflections: u32,
+}
+
+// the same camera on the other side of the water: y and the pitch flip, the yaw stays. its up
+// is still +y, so what it sees is the reflection upside down, which water.wgsl flips back
+fn mirrored(camera: &Camera) -> Camera {
+    let position = camera.position * Vec3::new(1., -1., 1.);
+    Camera::new(position, camera.yaw, -camera.pitch, camera.aspect)
+}
+
+// a camera uniform's buffer and its bind group
+fn camera_binding(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    label: &str,
+    uniform: CameraUniform,
+) -> (wgpu::Buffer, wgpu::BindGroup) {
+    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+        label: Some(label),
+        contents: bytemuck::bytes_of(&uniform),
+        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+    });
+    let bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some(label),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+    (buffer, bindgroup)
+}
+
+fn reflection_bindgroup(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    reflection: &Texture,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("reflection bind group"),
+        layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&reflection.view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&reflection.sampler),
+            },
+        ],
+    })
+}
+
+// the objects at `time`: a torus standing up and spinning, a bobbing ball, three pillars going
+// into the water and a rock just breaking the surface
+fn objects(time: f32) -> [(Transform, [f32; 3]); 6] {
+    let pillar = |x: f32, y: f32, z: f32| Transform::from_translation(Vec3::new(x, y, z));
+    [
+        (
+            Transform {
+                translation: Vec3::new(0., 1.7, -5.),
+                rotation: Quat::from_rotation_y(time * 0.6) * Quat::from_rotation_x(FRAC_PI_2),
+                scale: Vec3::ONE,
+            },
+            [0.9, 0.45, 0.2],
+        ),
+        (
+            Transform {
+                translation: Vec3::new(-4., 0.5 + 0.3 * (time * 1.4).sin(), -2.),
+                rotation: Quat::IDENTITY,
+                scale: Vec3::splat(0.8),
+            },
+            [0.85, 0.85, 0.8],
+        ),
+        (pillar(4.5, 1., -7.), [0.6, 0.55, 0.5]),
+        (pillar(6., 0.6, -3.5), [0.6, 0.55, 0.5]),
+        (pillar(-6.5, 1.2, -8.), [0.6, 0.55, 0.5]),
+        (
+            Transform {
+                translation: Vec3::new(1.8, 0.1, 1.5),
+                rotation: Quat::from_rotation_y(0.5) * Quat::from_rotation_x(0.3),
+                scale: Vec3::splat(1.4),
+            },
+            [0.35, 0.4, 0.3],
+        ),
+    ]
+}
+
+// one of the things standing in the water, `shape` indexes `Water::ranges`
+struct Object {
+    name: String,
+    shape: usize,
+    transform: Transform,
+    color: [f32; 3],
+    // follows `objects` until a script moves it, spawned ones never do
+    animated: bool,
+}
+
+pub struct Water {
+    scene_pipeline: wgpu::RenderPipeline,
+    water_pipeline: wgpu::RenderPipeline,
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    /// index ranges of the torus, the sphere, a pillar and the rock, then the water's
+    ranges: [std::ops::Range<u32>; 5],
+    objects: Vec<Object>,
+    instance_buffer: wgpu::Buffer,
+    camera: Camera,
+    camera_controller: OrbitCameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bindgroup: wgpu::BindGroup,
+    mirrored_buffer: wgpu::Buffer,
+    mirrored_bindgroup: wgpu::BindGroup,
+    water_buffer: wgpu::Buffer,
+    water_bindgroup: wgpu::BindGroup,
+    reflection_layout: wgpu::BindGroupLayout,
+    reflection_bindgroup: wgpu::BindGroup,
+    targets: Targets,
+    reflection_depth: Texture,
+    screen_size: [f32; 2],
+    wave_scale: f32,
+    reflections: bool,
+    paused: bool,
+    time: f32,
+}
+
+impl framework::App for Water {
+    fn depth_buffer() -> bool {
+        true
+    }
+
+    fn init(
+        config: &wgpu::SurfaceConfiguration,
+        _adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) -> Self {
+        // camera, a little above the water looking across it
+        let mut camera = Camera::new(
+            Vec3::ZERO,
+            0.,
+            -0.3,
+            config.width as f32 / config.height as f32,
+        );
+        let mut camera_controller =
+            OrbitCameraController::new(Vec3::new(0., 0.5, -2.), 14., CAMERA_SENSITIVITY);
+        camera_controller.update_camera(&mut camera, 0.);
+
+        let camera_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("camera bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<CameraUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        // the window's camera keeps everything, the mirrored one only what's above the water
+        let (camera_buffer, camera_bindgroup) = camera_binding(
+            device,
+            &camera_bindgroup_layout,
+            "camera",
+            CameraUniform::new(&camera, Vec4::W),
+        );
+        let (mirrored_buffer, mirrored_bindgroup) = camera_binding(
+            device,
+            &camera_bindgroup_layout,
+            "mirrored camera",
+            CameraUniform::new(&mirrored(&camera), Vec4::Y),
+        );
+
+        let water_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Water Buffer"),
+            size: std::mem::size_of::<WaterUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let water_bindgroup_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("water bindgroup layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: wgpu::BufferSize::new(
+                            std::mem::size_of::<WaterUniform>() as u64,
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let water_bindgroup = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("water bind group"),
+            layout: &water_bindgroup_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: water_buffer.as_entire_binding(),
+            }],
+        });
+
+        // the reflection, drawn into every frame and sampled by the water
+        let targets = Targets::new(
+            device,
+            config.width,
+            config.height,
+            vec![TargetDesc::new(
+                config.format,
+                REFLECTION_DIVISOR,
+                "reflection",
+            )],
+        );
+        let (width, height) = targets.size(0);
+        let reflection_depth = Texture::create_depth(device, width, height, "reflection depth");
+
+        let reflection_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("reflection bindgroup layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let reflection_bindgroup = reflection_bindgroup(device, &reflection_layout, &targets[0]);
+
+        // every shape one after the other in the same buffers, the water last
+        let shapes = [
+            Mesh::torus(1.2, 0.35, 48, 16),
+            Mesh::sphere(32, 16),
+            Mesh::cylinder(24).transformed(Mat4::from_scale(Vec3::new(1., 4., 1.))),
+            Mesh::cube(1),
+            Mesh::plane(60., 240),
+        ];
+        let mut mesh = Mesh::default();
+        let mut ranges: [std::ops::Range<u32>; 5] = Default::default();
+        for (range, shape) in ranges.iter_mut().zip(shapes) {
+            let start = mesh.indices.len() as u32;
+            mesh.append(shape);
+            *range = start..mesh.indices.len() as u32;
+        }
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&mesh.vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Indeices Buffer"),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        // an instance per object, rewritten every frame
+        let objects = objects(0.)
+            .into_iter()
+            .zip(NAMES.into_iter().zip(SHAPES))
+            .map(|((transform, color), (name, shape))| Object {
+                name: name.to_string(),
+                shape,
+                transform,
+                color,
+                animated: true,
+            })
+            .collect();
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (std::mem::size_of::<Instance>() * MAX_OBJECTS) as u64,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // shaders
+        let scene_shader = vertex_layout::shader(
+            device,
+            "scene.wgsl",
+            include_str!("scene.wgsl"),
+            &[primitives::Vertex::WGSL, Instance::WGSL],
+        );
+        let water_shader = vertex_layout::shader(
+            device,
+            "water.wgsl",
+            include_str!("water.wgsl"),
+            &[primitives::Vertex::WGSL],
+        );
+
+        let depth_stencil = wgpu::DepthStencilState {
+            format: DepthTexture::FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        };
+
+        let scene_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&camera_bindgroup_layout],
+                push_constant_ranges: &[],
+            });
+
+        // the same pipeline draws into the reflection and the window, they share the format
+        let scene_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("scene"),
+            layout: Some(&scene_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &scene_shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    primitives::Vertex::buffer_layout(),
+                    Instance::buffer_layout(),
+                ],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &scene_shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                // the mirrored camera is turned, not mirrored, the winding stays the same
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(depth_stencil.clone()),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let water_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: None,
+                bind_group_layouts: &[&water_bindgroup_layout, &reflection_layout],
+                push_constant_ranges: &[],
+            });
+
+        let water_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("water"),
+            layout: Some(&water_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &water_shader,
+                entry_point: "vs_main",
+                buffers: &[primitives::Vertex::buffer_layout()],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &water_shader,
+                entry_point: "fs_main",
+                targets: &[Some(config.format.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                // a steep crest can fold over and show its back
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: Some(depth_stencil),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        info!("press space to pause, up / down to change the waves, r to turn reflections off");
+
+        Water {
+            scene_pipeline,
+            water_pipeline,
+            vertex_buffer,
+            index_buffer,
+            ranges,
+            objects,
+            instance_buffer,
+            camera,
+            camera_controller,
+            camera_buffer,
+            camera_bindgroup,
+            mirrored_buffer,
+            mirrored_bindgroup,
+            water_buffer,
+            water_bindgroup,
+            reflection_layout,
+            reflection_bindgroup,
+            targets,
+            reflection_depth,
+            screen_size: [config.width as f32, config.height as f32],
+            wave_scale: 1.,
+            reflections: true,
+            paused: false,
+            time: 0.,
+        }
+    }
+
+    fn resize(
+        &mut self,
+        config: &wgpu::SurfaceConfiguration,
+        device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        self.camera.resize(config.width, config.height);
+        self.screen_size = [config.width as f32, config.height as f32];
+        if self.targets.resize(device, config.width, config.height) {
+            let (width, height) = self.targets.size(0);
+            self.reflection_depth =
+                Texture::create_depth(device, width, height, "reflection depth");
+            self.reflection_bindgroup =
+                reflection_bindgroup(device, &self.reflection_layout, &self.targets[0]);
+        }
+    }
+
+    fn update(&mut self, window: &Window, event: WindowEvent) {
+        if self.camera_controller.process_window_event(&event) {
+            return;
+        }
+
+        if let WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(key),
+                    ..
+                },
+            ..
+        } = event
+        {
+            match key {
+                VirtualKeyCode::Space => self.paused = !self.paused,
+                VirtualKeyCode::Up => {
+                    self.wave_scale = (self.wave_scale + WAVE_SCALE_STEP).min(MAX_WAVE_SCALE)
+                }
+                VirtualKeyCode::Down => {
+                    self.wave_scale = (self.wave_scale - WAVE_SCALE_STEP).max(0.)
+                }
+                VirtualKeyCode::R => self.reflections = !self.reflections,
+                _ => return,
+            }
+            window.set_title(&self.title());
+        }
+    }
+
+    fn device_event(&mut self, event: DeviceEvent) {
+        self.camera_controller.process_device_event(&event);
+    }
+
+    fn scene(&mut self) -> Option<&mut dyn Scene> {
+        Some(self)
+    }
+
+    fn dump_targets(&self) -> Vec<DumpTarget<'_>> {
+        self.targets.dump_targets()
+    }
+
+    fn pass_graph(&self) -> Option<PassGraph> {
+        Some(
+            PassGraph::new()
+                .pass("reflection", &[], &["reflection"])
+                .pass("scene and water", &["reflection"], &["window"]),
+        )
+    }
+
+    fn render(&mut self, frame: &framework::Frame, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.paused {
+            self.time += frame.delta;
+        }
+
+        self.camera_controller
+            .update_camera(&mut self.camera, frame.real_delta);
+        watchdog::write_buffer(
+            queue,
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&self.camera, Vec4::W)),
+        );
+        watchdog::write_buffer(
+            queue,
+            &self.mirrored_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniform::new(&mirrored(&self.camera), Vec4::Y)),
+        );
+        watchdog::write_buffer(
+            queue,
+            &self.water_buffer,
+            0,
+            bytemuck::bytes_of(&WaterUniform {
+                view_proj: self.camera.view_proj().to_cols_array_2d(),
+                camera_position: self.camera.position.to_array(),
+                time: self.time,
+                screen_size: self.screen_size,
+                wave_scale: self.wave_scale,
+                reflections: self.reflections as u32,
+            }),
+        );
+
+        for (object, (transform, _)) in self.objects.iter_mut().zip(objects(self.time)) {
+            if object.animated {
+                object.transform = transform;
+            }
+        }
+        let instances: Vec<Instance> = self
+            .objects
+            .iter()
+            .map(|object| Instance::new(object.transform.matrix(), object.color))
+            .collect();
+        watchdog::write_buffer(
+            queue,
+            &self.instance_buffer,
+            0,
+            bytemuck::cast_slice(&instances),
+        );
+
+        let mut encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+
+        // the scene from under the water, into the reflection
+        if self.reflections {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("reflection pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.targets[0].view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(SKY),
+                        // the water samples it
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.reflection_depth.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            self.draw_scene(&mut rpass, &self.mirrored_bindgroup);
+        }
+
+        // the scene again from the camera, then the water over it
+        {
+            let mut rpass = frame.begin_render_pass(&mut encoder, SKY);
+            self.draw_scene(&mut rpass, &self.camera_bindgroup);
+
+            rpass.set_pipeline(&self.water_pipeline);
+            rpass.set_bind_group(0, &self.water_bindgroup, &[]);
+            rpass.set_bind_group(1, &self.reflection_bindgroup, &[]);
+            rpass.draw_indexed(self.ranges[4].clone(), 0, 0..1);
+        }
+
+        queue.submit(Some(encoder.finish()));
+    }
+}
+
+impl Water {
+    fn title(&self) -> String {
+        format!(
+            "water, waves {:.2}, reflections {}",
+            self.wave_scale,
+            if self.reflections { "on" } else { "off" }
+        )
+    }
+
+    // the objects, seen by the camera `camera_bindgroup` holds. the vertex and index buffers
+    // stay bound for the water after it
+    fn draw_scene<'a>(
+        &'a self,
+        rpass: &mut wgpu::RenderPass<'a>,
+        camera_bindgroup: &'a wgpu::BindGroup,
+    ) {
+        rpass.set_pipeline(&self.scene_pipeline);
+        rpass.set_bind_group(0, camera_bindgroup, &[]);
+        rpass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        rpass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        rpass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+        for (i, object) in self.objects.iter().enumerate() {
+            let instance = i as u32;
+            rpass.draw_indexed(self.ranges[object.shape].clone(), 0, instance..instance + 1);
+        }
+    }
+
+    fn find(&mut self, name: &str) -> Result<&mut Object, String> {
+        self.objects
+            .iter_mut()
+            .find(|object| object.name == name)
+            .ok_or_else(|| format!("there's no object `{}`", name))
+    }
+}
+
+// what a script gets to change: the objects' transforms, their color and copies of them
+impl Scene for Water {
+    fn objects(&self) -> Vec<(String, Transform)> {
+        self.objects
+            .iter()
+            .map(|object| (object.name.clone(), object.transform))
+            .collect()
+    }
+
+    fn set_transform(&mut self, object: &str, transform: Transform) -> Result<(), String> {
+        let object = self.find(object)?;
+        object.transform = transform;
+        object.animated = false;
+        Ok(())
+    }
+
+    fn set_material(&mut self, object: &str, param: &str, value: &[f32]) -> Result<(), String> {
+        let object = self.find(object)?;
+        match (param, value) {
+            ("color", &[r, g, b]) => {
+                object.color = [r, g, b];
+                Ok(())
+            }
+            ("color", _) => Err("color takes [r, g, b]".to_string()),
+            _ => Err(format!(
+                "the objects' only material param is color, not {}",
+                param
+            )),
+        }
+    }
+
+    fn spawn(&mut self, object: &str, transform: Transform) -> Result<(), String> {
+        if self.objects.len() == MAX_OBJECTS {
+            return Err(format!("there's only room for {} objects", MAX_OBJECTS));
+        }
+        let source = self.find(object)?;
+        let (shape, color) = (source.shape, source.color);
+        let count = self.objects.iter().filter(|o| o.shape == shape).count();
+        self.objects.push(Object {
+            name: format!("{} {}", SHAPE_NAMES[shape], count + 1),
+            shape,
+            transform,
+            color,
+            animated: false,
+        });
+        Ok(())
+    }
+}
@@ -0,0 +1,3 @@
+fn main() {
+    framework::run::<t57_water::Water>(t57_water::TITLE);
+}
@@ -0,0 +1,60 @@
+// what stands in and around the water, drawn twice a frame: into the reflection from the camera
+// mirrored under the water, and into the window. each instance is a model matrix and a color
+
+// generated from `primitives::Vertex` and `Instance` in lib.rs
+struct VertexInput {
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+struct Instance {
+    @location(3) model0: vec4<f32>,
+    @location(4) model1: vec4<f32>,
+    @location(5) model2: vec4<f32>,
+    @location(6) model3: vec4<f32>,
+    @location(7) color: vec3<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) color: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// matches `CameraUniform` in lib.rs
+struct Camera {
+    view_proj: mat4x4<f32>,
+    // a point is kept where dot(plane.xyz, point) + plane.w >= 0
+    clip_plane: vec4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: Camera;
+
+@vertex
+fn vs_main(input: VertexInput, instance: Instance) -> FragInput {
+    let model = mat4x4<f32>(instance.model0, instance.model1, instance.model2, instance.model3);
+    let world_pos = model * vec4<f32>(input.pos, 1.0);
+
+    var fragInput : FragInput;
+    fragInput.clip_position = camera.view_proj * world_pos;
+    fragInput.world_pos = world_pos.xyz;
+    // rotations and uniform scales only, the model matrix turns normals as it is
+    fragInput.normal = (model * vec4<f32>(input.normal, 0.0)).xyz;
+    fragInput.color = instance.color;
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    // the reflection only shows what's above the water, what's under it would be in the way
+    if (dot(camera.clip_plane.xyz, input.world_pos) + camera.clip_plane.w < 0.0) {
+        discard;
+    }
+
+    let light_dir = normalize(vec3<f32>(-0.4, 0.7, -0.6));
+    let diffuse = max(dot(normalize(input.normal), light_dir), 0.0);
+    return vec4<f32>(input.color * (0.25 + 0.75 * diffuse), 1.0);
+}
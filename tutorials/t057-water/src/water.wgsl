@@ -0,0 +1,115 @@
+// the water: a flat grid moved by gerstner waves in the vertex shader, and in the fragment
+// shader a blend of the reflection texture and the water's own tint, weighted by fresnel
+
+// generated from `primitives::Vertex` in lib.rs, only the position is used
+struct VertexInput {
+    @location(0) pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+struct FragInput {
+    @location(0) world_pos: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @builtin(position) clip_position: vec4<f32>,
+};
+
+// matches `WaterUniform` in lib.rs
+struct Water {
+    view_proj: mat4x4<f32>,
+    camera_position: vec3<f32>,
+    time: f32,
+    // of the window, in pixels
+    screen_size: vec2<f32>,
+    // multiplies every wave's steepness, 0 is a mirror
+    wave_scale: f32,
+    // 0 shows the sky's color where the reflection would be
+    reflections: u32,
+};
+
+@group(0) @binding(0)
+var<uniform> water: Water;
+@group(1) @binding(0)
+var reflection_texture: texture_2d<f32>;
+@group(1) @binding(1)
+var reflection_sampler: sampler;
+
+// matches `SKY` in lib.rs
+let sky = vec3<f32>(0.55, 0.7, 0.88);
+let light_dir = vec3<f32>(-0.4, 0.7, -0.6);
+let deep = vec3<f32>(0.01, 0.07, 0.1);
+let shallow = vec3<f32>(0.03, 0.25, 0.28);
+// how far the waves' slope pushes the reflection around, in uv
+let distortion = 0.04;
+
+// one wave's displacement of a point, and what it adds to the surface's slopes along x and z
+struct Wave {
+    offset: vec3<f32>,
+    tangent: vec3<f32>,
+    binormal: vec3<f32>,
+};
+
+// a gerstner wave: points move in circles instead of just up and down, so crests are sharp and
+// troughs wide. `steepness` 1 makes the crests a point, the steepnesses added up stay below that
+fn gerstner(direction: vec2<f32>, steepness: f32, wavelength: f32, p: vec2<f32>) -> Wave {
+    let k = 2.0 * 3.14159265 / wavelength;
+    // deep water waves, the longer ones travel faster
+    let speed = sqrt(9.8 / k);
+    let d = normalize(direction);
+    let f = k * (dot(d, p) - speed * water.time);
+    let s = steepness * water.wave_scale;
+    let a = s / k;
+
+    var wave: Wave;
+    wave.offset = vec3<f32>(d.x * a * cos(f), a * sin(f), d.y * a * cos(f));
+    wave.tangent = vec3<f32>(-d.x * d.x * s * sin(f), d.x * s * cos(f), -d.x * d.y * s * sin(f));
+    wave.binormal = vec3<f32>(-d.x * d.y * s * sin(f), d.y * s * cos(f), -d.y * d.y * s * sin(f));
+    return wave;
+}
+
+@vertex
+fn vs_main(input: VertexInput) -> FragInput {
+    let p = input.pos.xz;
+    let w0 = gerstner(vec2<f32>(1.0, 0.6), 0.22, 11.0, p);
+    let w1 = gerstner(vec2<f32>(-0.4, 1.0), 0.18, 6.5, p);
+    let w2 = gerstner(vec2<f32>(0.8, -0.7), 0.14, 3.7, p);
+    let w3 = gerstner(vec2<f32>(-0.9, -0.3), 0.1, 2.1, p);
+
+    let world_pos = input.pos + w0.offset + w1.offset + w2.offset + w3.offset;
+    let tangent = vec3<f32>(1.0, 0.0, 0.0) + w0.tangent + w1.tangent + w2.tangent + w3.tangent;
+    let binormal = vec3<f32>(0.0, 0.0, 1.0) + w0.binormal + w1.binormal + w2.binormal + w3.binormal;
+
+    var fragInput : FragInput;
+    fragInput.clip_position = water.view_proj * vec4<f32>(world_pos, 1.0);
+    fragInput.world_pos = world_pos;
+    fragInput.normal = normalize(cross(binormal, tangent));
+    return fragInput;
+}
+
+@fragment
+fn fs_main(input: FragInput) -> @location(0) vec4<f32> {
+    let normal = normalize(input.normal);
+    let view = normalize(water.camera_position - input.world_pos);
+
+    // schlick's fresnel with water's 2% at normal incidence: looking straight down shows the
+    // water, toward the horizon it turns into a mirror
+    let cos_theta = max(dot(view, normal), 0.0);
+    let fresnel = 0.02 + 0.98 * pow(1.0 - cos_theta, 5.0);
+
+    // the reflection was drawn from under the water with up still up, it's upside down
+    let screen_uv = input.clip_position.xy / water.screen_size;
+    let uv = vec2<f32>(screen_uv.x, 1.0 - screen_uv.y) + normal.xz * distortion;
+    var reflection = textureSample(reflection_texture, reflection_sampler, clamp(uv, vec2<f32>(0.001), vec2<f32>(0.999))).rgb;
+    if (water.reflections == 0u) {
+        reflection = sky;
+    }
+
+    // no refraction texture, the light coming up out of the water is a tint, lighter where
+    // the view goes through less of it
+    let refraction = mix(deep, shallow, cos_theta);
+
+    let half_dir = normalize(view + normalize(light_dir));
+    let specular = pow(max(dot(normal, half_dir), 0.0), 400.0) * 1.5;
+
+    return vec4<f32>(mix(refraction, reflection, fresnel) + vec3<f32>(specular), 1.0);
+}
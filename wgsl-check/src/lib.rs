@@ -0,0 +1,114 @@
+//! build time wgsl validation, for `build.rs` scripts.
+//!
+//! ```ignore
+//! fn main() {
+//!     wgsl_check::validate("src");
+//! }
+//! ```
+//!
+//! every `.wgsl` file under the directory goes through naga's parser and validator, the same
+//! ones wgpu runs when the shader module is created. a broken shader fails the build with
+//! file:line:column diagnostics instead of a panic once the window is already open.
+//!
+//! shaders with [`preprocess`] directives are checked once per combination of the names they
+//! test, so a feature that only breaks next to another one still fails the build.
+
+mod preprocess;
+
+pub use preprocess::{defines, preprocess};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use naga::valid::{Capabilities, ValidationFlags, Validator};
+
+// 2^n combinations get checked, past this many names that's too slow for a build script
+const MAX_DEFINES: usize = 8;
+
+/// validate every `.wgsl` file under `dir`, relative to the crate root. panics with the
+/// diagnostics of all the broken ones, and reruns the build script when any shader changes
+pub fn validate(dir: impl AsRef<Path>) {
+    let dir = dir.as_ref();
+    // the directory itself too, so new shaders get picked up
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    let mut shaders = Vec::new();
+    collect(dir, &mut shaders);
+    shaders.sort();
+
+    let errors: Vec<String> = shaders
+        .iter()
+        .filter_map(|path| {
+            println!("cargo:rerun-if-changed={}", path.display());
+            check(path).err()
+        })
+        .collect();
+
+    if !errors.is_empty() {
+        panic!(
+            "{} invalid shader(s)\n\n{}",
+            errors.len(),
+            errors.join("\n")
+        );
+    }
+}
+
+fn collect(dir: &Path, shaders: &mut Vec<PathBuf>) {
+    let entries =
+        fs::read_dir(dir).unwrap_or_else(|err| panic!("Fail to read {}: {}", dir.display(), err));
+
+    for entry in entries {
+        let path = entry.expect("Fail to read dir entry").path();
+        if path.is_dir() {
+            collect(&path, shaders);
+        } else if path.extension().is_some_and(|ext| ext == "wgsl") {
+            shaders.push(path);
+        }
+    }
+}
+
+/// the rendered diagnostic on failure
+fn check(path: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("Fail to load {}: {}", path.display(), err));
+    let name = path.display().to_string();
+
+    let names = defines(&source);
+    if names.len() > MAX_DEFINES {
+        return Err(format!(
+            "{}: {} names in #ifdefs, at most {} get checked\n",
+            name,
+            names.len(),
+            MAX_DEFINES
+        ));
+    }
+    for combination in 0..1u32 << names.len() {
+        let defined: Vec<&str> = names
+            .iter()
+            .enumerate()
+            .filter(|(bit, _)| combination & 1 << bit != 0)
+            .map(|(_, &name)| name)
+            .collect();
+        let preprocessed =
+            preprocess(&source, &defined).map_err(|err| format!("{}: {}\n", name, err))?;
+        let name = if names.is_empty() {
+            name.clone()
+        } else {
+            format!("{} [{}]", name, defined.join(", "))
+        };
+        check_source(&preprocessed, &name)?;
+    }
+    Ok(())
+}
+
+fn check_source(source: &str, name: &str) -> Result<(), String> {
+    let module = naga::front::wgsl::parse_str(source)
+        .map_err(|err| err.emit_to_string_with_path(source, name))?;
+
+    // capabilities depend on the device, leave them to wgpu and check everything else
+    Validator::new(ValidationFlags::all(), Capabilities::all())
+        .validate(&module)
+        .map_err(|err| err.emit_to_string_with_path(source, name))?;
+
+    Ok(())
+}
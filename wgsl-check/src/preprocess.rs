@@ -0,0 +1,100 @@
+//! `#ifdef NAME`, `#ifndef NAME`, `#else` and `#endif`, each on a line of its own. wgsl has no
+//! preprocessor, so one file with a few of these stands in for every combination of features
+//! that would otherwise be a file each. nesting works, there's no `#define` or `#include`:
+//! what's defined is decided by whoever builds the shader.
+//!
+//! lines that are left out, and the directives themselves, become empty lines, so line numbers
+//! in naga's errors still point into the file.
+
+/// `source` with the blocks `defines` switch off blanked out
+pub fn preprocess(source: &str, defines: &[&str]) -> Result<String, String> {
+    // one entry per open `#if..`: whether its current branch is kept
+    let mut stack: Vec<bool> = Vec::new();
+    let mut output = String::with_capacity(source.len());
+
+    for (number, line) in source.lines().enumerate() {
+        let at = |message: &str| format!("line {}: {}", number + 1, message);
+        let keeping = stack.iter().all(|&kept| kept);
+
+        match directive(line) {
+            Some(("#ifdef", name)) | Some(("#ifndef", name)) if name.is_empty() => {
+                return Err(at("a name is missing"));
+            }
+            Some(("#ifdef", name)) => stack.push(defines.contains(&name)),
+            Some(("#ifndef", name)) => stack.push(!defines.contains(&name)),
+            Some(("#else", _)) => match stack.last_mut() {
+                Some(kept) => *kept = !*kept,
+                None => return Err(at("#else without #ifdef")),
+            },
+            Some(("#endif", _)) => {
+                stack.pop().ok_or_else(|| at("#endif without #ifdef"))?;
+            }
+            Some((unknown, _)) => return Err(at(&format!("unknown directive {}", unknown))),
+            None if keeping => output.push_str(line),
+            None => {}
+        }
+        output.push('\n');
+    }
+
+    if !stack.is_empty() {
+        return Err(format!("{} #ifdef without #endif", stack.len()));
+    }
+    Ok(output)
+}
+
+/// every name an `#ifdef` or `#ifndef` in `source` tests, sorted
+pub fn defines(source: &str) -> Vec<&str> {
+    let mut names: Vec<&str> = source
+        .lines()
+        .filter_map(|line| match directive(line) {
+            Some(("#ifdef", name)) | Some(("#ifndef", name)) if !name.is_empty() => Some(name),
+            _ => None,
+        })
+        .collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+// `#word` and what follows it, for lines that start with `#`
+fn directive(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if !line.starts_with('#') {
+        return None;
+    }
+    let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    Some((word, rest.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "a\n#ifdef X\nx\n#ifndef Y\nx not y\n#else\nx y\n#endif\n#endif\nb\n";
+
+    #[test]
+    fn keeps_the_defined_blocks() {
+        assert_eq!(preprocess(SOURCE, &[]).unwrap(), "a\n\n\n\n\n\n\n\n\nb\n");
+        assert_eq!(
+            preprocess(SOURCE, &["X"]).unwrap(),
+            "a\n\nx\n\nx not y\n\n\n\n\nb\n"
+        );
+        assert_eq!(
+            preprocess(SOURCE, &["X", "Y"]).unwrap(),
+            "a\n\nx\n\n\n\nx y\n\n\nb\n"
+        );
+        assert_eq!(defines(SOURCE), vec!["X", "Y"]);
+    }
+
+    #[test]
+    fn unbalanced_directives_are_errors() {
+        assert!(preprocess("#ifdef X\n", &[]).is_err());
+        assert_eq!(
+            preprocess("a\n#endif\n", &[]),
+            Err("line 2: #endif without #ifdef".to_string())
+        );
+        assert!(preprocess("#else\n", &[]).is_err());
+        assert!(preprocess("#ifdef\n#endif\n", &[]).is_err());
+        assert!(preprocess("#define X\n", &[]).is_err());
+    }
+}